@@ -0,0 +1,55 @@
+// Service worker for the remote page: keeps the page shell for launches while the Mac is
+// unreachable and shows the phase pushes the app sends.
+const CACHE = "pomodoro-remote-v1";
+const SHELL = ["/", "/manifest.json", "/icon-256.png", "/icon-512.png"];
+
+self.addEventListener("install", (event) => {
+  event.waitUntil(caches.open(CACHE).then((cache) => cache.addAll(SHELL)));
+  self.skipWaiting();
+});
+
+self.addEventListener("activate", (event) => {
+  event.waitUntil(
+    caches
+      .keys()
+      .then((keys) => Promise.all(keys.filter((key) => key !== CACHE).map((key) => caches.delete(key))))
+      .then(() => self.clients.claim())
+  );
+});
+
+// Network first, so a new app version's page wins; the API is never cached.
+self.addEventListener("fetch", (event) => {
+  const url = new URL(event.request.url);
+  if (event.request.method !== "GET" || url.origin !== location.origin || !SHELL.includes(url.pathname)) {
+    return;
+  }
+  event.respondWith(
+    fetch(event.request)
+      .then((response) => {
+        const copy = response.clone();
+        caches.open(CACHE).then((cache) => cache.put(url.pathname, copy));
+        return response;
+      })
+      .catch(() => caches.match(url.pathname))
+  );
+});
+
+self.addEventListener("push", (event) => {
+  const data = event.data ? event.data.json() : {};
+  event.waitUntil(
+    self.registration.showNotification(data.title || "Pomodoro", {
+      body: data.body || "",
+      tag: "pomodoro-phase",
+      renotify: true
+    })
+  );
+});
+
+self.addEventListener("notificationclick", (event) => {
+  event.notification.close();
+  event.waitUntil(
+    clients.matchAll({ type: "window", includeUncontrolled: true }).then((windows) =>
+      windows.length > 0 ? windows[0].focus() : clients.openWindow("/")
+    )
+  );
+});
@@ -1,31 +1,56 @@
+use base64::{engine::general_purpose::STANDARD as BASE64_STANDARD, Engine};
+use chacha20poly1305::{
+    aead::{Aead, KeyInit},
+    ChaCha20Poly1305, Key, Nonce,
+};
 use chrono::{Datelike, Local, TimeZone, Utc};
 use httparse::Status;
 use rand::{distributions::Alphanumeric, Rng};
 use rusqlite::{params, types::Value, Connection, OptionalExtension};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::{
-    collections::{BTreeMap, HashSet},
+    collections::{BTreeMap, BTreeSet, HashSet},
     fs,
     io::{Read, Write},
-    net::{TcpListener, UdpSocket},
+    net::{IpAddr, TcpListener, UdpSocket},
     sync::{
-        atomic::{AtomicBool, Ordering},
+        atomic::{AtomicBool, AtomicUsize, Ordering},
         Arc,
     },
     sync::{Mutex, MutexGuard},
     thread,
-    time::Duration,
+    time::{Duration, Instant},
 };
 use tauri::{
     menu::{Menu, MenuItem},
     tray::TrayIconBuilder,
-    AppHandle, Emitter, Manager, State,
+    AppHandle, Emitter, Listener, Manager, State,
 };
+use tauri_plugin_dialog::DialogExt;
 use tauri_plugin_notification::NotificationExt;
 
 const APP_SETTINGS_KEY: &str = "app_settings";
 const TIMER_STATE_KEY: &str = "timer_state";
+// Bump when export_json's layout changes in a way import_json must tell apart.
+const BACKUP_SCHEMA_VERSION: i64 = 1;
 const TRAY_ID: &str = "pomodoro-tray";
+// Routes reachable without a token under /api/local from 127.0.0.1 only.
+const LOCAL_API_ROUTES: &[&str] = &["/state", "/toggle", "/start", "/pause", "/resume", "/skip"];
+const CLOUD_BACKUP_KEY: &str = "cloud_backup";
+const WEB_PUSH_KEY: &str = "web_push_vapid";
+// Brightness to put back after a dimmed break, kept until it is, so a crash mid-break does not
+// leave the display dark for good.
+const SCREEN_DIM_RESTORE_KEY: &str = "screen_dim_restore";
+const CLOUD_BACKUP_FILENAME: &str = "pomodoro-backup.ppbk";
+const CLOUD_SYNC_FILENAME: &str = "pomodoro-sync.json";
+const CLOUD_BACKUP_MAGIC: &[u8] = b"PPBK1";
+const CLOUD_BACKUP_RETRY_SECONDS: i64 = 15 * 60;
+const CLOUD_BACKUP_MAX_BYTES: u64 = 256 * 1024 * 1024;
+const TRASH_RETENTION_SECONDS: i64 = 30 * 86_400;
+const SUPPORTED_LANGUAGES: [&str; 3] = ["en", "ru", "uk"];
+// Distinct "METHOD /path" keys kept in remote metrics; scans of random paths share "other".
+const REMOTE_METRICS_MAX_ROUTES: usize = 64;
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
@@ -65,6 +90,93 @@ impl TimerPhase {
     }
 }
 
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+enum TimerEventKind {
+    Started,
+    Paused,
+    Resumed,
+    Skipped,
+    Completed,
+}
+
+impl TimerEventKind {
+    fn as_db_value(&self) -> &'static str {
+        match self {
+            TimerEventKind::Started => "started",
+            TimerEventKind::Paused => "paused",
+            TimerEventKind::Resumed => "resumed",
+            TimerEventKind::Skipped => "skipped",
+            TimerEventKind::Completed => "completed",
+        }
+    }
+
+    fn from_db_value(value: &str) -> AppResult<Self> {
+        match value {
+            "started" => Ok(TimerEventKind::Started),
+            "paused" => Ok(TimerEventKind::Paused),
+            "resumed" => Ok(TimerEventKind::Resumed),
+            "skipped" => Ok(TimerEventKind::Skipped),
+            "completed" => Ok(TimerEventKind::Completed),
+            other => Err(format!("unknown timer event: {other}")),
+        }
+    }
+}
+
+// Remote API endpoint groups a token can be limited to.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+enum RemoteScope {
+    // Start, pause, resume, skip, toggle and the session context.
+    Timer,
+    // Every GET: state, sessions, today and analytics.
+    Read,
+    // Anything else that changes stored data.
+    Write,
+}
+
+impl RemoteScope {
+    const ALL: [RemoteScope; 3] = [RemoteScope::Timer, RemoteScope::Read, RemoteScope::Write];
+
+    fn as_str(self) -> &'static str {
+        match self {
+            RemoteScope::Timer => "timer",
+            RemoteScope::Read => "read",
+            RemoteScope::Write => "write",
+        }
+    }
+}
+
+const REMOTE_TIMER_ROUTES: &[&str] = &[
+    "/api/toggle",
+    "/api/start",
+    "/api/pause",
+    "/api/resume",
+    "/api/skip",
+    "/api/context",
+];
+
+// Posts that only concern what the caller receives, so a read-only token may make them.
+const REMOTE_READ_POSTS: &[&str] = &["/api/push/subscribe", "/api/push/unsubscribe"];
+
+// Read-only mode turns away every request that could change something, the loopback aliases
+// included; reads, /ws, /api/events and the push posts a read token may make still work.
+fn remote_read_only_allows(method: &str, path: &str) -> bool {
+    remote_route_scope(method, path) == RemoteScope::Read
+}
+
+// The scope a token needs for a route. Unknown routes need one too, so a route added
+// without a thought for scopes still falls under read or write.
+fn remote_route_scope(method: &str, path: &str) -> RemoteScope {
+    if method.eq_ignore_ascii_case("GET") || REMOTE_READ_POSTS.contains(&path) {
+        RemoteScope::Read
+    } else if REMOTE_TIMER_ROUTES.contains(&path) {
+        RemoteScope::Timer
+    } else {
+        RemoteScope::Write
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase", default)]
 struct AppSettings {
@@ -77,7 +189,83 @@ struct AppSettings {
     notifications_enabled: bool,
     remote_control_enabled: bool,
     remote_control_port: i64,
-    remote_control_token: String,
+    cycle_reset_at_midnight: bool,
+    cycle_reset_idle_min: i64,
+    max_pauses_per_focus: i64,
+    pause_budget_action: String,
+    // Opt-in /api/team/daily endpoint, authenticated with its own key instead of the control token.
+    team_stats_enabled: bool,
+    team_stats_key: String,
+    // What to do with notifications while OS Do Not Disturb/Focus is on: "bypass", "suppress" or "queue".
+    dnd_mode: String,
+    // Always deliver "break is over" alerts, whatever dnd_mode says.
+    dnd_break_end_bypass: bool,
+    // IANA zone name (e.g. "Europe/Kyiv") for local times in exports; empty means the system zone.
+    timezone: String,
+    // Archive projects with no sessions for this many days; 0 disables the job.
+    auto_archive_days: i64,
+    // How a running focus reacts when the desktop session leaves the console (RDP/VDI detach,
+    // screen lock): "ignore", "interruption" or "pause".
+    remote_detach_action: String,
+    // Language of backend strings (tray, notifications): "en", "ru" or "uk".
+    language: String,
+    // Work hours as minutes after local midnight; gap detection only looks inside them.
+    work_day_start_min: i64,
+    work_day_end_min: i64,
+    // Shortest untracked stretch reported by sessions_find_gaps.
+    gap_min_minutes: i64,
+    // Days off neither break the streak nor count toward the daily average.
+    skip_days_off: bool,
+    // Streak rules. A day counts once it has this many completed pomodoros (0: any focus time);
+    // weekends may be stepped over; streak_freeze_tokens missed days can be bridged in all, each
+    // spent token staying with its day in streak_freezes.
+    streak_min_pomodoros: i64,
+    streak_skip_weekends: bool,
+    streak_freeze_tokens: i64,
+    // Each completed focus session is POSTed here as JSON; empty disables it. The optional
+    // secret is sent as X-Pomodoro-Webhook-Secret.
+    session_webhook_url: String,
+    session_webhook_secret: String,
+    // Guided breathing during breaks: "off", "box" (4-4-4-4), "relax" (4-7-8), "coherent" (5-5)
+    // or "custom", which uses breathing_custom as inhale/hold/exhale/hold seconds.
+    breathing_pattern: String,
+    breathing_custom: Vec<i64>,
+    // Play the local music_tracks playlist while a focus phase runs; shuffled unless turned off.
+    music_enabled: bool,
+    music_shuffle: bool,
+    // Lower the display brightness to break_dim_percent while a break runs, restoring it for focus
+    // and on exit. Needs `brightness` on macOS and `brightnessctl` on Linux.
+    break_dim_enabled: bool,
+    break_dim_percent: i64,
+    // Sessions on these projects or carrying these tags are left out of analytics (summary,
+    // streaks, records, charts) unless a range sets includeExcluded.
+    analytics_excluded_project_ids: Vec<i64>,
+    analytics_excluded_tag_ids: Vec<i64>,
+    // Goals; 0 turns one off. The week starts on Monday, and analytics exclusions apply.
+    daily_goal_pomodoros: i64,
+    weekly_goal_minutes: i64,
+    // Show a notification when a goal is reached; goal://reached is emitted either way.
+    goal_notification: bool,
+    // Tick the tray countdown every second; otherwise it shows whole minutes until the final one.
+    tray_title_seconds: bool,
+    // Scheduled local backups to backup_directory.
+    backup_enabled: bool,
+    // Empty means a backups folder in the app data directory.
+    backup_directory: String,
+    backup_interval_hours: i64,
+    // Older backups beyond this count are deleted after each run.
+    backup_keep: i64,
+    // "json" (the export_json payload) or "sqlite" (a copy of the database).
+    backup_format: String,
+    // Advertise the running remote server over mDNS as _pomodoro._tcp. On for new installs;
+    // settings saved before the option existed load with it off, so an upgrade does not
+    // start announcing the server on the network.
+    #[serde(default)]
+    remote_mdns_enabled: bool,
+    // A device must be approved on the desktop before its token works.
+    remote_pairing_required: bool,
+    // Only GET routes answer, for a wall display that must not control the timer.
+    remote_read_only: bool,
 }
 
 impl Default for AppSettings {
@@ -92,7 +280,47 @@ impl Default for AppSettings {
             notifications_enabled: true,
             remote_control_enabled: false,
             remote_control_port: 48484,
-            remote_control_token: String::new(),
+            cycle_reset_at_midnight: true,
+            cycle_reset_idle_min: 0,
+            max_pauses_per_focus: 0,
+            pause_budget_action: "reject".to_string(),
+            team_stats_enabled: false,
+            team_stats_key: String::new(),
+            dnd_mode: "bypass".to_string(),
+            dnd_break_end_bypass: true,
+            timezone: String::new(),
+            auto_archive_days: 0,
+            remote_detach_action: "ignore".to_string(),
+            language: "en".to_string(),
+            work_day_start_min: 540,
+            work_day_end_min: 1080,
+            gap_min_minutes: 30,
+            skip_days_off: false,
+            streak_min_pomodoros: 0,
+            streak_skip_weekends: false,
+            streak_freeze_tokens: 0,
+            session_webhook_url: String::new(),
+            session_webhook_secret: String::new(),
+            breathing_pattern: "off".to_string(),
+            breathing_custom: vec![4, 4, 4, 4],
+            music_enabled: false,
+            music_shuffle: true,
+            break_dim_enabled: false,
+            break_dim_percent: 30,
+            analytics_excluded_project_ids: Vec::new(),
+            analytics_excluded_tag_ids: Vec::new(),
+            daily_goal_pomodoros: 0,
+            weekly_goal_minutes: 0,
+            goal_notification: true,
+            tray_title_seconds: false,
+            backup_enabled: false,
+            backup_directory: String::new(),
+            backup_interval_hours: 24,
+            backup_keep: 7,
+            backup_format: "json".to_string(),
+            remote_mdns_enabled: true,
+            remote_pairing_required: true,
+            remote_read_only: false,
         }
     }
 }
@@ -107,7 +335,7 @@ impl AppSettings {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct AppSettingsPatch {
     focus_min: Option<i64>,
@@ -119,7 +347,47 @@ struct AppSettingsPatch {
     notifications_enabled: Option<bool>,
     remote_control_enabled: Option<bool>,
     remote_control_port: Option<i64>,
-    remote_control_token: Option<String>,
+    cycle_reset_at_midnight: Option<bool>,
+    cycle_reset_idle_min: Option<i64>,
+    max_pauses_per_focus: Option<i64>,
+    pause_budget_action: Option<String>,
+    team_stats_enabled: Option<bool>,
+    team_stats_key: Option<String>,
+    dnd_mode: Option<String>,
+    dnd_break_end_bypass: Option<bool>,
+    timezone: Option<String>,
+    auto_archive_days: Option<i64>,
+    remote_detach_action: Option<String>,
+    language: Option<String>,
+    work_day_start_min: Option<i64>,
+    work_day_end_min: Option<i64>,
+    gap_min_minutes: Option<i64>,
+    skip_days_off: Option<bool>,
+    streak_min_pomodoros: Option<i64>,
+    streak_skip_weekends: Option<bool>,
+    streak_freeze_tokens: Option<i64>,
+    session_webhook_url: Option<String>,
+    session_webhook_secret: Option<String>,
+    breathing_pattern: Option<String>,
+    breathing_custom: Option<Vec<i64>>,
+    music_enabled: Option<bool>,
+    music_shuffle: Option<bool>,
+    break_dim_enabled: Option<bool>,
+    break_dim_percent: Option<i64>,
+    analytics_excluded_project_ids: Option<Vec<i64>>,
+    analytics_excluded_tag_ids: Option<Vec<i64>>,
+    daily_goal_pomodoros: Option<i64>,
+    weekly_goal_minutes: Option<i64>,
+    goal_notification: Option<bool>,
+    tray_title_seconds: Option<bool>,
+    backup_enabled: Option<bool>,
+    backup_directory: Option<String>,
+    backup_interval_hours: Option<i64>,
+    backup_keep: Option<i64>,
+    backup_format: Option<String>,
+    remote_mdns_enabled: Option<bool>,
+    remote_pairing_required: Option<bool>,
+    remote_read_only: Option<bool>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -135,6 +403,27 @@ struct TimerState {
     current_project_id: Option<i64>,
     current_tag_ids: Vec<i64>,
     target_ends_at: Option<i64>,
+    // Local day (YYYY-MM-DD) on which the current cycle count started.
+    cycle_anchor_date: Option<String>,
+    // Last time a phase stopped running (pause, skip, or completion).
+    last_active_at: Option<i64>,
+    // Pauses left before the focus budget is exhausted; None when unlimited.
+    pauses_remaining: Option<i64>,
+    // Set once a pause exceeded the budget; the session is then recorded as not completed.
+    #[serde(default)]
+    pause_budget_exceeded: bool,
+    // The next long break is swapped for a short one when this focus finishes.
+    #[serde(default)]
+    long_break_postponed: bool,
+    // A postponed long break is owed and follows the next focus, whatever the cycle.
+    #[serde(default)]
+    long_break_requeued: bool,
+    // Time added to the current break with break_extend, on top of the configured length.
+    #[serde(default)]
+    break_extension_seconds: i64,
+    // Whether the current focus was started from the plan queue.
+    #[serde(default)]
+    current_planned: bool,
 }
 
 impl TimerState {
@@ -152,6 +441,15 @@ impl TimerState {
             current_project_id: None,
             current_tag_ids: Vec::new(),
             target_ends_at: None,
+            cycle_anchor_date: None,
+            last_active_at: None,
+            pauses_remaining: (settings.max_pauses_per_focus > 0)
+                .then_some(settings.max_pauses_per_focus),
+            pause_budget_exceeded: false,
+            long_break_postponed: false,
+            long_break_requeued: false,
+            current_planned: false,
+            break_extension_seconds: 0,
         }
     }
 }
@@ -165,6 +463,9 @@ struct StartTimerRequest {
     // - field present as number: set current project (Some(Some(id)))
     project_id: Option<Option<i64>>,
     tag_ids: Option<Vec<i64>>,
+    // Length of the phase being started, instead of the configured one. Ignored when resuming
+    // and by timer_set_context.
+    duration_minutes: Option<i64>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -178,6 +479,58 @@ struct CompleteSessionRequest {
     interruptions: i64,
     project_id: Option<i64>,
     tag_ids: Option<Vec<i64>>,
+    notes: Option<String>,
+    quality: Option<i64>,
+    // Skip the overlap check, e.g. when logging a session recorded on another device.
+    allow_overlap: Option<bool>,
+}
+
+// An untracked stretch inside work hours, shaped to prefill a manual session_complete entry.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct SessionGap {
+    started_at: i64,
+    ended_at: i64,
+    duration_sec: i64,
+}
+
+// Rejections from session_complete. Rendered as "<code>: <message>" so callers can match on the code.
+#[derive(Debug, PartialEq, Eq)]
+enum SessionValidationError {
+    InvalidRange,
+    NegativeValue,
+    DurationExceedsWindow,
+    InvalidQuality,
+    Overlap(i64),
+}
+
+impl SessionValidationError {
+    fn code(&self) -> &'static str {
+        match self {
+            SessionValidationError::InvalidRange => "invalid_range",
+            SessionValidationError::NegativeValue => "negative_value",
+            SessionValidationError::DurationExceedsWindow => "duration_exceeds_window",
+            SessionValidationError::InvalidQuality => "invalid_quality",
+            SessionValidationError::Overlap(_) => "overlap",
+        }
+    }
+}
+
+impl std::fmt::Display for SessionValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let message = match self {
+            SessionValidationError::InvalidRange => "endedAt must be after startedAt".to_string(),
+            SessionValidationError::NegativeValue => {
+                "durationSec and interruptions must not be negative".to_string()
+            }
+            SessionValidationError::DurationExceedsWindow => {
+                "durationSec is longer than the time between startedAt and endedAt".to_string()
+            }
+            SessionValidationError::InvalidQuality => "quality must be between 1 and 5".to_string(),
+            SessionValidationError::Overlap(id) => format!("overlaps existing session {id}"),
+        };
+        write!(f, "{}: {message}", self.code())
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -192,15 +545,146 @@ struct SessionRecord {
     interruptions: i64,
     project_id: Option<i64>,
     tag_ids: Vec<i64>,
+    #[serde(default)]
+    notes: Option<String>,
+    // Seconds added to a break via break_extend (included in duration_sec).
+    #[serde(default)]
+    extended_sec: i64,
+    // Shares of a focus session spent on several projects; empty when a single project applies.
+    #[serde(default)]
+    project_splits: Vec<ProjectSplit>,
+    // Self-reported focus quality, 1 (poor) to 5 (excellent).
+    #[serde(default)]
+    quality: Option<i64>,
+    // Focus started from the plan queue (true) or ad hoc (false); None for manual logs and
+    // sessions recorded before the flag existed.
+    #[serde(default)]
+    planned: Option<bool>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+struct ProjectSplit {
+    project_id: i64,
+    percent: i64,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct AnalyticsRange {
     from: Option<i64>,
     to: Option<i64>,
     project_id: Option<i64>,
     tag_id: Option<i64>,
+    // Bucket size for analytics_get_timeseries; ignored elsewhere. Defaults to day.
+    granularity: Option<Granularity>,
+    // Analytics commands drop sessions matching the settings' exclusion rules unless set.
+    #[serde(default)]
+    include_excluded: bool,
+    // Only sessions in one of these phases; empty means every phase.
+    #[serde(default)]
+    phases: Vec<TimerPhase>,
+    // Filled in from settings by `with_exclusions`; never sent by the frontend.
+    #[serde(skip)]
+    excluded: AnalyticsExclusions,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+struct AnalyticsExclusions {
+    project_ids: Vec<i64>,
+    tag_ids: Vec<i64>,
+}
+
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+enum Granularity {
+    #[default]
+    Day,
+    Week,
+    Month,
+}
+
+impl Granularity {
+    // Local date a session's bucket starts on; weeks start on Monday.
+    fn bucket_sql(self) -> &'static str {
+        match self {
+            Granularity::Day => "date(ended_at, 'unixepoch', 'localtime')",
+            Granularity::Week => "date(ended_at, 'unixepoch', 'localtime', 'weekday 0', '-6 days')",
+            Granularity::Month => "date(ended_at, 'unixepoch', 'localtime', 'start of month')",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SessionQuery {
+    #[serde(flatten)]
+    range: AnalyticsRange,
+    limit: Option<i64>,
+    // Opaque `endedAt:id` cursor returned as `nextCursor` by the previous page.
+    cursor: Option<String>,
+}
+
+// One row of the append-only timer transition log.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct TimerEvent {
+    id: i64,
+    at: i64,
+    kind: TimerEventKind,
+    phase: TimerPhase,
+    cycle_index: i64,
+    remaining_seconds: i64,
+    // Session written by a skipped/completed transition.
+    session_id: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TimerEventQuery {
+    from: Option<i64>,
+    to: Option<i64>,
+    limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SessionSearchQuery {
+    #[serde(flatten)]
+    range: AnalyticsRange,
+    text: String,
+    limit: Option<i64>,
+}
+
+// Fields of a logged session that session_update may change; omitted fields are kept.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SessionUpdate {
+    started_at: Option<i64>,
+    ended_at: Option<i64>,
+    duration_sec: Option<i64>,
+    completed: Option<bool>,
+    interruptions: Option<i64>,
+    allow_overlap: Option<bool>,
+}
+
+// The session as it was before one edit; `source` names the command that made the edit.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SessionRevision {
+    id: i64,
+    session_id: i64,
+    revised_at: i64,
+    source: String,
+    session: SessionRecord,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SessionPage {
+    total: i64,
+    sessions: Vec<SessionRecord>,
+    next_cursor: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -211,1881 +695,19908 @@ struct AnalyticsSummary {
     streak_days: i64,
     interruptions: i64,
     avg_daily_focus_sec: i64,
+    // Share of focus sessions started from the plan queue; None when none carry the flag.
+    planned_ratio: Option<f64>,
+    break_compliance: BreakCompliance,
+}
+
+// Breaks in the range by how they ended. A break skipped within BREAK_SKIP_GRACE_SECONDS of
+// starting counts as skipped, a later early end as cut short. Breaks carry no project or
+// tags, so those filters do not apply.
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct BreakCompliance {
+    breaks: i64,
+    taken: i64,
+    cut_short: i64,
+    skipped: i64,
+    // Break time taken over the configured lengths, capped at 1 per break.
+    taken_ratio: Option<f64>,
+    // Share of breaks taken fully; None without breaks.
+    compliance_rate: Option<f64>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
-struct TimeseriesPoint {
+struct DailyQuality {
     date: String,
-    focus_seconds: i64,
-    completed_pomodoros: i64,
-    interruptions: i64,
+    avg_quality: f64,
+    rated_sessions: i64,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
-struct Project {
-    id: i64,
-    name: String,
-    color: Option<String>,
-    archived: bool,
+struct ProjectQuality {
+    project_id: Option<i64>,
+    avg_quality: f64,
+    rated_sessions: i64,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
-struct ProjectInput {
-    id: Option<i64>,
-    name: String,
-    color: Option<String>,
-    archived: Option<bool>,
+struct QualityBreakdown {
+    by_day: Vec<DailyQuality>,
+    by_project: Vec<ProjectQuality>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
-struct Tag {
-    id: i64,
-    name: String,
+struct FocusLengthBucket {
+    // Planned focus length, rounded to the nearest 5 minutes.
+    minutes: i64,
+    sessions: i64,
+    completed: i64,
+    completion_rate: f64,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
-struct TagInput {
-    id: Option<i64>,
-    name: String,
+struct FocusLengthRecommendation {
+    buckets: Vec<FocusLengthBucket>,
+    recommended_min: Option<i64>,
+    current_min: i64,
+    applied: bool,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
-struct ExportResult {
-    filename: String,
-    content: String,
+struct ProjectBilling {
+    project_id: i64,
+    project_name: String,
+    hourly_rate: f64,
+    billable_seconds: i64,
+    earnings: f64,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
-struct ExportRange {
-    from: Option<i64>,
-    to: Option<i64>,
+struct PeriodBilling {
+    // "YYYY-MM-DD", "YYYY-Www" (ISO week) or "YYYY-MM".
+    period: String,
+    billable_seconds: i64,
+    earnings: f64,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
-struct ResetAllResult {
-    settings: AppSettings,
-    timer: TimerState,
+struct BillingReport {
+    by_project: Vec<ProjectBilling>,
+    by_period: Vec<PeriodBilling>,
+    total_billable_seconds: i64,
+    total_earnings: f64,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
-struct PhaseCompletedEvent {
-    completed_phase: TimerPhase,
-    next_phase: TimerPhase,
+struct BreakUsage {
+    break_sessions: i64,
+    // Break time actually taken, including extensions.
+    break_seconds: i64,
+    // Break time the configured lengths account for (break_seconds minus extensions).
+    configured_seconds: i64,
+    extended_seconds: i64,
+    extended_sessions: i64,
 }
 
-struct AppModel {
-    conn: Connection,
-    settings: AppSettings,
-    timer: TimerState,
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct TimeseriesPoint {
+    date: String,
+    focus_seconds: i64,
+    completed_pomodoros: i64,
+    interruptions: i64,
 }
 
-struct RemoteServerHandle {
-    port: u16,
-    stop: Arc<AtomicBool>,
-    join: Option<thread::JoinHandle<()>>,
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct HourlyBucket {
+    hour: i64,
+    focus_seconds: i64,
+    completed_pomodoros: i64,
 }
 
-struct RemoteControlState {
-    server: Option<RemoteServerHandle>,
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct DayRecord {
+    date: String,
+    value: i64,
 }
 
-struct AppState {
-    model: Mutex<AppModel>,
-    remote: Mutex<RemoteControlState>,
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct StreakRecord {
+    days: i64,
+    start_date: String,
+    end_date: String,
 }
 
-type AppResult<T> = Result<T, String>;
+// All-time bests; each is None until there is a focus session to base it on. Ties go to
+// the earliest date.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct AnalyticsRecords {
+    longest_daily_focus: Option<DayRecord>,
+    most_pomodoros_day: Option<DayRecord>,
+    longest_streak: Option<StreakRecord>,
+    // Longest single focus session without interruptions, in seconds.
+    longest_focus_block: Option<DayRecord>,
+}
 
-fn now_ts() -> i64 {
-    Utc::now().timestamp()
+// A/B test of two focus lengths: while active, each new focus phase alternates between them.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct Experiment {
+    id: i64,
+    focus_a_min: i64,
+    focus_b_min: i64,
+    started_at: i64,
+    ends_at: i64,
+    stopped_at: Option<i64>,
 }
 
-fn lock_model<'a>(state: &'a State<'_, AppState>) -> AppResult<MutexGuard<'a, AppModel>> {
-    state.model.lock().map_err(|e| e.to_string())
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct ExperimentArm {
+    focus_min: i64,
+    sessions: i64,
+    completed: i64,
+    completion_rate: Option<f64>,
+    interruptions: i64,
+    interruptions_per_session: Option<f64>,
+    // Output: focus time actually logged, in total and per session.
+    focus_seconds: i64,
+    avg_focus_seconds: Option<f64>,
 }
 
-fn init_database(conn: &Connection) -> AppResult<()> {
-    conn.execute_batch(
-        r#"
-        PRAGMA foreign_keys = ON;
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct ExperimentReport {
+    experiment: Experiment,
+    active: bool,
+    arms: Vec<ExperimentArm>,
+}
 
-        CREATE TABLE IF NOT EXISTS settings (
-            key TEXT PRIMARY KEY,
-            value TEXT NOT NULL
-        );
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct GoalProgress {
+    target: i64,
+    value: i64,
+    // Local midnight the day or week began at.
+    period_start: i64,
+    reached: bool,
+}
 
-        CREATE TABLE IF NOT EXISTS projects (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            name TEXT NOT NULL UNIQUE,
-            color TEXT,
-            archived INTEGER NOT NULL DEFAULT 0,
-            created_at INTEGER NOT NULL
-        );
+// A goal is None while its setting is 0.
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct GoalsProgress {
+    daily_pomodoros: Option<GoalProgress>,
+    weekly_minutes: Option<GoalProgress>,
+}
 
-        CREATE TABLE IF NOT EXISTS tags (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            name TEXT NOT NULL UNIQUE,
-            created_at INTEGER NOT NULL
-        );
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct GoalReachedEvent {
+    // "daily" or "weekly".
+    goal: String,
+    target: i64,
+    value: i64,
+}
 
-        CREATE TABLE IF NOT EXISTS sessions (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            started_at INTEGER NOT NULL,
-            ended_at INTEGER NOT NULL,
-            phase TEXT NOT NULL,
-            duration_sec INTEGER NOT NULL,
-            completed INTEGER NOT NULL,
-            interruptions INTEGER NOT NULL DEFAULT 0,
-            project_id INTEGER,
-            FOREIGN KEY(project_id) REFERENCES projects(id) ON DELETE SET NULL
-        );
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct WeekdayBucket {
+    weekday: i64,
+    focus_seconds: i64,
+    completed_pomodoros: i64,
+    interruptions: i64,
+}
 
-        CREATE TABLE IF NOT EXISTS session_tags (
-            session_id INTEGER NOT NULL,
-            tag_id INTEGER NOT NULL,
-            PRIMARY KEY (session_id, tag_id),
-            FOREIGN KEY(session_id) REFERENCES sessions(id) ON DELETE CASCADE,
-            FOREIGN KEY(tag_id) REFERENCES tags(id) ON DELETE CASCADE
-        );
+// `to_sec` is None for the last, open-ended bucket.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct DistributionBucket {
+    from_sec: i64,
+    to_sec: Option<i64>,
+    count: i64,
+}
 
-        CREATE INDEX IF NOT EXISTS idx_sessions_ended_at ON sessions(ended_at);
-        CREATE INDEX IF NOT EXISTS idx_sessions_project_id ON sessions(project_id);
-        CREATE INDEX IF NOT EXISTS idx_session_tags_tag_id ON session_tags(tag_id);
-        "#,
-    )
-    .map_err(|e| e.to_string())?;
+// Percentiles use the nearest-rank method, so they are always observed values.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct Distribution {
+    count: i64,
+    p50_sec: Option<i64>,
+    p90_sec: Option<i64>,
+    buckets: Vec<DistributionBucket>,
+}
 
-    Ok(())
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct SessionDistribution {
+    durations: Distribution,
+    // Idle time between consecutive focus sessions on the same local day.
+    gaps: Distribution,
 }
 
-fn save_json_setting<T: Serialize>(conn: &Connection, key: &str, value: &T) -> AppResult<()> {
-    let json = serde_json::to_string(value).map_err(|e| e.to_string())?;
-    conn.execute(
-        "INSERT INTO settings (key, value) VALUES (?1, ?2)
-         ON CONFLICT(key) DO UPDATE SET value = excluded.value",
-        params![key, json],
-    )
-    .map_err(|e| e.to_string())?;
-    Ok(())
+// Focus start times in minutes after local midnight, for one range.granularity bucket.
+// `median_first_start_min` only looks at each day's first focus, the usual "when did I get
+// going" measure.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct StartTimeStats {
+    period: String,
+    sessions: i64,
+    days: i64,
+    median_start_min: i64,
+    earliest_start_min: i64,
+    latest_start_min: i64,
+    median_first_start_min: i64,
 }
 
-fn load_json_setting<T: for<'de> Deserialize<'de>>(
-    conn: &Connection,
-    key: &str,
-) -> AppResult<Option<T>> {
-    let value: Option<String> = conn
-        .query_row(
-            "SELECT value FROM settings WHERE key = ?1",
-            params![key],
-            |row| row.get(0),
-        )
-        .optional()
-        .map_err(|e| e.to_string())?;
-
-    match value {
-        Some(raw) => {
-            let parsed = serde_json::from_str::<T>(&raw).map_err(|e| e.to_string())?;
-            Ok(Some(parsed))
-        }
-        None => Ok(None),
-    }
+// Actual pomodoros are completed focus sessions credited to the project, so a split
+// session counts only the project's share.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct ProjectEstimate {
+    project_id: i64,
+    name: String,
+    archived: bool,
+    estimated_pomodoros: i64,
+    actual_pomodoros: f64,
+    // actual / estimated: above 1 means the project was underestimated.
+    ratio: f64,
+    last_pomodoro_at: Option<i64>,
 }
 
-fn normalize_settings(mut settings: AppSettings) -> AppSettings {
-    settings.focus_min = settings.focus_min.clamp(1, 180);
-    settings.short_break_min = settings.short_break_min.clamp(1, 60);
-    settings.long_break_min = settings.long_break_min.clamp(1, 90);
-    settings.long_break_every = settings.long_break_every.clamp(2, 10);
-    settings.theme = match settings.theme.as_str() {
-        "dark" => "dark".to_string(),
-        _ => "light".to_string(),
-    };
-    settings.remote_control_port = settings.remote_control_port.clamp(1024, 65535);
-    settings
+// Estimated projects grouped by the period of their last pomodoro.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct EstimationTrendPoint {
+    date: String,
+    projects: i64,
+    mean_ratio: f64,
+    underestimated: i64,
+    overestimated: i64,
 }
 
-fn generate_remote_token() -> String {
-    rand::thread_rng()
-        .sample_iter(&Alphanumeric)
-        .take(32)
-        .map(char::from)
-        .collect()
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct EstimationAccuracy {
+    projects: Vec<ProjectEstimate>,
+    mean_ratio: Option<f64>,
+    trend: Vec<EstimationTrendPoint>,
 }
 
-fn ensure_remote_token(settings: &mut AppSettings) {
-    if settings.remote_control_token.trim().is_empty() {
-        settings.remote_control_token = generate_remote_token();
-    }
+// `bucket` is the hour (0-23) or weekday (0 = Sunday) depending on the list it is in.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct InterruptionBucket {
+    bucket: i64,
+    interruptions: i64,
+    completed_pomodoros: i64,
+    per_pomodoro: f64,
 }
 
-fn load_or_create_settings(conn: &Connection) -> AppResult<AppSettings> {
-    let settings = load_json_setting::<AppSettings>(conn, APP_SETTINGS_KEY)?
-        .unwrap_or_else(AppSettings::default);
-    let mut settings = normalize_settings(settings);
-    ensure_remote_token(&mut settings);
-    save_json_setting(conn, APP_SETTINGS_KEY, &settings)?;
-    Ok(settings)
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct InterruptionTrendPoint {
+    date: String,
+    interruptions: i64,
+    completed_pomodoros: i64,
+    per_pomodoro: f64,
 }
 
-fn normalize_timer_state(mut timer: TimerState, settings: &AppSettings) -> TimerState {
-    timer.phase_total_seconds = settings.duration_for_phase_seconds(&timer.phase);
-    if timer.remaining_seconds <= 0 || timer.remaining_seconds > timer.phase_total_seconds {
-        timer.remaining_seconds = timer.phase_total_seconds;
-        timer.is_running = false;
-        timer.target_ends_at = None;
-    }
-    if timer.cycle_index < 0 {
-        timer.cycle_index = 0;
-    }
-    timer.interruptions = timer.interruptions.max(0);
-    timer
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct InterruptionAnalytics {
+    by_hour: Vec<InterruptionBucket>,
+    by_weekday: Vec<InterruptionBucket>,
+    average_per_pomodoro: f64,
+    // One point per range.granularity bucket (daily by default) that had focus time.
+    trend: Vec<InterruptionTrendPoint>,
 }
 
-fn load_or_create_timer(conn: &Connection, settings: &AppSettings) -> AppResult<TimerState> {
-    let timer = load_json_setting::<TimerState>(conn, TIMER_STATE_KEY)?
-        .unwrap_or_else(|| TimerState::default_with_settings(settings));
-    let timer = normalize_timer_state(timer, settings);
-    save_json_setting(conn, TIMER_STATE_KEY, &timer)?;
-    Ok(timer)
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct Project {
+    id: i64,
+    name: String,
+    color: Option<String>,
+    archived: bool,
+    #[serde(default)]
+    billable: bool,
+    // Per hour, in whatever currency the user invoices in.
+    #[serde(default)]
+    hourly_rate: Option<f64>,
+    // Pickers list pinned projects first, then by sort_order (set via projects_reorder).
+    #[serde(default)]
+    pinned: bool,
+    #[serde(default)]
+    sort_order: i64,
+    #[serde(default)]
+    notification_override: NotificationOverride,
+    // The pomodoros the project was expected to take.
+    #[serde(default)]
+    estimated_pomodoros: Option<i64>,
 }
 
-fn save_timer_state(conn: &Connection, timer: &TimerState) -> AppResult<()> {
-    save_json_setting(conn, TIMER_STATE_KEY, timer)
+// Per-project alert behaviour at phase completion; unset fields follow the global settings.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase", default)]
+struct NotificationOverride {
+    notify: Option<bool>,
+    // One of PROJECT_SOUNDS; "none" silences the project even when sounds are on.
+    sound: Option<String>,
 }
 
-fn refresh_remaining(timer: &mut TimerState) {
-    if timer.is_running {
-        if let Some(target_ends_at) = timer.target_ends_at {
-            timer.remaining_seconds = (target_ends_at - now_ts()).max(0);
-        }
-    }
-}
+const PROJECT_SOUNDS: [&str; 3] = ["chime", "gong", "none"];
 
-fn format_seconds(seconds: i64) -> String {
-    let minutes = seconds / 60;
-    let secs = seconds % 60;
-    format!("{minutes:02}:{secs:02}")
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ProjectInput {
+    id: Option<i64>,
+    name: String,
+    color: Option<String>,
+    archived: Option<bool>,
+    // Left unchanged on update when omitted.
+    billable: Option<bool>,
+    // Left unchanged on update when omitted; null clears the rate.
+    #[serde(default, deserialize_with = "deserialize_present")]
+    hourly_rate: Option<Option<f64>>,
+    pinned: Option<bool>,
+    // Replaces both override fields when present.
+    notification_override: Option<NotificationOverride>,
+    // Left unchanged on update when omitted; 0 clears the estimate.
+    estimated_pomodoros: Option<i64>,
 }
 
-fn update_tray_title(app: &AppHandle, timer: &TimerState) {
-    if let Some(tray) = app.tray_by_id(TRAY_ID) {
-        let status = if timer.is_running {
-            "Running"
-        } else {
-            "Paused"
-        };
-        let title = format!(
-            "{} {} {status}",
-            timer.phase,
-            format_seconds(timer.remaining_seconds)
-        );
-        let _ = tray.set_title(Some(&title));
-    }
+// For Option<Option<T>> fields: a missing field stays None, an explicit null is Some(None).
+fn deserialize_present<'de, T, D>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: serde::Deserializer<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
 }
 
-fn emit_timer_state(app: &AppHandle, timer: &TimerState) {
-    let _ = app.emit("timer://state", timer);
-    update_tray_title(app, timer);
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct Tag {
+    id: i64,
+    name: String,
 }
 
-fn record_session(
-    conn: &Connection,
-    timer: &TimerState,
-    completed: bool,
-    ended_at: i64,
-) -> AppResult<SessionRecord> {
-    let elapsed = if completed {
-        timer.phase_total_seconds
-    } else {
-        (timer.phase_total_seconds - timer.remaining_seconds).clamp(0, timer.phase_total_seconds)
-    };
+// A local YYYY-MM-DD date marked as vacation or holiday; source is "manual" or "ics".
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct DayOff {
+    day: String,
+    name: Option<String>,
+    source: String,
+}
 
-    let started_at = timer
-        .started_at
-        .unwrap_or_else(|| ended_at - elapsed.max(1));
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DaysOffImportResult {
+    imported: i64,
+    // Already marked, manually or by an earlier import; existing entries are kept.
+    skipped: i64,
+}
 
-    let project_id = match timer.phase {
-        TimerPhase::Focus => timer.current_project_id,
-        _ => None,
-    };
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TagWithStats {
+    #[serde(flatten)]
+    tag: Tag,
+    // All phases count; focus_seconds only sums focus time.
+    session_count: i64,
+    focus_seconds: i64,
+}
 
-    conn.execute(
-        "INSERT INTO sessions (started_at, ended_at, phase, duration_sec, completed, interruptions, project_id)
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
-        params![
-            started_at,
-            ended_at,
-            timer.phase.as_db_value(),
-            elapsed,
-            completed as i64,
-            timer.interruptions,
-            project_id,
-        ],
-    )
-    .map_err(|e| e.to_string())?;
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TagInput {
+    id: Option<i64>,
+    name: String,
+}
 
-    let id = conn.last_insert_rowid();
-    let mut tag_ids = Vec::new();
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct Trashed<T> {
+    #[serde(flatten)]
+    item: T,
+    deleted_at: i64,
+    // When the purge job will remove the item for good.
+    purge_at: i64,
+}
 
-    if timer.phase == TimerPhase::Focus {
-        for tag_id in &timer.current_tag_ids {
-            conn.execute(
-                "INSERT OR IGNORE INTO session_tags (session_id, tag_id) VALUES (?1, ?2)",
-                params![id, tag_id],
-            )
-            .map_err(|e| e.to_string())?;
-            tag_ids.push(*tag_id);
+impl<T> Trashed<T> {
+    fn new(item: T, deleted_at: i64) -> Self {
+        Self {
+            item,
+            deleted_at,
+            purge_at: deleted_at + TRASH_RETENTION_SECONDS,
         }
     }
+}
 
-    Ok(SessionRecord {
-        id,
-        started_at,
-        ended_at,
-        phase: timer.phase.clone(),
-        duration_sec: elapsed,
-        completed,
-        interruptions: timer.interruptions,
-        project_id,
-        tag_ids,
-    })
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TrashList {
+    sessions: Vec<Trashed<SessionRecord>>,
+    projects: Vec<Trashed<Project>>,
+    tags: Vec<Trashed<Tag>>,
 }
 
-fn advance_timer(timer: &mut TimerState, settings: &AppSettings) {
-    let next_phase = match timer.phase {
-        TimerPhase::Focus => {
-            timer.cycle_index += 1;
-            if timer.cycle_index % settings.long_break_every == 0 {
-                TimerPhase::LongBreak
-            } else {
-                TimerPhase::ShortBreak
-            }
-        }
-        TimerPhase::ShortBreak | TimerPhase::LongBreak => TimerPhase::Focus,
-    };
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DeleteResult {
+    // False when the item was already in the trash.
+    deleted: bool,
+    // Sessions that lost their link to the item because of `force`.
+    detached_sessions: i64,
+}
 
-    timer.phase = next_phase;
-    timer.phase_total_seconds = settings.duration_for_phase_seconds(&timer.phase);
-    timer.remaining_seconds = timer.phase_total_seconds;
-    timer.is_running = false;
-    timer.started_at = None;
-    timer.target_ends_at = None;
-    timer.interruptions = 0;
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MergeResult {
+    // Sessions that gained the target (ones already linked to it are not counted).
+    moved_sessions: i64,
+    removed: i64,
 }
 
-fn complete_and_advance(
-    app: &AppHandle,
-    model: &mut AppModel,
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BulkUpdateResult {
+    updated: i64,
+}
+
+// Focus sessions for sessions_bulk_tag: the usual range filters, optionally narrowed to
+// sessions that carry no tag yet.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BulkTagFilter {
+    #[serde(flatten)]
+    range: AnalyticsRange,
+    #[serde(default)]
+    untagged: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ExportResult {
+    filename: String,
+    content: String,
+}
+
+// A session read from an import file. Source ids mean nothing in this database, so
+// projects and tags are carried by name and matched (case-insensitively) on import.
+#[derive(Debug, Clone)]
+struct ImportedSession {
+    // 1-based data row (JSON array index or CSV line after the header).
+    row: usize,
+    started_at: i64,
+    ended_at: i64,
+    phase: TimerPhase,
+    duration_sec: i64,
     completed: bool,
-) -> AppResult<(SessionRecord, PhaseCompletedEvent, TimerState)> {
-    let finished_phase = model.timer.phase.clone();
-    let session = record_session(&model.conn, &model.timer, completed, now_ts())?;
+    interruptions: i64,
+    project_name: Option<String>,
+    tag_names: Vec<String>,
+    quality: Option<i64>,
+}
 
-    advance_timer(&mut model.timer, &model.settings);
-    save_timer_state(&model.conn, &model.timer)?;
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+struct ImportRowError {
+    row: usize,
+    message: String,
+}
 
-    let event = PhaseCompletedEvent {
-        completed_phase: finished_phase,
-        next_phase: model.timer.phase.clone(),
-    };
+// Header names holding each field of an import_csv file. Only start and end are required:
+// without a phase column every row is focus, and without a duration column it is end - start.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct CsvColumnMapping {
+    started_at: String,
+    ended_at: String,
+    phase: Option<String>,
+    duration_sec: Option<String>,
+    // Without a completed column every row counts as completed.
+    completed: Option<String>,
+    interruptions: Option<String>,
+    project_name: Option<String>,
+    tag_names: Option<String>,
+    // Separator between tags inside the tag column; defaults to ';'.
+    tag_separator: Option<String>,
+    quality: Option<String>,
+}
 
-    if model.settings.notifications_enabled {
-        let body = format!(
-            "{} complete. Next: {}",
-            event.completed_phase, event.next_phase
-        );
-        let _ = app
-            .notification()
-            .builder()
-            .title("Pomodoro update")
-            .body(&body)
-            .show();
+impl CsvColumnMapping {
+    // The columns export_csv writes, which import_preview expects.
+    fn pulse_export() -> Self {
+        let column = |name: &str| Some(name.to_string());
+        CsvColumnMapping {
+            started_at: "startedAt".to_string(),
+            ended_at: "endedAt".to_string(),
+            phase: column("phase"),
+            duration_sec: column("durationSec"),
+            completed: column("completed"),
+            interruptions: column("interruptions"),
+            project_name: column("projectName"),
+            tag_names: column("tagNames"),
+            tag_separator: None,
+            quality: column("quality"),
+        }
     }
+}
 
-    Ok((session, event, model.timer.clone()))
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct CsvImportResult {
+    imported: i64,
+    // Rows matching an existing session (same phase and start) or an earlier row. Rows that
+    // overlap a different session are reported as errors instead.
+    duplicates: i64,
+    projects_created: Vec<String>,
+    tags_created: Vec<String>,
+    errors: Vec<ImportRowError>,
 }
 
-fn setup_tray(app: &AppHandle) -> AppResult<()> {
-    let toggle = MenuItem::with_id(app, "toggle", "Start / Pause", true, None::<&str>)
-        .map_err(|e| e.to_string())?;
-    let skip = MenuItem::with_id(app, "skip", "Skip phase", true, None::<&str>)
-        .map_err(|e| e.to_string())?;
-    let open = MenuItem::with_id(app, "open", "Open dashboard", true, None::<&str>)
-        .map_err(|e| e.to_string())?;
-    let quit =
-        MenuItem::with_id(app, "quit", "Quit", true, None::<&str>).map_err(|e| e.to_string())?;
+struct ImportBatch {
+    sessions: Vec<ImportedSession>,
+    project_names: Vec<String>,
+    tag_names: Vec<String>,
+    errors: Vec<ImportRowError>,
+}
 
-    let menu = Menu::with_items(app, &[&toggle, &skip, &open, &quit]).map_err(|e| e.to_string())?;
+// "replace" wipes projects, tags and sessions and restores the backup's settings; "merge"
+// keeps them, matching projects and tags by name and skipping sessions already present.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+enum ImportMode {
+    Replace,
+    Merge,
+}
 
-    TrayIconBuilder::with_id(TRAY_ID)
-        .menu(&menu)
-        .title("Pomodoro")
-        .show_menu_on_left_click(true)
-        .on_menu_event(|app, event| match event.id().as_ref() {
-            "toggle" => {
-                let _ = tray_toggle_timer(app);
-            }
-            "skip" => {
-                let _ = tray_skip_timer(app);
-            }
-            "open" => {
-                if let Some(window) = app.get_webview_window("main") {
-                    let _ = window.show();
-                    let _ = window.set_focus();
-                }
-            }
-            "quit" => {
-                app.exit(0);
-            }
-            _ => {}
-        })
-        .build(app)
-        .map_err(|e| e.to_string())?;
-
-    Ok(())
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct JsonImportResult {
+    mode: ImportMode,
+    projects_created: i64,
+    tags_created: i64,
+    sessions_imported: i64,
+    // Merge mode: sessions with the same phase and start as an existing one.
+    sessions_skipped: i64,
+    settings_restored: bool,
 }
 
-fn tray_toggle_timer(app: &AppHandle) -> AppResult<()> {
-    let state = app.state::<AppState>();
-    let timer = {
-        let mut model = state.model.lock().map_err(|e| e.to_string())?;
-        refresh_remaining(&mut model.timer);
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ImportPreview {
+    format: String,
+    total_rows: usize,
+    valid_rows: usize,
+    // Valid rows matching an existing session (same phase and start) or an earlier row.
+    duplicate_rows: usize,
+    projects_to_create: Vec<String>,
+    tags_to_create: Vec<String>,
+    errors: Vec<ImportRowError>,
+}
 
-        if model.timer.is_running {
-            model.timer.is_running = false;
-            model.timer.target_ends_at = None;
-            if model.timer.phase == TimerPhase::Focus {
-                model.timer.interruptions += 1;
-            }
-        } else {
-            if model.timer.remaining_seconds <= 0 {
-                model.timer.remaining_seconds = model.timer.phase_total_seconds;
-            }
-            if model.timer.started_at.is_none() {
-                model.timer.started_at = Some(now_ts());
-            }
-            model.timer.is_running = true;
-            model.timer.target_ends_at = Some(now_ts() + model.timer.remaining_seconds);
-        }
+// Binary exports travel over IPC as base64.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BinaryExportResult {
+    filename: String,
+    content_base64: String,
+}
 
-        save_timer_state(&model.conn, &model.timer)?;
-        model.timer.clone()
-    };
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ExportRange {
+    from: Option<i64>,
+    to: Option<i64>,
+}
 
-    emit_timer_state(app, &timer);
-    Ok(())
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CsvOptions {
+    // "comma" (default), "semicolon" or "tab".
+    delimiter: Option<String>,
+    // "epoch" (default) or "iso_local".
+    date_format: Option<String>,
+    // Column names as in the default header; extra columns: projectName, tagNames, notes,
+    // quality, billable, hourlyRate, earnings.
+    columns: Option<Vec<String>>,
+    // Append startedAtLocal, endedAtLocal and weekday to the chosen columns.
+    local_timestamps: Option<bool>,
 }
 
-fn tray_skip_timer(app: &AppHandle) -> AppResult<()> {
-    let (session, phase_event, timer) = {
-        let state = app.state::<AppState>();
-        let mut model = state.model.lock().map_err(|e| e.to_string())?;
-        refresh_remaining(&mut model.timer);
-        complete_and_advance(app, &mut model, false)?
-    };
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct JsonExportOptions {
+    // Add startedAtLocal, endedAtLocal and weekday to every session.
+    local_timestamps: Option<bool>,
+}
 
-    let _ = app.emit("session://completed", &session);
-    let _ = app.emit("timer://phase-completed", &phase_event);
-    emit_timer_state(app, &timer);
-    Ok(())
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CsvColumn {
+    Id,
+    StartedAt,
+    EndedAt,
+    Phase,
+    DurationSec,
+    Completed,
+    Interruptions,
+    ProjectId,
+    TagIds,
+    ProjectName,
+    TagNames,
+    Notes,
+    Quality,
+    Billable,
+    HourlyRate,
+    Earnings,
+    StartedAtLocal,
+    EndedAtLocal,
+    Weekday,
 }
 
-fn timer_start_inner(
-    app: &AppHandle,
-    state: &AppState,
-    payload: Option<StartTimerRequest>,
-) -> AppResult<TimerState> {
-    let timer = {
-        let mut model = state.model.lock().map_err(|e| e.to_string())?;
-        refresh_remaining(&mut model.timer);
+impl CsvColumn {
+    const DEFAULT: [CsvColumn; 9] = [
+        CsvColumn::Id,
+        CsvColumn::StartedAt,
+        CsvColumn::EndedAt,
+        CsvColumn::Phase,
+        CsvColumn::DurationSec,
+        CsvColumn::Completed,
+        CsvColumn::Interruptions,
+        CsvColumn::ProjectId,
+        CsvColumn::TagIds,
+    ];
 
-        if let Some(payload) = payload {
-            if let Some(project_id) = payload.project_id {
-                model.timer.current_project_id = project_id;
-            }
-            if let Some(tag_ids) = payload.tag_ids {
-                model.timer.current_tag_ids = tag_ids;
-            }
+    fn header(&self) -> &'static str {
+        match self {
+            CsvColumn::Id => "id",
+            CsvColumn::StartedAt => "startedAt",
+            CsvColumn::EndedAt => "endedAt",
+            CsvColumn::Phase => "phase",
+            CsvColumn::DurationSec => "durationSec",
+            CsvColumn::Completed => "completed",
+            CsvColumn::Interruptions => "interruptions",
+            CsvColumn::ProjectId => "projectId",
+            CsvColumn::TagIds => "tagIds",
+            CsvColumn::ProjectName => "projectName",
+            CsvColumn::TagNames => "tagNames",
+            CsvColumn::Notes => "notes",
+            CsvColumn::Quality => "quality",
+            CsvColumn::Billable => "billable",
+            CsvColumn::HourlyRate => "hourlyRate",
+            CsvColumn::Earnings => "earnings",
+            CsvColumn::StartedAtLocal => "startedAtLocal",
+            CsvColumn::EndedAtLocal => "endedAtLocal",
+            CsvColumn::Weekday => "weekday",
         }
+    }
 
-        if model.timer.remaining_seconds <= 0 {
-            model.timer.remaining_seconds = model.timer.phase_total_seconds;
-        }
-        if model.timer.started_at.is_none() {
-            model.timer.started_at = Some(now_ts());
-        }
+    fn from_header(name: &str) -> AppResult<Self> {
+        Self::DEFAULT
+            .iter()
+            .chain(&[
+                CsvColumn::ProjectName,
+                CsvColumn::TagNames,
+                CsvColumn::Notes,
+                CsvColumn::Quality,
+                CsvColumn::Billable,
+                CsvColumn::HourlyRate,
+                CsvColumn::Earnings,
+                CsvColumn::StartedAtLocal,
+                CsvColumn::EndedAtLocal,
+                CsvColumn::Weekday,
+            ])
+            .find(|column| column.header() == name)
+            .copied()
+            .ok_or_else(|| format!("unknown CSV column: {name}"))
+    }
+}
 
-        model.timer.is_running = true;
-        model.timer.target_ends_at = Some(now_ts() + model.timer.remaining_seconds);
+struct CsvDialect {
+    delimiter: char,
+    iso_dates: bool,
+    timezone: String,
+}
 
-        save_timer_state(&model.conn, &model.timer)?;
-        model.timer.clone()
-    };
+impl CsvDialect {
+    fn from_options(options: &CsvOptions, timezone: &str) -> AppResult<Self> {
+        let delimiter = match options.delimiter.as_deref().unwrap_or("comma") {
+            "comma" => ',',
+            "semicolon" => ';',
+            "tab" => '\t',
+            other => return Err(format!("unknown CSV delimiter: {other}")),
+        };
+        let iso_dates = match options.date_format.as_deref().unwrap_or("epoch") {
+            "epoch" => false,
+            "iso_local" => true,
+            other => return Err(format!("unknown CSV date format: {other}")),
+        };
+        Ok(Self {
+            delimiter,
+            iso_dates,
+            timezone: timezone.to_string(),
+        })
+    }
 
-    emit_timer_state(app, &timer);
-    Ok(timer)
-}
+    // Quote only when needed, so the default dialect keeps producing the historical output.
+    fn field(&self, value: &str) -> String {
+        if value.contains([self.delimiter, '"', '\n', '\r']) {
+            format!("\"{}\"", value.replace('"', "\"\""))
+        } else {
+            value.to_string()
+        }
+    }
 
-fn timer_pause_inner(app: &AppHandle, state: &AppState) -> AppResult<TimerState> {
-    let timer = {
-        let mut model = state.model.lock().map_err(|e| e.to_string())?;
-        refresh_remaining(&mut model.timer);
-        if model.timer.phase == TimerPhase::Focus && model.timer.is_running {
-            model.timer.interruptions += 1;
+    fn timestamp(&self, ts: i64) -> String {
+        if self.iso_dates {
+            self.local(ts)
+        } else {
+            ts.to_string()
         }
-        model.timer.is_running = false;
-        model.timer.target_ends_at = None;
-        save_timer_state(&model.conn, &model.timer)?;
-        model.timer.clone()
-    };
+    }
 
-    emit_timer_state(app, &timer);
-    Ok(timer)
+    fn local(&self, ts: i64) -> String {
+        zoned_datetime(ts, &self.timezone).to_rfc3339()
+    }
+
+    fn weekday(&self, ts: i64) -> String {
+        zoned_datetime(ts, &self.timezone).format("%A").to_string()
+    }
 }
 
-fn timer_resume_inner(
-    app: &AppHandle,
-    state: &AppState,
-    payload: Option<StartTimerRequest>,
-) -> AppResult<TimerState> {
-    let timer = {
-        let mut model = state.model.lock().map_err(|e| e.to_string())?;
-        if let Some(payload) = payload {
-            if let Some(project_id) = payload.project_id {
-                model.timer.current_project_id = project_id;
-            }
-            if let Some(tag_ids) = payload.tag_ids {
-                model.timer.current_tag_ids = tag_ids;
-            }
-        }
-        if model.timer.remaining_seconds <= 0 {
-            model.timer.remaining_seconds = model.timer.phase_total_seconds;
-        }
-        if model.timer.started_at.is_none() {
-            model.timer.started_at = Some(now_ts());
-        }
-        model.timer.is_running = true;
-        model.timer.target_ends_at = Some(now_ts() + model.timer.remaining_seconds);
-        save_timer_state(&model.conn, &model.timer)?;
-        model.timer.clone()
-    };
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ResetAllResult {
+    settings: AppSettings,
+    timer: TimerState,
+}
 
-    emit_timer_state(app, &timer);
-    Ok(timer)
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct TailscaleHost {
+    // MagicDNS name, e.g. `laptop.tail1234.ts.net`; None when MagicDNS is off.
+    dns_name: Option<String>,
+    ip: Option<String>,
 }
 
-fn timer_skip_inner(app: &AppHandle, state: &AppState) -> AppResult<TimerState> {
-    let (session, phase_event, timer) = {
-        let mut model = state.model.lock().map_err(|e| e.to_string())?;
-        refresh_remaining(&mut model.timer);
-        complete_and_advance(app, &mut model, false)?
-    };
+// A named remote API token. Each phone or script gets its own, so one can be revoked alone.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct RemoteToken {
+    id: i64,
+    name: String,
+    // Only the remote_tokens_create response carries the token; the database keeps its hash.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    token: Option<String>,
+    // Endpoint groups the token may call; the local loopback aliases need no token.
+    scopes: Vec<RemoteScope>,
+    created_at: i64,
+    last_used_at: Option<i64>,
+}
 
-    let _ = app.emit("session://completed", &session);
-    let _ = app.emit("timer://phase-completed", &phase_event);
-    emit_timer_state(app, &timer);
-    Ok(timer)
+// The VAPID key pair push services use to check that pushes come from this app, as
+// base64url without padding. Stored under WEB_PUSH_KEY, generated on first use.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct VapidKeys {
+    private_key: String,
+    public_key: String,
 }
 
-fn timer_get_state_inner(state: &AppState) -> AppResult<TimerState> {
-    let mut model = state.model.lock().map_err(|e| e.to_string())?;
-    refresh_remaining(&mut model.timer);
-    Ok(model.timer.clone())
+// What `PushSubscription.toJSON()` returns in the browser.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct PushSubscriptionInput {
+    endpoint: String,
+    keys: PushSubscriptionKeys,
 }
 
-fn remote_stop(remote: &mut RemoteControlState) {
-    if let Some(mut handle) = remote.server.take() {
-        handle.stop.store(false, Ordering::SeqCst);
-        if let Some(join) = handle.join.take() {
-            let _ = join.join();
-        }
-    }
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+struct PushSubscriptionKeys {
+    p256dh: String,
+    auth: String,
 }
 
-fn bind_remote_listener(port: u16) -> AppResult<TcpListener> {
-    let addr = format!("0.0.0.0:{port}");
-    let listener = TcpListener::bind(&addr)
-        .map_err(|e| format!("remote control server bind failed on {addr}: {e}"))?;
-    listener
-        .set_nonblocking(true)
-        .map_err(|e| format!("remote control server nonblocking setup failed on {addr}: {e}"))?;
-    Ok(listener)
+// One authenticated remote request. `token_name` is the name the token had at the time, so
+// entries stay readable after it is revoked.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct RemoteAuditEntry {
+    id: i64,
+    at: i64,
+    // Repeats of the same request folded into this entry, and when the latest came in.
+    last_at: i64,
+    hits: i64,
+    ip: String,
+    route: String,
+    token_name: String,
 }
 
-fn remote_apply(app: &AppHandle, settings: &AppSettings) -> AppResult<()> {
-    let state = app.state::<AppState>();
-    let mut remote = state.remote.lock().map_err(|e| e.to_string())?;
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+enum RemoteDeviceStatus {
+    Pending,
+    Approved,
+    Rejected,
+}
 
-    if !settings.remote_control_enabled {
-        remote_stop(&mut remote);
-        return Ok(());
+impl RemoteDeviceStatus {
+    fn as_str(self) -> &'static str {
+        match self {
+            RemoteDeviceStatus::Pending => "pending",
+            RemoteDeviceStatus::Approved => "approved",
+            RemoteDeviceStatus::Rejected => "rejected",
+        }
     }
 
-    let port = settings.remote_control_port as u16;
-    let needs_restart = match remote.server.as_ref() {
-        None => true,
-        Some(handle) => handle.port != port,
-    };
-
-    if !needs_restart {
-        return Ok(());
+    fn from_db_value(value: &str) -> AppResult<Self> {
+        match value {
+            "pending" => Ok(RemoteDeviceStatus::Pending),
+            "approved" => Ok(RemoteDeviceStatus::Approved),
+            "rejected" => Ok(RemoteDeviceStatus::Rejected),
+            other => Err(format!("unknown device status: {other}")),
+        }
     }
+}
 
-    remote_stop(&mut remote);
+// A phone or script that presented a valid token. With pairing required, only approved
+// devices get through; the fingerprint ties the device to the token it used.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct RemoteDevice {
+    id: i64,
+    token_id: i64,
+    token_name: Option<String>,
+    label: String,
+    ip: String,
+    status: RemoteDeviceStatus,
+    created_at: i64,
+    decided_at: Option<i64>,
+    last_seen_at: Option<i64>,
+}
 
-    let listener = bind_remote_listener(port)?;
-    let stop = Arc::new(AtomicBool::new(true));
-    let stop_thread = stop.clone();
-    let app_handle = app.clone();
+// Ways a phone can reach the remote control page. `tailscale_url` is set when this machine is
+// on a running tailnet and works off-LAN. The URLs carry no token; append `?token=` for one
+// from remote_tokens_list.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct RemoteDiscoveryInfo {
+    enabled: bool,
+    port: i64,
+    lan_ip: Option<String>,
+    lan_url: Option<String>,
+    // Every local address, IPv4 first, so IPv6-only networks get a URL too.
+    lan_urls: Vec<String>,
+    tailscale: Option<TailscaleHost>,
+    tailscale_url: Option<String>,
+}
 
-    let join = thread::spawn(move || remote_server_loop(app_handle, listener, stop_thread));
-    remote.server = Some(RemoteServerHandle {
-        port,
-        stop,
-        join: Some(join),
-    });
+// A focus block queued ahead of time. Starting it marks the resulting session as planned; items
+// leave the queue once started.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct PlanItem {
+    id: i64,
+    title: String,
+    project_id: Option<i64>,
+    tag_ids: Vec<i64>,
+    created_at: i64,
+}
 
-    Ok(())
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct MusicTrack {
+    id: i64,
+    path: String,
+    title: String,
+    // 0.0 to 1.0, applied on top of the system volume.
+    volume: f64,
 }
 
-fn header_value<'a>(headers: &'a [httparse::Header<'a>], name: &str) -> Option<&'a str> {
-    for h in headers {
-        if h.name.eq_ignore_ascii_case(name) {
-            return std::str::from_utf8(h.value).ok();
-        }
-    }
-    None
+// `loop_track` is set when the playlist has a single track, so the window loops the decoded
+// buffer gaplessly instead of asking for the next one.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct MusicPlayEvent {
+    track: MusicTrack,
+    loop_track: bool,
 }
 
-fn parse_query_param<'a>(query: &'a str, key: &str) -> Option<&'a str> {
-    for part in query.split('&') {
-        let mut it = part.splitn(2, '=');
-        let k = it.next().unwrap_or("");
-        if k == key {
-            return Some(it.next().unwrap_or(""));
-        }
-    }
-    None
+#[derive(Debug, PartialEq)]
+enum MusicEvent {
+    Play(MusicPlayEvent),
+    Stop,
 }
 
-fn split_path_query(path: &str) -> (&str, &str) {
-    match path.split_once('?') {
-        Some((p, q)) => (p, q),
-        None => (path, ""),
-    }
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MusicTrackData {
+    track_id: i64,
+    mime: String,
+    content_base64: String,
 }
 
-fn write_response(stream: &mut std::net::TcpStream, code: &str, content_type: &str, body: &[u8]) {
-    let headers = format!(
-        "HTTP/1.1 {code}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\nAccess-Control-Allow-Origin: *\r\nAccess-Control-Allow-Headers: Content-Type, X-Pomodoro-Token\r\nAccess-Control-Allow-Methods: GET, POST, OPTIONS\r\n\r\n",
-        body.len()
-    );
-    let _ = stream.write_all(headers.as_bytes());
-    let _ = stream.write_all(body);
+// One step of the break breathing guide. `ends_at` is wall-clock, so the overlay can animate
+// toward it instead of counting with its own timers.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct BreathingStep {
+    // "inhale", "hold", "exhale" or "hold_out".
+    step: String,
+    step_index: usize,
+    step_seconds: i64,
+    ends_at: i64,
+    cycle: i64,
 }
 
-fn remote_html() -> String {
-    // Minimal, mobile-friendly control page served from the Rust backend.
-    r#"<!doctype html>
-<html lang="en">
-  <head>
-    <meta charset="utf-8" />
-    <meta name="viewport" content="width=device-width, initial-scale=1" />
-    <title>Pomodoro Remote</title>
-    <style>
-      :root { color-scheme: light; }
-      body { font-family: ui-sans-serif, system-ui, -apple-system, Segoe UI, Roboto, Helvetica, Arial; margin: 0; background: #0b1220; color: #e8eefc; }
-      .wrap { max-width: 520px; margin: 0 auto; padding: 16px; }
-      .card { background: rgba(255,255,255,0.06); border: 1px solid rgba(255,255,255,0.10); border-radius: 16px; padding: 16px; }
-      .title { font-size: 18px; font-weight: 650; letter-spacing: 0.2px; margin: 0 0 10px; }
-      .row { display: flex; gap: 10px; align-items: center; justify-content: space-between; }
-      .big { font-size: 44px; font-weight: 750; letter-spacing: 0.6px; }
-      .muted { color: rgba(232,238,252,0.72); font-size: 13px; }
-      .btns { display: grid; grid-template-columns: 1fr 1fr; gap: 10px; margin-top: 14px; }
-      button { appearance: none; border: 1px solid rgba(255,255,255,0.14); background: rgba(255,255,255,0.08); color: #e8eefc; padding: 12px 14px; border-radius: 12px; font-size: 16px; font-weight: 650; }
-      button:active { transform: translateY(1px); }
-      button.primary { background: rgba(46, 160, 255, 0.22); border-color: rgba(46, 160, 255, 0.35); }
-      button.danger { background: rgba(255, 77, 77, 0.18); border-color: rgba(255, 77, 77, 0.30); }
-      .token { width: 100%; padding: 12px 14px; border-radius: 12px; border: 1px solid rgba(255,255,255,0.14); background: rgba(0,0,0,0.25); color: #e8eefc; font-size: 16px; }
-      .sp { height: 12px; }
-      a { color: #a9d1ff; }
-    </style>
-  </head>
-  <body>
-    <div class="wrap">
-      <div class="card">
-        <p class="title">Pomodoro Remote</p>
-        <div id="auth">
-          <p class="muted">Enter your token (from the macOS app Settings) to control the timer.</p>
-          <input id="token" class="token" placeholder="Token" autocomplete="off" />
-          <div class="sp"></div>
-          <button class="primary" id="saveToken">Continue</button>
-        </div>
-        <div id="main" style="display:none">
-          <div class="row">
-            <div>
-              <div class="muted" id="phase">...</div>
-              <div class="big" id="time">--:--</div>
-            </div>
-            <div class="muted" id="status">...</div>
-          </div>
-          <div class="btns">
-            <button class="primary" id="toggle">Start / Pause</button>
-            <button class="danger" id="skip">Skip Phase</button>
-          </div>
-          <div class="sp"></div>
-          <p class="muted">Tip: you can bookmark this page. Token is stored in the URL as <code>?token=...</code>.</p>
-        </div>
-      </div>
-      <div class="sp"></div>
-      <p class="muted">If this page does not load: ensure Remote Control is enabled in the desktop app Settings, and your iPhone and Mac are on the same Wi‑Fi.</p>
-    </div>
+// Body POSTed to settings.session_webhook_url; names are resolved so receivers need no lookups.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SessionWebhookPayload {
+    event: String,
+    session: SessionRecord,
+    project_name: Option<String>,
+    tag_names: Vec<String>,
+}
 
-    <script>
-      const qs = new URLSearchParams(location.search);
-      let token = qs.get("token") || "";
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ReconcileResult {
+    settings: AppSettings,
+    timer: TimerState,
+    integrity: DbCheckReport,
+}
 
-      const auth = document.getElementById("auth");
-      const main = document.getElementById("main");
-      const tokenInput = document.getElementById("token");
-      const saveToken = document.getElementById("saveToken");
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DatabaseExportResult {
+    path: String,
+    size_bytes: u64,
+}
 
-      function withTokenUrl(t) {
-        const u = new URL(location.href);
-        u.searchParams.set("token", t);
-        return u.toString();
-      }
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DatabaseImportResult {
+    settings: AppSettings,
+    timer: TimerState,
+    // The replaced database, kept next to the new one.
+    backup_path: String,
+    tables: Vec<TableStats>,
+    integrity: DbCheckReport,
+}
 
-      function showMain() { auth.style.display = "none"; main.style.display = "block"; }
-      function showAuth() { auth.style.display = "block"; main.style.display = "none"; }
+// One sample for Apple Health (via a Shortcuts "Log Health Sample" action) or Google Fit.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct HealthEntry {
+    session_id: i64,
+    start_date: String,
+    end_date: String,
+    duration_minutes: f64,
+    // "mindful" for breaks, "focus" for focus sessions.
+    kind: String,
+}
 
-      if (token) showMain(); else showAuth();
-      tokenInput.value = token;
-      saveToken.addEventListener("click", () => {
-        const t = (tokenInput.value || "").trim();
-        if (!t) return;
-        location.href = withTokenUrl(t);
-      });
+// Glanceable numbers for the remote page next to the timer itself.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RemoteToday {
+    completed_pomodoros: i64,
+    focus_seconds: i64,
+    project_name: Option<String>,
+}
 
-      async function api(path, method) {
-        const res = await fetch(path, {
-          method,
-          headers: { "X-Pomodoro-Token": token }
-        });
-        if (res.status === 401) throw new Error("Unauthorized (bad token)");
-        if (!res.ok) throw new Error("HTTP " + res.status);
-        return res.json();
-      }
+// Aggregated per-day totals for team dashboards; deliberately carries no projects, tags or notes.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct TeamDailyTotal {
+    date: String,
+    focus_seconds: i64,
+    completed_pomodoros: i64,
+}
 
-      function phaseLabel(p) {
-        if (p === "focus") return "Focus";
-        if (p === "short_break") return "Short break";
-        if (p === "long_break") return "Long break";
-        return p;
-      }
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct HealthExport {
+    entries: Vec<HealthEntry>,
+    // Latest end time exported, for clients of the older `since` parameter.
+    next_since: Option<i64>,
+    // Pass back as `cursor` to fetch only new entries next time; unlike `since` it also
+    // picks up sessions that ended in the same second as the last one exported.
+    next_cursor: Option<String>,
+    // More than HEALTH_EXPORT_PAGE_SIZE entries were waiting; fetch again with next_cursor.
+    has_more: bool,
+}
 
-      function fmt(sec) {
-        const m = Math.floor(sec / 60);
-        const s = sec % 60;
-        return String(m).padStart(2, "0") + ":" + String(s).padStart(2, "0");
-      }
+const HEALTH_EXPORT_PAGE_SIZE: i64 = 500;
 
-      async function refresh() {
-        if (!token) return;
-        try {
-          const st = await api("/api/state", "GET");
-          document.getElementById("phase").textContent = phaseLabel(st.phase);
-          document.getElementById("time").textContent = fmt(st.remainingSeconds);
-          document.getElementById("status").textContent = st.isRunning ? "Running" : "Paused";
-        } catch (e) {
-          document.getElementById("status").textContent = String(e.message || e);
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PhaseCompletedEvent {
+    completed_phase: TimerPhase,
+    next_phase: TimerPhase,
+    // Lets the UI follow up with session_rate for the session just recorded.
+    session_id: i64,
+    // Resolved from global settings and the session's project override.
+    notify: bool,
+    // Sound for the UI to play; None means stay silent.
+    sound: Option<String>,
+}
+
+// Stored under its own settings key (not in AppSettings) so credentials and the
+// passphrase never end up in plain JSON exports.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct CloudBackupConfig {
+    enabled: bool,
+    webdav_url: String,
+    username: String,
+    password: String,
+    passphrase: String,
+    interval_hours: i64,
+    last_backup_at: Option<i64>,
+    last_attempt_at: Option<i64>,
+    last_error: Option<String>,
+    // Revision of the WebDAV sync file this device last pushed or pulled.
+    sync_revision: i64,
+    last_sync_at: Option<i64>,
+    device_id: String,
+    // Last mutation_log id that revision covers; later data changes are unsynced edits.
+    synced_mutation_id: i64,
+}
+
+impl CloudBackupConfig {
+    fn status(&self) -> CloudBackupStatus {
+        CloudBackupStatus {
+            enabled: self.enabled,
+            webdav_url: self.webdav_url.clone(),
+            username: self.username.clone(),
+            has_password: !self.password.is_empty(),
+            has_passphrase: !self.passphrase.is_empty(),
+            interval_hours: self.interval_hours,
+            last_backup_at: self.last_backup_at,
+            last_error: self.last_error.clone(),
+            sync_revision: self.sync_revision,
+            last_sync_at: self.last_sync_at,
         }
-      }
+    }
+}
 
-      document.getElementById("toggle").addEventListener("click", async () => {
-        try { await api("/api/toggle", "POST"); } finally { await refresh(); }
-      });
-      document.getElementById("skip").addEventListener("click", async () => {
-        try { await api("/api/skip", "POST"); } finally { await refresh(); }
-      });
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CloudBackupConfigPatch {
+    enabled: Option<bool>,
+    webdav_url: Option<String>,
+    username: Option<String>,
+    password: Option<String>,
+    passphrase: Option<String>,
+    interval_hours: Option<i64>,
+}
 
-      refresh();
-      setInterval(refresh, 1000);
-    </script>
-  </body>
-</html>
-"#
-        .to_string()
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CloudBackupStatus {
+    enabled: bool,
+    webdav_url: String,
+    username: String,
+    has_password: bool,
+    has_passphrase: bool,
+    interval_hours: i64,
+    last_backup_at: Option<i64>,
+    last_error: Option<String>,
+    sync_revision: i64,
+    last_sync_at: Option<i64>,
 }
 
-fn remote_handle_connection(app: &AppHandle, mut stream: std::net::TcpStream) {
-    let _ = stream.set_read_timeout(Some(Duration::from_secs(2)));
-    let _ = stream.set_write_timeout(Some(Duration::from_secs(2)));
+// Single-file sync format: the revision stays readable so conflicts can be detected
+// without the passphrase. New files are always encrypted; `backup` holds the plain data of
+// files written by older versions.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SyncEnvelope {
+    revision: i64,
+    device_id: String,
+    updated_at: i64,
+    #[serde(default)]
+    backup: Option<serde_json::Value>,
+    #[serde(default)]
+    encrypted: Option<String>,
+}
 
-    let mut buf = vec![0u8; 8192];
-    let mut filled = 0usize;
-    let mut header_end: Option<usize> = None;
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SyncOutcome {
+    // "pushed", "pulled", "up_to_date", or "conflict".
+    status: String,
+    local_revision: i64,
+    remote_revision: i64,
+}
 
-    // Read until headers complete or size limit hit.
-    while filled < buf.len() {
-        match stream.read(&mut buf[filled..]) {
-            Ok(0) => break,
-            Ok(n) => {
-                filled += n;
-                if let Some(pos) = buf[..filled].windows(4).position(|w| w == b"\r\n\r\n") {
-                    header_end = Some(pos + 4);
-                    break;
-                }
-            }
-            Err(_) => break,
-        }
-    }
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct BackupPayload {
+    // Absent in exports written before versioning, which match version 1.
+    schema_version: Option<i64>,
+    settings: Option<AppSettings>,
+    projects: Vec<Project>,
+    tags: Vec<Tag>,
+    sessions: Vec<SessionRecord>,
+}
 
-    let header_end = match header_end {
-        Some(v) => v,
-        None => {
-            write_response(
-                &mut stream,
-                "400 Bad Request",
-                "text/plain; charset=utf-8",
-                b"bad request",
-            );
-            return;
-        }
-    };
+struct AppModel {
+    conn: Connection,
+    settings: AppSettings,
+    timer: TimerState,
+    // Latest notification held back by dnd_mode "queue"; older ones are stale by then.
+    queued_notification: Option<String>,
+    // Last console probe result, so a detach is handled once, on its leading edge.
+    console_detached: bool,
+    // analytics_records result with the sessions revision and exclusions it was derived at.
+    records_cache: Option<(i64, usize, AnalyticsExclusions, AnalyticsRecords)>,
+    // (cycle, step index) of the last breathing://step event, so each step is sent once.
+    breathing_emitted: Option<(i64, usize)>,
+    // Track the window was last told to play; None while music is stopped.
+    music_playing: Option<i64>,
+    screen_dim: ScreenDim,
+    db_recovery: Option<DbRecoveryReport>,
+    read_only: Option<ReadOnlyMode>,
+}
 
-    let mut headers = [httparse::EMPTY_HEADER; 32];
-    let mut req = httparse::Request::new(&mut headers);
-    let _parsed = match req.parse(&buf[..filled]) {
-        Ok(Status::Complete(n)) => n,
-        _ => {
-            write_response(
-                &mut stream,
-                "400 Bad Request",
-                "text/plain; charset=utf-8",
-                b"bad request",
-            );
-            return;
-        }
-    };
+// `active` is set once a break has tried to dim, so an unsupported display is probed once per
+// break; `restore` is the brightness (percent) to put back when it ends.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+struct ScreenDim {
+    active: bool,
+    restore: Option<i64>,
+}
 
-    let method = req.method.unwrap_or("");
-    let path_raw = req.path.unwrap_or("/");
-    let (path, query) = split_path_query(path_raw);
+struct RemoteServerHandle {
+    // The configured port; `bound_port` differs when it was busy and a fallback was used.
+    port: u16,
+    bound_port: u16,
+    stop: Arc<AtomicBool>,
+    join: Option<thread::JoinHandle<()>>,
+    advert: Option<RemoteAdvert>,
+}
 
-    let content_length = header_value(req.headers, "Content-Length")
-        .and_then(|v| v.parse::<usize>().ok())
-        .unwrap_or(0);
+// A registered mDNS service; dropping it without `remote_unadvertise` leaves the daemon running.
+struct RemoteAdvert {
+    daemon: mdns_sd::ServiceDaemon,
+    fullname: String,
+}
 
-    let mut body = Vec::new();
-    if content_length > 0 {
-        // We may already have some body bytes in the initial buffer.
-        let already = filled.saturating_sub(header_end);
-        if already > 0 {
-            body.extend_from_slice(&buf[header_end..filled]);
-        }
+struct RemoteControlState {
+    server: Option<RemoteServerHandle>,
+    status: RemoteStatus,
+}
 
-        while body.len() < content_length {
-            let mut chunk = vec![0u8; (content_length - body.len()).min(4096)];
-            match stream.read(&mut chunk) {
-                Ok(0) => break,
-                Ok(n) => body.extend_from_slice(&chunk[..n]),
-                Err(_) => break,
-            }
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+enum RemoteRunState {
+    Stopped,
+    Running,
+    Failed,
+}
+
+// Sent as remote://status whenever the server starts, stops or fails to bind.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct RemoteStatus {
+    state: RemoteRunState,
+    configured_port: Option<u16>,
+    // The port actually listening; differs from configured_port after a fallback.
+    port: Option<u16>,
+    reason: Option<String>,
+}
+
+impl RemoteStatus {
+    fn stopped() -> Self {
+        RemoteStatus {
+            state: RemoteRunState::Stopped,
+            configured_port: None,
+            port: None,
+            reason: None,
         }
     }
+}
 
-    if method.eq_ignore_ascii_case("OPTIONS") {
-        write_response(
-            &mut stream,
-            "204 No Content",
-            "text/plain; charset=utf-8",
-            b"",
-        );
-        return;
-    }
+#[derive(Debug, Clone)]
+struct RemoteEvent {
+    name: &'static str,
+    // JSON, as Tauri serialized it for the webview.
+    payload: String,
+}
 
-    // Snapshot settings for auth/enable checks.
-    let (remote_enabled, token_expected) = {
-        let state = app.state::<AppState>();
-        let model = match state.model.lock() {
-            Ok(m) => m,
-            Err(_) => {
-                write_response(
-                    &mut stream,
-                    "500 Internal Server Error",
-                    "text/plain; charset=utf-8",
-                    b"error",
-                );
-                return;
-            }
-        };
-        (
-            model.settings.remote_control_enabled,
-            model.settings.remote_control_token.clone(),
-        )
-    };
+// Fans REMOTE_PUSH_EVENTS out to long-lived remote connections (WebSocket and SSE), one
+// channel per connection. A closed connection's sender is dropped on the next publish.
+#[derive(Debug, Default)]
+struct RemoteBroadcast {
+    subscribers: Vec<std::sync::mpsc::Sender<RemoteEvent>>,
+}
 
-    if !remote_enabled {
-        write_response(
-            &mut stream,
-            "404 Not Found",
-            "text/plain; charset=utf-8",
-            b"not found",
-        );
-        return;
+impl RemoteBroadcast {
+    fn subscribe(&mut self) -> std::sync::mpsc::Receiver<RemoteEvent> {
+        let (tx, rx) = std::sync::mpsc::channel();
+        self.subscribers.push(tx);
+        rx
     }
 
-    // Serve the control page without requiring auth, so users can load it and paste a token.
-    // All API endpoints remain token-protected.
-    if method.eq_ignore_ascii_case("GET") && path == "/" {
-        let html = remote_html();
-        write_response(
-            &mut stream,
-            "200 OK",
-            "text/html; charset=utf-8",
-            html.as_bytes(),
-        );
-        return;
+    fn publish(&mut self, event: RemoteEvent) {
+        self.subscribers
+            .retain(|subscriber| subscriber.send(event.clone()).is_ok());
     }
+}
 
-    let token_got = header_value(req.headers, "X-Pomodoro-Token")
-        .or_else(|| parse_query_param(query, "token"))
-        .unwrap_or("");
-    if token_got != token_expected {
-        write_response(
-            &mut stream,
-            "401 Unauthorized",
-            "text/plain; charset=utf-8",
-            b"unauthorized",
-        );
-        return;
-    }
+const REMOTE_RATE_WINDOW_SECONDS: i64 = 60;
+// The remote page polls once a second at most, so this leaves room for actions on top.
+const REMOTE_RATE_LIMIT: u32 = 180;
+const REMOTE_LOCKOUT_FAILURES: u32 = 5;
+const REMOTE_LOCKOUT_SECONDS: i64 = 15 * 60;
+const REMOTE_SECURITY_LOG_MAX: usize = 200;
+// Clients with failures are kept until they succeed, so the map needs a bound of its own.
+const REMOTE_GUARD_MAX_CLIENTS: usize = 4096;
 
-    // API routes.
-    let state = app.state::<AppState>();
-    let json = match (method, path) {
-        ("GET", "/api/state") => match timer_get_state_inner(state.inner()) {
-            Ok(v) => serde_json::to_vec(&v).ok(),
-            Err(e) => {
-                Some(serde_json::to_vec(&serde_json::json!({ "error": e })).unwrap_or_default())
-            }
+// Who a request counts against. An IPv6 host usually owns a whole /64 and can pick any address
+// in it, so limits apply to the /64; IPv4-mapped addresses count as the IPv4 host.
+fn remote_client_key(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V4(_) => ip,
+        IpAddr::V6(v6) => match v6.to_ipv4_mapped() {
+            Some(v4) => IpAddr::V4(v4),
+            None => IpAddr::V6(std::net::Ipv6Addr::from(
+                u128::from(v6) & !((1u128 << 64) - 1),
+            )),
         },
-        ("POST", "/api/toggle") => {
-            let current = timer_get_state_inner(state.inner());
-            let next = match current {
-                Ok(st) => {
-                    if st.is_running {
-                        timer_pause_inner(app, state.inner())
-                    } else if st.started_at.is_some() {
-                        timer_resume_inner(app, state.inner(), None)
-                    } else {
-                        timer_start_inner(app, state.inner(), None)
-                    }
-                }
-                Err(e) => Err(e),
-            };
-            match next {
-                Ok(v) => serde_json::to_vec(&v).ok(),
-                Err(e) => {
-                    Some(serde_json::to_vec(&serde_json::json!({ "error": e })).unwrap_or_default())
-                }
-            }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct RemoteClient {
+    window_start: i64,
+    requests: u32,
+    // Failed authentications since the last success or lockout.
+    failures: u32,
+    locked_until: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct RemoteSecurityEvent {
+    at: i64,
+    ip: String,
+    route: String,
+    // "unauthorized", "locked_out" (the failure that started a lockout) or "rate_limited".
+    kind: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct RemoteLockout {
+    ip: String,
+    until: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct RemoteSecurityLog {
+    // Newest first, at most REMOTE_SECURITY_LOG_MAX since app start.
+    events: Vec<RemoteSecurityEvent>,
+    lockouts: Vec<RemoteLockout>,
+    unauthorized_total: u64,
+    rate_limited_total: u64,
+}
+
+#[derive(Debug, PartialEq)]
+enum RemoteRejection {
+    LockedOut { until: i64 },
+    RateLimited,
+}
+
+// Per-IP request windows and failed-auth counts for the remote server, kept in memory only.
+// An IP making REMOTE_LOCKOUT_FAILURES bad attempts in a row is refused until its lockout
+// ends, which caps token guessing at a handful of tries per quarter hour.
+#[derive(Debug, Default)]
+struct RemoteGuard {
+    clients: BTreeMap<IpAddr, RemoteClient>,
+    events: std::collections::VecDeque<RemoteSecurityEvent>,
+    unauthorized_total: u64,
+    rate_limited_total: u64,
+}
+
+impl RemoteGuard {
+    // Counts the request against `ip` and says whether to serve it.
+    fn admit(&mut self, ip: IpAddr, route: &str, at: i64) -> Result<(), RemoteRejection> {
+        self.clients.retain(|_, client| {
+            client.locked_until.is_some_and(|until| until > at)
+                || client.failures > 0
+                || client.window_start + REMOTE_RATE_WINDOW_SECONDS > at
+        });
+        let client = self.client(ip);
+        if let Some(until) = client.locked_until.filter(|until| *until > at) {
+            return Err(RemoteRejection::LockedOut { until });
         }
-        ("POST", "/api/start") => {
-            let payload = serde_json::from_slice::<StartTimerRequest>(&body).ok();
-            match timer_start_inner(app, state.inner(), payload) {
-                Ok(v) => serde_json::to_vec(&v).ok(),
-                Err(e) => {
-                    Some(serde_json::to_vec(&serde_json::json!({ "error": e })).unwrap_or_default())
-                }
-            }
+        client.locked_until = None;
+        if client.window_start + REMOTE_RATE_WINDOW_SECONDS <= at {
+            client.window_start = at;
+            client.requests = 0;
         }
-        ("POST", "/api/pause") => match timer_pause_inner(app, state.inner()) {
-            Ok(v) => serde_json::to_vec(&v).ok(),
-            Err(e) => {
-                Some(serde_json::to_vec(&serde_json::json!({ "error": e })).unwrap_or_default())
-            }
-        },
-        ("POST", "/api/resume") => {
-            let payload = serde_json::from_slice::<StartTimerRequest>(&body).ok();
-            match timer_resume_inner(app, state.inner(), payload) {
-                Ok(v) => serde_json::to_vec(&v).ok(),
-                Err(e) => {
-                    Some(serde_json::to_vec(&serde_json::json!({ "error": e })).unwrap_or_default())
-                }
+        client.requests += 1;
+        if client.requests > REMOTE_RATE_LIMIT {
+            // Logged once per window rather than for every refused request.
+            if client.requests == REMOTE_RATE_LIMIT + 1 {
+                self.log(ip, route, "rate_limited", at);
             }
+            self.rate_limited_total += 1;
+            return Err(RemoteRejection::RateLimited);
         }
-        ("POST", "/api/skip") => match timer_skip_inner(app, state.inner()) {
-            Ok(v) => serde_json::to_vec(&v).ok(),
-            Err(e) => {
-                Some(serde_json::to_vec(&serde_json::json!({ "error": e })).unwrap_or_default())
-            }
-        },
-        _ => None,
-    };
+        Ok(())
+    }
+
+    fn record_failure(&mut self, ip: IpAddr, route: &str, at: i64) {
+        self.unauthorized_total += 1;
+        let client = self.client(ip);
+        client.failures += 1;
+        let kind = if client.failures >= REMOTE_LOCKOUT_FAILURES {
+            client.failures = 0;
+            client.locked_until = Some(at + REMOTE_LOCKOUT_SECONDS);
+            "locked_out"
+        } else {
+            "unauthorized"
+        };
+        self.log(ip, route, kind, at);
+    }
+
+    fn record_success(&mut self, ip: IpAddr) {
+        if let Some(client) = self.clients.get_mut(&remote_client_key(ip)) {
+            client.failures = 0;
+        }
+    }
+
+    // When the map is full, a new client evicts the one idle longest, locked-out clients last.
+    fn client(&mut self, ip: IpAddr) -> &mut RemoteClient {
+        let key = remote_client_key(ip);
+        if !self.clients.contains_key(&key) && self.clients.len() >= REMOTE_GUARD_MAX_CLIENTS {
+            let evict = self
+                .clients
+                .iter()
+                .min_by_key(|(_, client)| (client.locked_until.is_some(), client.window_start))
+                .map(|(key, _)| *key);
+            if let Some(evict) = evict {
+                self.clients.remove(&evict);
+            }
+        }
+        self.clients.entry(key).or_default()
+    }
+
+    fn log(&mut self, ip: IpAddr, route: &str, kind: &str, at: i64) {
+        if self.events.len() == REMOTE_SECURITY_LOG_MAX {
+            self.events.pop_back();
+        }
+        self.events.push_front(RemoteSecurityEvent {
+            at,
+            ip: ip.to_string(),
+            route: route.to_string(),
+            kind: kind.to_string(),
+        });
+    }
+
+    fn snapshot(&self, at: i64) -> RemoteSecurityLog {
+        RemoteSecurityLog {
+            events: self.events.iter().cloned().collect(),
+            lockouts: self
+                .clients
+                .iter()
+                .filter_map(|(ip, client)| {
+                    let until = client.locked_until.filter(|until| *until > at)?;
+                    Some(RemoteLockout {
+                        ip: ip.to_string(),
+                        until,
+                    })
+                })
+                .collect(),
+            unauthorized_total: self.unauthorized_total,
+            rate_limited_total: self.rate_limited_total,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct EndpointCounters {
+    requests: u64,
+    total_micros: u64,
+    max_micros: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct EndpointStats {
+    route: String,
+    requests: u64,
+    avg_latency_ms: f64,
+    max_latency_ms: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct RemoteHealth {
+    // "ok", or "degraded" when the timer worker is stuck, the database is read-only or
+    // its counts could not be read.
+    status: String,
+    version: String,
+    uptime_seconds: i64,
+    timer_worker_alive: bool,
+    last_tick_at: i64,
+    read_only: bool,
+    // Rows per HEALTH_COUNTED_TABLES entry; empty when the database could not be read.
+    rows: BTreeMap<String, i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct AppDiagnostics {
+    version: String,
+    remote_running: bool,
+    remote_port: Option<u16>,
+    // Since app start, busiest route first.
+    remote_endpoints: Vec<EndpointStats>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct TableStats {
+    table: String,
+    rows: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct DbStats {
+    size_bytes: i64,
+    journal_mode: String,
+    quick_check: String,
+    tables: Vec<TableStats>,
+    integrity: DbCheckReport,
+}
+
+// Integrity problems found by check_database; db_repair returns what it fixed in the same shape.
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct DbCheckReport {
+    // Tag links pointing at a missing session or tag.
+    orphaned_session_tags: i64,
+    // Negative, or longer than the session's own start-to-end window.
+    impossible_duration_session_ids: Vec<i64>,
+    // project_id set to a project that no longer exists.
+    dangling_project_session_ids: Vec<i64>,
+}
+
+impl DbCheckReport {
+    fn is_clean(&self) -> bool {
+        self.orphaned_session_tags == 0
+            && self.impossible_duration_session_ids.is_empty()
+            && self.dangling_project_session_ids.is_empty()
+    }
+}
+
+// Set while another process holds the database. The timer keeps running; its state,
+// finished sessions and timer events wait here until the database is writable again.
+#[derive(Debug, Clone)]
+struct ReadOnlyMode {
+    reason: String,
+    since: i64,
+    checked_at: i64,
+    timer_dirty: bool,
+    writes: Vec<PendingWrite>,
+}
+
+#[derive(Debug, Clone)]
+enum PendingWrite {
+    // Id 0 until written; `timer` is the state it was recorded from.
+    Session {
+        session: SessionRecord,
+        timer: TimerState,
+        event: TimerEventKind,
+    },
+    Event {
+        kind: TimerEventKind,
+        timer: TimerState,
+        at: i64,
+    },
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct ReadOnlyStatus {
+    read_only: bool,
+    reason: Option<String>,
+    since: Option<i64>,
+    pending_sessions: usize,
+    // Sessions written when the database became writable again.
+    flushed_sessions: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+enum DbRecoveryMode {
+    // sqlite3's `.recover` rebuilt the file.
+    Recover,
+    // Rows copied table by table from whatever was still readable.
+    Copy,
+    // Nothing could be read; the app started with an empty database.
+    Fresh,
+}
+
+// Set when the database failed to open at startup and was rebuilt.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct DbRecoveryReport {
+    error: String,
+    // The unreadable file, moved aside untouched.
+    backup_path: String,
+    mode: DbRecoveryMode,
+    tables: Vec<TableStats>,
+    failed_tables: Vec<String>,
+    at: i64,
+}
+
+struct AppState {
+    model: Mutex<AppModel>,
+    remote: Mutex<RemoteControlState>,
+    // Separate from `remote`: remote_stop joins the server thread while holding that lock,
+    // and the thread records metrics after every request.
+    remote_metrics: Mutex<BTreeMap<String, EndpointCounters>>,
+    // The tray is redrawn while the model is locked, so it keeps its own settings copy.
+    tray: Mutex<TrayTitleState>,
+    remote_events: Mutex<RemoteBroadcast>,
+    remote_guard: Mutex<RemoteGuard>,
+    // Tokens with a pairing dialog on screen; their other new devices wait their turn.
+    remote_prompts: Mutex<BTreeSet<i64>>,
+    // Open /ws sessions, capped at REMOTE_MAX_WEBSOCKETS.
+    remote_websockets: Arc<AtomicUsize>,
+    // Open /api/events streams, capped at REMOTE_MAX_EVENT_STREAMS.
+    remote_event_streams: Arc<AtomicUsize>,
+    started_at: i64,
+    // When the timer worker last went round its loop; a stale value means it is stuck.
+    timer_heartbeat: std::sync::atomic::AtomicI64,
+}
+
+#[derive(Debug, Default)]
+struct TrayTitleState {
+    language: String,
+    seconds: bool,
+    // Last title handed to the tray; redrawing an unchanged title is skipped.
+    shown: Option<String>,
+}
+
+type AppResult<T> = Result<T, String>;
+
+fn now_ts() -> i64 {
+    Utc::now().timestamp()
+}
+
+fn lock_model<'a>(state: &'a State<'_, AppState>) -> AppResult<MutexGuard<'a, AppModel>> {
+    state.model.lock().map_err(|e| e.to_string())
+}
+
+fn init_database(conn: &Connection) -> AppResult<()> {
+    conn.execute_batch(
+        r#"
+        PRAGMA foreign_keys = ON;
+
+        CREATE TABLE IF NOT EXISTS settings (
+            key TEXT PRIMARY KEY,
+            value TEXT NOT NULL
+        );
+
+        CREATE TABLE IF NOT EXISTS projects (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL,
+            color TEXT,
+            archived INTEGER NOT NULL DEFAULT 0,
+            created_at INTEGER NOT NULL
+        );
+
+        CREATE TABLE IF NOT EXISTS tags (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL,
+            created_at INTEGER NOT NULL
+        );
+
+        CREATE TABLE IF NOT EXISTS sessions (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            started_at INTEGER NOT NULL,
+            ended_at INTEGER NOT NULL,
+            phase TEXT NOT NULL,
+            duration_sec INTEGER NOT NULL,
+            completed INTEGER NOT NULL,
+            interruptions INTEGER NOT NULL DEFAULT 0,
+            project_id INTEGER,
+            FOREIGN KEY(project_id) REFERENCES projects(id) ON DELETE SET NULL
+        );
+
+        CREATE TABLE IF NOT EXISTS session_tags (
+            session_id INTEGER NOT NULL,
+            tag_id INTEGER NOT NULL,
+            PRIMARY KEY (session_id, tag_id),
+            FOREIGN KEY(session_id) REFERENCES sessions(id) ON DELETE CASCADE,
+            FOREIGN KEY(tag_id) REFERENCES tags(id) ON DELETE CASCADE
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_sessions_ended_at ON sessions(ended_at);
+        CREATE INDEX IF NOT EXISTS idx_sessions_project_id ON sessions(project_id);
+        CREATE TABLE IF NOT EXISTS session_project_splits (
+            session_id INTEGER NOT NULL,
+            project_id INTEGER NOT NULL,
+            percent INTEGER NOT NULL CHECK (percent BETWEEN 1 AND 100),
+            PRIMARY KEY (session_id, project_id),
+            FOREIGN KEY(session_id) REFERENCES sessions(id) ON DELETE CASCADE,
+            FOREIGN KEY(project_id) REFERENCES projects(id) ON DELETE CASCADE
+        );
+
+        CREATE TABLE IF NOT EXISTS events (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            at INTEGER NOT NULL,
+            kind TEXT NOT NULL,
+            phase TEXT NOT NULL,
+            cycle_index INTEGER NOT NULL,
+            remaining_seconds INTEGER NOT NULL,
+            session_id INTEGER
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_events_at ON events(at);
+
+        CREATE TABLE IF NOT EXISTS session_revisions (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            session_id INTEGER NOT NULL,
+            revised_at INTEGER NOT NULL,
+            source TEXT NOT NULL,
+            snapshot TEXT NOT NULL,
+            FOREIGN KEY(session_id) REFERENCES sessions(id) ON DELETE CASCADE
+        );
+
+        CREATE TABLE IF NOT EXISTS days_off (
+            day TEXT PRIMARY KEY,
+            name TEXT,
+            source TEXT NOT NULL DEFAULT 'manual'
+        );
+
+        -- Missed days a streak freeze token was spent on; they stay bridged from then on.
+        CREATE TABLE IF NOT EXISTS streak_freezes (
+            day TEXT PRIMARY KEY,
+            frozen_at INTEGER NOT NULL
+        );
+
+        CREATE TABLE IF NOT EXISTS experiments (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            focus_a_min INTEGER NOT NULL,
+            focus_b_min INTEGER NOT NULL,
+            started_at INTEGER NOT NULL,
+            ends_at INTEGER NOT NULL,
+            stopped_at INTEGER
+        );
+
+        CREATE TABLE IF NOT EXISTS plan_items (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            title TEXT NOT NULL,
+            project_id INTEGER,
+            tag_ids TEXT NOT NULL DEFAULT '[]',
+            created_at INTEGER NOT NULL,
+            started_at INTEGER
+        );
+
+        CREATE TABLE IF NOT EXISTS music_tracks (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            path TEXT NOT NULL UNIQUE,
+            title TEXT NOT NULL,
+            volume REAL NOT NULL DEFAULT 1.0,
+            created_at INTEGER NOT NULL
+        );
+
+        CREATE TABLE IF NOT EXISTS remote_tokens (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL,
+            token_hash TEXT NOT NULL UNIQUE,
+            scopes TEXT NOT NULL,
+            created_at INTEGER NOT NULL,
+            last_used_at INTEGER
+        );
+
+        CREATE TABLE IF NOT EXISTS push_subscriptions (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            endpoint TEXT NOT NULL UNIQUE,
+            p256dh TEXT NOT NULL,
+            auth TEXT NOT NULL,
+            token_id INTEGER NOT NULL,
+            created_at INTEGER NOT NULL
+        );
+
+        CREATE TABLE IF NOT EXISTS remote_audit (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            at INTEGER NOT NULL,
+            ip TEXT NOT NULL,
+            route TEXT NOT NULL,
+            token_name TEXT NOT NULL,
+            last_at INTEGER,
+            hits INTEGER NOT NULL DEFAULT 1
+        );
+
+        CREATE TABLE IF NOT EXISTS remote_devices (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            fingerprint TEXT NOT NULL UNIQUE,
+            token_id INTEGER NOT NULL,
+            label TEXT NOT NULL,
+            ip TEXT NOT NULL,
+            status TEXT NOT NULL,
+            created_at INTEGER NOT NULL,
+            decided_at INTEGER,
+            last_seen_at INTEGER
+        );
+
+        CREATE TABLE IF NOT EXISTS mutation_log (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            command TEXT NOT NULL,
+            domains TEXT NOT NULL,
+            at INTEGER NOT NULL
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_session_revisions_session_id ON session_revisions(session_id);
+        CREATE INDEX IF NOT EXISTS idx_session_tags_tag_id ON session_tags(tag_id);
+        CREATE INDEX IF NOT EXISTS idx_session_project_splits_project_id ON session_project_splits(project_id);
+        "#,
+    )
+    .map_err(|e| e.to_string())?;
+
+    ensure_column(conn, "sessions", "notes", "TEXT")?;
+    // Configured phase length when the session ran; NULL for sessions logged before it existed.
+    ensure_column(conn, "sessions", "planned_sec", "INTEGER")?;
+    ensure_column(conn, "sessions", "planned", "INTEGER")?;
+    for table in ["sessions", "projects", "tags"] {
+        ensure_column(conn, table, "deleted_at", "INTEGER")?;
+    }
+    // Names are unique among live rows only, so a trashed project or tag does not block
+    // creating another one with its name.
+    for table in ["projects", "tags"] {
+        drop_unique_name_column(conn, table)?;
+        conn.execute(
+            &format!(
+                "CREATE UNIQUE INDEX IF NOT EXISTS idx_{table}_live_name ON {table}(name)
+                 WHERE deleted_at IS NULL"
+            ),
+            [],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    ensure_column(conn, "projects", "billable", "INTEGER NOT NULL DEFAULT 0")?;
+    ensure_column(conn, "projects", "hourly_rate", "REAL")?;
+    ensure_column(conn, "projects", "pinned", "INTEGER NOT NULL DEFAULT 0")?;
+    ensure_column(conn, "projects", "sort_order", "INTEGER NOT NULL DEFAULT 0")?;
+    ensure_column(conn, "projects", "notify_override", "INTEGER")?;
+    ensure_column(conn, "projects", "sound_override", "TEXT")?;
+    ensure_column(conn, "projects", "estimated_pomodoros", "INTEGER")?;
+    ensure_column(
+        conn,
+        "sessions",
+        "quality",
+        "INTEGER CHECK (quality BETWEEN 1 AND 5)",
+    )?;
+    ensure_column(
+        conn,
+        "sessions",
+        "extended_sec",
+        "INTEGER NOT NULL DEFAULT 0",
+    )?;
+    ensure_column(conn, "remote_audit", "last_at", "INTEGER")?;
+    ensure_column(conn, "remote_audit", "hits", "INTEGER NOT NULL DEFAULT 1")?;
+
+    let fts_exists: bool = conn
+        .query_row(
+            "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE name = 'session_notes_fts')",
+            [],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+
+    // External-content FTS index over sessions.notes, kept in sync by triggers.
+    conn.execute_batch(
+        r#"
+        CREATE VIRTUAL TABLE IF NOT EXISTS session_notes_fts
+            USING fts5(notes, content='sessions', content_rowid='id');
+
+        CREATE TRIGGER IF NOT EXISTS sessions_notes_ai AFTER INSERT ON sessions BEGIN
+            INSERT INTO session_notes_fts(rowid, notes) VALUES (new.id, new.notes);
+        END;
+        CREATE TRIGGER IF NOT EXISTS sessions_notes_ad AFTER DELETE ON sessions BEGIN
+            INSERT INTO session_notes_fts(session_notes_fts, rowid, notes)
+                VALUES ('delete', old.id, old.notes);
+        END;
+        CREATE TRIGGER IF NOT EXISTS sessions_notes_au AFTER UPDATE OF notes ON sessions BEGIN
+            INSERT INTO session_notes_fts(session_notes_fts, rowid, notes)
+                VALUES ('delete', old.id, old.notes);
+            INSERT INTO session_notes_fts(rowid, notes) VALUES (new.id, new.notes);
+        END;
+        "#,
+    )
+    .map_err(|e| e.to_string())?;
+
+    if !fts_exists {
+        conn.execute(
+            "INSERT INTO session_notes_fts(session_notes_fts) VALUES ('rebuild')",
+            [],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+
+    init_data_revisions(conn)
+}
+
+// Which data_revisions domain a write to each table touches. Settings rows split by key so
+// the once-a-second timer save does not look like a settings edit.
+const DATA_REVISION_TABLES: [(&str, &str); 6] = [
+    ("sessions", "'sessions'"),
+    ("session_tags", "'sessions'"),
+    ("session_project_splits", "'sessions'"),
+    ("projects", "'projects'"),
+    ("tags", "'tags'"),
+    (
+        "settings",
+        "CASE {row}.key WHEN 'timer_state' THEN 'timer' ELSE 'settings' END",
+    ),
+];
+
+// Per-domain write counters bumped by triggers, so writes from any process (CLI, sync,
+// sqlite3 shell) are visible to the data watcher.
+fn init_data_revisions(conn: &Connection) -> AppResult<()> {
+    let mut sql = String::from(
+        "CREATE TABLE IF NOT EXISTS data_revisions (
+            domain TEXT PRIMARY KEY,
+            revision INTEGER NOT NULL
+        );",
+    );
+    for (table, domain) in DATA_REVISION_TABLES {
+        for (event, row) in [("INSERT", "new"), ("UPDATE", "new"), ("DELETE", "old")] {
+            let domain = domain.replace("{row}", row);
+            sql.push_str(&format!(
+                "CREATE TRIGGER IF NOT EXISTS data_rev_{table}_{event} AFTER {event} ON {table} BEGIN
+                    INSERT INTO data_revisions (domain, revision) VALUES ({domain}, 1)
+                        ON CONFLICT(domain) DO UPDATE SET revision = revision + 1;
+                END;"
+            ));
+        }
+    }
+    conn.execute_batch(&sql).map_err(|e| e.to_string())
+}
+
+// Databases created before soft delete declare `name TEXT NOT NULL UNIQUE`, which SQLite
+// can only drop by rebuilding the table. Foreign keys are off while the old table is dropped,
+// so sessions and tags keep pointing at the same ids. Only runs on a fresh connection at open.
+fn drop_unique_name_column(conn: &Connection, table: &str) -> AppResult<()> {
+    let sql: String = conn
+        .query_row(
+            "SELECT sql FROM sqlite_master WHERE type = 'table' AND name = ?1",
+            params![table],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+    if !sql.contains("name TEXT NOT NULL UNIQUE") {
+        return Ok(());
+    }
+    let columns = sql
+        .find('(')
+        .map(|start| sql[start..].replacen("name TEXT NOT NULL UNIQUE", "name TEXT NOT NULL", 1))
+        .ok_or_else(|| format!("unexpected schema for {table}"))?;
+    conn.execute_batch("PRAGMA foreign_keys = OFF")
+        .map_err(|e| e.to_string())?;
+    let rebuilt = conn.execute_batch(&format!(
+        "BEGIN;
+         CREATE TABLE {table}_rebuild {columns};
+         INSERT INTO {table}_rebuild SELECT * FROM {table};
+         DROP TABLE {table};
+         ALTER TABLE {table}_rebuild RENAME TO {table};
+         COMMIT;"
+    ));
+    if rebuilt.is_err() {
+        let _ = conn.execute_batch("ROLLBACK");
+    }
+    conn.execute_batch("PRAGMA foreign_keys = ON")
+        .map_err(|e| e.to_string())?;
+    rebuilt.map_err(|e| e.to_string())
+}
+
+// Adds a column to databases created before it existed; CREATE TABLE IF NOT EXISTS
+// never alters an existing table.
+fn ensure_column(conn: &Connection, table: &str, column: &str, definition: &str) -> AppResult<()> {
+    let mut stmt = conn
+        .prepare(&format!("PRAGMA table_info({table})"))
+        .map_err(|e| e.to_string())?;
+    let exists = stmt
+        .query_map([], |row| row.get::<_, String>(1))
+        .map_err(|e| e.to_string())?
+        .filter_map(Result::ok)
+        .any(|name| name == column);
+    if !exists {
+        conn.execute(
+            &format!("ALTER TABLE {table} ADD COLUMN {column} {definition}"),
+            [],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+fn save_json_setting<T: Serialize>(conn: &Connection, key: &str, value: &T) -> AppResult<()> {
+    let json = serde_json::to_string(value).map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT INTO settings (key, value) VALUES (?1, ?2)
+         ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+        params![key, json],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+fn load_json_setting<T: for<'de> Deserialize<'de>>(
+    conn: &Connection,
+    key: &str,
+) -> AppResult<Option<T>> {
+    let value: Option<String> = conn
+        .query_row(
+            "SELECT value FROM settings WHERE key = ?1",
+            params![key],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+
+    match value {
+        Some(raw) => {
+            let parsed = serde_json::from_str::<T>(&raw).map_err(|e| e.to_string())?;
+            Ok(Some(parsed))
+        }
+        None => Ok(None),
+    }
+}
+
+fn normalize_settings(mut settings: AppSettings) -> AppSettings {
+    settings.focus_min = settings.focus_min.clamp(1, 180);
+    settings.short_break_min = settings.short_break_min.clamp(1, 60);
+    settings.long_break_min = settings.long_break_min.clamp(1, 90);
+    settings.long_break_every = settings.long_break_every.clamp(2, 10);
+    settings.theme = match settings.theme.as_str() {
+        "dark" => "dark".to_string(),
+        _ => "light".to_string(),
+    };
+    settings.remote_control_port = settings.remote_control_port.clamp(1024, 65535);
+    settings.cycle_reset_idle_min = settings.cycle_reset_idle_min.clamp(0, 1440);
+    settings.max_pauses_per_focus = settings.max_pauses_per_focus.clamp(0, 20);
+    settings.pause_budget_action = match settings.pause_budget_action.as_str() {
+        "mark_incomplete" => "mark_incomplete".to_string(),
+        _ => "reject".to_string(),
+    };
+    settings.dnd_mode = match settings.dnd_mode.as_str() {
+        "suppress" => "suppress".to_string(),
+        "queue" => "queue".to_string(),
+        _ => "bypass".to_string(),
+    };
+    if settings.timezone.parse::<chrono_tz::Tz>().is_err() {
+        settings.timezone = String::new();
+    }
+    settings.auto_archive_days = settings.auto_archive_days.clamp(0, 3650);
+    settings.remote_detach_action = match settings.remote_detach_action.as_str() {
+        "interruption" if REMOTE_DETACH_SUPPORTED => "interruption".to_string(),
+        "pause" if REMOTE_DETACH_SUPPORTED => "pause".to_string(),
+        _ => "ignore".to_string(),
+    };
+    if !SUPPORTED_LANGUAGES.contains(&settings.language.as_str()) {
+        settings.language = "en".to_string();
+    }
+    settings.work_day_start_min = settings.work_day_start_min.clamp(0, 1439);
+    settings.work_day_end_min = settings
+        .work_day_end_min
+        .clamp(settings.work_day_start_min + 1, 1440);
+    settings.gap_min_minutes = settings.gap_min_minutes.clamp(1, 480);
+    settings.streak_min_pomodoros = settings.streak_min_pomodoros.clamp(0, 50);
+    settings.streak_freeze_tokens = settings.streak_freeze_tokens.clamp(0, 30);
+    settings.session_webhook_url = settings.session_webhook_url.trim().to_string();
+    if !(settings.session_webhook_url.starts_with("http://")
+        || settings.session_webhook_url.starts_with("https://"))
+    {
+        settings.session_webhook_url = String::new();
+    }
+    settings.breathing_pattern = match settings.breathing_pattern.as_str() {
+        "box" | "relax" | "coherent" | "custom" => settings.breathing_pattern.clone(),
+        _ => "off".to_string(),
+    };
+    if settings.breathing_custom.len() != 4 {
+        settings.breathing_custom = vec![4, 4, 4, 4];
+    }
+    for (i, seconds) in settings.breathing_custom.iter_mut().enumerate() {
+        // Inhale and exhale need at least a second; holds may be skipped.
+        *seconds = (*seconds).clamp(if i % 2 == 0 { 1 } else { 0 }, 20);
+    }
+    settings.break_dim_percent = settings.break_dim_percent.clamp(5, 100);
+    settings.analytics_excluded_project_ids.sort_unstable();
+    settings.analytics_excluded_project_ids.dedup();
+    settings.analytics_excluded_tag_ids.sort_unstable();
+    settings.analytics_excluded_tag_ids.dedup();
+    settings.daily_goal_pomodoros = settings.daily_goal_pomodoros.clamp(0, 50);
+    settings.weekly_goal_minutes = settings.weekly_goal_minutes.clamp(0, 10_080);
+    settings.backup_interval_hours = settings.backup_interval_hours.clamp(1, 24 * 30);
+    settings.backup_keep = settings.backup_keep.clamp(1, 365);
+    settings.backup_format = match settings.backup_format.as_str() {
+        "sqlite" => "sqlite".to_string(),
+        _ => "json".to_string(),
+    };
+    settings
+}
+
+fn generate_remote_token() -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(32)
+        .map(char::from)
+        .collect()
+}
+
+fn ensure_team_stats_key(settings: &mut AppSettings) {
+    if settings.team_stats_key.trim().is_empty() {
+        settings.team_stats_key = generate_remote_token();
+    }
+}
+
+// Scopes are stored as a comma-separated list, e.g. "timer,read".
+fn parse_remote_scopes(text: &str) -> Vec<RemoteScope> {
+    RemoteScope::ALL
+        .into_iter()
+        .filter(|scope| text.split(',').any(|part| part == scope.as_str()))
+        .collect()
+}
+
+fn format_remote_scopes(scopes: &[RemoteScope]) -> String {
+    RemoteScope::ALL
+        .into_iter()
+        .filter(|scope| scopes.contains(scope))
+        .map(RemoteScope::as_str)
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn fetch_remote_tokens(conn: &Connection) -> AppResult<Vec<RemoteToken>> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, name, scopes, created_at, last_used_at
+             FROM remote_tokens ORDER BY id",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok(RemoteToken {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                token: None,
+                scopes: parse_remote_scopes(&row.get::<_, String>(2)?),
+                created_at: row.get(3)?,
+                last_used_at: row.get(4)?,
+            })
+        })
+        .map_err(|e| e.to_string())?;
+    rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+}
+
+// Hex SHA-256. Tokens are 32 random characters, so a fast unsalted hash is enough to keep a
+// copied database from handing out working tokens.
+fn hash_remote_token(token: &str) -> String {
+    Sha256::digest(token.as_bytes())
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+fn create_remote_token(
+    conn: &Connection,
+    name: &str,
+    token: String,
+    scopes: &[RemoteScope],
+    at: i64,
+) -> AppResult<RemoteToken> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("token name must not be empty".to_string());
+    }
+    if scopes.is_empty() {
+        return Err("a token needs at least one scope".to_string());
+    }
+    conn.execute(
+        "INSERT INTO remote_tokens (name, token_hash, scopes, created_at)
+         VALUES (?1, ?2, ?3, ?4)",
+        params![
+            name,
+            hash_remote_token(&token),
+            format_remote_scopes(scopes),
+            at
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(RemoteToken {
+        id: conn.last_insert_rowid(),
+        name: name.to_string(),
+        token: Some(token),
+        scopes: parse_remote_scopes(&format_remote_scopes(scopes)),
+        created_at: at,
+        last_used_at: None,
+    })
+}
+
+// The id and scopes of a known token. Lookups compare the hash of the whole token, so an empty
+// or partial value never matches.
+fn lookup_remote_token(
+    conn: &Connection,
+    token: &str,
+) -> AppResult<Option<(i64, Vec<RemoteScope>)>> {
+    if token.is_empty() {
+        return Ok(None);
+    }
+    conn.query_row(
+        "SELECT id, scopes FROM remote_tokens WHERE token_hash = ?1",
+        params![hash_remote_token(token)],
+        |row| Ok((row.get(0)?, parse_remote_scopes(&row.get::<_, String>(1)?))),
+    )
+    .optional()
+    .map_err(|e| e.to_string())
+}
+
+// Recorded at most once a minute, so a polling remote page does not write every second.
+fn touch_remote_token(conn: &Connection, id: i64, at: i64) -> AppResult<()> {
+    conn.execute(
+        "UPDATE remote_tokens SET last_used_at = ?1
+         WHERE id = ?2 AND (last_used_at IS NULL OR last_used_at <= ?1 - 60)",
+        params![at, id],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+// Oldest entries beyond this are dropped as new ones arrive.
+const REMOTE_AUDIT_MAX: i64 = 2000;
+// A page polling /api/state would otherwise fill the log within the hour.
+const REMOTE_AUDIT_COLLAPSE_SECONDS: i64 = 60;
+// Stands in for a token name on /api/team/daily, which the team key opens instead.
+const REMOTE_AUDIT_TEAM_KEY: &str = "team key";
+
+fn remote_token_name(conn: &Connection, id: i64) -> AppResult<String> {
+    conn.query_row(
+        "SELECT name FROM remote_tokens WHERE id = ?1",
+        params![id],
+        |row| row.get(0),
+    )
+    .map_err(|e| e.to_string())
+}
+
+// The same token calling the same route from the same address within
+// REMOTE_AUDIT_COLLAPSE_SECONDS of its last call bumps that entry instead of adding one.
+fn record_remote_audit(
+    conn: &Connection,
+    at: i64,
+    ip: &str,
+    route: &str,
+    token_name: &str,
+) -> AppResult<()> {
+    let collapsed = conn
+        .execute(
+            "UPDATE remote_audit SET last_at = ?1, hits = hits + 1 WHERE id = (
+                 SELECT id FROM remote_audit
+                 WHERE ip = ?2 AND route = ?3 AND token_name = ?4 AND COALESCE(last_at, at) >= ?1 - ?5
+                 ORDER BY id DESC LIMIT 1)",
+            params![at, ip, route, token_name, REMOTE_AUDIT_COLLAPSE_SECONDS],
+        )
+        .map_err(|e| e.to_string())?;
+    if collapsed > 0 {
+        return Ok(());
+    }
+    conn.execute(
+        "INSERT INTO remote_audit (at, ip, route, token_name, last_at) VALUES (?1, ?2, ?3, ?4, ?1)",
+        params![at, ip, route, token_name],
+    )
+    .map_err(|e| e.to_string())?;
+    conn.execute(
+        "DELETE FROM remote_audit WHERE id <= ?1 - ?2",
+        params![conn.last_insert_rowid(), REMOTE_AUDIT_MAX],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+// Most recently hit first.
+fn fetch_remote_audit(conn: &Connection, limit: i64) -> AppResult<Vec<RemoteAuditEntry>> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, at, COALESCE(last_at, at), hits, ip, route, token_name FROM remote_audit
+             ORDER BY COALESCE(last_at, at) DESC, id DESC LIMIT ?1",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(params![limit], |row| {
+            Ok(RemoteAuditEntry {
+                id: row.get(0)?,
+                at: row.get(1)?,
+                last_at: row.get(2)?,
+                hits: row.get(3)?,
+                ip: row.get(4)?,
+                route: row.get(5)?,
+                token_name: row.get(6)?,
+            })
+        })
+        .map_err(|e| e.to_string())?;
+    rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+}
+
+// The remote page sends a random id it keeps in localStorage; scripts that send none are
+// told apart by their User-Agent only, so every script with the same token and User-Agent
+// shares one approval. Either way the token is part of it, so a new token on the same phone
+// pairs again.
+fn remote_device_fingerprint(token_id: i64, device_id: Option<&str>, user_agent: &str) -> String {
+    let source = match device_id.filter(|id| !id.trim().is_empty()) {
+        Some(id) => format!("{token_id}\ndevice:{}", id.trim()),
+        None => format!("{token_id}\nagent:{user_agent}"),
+    };
+    sha1(source.as_bytes())
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+// A short name for the approval prompt, e.g. "iPhone (Safari)".
+fn remote_device_label(user_agent: &str) -> String {
+    const DEVICES: [(&str, &str); 7] = [
+        ("iPhone", "iPhone"),
+        ("iPad", "iPad"),
+        ("Android", "Android"),
+        ("Macintosh", "Mac"),
+        ("Windows", "Windows PC"),
+        ("Linux", "Linux"),
+        ("CrOS", "Chromebook"),
+    ];
+    const CLIENTS: [(&str, &str); 5] = [
+        ("curl/", "curl"),
+        ("Firefox/", "Firefox"),
+        ("Edg/", "Edge"),
+        ("Chrome/", "Chrome"),
+        ("Safari/", "Safari"),
+    ];
+    let device = DEVICES
+        .iter()
+        .find(|(needle, _)| user_agent.contains(needle))
+        .map(|(_, name)| *name);
+    let client = CLIENTS
+        .iter()
+        .find(|(needle, _)| user_agent.contains(needle))
+        .map(|(_, name)| *name);
+    match (device, client) {
+        (Some(device), Some(client)) => format!("{device} ({client})"),
+        (Some(name), None) | (None, Some(name)) => name.to_string(),
+        (None, None) if user_agent.trim().is_empty() => "Unknown device".to_string(),
+        (None, None) => user_agent.chars().take(40).collect(),
+    }
+}
+
+fn fetch_remote_devices(conn: &Connection) -> AppResult<Vec<RemoteDevice>> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT d.id, d.token_id, t.name, d.label, d.ip, d.status, d.created_at, d.decided_at,
+                    d.last_seen_at
+             FROM remote_devices d LEFT JOIN remote_tokens t ON t.id = d.token_id
+             ORDER BY d.id",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((
+                RemoteDevice {
+                    id: row.get(0)?,
+                    token_id: row.get(1)?,
+                    token_name: row.get(2)?,
+                    label: row.get(3)?,
+                    ip: row.get(4)?,
+                    status: RemoteDeviceStatus::Pending,
+                    created_at: row.get(6)?,
+                    decided_at: row.get(7)?,
+                    last_seen_at: row.get(8)?,
+                },
+                row.get::<_, String>(5)?,
+            ))
+        })
+        .map_err(|e| e.to_string())?;
+    rows.map(|row| {
+        let (mut device, status) = row.map_err(|e| e.to_string())?;
+        device.status = RemoteDeviceStatus::from_db_value(&status)?;
+        Ok(device)
+    })
+    .collect()
+}
+
+// Anyone holding a token can make up device ids, so the pending ones are bounded per token.
+const MAX_PENDING_DEVICES_PER_TOKEN: i64 = 3;
+
+// Looks the device up, recording it as pending the first time it shows up. Returns its
+// status and, for a device seen for the first time, the new row so the desktop can ask.
+// Past the pending limit a new device is told to wait without being recorded.
+fn check_remote_device(
+    conn: &Connection,
+    token_id: i64,
+    fingerprint: &str,
+    label: &str,
+    ip: &str,
+    at: i64,
+) -> AppResult<(RemoteDeviceStatus, Option<RemoteDevice>)> {
+    let known = conn
+        .query_row(
+            "SELECT id, status FROM remote_devices WHERE fingerprint = ?1",
+            params![fingerprint],
+            |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+    if let Some((id, status)) = known {
+        let status = RemoteDeviceStatus::from_db_value(&status)?;
+        if status == RemoteDeviceStatus::Approved {
+            // Like touch_remote_token, at most once a minute.
+            conn.execute(
+                "UPDATE remote_devices SET last_seen_at = ?1, ip = ?2
+                 WHERE id = ?3 AND (last_seen_at IS NULL OR last_seen_at <= ?1 - 60)",
+                params![at, ip, id],
+            )
+            .map_err(|e| e.to_string())?;
+        }
+        return Ok((status, None));
+    }
+    let pending: i64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM remote_devices WHERE token_id = ?1 AND status = ?2",
+            params![token_id, RemoteDeviceStatus::Pending.as_str()],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+    if pending >= MAX_PENDING_DEVICES_PER_TOKEN {
+        return Ok((RemoteDeviceStatus::Pending, None));
+    }
+    conn.execute(
+        "INSERT INTO remote_devices (fingerprint, token_id, label, ip, status, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            fingerprint,
+            token_id,
+            label,
+            ip,
+            RemoteDeviceStatus::Pending.as_str(),
+            at
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+    let id = conn.last_insert_rowid();
+    let device = fetch_remote_devices(conn)?
+        .into_iter()
+        .find(|d| d.id == id)
+        .ok_or_else(|| "device not found".to_string())?;
+    Ok((RemoteDeviceStatus::Pending, Some(device)))
+}
+
+// The oldest device of the token still waiting for an answer.
+fn next_pending_device(conn: &Connection, token_id: i64) -> AppResult<Option<RemoteDevice>> {
+    Ok(fetch_remote_devices(conn)?
+        .into_iter()
+        .find(|d| d.token_id == token_id && d.status == RemoteDeviceStatus::Pending))
+}
+
+fn decide_remote_device(conn: &Connection, id: i64, approved: bool, at: i64) -> AppResult<bool> {
+    let status = if approved {
+        RemoteDeviceStatus::Approved
+    } else {
+        RemoteDeviceStatus::Rejected
+    };
+    let changed = conn
+        .execute(
+            "UPDATE remote_devices SET status = ?1, decided_at = ?2 WHERE id = ?3",
+            params![status.as_str(), at, id],
+        )
+        .map_err(|e| e.to_string())?;
+    Ok(changed > 0)
+}
+
+// Before remote_tokens, settings held one token and its scopes. On the first start after
+// upgrading that becomes a token named "Default" so existing remote URLs keep working; settings
+// are saved without the old fields right after, so this runs once. New installs start with no
+// token, since a stored hash could never be shown to the user.
+fn migrate_remote_token(
+    conn: &Connection,
+    stored: Option<&serde_json::Value>,
+    at: i64,
+) -> AppResult<()> {
+    let Some(token) = stored
+        .and_then(|settings| settings.get("remoteControlToken"))
+        .and_then(|token| token.as_str())
+        .map(str::trim)
+        .filter(|token| !token.is_empty())
+    else {
+        return Ok(());
+    };
+    let existing: i64 = conn
+        .query_row("SELECT COUNT(*) FROM remote_tokens", [], |row| row.get(0))
+        .map_err(|e| e.to_string())?;
+    if existing > 0 {
+        return Ok(());
+    }
+    let scopes = stored
+        .and_then(|settings| settings.get("remoteTokenScopes"))
+        .and_then(|scopes| serde_json::from_value::<Vec<RemoteScope>>(scopes.clone()).ok())
+        .filter(|scopes| !scopes.is_empty())
+        .unwrap_or_else(|| RemoteScope::ALL.to_vec());
+    create_remote_token(conn, "Default", token.to_string(), &scopes, at)?;
+    Ok(())
+}
+
+fn load_or_create_settings(conn: &Connection) -> AppResult<AppSettings> {
+    let stored = load_json_setting::<serde_json::Value>(conn, APP_SETTINGS_KEY)?;
+    migrate_remote_token(conn, stored.as_ref(), now_ts())?;
+    let settings = stored
+        .map(serde_json::from_value::<AppSettings>)
+        .transpose()
+        .map_err(|e| e.to_string())?
+        .unwrap_or_default();
+    let mut settings = normalize_settings(settings);
+    ensure_team_stats_key(&mut settings);
+    save_json_setting(conn, APP_SETTINGS_KEY, &settings)?;
+    Ok(settings)
+}
+
+fn normalize_timer_state(mut timer: TimerState, settings: &AppSettings) -> TimerState {
+    if timer.phase == TimerPhase::Focus {
+        timer.break_extension_seconds = 0;
+    }
+    timer.break_extension_seconds = timer.break_extension_seconds.max(0);
+    timer.phase_total_seconds =
+        settings.duration_for_phase_seconds(&timer.phase) + timer.break_extension_seconds;
+    if timer.remaining_seconds <= 0 || timer.remaining_seconds > timer.phase_total_seconds {
+        timer.remaining_seconds = timer.phase_total_seconds;
+        timer.is_running = false;
+        timer.target_ends_at = None;
+    }
+    if timer.cycle_index < 0 {
+        timer.cycle_index = 0;
+    }
+    timer.interruptions = timer.interruptions.max(0);
+    sync_pause_budget(&mut timer, settings);
+    timer
+}
+
+fn sync_pause_budget(timer: &mut TimerState, settings: &AppSettings) {
+    timer.pauses_remaining =
+        if timer.phase == TimerPhase::Focus && settings.max_pauses_per_focus > 0 {
+            Some((settings.max_pauses_per_focus - timer.interruptions).max(0))
+        } else {
+            None
+        };
+}
+
+// Shared pause transition for the window, tray, and remote. Focus pauses count against
+// `max_pauses_per_focus`; once exhausted they are either rejected or forfeit completion.
+fn pause_timer(timer: &mut TimerState, settings: &AppSettings, now: i64) -> AppResult<()> {
+    if timer.is_running {
+        if timer.phase == TimerPhase::Focus {
+            let budget = settings.max_pauses_per_focus;
+            if budget > 0 && timer.interruptions >= budget {
+                if settings.pause_budget_action == "reject" {
+                    return Err(format!(
+                        "pause budget exhausted ({budget} per focus session)"
+                    ));
+                }
+                timer.pause_budget_exceeded = true;
+            }
+            timer.interruptions += 1;
+        }
+        timer.last_active_at = Some(now);
+    }
+    timer.is_running = false;
+    timer.target_ends_at = None;
+    sync_pause_budget(timer, settings);
+    Ok(())
+}
+
+// Restart the long-break cadence when a new local day begins or after a long idle gap.
+// Only applies between sessions so an in-progress phase keeps its position in the cycle.
+fn apply_cycle_reset(timer: &mut TimerState, settings: &AppSettings, now: i64) {
+    if timer.started_at.is_some() {
+        return;
+    }
+
+    let today = day_key(now);
+    let new_day = settings.cycle_reset_at_midnight
+        && timer
+            .cycle_anchor_date
+            .as_deref()
+            .is_some_and(|anchor| anchor != today);
+    let idle_expired = settings.cycle_reset_idle_min > 0
+        && timer
+            .last_active_at
+            .is_some_and(|last| now - last >= settings.cycle_reset_idle_min * 60);
+
+    if new_day || idle_expired {
+        timer.cycle_index = 0;
+        timer.cycle_anchor_date = Some(today);
+    } else if timer.cycle_anchor_date.is_none() {
+        timer.cycle_anchor_date = Some(today);
+    }
+}
+
+fn load_or_create_timer(conn: &Connection, settings: &AppSettings) -> AppResult<TimerState> {
+    let timer = load_json_setting::<TimerState>(conn, TIMER_STATE_KEY)?
+        .unwrap_or_else(|| TimerState::default_with_settings(settings));
+    let mut timer = normalize_timer_state(timer, settings);
+    apply_cycle_reset(&mut timer, settings, now_ts());
+    save_json_setting(conn, TIMER_STATE_KEY, &timer)?;
+    Ok(timer)
+}
+
+fn save_timer_state(conn: &Connection, timer: &TimerState) -> AppResult<()> {
+    save_json_setting(conn, TIMER_STATE_KEY, timer)
+}
+
+fn refresh_remaining(timer: &mut TimerState) {
+    if timer.is_running {
+        if let Some(target_ends_at) = timer.target_ends_at {
+            timer.remaining_seconds = (target_ends_at - now_ts()).max(0);
+        }
+    }
+}
+
+fn format_seconds(seconds: i64) -> String {
+    let minutes = seconds / 60;
+    let secs = seconds % 60;
+    format!("{minutes:02}:{secs:02}")
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Message {
+    PhaseComplete,
+    PomodorosToday,
+    Running,
+    Paused,
+    Focus,
+    ShortBreak,
+    LongBreak,
+    DailyGoalReached,
+    WeeklyGoalReached,
+    DatabaseReadOnly,
+}
+
+// Templates use a small ICU MessageFormat subset: `{name}` substitutes an argument and
+// `{name, plural, =0 {...} one {...} few {...} many {...} other {...}}` picks a branch,
+// with `#` standing for the number.
+fn message_template(language: &str, message: Message) -> &'static str {
+    match (language, message) {
+        ("ru", Message::PhaseComplete) => "{phase}: готово. Далее: {next}",
+        ("ru", Message::PomodorosToday) => {
+            "Сегодня {count, plural, one {# помидор} few {# помидора} many {# помидоров} other {# помидора}}"
+        }
+        ("ru", Message::Running) => "идёт",
+        ("ru", Message::Paused) => "пауза",
+        ("ru", Message::Focus) => "Фокус",
+        ("ru", Message::ShortBreak) => "Короткий перерыв",
+        ("ru", Message::LongBreak) => "Длинный перерыв",
+        ("ru", Message::DailyGoalReached) => {
+            "Дневная цель достигнута: {count, plural, one {# помидор} few {# помидора} many {# помидоров} other {# помидора}}"
+        }
+        ("ru", Message::WeeklyGoalReached) => "Недельная цель достигнута: {minutes} мин",
+        ("ru", Message::DatabaseReadOnly) => {
+            "База данных занята другим процессом. Таймер работает, сессии сохранятся позже"
+        }
+        ("uk", Message::PhaseComplete) => "{phase}: завершено. Далі: {next}",
+        ("uk", Message::PomodorosToday) => {
+            "Сьогодні {count, plural, one {# помідор} few {# помідори} many {# помідорів} other {# помідора}}"
+        }
+        ("uk", Message::Running) => "триває",
+        ("uk", Message::Paused) => "пауза",
+        ("uk", Message::Focus) => "Фокус",
+        ("uk", Message::ShortBreak) => "Коротка перерва",
+        ("uk", Message::LongBreak) => "Довга перерва",
+        ("uk", Message::DailyGoalReached) => {
+            "Денну ціль досягнуто: {count, plural, one {# помідор} few {# помідори} many {# помідорів} other {# помідора}}"
+        }
+        ("uk", Message::WeeklyGoalReached) => "Тижневу ціль досягнуто: {minutes} хв",
+        ("uk", Message::DatabaseReadOnly) => {
+            "База даних зайнята іншим процесом. Таймер працює, сесії збережуться пізніше"
+        }
+        (_, Message::PhaseComplete) => "{phase} complete. Next: {next}",
+        (_, Message::PomodorosToday) => {
+            "{count, plural, one {# pomodoro} other {# pomodoros}} today"
+        }
+        (_, Message::Running) => "Running",
+        (_, Message::Paused) => "Paused",
+        (_, Message::Focus) => "Focus",
+        (_, Message::ShortBreak) => "Short break",
+        (_, Message::LongBreak) => "Long break",
+        (_, Message::DailyGoalReached) => {
+            "Daily goal reached: {count, plural, one {# pomodoro} other {# pomodoros}}"
+        }
+        (_, Message::WeeklyGoalReached) => "Weekly goal reached: {minutes} min",
+        (_, Message::DatabaseReadOnly) => {
+            "The database is in use by another process. The timer keeps running and sessions will be saved later"
+        }
+    }
+}
+
+fn localize(language: &str, message: Message, args: &[(&str, &str)]) -> String {
+    format_message(message_template(language, message), language, args)
+}
+
+fn phase_label(language: &str, phase: &TimerPhase) -> String {
+    let message = match phase {
+        TimerPhase::Focus => Message::Focus,
+        TimerPhase::ShortBreak => Message::ShortBreak,
+        TimerPhase::LongBreak => Message::LongBreak,
+    };
+    localize(language, message, &[])
+}
+
+// CLDR cardinal rules for integers. Russian and Ukrainian share theirs.
+fn plural_category(language: &str, n: i64) -> &'static str {
+    let n = n.unsigned_abs();
+    match language {
+        "ru" | "uk" => {
+            let (last, last_two) = (n % 10, n % 100);
+            if last == 1 && last_two != 11 {
+                "one"
+            } else if (2..=4).contains(&last) && !(12..=14).contains(&last_two) {
+                "few"
+            } else {
+                "many"
+            }
+        }
+        _ if n == 1 => "one",
+        _ => "other",
+    }
+}
+
+// Byte length of the `{...}` group `text` starts with, braces included.
+fn brace_group_len(text: &str) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in text.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i + 1);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+fn plural_branch<'a>(branches: &'a str, selector: &str) -> Option<&'a str> {
+    let mut rest = branches.trim_start();
+    while let Some(open) = rest.find('{') {
+        let len = brace_group_len(&rest[open..])?;
+        if rest[..open].trim() == selector {
+            return Some(&rest[open + 1..open + len - 1]);
+        }
+        rest = rest[open + len..].trim_start();
+    }
+    None
+}
+
+// Unknown arguments render empty and unbalanced braces are copied through, so a bad
+// template degrades instead of failing the notification.
+fn format_message(template: &str, language: &str, args: &[(&str, &str)]) -> String {
+    let mut out = String::new();
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        out.push_str(&rest[..open]);
+        rest = &rest[open..];
+        let Some(len) = brace_group_len(rest) else {
+            break;
+        };
+        let mut parts = rest[1..len - 1].splitn(3, ',').map(str::trim);
+        let name = parts.next().unwrap_or_default();
+        let value = args
+            .iter()
+            .find(|(key, _)| *key == name)
+            .map_or("", |(_, value)| *value);
+        match (parts.next(), parts.next()) {
+            (Some("plural"), Some(branches)) => {
+                let n: i64 = value.parse().unwrap_or(0);
+                let branch = plural_branch(branches, &format!("={n}"))
+                    .or_else(|| plural_branch(branches, plural_category(language, n)))
+                    .or_else(|| plural_branch(branches, "other"))
+                    .unwrap_or_default();
+                out.push_str(&format_message(&branch.replace('#', value), language, args));
+            }
+            _ => out.push_str(value),
+        }
+        rest = &rest[len..];
+    }
+    out.push_str(rest);
+    out
+}
+
+// Whole minutes, rounded up, so the menu bar redraws once a minute; the final minute (or
+// every tick with tray_title_seconds) counts down to the second.
+fn tray_countdown(remaining_seconds: i64, seconds: bool) -> String {
+    if seconds || remaining_seconds < 60 {
+        format_seconds(remaining_seconds)
+    } else {
+        format!("{}m", (remaining_seconds + 59) / 60)
+    }
+}
+
+fn update_tray_title(app: &AppHandle, timer: &TimerState) {
+    let Some(state) = app.try_state::<AppState>() else {
+        return;
+    };
+    let Ok(mut shown) = state.tray.lock() else {
+        return;
+    };
+    if let Some(tray) = app.tray_by_id(TRAY_ID) {
+        let language = shown.language.clone();
+        let status = if timer.is_running {
+            Message::Running
+        } else {
+            Message::Paused
+        };
+        let title = format!(
+            "{} {} {}",
+            phase_label(&language, &timer.phase),
+            tray_countdown(timer.remaining_seconds, shown.seconds),
+            localize(&language, status, &[])
+        );
+        if shown.shown.as_deref() != Some(title.as_str()) {
+            let _ = tray.set_title(Some(&title));
+            shown.shown = Some(title);
+        }
+    }
+}
+
+fn emit_timer_state(app: &AppHandle, timer: &TimerState) {
+    let _ = app.emit("timer://state", timer);
+    update_tray_title(app, timer);
+}
+
+fn record_session(
+    conn: &Connection,
+    timer: &TimerState,
+    completed: bool,
+    ended_at: i64,
+) -> AppResult<SessionRecord> {
+    let mut session = session_from_timer(timer, completed, ended_at);
+    session.id = insert_session(conn, timer, &session)?;
+    Ok(session)
+}
+
+// The session `timer` would record, with id 0.
+fn session_from_timer(timer: &TimerState, completed: bool, ended_at: i64) -> SessionRecord {
+    let elapsed = if completed {
+        timer.phase_total_seconds
+    } else {
+        (timer.phase_total_seconds - timer.remaining_seconds).clamp(0, timer.phase_total_seconds)
+    };
+
+    let started_at = timer
+        .started_at
+        .unwrap_or_else(|| ended_at - elapsed.max(1));
+
+    let project_id = match timer.phase {
+        TimerPhase::Focus => timer.current_project_id,
+        _ => None,
+    };
+
+    let completed = completed && !timer.pause_budget_exceeded;
+    // A break skipped early only used the part of its extension it actually ran into.
+    let configured = timer.phase_total_seconds - timer.break_extension_seconds;
+    let extended_sec = (elapsed - configured).clamp(0, timer.break_extension_seconds.max(0));
+    let planned = (timer.phase == TimerPhase::Focus).then_some(timer.current_planned);
+    let tag_ids = match timer.phase {
+        TimerPhase::Focus => timer.current_tag_ids.clone(),
+        _ => Vec::new(),
+    };
+
+    SessionRecord {
+        id: 0,
+        started_at,
+        ended_at,
+        phase: timer.phase.clone(),
+        duration_sec: elapsed,
+        completed,
+        interruptions: timer.interruptions,
+        project_id,
+        tag_ids,
+        notes: None,
+        extended_sec,
+        project_splits: Vec::new(),
+        quality: None,
+        planned,
+    }
+}
+
+fn insert_session(
+    conn: &Connection,
+    timer: &TimerState,
+    session: &SessionRecord,
+) -> AppResult<i64> {
+    conn.execute(
+        "INSERT INTO sessions (started_at, ended_at, phase, duration_sec, completed, interruptions, project_id, extended_sec, planned_sec, planned)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+        params![
+            session.started_at,
+            session.ended_at,
+            session.phase.as_db_value(),
+            session.duration_sec,
+            session.completed as i64,
+            session.interruptions,
+            session.project_id,
+            session.extended_sec,
+            timer.phase_total_seconds - timer.break_extension_seconds,
+            session.planned.map(|v| v as i64),
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+
+    let id = conn.last_insert_rowid();
+    for tag_id in &session.tag_ids {
+        conn.execute(
+            "INSERT OR IGNORE INTO session_tags (session_id, tag_id) VALUES (?1, ?2)",
+            params![id, tag_id],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    Ok(id)
+}
+
+fn record_timer_event(
+    conn: &Connection,
+    kind: TimerEventKind,
+    timer: &TimerState,
+    session_id: Option<i64>,
+    at: i64,
+) -> AppResult<()> {
+    conn.execute(
+        "INSERT INTO events (at, kind, phase, cycle_index, remaining_seconds, session_id)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            at,
+            kind.as_db_value(),
+            timer.phase.as_db_value(),
+            timer.cycle_index,
+            timer.remaining_seconds,
+            session_id,
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+fn log_timer_event(
+    model: &mut AppModel,
+    kind: TimerEventKind,
+    session_id: Option<i64>,
+    at: i64,
+) -> AppResult<()> {
+    match &mut model.read_only {
+        Some(mode) => {
+            mode.writes.push(PendingWrite::Event {
+                kind,
+                timer: model.timer.clone(),
+                at,
+            });
+            Ok(())
+        }
+        None => record_timer_event(&model.conn, kind, &model.timer, session_id, at),
+    }
+}
+
+fn persist_timer(model: &mut AppModel) -> AppResult<()> {
+    match &mut model.read_only {
+        Some(mode) => {
+            mode.timer_dirty = true;
+            Ok(())
+        }
+        None => save_timer_state(&model.conn, &model.timer),
+    }
+}
+
+// Started for a fresh phase, resumed when the phase was already under way.
+fn run_event_kind(timer: &TimerState) -> TimerEventKind {
+    if timer.started_at.is_some() {
+        TimerEventKind::Resumed
+    } else {
+        TimerEventKind::Started
+    }
+}
+
+fn fetch_timer_events(conn: &Connection, query: &TimerEventQuery) -> AppResult<Vec<TimerEvent>> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, at, kind, phase, cycle_index, remaining_seconds, session_id FROM events
+             WHERE (?1 IS NULL OR at >= ?1) AND (?2 IS NULL OR at <= ?2)
+             ORDER BY at, id LIMIT ?3",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(
+            params![
+                query.from,
+                query.to,
+                query.limit.unwrap_or(1000).clamp(1, 10_000)
+            ],
+            |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, i64>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, String>(3)?,
+                    row.get::<_, i64>(4)?,
+                    row.get::<_, i64>(5)?,
+                    row.get::<_, Option<i64>>(6)?,
+                ))
+            },
+        )
+        .map_err(|e| e.to_string())?;
+
+    let mut events = Vec::new();
+    for row in rows {
+        let (id, at, kind, phase, cycle_index, remaining_seconds, session_id) =
+            row.map_err(|e| e.to_string())?;
+        events.push(TimerEvent {
+            id,
+            at,
+            kind: TimerEventKind::from_db_value(&kind)?,
+            phase: TimerPhase::from_db_value(&phase)?,
+            cycle_index,
+            remaining_seconds,
+            session_id,
+        });
+    }
+    Ok(events)
+}
+
+fn advance_timer(timer: &mut TimerState, settings: &AppSettings) {
+    let next_phase = match timer.phase {
+        TimerPhase::Focus => {
+            timer.cycle_index += 1;
+            let long_due = timer.cycle_index % settings.long_break_every == 0;
+            if timer.long_break_requeued {
+                timer.long_break_requeued = false;
+                TimerPhase::LongBreak
+            } else if long_due && timer.long_break_postponed {
+                timer.long_break_postponed = false;
+                timer.long_break_requeued = true;
+                TimerPhase::ShortBreak
+            } else if long_due {
+                TimerPhase::LongBreak
+            } else {
+                TimerPhase::ShortBreak
+            }
+        }
+        TimerPhase::ShortBreak | TimerPhase::LongBreak => TimerPhase::Focus,
+    };
+
+    timer.phase = next_phase;
+    timer.phase_total_seconds = settings.duration_for_phase_seconds(&timer.phase);
+    timer.remaining_seconds = timer.phase_total_seconds;
+    timer.is_running = false;
+    timer.started_at = None;
+    timer.target_ends_at = None;
+    timer.interruptions = 0;
+    timer.pause_budget_exceeded = false;
+    timer.break_extension_seconds = 0;
+    timer.current_planned = false;
+    sync_pause_budget(timer, settings);
+}
+
+// Lengthen the current break; the extra time is tracked separately from the configured length.
+fn extend_break(timer: &mut TimerState, minutes: i64) -> AppResult<()> {
+    if timer.phase == TimerPhase::Focus {
+        return Err("only breaks can be extended".to_string());
+    }
+    let extra = minutes.clamp(1, 60) * 60;
+    timer.break_extension_seconds += extra;
+    timer.phase_total_seconds += extra;
+    timer.remaining_seconds += extra;
+    if let Some(target_ends_at) = timer.target_ends_at.as_mut() {
+        *target_ends_at += extra;
+    }
+    Ok(())
+}
+
+// Trade the upcoming long break for a short one; the long break follows the next focus.
+// Works while focusing toward a long break, or on a long break that has not started yet.
+fn postpone_long_break(timer: &mut TimerState, settings: &AppSettings) -> AppResult<()> {
+    match timer.phase {
+        TimerPhase::Focus => {
+            let long_due = (timer.cycle_index + 1) % settings.long_break_every == 0;
+            if !long_due || timer.long_break_requeued {
+                return Err("no long break is coming up after this focus".to_string());
+            }
+            timer.long_break_postponed = true;
+        }
+        TimerPhase::LongBreak => {
+            if timer.started_at.is_some() {
+                return Err("the long break has already started".to_string());
+            }
+            timer.phase = TimerPhase::ShortBreak;
+            timer.phase_total_seconds = settings.duration_for_phase_seconds(&timer.phase);
+            timer.remaining_seconds = timer.phase_total_seconds;
+            timer.is_running = false;
+            timer.target_ends_at = None;
+            timer.long_break_requeued = true;
+        }
+        TimerPhase::ShortBreak => {
+            return Err("no long break is coming up".to_string());
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum NotificationAction {
+    Show,
+    Queue,
+    Drop,
+}
+
+fn notification_action(
+    settings: &AppSettings,
+    completed_phase: &TimerPhase,
+    dnd: bool,
+) -> NotificationAction {
+    let break_ended = *completed_phase != TimerPhase::Focus;
+    if !dnd || (break_ended && settings.dnd_break_end_bypass) {
+        return NotificationAction::Show;
+    }
+    match settings.dnd_mode.as_str() {
+        "suppress" => NotificationAction::Drop,
+        "queue" => NotificationAction::Queue,
+        _ => NotificationAction::Show,
+    }
+}
+
+// Project overrides win over the global switches; "none" silences a project outright.
+fn resolve_phase_alerts(
+    settings: &AppSettings,
+    project: Option<&Project>,
+) -> (bool, Option<String>) {
+    let alerts = project
+        .map(|p| p.notification_override.clone())
+        .unwrap_or_default();
+    let notify = alerts.notify.unwrap_or(settings.notifications_enabled);
+    let sound = match alerts.sound {
+        Some(sound) if sound == "none" => None,
+        Some(sound) => Some(sound),
+        None => settings.sound_enabled.then(|| "chime".to_string()),
+    };
+    (notify, sound)
+}
+
+fn show_notification(app: &AppHandle, body: &str) {
+    let _ = app
+        .notification()
+        .builder()
+        .title("Pomodoro update")
+        .body(body)
+        .show();
+}
+
+// macOS records manually enabled Focus modes (including Do Not Disturb) as assertions in
+// this file. Scheduled Focus modes are not covered; other platforms report DND as off.
+#[cfg(target_os = "macos")]
+fn dnd_assertions_path() -> Option<std::path::PathBuf> {
+    let home = std::env::var_os("HOME")?;
+    Some(std::path::PathBuf::from(home).join("Library/DoNotDisturb/DB/Assertions.json"))
+}
+
+#[cfg(not(target_os = "macos"))]
+fn dnd_assertions_path() -> Option<std::path::PathBuf> {
+    None
+}
+
+fn dnd_active() -> bool {
+    dnd_assertions_path()
+        .and_then(|path| fs::read(path).ok())
+        .and_then(|raw| serde_json::from_slice::<serde_json::Value>(&raw).ok())
+        .is_some_and(|json| focus_assertions_active(&json))
+}
+
+fn focus_assertions_active(json: &serde_json::Value) -> bool {
+    json.get("data")
+        .and_then(|data| data.as_array())
+        .is_some_and(|entries| {
+            entries.iter().any(|entry| {
+                entry
+                    .get("storeAssertionRecords")
+                    .and_then(|records| records.as_array())
+                    .is_some_and(|records| !records.is_empty())
+            })
+        })
+}
+
+// Seconds between console probes; each probe spawns a process.
+const DETACH_POLL_SECONDS: i64 = 5;
+
+// Only macOS has a console session probe; elsewhere remoteDetachAction stays "ignore".
+const REMOTE_DETACH_SUPPORTED: bool = cfg!(target_os = "macos");
+
+// `ioreg -n Root -d1` lists console sessions under IOConsoleUsers. A session is detached
+// when it is no longer on the console (e.g. a remote or virtual-display login) or locked.
+#[cfg(target_os = "macos")]
+fn console_session_probe() -> Option<String> {
+    probe_output("ioreg", &["-n", "Root", "-d1"])
+}
+
+// No reliable probe elsewhere yet; the session is always treated as attached.
+#[cfg(not(target_os = "macos"))]
+fn console_session_probe() -> Option<String> {
+    None
+}
+
+fn probe_output(program: &str, args: &[&str]) -> Option<String> {
+    let output = std::process::Command::new(program)
+        .args(args)
+        .output()
+        .ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+// Display brightness as a percentage. macOS has no built-in CLI, so this relies on the
+// Homebrew `brightness` tool and reads the first display.
+#[cfg(target_os = "macos")]
+fn display_brightness() -> Option<i64> {
+    let output = probe_output("brightness", &["-l"])?;
+    let value = output
+        .lines()
+        .find_map(|line| line.split("brightness ").nth(1))?;
+    let level = value.trim().parse::<f64>().ok()?;
+    Some((level * 100.0).round() as i64)
+}
+
+#[cfg(target_os = "macos")]
+fn set_display_brightness(percent: i64) -> bool {
+    let level = format!("{:.2}", percent as f64 / 100.0);
+    probe_output("brightness", &[level.as_str()]).is_some()
+}
+
+// `brightnessctl -m` prints `device,class,current,percent%,max` for the default backlight.
+#[cfg(target_os = "linux")]
+fn display_brightness() -> Option<i64> {
+    let output = probe_output("brightnessctl", &["-m"])?;
+    let percent = output.lines().next()?.split(',').nth(3)?;
+    percent.trim().trim_end_matches('%').parse().ok()
+}
+
+#[cfg(target_os = "linux")]
+fn set_display_brightness(percent: i64) -> bool {
+    let level = format!("{percent}%");
+    probe_output("brightnessctl", &["-q", "set", level.as_str()]).is_some()
+}
+
+// WMI brightness only covers built-in panels; external monitors report nothing.
+#[cfg(target_os = "windows")]
+fn display_brightness() -> Option<i64> {
+    let output = probe_output(
+        "powershell",
+        &[
+            "-NoProfile",
+            "-Command",
+            "(Get-CimInstance -Namespace root/WMI -ClassName WmiMonitorBrightness).CurrentBrightness",
+        ],
+    )?;
+    output.lines().next()?.trim().parse().ok()
+}
+
+#[cfg(target_os = "windows")]
+fn set_display_brightness(percent: i64) -> bool {
+    let command = format!(
+        "(Get-CimInstance -Namespace root/WMI -ClassName WmiMonitorBrightnessMethods) | Invoke-CimMethod -MethodName WmiSetBrightness -Arguments @{{Timeout=1; Brightness={percent}}}"
+    );
+    probe_output("powershell", &["-NoProfile", "-Command", command.as_str()]).is_some()
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "linux", target_os = "windows")))]
+fn display_brightness() -> Option<i64> {
+    None
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "linux", target_os = "windows")))]
+fn set_display_brightness(_percent: i64) -> bool {
+    false
+}
+
+fn wants_screen_dim(settings: &AppSettings, timer: &TimerState) -> bool {
+    settings.break_dim_enabled && timer.is_running && timer.phase != TimerPhase::Focus
+}
+
+// Brightness is only lowered, never raised, and the original level is kept for the restore.
+// `read` and `write` stand in for the platform calls.
+fn screen_dim_step(
+    dim: ScreenDim,
+    wanted: bool,
+    percent: i64,
+    read: impl FnOnce() -> Option<i64>,
+    write: impl FnOnce(i64) -> bool,
+) -> ScreenDim {
+    match (wanted, dim.active) {
+        (true, false) => {
+            let restore = read().filter(|current| *current > percent && write(percent));
+            ScreenDim {
+                active: true,
+                restore,
+            }
+        }
+        (false, true) => {
+            if let Some(level) = dim.restore {
+                write(level);
+            }
+            ScreenDim::default()
+        }
+        _ => dim,
+    }
+}
+
+// What the timer worker has to do about the display: the current state, whether a dim is
+// wanted and how far. None when nothing changes. The brightness tools are separate processes,
+// so the worker runs the step with the model unlocked.
+fn screen_dim_job(model: &AppModel) -> Option<(ScreenDim, bool, i64)> {
+    let wanted = wants_screen_dim(&model.settings, &model.timer);
+    (wanted != model.screen_dim.active).then_some((
+        model.screen_dim,
+        wanted,
+        model.settings.break_dim_percent,
+    ))
+}
+
+fn set_screen_dim(model: &mut AppModel, dim: ScreenDim) {
+    if dim.restore != model.screen_dim.restore {
+        let _ = save_json_setting(&model.conn, SCREEN_DIM_RESTORE_KEY, &dim.restore);
+    }
+    model.screen_dim = dim;
+}
+
+// A level left over from the last run counts as an active dim, so the worker's first round
+// restores it unless that run's break is still going.
+fn load_screen_dim(conn: &Connection) -> ScreenDim {
+    match load_json_setting::<Option<i64>>(conn, SCREEN_DIM_RESTORE_KEY) {
+        Ok(Some(Some(level))) => ScreenDim {
+            active: true,
+            restore: Some(level),
+        },
+        _ => ScreenDim::default(),
+    }
+}
+
+fn screen_dim_restore(model: &mut AppModel) {
+    let dim = screen_dim_step(
+        model.screen_dim,
+        false,
+        model.settings.break_dim_percent,
+        display_brightness,
+        set_display_brightness,
+    );
+    set_screen_dim(model, dim);
+}
+
+fn console_detached() -> bool {
+    let Ok(user) = std::env::var("USER") else {
+        return false;
+    };
+    console_session_probe()
+        .and_then(|output| console_user_detached(&output, &user))
+        .unwrap_or(false)
+}
+
+fn console_user_detached(ioreg: &str, user: &str) -> Option<bool> {
+    let users = ioreg
+        .lines()
+        .find(|line| line.contains("\"IOConsoleUsers\""))?;
+    let name = format!("\"kCGSSessionUserNameKey\"=\"{user}\"");
+    let entry = users.split("},{").find(|entry| entry.contains(&name))?;
+    Some(
+        entry.contains("\"kCGSSessionOnConsoleKey\"=No")
+            || entry.contains("\"CGSSessionScreenIsLocked\"=Yes"),
+    )
+}
+
+// Returns whether the timer changed. Only a running focus reacts, and a forced pause is
+// never rejected by the pause budget: an exhausted budget marks the session instead.
+fn apply_remote_detach(timer: &mut TimerState, settings: &AppSettings, now: i64) -> bool {
+    if !timer.is_running || timer.phase != TimerPhase::Focus {
+        return false;
+    }
+    match settings.remote_detach_action.as_str() {
+        "pause" => {
+            let forced = AppSettings {
+                pause_budget_action: "mark_incomplete".to_string(),
+                ..settings.clone()
+            };
+            pause_timer(timer, &forced, now).is_ok()
+        }
+        "interruption" => {
+            timer.interruptions += 1;
+            sync_pause_budget(timer, settings);
+            true
+        }
+        _ => false,
+    }
+}
+
+// Whether the timer worker should probe the console session on its next round: only while a
+// focus runs and an action is configured.
+fn watches_console_session(model: &AppModel) -> bool {
+    REMOTE_DETACH_SUPPORTED
+        && model.settings.remote_detach_action != "ignore"
+        && model.timer.is_running
+        && model.timer.phase == TimerPhase::Focus
+}
+
+// `detached` is a probe the worker ran before locking the model.
+fn handle_remote_detach(model: &mut AppModel, detached: bool, now: i64) -> bool {
+    if !watches_console_session(model) {
+        model.console_detached = detached;
+        return false;
+    }
+    let newly_detached = detached && !model.console_detached;
+    model.console_detached = detached;
+    if !newly_detached {
+        return false;
+    }
+
+    let was_running = model.timer.is_running;
+    let changed = apply_remote_detach(&mut model.timer, &model.settings, now);
+    if changed && was_running && !model.timer.is_running {
+        let _ = log_timer_event(model, TimerEventKind::Paused, None, now);
+    }
+    changed
+}
+
+const SESSION_WEBHOOK_ATTEMPTS: u32 = 3;
+
+fn session_webhook_payload(
+    conn: &Connection,
+    session: &SessionRecord,
+) -> AppResult<SessionWebhookPayload> {
+    let project_name = match session.project_id {
+        Some(id) => conn
+            .query_row(
+                "SELECT name FROM projects WHERE id = ?1",
+                params![id],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| e.to_string())?,
+        None => None,
+    };
+    let mut tag_names = Vec::new();
+    for tag_id in &session.tag_ids {
+        let name: Option<String> = conn
+            .query_row(
+                "SELECT name FROM tags WHERE id = ?1",
+                params![tag_id],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| e.to_string())?;
+        tag_names.extend(name);
+    }
+    Ok(SessionWebhookPayload {
+        event: "session.completed".to_string(),
+        session: session.clone(),
+        project_name,
+        tag_names,
+    })
+}
+
+// Delivered off the timer thread with a few quick retries; a receiver that stays down
+// misses the session, which is still in the database.
+fn send_session_webhook(url: String, secret: String, payload: SessionWebhookPayload) {
+    thread::spawn(move || {
+        let body = match serde_json::to_string(&payload) {
+            Ok(body) => body,
+            Err(error) => return eprintln!("session webhook encoding failed: {error}"),
+        };
+        for attempt in 1..=SESSION_WEBHOOK_ATTEMPTS {
+            let mut request = ureq::post(&url)
+                .timeout(Duration::from_secs(10))
+                .set("Content-Type", "application/json");
+            if !secret.is_empty() {
+                request = request.set("X-Pomodoro-Webhook-Secret", &secret);
+            }
+            match request.send_string(&body) {
+                Ok(_) => return,
+                Err(error) if attempt == SESSION_WEBHOOK_ATTEMPTS => {
+                    eprintln!("session webhook to {url} failed: {error}")
+                }
+                Err(_) => thread::sleep(Duration::from_secs(2u64.pow(attempt))),
+            }
+        }
+    });
+}
+
+// Push services want a contact for the sender in the VAPID token.
+const WEB_PUSH_SUBJECT: &str = "https://landing-pomodoro.vercel.app/";
+// A phone that stays offline longer than this skips the push; it is stale by then.
+const WEB_PUSH_TTL_SECONDS: u32 = 15 * 60;
+
+fn load_or_create_vapid_keys(conn: &Connection) -> AppResult<VapidKeys> {
+    if let Some(keys) = load_json_setting::<VapidKeys>(conn, WEB_PUSH_KEY)? {
+        return Ok(keys);
+    }
+    use p256::elliptic_curve::sec1::ToEncodedPoint;
+    let secret = p256::SecretKey::random(&mut rand::rngs::OsRng);
+    let encode = |bytes: &[u8]| base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(bytes);
+    let keys = VapidKeys {
+        private_key: encode(&secret.to_bytes()),
+        public_key: encode(secret.public_key().to_encoded_point(false).as_bytes()),
+    };
+    save_json_setting(conn, WEB_PUSH_KEY, &keys)?;
+    Ok(keys)
+}
+
+// The push services browsers hand out endpoints for: FCM (Chrome, Edge on Android), Mozilla
+// autopush, Apple and WNS. The app posts to whatever a subscription names, so anything else
+// would let a token holder point those posts at the LAN.
+const PUSH_SERVICE_HOSTS: &[&str] = &["fcm.googleapis.com", "updates.push.services.mozilla.com"];
+const PUSH_SERVICE_DOMAINS: &[&str] = &[".push.apple.com", ".notify.windows.com"];
+
+// Each send is a serial post with a 10 s timeout, so one token gets a handful of devices.
+const MAX_PUSH_SUBSCRIPTIONS_PER_TOKEN: i64 = 5;
+
+fn push_endpoint_allowed(endpoint: &str) -> bool {
+    let Some(rest) = endpoint.strip_prefix("https://") else {
+        return false;
+    };
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    if authority.contains('@') {
+        return false;
+    }
+    let host = match authority.rsplit_once(':') {
+        Some((host, port)) if port == "443" => host,
+        Some(_) => return false,
+        None => authority,
+    }
+    .to_ascii_lowercase();
+    PUSH_SERVICE_HOSTS.contains(&host.as_str())
+        || PUSH_SERVICE_DOMAINS
+            .iter()
+            .any(|domain| host.ends_with(domain))
+}
+
+fn save_push_subscription(
+    conn: &Connection,
+    input: &PushSubscriptionInput,
+    token_id: i64,
+    at: i64,
+) -> AppResult<()> {
+    if !push_endpoint_allowed(&input.endpoint) {
+        return Err("push endpoint must be an https URL of a known push service".to_string());
+    }
+    let others: i64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM push_subscriptions WHERE token_id = ?1 AND endpoint != ?2",
+            params![token_id, input.endpoint],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+    if others >= MAX_PUSH_SUBSCRIPTIONS_PER_TOKEN {
+        return Err(format!(
+            "a token can have at most {MAX_PUSH_SUBSCRIPTIONS_PER_TOKEN} push subscriptions"
+        ));
+    }
+    if input.keys.p256dh.is_empty() || input.keys.auth.is_empty() {
+        return Err("push subscription is missing its keys".to_string());
+    }
+    conn.execute(
+        "INSERT INTO push_subscriptions (endpoint, p256dh, auth, token_id, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5)
+         ON CONFLICT(endpoint) DO UPDATE SET p256dh = excluded.p256dh, auth = excluded.auth,
+             token_id = excluded.token_id",
+        params![
+            input.endpoint,
+            input.keys.p256dh,
+            input.keys.auth,
+            token_id,
+            at
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+// Only the token that subscribed an endpoint can drop it.
+fn delete_push_subscription(conn: &Connection, endpoint: &str, token_id: i64) -> AppResult<bool> {
+    let removed = conn
+        .execute(
+            "DELETE FROM push_subscriptions WHERE endpoint = ?1 AND token_id = ?2",
+            params![endpoint, token_id],
+        )
+        .map_err(|e| e.to_string())?;
+    Ok(removed > 0)
+}
+
+fn fetch_push_subscriptions(conn: &Connection) -> AppResult<Vec<PushSubscriptionInput>> {
+    let mut stmt = conn
+        .prepare("SELECT endpoint, p256dh, auth FROM push_subscriptions ORDER BY id")
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok(PushSubscriptionInput {
+                endpoint: row.get(0)?,
+                keys: PushSubscriptionKeys {
+                    p256dh: row.get(1)?,
+                    auth: row.get(2)?,
+                },
+            })
+        })
+        .map_err(|e| e.to_string())?;
+    rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+}
+
+// The notification the service worker shows for a completed phase.
+fn phase_push_payload(completed: &TimerPhase, next: &TimerPhase) -> serde_json::Value {
+    let title = match completed {
+        TimerPhase::Focus => "Focus complete",
+        _ => "Break is over",
+    };
+    let next = match next {
+        TimerPhase::Focus => "focus",
+        TimerPhase::ShortBreak => "a short break",
+        TimerPhase::LongBreak => "a long break",
+    };
+    serde_json::json!({ "title": title, "body": format!("Up next: {next}.") })
+}
+
+// Encrypts the payload for one subscription (RFC 8291) and signs a VAPID token for its push
+// service (RFC 8292).
+fn build_web_push(
+    keys: &VapidKeys,
+    subscription: &PushSubscriptionInput,
+    payload: &[u8],
+) -> AppResult<web_push::WebPushMessage> {
+    let info = web_push::SubscriptionInfo::new(
+        &subscription.endpoint,
+        &subscription.keys.p256dh,
+        &subscription.keys.auth,
+    );
+    let mut signature = web_push::VapidSignatureBuilder::from_base64(&keys.private_key, &info)
+        .map_err(|e| e.to_string())?;
+    signature.add_claim("sub", WEB_PUSH_SUBJECT);
+    let mut builder = web_push::WebPushMessageBuilder::new(&info);
+    builder.set_payload(web_push::ContentEncoding::Aes128Gcm, payload);
+    builder.set_vapid_signature(signature.build().map_err(|e| e.to_string())?);
+    builder.set_ttl(WEB_PUSH_TTL_SECONDS);
+    builder.build().map_err(|e| e.to_string())
+}
+
+// Sent off the timer thread. Subscriptions the push service reports gone (404/410), e.g.
+// after the phone unsubscribed or cleared site data, are deleted.
+fn send_web_pushes(
+    app: AppHandle,
+    keys: VapidKeys,
+    subscriptions: Vec<PushSubscriptionInput>,
+    payload: serde_json::Value,
+) {
+    thread::spawn(move || {
+        let body = payload.to_string();
+        let mut gone = Vec::new();
+        for subscription in subscriptions {
+            // Rows saved before the host check are skipped rather than posted to.
+            if !push_endpoint_allowed(&subscription.endpoint) {
+                continue;
+            }
+            let message = match build_web_push(&keys, &subscription, body.as_bytes()) {
+                Ok(message) => message,
+                Err(error) => {
+                    eprintln!("web push skipped: {error}");
+                    continue;
+                }
+            };
+            let mut request = ureq::post(&message.endpoint.to_string())
+                .timeout(Duration::from_secs(10))
+                .set("TTL", &message.ttl.to_string());
+            let result = match &message.payload {
+                Some(payload) => {
+                    request = request.set("Content-Encoding", payload.content_encoding.to_str());
+                    for (name, value) in &payload.crypto_headers {
+                        request = request.set(name, value);
+                    }
+                    request.send_bytes(&payload.content)
+                }
+                None => request.call(),
+            };
+            match result {
+                Ok(_) => {}
+                Err(ureq::Error::Status(404 | 410, _)) => gone.push(subscription.endpoint),
+                Err(error) => eprintln!("web push failed: {error}"),
+            }
+        }
+        if gone.is_empty() {
+            return;
+        }
+        let state = app.state::<AppState>();
+        if let Ok(model) = state.model.lock() {
+            for endpoint in gone {
+                let _ = delete_push_subscription(&model.conn, &endpoint);
+            }
+        }
+    });
+}
+
+fn complete_and_advance(
+    app: &AppHandle,
+    model: &mut AppModel,
+    completed: bool,
+) -> AppResult<(SessionRecord, PhaseCompletedEvent, TimerState)> {
+    let finished_phase = model.timer.phase.clone();
+    let ended_at = now_ts();
+    let kind = if completed {
+        TimerEventKind::Completed
+    } else {
+        TimerEventKind::Skipped
+    };
+    // A session held back by read-only mode has no row yet, so nothing may point at its id.
+    let written = model.read_only.is_none();
+    let session = match &mut model.read_only {
+        Some(mode) => {
+            let session = session_from_timer(&model.timer, completed, ended_at);
+            mode.writes.push(PendingWrite::Session {
+                session: session.clone(),
+                timer: model.timer.clone(),
+                event: kind,
+            });
+            session
+        }
+        None => {
+            let session = record_session(&model.conn, &model.timer, completed, ended_at)?;
+            record_timer_event(&model.conn, kind, &model.timer, Some(session.id), ended_at)?;
+            if finished_phase == TimerPhase::Focus {
+                spend_streak_freezes(&model.conn, &model.settings, ended_at)?;
+            }
+            session
+        }
+    };
+    if completed
+        && written
+        && finished_phase == TimerPhase::Focus
+        && !model.settings.session_webhook_url.is_empty()
+    {
+        match session_webhook_payload(&model.conn, &session) {
+            Ok(payload) => send_session_webhook(
+                model.settings.session_webhook_url.clone(),
+                model.settings.session_webhook_secret.clone(),
+                payload,
+            ),
+            Err(error) => eprintln!("session webhook skipped: {error}"),
+        }
+    }
+
+    let goals_reached = if completed && finished_phase == TimerPhase::Focus {
+        newly_reached_goals(&model.conn, &model.settings, ended_at).unwrap_or_else(|error| {
+            eprintln!("goal progress skipped: {error}");
+            Vec::new()
+        })
+    } else {
+        Vec::new()
+    };
+
+    advance_timer(&mut model.timer, &model.settings);
+    if completed && model.settings.remote_control_enabled {
+        // A phone with the remote page in the background still hears about the phase change.
+        let subscriptions = fetch_push_subscriptions(&model.conn).unwrap_or_default();
+        if !subscriptions.is_empty() {
+            match load_or_create_vapid_keys(&model.conn) {
+                Ok(keys) => send_web_pushes(
+                    app.clone(),
+                    keys,
+                    subscriptions,
+                    phase_push_payload(&finished_phase, &model.timer.phase),
+                ),
+                Err(error) => eprintln!("web push skipped: {error}"),
+            }
+        }
+    }
+    model.timer.last_active_at = Some(ended_at);
+    apply_experiment_length(&model.conn, &mut model.timer, ended_at)?;
+    persist_timer(model)?;
+
+    let project = session
+        .project_id
+        .and_then(|id| fetch_project(&model.conn, id).ok());
+    let (notify, sound) = resolve_phase_alerts(&model.settings, project.as_ref());
+    let event = PhaseCompletedEvent {
+        completed_phase: finished_phase,
+        next_phase: model.timer.phase.clone(),
+        session_id: session.id,
+        notify,
+        sound,
+    };
+
+    if event.notify {
+        let language = model.settings.language.clone();
+        let mut body = localize(
+            &language,
+            Message::PhaseComplete,
+            &[
+                ("phase", &phase_label(&language, &event.completed_phase)),
+                ("next", &phase_label(&language, &event.next_phase)),
+            ],
+        );
+        // The session is already saved, so a failed count only costs the notification its tally.
+        if event.completed_phase == TimerPhase::Focus {
+            match remote_today(&model.conn, &model.timer, ended_at) {
+                Ok(summary) => {
+                    let today = localize(
+                        &language,
+                        Message::PomodorosToday,
+                        &[("count", &summary.completed_pomodoros.to_string())],
+                    );
+                    body = format!("{body}. {today}");
+                }
+                Err(error) => eprintln!("today's pomodoro count skipped: {error}"),
+            }
+        }
+        match notification_action(&model.settings, &event.completed_phase, dnd_active()) {
+            NotificationAction::Show => show_notification(app, &body),
+            NotificationAction::Queue => model.queued_notification = Some(body),
+            NotificationAction::Drop => {}
+        }
+    }
+
+    for goal in &goals_reached {
+        let _ = app.emit("goal://reached", goal);
+        if !model.settings.goal_notification {
+            continue;
+        }
+        let body = goal_reached_message(&model.settings.language, goal);
+        match notification_action(&model.settings, &TimerPhase::Focus, dnd_active()) {
+            NotificationAction::Show => show_notification(app, &body),
+            NotificationAction::Queue => {
+                model.queued_notification = Some(match model.queued_notification.take() {
+                    Some(queued) => format!("{queued}. {body}"),
+                    None => body,
+                });
+            }
+            NotificationAction::Drop => {}
+        }
+    }
+
+    Ok((session, event, model.timer.clone()))
+}
+
+fn setup_tray(app: &AppHandle) -> AppResult<()> {
+    let toggle = MenuItem::with_id(app, "toggle", "Start / Pause", true, None::<&str>)
+        .map_err(|e| e.to_string())?;
+    let skip = MenuItem::with_id(app, "skip", "Skip phase", true, None::<&str>)
+        .map_err(|e| e.to_string())?;
+    let open = MenuItem::with_id(app, "open", "Open dashboard", true, None::<&str>)
+        .map_err(|e| e.to_string())?;
+    let quit =
+        MenuItem::with_id(app, "quit", "Quit", true, None::<&str>).map_err(|e| e.to_string())?;
+
+    let menu = Menu::with_items(app, &[&toggle, &skip, &open, &quit]).map_err(|e| e.to_string())?;
+
+    TrayIconBuilder::with_id(TRAY_ID)
+        .menu(&menu)
+        .title("Pomodoro")
+        .show_menu_on_left_click(true)
+        .on_menu_event(|app, event| match event.id().as_ref() {
+            "toggle" => {
+                let _ = tray_toggle_timer(app);
+            }
+            "skip" => {
+                let _ = tray_skip_timer(app);
+            }
+            "open" => {
+                if let Some(window) = app.get_webview_window("main") {
+                    let _ = window.show();
+                    let _ = window.set_focus();
+                }
+            }
+            "quit" => {
+                app.exit(0);
+            }
+            _ => {}
+        })
+        .build(app)
+        .map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+fn tray_toggle_timer(app: &AppHandle) -> AppResult<()> {
+    mutate(app, "timer_toggle", &[DataDomain::Timer], |model, _| {
+        refresh_remaining(&mut model.timer);
+
+        let settings = model.settings.clone();
+        if model.timer.is_running {
+            pause_timer(&mut model.timer, &settings, now_ts())?;
+            log_timer_event(model, TimerEventKind::Paused, None, now_ts())?;
+        } else {
+            apply_cycle_reset(&mut model.timer, &settings, now_ts());
+            if model.timer.remaining_seconds <= 0 {
+                model.timer.remaining_seconds = model.timer.phase_total_seconds;
+            }
+            let kind = run_event_kind(&model.timer);
+            log_timer_event(model, kind, None, now_ts())?;
+            if model.timer.started_at.is_none() {
+                model.timer.started_at = Some(now_ts());
+            }
+            model.timer.is_running = true;
+            model.timer.target_ends_at = Some(now_ts() + model.timer.remaining_seconds);
+        }
+        Ok(())
+    })
+}
+
+fn tray_skip_timer(app: &AppHandle) -> AppResult<()> {
+    timer_skip_inner(app).map(|_| ())
+}
+
+// Same ceiling as the focus length setting.
+const MAX_CUSTOM_DURATION_MIN: i64 = 180;
+
+fn timer_start_inner(app: &AppHandle, payload: Option<StartTimerRequest>) -> AppResult<TimerState> {
+    mutate(app, "timer_start", &[DataDomain::Timer], |model, _| {
+        start_timer(model, payload, false)
+    })
+}
+
+// `planned` is only true when the plan queue starts the focus; other starts are ad hoc.
+fn start_timer(
+    model: &mut AppModel,
+    payload: Option<StartTimerRequest>,
+    planned: bool,
+) -> AppResult<TimerState> {
+    refresh_remaining(&mut model.timer);
+
+    let duration_minutes = payload.as_ref().and_then(|p| p.duration_minutes);
+    if let Some(minutes) = duration_minutes {
+        if !(1..=MAX_CUSTOM_DURATION_MIN).contains(&minutes) {
+            return Err(format!(
+                "durationMinutes must be between 1 and {MAX_CUSTOM_DURATION_MIN}"
+            ));
+        }
+    }
+    if let Some(payload) = payload {
+        if let Some(project_id) = payload.project_id {
+            model.timer.current_project_id = project_id;
+        }
+        if let Some(tag_ids) = payload.tag_ids {
+            model.timer.current_tag_ids = tag_ids;
+        }
+    }
+    // Only a fresh start decides; resuming keeps how the focus began.
+    if model.timer.started_at.is_none() {
+        model.timer.current_planned = planned;
+        if let Some(minutes) = duration_minutes {
+            model.timer.phase_total_seconds = minutes * 60;
+            model.timer.remaining_seconds = minutes * 60;
+        }
+    }
+
+    let settings = model.settings.clone();
+    apply_cycle_reset(&mut model.timer, &settings, now_ts());
+    if model.timer.remaining_seconds <= 0 {
+        model.timer.remaining_seconds = model.timer.phase_total_seconds;
+    }
+    if !model.timer.is_running {
+        let kind = run_event_kind(&model.timer);
+        log_timer_event(model, kind, None, now_ts())?;
+    }
+    if model.timer.started_at.is_none() {
+        model.timer.started_at = Some(now_ts());
+    }
+
+    model.timer.is_running = true;
+    model.timer.target_ends_at = Some(now_ts() + model.timer.remaining_seconds);
+    Ok(model.timer.clone())
+}
+
+fn timer_pause_inner(app: &AppHandle) -> AppResult<TimerState> {
+    mutate(app, "timer_pause", &[DataDomain::Timer], |model, _| {
+        refresh_remaining(&mut model.timer);
+        let settings = model.settings.clone();
+        let was_running = model.timer.is_running;
+        pause_timer(&mut model.timer, &settings, now_ts())?;
+        if was_running {
+            log_timer_event(model, TimerEventKind::Paused, None, now_ts())?;
+        }
+        Ok(model.timer.clone())
+    })
+}
+
+fn timer_resume_inner(
+    app: &AppHandle,
+    payload: Option<StartTimerRequest>,
+) -> AppResult<TimerState> {
+    mutate(app, "timer_resume", &[DataDomain::Timer], |model, _| {
+        if let Some(payload) = payload {
+            if let Some(project_id) = payload.project_id {
+                model.timer.current_project_id = project_id;
+            }
+            if let Some(tag_ids) = payload.tag_ids {
+                model.timer.current_tag_ids = tag_ids;
+            }
+        }
+        let settings = model.settings.clone();
+        apply_cycle_reset(&mut model.timer, &settings, now_ts());
+        if model.timer.remaining_seconds <= 0 {
+            model.timer.remaining_seconds = model.timer.phase_total_seconds;
+        }
+        if !model.timer.is_running {
+            let kind = run_event_kind(&model.timer);
+            log_timer_event(model, kind, None, now_ts())?;
+        }
+        if model.timer.started_at.is_none() {
+            model.timer.started_at = Some(now_ts());
+        }
+        model.timer.is_running = true;
+        model.timer.target_ends_at = Some(now_ts() + model.timer.remaining_seconds);
+        Ok(model.timer.clone())
+    })
+}
+
+fn timer_skip_inner(app: &AppHandle) -> AppResult<TimerState> {
+    let (session, phase_event, timer) = mutate(
+        app,
+        "timer_skip",
+        &[DataDomain::Sessions, DataDomain::Timer],
+        |model, _| {
+            refresh_remaining(&mut model.timer);
+            complete_and_advance(app, model, false)
+        },
+    )?;
+
+    let _ = app.emit("session://completed", &session);
+    let _ = app.emit("timer://phase-completed", &phase_event);
+    Ok(timer)
+}
+
+fn timer_get_state_inner(state: &AppState) -> AppResult<TimerState> {
+    let mut model = state.model.lock().map_err(|e| e.to_string())?;
+    refresh_remaining(&mut model.timer);
+    Ok(model.timer.clone())
+}
+
+fn remote_stop(remote: &mut RemoteControlState) {
+    if let Some(mut handle) = remote.server.take() {
+        if let Some(advert) = handle.advert.take() {
+            remote_unadvertise(advert);
+        }
+        handle.stop.store(false, Ordering::SeqCst);
+        if let Some(join) = handle.join.take() {
+            let _ = join.join();
+        }
+    }
+}
+
+// Ports tried after the configured one when it is taken, e.g. by a second copy of the app.
+const REMOTE_PORT_FALLBACKS: u16 = 10;
+
+// Binds the configured port, else the next free one of the following REMOTE_PORT_FALLBACKS.
+// The IPv4 listener decides the port; the IPv6 one joins it when the machine has IPv6.
+fn bind_remote_listener_with_fallback(port: u16) -> AppResult<(Vec<TcpListener>, u16)> {
+    let last = port.saturating_add(REMOTE_PORT_FALLBACKS);
+    let mut first_error = None;
+    for candidate in port..=last {
+        match bind_remote_listener(candidate) {
+            Ok(listener) => {
+                let mut listeners = vec![listener];
+                listeners.extend(bind_remote_listener_v6(candidate));
+                return Ok((listeners, candidate));
+            }
+            Err(error) => {
+                first_error.get_or_insert(error);
+            }
+        }
+    }
+    Err(format!(
+        "{}; ports {}-{last} are busy too",
+        first_error.unwrap_or_default(),
+        port.saturating_add(1)
+    ))
+}
+
+fn bind_remote_listener(port: u16) -> AppResult<TcpListener> {
+    let addr = format!("0.0.0.0:{port}");
+    let listener = TcpListener::bind(&addr)
+        .map_err(|e| format!("remote control server bind failed on {addr}: {e}"))?;
+    listener
+        .set_nonblocking(true)
+        .map_err(|e| format!("remote control server nonblocking setup failed on {addr}: {e}"))?;
+    Ok(listener)
+}
+
+// A separate IPv6-only socket rather than one dual-stack socket: whether `[::]` also takes
+// IPv4 differs between macOS, Linux and Windows, while two sockets behave the same everywhere.
+fn bind_remote_listener_v6(port: u16) -> Option<TcpListener> {
+    let bind = || -> std::io::Result<TcpListener> {
+        let socket = socket2::Socket::new(
+            socket2::Domain::IPV6,
+            socket2::Type::STREAM,
+            Some(socket2::Protocol::TCP),
+        )?;
+        socket.set_only_v6(true)?;
+        #[cfg(unix)]
+        socket.set_reuse_address(true)?;
+        let addr = std::net::SocketAddr::from((std::net::Ipv6Addr::UNSPECIFIED, port));
+        socket.bind(&addr.into())?;
+        socket.listen(128)?;
+        socket.set_nonblocking(true)?;
+        Ok(socket.into())
+    };
+    bind()
+        .map_err(|e| eprintln!("remote control server IPv6 bind failed on [::]:{port}: {e}"))
+        .ok()
+}
+
+// Settings state that lives beside the model: the tray's settings copy and the remote server.
+// Call after the model lock is released, and before emitting the timer so the tray redraws
+// in the new language.
+fn apply_settings_outside_model(app: &AppHandle, settings: &AppSettings) -> AppResult<()> {
+    let state = app.state::<AppState>();
+    {
+        let mut tray = state.tray.lock().map_err(|e| e.to_string())?;
+        tray.language = settings.language.clone();
+        tray.seconds = settings.tray_title_seconds;
+    }
+    remote_apply(app, settings)
+}
+
+// A port that cannot be bound is not an error for the caller: settings still save, and the
+// failure is reported through remote://status for the settings screen.
+fn remote_apply(app: &AppHandle, settings: &AppSettings) -> AppResult<()> {
+    let state = app.state::<AppState>();
+    let port = settings.remote_control_port as u16;
+    // Looking up what to advertise runs a subprocess, so it happens before taking the remote
+    // lock, and only when the server will need a new advert.
+    let advert_needed = settings.remote_control_enabled
+        && settings.remote_mdns_enabled
+        && state.remote.lock().map_or(true, |remote| {
+            remote
+                .server
+                .as_ref()
+                .is_none_or(|handle| handle.port != port || handle.advert.is_none())
+        });
+    let host = advert_needed.then(remote_advert_host).flatten();
+    let mut remote = state.remote.lock().map_err(|e| e.to_string())?;
+
+    if !settings.remote_control_enabled {
+        remote_stop(&mut remote);
+        set_remote_status(app, &mut remote, RemoteStatus::stopped());
+        return Ok(());
+    }
+
+    if let Some(handle) = remote.server.as_mut().filter(|handle| handle.port == port) {
+        let bound_port = handle.bound_port;
+        match (settings.remote_mdns_enabled, handle.advert.take()) {
+            (true, None) => {
+                handle.advert = host.and_then(|host| remote_advertise(&host, bound_port))
+            }
+            (false, Some(advert)) => remote_unadvertise(advert),
+            (_, advert) => handle.advert = advert,
+        }
+        return Ok(());
+    }
+
+    remote_stop(&mut remote);
+
+    let (listeners, bound_port) = match bind_remote_listener_with_fallback(port) {
+        Ok(bound) => bound,
+        Err(reason) => {
+            eprintln!("remote control server failed to start: {reason}");
+            let status = RemoteStatus {
+                state: RemoteRunState::Failed,
+                configured_port: Some(port),
+                port: None,
+                reason: Some(reason),
+            };
+            set_remote_status(app, &mut remote, status);
+            return Ok(());
+        }
+    };
+    let stop = Arc::new(AtomicBool::new(true));
+    let stop_thread = stop.clone();
+    let app_handle = app.clone();
+
+    let join = thread::spawn(move || remote_server_loop(app_handle, listeners, stop_thread));
+    remote.server = Some(RemoteServerHandle {
+        port,
+        bound_port,
+        stop,
+        join: Some(join),
+        advert: host
+            .filter(|_| settings.remote_mdns_enabled)
+            .and_then(|host| remote_advertise(&host, bound_port)),
+    });
+    let status = RemoteStatus {
+        state: RemoteRunState::Running,
+        configured_port: Some(port),
+        port: Some(bound_port),
+        reason: (bound_port != port).then(|| format!("port {port} is busy")),
+    };
+    set_remote_status(app, &mut remote, status);
+
+    Ok(())
+}
+
+fn set_remote_status(app: &AppHandle, remote: &mut RemoteControlState, status: RemoteStatus) {
+    if remote.status != status {
+        let _ = app.emit("remote://status", &status);
+        remote.status = status;
+    }
+}
+
+const REMOTE_MDNS_SERVICE: &str = "_pomodoro._tcp.local.";
+
+// The name shown to browsing devices: the macOS computer name, else the hostname.
+fn remote_device_name() -> String {
+    probe_output("scutil", &["--get", "ComputerName"])
+        .or_else(|| probe_output("hostname", &[]))
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "Pomodoro".to_string())
+}
+
+// A DNS label for the advertised `<label>.local.` host: ASCII letters, digits and dashes.
+fn mdns_host_label(name: &str) -> String {
+    let label = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect::<String>()
+        .split('-')
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-");
+    let label = label.chars().take(63).collect::<String>();
+    if label.is_empty() {
+        "pomodoro".to_string()
+    } else {
+        label
+    }
+}
+
+// The computer's name and addresses, which an advert publishes next to the port.
+struct RemoteAdvertHost {
+    name: String,
+    // mdns-sd takes a comma-separated list, so both A and AAAA records are published.
+    ips: String,
+}
+
+fn remote_advert_host() -> Option<RemoteAdvertHost> {
+    get_local_ip()
+        .map(|ips| RemoteAdvertHost {
+            name: remote_device_name(),
+            ips: ips.join(","),
+        })
+        .map_err(|e| eprintln!("remote mDNS advertising failed: {e}"))
+        .ok()
+}
+
+// Advertising is best effort: the server runs the same without it, so failures are logged.
+// The TXT record carries the page path and app version for companion apps.
+fn remote_advertise(host: &RemoteAdvertHost, port: u16) -> Option<RemoteAdvert> {
+    let advertise = || -> AppResult<RemoteAdvert> {
+        let hostname = format!("{}.local.", mdns_host_label(&host.name));
+        let properties = [("path", "/"), ("version", env!("CARGO_PKG_VERSION"))];
+        let info = mdns_sd::ServiceInfo::new(
+            REMOTE_MDNS_SERVICE,
+            &host.name,
+            &hostname,
+            host.ips.as_str(),
+            port,
+            &properties[..],
+        )
+        .map_err(|e| e.to_string())?;
+        let fullname = info.get_fullname().to_string();
+        let daemon = mdns_sd::ServiceDaemon::new().map_err(|e| e.to_string())?;
+        daemon.register(info).map_err(|e| e.to_string())?;
+        Ok(RemoteAdvert { daemon, fullname })
+    };
+    advertise()
+        .map_err(|e| eprintln!("remote mDNS advertising failed: {e}"))
+        .ok()
+}
+
+// Unregistering first sends the goodbye packets, so browsers drop the entry at once.
+fn remote_unadvertise(advert: RemoteAdvert) {
+    let _ = advert.daemon.unregister(&advert.fullname);
+    let _ = advert.daemon.shutdown();
+}
+
+fn header_value<'a>(headers: &'a [httparse::Header<'a>], name: &str) -> Option<&'a str> {
+    for h in headers {
+        if h.name.eq_ignore_ascii_case(name) {
+            return std::str::from_utf8(h.value).ok();
+        }
+    }
+    None
+}
+
+fn parse_query_param<'a>(query: &'a str, key: &str) -> Option<&'a str> {
+    for part in query.split('&') {
+        let mut it = part.splitn(2, '=');
+        let k = it.next().unwrap_or("");
+        if k == key {
+            return Some(it.next().unwrap_or(""));
+        }
+    }
+    None
+}
+
+// Decodes %XX escapes and '+' in a query value; malformed escapes are kept verbatim.
+fn decode_query_value(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => out.push(b' '),
+            b'%' if i + 2 < bytes.len() => {
+                let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok();
+                match hex.and_then(|h| u8::from_str_radix(h, 16).ok()) {
+                    Some(byte) => {
+                        out.push(byte);
+                        i += 2;
+                    }
+                    None => out.push(b'%'),
+                }
+            }
+            b => out.push(b),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+// Every remote list route reads the same parameters, mirroring SessionQuery over IPC:
+// `from`/`to` (epoch seconds, on endedAt), `limit`, `cursor` (the previous `nextCursor`),
+// `projectId` and `tagId`, plus `granularity` (day, week or month) for the analytics
+// routes. Unknown keys are ignored; malformed values are errors rather than silently
+// dropped filters.
+fn parse_remote_list_query(query: &str) -> AppResult<SessionQuery> {
+    let number = |key: &str| -> AppResult<Option<i64>> {
+        parse_query_param(query, key)
+            .map(decode_query_value)
+            .filter(|v| !v.is_empty())
+            .map(|v| {
+                v.parse::<i64>()
+                    .map_err(|_| format!("invalid query parameter {key}: {v}"))
+            })
+            .transpose()
+    };
+    let granularity = match parse_query_param(query, "granularity").map(decode_query_value) {
+        None => None,
+        Some(v) if v.is_empty() => None,
+        Some(v) => Some(match v.as_str() {
+            "day" => Granularity::Day,
+            "week" => Granularity::Week,
+            "month" => Granularity::Month,
+            _ => return Err(format!("invalid query parameter granularity: {v}")),
+        }),
+    };
+    Ok(SessionQuery {
+        range: AnalyticsRange {
+            from: number("from")?,
+            to: number("to")?,
+            project_id: number("projectId")?,
+            tag_id: number("tagId")?,
+            granularity,
+            include_excluded: false,
+            phases: Vec::new(),
+            excluded: AnalyticsExclusions::default(),
+        },
+        limit: number("limit")?,
+        cursor: parse_query_param(query, "cursor")
+            .map(decode_query_value)
+            .filter(|v| !v.is_empty()),
+    })
+}
+
+fn is_loopback_host(host: &str) -> bool {
+    let name = match host.strip_prefix('[') {
+        Some(rest) => rest.split(']').next().unwrap_or(""),
+        None => host.split(':').next().unwrap_or(""),
+    };
+    name.eq_ignore_ascii_case("localhost")
+        || name
+            .parse::<IpAddr>()
+            .map(|ip| ip.is_loopback())
+            .unwrap_or(false)
+}
+
+fn parse_optional_body<T: serde::de::DeserializeOwned>(
+    body: &[u8],
+) -> serde_json::Result<Option<T>> {
+    if body.iter().all(u8::is_ascii_whitespace) {
+        return Ok(None);
+    }
+    serde_json::from_slice(body).map(Some)
+}
+
+fn split_path_query(path: &str) -> (&str, &str) {
+    match path.split_once('?') {
+        Some((p, q)) => (p, q),
+        None => (path, ""),
+    }
+}
+
+fn write_response(stream: &mut std::net::TcpStream, code: &str, content_type: &str, body: &[u8]) {
+    write_response_encoded(stream, code, content_type, body, None, false);
+}
+
+// Smaller bodies are not worth the CPU or the gzip header.
+const REMOTE_COMPRESS_MIN_BYTES: usize = 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ContentEncoding {
+    Gzip,
+    Deflate,
+}
+
+impl ContentEncoding {
+    fn as_str(self) -> &'static str {
+        match self {
+            ContentEncoding::Gzip => "gzip",
+            ContentEncoding::Deflate => "deflate",
+        }
+    }
+}
+
+// Picks gzip over deflate when the client accepts both; `q=0` rules a coding out, also when a
+// `*` would otherwise cover it.
+fn negotiate_encoding(accept_encoding: Option<&str>) -> Option<ContentEncoding> {
+    let mut accepted = Vec::new();
+    let mut refused_codings = Vec::new();
+    for part in accept_encoding?.split(',') {
+        let mut fields = part.split(';');
+        let coding = fields.next().unwrap_or("").trim().to_ascii_lowercase();
+        let refused = fields.any(|p| {
+            p.trim()
+                .strip_prefix("q=")
+                .and_then(|q| q.trim().parse::<f32>().ok())
+                .is_some_and(|q| q <= 0.0)
+        });
+        if refused {
+            refused_codings.push(coding);
+        } else {
+            accepted.push(coding);
+        }
+    }
+    let listed = |codings: &[String], name: &str| codings.iter().any(|c| c == name);
+    let accepts = |name: &str| {
+        listed(&accepted, name) || (listed(&accepted, "*") && !listed(&refused_codings, name))
+    };
+    if accepts("gzip") {
+        Some(ContentEncoding::Gzip)
+    } else if accepts("deflate") {
+        Some(ContentEncoding::Deflate)
+    } else {
+        None
+    }
+}
+
+// HTTP's `deflate` is the zlib format, not raw deflate.
+fn compress_body(body: &[u8], encoding: ContentEncoding) -> std::io::Result<Vec<u8>> {
+    let level = flate2::Compression::fast();
+    match encoding {
+        ContentEncoding::Gzip => {
+            let mut encoder = flate2::write::GzEncoder::new(Vec::new(), level);
+            encoder.write_all(body)?;
+            encoder.finish()
+        }
+        ContentEncoding::Deflate => {
+            let mut encoder = flate2::write::ZlibEncoder::new(Vec::new(), level);
+            encoder.write_all(body)?;
+            encoder.finish()
+        }
+    }
+}
+
+// Compresses bodies of REMOTE_COMPRESS_MIN_BYTES or more when the client negotiated an
+// encoding; everything else goes out as is. With `keep_alive` the connection stays open for
+// the next request instead of closing after this response.
+fn write_response_encoded(
+    stream: &mut std::net::TcpStream,
+    code: &str,
+    content_type: &str,
+    body: &[u8],
+    encoding: Option<ContentEncoding>,
+    keep_alive: bool,
+) {
+    let compressed = encoding
+        .filter(|_| body.len() >= REMOTE_COMPRESS_MIN_BYTES)
+        .and_then(|enc| compress_body(body, enc).ok().map(|bytes| (enc, bytes)));
+    let (encoding_header, body) = match &compressed {
+        Some((enc, bytes)) => (
+            format!("Content-Encoding: {}\r\n", enc.as_str()),
+            bytes.as_slice(),
+        ),
+        None => (String::new(), body),
+    };
+    let connection = if keep_alive {
+        format!(
+            "keep-alive\r\nKeep-Alive: timeout={}, max={REMOTE_KEEP_ALIVE_MAX}",
+            REMOTE_KEEP_ALIVE_IDLE.as_secs()
+        )
+    } else {
+        "close".to_string()
+    };
+    let headers = format!(
+        "HTTP/1.1 {code}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\n{encoding_header}Vary: Accept-Encoding\r\nConnection: {connection}\r\nAccess-Control-Allow-Origin: *\r\nAccess-Control-Allow-Headers: Content-Type, X-Pomodoro-Token, X-Pomodoro-Device\r\nAccess-Control-Allow-Methods: GET, POST, PATCH, OPTIONS\r\n\r\n",
+        body.len()
+    );
+    let _ = stream.write_all(headers.as_bytes());
+    let _ = stream.write_all(body);
+}
+
+// Events pushed to remote clients over /ws and /api/events.
+const REMOTE_PUSH_EVENTS: &[&str] = &[
+    "timer://state",
+    "timer://phase-completed",
+    "session://completed",
+];
+const WEBSOCKET_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+// Clients only send pings, pongs and close frames; anything bigger is a misbehaving peer.
+const WEBSOCKET_MAX_CLIENT_FRAME: usize = 64 * 1024;
+const WEBSOCKET_PING_SECONDS: u64 = 30;
+
+// RFC 6455 fixes SHA-1 for the handshake's accept key; device fingerprints reuse it.
+fn sha1(data: &[u8]) -> [u8; 20] {
+    sha1_smol::Sha1::from(data).digest().bytes()
+}
+
+// Each WebSocket or SSE stream holds a thread and a broadcast subscriber for as long as it
+// stays open; the two kinds are capped separately.
+const REMOTE_MAX_WEBSOCKETS: usize = 16;
+const REMOTE_MAX_EVENT_STREAMS: usize = 16;
+
+// One place among a capped number of long-lived remote streams; dropping it frees the place.
+struct StreamSlot(Arc<AtomicUsize>);
+
+impl StreamSlot {
+    fn acquire(open: &Arc<AtomicUsize>, limit: usize) -> Option<Self> {
+        open.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| {
+            (n < limit).then_some(n + 1)
+        })
+        .ok()
+        .map(|_| StreamSlot(open.clone()))
+    }
+}
+
+impl Drop for StreamSlot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+fn websocket_accept_key(key: &str) -> String {
+    BASE64_STANDARD.encode(sha1(format!("{key}{WEBSOCKET_GUID}").as_bytes()))
+}
+
+// A single unmasked, unfragmented server frame.
+fn websocket_frame(opcode: u8, payload: &[u8]) -> Vec<u8> {
+    let mut frame = vec![0x80 | opcode];
+    match payload.len() {
+        len @ 0..=125 => frame.push(len as u8),
+        len @ 126..=0xFFFF => {
+            frame.push(126);
+            frame.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        len => {
+            frame.push(127);
+            frame.extend_from_slice(&(len as u64).to_be_bytes());
+        }
+    }
+    frame.extend_from_slice(payload);
+    frame
+}
+
+// Reads one client frame off the front of `buf` as (opcode, unmasked payload, bytes used);
+// None until the frame is complete. Clients must mask, and fragmented messages are refused
+// because nothing the remote page sends needs them.
+fn parse_websocket_frame(buf: &[u8]) -> AppResult<Option<(u8, Vec<u8>, usize)>> {
+    if buf.len() < 2 {
+        return Ok(None);
+    }
+    if buf[0] & 0x80 == 0 {
+        return Err("fragmented websocket frames are not supported".to_string());
+    }
+    if buf[1] & 0x80 == 0 {
+        return Err("websocket client frames must be masked".to_string());
+    }
+    let (len, header) = match buf[1] & 0x7F {
+        126 if buf.len() >= 4 => (u16::from_be_bytes([buf[2], buf[3]]) as usize, 4),
+        127 if buf.len() >= 10 => {
+            let mut len = [0u8; 8];
+            len.copy_from_slice(&buf[2..10]);
+            (u64::from_be_bytes(len) as usize, 10)
+        }
+        126 | 127 => return Ok(None),
+        len => (len as usize, 2),
+    };
+    if len > WEBSOCKET_MAX_CLIENT_FRAME {
+        return Err(format!("websocket frame of {len} bytes is too large"));
+    }
+    let start = header + 4;
+    if buf.len() < start + len {
+        return Ok(None);
+    }
+    let mask = &buf[header..start];
+    let payload = buf[start..start + len]
+        .iter()
+        .enumerate()
+        .map(|(i, byte)| byte ^ mask[i % 4])
+        .collect();
+    Ok(Some((buf[0] & 0x0F, payload, start + len)))
+}
+
+// Registered once at startup, so every emit reaches the broadcast whether or not the remote
+// server is running; with no subscribers a publish is a no-op.
+fn forward_remote_events(app: &AppHandle) {
+    for name in REMOTE_PUSH_EVENTS {
+        let handle = app.clone();
+        app.listen_any(*name, move |event| {
+            if let Ok(mut broadcast) = handle.state::<AppState>().remote_events.lock() {
+                broadcast.publish(RemoteEvent {
+                    name,
+                    payload: event.payload().to_string(),
+                });
+            }
+        });
+    }
+}
+
+// A new subscription whose first event is the current timer state, so clients render at
+// once instead of waiting for the next tick.
+fn subscribe_remote_events(app: &AppHandle) -> AppResult<std::sync::mpsc::Receiver<RemoteEvent>> {
+    let state = app.state::<AppState>();
+    let timer = timer_get_state_inner(state.inner())?;
+    let mut broadcast = state.remote_events.lock().map_err(|e| e.to_string())?;
+    let rx = broadcast.subscribe();
+    if let Some(subscriber) = broadcast.subscribers.last() {
+        let _ = subscriber.send(RemoteEvent {
+            name: "timer://state",
+            payload: serde_json::to_string(&timer).map_err(|e| e.to_string())?,
+        });
+    }
+    Ok(rx)
+}
+
+// One Server-Sent Events message; each payload line gets its own `data:` field.
+fn sse_message(event: &str, payload: &str) -> String {
+    let mut message = format!("event: {event}\n");
+    for line in payload.lines() {
+        message.push_str("data: ");
+        message.push_str(line);
+        message.push('\n');
+    }
+    message.push('\n');
+    message
+}
+
+const SSE_KEEPALIVE_SECONDS: u64 = 15;
+
+// Runs on its own thread after the stream headers, like the WebSocket session. Comment lines
+// keep proxies from timing the stream out and surface a closed client as a write error.
+fn remote_sse_session(app: AppHandle, mut stream: std::net::TcpStream, token: String) {
+    let Ok(rx) = subscribe_remote_events(&app) else {
+        return;
+    };
+    if stream.write_all(b"retry: 3000\n\n").is_err() {
+        return;
+    }
+    let mut kept_alive_at = Instant::now();
+    loop {
+        match rx.recv_timeout(Duration::from_secs(1)) {
+            Ok(event) => {
+                let message = sse_message(event.name, &event.payload);
+                if stream.write_all(message.as_bytes()).is_err() {
+                    break;
+                }
+            }
+            Err(std::sync::mpsc::RecvTimeoutError::Timeout) => {}
+            Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => break,
+        }
+        if !remote_socket_authorized(&app, &token) {
+            break;
+        }
+        if kept_alive_at.elapsed() >= Duration::from_secs(SSE_KEEPALIVE_SECONDS) {
+            kept_alive_at = Instant::now();
+            if stream.write_all(b": keep-alive\n\n").is_err() {
+                break;
+            }
+        }
+    }
+}
+
+// `payload` is the event's JSON as Tauri serialized it.
+fn websocket_event_message(event: &str, payload: &str) -> String {
+    format!(
+        "{{\"event\":{},\"payload\":{payload}}}",
+        serde_json::Value::from(event)
+    )
+}
+
+// Whether the token a socket connected with still grants read access. Checked every second,
+// so turning remote control off or revoking the token drops live clients too.
+fn remote_socket_authorized(app: &AppHandle, token: &str) -> bool {
+    let state = app.state::<AppState>();
+    let Ok(model) = state.model.lock() else {
+        return false;
+    };
+    model.settings.remote_control_enabled
+        && lookup_remote_token(&model.conn, token)
+            .ok()
+            .flatten()
+            .is_some_and(|(_, scopes)| scopes.contains(&RemoteScope::Read))
+}
+
+// Runs on its own thread after the 101 response, so the accept loop keeps serving requests.
+fn remote_websocket_session(app: AppHandle, mut stream: std::net::TcpStream, token: String) {
+    let Ok(rx) = subscribe_remote_events(&app) else {
+        // 1011: internal error.
+        let _ = stream.write_all(&websocket_frame(0x8, &1011u16.to_be_bytes()));
+        return;
+    };
+
+    let _ = stream.set_read_timeout(Some(Duration::from_millis(100)));
+    let mut pending = Vec::new();
+    let mut checked_at = Instant::now();
+    let mut pinged_at = Instant::now();
+    let send = |stream: &mut std::net::TcpStream, opcode: u8, payload: &[u8]| {
+        stream.write_all(&websocket_frame(opcode, payload)).is_ok()
+    };
+
+    'session: loop {
+        while let Ok(event) = rx.try_recv() {
+            let message = websocket_event_message(event.name, &event.payload);
+            if !send(&mut stream, 0x1, message.as_bytes()) {
+                break 'session;
+            }
+        }
+
+        let mut chunk = [0u8; 1024];
+        match stream.read(&mut chunk) {
+            Ok(0) => break,
+            Ok(n) => pending.extend_from_slice(&chunk[..n]),
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+                ) => {}
+            Err(_) => break,
+        }
+        loop {
+            match parse_websocket_frame(&pending) {
+                Ok(Some((opcode, payload, used))) => {
+                    pending.drain(..used);
+                    match opcode {
+                        0x8 => {
+                            send(&mut stream, 0x8, &payload);
+                            break 'session;
+                        }
+                        0x9 if !send(&mut stream, 0xA, &payload) => break 'session,
+                        _ => {}
+                    }
+                }
+                Ok(None) => break,
+                Err(_) => {
+                    // 1002: protocol error.
+                    send(&mut stream, 0x8, &1002u16.to_be_bytes());
+                    break 'session;
+                }
+            }
+        }
+
+        if checked_at.elapsed() >= Duration::from_secs(1) {
+            checked_at = Instant::now();
+            if !remote_socket_authorized(&app, &token) {
+                // 1008: policy violation.
+                send(&mut stream, 0x8, &1008u16.to_be_bytes());
+                break;
+            }
+        }
+        if pinged_at.elapsed() >= Duration::from_secs(WEBSOCKET_PING_SECONDS) {
+            pinged_at = Instant::now();
+            if !send(&mut stream, 0x9, b"") {
+                break;
+            }
+        }
+    }
+}
+
+struct RemoteApiRoute {
+    method: &'static str,
+    // Under /api/v1.
+    path: &'static str,
+    summary: &'static str,
+    // Names of `components.parameters` entries.
+    query: &'static [&'static str],
+    // Schema names; a `[]` suffix is an array of that schema.
+    request: Option<&'static str>,
+    response: &'static str,
+}
+
+const REMOTE_RANGE_QUERY: &[&str] = &["from", "to", "projectId", "tagId"];
+
+// Every token-protected JSON route, in the order the OpenAPI document lists them. Add a route
+// here when adding it to remote_handle_request.
+const REMOTE_API_ROUTES: &[RemoteApiRoute] = &[
+    RemoteApiRoute {
+        method: "GET",
+        path: "/state",
+        summary: "Current timer state",
+        query: &[],
+        request: None,
+        response: "TimerState",
+    },
+    RemoteApiRoute {
+        method: "POST",
+        path: "/toggle",
+        summary: "Pause a running timer, otherwise resume or start it",
+        query: &[],
+        request: None,
+        response: "TimerState",
+    },
+    RemoteApiRoute {
+        method: "POST",
+        path: "/start",
+        summary: "Start the current phase",
+        query: &[],
+        request: Some("StartTimerRequest"),
+        response: "TimerState",
+    },
+    RemoteApiRoute {
+        method: "POST",
+        path: "/context",
+        summary: "Set the project and tags for the current and next sessions",
+        query: &[],
+        request: Some("StartTimerRequest"),
+        response: "TimerState",
+    },
+    RemoteApiRoute {
+        method: "POST",
+        path: "/pause",
+        summary: "Pause the timer",
+        query: &[],
+        request: None,
+        response: "TimerState",
+    },
+    RemoteApiRoute {
+        method: "POST",
+        path: "/resume",
+        summary: "Resume a paused timer",
+        query: &[],
+        request: Some("StartTimerRequest"),
+        response: "TimerState",
+    },
+    RemoteApiRoute {
+        method: "POST",
+        path: "/skip",
+        summary: "Finish the current phase now and move to the next",
+        query: &[],
+        request: None,
+        response: "TimerState",
+    },
+    RemoteApiRoute {
+        method: "GET",
+        path: "/today",
+        summary: "Today's completed pomodoros, focus time and current project",
+        query: &[],
+        request: None,
+        response: "RemoteToday",
+    },
+    RemoteApiRoute {
+        method: "GET",
+        path: "/sessions",
+        summary: "One page of session history, newest first",
+        query: &["from", "to", "projectId", "tagId", "limit", "cursor"],
+        request: None,
+        response: "SessionPage",
+    },
+    RemoteApiRoute {
+        method: "GET",
+        path: "/summary",
+        summary: "Focus totals, streak and averages for a range",
+        query: REMOTE_RANGE_QUERY,
+        request: None,
+        response: "AnalyticsSummary",
+    },
+    RemoteApiRoute {
+        method: "GET",
+        path: "/timeseries",
+        summary: "Focus totals per day, week or month",
+        query: &["from", "to", "projectId", "tagId", "granularity"],
+        request: None,
+        response: "TimeseriesPoint[]",
+    },
+    RemoteApiRoute {
+        method: "GET",
+        path: "/projects",
+        summary: "All projects",
+        query: &[],
+        request: None,
+        response: "Project[]",
+    },
+    RemoteApiRoute {
+        method: "POST",
+        path: "/projects",
+        summary: "Create a project, or update the one with the given id",
+        query: &[],
+        request: Some("ProjectInput"),
+        response: "Project",
+    },
+    RemoteApiRoute {
+        method: "GET",
+        path: "/tags",
+        summary: "All tags",
+        query: &[],
+        request: None,
+        response: "Tag[]",
+    },
+    RemoteApiRoute {
+        method: "POST",
+        path: "/tags",
+        summary: "Create a tag, or rename the one with the given id",
+        query: &[],
+        request: Some("TagInput"),
+        response: "Tag",
+    },
+    RemoteApiRoute {
+        method: "GET",
+        path: "/settings",
+        summary: "Settings, without secrets",
+        query: &[],
+        request: None,
+        response: "Settings",
+    },
+    RemoteApiRoute {
+        method: "PATCH",
+        path: "/settings",
+        summary: "Change durations, alerts and other non-remote settings",
+        query: &[],
+        request: Some("Settings"),
+        response: "Settings",
+    },
+    RemoteApiRoute {
+        method: "GET",
+        path: "/health",
+        summary: "App version, uptime, row counts and whether the timer worker is running",
+        query: &[],
+        request: None,
+        response: "Health",
+    },
+    RemoteApiRoute {
+        method: "GET",
+        path: "/push/key",
+        summary: "VAPID public key for PushManager.subscribe",
+        query: &[],
+        request: None,
+        response: "PushKey",
+    },
+    RemoteApiRoute {
+        method: "POST",
+        path: "/push/subscribe",
+        summary: "Get a Web Push when a phase completes",
+        query: &[],
+        request: Some("PushSubscription"),
+        response: "PushSubscribed",
+    },
+    RemoteApiRoute {
+        method: "POST",
+        path: "/push/unsubscribe",
+        summary: "Stop Web Pushes to a subscription",
+        query: &[],
+        request: Some("PushSubscription"),
+        response: "PushSubscribed",
+    },
+    RemoteApiRoute {
+        method: "GET",
+        path: "/health/mindful",
+        summary: "Breaks or focus sessions shaped for health apps, oldest first, 500 a page",
+        query: &["cursor", "since", "kind"],
+        request: None,
+        response: "Object",
+    },
+];
+
+fn openapi_schema_ref(name: &str) -> serde_json::Value {
+    match name.strip_suffix("[]") {
+        Some(item) => serde_json::json!({ "type": "array", "items": openapi_schema_ref(item) }),
+        None => serde_json::json!({ "$ref": format!("#/components/schemas/{name}") }),
+    }
+}
+
+// The OpenAPI 3 description served at /api/v1/openapi.json, built from REMOTE_API_ROUTES.
+// Schemas list the fields clients rely on; responses may carry more.
+fn remote_openapi(version: &str) -> serde_json::Value {
+    use serde_json::json;
+
+    let json_body = |schema: serde_json::Value| json!({ "application/json": { "schema": schema } });
+    let mut paths = serde_json::Map::new();
+    for route in REMOTE_API_ROUTES {
+        let scope = remote_route_scope(route.method, &format!("/api{}", route.path));
+        let parameters: Vec<_> = route
+            .query
+            .iter()
+            .map(|name| json!({ "$ref": format!("#/components/parameters/{name}") }))
+            .collect();
+        let mut operation = json!({
+            "summary": route.summary,
+            "x-pomodoro-scope": scope.as_str(),
+            "parameters": parameters,
+            "responses": {
+                "200": {
+                    "description": "Success, or `{\"error\": ...}` when the request failed",
+                    "content": json_body(openapi_schema_ref(route.response)),
+                },
+                "401": { "description": "Missing or unknown token" },
+                "403": { "description": "The token lacks the route's scope" },
+                "429": { "description": "Rate limited or locked out" },
+            },
+        });
+        if let Some(request) = route.request {
+            operation["requestBody"] = json!({
+                "required": true,
+                "content": json_body(openapi_schema_ref(request)),
+            });
+        }
+        let item = paths
+            .entry(format!("/api/v1{}", route.path))
+            .or_insert_with(|| json!({}));
+        item[route.method.to_ascii_lowercase()] = operation;
+    }
+    paths.insert(
+        "/api/v1/events".to_string(),
+        json!({ "get": {
+            "summary": "Server-Sent Events: timer://state, timer://phase-completed and session://completed",
+            "x-pomodoro-scope": "read",
+            "responses": { "200": {
+                "description": "An open event stream",
+                "content": { "text/event-stream": { "schema": { "type": "string" } } },
+            } },
+        } }),
+    );
+    paths.insert(
+        "/api/v1/team/daily".to_string(),
+        json!({ "get": {
+            "summary": "Per-day focus totals for team stats",
+            "security": [{ "teamKey": [] }],
+            "parameters": [{ "$ref": "#/components/parameters/days" }],
+            "responses": {
+                "200": { "description": "Daily totals", "content": json_body(openapi_schema_ref("Object")) },
+                "401": { "description": "Team stats off or wrong key" },
+            },
+        } }),
+    );
+
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "Pomodoro remote API",
+            "version": version,
+            "description": "Unversioned /api/... paths are aliases of /api/v1/.... \
+                GET /ws?token=... upgrades to a WebSocket with the same events as /api/v1/events.",
+        },
+        "security": [{ "token": [] }, { "tokenQuery": [] }],
+        "paths": paths,
+        "components": {
+            "securitySchemes": {
+                "token": { "type": "apiKey", "in": "header", "name": "X-Pomodoro-Token" },
+                "tokenQuery": { "type": "apiKey", "in": "query", "name": "token" },
+                "teamKey": { "type": "apiKey", "in": "header", "name": "X-Pomodoro-Team-Key" },
+            },
+            "parameters": openapi_parameters(),
+            "schemas": openapi_schemas(),
+        },
+    })
+}
+
+fn openapi_parameters() -> serde_json::Map<String, serde_json::Value> {
+    use serde_json::json;
+
+    let integer = json!({ "type": "integer" });
+    [
+        (
+            "from",
+            integer.clone(),
+            "Epoch seconds, matched on session end",
+        ),
+        (
+            "to",
+            integer.clone(),
+            "Epoch seconds, matched on session end",
+        ),
+        ("projectId", integer.clone(), "Only this project"),
+        ("tagId", integer.clone(), "Only sessions with this tag"),
+        ("limit", integer.clone(), "Page size"),
+        (
+            "cursor",
+            json!({ "type": "string" }),
+            "nextCursor from the previous page",
+        ),
+        (
+            "granularity",
+            json!({ "type": "string", "enum": ["day", "week", "month"] }),
+            "Bucket size; day by default",
+        ),
+        (
+            "since",
+            integer.clone(),
+            "Epoch seconds; superseded by cursor",
+        ),
+        (
+            "kind",
+            json!({ "type": "string", "enum": ["breaks", "focus"] }),
+            "breaks by default",
+        ),
+        ("days", integer, "How many days back; 30 by default"),
+    ]
+    .into_iter()
+    .map(|(name, schema, description)| {
+        let parameter = json!({
+            "name": name,
+            "in": "query",
+            "required": false,
+            "schema": schema,
+            "description": description,
+        });
+        (name.to_string(), parameter)
+    })
+    .collect()
+}
+
+fn openapi_schemas() -> serde_json::Map<String, serde_json::Value> {
+    use serde_json::json;
+
+    let integer = json!({ "type": "integer" });
+    let nullable_integer = json!({ "type": "integer", "nullable": true });
+    let nullable_string = json!({ "type": "string", "nullable": true });
+    let boolean = json!({ "type": "boolean" });
+    let object =
+        |properties: serde_json::Value| json!({ "type": "object", "properties": properties });
+    [
+        ("Object", json!({ "type": "object" })),
+        ("PushKey", object(json!({ "publicKey": { "type": "string" } }))),
+        (
+            "PushSubscription",
+            // PushSubscription.toJSON(); unsubscribe only needs the endpoint.
+            object(json!({
+                "endpoint": { "type": "string" },
+                "keys": object(json!({
+                    "p256dh": { "type": "string" },
+                    "auth": { "type": "string" },
+                })),
+            })),
+        ),
+        ("PushSubscribed", object(json!({ "subscribed": boolean }))),
+        (
+            "Health",
+            object(json!({
+                "status": { "type": "string", "enum": ["ok", "degraded"] },
+                "version": { "type": "string" },
+                "uptimeSeconds": integer,
+                "timerWorkerAlive": boolean,
+                "lastTickAt": integer,
+                "readOnly": boolean,
+                "rows": { "type": "object", "additionalProperties": integer },
+            })),
+        ),
+        (
+            "TimerPhase",
+            json!({ "type": "string", "enum": ["focus", "short_break", "long_break"] }),
+        ),
+        (
+            "TimerState",
+            object(json!({
+                "phase": { "$ref": "#/components/schemas/TimerPhase" },
+                "remainingSeconds": integer,
+                "isRunning": boolean,
+                "cycleIndex": integer,
+                "startedAt": nullable_integer,
+                "phaseTotalSeconds": integer,
+                "interruptions": integer,
+                "currentProjectId": nullable_integer,
+                "currentTagIds": { "type": "array", "items": integer },
+                "targetEndsAt": nullable_integer,
+            })),
+        ),
+        (
+            "StartTimerRequest",
+            // Omitting projectId keeps the current project; null clears it. durationMinutes
+            // only applies to /start.
+            object(json!({
+                "projectId": nullable_integer,
+                "tagIds": { "type": "array", "items": integer },
+                "durationMinutes": { "type": "integer", "minimum": 1, "maximum": MAX_CUSTOM_DURATION_MIN },
+            })),
+        ),
+        (
+            "RemoteToday",
+            object(json!({
+                "completedPomodoros": integer,
+                "focusSeconds": integer,
+                "projectName": nullable_string,
+            })),
+        ),
+        (
+            "SessionPage",
+            object(json!({
+                "total": integer,
+                "sessions": { "type": "array", "items": { "type": "object" } },
+                "nextCursor": nullable_string,
+            })),
+        ),
+        (
+            "AnalyticsSummary",
+            object(json!({
+                "totalFocusSec": integer,
+                "completedPomodoros": integer,
+                "streakDays": integer,
+                "interruptions": integer,
+                "avgDailyFocusSec": integer,
+            })),
+        ),
+        (
+            "TimeseriesPoint",
+            object(json!({
+                "date": { "type": "string", "format": "date" },
+                "focusSeconds": integer,
+                "completedPomodoros": integer,
+                "interruptions": integer,
+            })),
+        ),
+        (
+            "Project",
+            object(json!({
+                "id": integer,
+                "name": { "type": "string" },
+                "color": nullable_string,
+                "archived": boolean,
+                "pinned": boolean,
+            })),
+        ),
+        (
+            "ProjectInput",
+            object(json!({
+                "id": nullable_integer,
+                "name": { "type": "string" },
+                "color": nullable_string,
+                "archived": boolean,
+            })),
+        ),
+        (
+            "Tag",
+            object(json!({ "id": integer, "name": { "type": "string" } })),
+        ),
+        (
+            "TagInput",
+            object(json!({ "id": nullable_integer, "name": { "type": "string" } })),
+        ),
+        (
+            "Settings",
+            // PATCH takes any subset; GET returns every setting except secrets.
+            object(json!({
+                "focusMin": integer,
+                "shortBreakMin": integer,
+                "longBreakMin": integer,
+                "longBreakEvery": integer,
+                "soundEnabled": boolean,
+                "notificationsEnabled": boolean,
+            })),
+        ),
+    ]
+    .into_iter()
+    .map(|(name, schema)| (name.to_string(), schema))
+    .collect()
+}
+
+// Files of the remote page, bundled as resources under `remote/` (see tauri.conf.json):
+// route, file name and content type. Browsers fetch the manifest, service worker and icons
+// without custom headers, so these are public like the page itself; none of them holds data.
+const REMOTE_ASSETS: &[(&str, &str, &str)] = &[
+    ("/", "index.html", "text/html; charset=utf-8"),
+    (
+        "/manifest.json",
+        "manifest.json",
+        "application/manifest+json",
+    ),
+    ("/sw.js", "sw.js", "text/javascript; charset=utf-8"),
+    ("/icon-256.png", "icon-256.png", "image/png"),
+    ("/icon-512.png", "icon-512.png", "image/png"),
+];
+
+// Served instead of index.html when the bundled file is missing (a broken install, or a dev
+// build run without resources): enough to paste a token and control the timer.
+const REMOTE_FALLBACK_HTML: &str = r#"<!doctype html>
+<html lang="en">
+  <head>
+    <meta charset="utf-8" />
+    <meta name="viewport" content="width=device-width, initial-scale=1" />
+    <title>Pomodoro Remote</title>
+    <style>
+      body { font-family: -apple-system, system-ui, sans-serif; background: #0b1220; color: #e5e7eb; margin: 0; padding: 24px; }
+      input, button { font: inherit; padding: 10px 14px; border-radius: 10px; border: 1px solid #334155; background: #111827; color: inherit; }
+      button { margin-right: 8px; }
+      #state { font-size: 40px; font-weight: 700; margin: 16px 0; }
+    </style>
+  </head>
+  <body>
+    <h1>Pomodoro Remote</h1>
+    <p><input id="token" placeholder="Token" /></p>
+    <div id="state">--:--</div>
+    <p><button id="toggle">Start / Pause</button><button id="skip">Skip</button></p>
+    <p id="error"></p>
+    <script>
+      const tokenInput = document.getElementById("token");
+      tokenInput.value = new URLSearchParams(location.search).get("token") || "";
+      async function api(path, method) {
+        const res = await fetch(path, { method, headers: { "X-Pomodoro-Token": tokenInput.value } });
+        const data = await res.json();
+        if (!res.ok || data.error) throw new Error(data.error || res.status);
+        return data;
+      }
+      async function refresh() {
+        try {
+          const state = await api("/api/state", "GET");
+          const left = Math.max(0, state.remainingSeconds);
+          document.getElementById("state").textContent =
+            state.phase + " " + Math.floor(left / 60) + ":" + String(left % 60).padStart(2, "0");
+          document.getElementById("error").textContent = "";
+        } catch (err) {
+          document.getElementById("error").textContent = String(err.message || err);
+        }
+      }
+      document.getElementById("toggle").onclick = () => api("/api/toggle", "POST").then(refresh, refresh);
+      document.getElementById("skip").onclick = () => api("/api/skip", "POST").then(refresh, refresh);
+      setInterval(refresh, 2000);
+      refresh();
+    </script>
+  </body>
+</html>
+"#;
+
+// Reads a remote page file from `dir` (the bundled `remote/` resources). A missing
+// index.html falls back to the inline page; any other missing file is a 404.
+fn load_remote_asset(dir: Option<&std::path::Path>, path: &str) -> Option<(Vec<u8>, &'static str)> {
+    let (_, file, content_type) = REMOTE_ASSETS.iter().find(|(route, _, _)| *route == path)?;
+    match dir.and_then(|dir| fs::read(dir.join(file)).ok()) {
+        Some(body) => Some((body, content_type)),
+        None if *file == "index.html" => {
+            Some((REMOTE_FALLBACK_HTML.as_bytes().to_vec(), content_type))
+        }
+        None => None,
+    }
+}
+
+fn record_remote_request(
+    metrics: &mut BTreeMap<String, EndpointCounters>,
+    route: &str,
+    elapsed: Duration,
+) {
+    let key = if metrics.contains_key(route) || metrics.len() < REMOTE_METRICS_MAX_ROUTES - 1 {
+        route
+    } else {
+        "other"
+    };
+    let micros = u64::try_from(elapsed.as_micros()).unwrap_or(u64::MAX);
+    let counters = metrics.entry(key.to_string()).or_default();
+    counters.requests += 1;
+    counters.total_micros = counters.total_micros.saturating_add(micros);
+    counters.max_micros = counters.max_micros.max(micros);
+}
+
+fn endpoint_stats(metrics: &BTreeMap<String, EndpointCounters>) -> Vec<EndpointStats> {
+    let mut stats: Vec<EndpointStats> = metrics
+        .iter()
+        .map(|(route, c)| EndpointStats {
+            route: route.clone(),
+            requests: c.requests,
+            avg_latency_ms: c.total_micros as f64 / c.requests.max(1) as f64 / 1000.0,
+            max_latency_ms: c.max_micros as f64 / 1000.0,
+        })
+        .collect();
+    stats.sort_by(|a, b| b.requests.cmp(&a.requests).then(a.route.cmp(&b.route)));
+    stats
+}
+
+// Handles one request. `pending` holds bytes already read from the connection (a pipelined
+// request) and keeps whatever follows this request's body. Returns the stream when the
+// connection may serve another request, None once it is closed or handed to a WebSocket or
+// SSE session. `route` is set to "METHOD /path" as soon as the request line parses, for
+// metrics.
+fn remote_handle_request(
+    app: &AppHandle,
+    mut stream: std::net::TcpStream,
+    pending: &mut Vec<u8>,
+    reuse: bool,
+    route: &mut String,
+) -> Option<std::net::TcpStream> {
+    let _ = stream.set_read_timeout(Some(Duration::from_secs(2)));
+
+    let started = Instant::now();
+    let mut head = std::mem::take(pending);
+    let mut chunk = [0u8; 4096];
+    let mut header_end: Option<usize> = None;
+
+    // Read until headers complete or size limit hit.
+    while started.elapsed() < REMOTE_READ_DEADLINE {
+        if let Some(pos) = head.windows(4).position(|w| w == b"\r\n\r\n") {
+            header_end = Some(pos + 4);
+            break;
+        }
+        if head.len() >= REMOTE_MAX_HEAD_BYTES {
+            break;
+        }
+        match stream.read(&mut chunk) {
+            Ok(0) => break,
+            Ok(n) => head.extend_from_slice(&chunk[..n]),
+            Err(_) => break,
+        }
+    }
+
+    let header_end = match header_end {
+        Some(v) => v,
+        None => {
+            write_response(
+                &mut stream,
+                "400 Bad Request",
+                "text/plain; charset=utf-8",
+                b"bad request",
+            );
+            return None;
+        }
+    };
+
+    let mut headers = [httparse::EMPTY_HEADER; 32];
+    let mut req = httparse::Request::new(&mut headers);
+    let _parsed = match req.parse(&head[..header_end]) {
+        Ok(Status::Complete(n)) => n,
+        _ => {
+            write_response(
+                &mut stream,
+                "400 Bad Request",
+                "text/plain; charset=utf-8",
+                b"bad request",
+            );
+            return None;
+        }
+    };
+
+    let method = req.method.unwrap_or("");
+    let path_raw = req.path.unwrap_or("/");
+    let (path, query) = split_path_query(path_raw);
+    // `/api/v1/...` is the documented form; the unversioned `/api/...` paths are aliases.
+    let unversioned;
+    let path = match path.strip_prefix("/api/v1/") {
+        Some(rest) => {
+            unversioned = format!("/api/{rest}");
+            unversioned.as_str()
+        }
+        None => path,
+    };
+    *route = format!("{} {path}", method.to_ascii_uppercase());
+
+    let peer_ip = stream.peer_addr().ok().map(|addr| addr.ip());
+    let admitted = peer_ip.map(|ip| {
+        app.state::<AppState>()
+            .remote_guard
+            .lock()
+            .map(|mut guard| guard.admit(ip, route, now_ts()))
+    });
+    if let Some(Ok(Err(rejection))) = admitted {
+        let body = match rejection {
+            RemoteRejection::LockedOut { until } => {
+                format!("too many failed attempts; locked out until {until}")
+            }
+            RemoteRejection::RateLimited => "too many requests".to_string(),
+        };
+        write_response(
+            &mut stream,
+            "429 Too Many Requests",
+            "text/plain; charset=utf-8",
+            body.as_bytes(),
+        );
+        return None;
+    }
+    let auth_result = |ok: bool| {
+        let Some(ip) = peer_ip else {
+            return;
+        };
+        if let Ok(mut guard) = app.state::<AppState>().remote_guard.lock() {
+            if ok {
+                guard.record_success(ip);
+            } else {
+                guard.record_failure(ip, route, now_ts());
+            }
+        }
+    };
+
+    let encoding = negotiate_encoding(header_value(req.headers, "Accept-Encoding"));
+    let content_length = header_value(req.headers, "Content-Length")
+        .and_then(|v| v.parse::<usize>().ok())
+        .unwrap_or(0);
+
+    // Bytes read past the headers belong to the body first, then to the next request.
+    let after_head = &head[header_end..];
+    let (body, rest) = after_head.split_at(after_head.len().min(content_length));
+    *pending = rest.to_vec();
+    let mut body = body.to_vec();
+    while body.len() < content_length && started.elapsed() < REMOTE_READ_DEADLINE {
+        let want = (content_length - body.len()).min(chunk.len());
+        match stream.read(&mut chunk[..want]) {
+            Ok(0) => break,
+            Ok(n) => body.extend_from_slice(&chunk[..n]),
+            Err(_) => break,
+        }
+    }
+    // A short body leaves the stream mid-request, so it cannot carry another one.
+    let keep_alive = reuse
+        && body.len() == content_length
+        && wants_keep_alive(req.version, header_value(req.headers, "Connection"));
+
+    if method.eq_ignore_ascii_case("OPTIONS") {
+        write_response_encoded(
+            &mut stream,
+            "204 No Content",
+            "text/plain; charset=utf-8",
+            b"",
+            None,
+            keep_alive,
+        );
+        return keep_alive.then_some(stream);
+    }
+
+    // Snapshot settings for auth/enable checks.
+    let (remote_enabled, team_key, pairing_required, read_only) = {
+        let state = app.state::<AppState>();
+        let model = match state.model.lock() {
+            Ok(m) => m,
+            Err(_) => {
+                write_response(
+                    &mut stream,
+                    "500 Internal Server Error",
+                    "text/plain; charset=utf-8",
+                    b"error",
+                );
+                return None;
+            }
+        };
+        (
+            model.settings.remote_control_enabled,
+            model
+                .settings
+                .team_stats_enabled
+                .then(|| model.settings.team_stats_key.clone()),
+            model.settings.remote_pairing_required,
+            model.settings.remote_read_only,
+        )
+    };
+
+    if !remote_enabled {
+        write_response(
+            &mut stream,
+            "404 Not Found",
+            "text/plain; charset=utf-8",
+            b"not found",
+        );
+        return None;
+    }
+
+    if read_only && !remote_read_only_allows(method, path) {
+        write_response(
+            &mut stream,
+            "403 Forbidden",
+            "text/plain; charset=utf-8",
+            b"remote control is read-only",
+        );
+        return None;
+    }
+
+    // The API description is public, like the page: it holds no data.
+    if method.eq_ignore_ascii_case("GET") && path == "/api/openapi.json" {
+        let spec = remote_openapi(&app.package_info().version.to_string());
+        write_response_encoded(
+            &mut stream,
+            "200 OK",
+            "application/json; charset=utf-8",
+            &serde_json::to_vec(&spec).unwrap_or_default(),
+            encoding,
+            keep_alive,
+        );
+        return keep_alive.then_some(stream);
+    }
+
+    // Serve the control page and its PWA files without requiring auth, so users can load it
+    // and paste a token. All API endpoints remain token-protected.
+    if method.eq_ignore_ascii_case("GET") {
+        let dir = app.path().resource_dir().ok().map(|dir| dir.join("remote"));
+        if let Some((body, content_type)) = load_remote_asset(dir.as_deref(), path) {
+            write_response_encoded(
+                &mut stream,
+                "200 OK",
+                content_type,
+                &body,
+                // PNGs are already compressed.
+                encoding.filter(|_| !content_type.starts_with("image/")),
+                keep_alive,
+            );
+            return keep_alive.then_some(stream);
+        }
+    }
+
+    // Token-free aliases for Raycast/Alfred scripts. Only callers on this machine are
+    // accepted, and never browser pages (they always send Origin), so no-auth POSTs
+    // cannot be triggered cross-site.
+    let local_route = path.strip_prefix("/api/local");
+    if let Some(rest) = local_route {
+        if !LOCAL_API_ROUTES.contains(&rest) {
+            write_response(
+                &mut stream,
+                "404 Not Found",
+                "text/plain; charset=utf-8",
+                b"not found",
+            );
+            return None;
+        }
+        let from_loopback = stream
+            .peer_addr()
+            .map(|addr| addr.ip().is_loopback())
+            .unwrap_or(false);
+        let host_ok = header_value(req.headers, "Host").is_some_and(is_loopback_host);
+        if !from_loopback || !host_ok || header_value(req.headers, "Origin").is_some() {
+            write_response(
+                &mut stream,
+                "403 Forbidden",
+                "text/plain; charset=utf-8",
+                b"forbidden",
+            );
+            return None;
+        }
+    }
+    let routed_path;
+    let path = match local_route {
+        Some(rest) => {
+            routed_path = format!("/api{rest}");
+            routed_path.as_str()
+        }
+        None => path,
+    };
+
+    // The team key only unlocks aggregated daily totals and nothing else.
+    if method == "GET" && path == "/api/team/daily" {
+        let key_got = header_value(req.headers, "X-Pomodoro-Team-Key")
+            .or_else(|| parse_query_param(query, "key"))
+            .unwrap_or("");
+        if !team_key.is_some_and(|k| !k.is_empty() && k == key_got) {
+            auth_result(false);
+            write_response(
+                &mut stream,
+                "401 Unauthorized",
+                "text/plain; charset=utf-8",
+                b"unauthorized",
+            );
+            return None;
+        }
+        let days = parse_query_param(query, "days")
+            .and_then(|v| v.parse::<i64>().ok())
+            .unwrap_or(30);
+        let state = app.state::<AppState>();
+        if let Ok(model) = state.model.lock() {
+            let ip = peer_ip.map(|ip| ip.to_string()).unwrap_or_default();
+            let _ = record_remote_audit(&model.conn, now_ts(), &ip, route, REMOTE_AUDIT_TEAM_KEY);
+        }
+        let result = state
+            .model
+            .lock()
+            .map_err(|e| e.to_string())
+            .and_then(|model| team_daily_totals(&model.conn, days, now_ts()));
+        let body = match result {
+            Ok(v) => serde_json::to_vec(&serde_json::json!({ "days": v })).unwrap_or_default(),
+            Err(e) => serde_json::to_vec(&serde_json::json!({ "error": e })).unwrap_or_default(),
+        };
+        write_response_encoded(
+            &mut stream,
+            "200 OK",
+            "application/json; charset=utf-8",
+            &body,
+            encoding,
+            keep_alive,
+        );
+        return keep_alive.then_some(stream);
+    }
+
+    let token_got = header_value(req.headers, "X-Pomodoro-Token")
+        .or_else(|| parse_query_param(query, "token"))
+        .unwrap_or("")
+        .to_string();
+    let (token_id, token_scopes) = if local_route.is_some() {
+        (None, Vec::new())
+    } else {
+        let state = app.state::<AppState>();
+        let found = state
+            .model
+            .lock()
+            .map_err(|e| e.to_string())
+            .and_then(|model| {
+                let found = lookup_remote_token(&model.conn, &token_got)?;
+                if let Some((id, _)) = &found {
+                    // Best effort: a read-only database must not lock the remote out.
+                    let _ = touch_remote_token(&model.conn, *id, now_ts());
+                }
+                Ok(found)
+            });
+        match found {
+            Ok(Some((token_id, scopes))) => {
+                auth_result(true);
+                if pairing_required {
+                    let device_id = header_value(req.headers, "X-Pomodoro-Device")
+                        .or_else(|| parse_query_param(query, "device"));
+                    let user_agent = header_value(req.headers, "User-Agent").unwrap_or("");
+                    let fingerprint = remote_device_fingerprint(token_id, device_id, user_agent);
+                    let ip = peer_ip.map(|ip| ip.to_string()).unwrap_or_default();
+                    let checked = state
+                        .model
+                        .lock()
+                        .map_err(|e| e.to_string())
+                        .and_then(|model| {
+                            check_remote_device(
+                                &model.conn,
+                                token_id,
+                                &fingerprint,
+                                &remote_device_label(user_agent),
+                                &ip,
+                                now_ts(),
+                            )
+                        });
+                    let refusal = match checked {
+                        Ok((RemoteDeviceStatus::Approved, _)) => None,
+                        Ok((RemoteDeviceStatus::Pending, new_device)) => {
+                            if let Some(device) = new_device {
+                                prompt_remote_pairing(app, device);
+                            }
+                            Some("this device is waiting for approval on the desktop")
+                        }
+                        Ok((RemoteDeviceStatus::Rejected, _)) => {
+                            Some("this device was rejected on the desktop")
+                        }
+                        Err(_) => Some("device check failed"),
+                    };
+                    if let Some(reason) = refusal {
+                        write_response(
+                            &mut stream,
+                            "403 Forbidden",
+                            "text/plain; charset=utf-8",
+                            reason.as_bytes(),
+                        );
+                        return None;
+                    }
+                }
+                (Some(token_id), scopes)
+            }
+            _ => {
+                auth_result(false);
+                write_response(
+                    &mut stream,
+                    "401 Unauthorized",
+                    "text/plain; charset=utf-8",
+                    b"unauthorized",
+                );
+                return None;
+            }
+        }
+    };
+    let scope = remote_route_scope(method, path);
+    if local_route.is_none() && !token_scopes.contains(&scope) {
+        write_response(
+            &mut stream,
+            "403 Forbidden",
+            "text/plain; charset=utf-8",
+            format!("token lacks the {} scope", scope.as_str()).as_bytes(),
+        );
+        return None;
+    }
+    if let Some(token_id) = token_id {
+        let ip = peer_ip.map(|ip| ip.to_string()).unwrap_or_default();
+        let state = app.state::<AppState>();
+        if let Ok(model) = state.model.lock() {
+            // Best effort, like touch_remote_token.
+            let _ = remote_token_name(&model.conn, token_id)
+                .and_then(|name| record_remote_audit(&model.conn, now_ts(), &ip, route, &name));
+        }
+    }
+
+    // Live events for the remote page. Browsers cannot set headers on a WebSocket, so the
+    // token arrives as `?token=`, checked above like for any other GET.
+    if method == "GET" && path == "/ws" && local_route.is_none() {
+        let upgrade = header_value(req.headers, "Upgrade")
+            .is_some_and(|v| v.eq_ignore_ascii_case("websocket"));
+        let key = header_value(req.headers, "Sec-WebSocket-Key").filter(|_| {
+            header_value(req.headers, "Sec-WebSocket-Version").map(str::trim) == Some("13")
+        });
+        let Some(key) = key.filter(|_| upgrade) else {
+            write_response(
+                &mut stream,
+                "400 Bad Request",
+                "text/plain; charset=utf-8",
+                b"websocket upgrade required",
+            );
+            return None;
+        };
+        let open = &app.state::<AppState>().remote_websockets;
+        let Some(slot) = StreamSlot::acquire(open, REMOTE_MAX_WEBSOCKETS) else {
+            write_response(
+                &mut stream,
+                "503 Service Unavailable",
+                "text/plain; charset=utf-8",
+                b"too many open websockets",
+            );
+            return None;
+        };
+        let handshake = format!(
+            "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+            websocket_accept_key(key.trim())
+        );
+        if stream.write_all(handshake.as_bytes()).is_ok() {
+            let app = app.clone();
+            thread::spawn(move || {
+                let _slot = slot;
+                remote_websocket_session(app, stream, token_got)
+            });
+        }
+        return None;
+    }
+
+    // Server-Sent Events for clients without WebSocket support; EventSource cannot set
+    // headers either, so the token comes as `?token=` too.
+    if method == "GET" && path == "/api/events" && local_route.is_none() {
+        let open = &app.state::<AppState>().remote_event_streams;
+        let Some(slot) = StreamSlot::acquire(open, REMOTE_MAX_EVENT_STREAMS) else {
+            write_response(
+                &mut stream,
+                "503 Service Unavailable",
+                "text/plain; charset=utf-8",
+                b"too many open event streams",
+            );
+            return None;
+        };
+        let headers = "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\nConnection: keep-alive\r\nAccess-Control-Allow-Origin: *\r\n\r\n";
+        if stream.write_all(headers.as_bytes()).is_ok() {
+            let app = app.clone();
+            thread::spawn(move || {
+                let _slot = slot;
+                remote_sse_session(app, stream, token_got)
+            });
+        }
+        return None;
+    }
+
+    // API routes.
+    let state = app.state::<AppState>();
+    let json = match (method, path) {
+        ("GET", "/api/state") => match timer_get_state_inner(state.inner()) {
+            Ok(v) => serde_json::to_vec(&v).ok(),
+            Err(e) => {
+                Some(serde_json::to_vec(&serde_json::json!({ "error": e })).unwrap_or_default())
+            }
+        },
+        ("POST", "/api/toggle") => {
+            let current = timer_get_state_inner(state.inner());
+            let next = match current {
+                Ok(st) => {
+                    if st.is_running {
+                        timer_pause_inner(app)
+                    } else if st.started_at.is_some() {
+                        timer_resume_inner(app, None)
+                    } else {
+                        timer_start_inner(app, None)
+                    }
+                }
+                Err(e) => Err(e),
+            };
+            match next {
+                Ok(v) => serde_json::to_vec(&v).ok(),
+                Err(e) => {
+                    Some(serde_json::to_vec(&serde_json::json!({ "error": e })).unwrap_or_default())
+                }
+            }
+        }
+        // An empty body starts with the current context, like the toggle.
+        ("POST", "/api/start") => {
+            let result = parse_optional_body::<StartTimerRequest>(&body)
+                .map_err(|e| format!("invalid start request: {e}"))
+                .and_then(|payload| timer_start_inner(app, payload));
+            match result {
+                Ok(v) => serde_json::to_vec(&v).ok(),
+                Err(e) => {
+                    Some(serde_json::to_vec(&serde_json::json!({ "error": e })).unwrap_or_default())
+                }
+            }
+        }
+        ("POST", "/api/context") => {
+            let result = serde_json::from_slice::<StartTimerRequest>(&body)
+                .map_err(|e| format!("invalid context: {e}"))
+                .and_then(|payload| timer_set_context(app.clone(), payload));
+            match result {
+                Ok(v) => serde_json::to_vec(&v).ok(),
+                Err(e) => {
+                    Some(serde_json::to_vec(&serde_json::json!({ "error": e })).unwrap_or_default())
+                }
+            }
+        }
+        ("POST", "/api/pause") => match timer_pause_inner(app) {
+            Ok(v) => serde_json::to_vec(&v).ok(),
+            Err(e) => {
+                Some(serde_json::to_vec(&serde_json::json!({ "error": e })).unwrap_or_default())
+            }
+        },
+        ("POST", "/api/resume") => {
+            let payload = serde_json::from_slice::<StartTimerRequest>(&body).ok();
+            match timer_resume_inner(app, payload) {
+                Ok(v) => serde_json::to_vec(&v).ok(),
+                Err(e) => {
+                    Some(serde_json::to_vec(&serde_json::json!({ "error": e })).unwrap_or_default())
+                }
+            }
+        }
+        ("GET", "/api/push/key") => {
+            let result = state
+                .model
+                .lock()
+                .map_err(|e| e.to_string())
+                .and_then(|model| load_or_create_vapid_keys(&model.conn));
+            match result {
+                Ok(keys) => {
+                    serde_json::to_vec(&serde_json::json!({ "publicKey": keys.public_key })).ok()
+                }
+                Err(e) => {
+                    Some(serde_json::to_vec(&serde_json::json!({ "error": e })).unwrap_or_default())
+                }
+            }
+        }
+        ("POST", "/api/push/subscribe") => {
+            let result = serde_json::from_slice::<PushSubscriptionInput>(&body)
+                .map_err(|e| format!("invalid subscription: {e}"))
+                .and_then(|input| {
+                    let token_id = token_id.ok_or_else(|| "a token is required".to_string())?;
+                    let model = state.model.lock().map_err(|e| e.to_string())?;
+                    save_push_subscription(&model.conn, &input, token_id, now_ts())
+                });
+            match result {
+                Ok(()) => serde_json::to_vec(&serde_json::json!({ "subscribed": true })).ok(),
+                Err(e) => {
+                    Some(serde_json::to_vec(&serde_json::json!({ "error": e })).unwrap_or_default())
+                }
+            }
+        }
+        ("POST", "/api/push/unsubscribe") => {
+            let result = serde_json::from_slice::<serde_json::Value>(&body)
+                .map_err(|e| format!("invalid subscription: {e}"))
+                .and_then(|input| {
+                    let endpoint = input["endpoint"].as_str().unwrap_or_default().to_string();
+                    let token_id = token_id.ok_or_else(|| "a token is required".to_string())?;
+                    let model = state.model.lock().map_err(|e| e.to_string())?;
+                    delete_push_subscription(&model.conn, &endpoint, token_id)
+                });
+            match result {
+                Ok(_) => serde_json::to_vec(&serde_json::json!({ "subscribed": false })).ok(),
+                Err(e) => {
+                    Some(serde_json::to_vec(&serde_json::json!({ "error": e })).unwrap_or_default())
+                }
+            }
+        }
+        ("GET", "/api/health") => {
+            let version = app.package_info().version.to_string();
+            serde_json::to_vec(&remote_health(state.inner(), &version, now_ts())).ok()
+        }
+        ("GET", "/api/health/mindful") => {
+            // `since` predates the cursor: everything that ended after that second.
+            let since = parse_query_param(query, "since").and_then(|v| v.parse::<i64>().ok());
+            let kind = parse_query_param(query, "kind").unwrap_or("breaks");
+            let result = parse_query_param(query, "cursor")
+                .map(|cursor| parse_session_cursor(&decode_query_value(cursor)))
+                .transpose()
+                .map(|cursor| cursor.or(since.map(|since| (since, i64::MAX))))
+                .and_then(|after| {
+                    let model = state.model.lock().map_err(|e| e.to_string())?;
+                    health_export(&model.conn, after, kind)
+                });
+            match result {
+                Ok(v) => serde_json::to_vec(&v).ok(),
+                Err(e) => {
+                    Some(serde_json::to_vec(&serde_json::json!({ "error": e })).unwrap_or_default())
+                }
+            }
+        }
+        ("GET", "/api/sessions") => {
+            let result = parse_remote_list_query(query).and_then(|list_query| {
+                let model = state.model.lock().map_err(|e| e.to_string())?;
+                fetch_sessions_page(&model.conn, &list_query)
+            });
+            match result {
+                Ok(v) => serde_json::to_vec(&v).ok(),
+                Err(e) => {
+                    Some(serde_json::to_vec(&serde_json::json!({ "error": e })).unwrap_or_default())
+                }
+            }
+        }
+        // Analytics honour the configured exclusions, like the desktop charts.
+        ("GET", "/api/summary") => {
+            let result = parse_remote_list_query(query).and_then(|list_query| {
+                let model = state.model.lock().map_err(|e| e.to_string())?;
+                let range = with_exclusions(list_query.range, &model.settings);
+                compute_summary(&model.conn, &model.settings, &range)
+            });
+            match result {
+                Ok(v) => serde_json::to_vec(&v).ok(),
+                Err(e) => {
+                    Some(serde_json::to_vec(&serde_json::json!({ "error": e })).unwrap_or_default())
+                }
+            }
+        }
+        ("GET", "/api/timeseries") => {
+            let result = parse_remote_list_query(query).and_then(|list_query| {
+                let model = state.model.lock().map_err(|e| e.to_string())?;
+                let range = with_exclusions(list_query.range, &model.settings);
+                fetch_timeseries(&model.conn, &range)
+            });
+            match result {
+                Ok(v) => serde_json::to_vec(&v).ok(),
+                Err(e) => {
+                    Some(serde_json::to_vec(&serde_json::json!({ "error": e })).unwrap_or_default())
+                }
+            }
+        }
+        ("GET", "/api/settings") => {
+            let result = state
+                .model
+                .lock()
+                .map_err(|e| e.to_string())
+                .and_then(|model| remote_settings_view(&model.settings));
+            match result {
+                Ok(v) => serde_json::to_vec(&v).ok(),
+                Err(e) => {
+                    Some(serde_json::to_vec(&serde_json::json!({ "error": e })).unwrap_or_default())
+                }
+            }
+        }
+        ("PATCH", "/api/settings") => {
+            let result = serde_json::from_slice::<AppSettingsPatch>(&body)
+                .map_err(|e| format!("invalid settings: {e}"))
+                .and_then(|patch| {
+                    check_remote_settings_patch(&patch)?;
+                    settings_update(app.clone(), patch)
+                })
+                .and_then(|settings| remote_settings_view(&settings));
+            match result {
+                Ok(v) => serde_json::to_vec(&v).ok(),
+                Err(e) => {
+                    Some(serde_json::to_vec(&serde_json::json!({ "error": e })).unwrap_or_default())
+                }
+            }
+        }
+        ("GET", "/api/today") => {
+            let result = state
+                .model
+                .lock()
+                .map_err(|e| e.to_string())
+                .and_then(|model| remote_today(&model.conn, &model.timer, now_ts()));
+            match result {
+                Ok(v) => serde_json::to_vec(&v).ok(),
+                Err(e) => {
+                    Some(serde_json::to_vec(&serde_json::json!({ "error": e })).unwrap_or_default())
+                }
+            }
+        }
+        ("GET", "/api/projects") => {
+            let result = state
+                .model
+                .lock()
+                .map_err(|e| e.to_string())
+                .and_then(|model| fetch_projects(&model.conn));
+            match result {
+                Ok(v) => serde_json::to_vec(&v).ok(),
+                Err(e) => {
+                    Some(serde_json::to_vec(&serde_json::json!({ "error": e })).unwrap_or_default())
+                }
+            }
+        }
+        // Same body as `projects_upsert`: no `id` creates, an `id` updates.
+        ("POST", "/api/projects") => {
+            let result = serde_json::from_slice::<ProjectInput>(&body)
+                .map_err(|e| format!("invalid project: {e}"))
+                .and_then(|input| projects_upsert(app.clone(), input));
+            match result {
+                Ok(v) => serde_json::to_vec(&v).ok(),
+                Err(e) => {
+                    Some(serde_json::to_vec(&serde_json::json!({ "error": e })).unwrap_or_default())
+                }
+            }
+        }
+        ("GET", "/api/tags") => {
+            let result = state
+                .model
+                .lock()
+                .map_err(|e| e.to_string())
+                .and_then(|model| fetch_tags(&model.conn));
+            match result {
+                Ok(v) => serde_json::to_vec(&v).ok(),
+                Err(e) => {
+                    Some(serde_json::to_vec(&serde_json::json!({ "error": e })).unwrap_or_default())
+                }
+            }
+        }
+        ("POST", "/api/tags") => {
+            let result = serde_json::from_slice::<TagInput>(&body)
+                .map_err(|e| format!("invalid tag: {e}"))
+                .and_then(|input| tags_upsert(app.clone(), input));
+            match result {
+                Ok(v) => serde_json::to_vec(&v).ok(),
+                Err(e) => {
+                    Some(serde_json::to_vec(&serde_json::json!({ "error": e })).unwrap_or_default())
+                }
+            }
+        }
+        ("POST", "/api/skip") => match timer_skip_inner(app) {
+            Ok(v) => serde_json::to_vec(&v).ok(),
+            Err(e) => {
+                Some(serde_json::to_vec(&serde_json::json!({ "error": e })).unwrap_or_default())
+            }
+        },
+        _ => None,
+    };
 
     match json {
-        Some(body) if !body.is_empty() => write_response(
-            &mut stream,
-            "200 OK",
-            "application/json; charset=utf-8",
-            &body,
+        Some(body) if !body.is_empty() => {
+            write_response_encoded(
+                &mut stream,
+                "200 OK",
+                "application/json; charset=utf-8",
+                &body,
+                encoding,
+                keep_alive,
+            );
+            keep_alive.then_some(stream)
+        }
+        Some(_) => {
+            write_response(
+                &mut stream,
+                "500 Internal Server Error",
+                "text/plain; charset=utf-8",
+                b"error",
+            );
+            None
+        }
+        None => {
+            write_response(
+                &mut stream,
+                "404 Not Found",
+                "text/plain; charset=utf-8",
+                b"not found",
+            );
+            None
+        }
+    }
+}
+
+// Serves requests on one connection until the client closes it, asks to, goes idle for
+// REMOTE_KEEP_ALIVE_IDLE, or has sent REMOTE_KEEP_ALIVE_MAX requests. Connections waiting
+// for a worker, or the server stopping, end keep-alive after the current response so an idle
+// phone cannot hold a worker while others queue.
+fn remote_handle_connection(
+    app: &AppHandle,
+    mut stream: std::net::TcpStream,
+    running: &AtomicBool,
+    queued: &AtomicUsize,
+) {
+    let _ = stream.set_write_timeout(Some(Duration::from_secs(2)));
+    let mut pending = Vec::new();
+    for served in 0..REMOTE_KEEP_ALIVE_MAX {
+        if served > 0 && pending.is_empty() {
+            let _ = stream.set_read_timeout(Some(REMOTE_KEEP_ALIVE_IDLE));
+            if !matches!(stream.peek(&mut [0u8; 1]), Ok(n) if n > 0) {
+                return;
+            }
+        }
+        let started = Instant::now();
+        let mut route = "unparsed".to_string();
+        let reuse = served + 1 < REMOTE_KEEP_ALIVE_MAX
+            && running.load(Ordering::SeqCst)
+            && queued.load(Ordering::SeqCst) == 0;
+        let next = remote_handle_request(app, stream, &mut pending, reuse, &mut route);
+        if let Ok(mut metrics) = app.state::<AppState>().remote_metrics.lock() {
+            record_remote_request(&mut metrics, &route, started.elapsed());
+        }
+        match next {
+            Some(kept) => stream = kept,
+            None => return,
+        }
+    }
+}
+
+// HTTP/1.1 connections persist unless the client sends `Connection: close`; HTTP/1.0 ones
+// only when it asks for `keep-alive`.
+fn wants_keep_alive(version: Option<u8>, connection: Option<&str>) -> bool {
+    let has = |token: &str| {
+        connection.is_some_and(|v| v.split(',').any(|t| t.trim().eq_ignore_ascii_case(token)))
+    };
+    match version {
+        Some(1) => !has("close"),
+        _ => has("keep-alive"),
+    }
+}
+
+const REMOTE_WORKERS: usize = 4;
+// Accepted connections waiting for a worker; beyond this new ones get a 503.
+const REMOTE_QUEUE: usize = 32;
+// Whole-request budget for reading headers and body, on top of the per-read timeout, so a
+// client trickling bytes cannot hold a worker.
+const REMOTE_READ_DEADLINE: Duration = Duration::from_secs(5);
+const REMOTE_MAX_HEAD_BYTES: usize = 8192;
+// How long a kept-alive connection may sit between requests, and how many it may carry.
+const REMOTE_KEEP_ALIVE_IDLE: Duration = Duration::from_secs(5);
+const REMOTE_KEEP_ALIVE_MAX: usize = 100;
+
+// Accepts on this thread and hands connections to REMOTE_WORKERS threads, so one slow client
+// only ties up its own worker. Clearing `stop` ends accepting; workers finish the request in
+// hand and exit, and this function returns once they have. WebSocket and SSE sessions run on
+// their own threads and close themselves when remote control is turned off.
+fn remote_server_loop(app: AppHandle, listeners: Vec<TcpListener>, stop: Arc<AtomicBool>) {
+    remote_accept_loop(
+        listeners,
+        stop,
+        REMOTE_WORKERS,
+        REMOTE_QUEUE,
+        move |stream, running, queued| remote_handle_connection(&app, stream, running, queued),
+    );
+}
+
+fn remote_accept_loop<H>(
+    listeners: Vec<TcpListener>,
+    stop: Arc<AtomicBool>,
+    workers: usize,
+    queue: usize,
+    handle: H,
+) where
+    H: Fn(std::net::TcpStream, &AtomicBool, &AtomicUsize) + Send + Sync + 'static,
+{
+    let (tx, rx) = std::sync::mpsc::sync_channel::<std::net::TcpStream>(queue);
+    let rx = Arc::new(Mutex::new(rx));
+    let queued = Arc::new(AtomicUsize::new(0));
+    let handle = Arc::new(handle);
+    let workers: Vec<_> = (0..workers)
+        .map(|_| {
+            let handle = handle.clone();
+            let rx = rx.clone();
+            let running = stop.clone();
+            let queued = queued.clone();
+            thread::spawn(move || remote_worker(handle, rx, running, queued))
+        })
+        .collect();
+
+    // The listeners (IPv4, and IPv6 when bound) are non-blocking, so one loop polls them all
+    // and only sleeps when none had a connection waiting.
+    while stop.load(Ordering::SeqCst) {
+        let mut idle = true;
+        for listener in &listeners {
+            match listener.accept() {
+                Ok((stream, _)) => {
+                    idle = false;
+                    queued.fetch_add(1, Ordering::SeqCst);
+                    if let Err(std::sync::mpsc::TrySendError::Full(mut stream)) =
+                        tx.try_send(stream)
+                    {
+                        queued.fetch_sub(1, Ordering::SeqCst);
+                        write_response(
+                            &mut stream,
+                            "503 Service Unavailable",
+                            "text/plain; charset=utf-8",
+                            b"busy",
+                        );
+                    }
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {}
+                Err(_) => {
+                    thread::sleep(Duration::from_millis(200));
+                }
+            }
+        }
+        if idle {
+            thread::sleep(Duration::from_millis(50));
+        }
+    }
+
+    drop(tx);
+    for worker in workers {
+        let _ = worker.join();
+    }
+}
+
+// Exits once the accept loop drops its sender and the queue is drained.
+fn remote_worker<H>(
+    handle: Arc<H>,
+    rx: Arc<Mutex<std::sync::mpsc::Receiver<std::net::TcpStream>>>,
+    running: Arc<AtomicBool>,
+    queued: Arc<AtomicUsize>,
+) where
+    H: Fn(std::net::TcpStream, &AtomicBool, &AtomicUsize),
+{
+    loop {
+        let next = match rx.lock() {
+            Ok(rx) => rx.recv(),
+            Err(_) => return,
+        };
+        let Ok(stream) = next else {
+            return;
+        };
+        queued.fetch_sub(1, Ordering::SeqCst);
+        // Accepted sockets inherit the listener's non-blocking mode on some platforms.
+        let _ = stream.set_nonblocking(false);
+        handle(stream, &running, &queued);
+    }
+}
+
+const MUSIC_MAX_TRACK_BYTES: u64 = 100 * 1024 * 1024;
+
+fn music_mime(path: &str) -> Option<&'static str> {
+    let extension = std::path::Path::new(path)
+        .extension()?
+        .to_str()?
+        .to_ascii_lowercase();
+    match extension.as_str() {
+        "mp3" => Some("audio/mpeg"),
+        "m4a" | "aac" => Some("audio/mp4"),
+        "wav" => Some("audio/wav"),
+        "ogg" | "oga" => Some("audio/ogg"),
+        "flac" => Some("audio/flac"),
+        _ => None,
+    }
+}
+
+fn fetch_music_tracks(conn: &Connection) -> AppResult<Vec<MusicTrack>> {
+    let mut stmt = conn
+        .prepare("SELECT id, path, title, volume FROM music_tracks ORDER BY id")
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok(MusicTrack {
+                id: row.get(0)?,
+                path: row.get(1)?,
+                title: row.get(2)?,
+                volume: row.get(3)?,
+            })
+        })
+        .map_err(|e| e.to_string())?;
+    rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+}
+
+fn fetch_plan_items(conn: &Connection) -> AppResult<Vec<PlanItem>> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, title, project_id, tag_ids, created_at FROM plan_items
+             WHERE started_at IS NULL ORDER BY id",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([], |row| {
+            let tag_ids: String = row.get(3)?;
+            Ok(PlanItem {
+                id: row.get(0)?,
+                title: row.get(1)?,
+                project_id: row.get(2)?,
+                tag_ids: serde_json::from_str(&tag_ids).unwrap_or_default(),
+                created_at: row.get(4)?,
+            })
+        })
+        .map_err(|e| e.to_string())?;
+    rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+}
+
+// Starts a fresh focus with the first queued item's project and tags. A focus already under way,
+// or a break, is left alone so the queue never cuts into one.
+fn start_next_plan_item(model: &mut AppModel) -> AppResult<TimerState> {
+    if model.timer.phase != TimerPhase::Focus || model.timer.started_at.is_some() {
+        return Err("the plan queue starts the next focus once the current phase ends".into());
+    }
+    let item = fetch_plan_items(&model.conn)?
+        .into_iter()
+        .next()
+        .ok_or("the plan queue is empty")?;
+    model
+        .conn
+        .execute(
+            "UPDATE plan_items SET started_at = ?1 WHERE id = ?2",
+            params![now_ts(), item.id],
+        )
+        .map_err(|e| e.to_string())?;
+    let payload = StartTimerRequest {
+        project_id: Some(item.project_id),
+        tag_ids: Some(item.tag_ids),
+        duration_minutes: None,
+    };
+    start_timer(model, Some(payload), true)
+}
+
+// In order, the track after `current`, wrapping; shuffled, any track but `current`.
+fn pick_next_track(
+    tracks: &[MusicTrack],
+    current: Option<i64>,
+    shuffle: bool,
+) -> Option<&MusicTrack> {
+    if shuffle && tracks.len() > 1 {
+        let others: Vec<&MusicTrack> = tracks.iter().filter(|t| Some(t.id) != current).collect();
+        return Some(others[rand::thread_rng().gen_range(0..others.len())]);
+    }
+    match current.and_then(|id| tracks.iter().position(|t| t.id == id)) {
+        Some(i) => tracks.get((i + 1) % tracks.len()),
+        None => tracks.first(),
+    }
+}
+
+fn music_play_next(model: &mut AppModel) -> AppResult<Option<MusicEvent>> {
+    let tracks = fetch_music_tracks(&model.conn)?;
+    let Some(track) = pick_next_track(&tracks, model.music_playing, model.settings.music_shuffle)
+    else {
+        return Ok(model.music_playing.take().map(|_| MusicEvent::Stop));
+    };
+    model.music_playing = Some(track.id);
+    Ok(Some(MusicEvent::Play(MusicPlayEvent {
+        track: track.clone(),
+        loop_track: tracks.len() == 1,
+    })))
+}
+
+// Music follows the timer: it starts when a focus phase runs and stops on pause or break.
+fn music_sync(model: &mut AppModel) -> AppResult<Option<MusicEvent>> {
+    let should_play = model.settings.music_enabled
+        && model.timer.is_running
+        && model.timer.phase == TimerPhase::Focus;
+    match (should_play, model.music_playing) {
+        (true, None) => music_play_next(model),
+        (false, Some(_)) => {
+            model.music_playing = None;
+            Ok(Some(MusicEvent::Stop))
+        }
+        _ => Ok(None),
+    }
+}
+
+fn emit_music_event(app: &AppHandle, event: MusicEvent) {
+    let _ = match event {
+        MusicEvent::Play(play) => app.emit("music://play", &play),
+        MusicEvent::Stop => app.emit("music://stop", ()),
+    };
+}
+
+const BREATHING_STEPS: [&str; 4] = ["inhale", "hold", "exhale", "hold_out"];
+
+fn breathing_timings(settings: &AppSettings) -> Option<[i64; 4]> {
+    match settings.breathing_pattern.as_str() {
+        "box" => Some([4, 4, 4, 4]),
+        "relax" => Some([4, 7, 8, 0]),
+        "coherent" => Some([5, 0, 5, 0]),
+        "custom" => settings.breathing_custom.clone().try_into().ok(),
+        _ => None,
+    }
+}
+
+// The step `elapsed` seconds into a pattern that repeats from the start of the break.
+// Zero-length holds are never reported.
+fn breathing_step_at(timings: [i64; 4], elapsed: i64, now: i64) -> Option<BreathingStep> {
+    let cycle_len: i64 = timings.iter().sum();
+    if cycle_len <= 0 || elapsed < 0 {
+        return None;
+    }
+    let cycle = elapsed / cycle_len;
+    let mut offset = elapsed % cycle_len;
+    for (step_index, seconds) in timings.into_iter().enumerate() {
+        if offset < seconds {
+            return Some(BreathingStep {
+                step: BREATHING_STEPS[step_index].to_string(),
+                step_index,
+                step_seconds: seconds,
+                ends_at: now + seconds - offset,
+                cycle,
+            });
+        }
+        offset -= seconds;
+    }
+    None
+}
+
+fn current_breathing_step(
+    settings: &AppSettings,
+    timer: &TimerState,
+    now: i64,
+) -> Option<BreathingStep> {
+    if !timer.is_running || timer.phase == TimerPhase::Focus {
+        return None;
+    }
+    let timings = breathing_timings(settings)?;
+    let remaining = timer
+        .target_ends_at
+        .map_or(timer.remaining_seconds, |t| t - now);
+    breathing_step_at(timings, timer.phase_total_seconds - remaining, now)
+}
+
+// Returns the step to emit when it changed since the last call.
+fn breathing_update(model: &mut AppModel, now: i64) -> Option<BreathingStep> {
+    let step = current_breathing_step(&model.settings, &model.timer, now);
+    let key = step.as_ref().map(|s| (s.cycle, s.step_index));
+    if key == model.breathing_emitted {
+        return None;
+    }
+    model.breathing_emitted = key;
+    step
+}
+
+const WORKER_RESTART_DELAY: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WorkerRestartedEvent {
+    worker: String,
+    restarts: u32,
+    message: String,
+    at: i64,
+}
+
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown panic".to_string()
+    }
+}
+
+// Runs `work` on its own thread and starts it again after `delay` whenever it panics.
+// Returns once `work` finishes without panicking.
+fn supervise_worker<W, P>(name: &str, delay: Duration, work: W, on_panic: P)
+where
+    W: Fn() + Send + Sync + 'static,
+    P: Fn(u32, String),
+{
+    let work = Arc::new(work);
+    let mut restarts = 0;
+    loop {
+        let work = Arc::clone(&work);
+        let handle = thread::Builder::new()
+            .name(format!("{name}-worker"))
+            .spawn(move || work());
+        let outcome = match handle {
+            Ok(handle) => handle.join(),
+            Err(error) => {
+                eprintln!("{name} worker failed to start: {error}");
+                thread::sleep(delay);
+                continue;
+            }
+        };
+        match outcome {
+            Ok(()) => return,
+            Err(payload) => {
+                restarts += 1;
+                let message = panic_message(payload.as_ref());
+                eprintln!("{name} worker panicked (restart {restarts}): {message}");
+                on_panic(restarts, message);
+                thread::sleep(delay);
+            }
+        }
+    }
+}
+
+fn spawn_timer_worker(app: AppHandle) {
+    thread::spawn(move || {
+        let worker_app = app.clone();
+        supervise_worker(
+            "timer",
+            WORKER_RESTART_DELAY,
+            move || run_timer_worker(&worker_app),
+            |restarts, message| {
+                // A panic while the model was locked poisons it for every command too.
+                app.state::<AppState>().model.clear_poison();
+                let _ = app.emit(
+                    "system://worker-restarted",
+                    WorkerRestartedEvent {
+                        worker: "timer".to_string(),
+                        restarts,
+                        message,
+                        at: now_ts(),
+                    },
+                );
+            },
+        );
+    });
+}
+
+fn run_timer_worker(app: &AppHandle) {
+    let mut watch_console = false;
+    let mut console_probed_at = 0;
+    let mut dim_job = None;
+    loop {
+        thread::sleep(Duration::from_secs(1));
+        let dimmed = dim_job.take().map(|(dim, wanted, percent)| {
+            screen_dim_step(
+                dim,
+                wanted,
+                percent,
+                display_brightness,
+                set_display_brightness,
+            )
+        });
+        // Spawning ioreg can take a while, so it runs before the model is locked.
+        let console_probe = if watch_console && now_ts() >= console_probed_at + DETACH_POLL_SECONDS
+        {
+            console_probed_at = now_ts();
+            Some(console_detached())
+        } else {
+            None
+        };
+        app.state::<AppState>()
+            .timer_heartbeat
+            .store(now_ts(), Ordering::SeqCst);
+
+        let mut should_emit = false;
+        let mut emit_state: Option<TimerState> = None;
+        let mut session_event: Option<SessionRecord> = None;
+        let mut phase_event: Option<PhaseCompletedEvent> = None;
+        let breathing_event: Option<BreathingStep>;
+        let mut data_changed: Option<Mutation> = None;
+
+        {
+            let state = app.state::<AppState>();
+            let lock = state.model.lock();
+            let mut model = match lock {
+                Ok(guard) => guard,
+                Err(_) => continue,
+            };
+
+            if model.queued_notification.is_some() && !dnd_active() {
+                if let Some(body) = model.queued_notification.take() {
+                    show_notification(app, &body);
+                }
+            }
+
+            if let Some(dim) = dimmed {
+                set_screen_dim(&mut model, dim);
+            }
+            dim_job = screen_dim_job(&model);
+            watch_console = watches_console_session(&model);
+
+            match flush_read_only(&mut model, now_ts()) {
+                Ok(Some(flushed)) => {
+                    let _ = app.emit("db://read-only", read_only_status(&model, flushed));
+                }
+                Ok(None) => {}
+                Err(error) => eprintln!("read-only queue flush failed: {error}"),
+            }
+
+            match music_sync(&mut model) {
+                Ok(Some(event)) => emit_music_event(app, event),
+                Ok(None) => {}
+                Err(error) => eprintln!("music playlist failed: {error}"),
+            }
+
+            if !model.timer.is_running {
+                continue;
+            }
+
+            let before = model.timer.remaining_seconds;
+            refresh_remaining(&mut model.timer);
+
+            if model.timer.remaining_seconds <= 0 {
+                let completed = mutate_model(
+                    &mut model,
+                    "timer_complete",
+                    &[DataDomain::Sessions, DataDomain::Timer],
+                    |model, _| complete_and_advance(app, model, true),
+                );
+                if let Ok(((session, phase, timer), mutation)) = completed {
+                    session_event = Some(session);
+                    phase_event = Some(phase);
+                    emit_state = Some(timer);
+                    data_changed = Some(mutation);
+                    should_emit = true;
+                }
+            } else if console_probe
+                .is_some_and(|detached| handle_remote_detach(&mut model, detached, now_ts()))
+                || model.timer.remaining_seconds != before
+            {
+                let _ = persist_timer(&mut model);
+                emit_state = Some(model.timer.clone());
+                should_emit = true;
+            }
+            breathing_event = breathing_update(&mut model, now_ts());
+        }
+
+        if let Some(step) = breathing_event {
+            let _ = app.emit("breathing://step", &step);
+        }
+
+        if should_emit {
+            if let Some(session) = session_event {
+                let _ = app.emit("session://completed", &session);
+            }
+            if let Some(phase) = phase_event {
+                let _ = app.emit("timer://phase-completed", &phase);
+            }
+            if let Some(timer) = emit_state {
+                emit_timer_state(app, &timer);
+            }
+            if let Some(mutation) = data_changed {
+                emit_data_changed(app, &mutation);
+            }
+        }
+    }
+}
+
+const SESSION_COLUMNS: &str =
+    "id, started_at, ended_at, phase, duration_sec, completed, interruptions, project_id, notes, extended_sec, quality, planned";
+
+// WHERE conditions (each prefixed with " AND ") shared by listing, counting, and paging.
+fn build_sessions_filter(range: &AnalyticsRange) -> (String, Vec<Value>) {
+    let mut query = " AND deleted_at IS NULL".to_string();
+    let mut values: Vec<Value> = Vec::new();
+
+    if let Some(from) = range.from {
+        query.push_str(" AND ended_at >= ?");
+        values.push(Value::Integer(from));
+    }
+    if let Some(to) = range.to {
+        query.push_str(" AND ended_at <= ?");
+        values.push(Value::Integer(to));
+    }
+    if let Some(project_id) = range.project_id {
+        query.push_str(" AND (project_id = ? OR EXISTS (SELECT 1 FROM session_project_splits sps WHERE sps.session_id = sessions.id AND sps.project_id = ?))");
+        values.push(Value::Integer(project_id));
+        values.push(Value::Integer(project_id));
+    }
+    if let Some(tag_id) = range.tag_id {
+        query.push_str(" AND EXISTS (SELECT 1 FROM session_tags st WHERE st.session_id = sessions.id AND st.tag_id = ?)");
+        values.push(Value::Integer(tag_id));
+    }
+    if !range.phases.is_empty() {
+        let marks = vec!["?"; range.phases.len()].join(", ");
+        query.push_str(&format!(" AND phase IN ({marks})"));
+        values.extend(
+            range
+                .phases
+                .iter()
+                .map(|phase| Value::Text(phase.as_db_value().to_string())),
+        );
+    }
+    let excluded = &range.excluded;
+    if !excluded.project_ids.is_empty() {
+        let marks = vec!["?"; excluded.project_ids.len()].join(", ");
+        query.push_str(&format!(
+            " AND (project_id IS NULL OR project_id NOT IN ({marks}))"
+        ));
+        values.extend(excluded.project_ids.iter().map(|id| Value::Integer(*id)));
+    }
+    if !excluded.tag_ids.is_empty() {
+        let marks = vec!["?"; excluded.tag_ids.len()].join(", ");
+        query.push_str(&format!(" AND NOT EXISTS (SELECT 1 FROM session_tags st WHERE st.session_id = sessions.id AND st.tag_id IN ({marks}))"));
+        values.extend(excluded.tag_ids.iter().map(|id| Value::Integer(*id)));
+    }
+
+    (query, values)
+}
+
+// Applies the settings' exclusion rules to an analytics range. A project or tag the range
+// filters on explicitly is never excluded from it.
+fn with_exclusions(mut range: AnalyticsRange, settings: &AppSettings) -> AnalyticsRange {
+    if range.include_excluded {
+        return range;
+    }
+    range.excluded = AnalyticsExclusions {
+        project_ids: settings
+            .analytics_excluded_project_ids
+            .iter()
+            .copied()
+            .filter(|id| Some(*id) != range.project_id)
+            .collect(),
+        tag_ids: settings
+            .analytics_excluded_tag_ids
+            .iter()
+            .copied()
+            .filter(|id| Some(*id) != range.tag_id)
+            .collect(),
+    };
+    range
+}
+
+fn build_sessions_query(range: &AnalyticsRange) -> (String, Vec<Value>) {
+    let (filter, values) = build_sessions_filter(range);
+    let query = format!(
+        "SELECT {SESSION_COLUMNS} FROM sessions WHERE 1 = 1{filter} ORDER BY ended_at DESC, id DESC"
+    );
+    (query, values)
+}
+
+fn parse_session_cursor(cursor: &str) -> AppResult<(i64, i64)> {
+    cursor
+        .split_once(':')
+        .and_then(|(ended_at, id)| Some((ended_at.parse().ok()?, id.parse().ok()?)))
+        .ok_or_else(|| format!("invalid session cursor: {cursor}"))
+}
+
+// Keyset pagination on (ended_at, id) so deep pages stay as cheap as the first one.
+fn fetch_sessions_page(conn: &Connection, query: &SessionQuery) -> AppResult<SessionPage> {
+    let (filter, values) = build_sessions_filter(&query.range);
+    let total = conn
+        .query_row(
+            &format!("SELECT COUNT(*) FROM sessions WHERE 1 = 1{filter}"),
+            rusqlite::params_from_iter(values.clone()),
+            |row| row.get::<_, i64>(0),
+        )
+        .map_err(|e| e.to_string())?;
+
+    let limit = query.limit.unwrap_or(50).clamp(1, 500);
+    let (sessions, next_cursor) =
+        fetch_session_keyset(conn, &filter, values, query.cursor.as_deref(), limit)?;
+
+    Ok(SessionPage {
+        total,
+        sessions,
+        next_cursor,
+    })
+}
+
+// One page after `cursor` (newest first) and the cursor of the page that follows, if any.
+fn fetch_session_keyset(
+    conn: &Connection,
+    filter: &str,
+    mut values: Vec<Value>,
+    cursor: Option<&str>,
+    limit: i64,
+) -> AppResult<(Vec<SessionRecord>, Option<String>)> {
+    let mut sql = format!("SELECT {SESSION_COLUMNS} FROM sessions WHERE 1 = 1{filter}");
+    if let Some(cursor) = cursor.filter(|c| !c.is_empty()) {
+        let (ended_at, id) = parse_session_cursor(cursor)?;
+        sql.push_str(" AND (ended_at < ? OR (ended_at = ? AND id < ?))");
+        values.push(Value::Integer(ended_at));
+        values.push(Value::Integer(ended_at));
+        values.push(Value::Integer(id));
+    }
+    sql.push_str(" ORDER BY ended_at DESC, id DESC LIMIT ?");
+    // Fetch one extra row to learn whether another page exists.
+    values.push(Value::Integer(limit + 1));
+
+    let mut sessions = query_sessions(conn, &sql, values)?;
+    let next_cursor = if sessions.len() as i64 > limit {
+        sessions.truncate(limit as usize);
+        sessions.last().map(|s| format!("{}:{}", s.ended_at, s.id))
+    } else {
+        None
+    };
+    Ok((sessions, next_cursor))
+}
+
+// Rows of a session-keyed child table for every id in `session_ids`, in one query. `sql`
+// selects session_id first and filters with `session_id IN (SELECT value FROM json_each(?1))`.
+fn read_by_session<T>(
+    conn: &Connection,
+    sql: &str,
+    session_ids: &[i64],
+    read: impl Fn(&rusqlite::Row) -> rusqlite::Result<T>,
+) -> AppResult<BTreeMap<i64, Vec<T>>> {
+    let ids = serde_json::to_string(session_ids).map_err(|e| e.to_string())?;
+    let mut stmt = conn.prepare(sql).map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(params![ids], |row| Ok((row.get::<_, i64>(0)?, read(row)?)))
+        .map_err(|e| e.to_string())?;
+
+    let mut grouped: BTreeMap<i64, Vec<T>> = BTreeMap::new();
+    for row in rows {
+        let (session_id, value) = row.map_err(|e| e.to_string())?;
+        grouped.entry(session_id).or_default().push(value);
+    }
+    Ok(grouped)
+}
+
+fn read_session_splits(
+    conn: &Connection,
+    session_ids: &[i64],
+) -> AppResult<BTreeMap<i64, Vec<ProjectSplit>>> {
+    read_by_session(
+        conn,
+        "SELECT session_id, project_id, percent FROM session_project_splits
+         WHERE session_id IN (SELECT value FROM json_each(?1))
+         ORDER BY session_id, percent DESC, project_id",
+        session_ids,
+        |row| {
+            Ok(ProjectSplit {
+                project_id: row.get(1)?,
+                percent: row.get(2)?,
+            })
+        },
+    )
+}
+
+// Replaces a session's project splits. Shares must add up to 100; the largest share becomes
+// the session's primary project_id so unsplit views keep working. An empty list unsplits.
+fn write_session_splits(
+    conn: &mut Connection,
+    session_id: i64,
+    splits: &[ProjectSplit],
+) -> AppResult<()> {
+    if !splits.is_empty() {
+        if splits.iter().any(|s| !(1..=100).contains(&s.percent)) {
+            return Err("each split must be between 1 and 100 percent".to_string());
+        }
+        if splits.iter().map(|s| s.percent).sum::<i64>() != 100 {
+            return Err("project splits must add up to 100 percent".to_string());
+        }
+        let unique: HashSet<i64> = splits.iter().map(|s| s.project_id).collect();
+        if unique.len() != splits.len() {
+            return Err("each project can appear only once in a split".to_string());
+        }
+    }
+
+    let tx = conn.savepoint().map_err(|e| e.to_string())?;
+    let phase: Option<String> = tx
+        .query_row(
+            "SELECT phase FROM sessions WHERE id = ?1",
+            params![session_id],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+    match phase.as_deref() {
+        None => return Err(format!("session {session_id} not found")),
+        Some("focus") => {}
+        Some(_) => return Err("only focus sessions can be split across projects".to_string()),
+    }
+    record_session_revision(&tx, &fetch_session(&tx, session_id)?, "splits", now_ts())?;
+
+    tx.execute(
+        "DELETE FROM session_project_splits WHERE session_id = ?1",
+        params![session_id],
+    )
+    .map_err(|e| e.to_string())?;
+    for split in splits {
+        tx.execute(
+            "INSERT INTO session_project_splits (session_id, project_id, percent) VALUES (?1, ?2, ?3)",
+            params![session_id, split.project_id, split.percent],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    if let Some(primary) = splits.iter().max_by_key(|s| s.percent) {
+        tx.execute(
+            "UPDATE sessions SET project_id = ?1 WHERE id = ?2",
+            params![primary.project_id, session_id],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+fn read_session_tags(conn: &Connection, session_ids: &[i64]) -> AppResult<BTreeMap<i64, Vec<i64>>> {
+    read_by_session(
+        conn,
+        "SELECT session_id, tag_id FROM session_tags
+         WHERE session_id IN (SELECT value FROM json_each(?1))
+         ORDER BY session_id, tag_id",
+        session_ids,
+        |row| row.get(1),
+    )
+}
+
+fn phase_from_db(value: String) -> AppResult<TimerPhase> {
+    TimerPhase::from_db_value(&value)
+}
+
+const PROJECT_COLUMNS: &str =
+    "id, name, color, archived, billable, hourly_rate, pinned, sort_order,
+     notify_override, sound_override, estimated_pomodoros";
+
+fn project_from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<Project> {
+    Ok(Project {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        color: row.get(2)?,
+        archived: row.get::<_, i64>(3)? == 1,
+        billable: row.get::<_, i64>(4)? == 1,
+        hourly_rate: row.get(5)?,
+        pinned: row.get::<_, i64>(6)? == 1,
+        sort_order: row.get(7)?,
+        notification_override: NotificationOverride {
+            notify: row.get::<_, Option<i64>>(8)?.map(|v| v == 1),
+            sound: row.get(9)?,
+        },
+        estimated_pomodoros: row.get(10)?,
+    })
+}
+
+fn fetch_project(conn: &Connection, project_id: i64) -> AppResult<Project> {
+    conn.query_row(
+        &format!("SELECT {PROJECT_COLUMNS} FROM projects WHERE id = ?1"),
+        params![project_id],
+        project_from_row,
+    )
+    .map_err(|e| e.to_string())
+}
+
+// Every project including trashed ones, for resolving references on old sessions.
+fn fetch_project_map(conn: &Connection) -> AppResult<BTreeMap<i64, Project>> {
+    let mut stmt = conn
+        .prepare(&format!("SELECT {PROJECT_COLUMNS} FROM projects"))
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([], project_from_row)
+        .map_err(|e| e.to_string())?;
+    let mut projects = BTreeMap::new();
+    for row in rows {
+        let project = row.map_err(|e| e.to_string())?;
+        projects.insert(project.id, project);
+    }
+    Ok(projects)
+}
+
+fn fetch_projects(conn: &Connection) -> AppResult<Vec<Project>> {
+    let mut stmt = conn
+        .prepare(&format!("SELECT {PROJECT_COLUMNS} FROM projects WHERE deleted_at IS NULL ORDER BY archived ASC, pinned DESC, sort_order ASC, name ASC"))
+        .map_err(|e| e.to_string())?;
+
+    let rows = stmt
+        .query_map([], project_from_row)
+        .map_err(|e| e.to_string())?;
+
+    let mut projects = Vec::new();
+    for row in rows {
+        projects.push(row.map_err(|e| e.to_string())?);
+    }
+
+    Ok(projects)
+}
+
+fn fetch_tags(conn: &Connection) -> AppResult<Vec<Tag>> {
+    let mut stmt = conn
+        .prepare("SELECT id, name FROM tags WHERE deleted_at IS NULL ORDER BY name ASC")
+        .map_err(|e| e.to_string())?;
+
+    let rows = stmt
+        .query_map([], |row| {
+            Ok(Tag {
+                id: row.get(0)?,
+                name: row.get(1)?,
+            })
+        })
+        .map_err(|e| e.to_string())?;
+
+    let mut tags = Vec::new();
+    for row in rows {
+        tags.push(row.map_err(|e| e.to_string())?);
+    }
+
+    Ok(tags)
+}
+
+// Most used first, so suggestions surface the tags people actually reach for.
+fn fetch_tags_with_stats(
+    conn: &Connection,
+    range: &AnalyticsRange,
+) -> AppResult<Vec<TagWithStats>> {
+    let (filter, values) = build_sessions_filter(range);
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT t.id, t.name, COUNT(s.id),
+                    COALESCE(SUM(CASE WHEN s.phase = 'focus' THEN s.duration_sec END), 0)
+             FROM tags t
+             LEFT JOIN session_tags st ON st.tag_id = t.id
+             LEFT JOIN (SELECT id, phase, duration_sec FROM sessions WHERE 1 = 1{filter}) s
+               ON s.id = st.session_id
+             WHERE t.deleted_at IS NULL
+             GROUP BY t.id
+             ORDER BY COUNT(s.id) DESC, t.name ASC"
+        ))
+        .map_err(|e| e.to_string())?;
+
+    let rows = stmt
+        .query_map(rusqlite::params_from_iter(values), |row| {
+            Ok(TagWithStats {
+                tag: Tag {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                },
+                session_count: row.get(2)?,
+                focus_seconds: row.get(3)?,
+            })
+        })
+        .map_err(|e| e.to_string())?;
+
+    rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+}
+
+// Turns free text into an FTS5 query of prefix-matched terms (all must match), so user
+// input can never produce FTS syntax errors.
+fn fts_match_query(text: &str) -> Option<String> {
+    let terms: Vec<String> = text
+        .split_whitespace()
+        .map(|term| format!("\"{}\"*", term.replace('"', "\"\"")))
+        .collect();
+    (!terms.is_empty()).then(|| terms.join(" "))
+}
+
+fn search_sessions(conn: &Connection, query: &SessionSearchQuery) -> AppResult<Vec<SessionRecord>> {
+    let Some(match_query) = fts_match_query(&query.text) else {
+        return Ok(Vec::new());
+    };
+
+    let (filter, mut values) = build_sessions_filter(&query.range);
+    let sql = format!(
+        "SELECT {SESSION_COLUMNS} FROM sessions WHERE id IN (SELECT rowid FROM session_notes_fts WHERE session_notes_fts MATCH ?){filter} ORDER BY ended_at DESC, id DESC LIMIT ?"
+    );
+    values.insert(0, Value::Text(match_query));
+    values.push(Value::Integer(query.limit.unwrap_or(100).clamp(1, 500)));
+    query_sessions(conn, &sql, values)
+}
+
+// Moves every focus session matching `filter` to `project_id` (None clears the project).
+fn reassign_sessions_project(
+    conn: &mut Connection,
+    filter: &AnalyticsRange,
+    project_id: Option<i64>,
+) -> AppResult<i64> {
+    let tx = conn.savepoint().map_err(|e| e.to_string())?;
+    if let Some(id) = project_id {
+        let exists: bool = tx
+            .query_row(
+                "SELECT EXISTS (SELECT 1 FROM projects WHERE id = ?1 AND deleted_at IS NULL)",
+                params![id],
+                |row| row.get(0),
+            )
+            .map_err(|e| e.to_string())?;
+        if !exists {
+            return Err(format!("project {id} not found"));
+        }
+    }
+
+    let (condition, values) = build_sessions_filter(filter);
+    let affected = query_sessions(
+        &tx,
+        &format!("SELECT {SESSION_COLUMNS} FROM sessions WHERE phase = 'focus'{condition}"),
+        values,
+    )?;
+    let now = now_ts();
+    // A split session moves whole: its splits would otherwise keep crediting the old projects.
+    for session in &affected {
+        record_session_revision(&tx, session, "reassign_project", now)?;
+        tx.execute(
+            "DELETE FROM session_project_splits WHERE session_id = ?1",
+            params![session.id],
+        )
+        .map_err(|e| e.to_string())?;
+        tx.execute(
+            "UPDATE sessions SET project_id = ?1 WHERE id = ?2",
+            params![project_id, session.id],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(affected.len() as i64)
+}
+
+// Adds and removes tags on every focus session the filter matches, in one transaction.
+// Returns the number of sessions whose tags changed; only those get a revision.
+fn bulk_tag_sessions(
+    conn: &mut Connection,
+    filter: &BulkTagFilter,
+    add_tag_ids: &[i64],
+    remove_tag_ids: &[i64],
+) -> AppResult<i64> {
+    if add_tag_ids.is_empty() && remove_tag_ids.is_empty() {
+        return Err("no tags to add or remove".to_string());
+    }
+    if let Some(id) = add_tag_ids.iter().find(|id| remove_tag_ids.contains(id)) {
+        return Err(format!("tag {id} is both added and removed"));
+    }
+    let tx = conn.savepoint().map_err(|e| e.to_string())?;
+    for id in add_tag_ids {
+        let exists: bool = tx
+            .query_row(
+                "SELECT EXISTS (SELECT 1 FROM tags WHERE id = ?1 AND deleted_at IS NULL)",
+                params![id],
+                |row| row.get(0),
+            )
+            .map_err(|e| e.to_string())?;
+        if !exists {
+            return Err(format!("tag {id} not found"));
+        }
+    }
+
+    let (mut condition, values) = build_sessions_filter(&filter.range);
+    if filter.untagged {
+        condition.push_str(
+            " AND NOT EXISTS (SELECT 1 FROM session_tags st WHERE st.session_id = sessions.id)",
+        );
+    }
+    let matched = query_sessions(
+        &tx,
+        &format!("SELECT {SESSION_COLUMNS} FROM sessions WHERE phase = 'focus'{condition}"),
+        values,
+    )?;
+    let now = now_ts();
+    let mut updated = 0;
+    for session in &matched {
+        let adds: Vec<i64> = add_tag_ids
+            .iter()
+            .copied()
+            .filter(|id| !session.tag_ids.contains(id))
+            .collect();
+        let removes: Vec<i64> = remove_tag_ids
+            .iter()
+            .copied()
+            .filter(|id| session.tag_ids.contains(id))
+            .collect();
+        if adds.is_empty() && removes.is_empty() {
+            continue;
+        }
+        record_session_revision(&tx, session, "bulk_tag", now)?;
+        for tag_id in adds {
+            tx.execute(
+                "INSERT INTO session_tags (session_id, tag_id) VALUES (?1, ?2)",
+                params![session.id, tag_id],
+            )
+            .map_err(|e| e.to_string())?;
+        }
+        for tag_id in removes {
+            tx.execute(
+                "DELETE FROM session_tags WHERE session_id = ?1 AND tag_id = ?2",
+                params![session.id, tag_id],
+            )
+            .map_err(|e| e.to_string())?;
+        }
+        updated += 1;
+    }
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(updated)
+}
+
+// Checks that need no database; shared with import validation.
+fn check_session_fields(
+    started_at: i64,
+    ended_at: i64,
+    duration_sec: i64,
+    interruptions: i64,
+    quality: Option<i64>,
+) -> Result<(), SessionValidationError> {
+    if ended_at <= started_at {
+        return Err(SessionValidationError::InvalidRange);
+    }
+    if duration_sec < 0 || interruptions < 0 {
+        return Err(SessionValidationError::NegativeValue);
+    }
+    if duration_sec > ended_at - started_at {
+        return Err(SessionValidationError::DurationExceedsWindow);
+    }
+    if quality.is_some_and(|q| !(1..=5).contains(&q)) {
+        return Err(SessionValidationError::InvalidQuality);
+    }
+    Ok(())
+}
+
+fn validate_new_session(
+    conn: &Connection,
+    payload: &CompleteSessionRequest,
+) -> AppResult<Result<(), SessionValidationError>> {
+    if let Err(error) = check_session_fields(
+        payload.started_at,
+        payload.ended_at,
+        payload.duration_sec,
+        payload.interruptions,
+        payload.quality,
+    ) {
+        return Ok(Err(error));
+    }
+    if payload.allow_overlap.unwrap_or(false) {
+        return Ok(Ok(()));
+    }
+
+    Ok(
+        match find_overlap(conn, payload.started_at, payload.ended_at, None)? {
+            Some(id) => Err(SessionValidationError::Overlap(id)),
+            None => Ok(()),
+        },
+    )
+}
+
+// Epoch bounds of the work hours on a local YYYY-MM-DD date. On a DST switch the earlier
+// reading of an ambiguous time wins; a skipped time is an error.
+fn work_hours_window(date: &str, start_min: i64, end_min: i64) -> AppResult<(i64, i64)> {
+    let midnight = chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .map_err(|_| format!("invalid date: {date}"))?
+        .and_time(chrono::NaiveTime::MIN);
+    let at = |minutes: i64| {
+        Local
+            .from_local_datetime(&(midnight + chrono::Duration::minutes(minutes)))
+            .earliest()
+            .map(|dt| dt.timestamp())
+            .ok_or_else(|| format!("work hours on {date} fall in a DST gap"))
+    };
+    Ok((at(start_min)?, at(end_min)?))
+}
+
+// Walks busy intervals in start order and reports every hole of at least `min_gap_sec`
+// between `window_start` and `window_end`. Busy intervals may overlap or spill outside.
+fn find_gaps(
+    mut busy: Vec<(i64, i64)>,
+    window_start: i64,
+    window_end: i64,
+    min_gap_sec: i64,
+) -> Vec<SessionGap> {
+    busy.sort_unstable();
+    let mut gaps = Vec::new();
+    let mut cursor = window_start;
+    let mut push = |from: i64, to: i64| {
+        if to - from >= min_gap_sec {
+            gaps.push(SessionGap {
+                started_at: from,
+                ended_at: to,
+                duration_sec: to - from,
+            });
+        }
+    };
+    for (started_at, ended_at) in busy {
+        if started_at >= window_end {
+            break;
+        }
+        if started_at > cursor {
+            push(cursor, started_at);
+        }
+        cursor = cursor.max(ended_at);
+    }
+    if window_end > cursor {
+        push(cursor, window_end);
+    }
+    gaps
+}
+
+// Gaps in the day's work hours, counting sessions of every phase and the phase in progress
+// as tracked. Hours still ahead of `now` are not gaps yet.
+fn detect_session_gaps(
+    conn: &Connection,
+    settings: &AppSettings,
+    timer: &TimerState,
+    date: &str,
+    min_gap_min: i64,
+    now: i64,
+) -> AppResult<Vec<SessionGap>> {
+    let (window_start, window_end) =
+        work_hours_window(date, settings.work_day_start_min, settings.work_day_end_min)?;
+    let window_end = window_end.min(now);
+    if window_end <= window_start {
+        return Ok(Vec::new());
+    }
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT started_at, ended_at FROM sessions
+             WHERE deleted_at IS NULL AND started_at < ?2 AND ended_at > ?1",
+        )
+        .map_err(|e| e.to_string())?;
+    let mut busy = stmt
+        .query_map(params![window_start, window_end], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?))
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    if let Some(started_at) = timer.started_at {
+        busy.push((started_at, now));
+    }
+
+    Ok(find_gaps(busy, window_start, window_end, min_gap_min * 60))
+}
+
+fn find_overlap(
+    conn: &Connection,
+    started_at: i64,
+    ended_at: i64,
+    exclude_id: Option<i64>,
+) -> AppResult<Option<i64>> {
+    conn.query_row(
+        "SELECT id FROM sessions WHERE deleted_at IS NULL AND started_at < ?2 AND ended_at > ?1
+             AND id IS NOT ?3
+         ORDER BY started_at LIMIT 1",
+        params![started_at, ended_at, exclude_id],
+        |row| row.get::<_, i64>(0),
+    )
+    .optional()
+    .map_err(|e| e.to_string())
+}
+
+// Called inside the editing transaction, before the change, so every edit leaves the
+// previous version behind.
+fn record_session_revision(
+    conn: &Connection,
+    session: &SessionRecord,
+    source: &str,
+    now: i64,
+) -> AppResult<()> {
+    let snapshot = serde_json::to_string(session).map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT INTO session_revisions (session_id, revised_at, source, snapshot) VALUES (?1, ?2, ?3, ?4)",
+        params![session.id, now, source, snapshot],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+const IMPOSSIBLE_DURATION_SQL: &str =
+    "duration_sec < 0 OR duration_sec > MAX(ended_at - started_at, 0)";
+
+fn session_ids_where(conn: &Connection, condition: &str) -> AppResult<Vec<i64>> {
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT id FROM sessions WHERE {condition} ORDER BY id"
+        ))
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([], |row| row.get::<_, i64>(0))
+        .map_err(|e| e.to_string())?;
+    rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+}
+
+fn is_lock_error(error: &rusqlite::Error) -> bool {
+    matches!(
+        error.sqlite_error_code(),
+        Some(
+            rusqlite::ErrorCode::DatabaseBusy
+                | rusqlite::ErrorCode::DatabaseLocked
+                | rusqlite::ErrorCode::ReadOnly
+        )
+    )
+}
+
+// Why writes cannot go through right now, if they cannot: the file is read-only, or another
+// process still holds the write lock once the busy timeout runs out.
+fn write_blocked(conn: &Connection) -> Option<String> {
+    if conn
+        .is_readonly(rusqlite::DatabaseName::Main)
+        .unwrap_or(false)
+    {
+        return Some("the database file is read-only".to_string());
+    }
+    match conn.execute_batch("BEGIN IMMEDIATE; ROLLBACK") {
+        Err(error) if is_lock_error(&error) => Some(error.to_string()),
+        _ => None,
+    }
+}
+
+// rusqlite's default; read-only mode drops it to zero so blocked writes fail at once.
+const DB_BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Returns the connection and, when another process blocks writes, the reason. A locked file
+// is not damaged, so the integrity check and migrations are left to the process holding it.
+fn open_database(path: &std::path::Path) -> Result<(Connection, Option<String>), DbOpenError> {
+    let failed = |e: rusqlite::Error| DbOpenError::Failed(e.to_string());
+    let conn = Connection::open(path).map_err(failed)?;
+    if let Some(reason) = write_blocked(&conn) {
+        conn.busy_timeout(Duration::ZERO).map_err(failed)?;
+        return Ok((conn, Some(reason)));
+    }
+    let check: String = conn
+        .query_row("PRAGMA quick_check", [], |row| row.get(0))
+        .map_err(|e| {
+            if is_corruption_error(&e) {
+                DbOpenError::Corrupt(e.to_string())
+            } else {
+                DbOpenError::Failed(e.to_string())
+            }
+        })?;
+    if check != "ok" {
+        return Err(DbOpenError::Corrupt(format!(
+            "database integrity check failed: {check}"
+        )));
+    }
+    init_database(&conn).map_err(DbOpenError::Failed)?;
+    Ok((conn, None))
+}
+
+// Why the database did not open. Only a damaged file is worth moving aside and rebuilding;
+// anything else (an I/O error, a migration that fails, a schema from a newer version) is
+// reported and the file is left where it is.
+#[derive(Debug, Clone, PartialEq)]
+enum DbOpenError {
+    Corrupt(String),
+    Failed(String),
+}
+
+impl From<DbOpenError> for String {
+    fn from(error: DbOpenError) -> Self {
+        match error {
+            DbOpenError::Corrupt(message) | DbOpenError::Failed(message) => message,
+        }
+    }
+}
+
+fn is_corruption_error(error: &rusqlite::Error) -> bool {
+    matches!(
+        error.sqlite_error_code(),
+        Some(rusqlite::ErrorCode::DatabaseCorrupt | rusqlite::ErrorCode::NotADatabase)
+    )
+}
+
+const READ_ONLY_RETRY_SECONDS: i64 = 30;
+
+fn read_only_mode(reason: String, now: i64) -> ReadOnlyMode {
+    ReadOnlyMode {
+        reason,
+        since: now,
+        checked_at: now,
+        timer_dirty: false,
+        writes: Vec::new(),
+    }
+}
+
+fn read_only_status(model: &AppModel, flushed_sessions: usize) -> ReadOnlyStatus {
+    let mode = model.read_only.as_ref();
+    ReadOnlyStatus {
+        read_only: mode.is_some(),
+        reason: mode.map(|m| m.reason.clone()),
+        since: mode.map(|m| m.since),
+        pending_sessions: mode.map_or(0, |m| {
+            m.writes
+                .iter()
+                .filter(|w| matches!(w, PendingWrite::Session { .. }))
+                .count()
+        }),
+        flushed_sessions,
+    }
+}
+
+// Retries at most every READ_ONLY_RETRY_SECONDS and, once writes go through, saves what
+// read-only mode held back in one transaction and leaves it. Returns the number of sessions
+// written, or None while still blocked or not read-only.
+fn flush_read_only(model: &mut AppModel, now: i64) -> AppResult<Option<usize>> {
+    let Some(mode) = &mut model.read_only else {
+        return Ok(None);
+    };
+    if now - mode.checked_at < READ_ONLY_RETRY_SECONDS {
+        return Ok(None);
+    }
+    mode.checked_at = now;
+    if let Some(reason) = write_blocked(&model.conn) {
+        mode.reason = reason;
+        return Ok(None);
+    }
+
+    let tx = model.conn.savepoint().map_err(|e| e.to_string())?;
+    let mut sessions = 0;
+    for write in &mode.writes {
+        match write {
+            PendingWrite::Session {
+                session,
+                timer,
+                event,
+            } => {
+                let id = insert_session(&tx, timer, session)?;
+                record_timer_event(&tx, *event, timer, Some(id), session.ended_at)?;
+                sessions += 1;
+            }
+            PendingWrite::Event { kind, timer, at } => {
+                record_timer_event(&tx, *kind, timer, None, *at)?;
+            }
+        }
+    }
+    if mode.timer_dirty {
+        save_timer_state(&tx, &model.timer)?;
+    }
+    tx.commit().map_err(|e| e.to_string())?;
+    model.read_only = None;
+    model
+        .conn
+        .busy_timeout(DB_BUSY_TIMEOUT)
+        .map_err(|e| e.to_string())?;
+    Ok(Some(sessions))
+}
+
+// Moves the unreadable file and its WAL sidecars aside, then rebuilds `path` with sqlite3's
+// `.recover`, falling back to copying whatever rows still read, and failing that starts empty.
+fn recover_database(
+    path: &std::path::Path,
+    error: String,
+    now: i64,
+) -> AppResult<(Connection, DbRecoveryReport)> {
+    let backup = path.with_extension(format!("corrupt-{now}.db"));
+    fs::rename(path, &backup).map_err(|e| e.to_string())?;
+    for suffix in ["-wal", "-shm"] {
+        let sidecar = format!("{}{suffix}", path.display());
+        if std::path::Path::new(&sidecar).exists() {
+            let _ = fs::rename(&sidecar, format!("{}{suffix}", backup.display()));
+        }
+    }
+    let backup_path = backup.to_string_lossy().into_owned();
+
+    let (conn, mode, failed_tables) = match recover_with_sqlite_cli(path, &backup_path) {
+        Some(conn) => (conn, DbRecoveryMode::Recover, Vec::new()),
+        None => {
+            let conn = Connection::open(path).map_err(|e| e.to_string())?;
+            init_database(&conn)?;
+            match copy_readable_tables(&conn, &backup_path) {
+                Ok(failed) => (conn, DbRecoveryMode::Copy, failed),
+                Err(error) => {
+                    eprintln!("database salvage failed: {error}");
+                    (conn, DbRecoveryMode::Fresh, Vec::new())
+                }
+            }
+        }
+    };
+    let tables = db_stats(&conn)?.tables;
+    Ok((
+        conn,
+        DbRecoveryReport {
+            error,
+            backup_path,
+            mode,
+            tables,
+            failed_tables,
+            at: now,
+        },
+    ))
+}
+
+fn recover_with_sqlite_cli(path: &std::path::Path, backup_path: &str) -> Option<Connection> {
+    let dump = probe_output("sqlite3", &[backup_path, ".recover"])?;
+    let conn = Connection::open(path).ok()?;
+    let loaded = conn
+        .execute_batch(&dump)
+        .map_err(|e| e.to_string())
+        .and_then(|_| init_database(&conn));
+    match loaded {
+        Ok(()) => Some(conn),
+        Err(error) => {
+            eprintln!("sqlite3 .recover output could not be loaded: {error}");
+            drop(conn);
+            let _ = fs::remove_file(path);
+            None
+        }
+    }
+}
+
+// Copies the columns each table shares with the current schema. A table that cannot be read
+// is skipped and named in the result; failing to read the schema at all is an error.
+fn copy_readable_tables(conn: &Connection, backup_path: &str) -> AppResult<Vec<String>> {
+    conn.execute("ATTACH DATABASE ?1 AS damaged", [backup_path])
+        .map_err(|e| e.to_string())?;
+    let columns = |schema: &str, table: &str| -> AppResult<Vec<String>> {
+        let mut stmt = conn
+            .prepare("SELECT name FROM pragma_table_info(?1, ?2)")
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map([table, schema], |row| row.get(0))
+            .map_err(|e| e.to_string())?;
+        rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+    };
+    let tables: AppResult<Vec<String>> = conn
+        .prepare(
+            "SELECT name FROM damaged.sqlite_master
+             WHERE type = 'table' AND name NOT LIKE 'sqlite_%' ORDER BY name",
+        )
+        .and_then(|mut stmt| {
+            stmt.query_map([], |row| row.get(0))?
+                .collect::<Result<_, _>>()
+        })
+        .map_err(|e| e.to_string());
+    let tables = match tables {
+        Ok(tables) => tables,
+        Err(error) => {
+            let _ = conn.execute_batch("DETACH DATABASE damaged");
+            return Err(error);
+        }
+    };
+
+    conn.execute_batch("PRAGMA foreign_keys = OFF")
+        .map_err(|e| e.to_string())?;
+    let mut failed = Vec::new();
+    for table in tables {
+        let copied = columns("main", &table).and_then(|current| {
+            let old = columns("damaged", &table)?;
+            let shared: Vec<String> = current
+                .into_iter()
+                .filter(|column| old.contains(column))
+                .map(|column| format!("\"{column}\""))
+                .collect();
+            if shared.is_empty() {
+                return Err("no columns in the current schema".to_string());
+            }
+            let list = shared.join(", ");
+            conn.execute(
+                &format!(
+                    "INSERT OR REPLACE INTO main.\"{table}\" ({list})
+                     SELECT {list} FROM damaged.\"{table}\""
+                ),
+                [],
+            )
+            .map_err(|e| e.to_string())
+        });
+        if let Err(error) = copied {
+            eprintln!("database salvage skipped {table}: {error}");
+            failed.push(table);
+        }
+    }
+    conn.execute_batch("PRAGMA foreign_keys = ON; DETACH DATABASE damaged")
+        .map_err(|e| e.to_string())?;
+    Ok(failed)
+}
+
+// Trashed sessions are checked too; a restore would bring their problems back.
+fn check_database(conn: &Connection) -> AppResult<DbCheckReport> {
+    let orphaned_session_tags = conn
+        .query_row(
+            "SELECT COUNT(*) FROM session_tags st
+             WHERE NOT EXISTS (SELECT 1 FROM sessions s WHERE s.id = st.session_id)
+                OR NOT EXISTS (SELECT 1 FROM tags t WHERE t.id = st.tag_id)",
+            [],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+    Ok(DbCheckReport {
+        orphaned_session_tags,
+        impossible_duration_session_ids: session_ids_where(conn, IMPOSSIBLE_DURATION_SQL)?,
+        dangling_project_session_ids: session_ids_where(
+            conn,
+            "project_id IS NOT NULL AND NOT EXISTS (SELECT 1 FROM projects p WHERE p.id = sessions.project_id)",
+        )?,
+    })
+}
+
+// Drops orphaned tag links, clamps durations into the session window and clears dangling
+// project ids, all in one transaction. Changed sessions get a "repair" revision.
+fn repair_database(conn: &mut Connection) -> AppResult<DbCheckReport> {
+    let found = check_database(conn)?;
+    let now = now_ts();
+    let tx = conn.savepoint().map_err(|e| e.to_string())?;
+    let mut touched: Vec<i64> = found
+        .impossible_duration_session_ids
+        .iter()
+        .chain(&found.dangling_project_session_ids)
+        .copied()
+        .collect();
+    touched.sort_unstable();
+    touched.dedup();
+    for id in touched {
+        record_session_revision(&tx, &fetch_session(&tx, id)?, "repair", now)?;
+    }
+
+    tx.execute(
+        "DELETE FROM session_tags
+         WHERE NOT EXISTS (SELECT 1 FROM sessions s WHERE s.id = session_tags.session_id)
+            OR NOT EXISTS (SELECT 1 FROM tags t WHERE t.id = session_tags.tag_id)",
+        [],
+    )
+    .map_err(|e| e.to_string())?;
+    tx.execute(
+        &format!(
+            "UPDATE sessions SET duration_sec = MAX(0, MIN(duration_sec, ended_at - started_at))
+             WHERE {IMPOSSIBLE_DURATION_SQL}"
+        ),
+        [],
+    )
+    .map_err(|e| e.to_string())?;
+    tx.execute(
+        "UPDATE sessions SET project_id = NULL
+         WHERE project_id IS NOT NULL AND NOT EXISTS (SELECT 1 FROM projects p WHERE p.id = sessions.project_id)",
+        [],
+    )
+    .map_err(|e| e.to_string())?;
+    tx.commit().map_err(|e| e.to_string())?;
+
+    Ok(found)
+}
+
+fn fetch_session_revisions(conn: &Connection, session_id: i64) -> AppResult<Vec<SessionRevision>> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, session_id, revised_at, source, snapshot FROM session_revisions
+             WHERE session_id = ?1 ORDER BY id DESC",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(params![session_id], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, i64>(1)?,
+                row.get::<_, i64>(2)?,
+                row.get::<_, String>(3)?,
+                row.get::<_, String>(4)?,
+            ))
+        })
+        .map_err(|e| e.to_string())?;
+
+    let mut revisions = Vec::new();
+    for row in rows {
+        let (id, session_id, revised_at, source, snapshot) = row.map_err(|e| e.to_string())?;
+        revisions.push(SessionRevision {
+            id,
+            session_id,
+            revised_at,
+            source,
+            session: serde_json::from_str(&snapshot).map_err(|e| e.to_string())?,
+        });
+    }
+    Ok(revisions)
+}
+
+fn update_session(conn: &mut Connection, session_id: i64, update: &SessionUpdate) -> AppResult<()> {
+    let tx = conn.savepoint().map_err(|e| e.to_string())?;
+    let current = fetch_session(&tx, session_id)?;
+    let started_at = update.started_at.unwrap_or(current.started_at);
+    let ended_at = update.ended_at.unwrap_or(current.ended_at);
+    let duration_sec = update.duration_sec.unwrap_or(current.duration_sec);
+    let interruptions = update.interruptions.unwrap_or(current.interruptions);
+    check_session_fields(
+        started_at,
+        ended_at,
+        duration_sec,
+        interruptions,
+        current.quality,
+    )
+    .map_err(|e| e.to_string())?;
+    if !update.allow_overlap.unwrap_or(false) {
+        if let Some(id) = find_overlap(&tx, started_at, ended_at, Some(session_id))? {
+            return Err(SessionValidationError::Overlap(id).to_string());
+        }
+    }
+
+    record_session_revision(&tx, &current, "update", now_ts())?;
+    tx.execute(
+        "UPDATE sessions SET started_at = ?1, ended_at = ?2, duration_sec = ?3, completed = ?4,
+             interruptions = ?5
+         WHERE id = ?6",
+        params![
+            started_at,
+            ended_at,
+            duration_sec,
+            update.completed.unwrap_or(current.completed) as i64,
+            interruptions,
+            session_id
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+    tx.commit().map_err(|e| e.to_string())
+}
+
+// Restores a session to a stored revision, tags and project splits included. The state
+// being replaced is itself kept as a revision, so a revert can be undone.
+// Checked like an edit: the restored times must not overlap another session unless
+// `allow_overlap` says so.
+fn revert_session_revision(
+    conn: &mut Connection,
+    revision_id: i64,
+    allow_overlap: bool,
+) -> AppResult<i64> {
+    let tx = conn.savepoint().map_err(|e| e.to_string())?;
+    let snapshot: String = tx
+        .query_row(
+            "SELECT snapshot FROM session_revisions WHERE id = ?1",
+            params![revision_id],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("revision {revision_id} not found"))?;
+    let old: SessionRecord = serde_json::from_str(&snapshot).map_err(|e| e.to_string())?;
+    let current = fetch_session(&tx, old.id)?;
+    check_session_fields(
+        old.started_at,
+        old.ended_at,
+        old.duration_sec,
+        old.interruptions,
+        old.quality,
+    )
+    .map_err(|e| e.to_string())?;
+    if !allow_overlap {
+        if let Some(id) = find_overlap(&tx, old.started_at, old.ended_at, Some(old.id))? {
+            return Err(SessionValidationError::Overlap(id).to_string());
+        }
+    }
+    record_session_revision(&tx, &current, "revert", now_ts())?;
+
+    tx.execute(
+        "UPDATE sessions SET started_at = ?1, ended_at = ?2, phase = ?3, duration_sec = ?4,
+             completed = ?5, interruptions = ?6, project_id = ?7, notes = ?8, extended_sec = ?9,
+             quality = ?10, planned = ?11
+         WHERE id = ?12",
+        params![
+            old.started_at,
+            old.ended_at,
+            old.phase.as_db_value(),
+            old.duration_sec,
+            old.completed as i64,
+            old.interruptions,
+            old.project_id,
+            old.notes,
+            old.extended_sec,
+            old.quality,
+            old.planned.map(|v| v as i64),
+            old.id
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+    tx.execute(
+        "DELETE FROM session_tags WHERE session_id = ?1",
+        params![old.id],
+    )
+    .map_err(|e| e.to_string())?;
+    // Tags or projects removed since the snapshot are skipped rather than failing the revert.
+    for tag_id in &old.tag_ids {
+        tx.execute(
+            "INSERT INTO session_tags (session_id, tag_id) SELECT ?1, id FROM tags WHERE id = ?2",
+            params![old.id, tag_id],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    tx.execute(
+        "DELETE FROM session_project_splits WHERE session_id = ?1",
+        params![old.id],
+    )
+    .map_err(|e| e.to_string())?;
+    for split in &old.project_splits {
+        tx.execute(
+            "INSERT INTO session_project_splits (session_id, project_id, percent)
+             SELECT ?1, id, ?3 FROM projects WHERE id = ?2",
+            params![old.id, split.project_id, split.percent],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(old.id)
+}
+
+fn rate_session(conn: &mut Connection, session_id: i64, quality: Option<i64>) -> AppResult<()> {
+    if quality.is_some_and(|q| !(1..=5).contains(&q)) {
+        return Err(SessionValidationError::InvalidQuality.to_string());
+    }
+    let tx = conn.savepoint().map_err(|e| e.to_string())?;
+    record_session_revision(&tx, &fetch_session(&tx, session_id)?, "quality", now_ts())?;
+    tx.execute(
+        "UPDATE sessions SET quality = ?1 WHERE id = ?2",
+        params![quality, session_id],
+    )
+    .map_err(|e| e.to_string())?;
+    tx.commit().map_err(|e| e.to_string())
+}
+
+// Average rating per local day and per project, over rated focus sessions only.
+fn quality_breakdown(sessions: &[SessionRecord]) -> QualityBreakdown {
+    let mut by_day: BTreeMap<String, (i64, i64)> = BTreeMap::new();
+    let mut by_project: BTreeMap<Option<i64>, (i64, i64)> = BTreeMap::new();
+    for session in sessions {
+        let Some(quality) = session.quality else {
+            continue;
+        };
+        if session.phase != TimerPhase::Focus {
+            continue;
+        }
+        for entry in [
+            by_day.entry(day_key(session.ended_at)).or_default(),
+            by_project.entry(session.project_id).or_default(),
+        ] {
+            entry.0 += quality;
+            entry.1 += 1;
+        }
+    }
+
+    QualityBreakdown {
+        by_day: by_day
+            .into_iter()
+            .map(|(date, (sum, count))| DailyQuality {
+                date,
+                avg_quality: sum as f64 / count as f64,
+                rated_sessions: count,
+            })
+            .collect(),
+        by_project: by_project
+            .into_iter()
+            .map(|(project_id, (sum, count))| ProjectQuality {
+                project_id,
+                avg_quality: sum as f64 / count as f64,
+                rated_sessions: count,
+            })
+            .collect(),
+    }
+}
+
+// Soft delete: rows stay in place with deleted_at set until purge_trash removes them.
+// `table` is always one of the fixed names below, never user input.
+fn set_trashed(
+    conn: &Connection,
+    table: &str,
+    ids: &[i64],
+    deleted_at: Option<i64>,
+) -> AppResult<i64> {
+    let sql = if deleted_at.is_some() {
+        format!("UPDATE {table} SET deleted_at = ?1 WHERE id = ?2 AND deleted_at IS NULL")
+    } else {
+        format!("UPDATE {table} SET deleted_at = ?1 WHERE id = ?2 AND deleted_at IS NOT NULL")
+    };
+    let mut updated = 0;
+    for id in ids {
+        updated += conn.execute(&sql, params![deleted_at, id]).map_err(|e| {
+            match e.sqlite_error_code() {
+                // Restoring a project or tag whose name was reused while it sat in the trash.
+                Some(rusqlite::ErrorCode::ConstraintViolation) => {
+                    format!("{table}: another item already has the name of #{id}; rename it first")
+                }
+                _ => e.to_string(),
+            }
+        })? as i64;
+    }
+    Ok(updated)
+}
+
+// Without `force`, refuses while live sessions still reference the project. With it, those
+// sessions are detached first (project cleared, splits dropped) so restoring the project later
+// does not silently re-attribute history.
+fn delete_project(conn: &mut Connection, project_id: i64, force: bool) -> AppResult<DeleteResult> {
+    let tx = conn.savepoint().map_err(|e| e.to_string())?;
+    let history: i64 = tx
+        .query_row(
+            "SELECT COUNT(*) FROM sessions WHERE deleted_at IS NULL AND (project_id = ?1
+                OR id IN (SELECT session_id FROM session_project_splits WHERE project_id = ?1))",
+            params![project_id],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+    if history > 0 && !force {
+        return Err(format!(
+            "has_history: {history} sessions reference this project; pass force to delete anyway"
+        ));
+    }
+
+    tx.execute(
+        "DELETE FROM session_project_splits WHERE session_id IN
+            (SELECT session_id FROM session_project_splits WHERE project_id = ?1)",
+        params![project_id],
+    )
+    .map_err(|e| e.to_string())?;
+    tx.execute(
+        "UPDATE sessions SET project_id = NULL WHERE project_id = ?1",
+        params![project_id],
+    )
+    .map_err(|e| e.to_string())?;
+    let deleted = set_trashed(&tx, "projects", &[project_id], Some(now_ts()))? > 0;
+    tx.commit().map_err(|e| e.to_string())?;
+
+    Ok(DeleteResult {
+        deleted,
+        detached_sessions: history,
+    })
+}
+
+fn delete_tag(conn: &mut Connection, tag_id: i64, force: bool) -> AppResult<DeleteResult> {
+    let tx = conn.savepoint().map_err(|e| e.to_string())?;
+    let history: i64 = tx
+        .query_row(
+            "SELECT COUNT(*) FROM session_tags st JOIN sessions s ON s.id = st.session_id
+             WHERE st.tag_id = ?1 AND s.deleted_at IS NULL",
+            params![tag_id],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+    if history > 0 && !force {
+        return Err(format!(
+            "has_history: {history} sessions use this tag; pass force to delete anyway"
+        ));
+    }
+
+    tx.execute(
+        "DELETE FROM session_tags WHERE tag_id = ?1",
+        params![tag_id],
+    )
+    .map_err(|e| e.to_string())?;
+    let deleted = set_trashed(&tx, "tags", &[tag_id], Some(now_ts()))? > 0;
+    tx.commit().map_err(|e| e.to_string())?;
+
+    Ok(DeleteResult {
+        deleted,
+        detached_sessions: history,
+    })
+}
+
+// Sources are removed outright rather than trashed: their sessions already moved to the target.
+fn merge_tags(conn: &mut Connection, source_ids: &[i64], target_id: i64) -> AppResult<MergeResult> {
+    if source_ids.contains(&target_id) {
+        return Err("the target tag cannot also be a source".to_string());
+    }
+    let tx = conn.savepoint().map_err(|e| e.to_string())?;
+    let target_exists: bool = tx
+        .query_row(
+            "SELECT EXISTS (SELECT 1 FROM tags WHERE id = ?1 AND deleted_at IS NULL)",
+            params![target_id],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+    if !target_exists {
+        return Err(format!("tag {target_id} not found"));
+    }
+
+    let mut moved_sessions = 0;
+    let mut removed = 0;
+    for source_id in source_ids {
+        moved_sessions += tx
+            .execute(
+                "INSERT OR IGNORE INTO session_tags (session_id, tag_id)
+                 SELECT session_id, ?1 FROM session_tags WHERE tag_id = ?2",
+                params![target_id, source_id],
+            )
+            .map_err(|e| e.to_string())? as i64;
+        removed += tx
+            .execute("DELETE FROM tags WHERE id = ?1", params![source_id])
+            .map_err(|e| e.to_string())? as i64;
+    }
+    tx.commit().map_err(|e| e.to_string())?;
+
+    Ok(MergeResult {
+        moved_sessions,
+        removed,
+    })
+}
+
+// Moves every session (and split share) of `source_id` onto `target_id`, then archives the
+// source or removes it. Per-project attributes the target lacks are taken from the source.
+fn merge_projects(
+    conn: &mut Connection,
+    source_id: i64,
+    target_id: i64,
+    delete_source: bool,
+) -> AppResult<MergeResult> {
+    if source_id == target_id {
+        return Err("cannot merge a project into itself".to_string());
+    }
+    let tx = conn.savepoint().map_err(|e| e.to_string())?;
+    let live: i64 = tx
+        .query_row(
+            "SELECT COUNT(*) FROM projects WHERE id IN (?1, ?2) AND deleted_at IS NULL",
+            params![source_id, target_id],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+    if live != 2 {
+        return Err("both projects must exist".to_string());
+    }
+
+    let moved_sessions = tx
+        .execute(
+            "UPDATE sessions SET project_id = ?1 WHERE project_id = ?2",
+            params![target_id, source_id],
+        )
+        .map_err(|e| e.to_string())? as i64;
+    tx.execute(
+        "INSERT INTO session_project_splits (session_id, project_id, percent)
+         SELECT session_id, ?1, percent FROM session_project_splits WHERE project_id = ?2
+         ON CONFLICT(session_id, project_id) DO UPDATE SET percent = percent + excluded.percent",
+        params![target_id, source_id],
+    )
+    .map_err(|e| e.to_string())?;
+    tx.execute(
+        "DELETE FROM session_project_splits WHERE project_id = ?1",
+        params![source_id],
+    )
+    .map_err(|e| e.to_string())?;
+    tx.execute(
+        "UPDATE projects SET
+             color = COALESCE(color, (SELECT color FROM projects WHERE id = ?2)),
+             hourly_rate = COALESCE(hourly_rate, (SELECT hourly_rate FROM projects WHERE id = ?2)),
+             billable = MAX(billable, (SELECT billable FROM projects WHERE id = ?2)),
+             pinned = MAX(pinned, (SELECT pinned FROM projects WHERE id = ?2)),
+             notify_override = COALESCE(notify_override, (SELECT notify_override FROM projects WHERE id = ?2)),
+             sound_override = COALESCE(sound_override, (SELECT sound_override FROM projects WHERE id = ?2))
+         WHERE id = ?1",
+        params![target_id, source_id],
+    )
+    .map_err(|e| e.to_string())?;
+
+    let removed = if delete_source {
+        tx.execute("DELETE FROM projects WHERE id = ?1", params![source_id])
+            .map_err(|e| e.to_string())? as i64
+    } else {
+        tx.execute(
+            "UPDATE projects SET archived = 1 WHERE id = ?1",
+            params![source_id],
+        )
+        .map_err(|e| e.to_string())?;
+        0
+    };
+    tx.commit().map_err(|e| e.to_string())?;
+
+    Ok(MergeResult {
+        moved_sessions,
+        removed,
+    })
+}
+
+// Listed ids take positions 0..n in the given order; projects left out keep their relative
+// order after them, so a picker can reorder just the visible subset.
+fn reorder_projects(conn: &mut Connection, project_ids: &[i64]) -> AppResult<()> {
+    let tx = conn.savepoint().map_err(|e| e.to_string())?;
+    let current: Vec<i64> = {
+        let mut stmt = tx
+            .prepare("SELECT id FROM projects WHERE deleted_at IS NULL ORDER BY sort_order, name")
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map([], |row| row.get(0))
+            .map_err(|e| e.to_string())?;
+        rows.collect::<Result<_, _>>().map_err(|e| e.to_string())?
+    };
+    if let Some(unknown) = project_ids.iter().find(|id| !current.contains(id)) {
+        return Err(format!("project {unknown} not found"));
+    }
+    let mut seen = HashSet::new();
+    let order = project_ids
+        .iter()
+        .chain(current.iter().filter(|id| !project_ids.contains(id)))
+        .filter(|id| seen.insert(**id));
+    for (position, id) in order.enumerate() {
+        tx.execute(
+            "UPDATE projects SET sort_order = ?1 WHERE id = ?2",
+            params![position as i64, id],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    tx.commit().map_err(|e| e.to_string())
+}
+
+// Archives live projects whose latest session (as primary project or in a split) ended
+// before the cutoff. Projects never used count from their creation; pinned projects and
+// `keep_id` (the timer's current project) are left alone.
+fn auto_archive_projects(
+    conn: &mut Connection,
+    days: i64,
+    keep_id: Option<i64>,
+    now: i64,
+) -> AppResult<Vec<Project>> {
+    if days <= 0 {
+        return Ok(Vec::new());
+    }
+    let cutoff = now - days * 86_400;
+    let tx = conn.savepoint().map_err(|e| e.to_string())?;
+    let stale: Vec<i64> = {
+        let mut stmt = tx
+            .prepare(
+                "SELECT p.id FROM projects p
+                 WHERE p.archived = 0 AND p.pinned = 0 AND p.deleted_at IS NULL AND p.id IS NOT ?2
+                   AND COALESCE(
+                       (SELECT MAX(s.ended_at) FROM sessions s
+                        WHERE s.deleted_at IS NULL
+                          AND (s.project_id = p.id OR EXISTS (
+                              SELECT 1 FROM session_project_splits sps
+                              WHERE sps.session_id = s.id AND sps.project_id = p.id))),
+                       p.created_at) < ?1
+                 ORDER BY p.id",
+            )
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map(params![cutoff, keep_id], |row| row.get(0))
+            .map_err(|e| e.to_string())?;
+        rows.collect::<Result<_, _>>().map_err(|e| e.to_string())?
+    };
+    let mut archived = Vec::new();
+    for id in stale {
+        tx.execute(
+            "UPDATE projects SET archived = 1 WHERE id = ?1",
+            params![id],
+        )
+        .map_err(|e| e.to_string())?;
+        archived.push(fetch_project(&tx, id)?);
+    }
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(archived)
+}
+
+fn trashed_ids(conn: &Connection, table: &str) -> AppResult<Vec<(i64, i64)>> {
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT id, deleted_at FROM {table} WHERE deleted_at IS NOT NULL ORDER BY deleted_at DESC, id DESC"
+        ))
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+        .map_err(|e| e.to_string())?;
+    rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+}
+
+fn fetch_trash(conn: &Connection) -> AppResult<TrashList> {
+    let mut sessions = Vec::new();
+    for (id, deleted_at) in trashed_ids(conn, "sessions")? {
+        sessions.push(Trashed::new(fetch_session(conn, id)?, deleted_at));
+    }
+    let mut projects = Vec::new();
+    for (id, deleted_at) in trashed_ids(conn, "projects")? {
+        projects.push(Trashed::new(fetch_project(conn, id)?, deleted_at));
+    }
+    let mut tags = Vec::new();
+    for (id, deleted_at) in trashed_ids(conn, "tags")? {
+        let tag = conn
+            .query_row(
+                "SELECT id, name FROM tags WHERE id = ?1",
+                params![id],
+                |row| {
+                    Ok(Tag {
+                        id: row.get(0)?,
+                        name: row.get(1)?,
+                    })
+                },
+            )
+            .map_err(|e| e.to_string())?;
+        tags.push(Trashed::new(tag, deleted_at));
+    }
+
+    Ok(TrashList {
+        sessions,
+        projects,
+        tags,
+    })
+}
+
+// Permanently removes items trashed longer than the retention window. Foreign keys take care
+// of session_tags and project splits, and leave sessions of a purged project unassigned.
+fn purge_trash(conn: &mut Connection, now: i64) -> AppResult<i64> {
+    let cutoff = now - TRASH_RETENTION_SECONDS;
+    let tx = conn.savepoint().map_err(|e| e.to_string())?;
+    let mut removed = 0;
+    for table in ["sessions", "projects", "tags"] {
+        removed += tx
+            .execute(
+                &format!("DELETE FROM {table} WHERE deleted_at IS NOT NULL AND deleted_at <= ?1"),
+                params![cutoff],
+            )
+            .map_err(|e| e.to_string())? as i64;
+    }
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(removed)
+}
+
+// Buckets need this many attempts before they can be recommended.
+const FOCUS_LENGTH_MIN_SAMPLES: i64 = 5;
+// Completion rate a focus length must reach to count as sustainable.
+const FOCUS_LENGTH_TARGET_RATE: f64 = 0.8;
+
+// Completion rate per planned focus length. Incomplete sessions without a recorded plan are
+// left out, since their elapsed time says nothing about the length that was attempted.
+fn focus_length_buckets(
+    conn: &Connection,
+    range: &AnalyticsRange,
+) -> AppResult<Vec<FocusLengthBucket>> {
+    let (filter, values) = build_sessions_filter(range);
+    let sql = format!(
+        "SELECT (COALESCE(planned_sec, duration_sec) + 150) / 300 * 5 AS minutes, COUNT(*), SUM(completed)
+         FROM sessions
+         WHERE phase = 'focus' AND (planned_sec IS NOT NULL OR completed = 1){filter}
+         GROUP BY minutes HAVING minutes > 0 ORDER BY minutes"
+    );
+    let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(rusqlite::params_from_iter(values), |row| {
+            let sessions: i64 = row.get(1)?;
+            let completed: i64 = row.get(2)?;
+            Ok(FocusLengthBucket {
+                minutes: row.get(0)?,
+                sessions,
+                completed,
+                completion_rate: completed as f64 / sessions as f64,
+            })
+        })
+        .map_err(|e| e.to_string())?;
+    rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+}
+
+// The longest well-sampled length finished at least 80% of the time, or failing that the
+// well-sampled length with the best completion rate.
+fn recommend_focus_length(buckets: &[FocusLengthBucket]) -> Option<i64> {
+    let sampled = buckets
+        .iter()
+        .filter(|b| b.sessions >= FOCUS_LENGTH_MIN_SAMPLES);
+    sampled
+        .clone()
+        .filter(|b| b.completion_rate >= FOCUS_LENGTH_TARGET_RATE)
+        .map(|b| b.minutes)
+        .max()
+        .or_else(|| {
+            sampled
+                .max_by(|a, b| a.completion_rate.total_cmp(&b.completion_rate))
+                .map(|b| b.minutes)
+        })
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+struct FocusTotals {
+    focus_seconds: i64,
+    completed_pomodoros: i64,
+    interruptions: i64,
+}
+
+// Focus seconds credited to the project filter bound as ?1: the split share when the session
+// is split, otherwise the whole duration (also when ?1 is NULL).
+const ATTRIBUTED_DURATION_SQL: &str = "CASE
+    WHEN ?1 IS NOT NULL AND EXISTS (SELECT 1 FROM session_project_splits sps WHERE sps.session_id = sessions.id)
+    THEN duration_sec * COALESCE((SELECT percent FROM session_project_splits sps WHERE sps.session_id = sessions.id AND sps.project_id = ?1), 0) / 100
+    ELSE duration_sec
+END";
+
+fn project_filter_value(range: &AnalyticsRange) -> Value {
+    match range.project_id {
+        Some(id) => Value::Integer(id),
+        None => Value::Null,
+    }
+}
+
+// Focus totals grouped by `bucket`, an SQL expression over the sessions row. With a project
+// filter, split sessions count only that project's share.
+fn focus_totals_by<K: rusqlite::types::FromSql + Ord>(
+    conn: &Connection,
+    range: &AnalyticsRange,
+    bucket: &str,
+) -> AppResult<BTreeMap<K, FocusTotals>> {
+    let (filter, filter_values) = build_sessions_filter(range);
+    let mut values = vec![project_filter_value(range)];
+    values.extend(filter_values);
+    let sql = format!(
+        "SELECT {bucket} AS bucket,
+                SUM({ATTRIBUTED_DURATION_SQL}),
+                SUM(completed),
+                SUM(interruptions)
+         FROM sessions
+         WHERE phase = 'focus'{filter}
+         GROUP BY bucket"
+    );
+    let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(rusqlite::params_from_iter(values), |row| {
+            Ok((
+                row.get::<_, K>(0)?,
+                FocusTotals {
+                    focus_seconds: row.get(1)?,
+                    completed_pomodoros: row.get(2)?,
+                    interruptions: row.get(3)?,
+                },
+            ))
+        })
+        .map_err(|e| e.to_string())?;
+    rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+}
+
+// Always 24 buckets, keyed by the local hour a focus session started in.
+fn fetch_hourly_distribution(
+    conn: &Connection,
+    range: &AnalyticsRange,
+) -> AppResult<Vec<HourlyBucket>> {
+    let totals = focus_totals_by::<i64>(
+        conn,
+        range,
+        "CAST(strftime('%H', started_at, 'unixepoch', 'localtime') AS INTEGER)",
+    )?;
+    Ok((0..24)
+        .map(|hour| {
+            let t = totals.get(&hour).copied().unwrap_or_default();
+            HourlyBucket {
+                hour,
+                focus_seconds: t.focus_seconds,
+                completed_pomodoros: t.completed_pomodoros,
+            }
+        })
+        .collect())
+}
+
+// Always 7 buckets, 0 = Sunday as in JavaScript's getDay(), keyed by the local day a focus
+// session ended on like the daily timeseries.
+fn fetch_weekday_distribution(
+    conn: &Connection,
+    range: &AnalyticsRange,
+) -> AppResult<Vec<WeekdayBucket>> {
+    let totals = focus_totals_by::<i64>(
+        conn,
+        range,
+        "CAST(strftime('%w', ended_at, 'unixepoch', 'localtime') AS INTEGER)",
+    )?;
+    Ok((0..7)
+        .map(|weekday| {
+            let t = totals.get(&weekday).copied().unwrap_or_default();
+            WeekdayBucket {
+                weekday,
+                focus_seconds: t.focus_seconds,
+                completed_pomodoros: t.completed_pomodoros,
+                interruptions: t.interruptions,
+            }
+        })
+        .collect())
+}
+
+const DURATION_BUCKET_SEC: i64 = 5 * 60;
+const DURATION_BUCKETS: i64 = 24;
+const GAP_BUCKET_SEC: i64 = 15 * 60;
+const GAP_BUCKETS: i64 = 16;
+
+// Histogram and percentiles of the `v` column produced by `values_sql`, whose `?` parameters
+// are `values`. Buckets run from 0 to the last non-empty one, and everything past `buckets`
+// widths lands in the final one.
+fn distribution_of(
+    conn: &Connection,
+    values_sql: &str,
+    values: &[Value],
+    bucket_sec: i64,
+    buckets: i64,
+) -> AppResult<Distribution> {
+    // The CTE's positional parameters come first, so ours are numbered after them.
+    let (first, second) = (values.len() + 1, values.len() + 2);
+    let mut params = values.to_vec();
+    params.extend([Value::Integer(bucket_sec), Value::Integer(buckets)]);
+    let mut stmt = conn
+        .prepare(&format!(
+            "WITH vals AS ({values_sql})
+             SELECT MIN(v / ?{first}, ?{second}) AS bucket, COUNT(*) FROM vals
+             GROUP BY bucket ORDER BY bucket"
+        ))
+        .map_err(|e| e.to_string())?;
+    let counts: BTreeMap<i64, i64> = stmt
+        .query_map(rusqlite::params_from_iter(params), |row| {
+            Ok((row.get(0)?, row.get(1)?))
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<_, _>>()
+        .map_err(|e| e.to_string())?;
+
+    let percentile = |percent: i64| -> AppResult<Option<i64>> {
+        let mut params = values.to_vec();
+        params.push(Value::Integer(percent));
+        conn.query_row(
+            &format!(
+                "WITH vals AS ({values_sql}),
+                 ranked AS (SELECT v, ROW_NUMBER() OVER (ORDER BY v) AS rn, COUNT(*) OVER () AS n FROM vals)
+                 SELECT v FROM ranked WHERE rn * 100 >= ?{first} * n ORDER BY rn LIMIT 1"
+            ),
+            rusqlite::params_from_iter(params),
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| e.to_string())
+    };
+
+    let last = counts.keys().next_back().copied();
+    Ok(Distribution {
+        count: counts.values().sum(),
+        p50_sec: percentile(50)?,
+        p90_sec: percentile(90)?,
+        buckets: last
+            .map(|last| {
+                (0..=last)
+                    .map(|bucket| DistributionBucket {
+                        from_sec: bucket * bucket_sec,
+                        to_sec: (bucket < buckets).then_some((bucket + 1) * bucket_sec),
+                        count: counts.get(&bucket).copied().unwrap_or(0),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default(),
+    })
+}
+
+fn fetch_session_distribution(
+    conn: &Connection,
+    range: &AnalyticsRange,
+) -> AppResult<SessionDistribution> {
+    let (filter, values) = build_sessions_filter(range);
+    let durations_sql = format!(
+        "SELECT duration_sec AS v FROM sessions WHERE phase = 'focus' AND duration_sec > 0{filter}"
+    );
+    let gaps_sql = format!(
+        "SELECT MAX(v, 0) AS v FROM (
+             SELECT started_at - LAG(ended_at) OVER (
+                 PARTITION BY date(started_at, 'unixepoch', 'localtime') ORDER BY started_at, id
+             ) AS v
+             FROM sessions WHERE phase = 'focus' AND duration_sec > 0{filter}
+         ) WHERE v IS NOT NULL"
+    );
+    Ok(SessionDistribution {
+        durations: distribution_of(
+            conn,
+            &durations_sql,
+            &values,
+            DURATION_BUCKET_SEC,
+            DURATION_BUCKETS,
+        )?,
+        gaps: distribution_of(conn, &gaps_sql, &values, GAP_BUCKET_SEC, GAP_BUCKETS)?,
+    })
+}
+
+// Lower median for an even count, so the result is always a real start time.
+fn median_minute(sorted: &[i64]) -> i64 {
+    sorted[(sorted.len() - 1) / 2]
+}
+
+fn fetch_start_time_stats(
+    conn: &Connection,
+    range: &AnalyticsRange,
+) -> AppResult<Vec<StartTimeStats>> {
+    let bucket = range.granularity.unwrap_or_default().bucket_sql();
+    let (filter, values) = build_sessions_filter(range);
+    let sql = format!(
+        "SELECT {bucket},
+                date(started_at, 'unixepoch', 'localtime'),
+                CAST(strftime('%H', started_at, 'unixepoch', 'localtime') AS INTEGER) * 60
+                    + CAST(strftime('%M', started_at, 'unixepoch', 'localtime') AS INTEGER)
+         FROM sessions
+         WHERE phase = 'focus' AND duration_sec > 0{filter}
+         ORDER BY started_at"
+    );
+    let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(rusqlite::params_from_iter(values), |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, i64>(2)?,
+            ))
+        })
+        .map_err(|e| e.to_string())?;
+
+    // Rows arrive in start order, so the first row seen for a day is its first start.
+    let mut periods: BTreeMap<String, (Vec<i64>, BTreeMap<String, i64>)> = BTreeMap::new();
+    for row in rows {
+        let (period, day, minute) = row.map_err(|e| e.to_string())?;
+        let (starts, first_starts) = periods.entry(period).or_default();
+        starts.push(minute);
+        first_starts.entry(day).or_insert(minute);
+    }
+    Ok(periods
+        .into_iter()
+        .map(|(period, (mut starts, first_starts))| {
+            starts.sort_unstable();
+            let mut firsts: Vec<i64> = first_starts.into_values().collect();
+            firsts.sort_unstable();
+            StartTimeStats {
+                period,
+                sessions: starts.len() as i64,
+                days: firsts.len() as i64,
+                median_start_min: median_minute(&starts),
+                earliest_start_min: starts[0],
+                latest_start_min: starts[starts.len() - 1],
+                median_first_start_min: median_minute(&firsts),
+            }
+        })
+        .collect())
+}
+
+// Projects with an estimate whose last pomodoro falls in the range (or that have none yet).
+// The project filter and exclusions apply; the tag filter does not, since estimates are per
+// project. Actuals cover the project's whole history, not just the range.
+fn fetch_estimation_accuracy(
+    conn: &Connection,
+    range: &AnalyticsRange,
+) -> AppResult<EstimationAccuracy> {
+    let bucket = range.granularity.unwrap_or_default().bucket_sql();
+    let mut values = vec![
+        range.from.map_or(Value::Null, Value::Integer),
+        range.to.map_or(Value::Null, Value::Integer),
+        project_filter_value(range),
+    ];
+    let mut excluded = String::new();
+    if !range.excluded.project_ids.is_empty() {
+        let marks = vec!["?"; range.excluded.project_ids.len()].join(", ");
+        excluded = format!(" AND p.id NOT IN ({marks})");
+        values.extend(
+            range
+                .excluded
+                .project_ids
+                .iter()
+                .map(|id| Value::Integer(*id)),
+        );
+    }
+    let sql = format!(
+        "WITH credited AS (
+             SELECT COALESCE(sps.project_id, s.project_id) AS project_id,
+                    COALESCE(sps.percent, 100) / 100.0 AS share,
+                    s.ended_at
+             FROM sessions s
+             LEFT JOIN session_project_splits sps ON sps.session_id = s.id
+             WHERE s.phase = 'focus' AND s.completed = 1 AND s.deleted_at IS NULL
+         ),
+         per_project AS (
+             SELECT p.id, p.name, p.archived, p.estimated_pomodoros,
+                    COALESCE(SUM(c.share), 0) AS actual, MAX(c.ended_at) AS ended_at
+             FROM projects p
+             LEFT JOIN credited c ON c.project_id = p.id
+             WHERE p.deleted_at IS NULL AND p.estimated_pomodoros > 0
+               AND (?3 IS NULL OR p.id = ?3){excluded}
+             GROUP BY p.id
+         )
+         SELECT id, name, archived, estimated_pomodoros, actual, ended_at,
+                CASE WHEN ended_at IS NULL THEN NULL ELSE {bucket} END
+         FROM per_project
+         WHERE ended_at IS NULL
+            OR ((?1 IS NULL OR ended_at >= ?1) AND (?2 IS NULL OR ended_at <= ?2))
+         ORDER BY archived, name"
+    );
+    let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(rusqlite::params_from_iter(values), |row| {
+            let estimated: i64 = row.get(3)?;
+            let actual: f64 = row.get(4)?;
+            Ok((
+                ProjectEstimate {
+                    project_id: row.get(0)?,
+                    name: row.get(1)?,
+                    archived: row.get::<_, i64>(2)? == 1,
+                    estimated_pomodoros: estimated,
+                    actual_pomodoros: actual,
+                    ratio: actual / estimated as f64,
+                    last_pomodoro_at: row.get(5)?,
+                },
+                row.get::<_, Option<String>>(6)?,
+            ))
+        })
+        .map_err(|e| e.to_string())?;
+
+    let mut projects = Vec::new();
+    let mut periods: BTreeMap<String, Vec<f64>> = BTreeMap::new();
+    for row in rows {
+        let (project, period) = row.map_err(|e| e.to_string())?;
+        if let Some(period) = period {
+            periods.entry(period).or_default().push(project.ratio);
+        }
+        projects.push(project);
+    }
+    let mean = |ratios: &[f64]| ratios.iter().sum::<f64>() / ratios.len() as f64;
+    let started: Vec<f64> = periods.values().flatten().copied().collect();
+    Ok(EstimationAccuracy {
+        projects,
+        mean_ratio: (!started.is_empty()).then(|| mean(&started)),
+        trend: periods
+            .into_iter()
+            .map(|(date, ratios)| EstimationTrendPoint {
+                date,
+                projects: ratios.len() as i64,
+                mean_ratio: mean(&ratios),
+                underestimated: ratios.iter().filter(|r| **r > 1.0).count() as i64,
+                overestimated: ratios.iter().filter(|r| **r < 1.0).count() as i64,
+            })
+            .collect(),
+    })
+}
+
+// Interruptions over completed pomodoros; 0 when nothing was completed.
+fn interruptions_per_pomodoro(totals: &FocusTotals) -> f64 {
+    if totals.completed_pomodoros == 0 {
+        return 0.0;
+    }
+    totals.interruptions as f64 / totals.completed_pomodoros as f64
+}
+
+fn interruption_buckets(
+    totals: &BTreeMap<i64, FocusTotals>,
+    count: i64,
+) -> Vec<InterruptionBucket> {
+    (0..count)
+        .map(|bucket| {
+            let t = totals.get(&bucket).copied().unwrap_or_default();
+            InterruptionBucket {
+                bucket,
+                interruptions: t.interruptions,
+                completed_pomodoros: t.completed_pomodoros,
+                per_pomodoro: interruptions_per_pomodoro(&t),
+            }
+        })
+        .collect()
+}
+
+// Hours use the local start time as in the hourly distribution, weekdays the end date as in
+// the weekday one.
+fn fetch_interruption_analytics(
+    conn: &Connection,
+    range: &AnalyticsRange,
+) -> AppResult<InterruptionAnalytics> {
+    let by_hour = focus_totals_by::<i64>(
+        conn,
+        range,
+        "CAST(strftime('%H', started_at, 'unixepoch', 'localtime') AS INTEGER)",
+    )?;
+    let by_weekday = focus_totals_by::<i64>(
+        conn,
+        range,
+        "CAST(strftime('%w', ended_at, 'unixepoch', 'localtime') AS INTEGER)",
+    )?;
+    let bucket = range.granularity.unwrap_or_default().bucket_sql();
+    let trend = focus_totals_by::<String>(conn, range, bucket)?;
+
+    let overall = by_hour
+        .values()
+        .fold(FocusTotals::default(), |acc, t| FocusTotals {
+            focus_seconds: acc.focus_seconds + t.focus_seconds,
+            completed_pomodoros: acc.completed_pomodoros + t.completed_pomodoros,
+            interruptions: acc.interruptions + t.interruptions,
+        });
+    Ok(InterruptionAnalytics {
+        by_hour: interruption_buckets(&by_hour, 24),
+        by_weekday: interruption_buckets(&by_weekday, 7),
+        average_per_pomodoro: interruptions_per_pomodoro(&overall),
+        trend: trend
+            .into_iter()
+            .map(|(date, t)| InterruptionTrendPoint {
+                date,
+                interruptions: t.interruptions,
+                completed_pomodoros: t.completed_pomodoros,
+                per_pomodoro: interruptions_per_pomodoro(&t),
+            })
+            .collect(),
+    })
+}
+
+fn best_day(
+    days: &BTreeMap<String, FocusTotals>,
+    value: fn(&FocusTotals) -> i64,
+) -> Option<DayRecord> {
+    let mut best: Option<DayRecord> = None;
+    for (date, totals) in days {
+        let value = value(totals);
+        if value > 0 && best.as_ref().is_none_or(|b| value > b.value) {
+            best = Some(DayRecord {
+                date: date.clone(),
+                value,
+            });
+        }
+    }
+    best
+}
+
+// Walks every calendar day from the first focus day to the last under the same rules as the
+// current streak. Only freezes already spent bridge a miss; tokens are not spent on history.
+fn longest_streak(
+    focus_days: &BTreeMap<String, i64>,
+    days_off: &HashSet<String>,
+    frozen: &HashSet<String>,
+    rules: &StreakRules,
+) -> Option<StreakRecord> {
+    let parse = |key: &String| chrono::NaiveDate::parse_from_str(key, "%Y-%m-%d").ok();
+    let first = focus_days.keys().find_map(parse)?;
+    let last = focus_days.keys().rev().find_map(parse)?;
+
+    let mut best: Option<StreakRecord> = None;
+    let mut run: Option<(chrono::NaiveDate, chrono::NaiveDate, i64)> = None;
+    for day in first.iter_days().take_while(|day| *day <= last) {
+        let key = day.format("%Y-%m-%d").to_string();
+        run = match (rules.day(&key, day, focus_days, days_off, frozen), run) {
+            (StreakDay::Counts, Some((start, _, length))) => Some((start, day, length + 1)),
+            (StreakDay::Counts, None) => Some((day, day, 1)),
+            (StreakDay::Bridged, run) => run,
+            (StreakDay::Missed, _) => None,
+        };
+        if let Some((start, end, length)) = run {
+            if best.as_ref().is_none_or(|b| length > b.days) {
+                best = Some(StreakRecord {
+                    days: length,
+                    start_date: start.format("%Y-%m-%d").to_string(),
+                    end_date: end.format("%Y-%m-%d").to_string(),
+                });
+            }
+        }
+    }
+    best
+}
+
+fn compute_records(
+    conn: &Connection,
+    settings: &AppSettings,
+    range: &AnalyticsRange,
+) -> AppResult<AnalyticsRecords> {
+    let days = focus_totals_by::<String>(conn, range, Granularity::Day.bucket_sql())?;
+    let focus_days: BTreeMap<String, i64> = days
+        .iter()
+        .filter(|(_, totals)| totals.focus_seconds > 0)
+        .map(|(day, totals)| (day.clone(), totals.completed_pomodoros))
+        .collect();
+    let frozen = fetch_streak_freezes(conn)?;
+    let (filter, values) = build_sessions_filter(range);
+    let longest_focus_block = conn
+        .query_row(
+            &format!(
+                "SELECT date(ended_at, 'unixepoch', 'localtime'), duration_sec FROM sessions
+                 WHERE phase = 'focus' AND interruptions = 0 AND duration_sec > 0{filter}
+                 ORDER BY duration_sec DESC, ended_at LIMIT 1"
+            ),
+            rusqlite::params_from_iter(values),
+            |row| {
+                Ok(DayRecord {
+                    date: row.get(0)?,
+                    value: row.get(1)?,
+                })
+            },
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+    Ok(AnalyticsRecords {
+        longest_daily_focus: best_day(&days, |t| t.focus_seconds),
+        most_pomodoros_day: best_day(&days, |t| t.completed_pomodoros),
+        longest_streak: longest_streak(
+            &focus_days,
+            &skipped_days(conn, settings)?,
+            &frozen,
+            &StreakRules::from_settings(settings),
+        ),
+        longest_focus_block,
+    })
+}
+
+fn sessions_revision(conn: &Connection) -> AppResult<i64> {
+    conn.query_row(
+        "SELECT COALESCE((SELECT revision FROM data_revisions WHERE domain = 'sessions'), 0)",
+        [],
+        |row| row.get(0),
+    )
+    .map_err(|e| e.to_string())
+}
+
+// Records scan all history, so they are only recomputed after a session write, a change to
+// the exclusion rules, or a newly spent streak freeze. Settings and days off changes clear
+// the cache in finish_mutation.
+fn cached_records(model: &mut AppModel) -> AppResult<AnalyticsRecords> {
+    let revision = sessions_revision(&model.conn)?;
+    let freezes = fetch_streak_freezes(&model.conn)?.len();
+    let range = with_exclusions(AnalyticsRange::default(), &model.settings);
+    if let Some((cached_at, cached_freezes, excluded, records)) = &model.records_cache {
+        if *cached_at == revision && *cached_freezes == freezes && *excluded == range.excluded {
+            return Ok(records.clone());
+        }
+    }
+    let records = compute_records(&model.conn, &model.settings, &range)?;
+    model.records_cache = Some((revision, freezes, range.excluded, records.clone()));
+    Ok(records)
+}
+
+fn fetch_session(conn: &Connection, session_id: i64) -> AppResult<SessionRecord> {
+    let sql = format!("SELECT {SESSION_COLUMNS} FROM sessions WHERE id = ?");
+    query_sessions(conn, &sql, vec![Value::Integer(session_id)])?
+        .pop()
+        .ok_or_else(|| format!("session {session_id} not found"))
+}
+
+// How a merged session credits projects: each side's splits, or its project in full, weighted
+// by focus time. Empty when neither side was split, so project_id alone still says it. Time
+// with no project at all is left out; a single project comes back as one 100% share.
+fn merged_project_splits(keep: &SessionRecord, drop: &SessionRecord) -> Vec<ProjectSplit> {
+    if keep.project_splits.is_empty() && drop.project_splits.is_empty() {
+        return Vec::new();
+    }
+    let mut weights: BTreeMap<i64, i64> = BTreeMap::new();
+    for session in [keep, drop] {
+        let duration = session.duration_sec.max(1);
+        if session.project_splits.is_empty() {
+            if let Some(project_id) = session.project_id {
+                *weights.entry(project_id).or_default() += duration * 100;
+            }
+        }
+        for split in &session.project_splits {
+            *weights.entry(split.project_id).or_default() += duration * split.percent;
+        }
+    }
+    let total: i64 = weights.values().sum();
+    let mut splits: Vec<ProjectSplit> = weights
+        .into_iter()
+        .map(|(project_id, weight)| ProjectSplit {
+            project_id,
+            percent: weight * 100 / total,
+        })
+        .filter(|split| split.percent > 0)
+        .collect();
+    // Rounding down leaves a few percent over; the biggest share takes them.
+    let assigned: i64 = splits.iter().map(|split| split.percent).sum();
+    if let Some(largest) = splits.iter_mut().max_by_key(|split| split.percent) {
+        largest.percent += 100 - assigned;
+    }
+    splits
+}
+
+// Folds the later of two adjacent same-phase sessions into the earlier one.
+// Only neighbours merge: the two may touch or leave a gap, but must not overlap, and no other
+// session may sit in between.
+fn merge_sessions(conn: &mut Connection, first_id: i64, second_id: i64) -> AppResult<i64> {
+    if first_id == second_id {
+        return Err("cannot merge a session with itself".to_string());
+    }
+    let a = fetch_session(conn, first_id)?;
+    let b = fetch_session(conn, second_id)?;
+    let (keep, drop) = if (a.started_at, a.id) <= (b.started_at, b.id) {
+        (a, b)
+    } else {
+        (b, a)
+    };
+    if keep.phase != drop.phase {
+        return Err("only sessions of the same phase can be merged".to_string());
+    }
+    if keep.ended_at > drop.started_at {
+        return Err("sessions overlap".to_string());
+    }
+
+    let tx = conn.savepoint().map_err(|e| e.to_string())?;
+    let between: i64 = tx
+        .query_row(
+            "SELECT COUNT(*) FROM sessions WHERE deleted_at IS NULL AND id NOT IN (?1, ?2)
+                 AND started_at < ?4 AND ended_at > ?3",
+            params![keep.id, drop.id, keep.ended_at, drop.started_at],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+    if between > 0 {
+        return Err("sessions are not adjacent".to_string());
+    }
+    let mut splits = merged_project_splits(&keep, &drop);
+    let project_id = splits
+        .iter()
+        .max_by_key(|split| split.percent)
+        .map(|split| split.project_id)
+        .or(keep.project_id)
+        .or(drop.project_id);
+    if splits.len() == 1 {
+        splits.clear();
+    }
+
+    record_session_revision(&tx, &keep, "merge", now_ts())?;
+    let notes = match (&keep.notes, &drop.notes) {
+        (Some(a), Some(b)) => Some(format!("{a}\n{b}")),
+        (a, b) => a.clone().or_else(|| b.clone()),
+    };
+    tx.execute(
+        "UPDATE sessions SET started_at = ?1, ended_at = ?2, duration_sec = ?3, completed = ?4,
+             interruptions = ?5, project_id = ?6, notes = ?7, extended_sec = ?8, quality = ?9,
+             planned = ?10
+         WHERE id = ?11",
+        params![
+            keep.started_at.min(drop.started_at),
+            keep.ended_at.max(drop.ended_at),
+            keep.duration_sec + drop.duration_sec,
+            (keep.completed || drop.completed) as i64,
+            keep.interruptions + drop.interruptions,
+            project_id,
+            notes,
+            keep.extended_sec + drop.extended_sec,
+            keep.quality.or(drop.quality),
+            keep.planned.or(drop.planned).map(|v| v as i64),
+            keep.id,
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+    tx.execute(
+        "INSERT OR IGNORE INTO session_tags (session_id, tag_id)
+         SELECT ?1, tag_id FROM session_tags WHERE session_id = ?2",
+        params![keep.id, drop.id],
+    )
+    .map_err(|e| e.to_string())?;
+    tx.execute(
+        "DELETE FROM session_tags WHERE session_id = ?1",
+        params![drop.id],
+    )
+    .map_err(|e| e.to_string())?;
+    tx.execute(
+        "DELETE FROM session_project_splits WHERE session_id IN (?1, ?2)",
+        params![keep.id, drop.id],
+    )
+    .map_err(|e| e.to_string())?;
+    for split in &splits {
+        tx.execute(
+            "INSERT INTO session_project_splits (session_id, project_id, percent) VALUES (?1, ?2, ?3)",
+            params![keep.id, split.project_id, split.percent],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    tx.execute("DELETE FROM sessions WHERE id = ?1", params![drop.id])
+        .map_err(|e| e.to_string())?;
+    tx.commit().map_err(|e| e.to_string())?;
+
+    Ok(keep.id)
+}
+
+// Cuts a session at `at` into two records. Duration is divided in proportion to wall time;
+// tags and project splits are copied to both halves, notes and interruptions stay on the first.
+fn split_session(conn: &mut Connection, session_id: i64, at: i64) -> AppResult<(i64, i64)> {
+    let session = fetch_session(conn, session_id)?;
+    if at <= session.started_at || at >= session.ended_at {
+        return Err("split time must fall strictly inside the session".to_string());
+    }
+
+    let span = session.ended_at - session.started_at;
+    let first_duration = session.duration_sec * (at - session.started_at) / span;
+    let second_duration = session.duration_sec - first_duration;
+
+    let tx = conn.savepoint().map_err(|e| e.to_string())?;
+    record_session_revision(&tx, &session, "split", now_ts())?;
+    tx.execute(
+        "UPDATE sessions SET ended_at = ?1, duration_sec = ?2, completed = 0, extended_sec = 0 WHERE id = ?3",
+        params![at, first_duration, session.id],
+    )
+    .map_err(|e| e.to_string())?;
+    tx.execute(
+        "INSERT INTO sessions (started_at, ended_at, phase, duration_sec, completed, interruptions, project_id, extended_sec, quality, planned)
+         VALUES (?1, ?2, ?3, ?4, ?5, 0, ?6, ?7, ?8, ?9)",
+        params![
+            at,
+            session.ended_at,
+            session.phase.as_db_value(),
+            second_duration,
+            session.completed as i64,
+            session.project_id,
+            session.extended_sec,
+            session.quality,
+            session.planned.map(|v| v as i64),
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+    let second_id = tx.last_insert_rowid();
+    tx.execute(
+        "INSERT INTO session_tags (session_id, tag_id)
+         SELECT ?1, tag_id FROM session_tags WHERE session_id = ?2",
+        params![second_id, session.id],
+    )
+    .map_err(|e| e.to_string())?;
+    tx.execute(
+        "INSERT INTO session_project_splits (session_id, project_id, percent)
+         SELECT ?1, project_id, percent FROM session_project_splits WHERE session_id = ?2",
+        params![second_id, session.id],
+    )
+    .map_err(|e| e.to_string())?;
+    tx.commit().map_err(|e| e.to_string())?;
+
+    Ok((session.id, second_id))
+}
+
+fn fetch_sessions(conn: &Connection, range: &AnalyticsRange) -> AppResult<Vec<SessionRecord>> {
+    let (query, values) = build_sessions_query(range);
+    query_sessions(conn, &query, values)
+}
+
+fn query_sessions(
+    conn: &Connection,
+    query: &str,
+    values: Vec<Value>,
+) -> AppResult<Vec<SessionRecord>> {
+    let mut stmt = conn.prepare(query).map_err(|e| e.to_string())?;
+
+    let rows = stmt
+        .query_map(rusqlite::params_from_iter(values), |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, i64>(1)?,
+                row.get::<_, i64>(2)?,
+                row.get::<_, String>(3)?,
+                row.get::<_, i64>(4)?,
+                row.get::<_, i64>(5)?,
+                row.get::<_, i64>(6)?,
+                row.get::<_, Option<i64>>(7)?,
+                row.get::<_, Option<String>>(8)?,
+                row.get::<_, i64>(9)?,
+                row.get::<_, Option<i64>>(10)?,
+                row.get::<_, Option<i64>>(11)?,
+            ))
+        })
+        .map_err(|e| e.to_string())?;
+
+    let rows = rows
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    let ids: Vec<i64> = rows.iter().map(|row| row.0).collect();
+    let mut tags = read_session_tags(conn, &ids)?;
+    let mut splits = read_session_splits(conn, &ids)?;
+
+    let mut sessions = Vec::new();
+    for row in rows {
+        let (
+            id,
+            started_at,
+            ended_at,
+            phase_raw,
+            duration_sec,
+            completed,
+            interruptions,
+            project_id,
+            notes,
+            extended_sec,
+            quality,
+            planned,
+        ) = row;
+        sessions.push(SessionRecord {
+            id,
+            started_at,
+            ended_at,
+            phase: phase_from_db(phase_raw)?,
+            duration_sec,
+            completed: completed == 1,
+            interruptions,
+            project_id,
+            tag_ids: tags.remove(&id).unwrap_or_default(),
+            notes,
+            extended_sec,
+            project_splits: splits.remove(&id).unwrap_or_default(),
+            quality,
+            planned: planned.map(|v| v == 1),
+        });
+    }
+
+    Ok(sessions)
+}
+
+fn day_key(timestamp: i64) -> String {
+    let dt = Local
+        .timestamp_opt(timestamp, 0)
+        .single()
+        .unwrap_or_else(Local::now);
+    format!("{:04}-{:02}-{:02}", dt.year(), dt.month(), dt.day())
+}
+
+// Falls back to the system zone when `timezone` is empty or not a known IANA name.
+fn zoned_datetime(timestamp: i64, timezone: &str) -> chrono::DateTime<chrono::FixedOffset> {
+    let utc = Utc
+        .timestamp_opt(timestamp, 0)
+        .single()
+        .unwrap_or_else(Utc::now);
+    match timezone.parse::<chrono_tz::Tz>() {
+        Ok(zone) => utc.with_timezone(&zone).fixed_offset(),
+        Err(_) => utc.with_timezone(&Local).fixed_offset(),
+    }
+}
+
+fn local_rfc3339(timestamp: i64) -> String {
+    Local
+        .timestamp_opt(timestamp, 0)
+        .single()
+        .unwrap_or_else(Local::now)
+        .to_rfc3339()
+}
+
+// Epoch bounds of the local day holding `now`, from its first existing moment up to the next
+// day's.
+fn local_day_bounds(now: i64) -> (i64, i64) {
+    let date = Local
+        .timestamp_opt(now, 0)
+        .single()
+        .unwrap_or_else(Local::now)
+        .date_naive();
+    let start = local_day_start(date).unwrap_or(now - 86_400);
+    let end = date
+        .succ_opt()
+        .and_then(local_day_start)
+        .unwrap_or(start + 86_400);
+    (start, end)
+}
+
+// The first existing moment of `date` in local time: midnight, or 01:00 where a DST switch
+// skips midnight.
+fn local_day_start(date: chrono::NaiveDate) -> Option<i64> {
+    (0..2).find_map(|hour| {
+        Local
+            .from_local_datetime(&date.and_hms_opt(hour, 0, 0)?)
+            .earliest()
+            .map(|dt| dt.timestamp())
+    })
+}
+
+// Runs after every focus completion, so it sums today's rows in SQL instead of loading them.
+fn remote_today(conn: &Connection, timer: &TimerState, now: i64) -> AppResult<RemoteToday> {
+    let (day_start, day_end) = local_day_bounds(now);
+    let (completed_pomodoros, focus_seconds) = conn
+        .query_row(
+            "SELECT COALESCE(SUM(completed), 0), COALESCE(SUM(duration_sec), 0) FROM sessions
+             WHERE deleted_at IS NULL AND phase = 'focus' AND ended_at >= ?1 AND ended_at < ?2",
+            params![day_start, day_end],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .map_err(|e| e.to_string())?;
+
+    let mut summary = RemoteToday {
+        completed_pomodoros,
+        focus_seconds,
+        project_name: None,
+    };
+    if let Some(project_id) = timer.current_project_id {
+        summary.project_name = conn
+            .query_row(
+                "SELECT name FROM projects WHERE id = ?1",
+                params![project_id],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| e.to_string())?;
+    }
+
+    Ok(summary)
+}
+
+const EXPERIMENT_COLUMNS: &str = "id, focus_a_min, focus_b_min, started_at, ends_at, stopped_at";
+
+fn experiment_from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<Experiment> {
+    Ok(Experiment {
+        id: row.get(0)?,
+        focus_a_min: row.get(1)?,
+        focus_b_min: row.get(2)?,
+        started_at: row.get(3)?,
+        ends_at: row.get(4)?,
+        stopped_at: row.get(5)?,
+    })
+}
+
+fn active_experiment(conn: &Connection, now: i64) -> AppResult<Option<Experiment>> {
+    conn.query_row(
+        &format!(
+            "SELECT {EXPERIMENT_COLUMNS} FROM experiments
+             WHERE stopped_at IS NULL AND ends_at > ?1 ORDER BY id DESC LIMIT 1"
+        ),
+        params![now],
+        experiment_from_row,
+    )
+    .optional()
+    .map_err(|e| e.to_string())
+}
+
+fn experiment_period_end(experiment: &Experiment) -> i64 {
+    experiment.stopped_at.map_or(experiment.ends_at, |stopped| {
+        stopped.min(experiment.ends_at)
+    })
+}
+
+// Focus sessions inside the experiment window, attributed to an arm by their configured
+// length (planned_sec), so a session is counted however it ended.
+fn experiment_report(
+    conn: &Connection,
+    experiment: Experiment,
+    now: i64,
+) -> AppResult<ExperimentReport> {
+    let mut arms = Vec::new();
+    for focus_min in [experiment.focus_a_min, experiment.focus_b_min] {
+        let (sessions, completed, interruptions, focus_seconds): (i64, i64, i64, i64) = conn
+            .query_row(
+                "SELECT COUNT(*), COALESCE(SUM(completed), 0), COALESCE(SUM(interruptions), 0),
+                        COALESCE(SUM(duration_sec), 0)
+                 FROM sessions
+                 WHERE phase = 'focus' AND deleted_at IS NULL AND planned_sec = ?1
+                   AND started_at >= ?2 AND ended_at <= ?3",
+                params![
+                    focus_min * 60,
+                    experiment.started_at,
+                    experiment_period_end(&experiment)
+                ],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+            )
+            .map_err(|e| e.to_string())?;
+        let per_session = |value: i64| (sessions > 0).then(|| value as f64 / sessions as f64);
+        arms.push(ExperimentArm {
+            focus_min,
+            sessions,
+            completed,
+            completion_rate: per_session(completed),
+            interruptions,
+            interruptions_per_session: per_session(interruptions),
+            focus_seconds,
+            avg_focus_seconds: per_session(focus_seconds),
+        });
+    }
+    Ok(ExperimentReport {
+        active: experiment.stopped_at.is_none() && experiment.ends_at > now,
+        experiment,
+        arms,
+    })
+}
+
+// Arm A for even-numbered focus sessions since the experiment began, B for odd ones.
+fn experiment_focus_min(conn: &Connection, experiment: &Experiment) -> AppResult<i64> {
+    let done: i64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM sessions
+             WHERE phase = 'focus' AND deleted_at IS NULL AND started_at >= ?1",
+            params![experiment.started_at],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+    Ok(if done % 2 == 0 {
+        experiment.focus_a_min
+    } else {
+        experiment.focus_b_min
+    })
+}
+
+// Sets an unstarted focus phase to the experiment's next length; returns whether it changed.
+fn apply_experiment_length(conn: &Connection, timer: &mut TimerState, now: i64) -> AppResult<bool> {
+    let untouched = timer.phase == TimerPhase::Focus
+        && !timer.is_running
+        && timer.remaining_seconds == timer.phase_total_seconds;
+    if !untouched {
+        return Ok(false);
+    }
+    let Some(experiment) = active_experiment(conn, now)? else {
+        return Ok(false);
+    };
+    let seconds = experiment_focus_min(conn, &experiment)? * 60;
+    if seconds == timer.phase_total_seconds {
+        return Ok(false);
+    }
+    timer.phase_total_seconds = seconds;
+    timer.remaining_seconds = seconds;
+    Ok(true)
+}
+
+// Local midnight today and on this week's Monday.
+fn goal_period_starts(now: i64) -> AppResult<(i64, i64)> {
+    let today = Local
+        .timestamp_opt(now, 0)
+        .single()
+        .unwrap_or_else(Local::now)
+        .date_naive();
+    let monday = today - chrono::Days::new(u64::from(today.weekday().num_days_from_monday()));
+    let start = |date: chrono::NaiveDate| {
+        local_day_start(date).ok_or_else(|| format!("{date} has no start in the local time zone"))
+    };
+    Ok((start(today)?, start(monday)?))
+}
+
+// Progress in the day and week containing `now`, counting sessions that ended by `until`.
+fn goals_progress_at(
+    conn: &Connection,
+    settings: &AppSettings,
+    now: i64,
+    until: i64,
+) -> AppResult<GoalsProgress> {
+    let (daily, weekly) = (settings.daily_goal_pomodoros, settings.weekly_goal_minutes);
+    if daily == 0 && weekly == 0 {
+        return Ok(GoalsProgress::default());
+    }
+    let (day_start, week_start) = goal_period_starts(now)?;
+    let range = with_exclusions(
+        AnalyticsRange {
+            from: Some(day_start.min(week_start)),
+            to: Some(until),
+            ..Default::default()
+        },
+        settings,
+    );
+    let sessions = fetch_sessions(conn, &range)?;
+    let focus = sessions.iter().filter(|s| s.phase == TimerPhase::Focus);
+    let pomodoros = focus
+        .clone()
+        .filter(|s| s.completed && s.ended_at >= day_start)
+        .count() as i64;
+    let minutes = focus
+        .filter(|s| s.ended_at >= week_start)
+        .map(|s| s.duration_sec)
+        .sum::<i64>()
+        / 60;
+    let progress = |target: i64, value: i64, period_start: i64| {
+        (target > 0).then_some(GoalProgress {
+            target,
+            value,
+            period_start,
+            reached: value >= target,
+        })
+    };
+    Ok(GoalsProgress {
+        daily_pomodoros: progress(daily, pomodoros, day_start),
+        weekly_minutes: progress(weekly, minutes, week_start),
+    })
+}
+
+// Goals the session that ended at `ended_at` pushed over their target.
+fn newly_reached_goals(
+    conn: &Connection,
+    settings: &AppSettings,
+    ended_at: i64,
+) -> AppResult<Vec<GoalReachedEvent>> {
+    let before = goals_progress_at(conn, settings, ended_at, ended_at - 1)?;
+    let after = goals_progress_at(conn, settings, ended_at, ended_at)?;
+    let crossed = |goal: &str, before: Option<GoalProgress>, after: Option<GoalProgress>| {
+        let after = after.filter(|p| p.reached)?;
+        if before.is_some_and(|p| p.reached) {
+            return None;
+        }
+        Some(GoalReachedEvent {
+            goal: goal.to_string(),
+            target: after.target,
+            value: after.value,
+        })
+    };
+    Ok([
+        crossed("daily", before.daily_pomodoros, after.daily_pomodoros),
+        crossed("weekly", before.weekly_minutes, after.weekly_minutes),
+    ]
+    .into_iter()
+    .flatten()
+    .collect())
+}
+
+fn goal_reached_message(language: &str, event: &GoalReachedEvent) -> String {
+    let value = event.value.to_string();
+    if event.goal == "daily" {
+        localize(language, Message::DailyGoalReached, &[("count", &value)])
+    } else {
+        localize(language, Message::WeeklyGoalReached, &[("minutes", &value)])
+    }
+}
+
+fn team_daily_totals(conn: &Connection, days: i64, now: i64) -> AppResult<Vec<TeamDailyTotal>> {
+    let days = days.clamp(1, 366);
+    let sessions = fetch_sessions(
+        conn,
+        &AnalyticsRange {
+            from: Some(now - days * 86_400),
+            to: None,
+            project_id: None,
+            tag_id: None,
+            granularity: None,
+            include_excluded: false,
+            phases: Vec::new(),
+            excluded: AnalyticsExclusions::default(),
+        },
+    )?;
+
+    let mut by_day: BTreeMap<String, TeamDailyTotal> = BTreeMap::new();
+    for session in sessions {
+        if session.phase != TimerPhase::Focus {
+            continue;
+        }
+        let key = day_key(session.ended_at);
+        let entry = by_day.entry(key.clone()).or_insert(TeamDailyTotal {
+            date: key,
+            focus_seconds: 0,
+            completed_pomodoros: 0,
+        });
+        entry.focus_seconds += session.duration_sec;
+        if session.completed {
+            entry.completed_pomodoros += 1;
+        }
+    }
+
+    Ok(by_day.into_values().collect())
+}
+
+// Sessions after `after`, an (ended_at, id) position, oldest first and one page at a time.
+fn health_export(
+    conn: &Connection,
+    after: Option<(i64, i64)>,
+    kind: &str,
+) -> AppResult<HealthExport> {
+    let phase = if kind == "focus" {
+        "phase = 'focus'"
+    } else {
+        "phase != 'focus'"
+    };
+    let (filter, mut values) = build_sessions_filter(&AnalyticsRange::default());
+    let mut sql = format!(
+        "SELECT {SESSION_COLUMNS} FROM sessions WHERE {phase} AND duration_sec > 0{filter}"
+    );
+    if let Some((ended_at, id)) = after {
+        sql.push_str(" AND (ended_at > ? OR (ended_at = ? AND id > ?))");
+        values.push(Value::Integer(ended_at));
+        values.push(Value::Integer(ended_at));
+        values.push(Value::Integer(id));
+    }
+    sql.push_str(" ORDER BY ended_at ASC, id ASC LIMIT ?");
+    // One extra row tells whether another page follows.
+    values.push(Value::Integer(HEALTH_EXPORT_PAGE_SIZE + 1));
+    let mut sessions = query_sessions(conn, &sql, values)?;
+    let has_more = sessions.len() as i64 > HEALTH_EXPORT_PAGE_SIZE;
+    sessions.truncate(HEALTH_EXPORT_PAGE_SIZE as usize);
+
+    let last = sessions
+        .last()
+        .map(|session| (session.ended_at, session.id))
+        .or(after);
+    let entries = sessions
+        .iter()
+        .map(|session| HealthEntry {
+            session_id: session.id,
+            start_date: local_rfc3339(session.started_at),
+            end_date: local_rfc3339(session.ended_at),
+            duration_minutes: session.duration_sec as f64 / 60.0,
+            kind: if kind == "focus" { "focus" } else { "mindful" }.to_string(),
+        })
+        .collect();
+
+    Ok(HealthExport {
+        entries,
+        next_since: last.map(|(ended_at, _)| ended_at),
+        next_cursor: last.map(|(ended_at, id)| format!("{ended_at}:{id}")),
+        has_more,
+    })
+}
+
+fn period_key(timestamp: i64, period: &str) -> AppResult<String> {
+    let dt = Local
+        .timestamp_opt(timestamp, 0)
+        .single()
+        .unwrap_or_else(Local::now);
+    match period {
+        "day" => Ok(day_key(timestamp)),
+        "week" => {
+            let week = dt.iso_week();
+            Ok(format!("{:04}-W{:02}", week.year(), week.week()))
+        }
+        "month" => Ok(format!("{:04}-{:02}", dt.year(), dt.month())),
+        other => Err(format!("unknown period: {other}")),
+    }
+}
+
+fn round_cents(amount: f64) -> f64 {
+    (amount * 100.0).round() / 100.0
+}
+
+// Billable (project id, seconds, earnings) shares of a focus session, following its project
+// splits when present. With `only_project`, other projects' shares are left out.
+fn session_billing(
+    session: &SessionRecord,
+    projects: &BTreeMap<i64, Project>,
+    only_project: Option<i64>,
+) -> Vec<(i64, i64, f64)> {
+    if session.phase != TimerPhase::Focus {
+        return Vec::new();
+    }
+    let shares: Vec<(i64, i64)> = if session.project_splits.is_empty() {
+        session.project_id.map(|id| (id, 100)).into_iter().collect()
+    } else {
+        session
+            .project_splits
+            .iter()
+            .map(|split| (split.project_id, split.percent))
+            .collect()
+    };
+
+    shares
+        .into_iter()
+        .filter(|(project_id, _)| only_project.is_none_or(|only| only == *project_id))
+        .filter_map(|(project_id, percent)| {
+            let project = projects.get(&project_id)?;
+            let rate = project.hourly_rate.filter(|_| project.billable)?;
+            let seconds = session.duration_sec * percent / 100;
+            Some((project_id, seconds, seconds as f64 / 3600.0 * rate))
+        })
+        .collect()
+}
+
+fn billing_report(
+    sessions: &[SessionRecord],
+    projects: &BTreeMap<i64, Project>,
+    only_project: Option<i64>,
+    period: &str,
+) -> AppResult<BillingReport> {
+    let mut by_project: BTreeMap<i64, (i64, f64)> = BTreeMap::new();
+    let mut by_period: BTreeMap<String, (i64, f64)> = BTreeMap::new();
+    for session in sessions {
+        for (project_id, seconds, earnings) in session_billing(session, projects, only_project) {
+            let project = by_project.entry(project_id).or_default();
+            project.0 += seconds;
+            project.1 += earnings;
+            let bucket = by_period
+                .entry(period_key(session.ended_at, period)?)
+                .or_default();
+            bucket.0 += seconds;
+            bucket.1 += earnings;
+        }
+    }
+
+    let by_project: Vec<ProjectBilling> = by_project
+        .into_iter()
+        .map(|(project_id, (billable_seconds, earnings))| {
+            let project = &projects[&project_id];
+            ProjectBilling {
+                project_id,
+                project_name: project.name.clone(),
+                hourly_rate: project.hourly_rate.unwrap_or_default(),
+                billable_seconds,
+                earnings: round_cents(earnings),
+            }
+        })
+        .collect();
+    let total_billable_seconds = by_project.iter().map(|p| p.billable_seconds).sum();
+    let total_earnings = round_cents(by_project.iter().map(|p| p.earnings).sum());
+
+    Ok(BillingReport {
+        by_project,
+        by_period: by_period
+            .into_iter()
+            .map(|(period, (billable_seconds, earnings))| PeriodBilling {
+                period,
+                billable_seconds,
+                earnings: round_cents(earnings),
+            })
+            .collect(),
+        total_billable_seconds,
+        total_earnings,
+    })
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct StreakRules {
+    min_pomodoros: i64,
+    skip_weekends: bool,
+    freeze_tokens: i64,
+}
+
+#[derive(Debug, PartialEq)]
+enum StreakDay {
+    Counts,
+    // Stepped over without adding to the streak: a day off, a skipped weekend or a frozen day.
+    Bridged,
+    Missed,
+}
+
+impl StreakRules {
+    fn from_settings(settings: &AppSettings) -> Self {
+        Self {
+            min_pomodoros: settings.streak_min_pomodoros,
+            skip_weekends: settings.streak_skip_weekends,
+            freeze_tokens: settings.streak_freeze_tokens,
+        }
+    }
+
+    // `focus_days` holds completed pomodoros per day that had any focus time. A qualifying
+    // day off or weekend still counts.
+    fn day(
+        &self,
+        key: &str,
+        date: chrono::NaiveDate,
+        focus_days: &BTreeMap<String, i64>,
+        days_off: &HashSet<String>,
+        frozen: &HashSet<String>,
+    ) -> StreakDay {
+        let weekend = matches!(date.weekday(), chrono::Weekday::Sat | chrono::Weekday::Sun);
+        if focus_days
+            .get(key)
+            .is_some_and(|completed| *completed >= self.min_pomodoros)
+        {
+            StreakDay::Counts
+        } else if days_off.contains(key) || (self.skip_weekends && weekend) || frozen.contains(key)
+        {
+            StreakDay::Bridged
+        } else {
+            StreakDay::Missed
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct StreakCount {
+    days: i64,
+    // Missed days bridged by a token on this walk, for the caller to save in streak_freezes.
+    newly_frozen: Vec<String>,
+}
+
+// Walks back from today over qualifying days; today does not break the streak before it is
+// over. Other misses spend one of the tokens left, i.e. freeze_tokens minus `frozen`. The walk
+// stops at `since`, the start of the data `focus_days` was built from.
+fn calculate_streak_days(
+    focus_days: &BTreeMap<String, i64>,
+    days_off: &HashSet<String>,
+    frozen: &HashSet<String>,
+    rules: &StreakRules,
+    since: Option<chrono::NaiveDate>,
+) -> StreakCount {
+    let mut count = StreakCount {
+        days: 0,
+        newly_frozen: Vec::new(),
+    };
+    let mut tokens = (rules.freeze_tokens - frozen.len() as i64).max(0);
+    let today = Local::now().date_naive();
+    let mut current = today;
+    // A streak cannot bridge more than a year of skipped days; the bound keeps a fully
+    // marked calendar from looping back forever.
+    let mut skipped = 0;
+    while since.is_none_or(|since| current >= since) {
+        let key = current.format("%Y-%m-%d").to_string();
+        match rules.day(&key, current, focus_days, days_off, frozen) {
+            StreakDay::Counts => {
+                count.days += 1;
+                skipped = 0;
+            }
+            StreakDay::Bridged if skipped < 366 => skipped += 1,
+            StreakDay::Missed if current == today => {}
+            StreakDay::Missed if tokens > 0 => {
+                tokens -= 1;
+                count.newly_frozen.push(key);
+            }
+            _ => break,
+        }
+        match current.pred_opt() {
+            Some(prev) => current = prev,
+            None => break,
+        }
+    }
+
+    count
+}
+
+fn fetch_streak_freezes(conn: &Connection) -> AppResult<HashSet<String>> {
+    let mut stmt = conn
+        .prepare("SELECT day FROM streak_freezes")
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([], |row| row.get(0))
+        .map_err(|e| e.to_string())?;
+    rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+}
+
+// Spends tokens on the days the current streak needs bridged, judged on the whole history
+// with no filters or exclusions. Runs when a focus session is recorded; summaries only read
+// the saved freezes, so a filtered or past range never spends one.
+fn spend_streak_freezes(conn: &Connection, settings: &AppSettings, now: i64) -> AppResult<()> {
+    let focus_days = streak_focus_days(fetch_summary_days(conn, &AnalyticsRange::default())?);
+    let streak = calculate_streak_days(
+        &focus_days,
+        &skipped_days(conn, settings)?,
+        &fetch_streak_freezes(conn)?,
+        &StreakRules::from_settings(settings),
+        None,
+    );
+    save_streak_freezes(conn, &streak.newly_frozen, now)
+}
+
+fn save_streak_freezes(conn: &Connection, days: &[String], now: i64) -> AppResult<()> {
+    for day in days {
+        conn.execute(
+            "INSERT OR IGNORE INTO streak_freezes (day, frozen_at) VALUES (?1, ?2)",
+            params![day, now],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+fn fetch_days_off(
+    conn: &Connection,
+    from: Option<&str>,
+    to: Option<&str>,
+) -> AppResult<Vec<DayOff>> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT day, name, source FROM days_off
+             WHERE (?1 IS NULL OR day >= ?1) AND (?2 IS NULL OR day <= ?2) ORDER BY day",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(params![from, to], |row| {
+            Ok(DayOff {
+                day: row.get(0)?,
+                name: row.get(1)?,
+                source: row.get(2)?,
+            })
+        })
+        .map_err(|e| e.to_string())?;
+    rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+}
+
+// The days to skip under settings.skip_days_off; empty when the option is off.
+fn skipped_days(conn: &Connection, settings: &AppSettings) -> AppResult<HashSet<String>> {
+    if !settings.skip_days_off {
+        return Ok(HashSet::new());
+    }
+    Ok(fetch_days_off(conn, None, None)?
+        .into_iter()
+        .map(|d| d.day)
+        .collect())
+}
+
+fn parse_day(day: &str) -> AppResult<chrono::NaiveDate> {
+    chrono::NaiveDate::parse_from_str(day, "%Y-%m-%d").map_err(|_| format!("invalid date: {day}"))
+}
+
+fn ics_unescape(value: &str) -> String {
+    value
+        .replace("\\n", "\n")
+        .replace("\\N", "\n")
+        .replace("\\,", ",")
+        .replace("\\;", ";")
+        .replace("\\\\", "\\")
+}
+
+// `DTSTART;VALUE=DATE:20241225` or `DTSTART:20241225T090000Z`; only the date is kept.
+fn ics_date(value: &str) -> Option<chrono::NaiveDate> {
+    chrono::NaiveDate::parse_from_str(value.get(..8)?, "%Y%m%d").ok()
+}
+
+// Days covered by each VEVENT in an iCalendar file. All-day DTEND is exclusive per RFC 5545;
+// a timed DTEND keeps its own day unless it falls exactly on midnight. Events longer than
+// a year are cut to one.
+fn parse_ics_days_off(content: &str) -> Vec<DayOff> {
+    let mut lines: Vec<String> = Vec::new();
+    for raw in content.lines() {
+        let raw = raw.trim_end_matches('\r');
+        match raw.strip_prefix([' ', '\t']) {
+            Some(continued) if !lines.is_empty() => {
+                if let Some(last) = lines.last_mut() {
+                    last.push_str(continued);
+                }
+            }
+            _ => lines.push(raw.to_string()),
+        }
+    }
+
+    let mut days = Vec::new();
+    let mut event: Option<(Option<String>, Option<String>, Option<String>)> = None;
+    for line in lines {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let name = key
+            .split(';')
+            .next()
+            .unwrap_or_default()
+            .to_ascii_uppercase();
+        match (name.as_str(), event.as_mut()) {
+            ("BEGIN", _) if value.eq_ignore_ascii_case("VEVENT") => {
+                event = Some((None, None, None))
+            }
+            ("DTSTART", Some(e)) => e.0 = Some(value.to_string()),
+            ("DTEND", Some(e)) => e.1 = Some(value.to_string()),
+            ("SUMMARY", Some(e)) => e.2 = Some(ics_unescape(value)),
+            ("END", Some(_)) if value.eq_ignore_ascii_case("VEVENT") => {
+                let Some((Some(start_raw), end_raw, summary)) = event.take() else {
+                    continue;
+                };
+                let Some(start) = ics_date(&start_raw) else {
+                    continue;
+                };
+                let last = match end_raw.as_deref().and_then(|e| ics_date(e).map(|d| (e, d))) {
+                    Some((raw, end)) if raw.len() == 8 || raw.get(9..15) == Some("000000") => {
+                        end.pred_opt().unwrap_or(end)
+                    }
+                    Some((_, end)) => end,
+                    None => start,
+                };
+                let mut day = start;
+                for _ in 0..366 {
+                    if day > last {
+                        break;
+                    }
+                    days.push(DayOff {
+                        day: day.format("%Y-%m-%d").to_string(),
+                        name: summary.clone(),
+                        source: "ics".to_string(),
+                    });
+                    match day.succ_opt() {
+                        Some(next) => day = next,
+                        None => break,
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    days
+}
+
+fn ics_escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+// Content lines longer than 75 octets continue on the next line after a space (RFC 5545
+// 3.1), split on char boundaries.
+fn ics_fold(line: &str, out: &mut String) {
+    let mut width = 0;
+    for ch in line.chars() {
+        if width + ch.len_utf8() > 75 {
+            out.push_str("\r\n ");
+            width = 1;
+        }
+        out.push(ch);
+        width += ch.len_utf8();
+    }
+    out.push_str("\r\n");
+}
+
+fn ics_timestamp(ts: i64) -> String {
+    Utc.timestamp_opt(ts, 0)
+        .single()
+        .map(|t| t.format("%Y%m%dT%H%M%SZ").to_string())
+        .unwrap_or_default()
+}
+
+// One VEVENT per focus session: the project in SUMMARY, tags and notes in DESCRIPTION.
+fn sessions_to_ics(conn: &Connection, sessions: &[SessionRecord], now: i64) -> AppResult<String> {
+    let projects: BTreeMap<i64, String> = fetch_projects(conn)?
+        .into_iter()
+        .map(|p| (p.id, p.name))
+        .collect();
+    let tags: BTreeMap<i64, String> = fetch_tags(conn)?
+        .into_iter()
+        .map(|t| (t.id, t.name))
+        .collect();
+
+    let mut out = String::new();
+    for line in [
+        "BEGIN:VCALENDAR",
+        "VERSION:2.0",
+        "PRODID:-//Pomodoro Pulse//Sessions//EN",
+        "CALSCALE:GREGORIAN",
+    ] {
+        ics_fold(line, &mut out);
+    }
+    for session in sessions.iter().filter(|s| s.phase == TimerPhase::Focus) {
+        let summary = match session.project_id.and_then(|id| projects.get(&id)) {
+            Some(project) => format!("Focus: {project}"),
+            None => "Focus".to_string(),
+        };
+        let tag_names: Vec<&str> = session
+            .tag_ids
+            .iter()
+            .filter_map(|id| tags.get(id).map(String::as_str))
+            .collect();
+        let mut description = Vec::new();
+        if !tag_names.is_empty() {
+            description.push(format!("Tags: {}", tag_names.join(", ")));
+        }
+        if !session.completed {
+            description.push("Not completed".to_string());
+        }
+        description.extend(session.notes.clone());
+
+        ics_fold("BEGIN:VEVENT", &mut out);
+        ics_fold(
+            &format!("UID:session-{}@pomodoro-pulse", session.id),
+            &mut out,
+        );
+        ics_fold(&format!("DTSTAMP:{}", ics_timestamp(now)), &mut out);
+        ics_fold(
+            &format!("DTSTART:{}", ics_timestamp(session.started_at)),
+            &mut out,
+        );
+        ics_fold(
+            &format!("DTEND:{}", ics_timestamp(session.ended_at)),
+            &mut out,
+        );
+        ics_fold(&format!("SUMMARY:{}", ics_escape(&summary)), &mut out);
+        if !description.is_empty() {
+            ics_fold(
+                &format!("DESCRIPTION:{}", ics_escape(&description.join("\n"))),
+                &mut out,
+            );
+        }
+        ics_fold("END:VEVENT", &mut out);
+    }
+    ics_fold("END:VCALENDAR", &mut out);
+    Ok(out)
+}
+
+fn import_days_off(conn: &mut Connection, days: &[DayOff]) -> AppResult<DaysOffImportResult> {
+    let tx = conn.savepoint().map_err(|e| e.to_string())?;
+    let mut imported = 0;
+    for day in days {
+        imported += tx
+            .execute(
+                "INSERT OR IGNORE INTO days_off (day, name, source) VALUES (?1, ?2, ?3)",
+                params![day.day, day.name, day.source],
+            )
+            .map_err(|e| e.to_string())? as i64;
+    }
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(DaysOffImportResult {
+        imported,
+        skipped: days.len() as i64 - imported,
+    })
+}
+
+fn restore_backup_payload(conn: &mut Connection, backup: &BackupPayload) -> AppResult<()> {
+    let tx = conn.savepoint().map_err(|e| e.to_string())?;
+    tx.execute("DELETE FROM session_project_splits", [])
+        .map_err(|e| e.to_string())?;
+    tx.execute("DELETE FROM session_tags", [])
+        .map_err(|e| e.to_string())?;
+    tx.execute("DELETE FROM sessions", [])
+        .map_err(|e| e.to_string())?;
+    tx.execute("DELETE FROM projects", [])
+        .map_err(|e| e.to_string())?;
+    tx.execute("DELETE FROM tags", [])
+        .map_err(|e| e.to_string())?;
+
+    let now = now_ts();
+    let mut project_ids = HashSet::new();
+    for project in &backup.projects {
+        tx.execute(
+            "INSERT INTO projects (id, name, color, archived, billable, hourly_rate, pinned, sort_order,
+                 notify_override, sound_override, estimated_pomodoros, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+            params![
+                project.id,
+                project.name,
+                project.color,
+                project.archived as i64,
+                project.billable as i64,
+                project.hourly_rate,
+                project.pinned as i64,
+                project.sort_order,
+                project.notification_override.notify.map(|v| v as i64),
+                project.notification_override.sound,
+                project.estimated_pomodoros,
+                now
+            ],
+        )
+        .map_err(|e| e.to_string())?;
+        project_ids.insert(project.id);
+    }
+
+    let mut tag_ids = HashSet::new();
+    for tag in &backup.tags {
+        tx.execute(
+            "INSERT INTO tags (id, name, created_at) VALUES (?1, ?2, ?3)",
+            params![tag.id, tag.name, now],
+        )
+        .map_err(|e| e.to_string())?;
+        tag_ids.insert(tag.id);
+    }
+
+    for session in &backup.sessions {
+        // Drop references the backup itself cannot satisfy instead of failing the restore.
+        let project_id = session.project_id.filter(|id| project_ids.contains(id));
+        tx.execute(
+            "INSERT INTO sessions (id, started_at, ended_at, phase, duration_sec, completed, interruptions, project_id, notes, extended_sec, quality, planned)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+            params![
+                session.id,
+                session.started_at,
+                session.ended_at,
+                session.phase.as_db_value(),
+                session.duration_sec,
+                session.completed as i64,
+                session.interruptions,
+                project_id,
+                session.notes,
+                session.extended_sec,
+                session.quality.filter(|q| (1..=5).contains(q)),
+                session.planned.map(|v| v as i64),
+            ],
+        )
+        .map_err(|e| e.to_string())?;
+        for tag_id in session.tag_ids.iter().filter(|id| tag_ids.contains(id)) {
+            tx.execute(
+                "INSERT OR IGNORE INTO session_tags (session_id, tag_id) VALUES (?1, ?2)",
+                params![session.id, tag_id],
+            )
+            .map_err(|e| e.to_string())?;
+        }
+        for split in session
+            .project_splits
+            .iter()
+            .filter(|split| project_ids.contains(&split.project_id))
+        {
+            tx.execute(
+                "INSERT OR IGNORE INTO session_project_splits (session_id, project_id, percent) VALUES (?1, ?2, ?3)",
+                params![session.id, split.project_id, split.percent],
+            )
+            .map_err(|e| e.to_string())?;
+        }
+    }
+
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+// Id of the live project or tag called `name`, compared case-insensitively.
+fn find_named(conn: &Connection, table: &str, name: &str) -> AppResult<Option<i64>> {
+    conn.query_row(
+        &format!("SELECT id FROM {table} WHERE deleted_at IS NULL AND lower(name) = lower(?1)"),
+        params![name.trim()],
+        |row| row.get(0),
+    )
+    .optional()
+    .map_err(|e| e.to_string())
+}
+
+// Restores an export_json backup in one transaction. Rows get fresh ids; the backup's ids
+// are only used to remap project, tag and split references between its own rows.
+fn import_backup(
+    conn: &mut Connection,
+    backup: &BackupPayload,
+    mode: ImportMode,
+    now: i64,
+) -> AppResult<JsonImportResult> {
+    let version = backup.schema_version.unwrap_or(1);
+    if !(1..=BACKUP_SCHEMA_VERSION).contains(&version) {
+        return Err(format!(
+            "unsupported backup schema version {version} (this app reads up to {BACKUP_SCHEMA_VERSION})"
+        ));
+    }
+    for session in &backup.sessions {
+        check_session_fields(
+            session.started_at,
+            session.ended_at,
+            session.duration_sec,
+            session.interruptions,
+            session.quality,
+        )
+        .map_err(|error| format!("session {}: {error}", session.id))?;
+    }
+
+    let tx = conn.savepoint().map_err(|e| e.to_string())?;
+    if mode == ImportMode::Replace {
+        for table in [
+            "session_project_splits",
+            "session_tags",
+            "sessions",
+            "projects",
+            "tags",
+        ] {
+            tx.execute(&format!("DELETE FROM {table}"), [])
+                .map_err(|e| e.to_string())?;
+        }
+    }
+
+    let mut result = JsonImportResult {
+        mode,
+        projects_created: 0,
+        tags_created: 0,
+        sessions_imported: 0,
+        sessions_skipped: 0,
+        settings_restored: false,
+    };
+
+    let mut project_ids = BTreeMap::new();
+    for project in &backup.projects {
+        let id = match find_named(&tx, "projects", &project.name)? {
+            Some(id) => id,
+            None => {
+                tx.execute(
+                    "INSERT INTO projects (name, color, archived, billable, hourly_rate, pinned, sort_order,
+                         notify_override, sound_override, estimated_pomodoros, created_at)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, (SELECT COALESCE(MAX(sort_order), -1) + 1 FROM projects),
+                         ?7, ?8, ?9, ?10)",
+                    params![
+                        project.name,
+                        project.color,
+                        project.archived as i64,
+                        project.billable as i64,
+                        project.hourly_rate,
+                        project.pinned as i64,
+                        project.notification_override.notify.map(|v| v as i64),
+                        project.notification_override.sound,
+                        project.estimated_pomodoros,
+                        now
+                    ],
+                )
+                .map_err(|e| e.to_string())?;
+                result.projects_created += 1;
+                tx.last_insert_rowid()
+            }
+        };
+        project_ids.insert(project.id, id);
+    }
+
+    let mut tag_ids = BTreeMap::new();
+    for tag in &backup.tags {
+        let id = match find_named(&tx, "tags", &tag.name)? {
+            Some(id) => id,
+            None => {
+                tx.execute(
+                    "INSERT INTO tags (name, created_at) VALUES (?1, ?2)",
+                    params![tag.name, now],
+                )
+                .map_err(|e| e.to_string())?;
+                result.tags_created += 1;
+                tx.last_insert_rowid()
+            }
+        };
+        tag_ids.insert(tag.id, id);
+    }
+
+    for session in &backup.sessions {
+        let duplicate = tx
+            .prepare_cached(
+                "SELECT 1 FROM sessions WHERE deleted_at IS NULL AND started_at = ?1 AND phase = ?2",
+            )
+            .and_then(|mut stmt| {
+                stmt.exists(params![session.started_at, session.phase.as_db_value()])
+            })
+            .map_err(|e| e.to_string())?;
+        if duplicate {
+            result.sessions_skipped += 1;
+            continue;
+        }
+        // References the backup itself cannot satisfy are dropped rather than failing.
+        let project_id = session
+            .project_id
+            .and_then(|id| project_ids.get(&id).copied());
+        tx.execute(
+            "INSERT INTO sessions (started_at, ended_at, phase, duration_sec, completed, interruptions, project_id, notes, extended_sec, quality, planned)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+            params![
+                session.started_at,
+                session.ended_at,
+                session.phase.as_db_value(),
+                session.duration_sec,
+                session.completed as i64,
+                session.interruptions,
+                project_id,
+                session.notes,
+                session.extended_sec,
+                session.quality,
+                session.planned.map(|v| v as i64),
+            ],
+        )
+        .map_err(|e| e.to_string())?;
+        let session_id = tx.last_insert_rowid();
+        for tag_id in session.tag_ids.iter().filter_map(|id| tag_ids.get(id)) {
+            tx.execute(
+                "INSERT OR IGNORE INTO session_tags (session_id, tag_id) VALUES (?1, ?2)",
+                params![session_id, tag_id],
+            )
+            .map_err(|e| e.to_string())?;
+        }
+        for split in &session.project_splits {
+            if let Some(project_id) = project_ids.get(&split.project_id) {
+                tx.execute(
+                    "INSERT OR IGNORE INTO session_project_splits (session_id, project_id, percent) VALUES (?1, ?2, ?3)",
+                    params![session_id, project_id, split.percent],
+                )
+                .map_err(|e| e.to_string())?;
+            }
+        }
+        result.sessions_imported += 1;
+    }
+
+    if mode == ImportMode::Replace {
+        if let Some(settings) = &backup.settings {
+            save_json_setting(&tx, APP_SETTINGS_KEY, &normalize_settings(settings.clone()))?;
+            result.settings_restored = true;
+        }
+    }
+
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(result)
+}
+
+fn load_cloud_backup_config(conn: &Connection) -> AppResult<CloudBackupConfig> {
+    Ok(load_json_setting::<CloudBackupConfig>(conn, CLOUD_BACKUP_KEY)?.unwrap_or_default())
+}
+
+fn derive_backup_key(passphrase: &str, salt: &[u8]) -> AppResult<[u8; 32]> {
+    let mut key = [0u8; 32];
+    argon2::Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| format!("backup key derivation failed: {e}"))?;
+    Ok(key)
+}
+
+// Layout: magic | 16-byte salt | 12-byte nonce | ChaCha20-Poly1305 ciphertext.
+fn encrypt_backup(plaintext: &[u8], passphrase: &str) -> AppResult<Vec<u8>> {
+    if passphrase.is_empty() {
+        return Err("cloud backup passphrase is not set".to_string());
+    }
+
+    let mut salt = [0u8; 16];
+    let mut nonce = [0u8; 12];
+    rand::thread_rng().fill(&mut salt);
+    rand::thread_rng().fill(&mut nonce);
+
+    let key = derive_backup_key(passphrase, &salt)?;
+    let cipher = ChaCha20Poly1305::new(Key::from_slice(&key));
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(&nonce), plaintext)
+        .map_err(|_| "backup encryption failed".to_string())?;
+
+    let mut out = Vec::with_capacity(CLOUD_BACKUP_MAGIC.len() + 28 + ciphertext.len());
+    out.extend_from_slice(CLOUD_BACKUP_MAGIC);
+    out.extend_from_slice(&salt);
+    out.extend_from_slice(&nonce);
+    out.extend_from_slice(&ciphertext);
+    Ok(out)
+}
+
+fn decrypt_backup(data: &[u8], passphrase: &str) -> AppResult<Vec<u8>> {
+    let salt_start = CLOUD_BACKUP_MAGIC.len();
+    let nonce_start = salt_start + 16;
+    let body_start = nonce_start + 12;
+    if data.len() < body_start || !data.starts_with(CLOUD_BACKUP_MAGIC) {
+        return Err("not an encrypted pomodoro backup".to_string());
+    }
+
+    let key = derive_backup_key(passphrase, &data[salt_start..nonce_start])?;
+    let cipher = ChaCha20Poly1305::new(Key::from_slice(&key));
+    cipher
+        .decrypt(
+            Nonce::from_slice(&data[nonce_start..body_start]),
+            &data[body_start..],
+        )
+        .map_err(|_| "backup decryption failed (wrong passphrase?)".to_string())
+}
+
+fn webdav_file_url(config: &CloudBackupConfig, filename: &str) -> AppResult<String> {
+    let base = config.webdav_url.trim().trim_end_matches('/');
+    if base.is_empty() {
+        return Err("cloud backup URL is not configured".to_string());
+    }
+    Ok(format!("{base}/{filename}"))
+}
+
+fn webdav_request(config: &CloudBackupConfig, method: &str, url: &str) -> ureq::Request {
+    let mut request = ureq::request(method, url).timeout(Duration::from_secs(60));
+    if !config.username.is_empty() {
+        let credentials =
+            BASE64_STANDARD.encode(format!("{}:{}", config.username, config.password));
+        request = request.set("Authorization", &format!("Basic {credentials}"));
+    }
+    request
+}
+
+enum WebdavPrecondition<'a> {
+    None,
+    // Only overwrite the file if it still has this ETag.
+    Matches(&'a str),
+    // Only create the file if it does not exist yet.
+    Absent,
+}
+
+// Returns Ok(false) when the server rejected the write because its precondition failed.
+fn webdav_put(
+    config: &CloudBackupConfig,
+    filename: &str,
+    body: &[u8],
+    precondition: WebdavPrecondition<'_>,
+) -> AppResult<bool> {
+    let url = webdav_file_url(config, filename)?;
+    let request =
+        webdav_request(config, "PUT", &url).set("Content-Type", "application/octet-stream");
+    let request = match precondition {
+        WebdavPrecondition::None => request,
+        WebdavPrecondition::Matches(etag) => request.set("If-Match", etag),
+        WebdavPrecondition::Absent => request.set("If-None-Match", "*"),
+    };
+    match request.send_bytes(body) {
+        Ok(_) => Ok(true),
+        Err(ureq::Error::Status(412, _)) => Ok(false),
+        Err(e) => Err(format!("WebDAV upload of {filename} failed: {e}")),
+    }
+}
+
+// Returns the file body and its ETag, or None when the file does not exist.
+fn webdav_get(
+    config: &CloudBackupConfig,
+    filename: &str,
+) -> AppResult<Option<(Vec<u8>, Option<String>)>> {
+    let url = webdav_file_url(config, filename)?;
+    let response = match webdav_request(config, "GET", &url).call() {
+        Ok(response) => response,
+        Err(ureq::Error::Status(404, _)) => return Ok(None),
+        Err(e) => return Err(format!("WebDAV download of {filename} failed: {e}")),
+    };
+    let etag = response.header("ETag").map(str::to_string);
+    let mut data = Vec::new();
+    response
+        .into_reader()
+        .take(CLOUD_BACKUP_MAX_BYTES)
+        .read_to_end(&mut data)
+        .map_err(|e| format!("WebDAV download of {filename} failed: {e}"))?;
+    Ok(Some((data, etag)))
+}
+
+fn cloud_backup_due(config: &CloudBackupConfig, now: i64) -> bool {
+    if !config.enabled
+        || config.interval_hours <= 0
+        || config.webdav_url.trim().is_empty()
+        || config.passphrase.is_empty()
+    {
+        return false;
+    }
+
+    let interval = config.interval_hours * 3600;
+    // Failed uploads are retried sooner than the regular schedule.
+    let wait = if config.last_error.is_some() {
+        interval.min(CLOUD_BACKUP_RETRY_SECONDS)
+    } else {
+        interval
+    };
+    match config.last_attempt_at.or(config.last_backup_at) {
+        Some(last) => now - last >= wait,
+        None => true,
+    }
+}
+
+fn run_cloud_backup(app: &AppHandle) -> AppResult<CloudBackupStatus> {
+    let state = app.state::<AppState>();
+    let (config, payload) = {
+        let model = state.model.lock().map_err(|e| e.to_string())?;
+        let config = load_cloud_backup_config(&model.conn)?;
+        let payload = build_backup_payload(
+            &model.conn,
+            &model.settings,
+            &ExportRange {
+                from: None,
+                to: None,
+            },
+        )?;
+        (config, payload)
+    };
+
+    // Encrypt and upload without holding the model lock; the timer keeps ticking meanwhile.
+    let result = serde_json::to_vec(&payload)
+        .map_err(|e| e.to_string())
+        .and_then(|plaintext| encrypt_backup(&plaintext, &config.passphrase))
+        .and_then(|encrypted| {
+            webdav_put(
+                &config,
+                CLOUD_BACKUP_FILENAME,
+                &encrypted,
+                WebdavPrecondition::None,
+            )
+            .map(|_| ())
+        });
+
+    let status = {
+        let model = state.model.lock().map_err(|e| e.to_string())?;
+        let mut config = load_cloud_backup_config(&model.conn)?;
+        let now = now_ts();
+        config.last_attempt_at = Some(now);
+        match &result {
+            Ok(()) => {
+                config.last_backup_at = Some(now);
+                config.last_error = None;
+            }
+            Err(error) => config.last_error = Some(error.clone()),
+        }
+        save_json_setting(&model.conn, CLOUD_BACKUP_KEY, &config)?;
+        config.status()
+    };
+
+    match result {
+        Ok(()) => {
+            let _ = app.emit("cloud-backup://completed", &status);
+            Ok(status)
+        }
+        Err(error) => {
+            let _ = app.emit("cloud-backup://failed", &status);
+            Err(error)
+        }
+    }
+}
+
+fn spawn_cloud_backup_worker(app: AppHandle) {
+    thread::spawn(move || loop {
+        thread::sleep(Duration::from_secs(60));
+
+        let due = {
+            let state = app.state::<AppState>();
+            let model = match state.model.lock() {
+                Ok(guard) => guard,
+                Err(_) => continue,
+            };
+            match load_cloud_backup_config(&model.conn) {
+                Ok(config) => cloud_backup_due(&config, now_ts()),
+                Err(_) => false,
+            }
+        };
+
+        if due {
+            if let Err(error) = run_cloud_backup(&app) {
+                eprintln!("scheduled cloud backup failed: {error}");
+            }
+        }
+    });
+}
+
+const LOCAL_BACKUP_PREFIX: &str = "pomodoro-backup-";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LocalBackup {
+    path: String,
+    filename: String,
+    format: String,
+    created_at: i64,
+    size_bytes: u64,
+}
+
+fn local_backup_dir(app: &AppHandle, settings: &AppSettings) -> AppResult<std::path::PathBuf> {
+    if !settings.backup_directory.is_empty() {
+        return Ok(std::path::PathBuf::from(&settings.backup_directory));
+    }
+    let app_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    Ok(app_dir.join("backups"))
+}
+
+// Only files this subsystem wrote count: the timestamp is read back from the name, so
+// retention never touches anything else the user keeps in the same folder.
+fn list_local_backups(dir: &std::path::Path) -> AppResult<Vec<LocalBackup>> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(error) => return Err(format!("cannot read {}: {error}", dir.display())),
+    };
+
+    let mut backups = Vec::new();
+    for entry in entries.flatten() {
+        let filename = entry.file_name().to_string_lossy().to_string();
+        let Some(rest) = filename.strip_prefix(LOCAL_BACKUP_PREFIX) else {
+            continue;
+        };
+        let Some((stamp, format)) = rest
+            .strip_suffix(".json")
+            .map(|stamp| (stamp, "json"))
+            .or_else(|| rest.strip_suffix(".db").map(|stamp| (stamp, "sqlite")))
+        else {
+            continue;
+        };
+        let stamp = stamp.split_once('-').map_or(stamp, |(stamp, _)| stamp);
+        let Ok(created_at) = stamp.parse::<i64>() else {
+            continue;
+        };
+        let size_bytes = entry.metadata().map(|meta| meta.len()).unwrap_or(0);
+        backups.push(LocalBackup {
+            path: entry.path().to_string_lossy().to_string(),
+            filename,
+            format: format.to_string(),
+            created_at,
+            size_bytes,
+        });
+    }
+    backups.sort_by_key(|backup| std::cmp::Reverse(backup.created_at));
+    Ok(backups)
+}
+
+// The backup's bytes, taken while the model is locked so they are consistent. A sqlite
+// backup is copied to the local temp folder first, which is far quicker than writing to a slow
+// disk or share, and loses the private settings there before it goes anywhere else.
+fn snapshot_local_backup(conn: &Connection, settings: &AppSettings) -> AppResult<Vec<u8>> {
+    if settings.backup_format == "sqlite" {
+        let copy =
+            std::env::temp_dir().join(format!("pomodoro-snapshot-{}.db", std::process::id()));
+        let data = export_database_file(conn, &copy)
+            .and_then(|_| fs::read(&copy).map_err(|e| e.to_string()));
+        let _ = fs::remove_file(&copy);
+        return data;
+    }
+    let payload = build_backup_payload(
+        conn,
+        settings,
+        &ExportRange {
+            from: None,
+            to: None,
+        },
+    )?;
+    serde_json::to_vec_pretty(&payload).map_err(|e| e.to_string())
+}
+
+// Runs without the model lock. A second backup in the same second gets a `-2`, `-3`... suffix;
+// the name is claimed with create_new so two writers never pick the same one.
+fn write_local_backup(
+    dir: &std::path::Path,
+    format: &str,
+    content: &[u8],
+    now: i64,
+) -> AppResult<LocalBackup> {
+    fs::create_dir_all(dir).map_err(|e| format!("cannot create {}: {e}", dir.display()))?;
+    let extension = if format == "sqlite" { "db" } else { "json" };
+    let mut attempt = 1;
+    let (filename, path) = loop {
+        let filename = if attempt == 1 {
+            format!("{LOCAL_BACKUP_PREFIX}{now}.{extension}")
+        } else {
+            format!("{LOCAL_BACKUP_PREFIX}{now}-{attempt}.{extension}")
+        };
+        let path = dir.join(&filename);
+        match fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)
+        {
+            Ok(_) => break (filename, path),
+            Err(error) if error.kind() == std::io::ErrorKind::AlreadyExists => attempt += 1,
+            Err(error) => return Err(format!("cannot write {}: {error}", path.display())),
+        }
+    };
+
+    // Written next to the target first so a crash never leaves a truncated backup behind.
+    let partial = dir.join(format!("{filename}.partial"));
+    let written = fs::write(&partial, content).and_then(|_| fs::rename(&partial, &path));
+    if let Err(error) = written {
+        let _ = fs::remove_file(&partial);
+        let _ = fs::remove_file(&path);
+        return Err(error.to_string());
+    }
+
+    Ok(LocalBackup {
+        path: path.to_string_lossy().to_string(),
+        filename,
+        format: format.to_string(),
+        created_at: now,
+        size_bytes: content.len() as u64,
+    })
+}
+
+// Deletes everything past the newest `keep` backups; returns how many were removed.
+fn prune_local_backups(dir: &std::path::Path, keep: i64) -> AppResult<usize> {
+    let backups = list_local_backups(dir)?;
+    let keep = keep.max(1) as usize;
+    let mut removed = 0;
+    for backup in backups.iter().skip(keep) {
+        fs::remove_file(&backup.path)
+            .map_err(|e| format!("cannot delete {}: {e}", backup.filename))?;
+        removed += 1;
+    }
+    Ok(removed)
+}
+
+fn local_backup_due(settings: &AppSettings, backups: &[LocalBackup], now: i64) -> bool {
+    if !settings.backup_enabled {
+        return false;
+    }
+    match backups.first() {
+        Some(latest) => now - latest.created_at >= settings.backup_interval_hours * 3600,
+        None => true,
+    }
+}
+
+fn run_local_backup(app: &AppHandle) -> AppResult<LocalBackup> {
+    let state = app.state::<AppState>();
+    let (content, settings) = {
+        let model = state.model.lock().map_err(|e| e.to_string())?;
+        let content = snapshot_local_backup(&model.conn, &model.settings)?;
+        (content, model.settings.clone())
+    };
+    let dir = local_backup_dir(app, &settings)?;
+    let backup = write_local_backup(&dir, &settings.backup_format, &content, now_ts())?;
+    prune_local_backups(&dir, settings.backup_keep)?;
+    let _ = app.emit("backup://completed", &backup);
+    Ok(backup)
+}
+
+fn spawn_local_backup_worker(app: AppHandle) {
+    thread::spawn(move || loop {
+        thread::sleep(Duration::from_secs(60));
+
+        let due = {
+            let state = app.state::<AppState>();
+            let settings = match state.model.lock() {
+                Ok(model) => model.settings.clone(),
+                Err(_) => continue,
+            };
+            // Directory listing happens outside the lock; a slow network share must not stall the timer.
+            local_backup_dir(&app, &settings)
+                .and_then(|dir| list_local_backups(&dir))
+                .map(|backups| local_backup_due(&settings, &backups, now_ts()))
+                .unwrap_or(false)
+        };
+
+        if due {
+            if let Err(error) = run_local_backup(&app) {
+                eprintln!("scheduled local backup failed: {error}");
+                let _ = app.emit("backup://failed", &error);
+            }
+        }
+    });
+}
+
+// Last seen commit counter of other connections and per-domain revisions.
+#[derive(Debug, Default)]
+struct DataWatch {
+    data_version: i64,
+    revisions: BTreeMap<String, i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DataChangedEvent {
+    domains: Vec<String>,
+}
+
+fn read_data_revisions(conn: &Connection) -> AppResult<BTreeMap<String, i64>> {
+    let mut stmt = conn
+        .prepare("SELECT domain, revision FROM data_revisions")
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+        .map_err(|e| e.to_string())?;
+    rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+}
+
+// Domains written by other processes since the last poll. PRAGMA data_version only moves
+// when another connection commits, so the app's own writes just refresh the baseline. Both
+// kinds landing between two polls report the app's domains too, which only costs a reload.
+fn poll_external_changes(conn: &Connection, watch: &mut DataWatch) -> AppResult<Vec<String>> {
+    let data_version: i64 = conn
+        .query_row("PRAGMA data_version", [], |row| row.get(0))
+        .map_err(|e| e.to_string())?;
+    let revisions = read_data_revisions(conn)?;
+    let domains = if data_version == watch.data_version {
+        Vec::new()
+    } else {
+        revisions
+            .iter()
+            .filter(|(domain, revision)| watch.revisions.get(*domain) != Some(revision))
+            .map(|(domain, _)| domain.clone())
+            .collect()
+    };
+    watch.data_version = data_version;
+    watch.revisions = revisions;
+    Ok(domains)
+}
+
+fn spawn_data_watcher(app: AppHandle) {
+    thread::spawn(move || {
+        let mut watch = DataWatch::default();
+        let mut primed = false;
+        loop {
+            let state = app.state::<AppState>();
+            let changed = match state.model.lock() {
+                Ok(mut model) => {
+                    poll_external_changes(&model.conn, &mut watch).and_then(|domains| {
+                        let stale = primed
+                            && domains
+                                .iter()
+                                .any(|domain| domain == "settings" || domain == "timer");
+                        if stale {
+                            reload_model(&mut model)?;
+                        }
+                        Ok((
+                            domains,
+                            stale.then(|| (model.settings.clone(), model.timer.clone())),
+                        ))
+                    })
+                }
+                Err(e) => Err(e.to_string()),
+            };
+            match changed {
+                Ok((domains, reloaded)) if primed && !domains.is_empty() => {
+                    if let Some((settings, timer)) = reloaded {
+                        if let Err(error) = apply_settings_outside_model(&app, &settings) {
+                            eprintln!("data watcher settings refresh failed: {error}");
+                        }
+                        emit_timer_state(&app, &timer);
+                    }
+                    let _ = app.emit("data://changed", DataChangedEvent { domains });
+                }
+                Ok(_) => {}
+                Err(error) => eprintln!("data watcher poll failed: {error}"),
+            }
+            primed = true;
+            thread::sleep(Duration::from_secs(2));
+        }
+    });
+}
+
+// What a mutating command can change. Sessions, projects, tags, settings and the timer match
+// data_revisions domains, so data://changed carries the same names whether the watcher or
+// `mutate` sends it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum DataDomain {
+    Sessions,
+    Projects,
+    Tags,
+    Settings,
+    Timer,
+    DaysOff,
+    Music,
+    Experiments,
+    // Remote tokens, paired devices and push subscriptions.
+    Remote,
+    Plan,
+}
+
+impl DataDomain {
+    const ALL: [DataDomain; 10] = [
+        DataDomain::Sessions,
+        DataDomain::Projects,
+        DataDomain::Tags,
+        DataDomain::Settings,
+        DataDomain::Timer,
+        DataDomain::DaysOff,
+        DataDomain::Music,
+        DataDomain::Experiments,
+        DataDomain::Remote,
+        DataDomain::Plan,
+    ];
+
+    fn as_str(self) -> &'static str {
+        match self {
+            DataDomain::Sessions => "sessions",
+            DataDomain::Projects => "projects",
+            DataDomain::Tags => "tags",
+            DataDomain::Settings => "settings",
+            DataDomain::Timer => "timer",
+            DataDomain::DaysOff => "days_off",
+            DataDomain::Music => "music",
+            DataDomain::Experiments => "experiments",
+            DataDomain::Remote => "remote",
+            DataDomain::Plan => "plan",
+        }
+    }
+}
+
+// Domains a command changed. It starts with the ones the command declares; `touch` adds
+// incidental ones, like the timer when the current project is deleted.
+#[derive(Debug, Default)]
+struct Mutation {
+    domains: BTreeSet<DataDomain>,
+}
+
+impl Mutation {
+    fn touch(&mut self, domain: DataDomain) {
+        self.domains.insert(domain);
+    }
+
+    fn touches(&self, domain: DataDomain) -> bool {
+        self.domains.contains(&domain)
+    }
+}
+
+const MUTATION_LOG_LIMIT: i64 = 5000;
+
+// Central path for mutating commands: runs `run` under the model lock and does what every
+// mutation owes (see `finish_mutation`). Once the lock is released it applies changed
+// settings to the tray and remote server, then emits timer://state and data://changed.
+fn mutate<T>(
+    app: &AppHandle,
+    command: &str,
+    domains: &[DataDomain],
+    run: impl FnOnce(&mut AppModel, &mut Mutation) -> AppResult<T>,
+) -> AppResult<T> {
+    let state = app.state::<AppState>();
+    let (value, mutation, settings, timer) = {
+        let mut model = lock_model(&state)?;
+        let (value, mutation) = mutate_model(&mut model, command, domains, run)?;
+        let settings = mutation
+            .touches(DataDomain::Settings)
+            .then(|| model.settings.clone());
+        let timer = mutation
+            .touches(DataDomain::Timer)
+            .then(|| model.timer.clone());
+        (value, mutation, settings, timer)
+    };
+    if let Some(settings) = settings {
+        apply_settings_outside_model(app, &settings)?;
+    }
+    if let Some(timer) = timer {
+        emit_timer_state(app, &timer);
+    }
+    emit_data_changed(app, &mutation);
+    Ok(value)
+}
+
+// The transactional half of `mutate`, for callers that already hold the model lock (the
+// timer worker). The change, the saved timer and the mutation_log row commit together, so
+// a failure anywhere leaves nothing half-written. Functions that open their own
+// transaction use savepoints, which nest inside this one.
+fn mutate_model<T>(
+    model: &mut AppModel,
+    command: &str,
+    domains: &[DataDomain],
+    run: impl FnOnce(&mut AppModel, &mut Mutation) -> AppResult<T>,
+) -> AppResult<(T, Mutation)> {
+    let mut mutation = Mutation {
+        domains: domains.iter().copied().collect(),
+    };
+    model
+        .conn
+        .execute_batch("BEGIN")
+        .map_err(|e| e.to_string())?;
+    let result = run(model, &mut mutation)
+        .and_then(|value| finish_mutation(model, command, &mutation, now_ts()).map(|_| value));
+    // database_import swaps in a new connection, and the old one took the transaction with
+    // it; the new file is already complete and the log row was written on its own.
+    if model.conn.is_autocommit() {
+        return result.map(|value| (value, mutation));
+    }
+    let committed = result.and_then(|value| {
+        model
+            .conn
+            .execute_batch("COMMIT")
+            .map(|_| value)
+            .map_err(|e| e.to_string())
+    });
+    match committed {
+        Ok(value) => Ok((value, mutation)),
+        Err(error) => {
+            let _ = model.conn.execute_batch("ROLLBACK");
+            Err(error)
+        }
+    }
+}
+
+fn emit_data_changed(app: &AppHandle, mutation: &Mutation) {
+    let domains = mutation
+        .domains
+        .iter()
+        .map(|domain| domain.as_str().to_string())
+        .collect();
+    let _ = app.emit("data://changed", DataChangedEvent { domains });
+}
+
+// Saves the timer if it changed, drops the analytics records cache and appends the
+// command to mutation_log, which keeps the last MUTATION_LOG_LIMIT entries.
+fn finish_mutation(
+    model: &mut AppModel,
+    command: &str,
+    mutation: &Mutation,
+    at: i64,
+) -> AppResult<()> {
+    if mutation.touches(DataDomain::Timer) {
+        persist_timer(model)?;
+    }
+    if [
+        DataDomain::Sessions,
+        DataDomain::Projects,
+        DataDomain::Tags,
+        DataDomain::Settings,
+        DataDomain::DaysOff,
+    ]
+    .iter()
+    .any(|domain| mutation.touches(*domain))
+    {
+        model.records_cache = None;
+    }
+    // Only timer changes get through in read-only mode, and those are queued instead.
+    if model.read_only.is_some() {
+        return Ok(());
+    }
+    let domains = mutation
+        .domains
+        .iter()
+        .map(|domain| domain.as_str())
+        .collect::<Vec<_>>()
+        .join(",");
+    model
+        .conn
+        .execute(
+            "INSERT INTO mutation_log (command, domains, at) VALUES (?1, ?2, ?3)",
+            params![command, domains, at],
+        )
+        .map_err(|e| e.to_string())?;
+    let id = model.conn.last_insert_rowid();
+    model
+        .conn
+        .execute(
+            "DELETE FROM mutation_log WHERE id <= ?1",
+            params![id - MUTATION_LOG_LIMIT],
+        )
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+fn spawn_maintenance_worker(app: AppHandle) {
+    thread::spawn(move || loop {
+        {
+            let result = mutate(
+                &app,
+                "trash_purge",
+                &[DataDomain::Sessions, DataDomain::Projects, DataDomain::Tags],
+                |model, _| purge_trash(&mut model.conn, now_ts()),
+            );
+            if let Err(error) = result {
+                eprintln!("trash purge failed: {error}");
+            }
+            if let Err(error) = run_auto_archive(&app, None) {
+                eprintln!("auto-archive failed: {error}");
+            }
+        }
+        thread::sleep(Duration::from_secs(6 * 60 * 60));
+    });
+}
+
+// Swap in restored data and settings, then reset the timer the same way reset_all_data does.
+// `after` runs in the same transaction, for bookkeeping that must land with the data.
+fn apply_restored_backup(
+    app: &AppHandle,
+    command: &str,
+    backup: &BackupPayload,
+    after: impl FnOnce(&Connection) -> AppResult<()>,
+) -> AppResult<ResetAllResult> {
+    mutate(app, command, &DataDomain::ALL, |model, _| {
+        restore_backup_payload(&mut model.conn, backup)?;
+
+        let restored = backup
+            .settings
+            .clone()
+            .unwrap_or_else(|| model.settings.clone());
+        model.settings = normalize_settings(restored);
+        ensure_team_stats_key(&mut model.settings);
+        model.timer = TimerState::default_with_settings(&model.settings);
+        save_json_setting(&model.conn, APP_SETTINGS_KEY, &model.settings)?;
+        after(&model.conn)?;
+
+        Ok(ResetAllResult {
+            settings: model.settings.clone(),
+            timer: model.timer.clone(),
+        })
+    })
+}
+
+fn fetch_sync_envelope(
+    config: &CloudBackupConfig,
+) -> AppResult<Option<(SyncEnvelope, Option<String>)>> {
+    match webdav_get(config, CLOUD_SYNC_FILENAME)? {
+        Some((data, etag)) => {
+            let envelope = serde_json::from_slice::<SyncEnvelope>(&data)
+                .map_err(|e| format!("invalid sync file on the server: {e}"))?;
+            Ok(Some((envelope, etag)))
+        }
+        None => Ok(None),
+    }
+}
+
+// The payload carries the team key and webhook secret, so it never leaves unencrypted.
+fn encode_sync_envelope(
+    payload: &serde_json::Value,
+    revision: i64,
+    device_id: &str,
+    passphrase: &str,
+    at: i64,
+) -> AppResult<SyncEnvelope> {
+    if passphrase.is_empty() {
+        return Err(SYNC_PASSPHRASE_REQUIRED.to_string());
+    }
+    let plaintext = serde_json::to_vec(payload).map_err(|e| e.to_string())?;
+    let encrypted = encrypt_backup(&plaintext, passphrase)?;
+    Ok(SyncEnvelope {
+        revision,
+        device_id: device_id.to_string(),
+        updated_at: at,
+        backup: None,
+        encrypted: Some(BASE64_STANDARD.encode(encrypted)),
+    })
+}
+
+const SYNC_PASSPHRASE_REQUIRED: &str = "set a passphrase before syncing: sync data is encrypted";
+
+// Domains the sync payload carries; timer-only changes are not edits to sync.
+const SYNCED_DOMAINS: [DataDomain; 5] = [
+    DataDomain::Sessions,
+    DataDomain::Projects,
+    DataDomain::Tags,
+    DataDomain::Settings,
+    DataDomain::DaysOff,
+];
+
+fn last_mutation_id(conn: &Connection) -> AppResult<i64> {
+    conn.query_row("SELECT COALESCE(MAX(id), 0) FROM mutation_log", [], |row| {
+        row.get(0)
+    })
+    .map_err(|e| e.to_string())
+}
+
+// A pull's own log row is not a local edit.
+fn has_unsynced_changes(conn: &Connection, synced_mutation_id: i64) -> AppResult<bool> {
+    let mut stmt = conn
+        .prepare("SELECT domains FROM mutation_log WHERE id > ?1 AND command != 'sync_pull'")
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([synced_mutation_id], |row| row.get::<_, String>(0))
+        .map_err(|e| e.to_string())?;
+    for domains in rows {
+        let domains = domains.map_err(|e| e.to_string())?;
+        if domains
+            .split(',')
+            .any(|domain| SYNCED_DOMAINS.iter().any(|d| d.as_str() == domain))
+        {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+// Why a pull must not replace local data, if it must not: the revision is already applied,
+// or this device has edits the server never saw (push first, or force to discard them).
+fn sync_pull_blocker(
+    config: &CloudBackupConfig,
+    remote_revision: i64,
+    local_changes: bool,
+    force: bool,
+) -> Option<SyncOutcome> {
+    if force {
+        return None;
+    }
+    let status = if remote_revision == config.sync_revision {
+        "up_to_date"
+    } else if local_changes {
+        "conflict"
+    } else {
+        return None;
+    };
+    Some(SyncOutcome {
+        status: status.to_string(),
+        local_revision: config.sync_revision,
+        remote_revision,
+    })
+}
+
+fn decode_sync_envelope(envelope: &SyncEnvelope, passphrase: &str) -> AppResult<BackupPayload> {
+    let value = match (&envelope.backup, &envelope.encrypted) {
+        (Some(backup), _) => backup.clone(),
+        (None, Some(encrypted)) => {
+            let data = BASE64_STANDARD
+                .decode(encrypted)
+                .map_err(|e| format!("invalid encrypted sync payload: {e}"))?;
+            let plaintext = decrypt_backup(&data, passphrase)?;
+            serde_json::from_slice(&plaintext).map_err(|e| format!("invalid sync payload: {e}"))?
+        }
+        (None, None) => return Err("sync file has no payload".to_string()),
+    };
+    serde_json::from_value(value).map_err(|e| format!("invalid sync payload: {e}"))
+}
+
+// Publishes local data as revision N+1. Refuses when the server holds a revision this
+// device has not pulled yet, unless forced.
+fn run_sync_push(app: &AppHandle, force: bool) -> AppResult<SyncOutcome> {
+    let state = app.state::<AppState>();
+    let (mut config, payload, synced_mutation_id) = {
+        let model = state.model.lock().map_err(|e| e.to_string())?;
+        let mut config = load_cloud_backup_config(&model.conn)?;
+        if config.passphrase.is_empty() {
+            return Err(SYNC_PASSPHRASE_REQUIRED.to_string());
+        }
+        if config.device_id.is_empty() {
+            config.device_id = generate_remote_token();
+            save_json_setting(&model.conn, CLOUD_BACKUP_KEY, &config)?;
+        }
+        let payload = build_backup_payload(
+            &model.conn,
+            &model.settings,
+            &ExportRange {
+                from: None,
+                to: None,
+            },
+        )?;
+        (config, payload, last_mutation_id(&model.conn)?)
+    };
+
+    let remote = fetch_sync_envelope(&config)?;
+    let remote_revision = remote.as_ref().map_or(0, |(envelope, _)| envelope.revision);
+    let conflict = SyncOutcome {
+        status: "conflict".to_string(),
+        local_revision: config.sync_revision,
+        remote_revision,
+    };
+    if remote_revision > config.sync_revision && !force {
+        return Ok(conflict);
+    }
+
+    let revision = remote_revision.max(config.sync_revision) + 1;
+    let envelope = encode_sync_envelope(
+        &payload,
+        revision,
+        &config.device_id,
+        &config.passphrase,
+        now_ts(),
+    )?;
+    let body = serde_json::to_vec(&envelope).map_err(|e| e.to_string())?;
+
+    // Guard against another device writing between our read and write.
+    let precondition = match &remote {
+        Some((_, Some(etag))) => WebdavPrecondition::Matches(etag),
+        Some((_, None)) => WebdavPrecondition::None,
+        None => WebdavPrecondition::Absent,
+    };
+    let precondition = if force {
+        WebdavPrecondition::None
+    } else {
+        precondition
+    };
+    if !webdav_put(&config, CLOUD_SYNC_FILENAME, &body, precondition)? {
+        return Ok(conflict);
+    }
+
+    {
+        let model = state.model.lock().map_err(|e| e.to_string())?;
+        let device_id = config.device_id.clone();
+        config = load_cloud_backup_config(&model.conn)?;
+        config.device_id = device_id;
+        config.sync_revision = revision;
+        config.synced_mutation_id = synced_mutation_id;
+        config.last_sync_at = Some(now_ts());
+        save_json_setting(&model.conn, CLOUD_BACKUP_KEY, &config)?;
+    }
+
+    Ok(SyncOutcome {
+        status: "pushed".to_string(),
+        local_revision: revision,
+        remote_revision: revision,
+    })
+}
+
+#[tauri::command]
+fn timer_start(app: AppHandle, payload: Option<StartTimerRequest>) -> AppResult<TimerState> {
+    timer_start_inner(&app, payload)
+}
+
+#[tauri::command]
+fn timer_pause(app: AppHandle) -> AppResult<TimerState> {
+    timer_pause_inner(&app)
+}
+
+#[tauri::command]
+fn timer_resume(app: AppHandle, payload: Option<StartTimerRequest>) -> AppResult<TimerState> {
+    timer_resume_inner(&app, payload)
+}
+
+#[tauri::command]
+fn timer_skip(app: AppHandle) -> AppResult<TimerState> {
+    timer_skip_inner(&app)
+}
+
+#[tauri::command]
+fn timer_get_state(state: State<'_, AppState>) -> AppResult<TimerState> {
+    timer_get_state_inner(state.inner())
+}
+
+#[tauri::command]
+fn timer_set_context(app: AppHandle, payload: StartTimerRequest) -> AppResult<TimerState> {
+    mutate(
+        &app,
+        "timer_set_context",
+        &[DataDomain::Timer],
+        |model, _| {
+            refresh_remaining(&mut model.timer);
+
+            if let Some(project_id) = payload.project_id {
+                model.timer.current_project_id = project_id;
+            }
+            if let Some(tag_ids) = payload.tag_ids {
+                model.timer.current_tag_ids = tag_ids;
+            }
+            Ok(model.timer.clone())
+        },
+    )
+}
+
+#[tauri::command]
+fn timer_postpone_long_break(app: AppHandle) -> AppResult<TimerState> {
+    mutate(
+        &app,
+        "timer_postpone_long_break",
+        &[DataDomain::Timer],
+        |model, _| {
+            refresh_remaining(&mut model.timer);
+            let settings = model.settings.clone();
+            postpone_long_break(&mut model.timer, &settings)?;
+            Ok(model.timer.clone())
+        },
+    )
+}
+
+#[tauri::command]
+fn break_extend(app: AppHandle, minutes: i64) -> AppResult<TimerState> {
+    mutate(&app, "break_extend", &[DataDomain::Timer], |model, _| {
+        refresh_remaining(&mut model.timer);
+        extend_break(&mut model.timer, minutes)?;
+        Ok(model.timer.clone())
+    })
+}
+
+#[tauri::command]
+fn sessions_find_gaps(
+    date: String,
+    min_gap_min: Option<i64>,
+    state: State<'_, AppState>,
+) -> AppResult<Vec<SessionGap>> {
+    let model = lock_model(&state)?;
+    let min_gap_min = min_gap_min
+        .unwrap_or(model.settings.gap_min_minutes)
+        .clamp(1, 480);
+    detect_session_gaps(
+        &model.conn,
+        &model.settings,
+        &model.timer,
+        &date,
+        min_gap_min,
+        now_ts(),
+    )
+}
+
+#[tauri::command]
+fn session_complete(app: AppHandle, payload: CompleteSessionRequest) -> AppResult<SessionRecord> {
+    mutate(
+        &app,
+        "session_complete",
+        &[DataDomain::Sessions],
+        |model, _| {
+            validate_new_session(&model.conn, &payload)?.map_err(|e| e.to_string())?;
+
+            model
+                .conn
+                .execute(
+                    "INSERT INTO sessions (started_at, ended_at, phase, duration_sec, completed, interruptions, project_id, notes, quality)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                    params![
+                        payload.started_at,
+                        payload.ended_at,
+                        payload.phase.as_db_value(),
+                        payload.duration_sec,
+                        payload.completed as i64,
+                        payload.interruptions,
+                        payload.project_id,
+                        payload.notes,
+                        payload.quality,
+                    ],
+                )
+                .map_err(|e| e.to_string())?;
+
+            let id = model.conn.last_insert_rowid();
+            let tag_ids = payload.tag_ids.unwrap_or_default();
+            for tag_id in &tag_ids {
+                model
+                    .conn
+                    .execute(
+                        "INSERT OR IGNORE INTO session_tags (session_id, tag_id) VALUES (?1, ?2)",
+                        params![id, tag_id],
+                    )
+                    .map_err(|e| e.to_string())?;
+            }
+
+            Ok(SessionRecord {
+                id,
+                started_at: payload.started_at,
+                ended_at: payload.ended_at,
+                phase: payload.phase,
+                duration_sec: payload.duration_sec,
+                completed: payload.completed,
+                interruptions: payload.interruptions,
+                project_id: payload.project_id,
+                tag_ids,
+                notes: payload.notes,
+                extended_sec: 0,
+                project_splits: Vec::new(),
+                quality: payload.quality,
+                planned: None,
+            })
+        },
+    )
+}
+
+#[tauri::command]
+fn analytics_get_summary(
+    range: AnalyticsRange,
+    state: State<'_, AppState>,
+) -> AppResult<AnalyticsSummary> {
+    let model = lock_model(&state)?;
+    let range = with_exclusions(range, &model.settings);
+    compute_summary(&model.conn, &model.settings, &range)
+}
+
+// Focus sessions rolled up per local day they ended on.
+#[derive(Debug, Default, Clone, Copy)]
+struct SummaryDay {
+    focus_sec: i64,
+    completed: i64,
+    interruptions: i64,
+    // Sessions with any recorded time, and the completed ones among them; only these
+    // make the day count towards the average and the streak.
+    timed_sessions: i64,
+    timed_completed: i64,
+    planned: i64,
+    // Sessions that recorded a planned flag either way.
+    plan_flagged: i64,
+}
+
+// Completed pomodoros per day that had any timed focus, as StreakRules::day expects.
+fn streak_focus_days(days: BTreeMap<String, SummaryDay>) -> BTreeMap<String, i64> {
+    days.into_iter()
+        .filter(|(_, totals)| totals.timed_sessions > 0)
+        .map(|(day, totals)| (day, totals.timed_completed))
+        .collect()
+}
+
+fn fetch_summary_days(
+    conn: &Connection,
+    range: &AnalyticsRange,
+) -> AppResult<BTreeMap<String, SummaryDay>> {
+    let (filter, filter_values) = build_sessions_filter(range);
+    let mut values = vec![project_filter_value(range)];
+    values.extend(filter_values);
+    let day = Granularity::Day.bucket_sql();
+    let sql = format!(
+        "SELECT {day} AS day,
+                SUM({ATTRIBUTED_DURATION_SQL}),
+                SUM(completed),
+                SUM(interruptions),
+                SUM(duration_sec > 0),
+                SUM(CASE WHEN duration_sec > 0 THEN completed ELSE 0 END),
+                SUM(planned IS 1),
+                SUM(planned IS NOT NULL)
+         FROM sessions
+         WHERE phase = 'focus'{filter}
+         GROUP BY day"
+    );
+    let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(rusqlite::params_from_iter(values), |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                SummaryDay {
+                    focus_sec: row.get(1)?,
+                    completed: row.get(2)?,
+                    interruptions: row.get(3)?,
+                    timed_sessions: row.get(4)?,
+                    timed_completed: row.get(5)?,
+                    planned: row.get(6)?,
+                    plan_flagged: row.get(7)?,
+                },
+            ))
+        })
+        .map_err(|e| e.to_string())?;
+    rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+}
+
+fn compute_summary(
+    conn: &Connection,
+    settings: &AppSettings,
+    range: &AnalyticsRange,
+) -> AppResult<AnalyticsSummary> {
+    let days = fetch_summary_days(conn, range)?;
+    let days_off = skipped_days(conn, settings)?;
+
+    let mut total_focus_sec = 0;
+    let mut completed_pomodoros = 0;
+    let mut interruptions = 0;
+    let mut planned = 0;
+    let mut plan_flagged = 0;
+    let mut days_with_focus = 0;
+    // Excludes days off when they are skipped, so a week off does not move the average.
+    let mut averaged_focus_sec = 0;
+
+    for (day, totals) in &days {
+        total_focus_sec += totals.focus_sec;
+        completed_pomodoros += totals.completed;
+        interruptions += totals.interruptions;
+        planned += totals.planned;
+        plan_flagged += totals.plan_flagged;
+        if days_off.contains(day) {
+            continue;
+        }
+        averaged_focus_sec += totals.focus_sec;
+        if totals.timed_sessions > 0 {
+            days_with_focus += 1;
+        }
+    }
+
+    let avg_daily_focus_sec = if days_with_focus == 0 {
+        0
+    } else {
+        averaged_focus_sec / days_with_focus
+    };
+    let focus_days = streak_focus_days(days);
+
+    let frozen = fetch_streak_freezes(conn)?;
+    let since = range
+        .from
+        .and_then(|from| chrono::NaiveDate::parse_from_str(&day_key(from), "%Y-%m-%d").ok());
+    let streak = calculate_streak_days(
+        &focus_days,
+        &days_off,
+        &frozen,
+        &StreakRules::from_settings(settings),
+        since,
+    );
+
+    Ok(AnalyticsSummary {
+        total_focus_sec,
+        completed_pomodoros,
+        streak_days: streak.days,
+        interruptions,
+        avg_daily_focus_sec,
+        planned_ratio: (plan_flagged > 0).then(|| planned as f64 / plan_flagged as f64),
+        break_compliance: fetch_break_compliance(conn, range)?,
+    })
+}
+
+const BREAK_SKIP_GRACE_SECONDS: i64 = 60;
+
+fn fetch_break_compliance(conn: &Connection, range: &AnalyticsRange) -> AppResult<BreakCompliance> {
+    let (filter, mut values) = build_sessions_filter(&AnalyticsRange {
+        from: range.from,
+        to: range.to,
+        ..Default::default()
+    });
+    values.insert(0, Value::Integer(BREAK_SKIP_GRACE_SECONDS));
+    let sql = format!(
+        "SELECT COUNT(*),
+                COALESCE(SUM(completed), 0),
+                COALESCE(SUM(completed = 0 AND duration_sec >= ?1), 0),
+                COALESCE(SUM(MIN(duration_sec, COALESCE(planned_sec, duration_sec))), 0),
+                COALESCE(SUM(COALESCE(planned_sec, duration_sec)), 0)
+         FROM sessions
+         WHERE phase != 'focus'{filter}"
+    );
+    let (breaks, taken, cut_short, taken_sec, planned_sec): (i64, i64, i64, i64, i64) = conn
+        .query_row(&sql, rusqlite::params_from_iter(values), |row| {
+            Ok((
+                row.get(0)?,
+                row.get(1)?,
+                row.get(2)?,
+                row.get(3)?,
+                row.get(4)?,
+            ))
+        })
+        .map_err(|e| e.to_string())?;
+    Ok(BreakCompliance {
+        breaks,
+        taken,
+        cut_short,
+        skipped: breaks - taken - cut_short,
+        taken_ratio: (planned_sec > 0).then(|| taken_sec as f64 / planned_sec as f64),
+        compliance_rate: (breaks > 0).then(|| taken as f64 / breaks as f64),
+    })
+}
+
+#[tauri::command]
+fn analytics_quality(
+    range: AnalyticsRange,
+    state: State<'_, AppState>,
+) -> AppResult<QualityBreakdown> {
+    let model = lock_model(&state)?;
+    let range = with_exclusions(range, &model.settings);
+    let sessions = fetch_sessions(&model.conn, &range)?;
+    Ok(quality_breakdown(&sessions))
+}
+
+#[tauri::command]
+fn focus_length_recommend(
+    app: AppHandle,
+    range: AnalyticsRange,
+    apply: Option<bool>,
+    state: State<'_, AppState>,
+) -> AppResult<FocusLengthRecommendation> {
+    let (buckets, current_min) = {
+        let model = lock_model(&state)?;
+        let range = with_exclusions(range, &model.settings);
+        (
+            focus_length_buckets(&model.conn, &range)?,
+            model.settings.focus_min,
+        )
+    };
+    let recommended_min = recommend_focus_length(&buckets);
+
+    let mut applied = false;
+    if let Some(minutes) = recommended_min.filter(|m| apply.unwrap_or(false) && *m != current_min) {
+        let patch = AppSettingsPatch {
+            focus_min: Some(minutes),
+            ..Default::default()
+        };
+        settings_update(app, patch)?;
+        applied = true;
+    }
+
+    Ok(FocusLengthRecommendation {
+        buckets,
+        recommended_min,
+        current_min,
+        applied,
+    })
+}
+
+#[tauri::command]
+fn analytics_billing(
+    range: AnalyticsRange,
+    period: Option<String>,
+    state: State<'_, AppState>,
+) -> AppResult<BillingReport> {
+    let model = lock_model(&state)?;
+    let range = with_exclusions(range, &model.settings);
+    let sessions = fetch_sessions(&model.conn, &range)?;
+    let projects = fetch_project_map(&model.conn)?;
+    billing_report(
+        &sessions,
+        &projects,
+        range.project_id,
+        period.as_deref().unwrap_or("month"),
+    )
+}
+
+#[tauri::command]
+fn analytics_break_usage(
+    range: AnalyticsRange,
+    state: State<'_, AppState>,
+) -> AppResult<BreakUsage> {
+    let model = lock_model(&state)?;
+    let range = with_exclusions(range, &model.settings);
+    let sessions = fetch_sessions(&model.conn, &range)?;
+
+    let mut usage = BreakUsage {
+        break_sessions: 0,
+        break_seconds: 0,
+        configured_seconds: 0,
+        extended_seconds: 0,
+        extended_sessions: 0,
+    };
+    for session in sessions.iter().filter(|s| s.phase != TimerPhase::Focus) {
+        usage.break_sessions += 1;
+        usage.break_seconds += session.duration_sec;
+        usage.extended_seconds += session.extended_sec;
+        if session.extended_sec > 0 {
+            usage.extended_sessions += 1;
+        }
+    }
+    usage.configured_seconds = (usage.break_seconds - usage.extended_seconds).max(0);
+
+    Ok(usage)
+}
+
+#[tauri::command]
+fn analytics_get_timeseries(
+    range: AnalyticsRange,
+    state: State<'_, AppState>,
+) -> AppResult<Vec<TimeseriesPoint>> {
+    let model = lock_model(&state)?;
+    let range = with_exclusions(range, &model.settings);
+    fetch_timeseries(&model.conn, &range)
+}
+
+// Grouped in SQL so month buckets over years of history never load individual sessions.
+// Each point is dated by the first day of its bucket.
+fn fetch_timeseries(conn: &Connection, range: &AnalyticsRange) -> AppResult<Vec<TimeseriesPoint>> {
+    let bucket = range.granularity.unwrap_or_default().bucket_sql();
+    let totals = focus_totals_by::<String>(conn, range, bucket)?;
+    Ok(totals
+        .into_iter()
+        .map(|(date, t)| TimeseriesPoint {
+            date,
+            focus_seconds: t.focus_seconds,
+            completed_pomodoros: t.completed_pomodoros,
+            interruptions: t.interruptions,
+        })
+        .collect())
+}
+
+#[tauri::command]
+fn analytics_get_hourly(
+    range: AnalyticsRange,
+    state: State<'_, AppState>,
+) -> AppResult<Vec<HourlyBucket>> {
+    let model = lock_model(&state)?;
+    let range = with_exclusions(range, &model.settings);
+    fetch_hourly_distribution(&model.conn, &range)
+}
+
+#[tauri::command]
+fn plan_list(state: State<'_, AppState>) -> AppResult<Vec<PlanItem>> {
+    let model = lock_model(&state)?;
+    fetch_plan_items(&model.conn)
+}
+
+#[tauri::command]
+fn plan_add(
+    app: AppHandle,
+    title: String,
+    project_id: Option<i64>,
+    tag_ids: Vec<i64>,
+) -> AppResult<Vec<PlanItem>> {
+    let title = title.trim().to_string();
+    if title.is_empty() {
+        return Err("plan item title is required".into());
+    }
+    mutate(&app, "plan_add", &[DataDomain::Plan], |model, _| {
+        let tag_ids = serde_json::to_string(&tag_ids).map_err(|e| e.to_string())?;
+        model
+            .conn
+            .execute(
+                "INSERT INTO plan_items (title, project_id, tag_ids, created_at)
+                 VALUES (?1, ?2, ?3, ?4)",
+                params![title, project_id, tag_ids, now_ts()],
+            )
+            .map_err(|e| e.to_string())?;
+        fetch_plan_items(&model.conn)
+    })
+}
+
+#[tauri::command]
+fn plan_remove(app: AppHandle, id: i64) -> AppResult<Vec<PlanItem>> {
+    mutate(&app, "plan_remove", &[DataDomain::Plan], |model, _| {
+        model
+            .conn
+            .execute("DELETE FROM plan_items WHERE id = ?1", params![id])
+            .map_err(|e| e.to_string())?;
+        fetch_plan_items(&model.conn)
+    })
+}
+
+#[tauri::command]
+fn plan_start_next(app: AppHandle) -> AppResult<TimerState> {
+    mutate(
+        &app,
+        "plan_start_next",
+        &[DataDomain::Plan, DataDomain::Timer],
+        |model, _| start_next_plan_item(model),
+    )
+}
+
+#[tauri::command]
+fn music_list(state: State<'_, AppState>) -> AppResult<Vec<MusicTrack>> {
+    let model = lock_model(&state)?;
+    fetch_music_tracks(&model.conn)
+}
+
+// Paths already in the playlist are skipped; missing or unsupported files reject the batch.
+#[tauri::command]
+fn music_add(app: AppHandle, paths: Vec<String>) -> AppResult<Vec<MusicTrack>> {
+    mutate(&app, "music_add", &[DataDomain::Music], |model, _| {
+        for path in &paths {
+            if music_mime(path).is_none() {
+                return Err(format!("unsupported audio file: {path}"));
+            }
+            if !std::path::Path::new(path).is_file() {
+                return Err(format!("file not found: {path}"));
+            }
+        }
+        let now = now_ts();
+        for path in &paths {
+            let title = std::path::Path::new(path)
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_else(|| path.clone());
+            model
+                .conn
+                .execute(
+                    "INSERT OR IGNORE INTO music_tracks (path, title, created_at) VALUES (?1, ?2, ?3)",
+                    params![path, title, now],
+                )
+                .map_err(|e| e.to_string())?;
+        }
+        fetch_music_tracks(&model.conn)
+    })
+}
+
+#[tauri::command]
+fn music_remove(app: AppHandle, track_ids: Vec<i64>) -> AppResult<Vec<MusicTrack>> {
+    mutate(&app, "music_remove", &[DataDomain::Music], |model, _| {
+        for id in track_ids {
+            model
+                .conn
+                .execute("DELETE FROM music_tracks WHERE id = ?1", params![id])
+                .map_err(|e| e.to_string())?;
+        }
+        fetch_music_tracks(&model.conn)
+    })
+}
+
+#[tauri::command]
+fn music_set_volume(app: AppHandle, track_id: i64, volume: f64) -> AppResult<MusicTrack> {
+    if !volume.is_finite() {
+        return Err("volume must be a number".to_string());
+    }
+    mutate(
+        &app,
+        "music_set_volume",
+        &[DataDomain::Music],
+        |model, _| {
+            let changed = model
+                .conn
+                .execute(
+                    "UPDATE music_tracks SET volume = ?1 WHERE id = ?2",
+                    params![volume.clamp(0.0, 1.0), track_id],
+                )
+                .map_err(|e| e.to_string())?;
+            if changed == 0 {
+                return Err(format!("track {track_id} not found"));
+            }
+            fetch_music_tracks(&model.conn)?
+                .into_iter()
+                .find(|t| t.id == track_id)
+                .ok_or_else(|| format!("track {track_id} not found"))
+        },
+    )
+}
+
+// Called by the window when a track finishes (or to skip one); ignored while music is stopped.
+#[tauri::command]
+fn music_next(app: AppHandle, state: State<'_, AppState>) -> AppResult<Option<MusicTrack>> {
+    let event = {
+        let mut model = lock_model(&state)?;
+        if model.music_playing.is_none() {
+            return Ok(None);
+        }
+        music_play_next(&mut model)?
+    };
+    let track = match &event {
+        Some(MusicEvent::Play(play)) => Some(play.track.clone()),
+        _ => None,
+    };
+    if let Some(event) = event {
+        emit_music_event(&app, event);
+    }
+    Ok(track)
+}
+
+// Audio bytes for the window to decode; the webview cannot read arbitrary local paths.
+#[tauri::command]
+fn music_track_data(track_id: i64, state: State<'_, AppState>) -> AppResult<MusicTrackData> {
+    let path: String = {
+        let model = lock_model(&state)?;
+        model
+            .conn
+            .query_row(
+                "SELECT path FROM music_tracks WHERE id = ?1",
+                params![track_id],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| format!("track {track_id} not found"))?
+    };
+    let mime = music_mime(&path).ok_or_else(|| format!("unsupported audio file: {path}"))?;
+    let size = fs::metadata(&path).map_err(|e| e.to_string())?.len();
+    if size > MUSIC_MAX_TRACK_BYTES {
+        return Err(format!("{path} is larger than 100 MB"));
+    }
+    let bytes = fs::read(&path).map_err(|e| e.to_string())?;
+    Ok(MusicTrackData {
+        track_id,
+        mime: mime.to_string(),
+        content_base64: BASE64_STANDARD.encode(bytes),
+    })
+}
+
+// For an overlay opened mid-break; later steps arrive as breathing://step events.
+#[tauri::command]
+fn breathing_current(state: State<'_, AppState>) -> AppResult<Option<BreathingStep>> {
+    let model = lock_model(&state)?;
+    Ok(current_breathing_step(
+        &model.settings,
+        &model.timer,
+        now_ts(),
+    ))
+}
+
+#[tauri::command]
+fn experiment_start(
+    app: AppHandle,
+    focus_a_min: i64,
+    focus_b_min: i64,
+    days: i64,
+) -> AppResult<ExperimentReport> {
+    if !(1..=180).contains(&focus_a_min) || !(1..=180).contains(&focus_b_min) {
+        return Err("focus lengths must be between 1 and 180 minutes".to_string());
+    }
+    if focus_a_min == focus_b_min {
+        return Err("the two focus lengths must differ".to_string());
+    }
+    if !(1..=90).contains(&days) {
+        return Err("an experiment runs for 1 to 90 days".to_string());
+    }
+    mutate(
+        &app,
+        "experiment_start",
+        &[DataDomain::Experiments, DataDomain::Timer],
+        |model, _| {
+            let now = now_ts();
+            if active_experiment(&model.conn, now)?.is_some() {
+                return Err("an experiment is already running".to_string());
+            }
+            model
+                .conn
+                .execute(
+                    "INSERT INTO experiments (focus_a_min, focus_b_min, started_at, ends_at)
+                 VALUES (?1, ?2, ?3, ?4)",
+                    params![focus_a_min, focus_b_min, now, now + days * 86_400],
+                )
+                .map_err(|e| e.to_string())?;
+            let experiment = active_experiment(&model.conn, now)?
+                .ok_or_else(|| "experiment was not saved".to_string())?;
+            let AppModel { conn, timer, .. } = &mut *model;
+            apply_experiment_length(conn, timer, now)?;
+            experiment_report(&model.conn, experiment, now)
+        },
+    )
+}
+
+// Ends the running experiment early. The current focus keeps its length; the next one
+// follows settings again.
+#[tauri::command]
+fn experiment_stop(app: AppHandle) -> AppResult<ExperimentReport> {
+    mutate(
+        &app,
+        "experiment_stop",
+        &[DataDomain::Experiments],
+        |model, _| {
+            let now = now_ts();
+            let mut experiment = active_experiment(&model.conn, now)?
+                .ok_or_else(|| "no experiment is running".to_string())?;
+            model
+                .conn
+                .execute(
+                    "UPDATE experiments SET stopped_at = ?1 WHERE id = ?2",
+                    params![now, experiment.id],
+                )
+                .map_err(|e| e.to_string())?;
+            experiment.stopped_at = Some(now);
+            experiment_report(&model.conn, experiment, now)
+        },
+    )
+}
+
+// The given experiment, or the most recent one; None when none was ever started.
+#[tauri::command]
+fn experiment_get_report(
+    experiment_id: Option<i64>,
+    state: State<'_, AppState>,
+) -> AppResult<Option<ExperimentReport>> {
+    let model = lock_model(&state)?;
+    let experiment = model
+        .conn
+        .query_row(
+            &format!(
+                "SELECT {EXPERIMENT_COLUMNS} FROM experiments
+                 WHERE ?1 IS NULL OR id = ?1 ORDER BY id DESC LIMIT 1"
+            ),
+            params![experiment_id],
+            experiment_from_row,
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+    experiment
+        .map(|experiment| experiment_report(&model.conn, experiment, now_ts()))
+        .transpose()
+}
+
+#[tauri::command]
+fn goals_progress(state: State<'_, AppState>) -> AppResult<GoalsProgress> {
+    let model = lock_model(&state)?;
+    let now = now_ts();
+    goals_progress_at(&model.conn, &model.settings, now, now)
+}
+
+#[tauri::command]
+fn analytics_records(state: State<'_, AppState>) -> AppResult<AnalyticsRecords> {
+    let mut model = lock_model(&state)?;
+    cached_records(&mut model)
+}
+
+#[tauri::command]
+fn analytics_get_weekday(
+    range: AnalyticsRange,
+    state: State<'_, AppState>,
+) -> AppResult<Vec<WeekdayBucket>> {
+    let model = lock_model(&state)?;
+    let range = with_exclusions(range, &model.settings);
+    fetch_weekday_distribution(&model.conn, &range)
+}
+
+#[tauri::command]
+fn analytics_distribution(
+    range: AnalyticsRange,
+    state: State<'_, AppState>,
+) -> AppResult<SessionDistribution> {
+    let model = lock_model(&state)?;
+    let range = with_exclusions(range, &model.settings);
+    fetch_session_distribution(&model.conn, &range)
+}
+
+#[tauri::command]
+fn analytics_start_times(
+    range: AnalyticsRange,
+    state: State<'_, AppState>,
+) -> AppResult<Vec<StartTimeStats>> {
+    let model = lock_model(&state)?;
+    let range = with_exclusions(range, &model.settings);
+    fetch_start_time_stats(&model.conn, &range)
+}
+
+#[tauri::command]
+fn analytics_estimation_accuracy(
+    range: AnalyticsRange,
+    state: State<'_, AppState>,
+) -> AppResult<EstimationAccuracy> {
+    let model = lock_model(&state)?;
+    let range = with_exclusions(range, &model.settings);
+    fetch_estimation_accuracy(&model.conn, &range)
+}
+
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+enum ReportFormat {
+    #[default]
+    Markdown,
+    Html,
+}
+
+// What report_generate renders. Split sessions count towards their primary project.
+struct Report {
+    period: String,
+    summary: AnalyticsSummary,
+    projects: Vec<(String, FocusTotals)>,
+    best_days: Vec<(String, FocusTotals)>,
+    // (why it is notable, when, project, the session)
+    notable: Vec<(&'static str, String, String, SessionRecord)>,
+}
+
+const REPORT_BEST_DAYS: usize = 3;
+const REPORT_NOTABLE_SESSIONS: usize = 5;
+
+fn report_duration(seconds: i64) -> String {
+    let minutes = seconds / 60;
+    if minutes < 60 {
+        format!("{minutes}m")
+    } else {
+        format!("{}h {:02}m", minutes / 60, minutes % 60)
+    }
+}
+
+fn build_report(
+    conn: &Connection,
+    settings: &AppSettings,
+    range: &AnalyticsRange,
+) -> AppResult<Report> {
+    let period = match (range.from, range.to) {
+        (Some(from), Some(to)) => format!("{} – {}", day_key(from), day_key(to)),
+        (Some(from), None) => format!("since {}", day_key(from)),
+        (None, Some(to)) => format!("until {}", day_key(to)),
+        (None, None) => "all time".to_string(),
+    };
+    let names: BTreeMap<i64, String> = fetch_projects(conn)?
+        .into_iter()
+        .map(|p| (p.id, p.name))
+        .collect();
+    let project_name = |id: Option<i64>| {
+        id.and_then(|id| names.get(&id).cloned())
+            .unwrap_or_else(|| "No project".to_string())
+    };
+
+    let mut projects: Vec<(String, FocusTotals)> =
+        focus_totals_by::<Option<i64>>(conn, range, "project_id")?
+            .into_iter()
+            .map(|(id, totals)| (project_name(id), totals))
+            .collect();
+    projects.sort_by(|a, b| {
+        b.1.focus_seconds
+            .cmp(&a.1.focus_seconds)
+            .then(a.0.cmp(&b.0))
+    });
+
+    let mut best_days: Vec<(String, FocusTotals)> =
+        focus_totals_by::<String>(conn, range, Granularity::Day.bucket_sql())?
+            .into_iter()
+            .filter(|(_, totals)| totals.focus_seconds > 0)
+            .collect();
+    best_days.sort_by(|a, b| {
+        b.1.focus_seconds
+            .cmp(&a.1.focus_seconds)
+            .then(a.0.cmp(&b.0))
+    });
+    best_days.truncate(REPORT_BEST_DAYS);
+
+    // Longest first, then top-rated and annotated ones, each session listed once.
+    let sessions = fetch_sessions(
+        conn,
+        &AnalyticsRange {
+            phases: vec![TimerPhase::Focus],
+            ..range.clone()
+        },
+    )?;
+    let mut longest: Vec<&SessionRecord> = sessions.iter().filter(|s| s.completed).collect();
+    longest.sort_by_key(|s| std::cmp::Reverse(s.duration_sec));
+    let candidates = longest
+        .into_iter()
+        .take(1)
+        .map(|s| ("Longest session", s))
+        .chain(
+            sessions
+                .iter()
+                .filter(|s| s.quality == Some(5))
+                .map(|s| ("Rated 5/5", s)),
+        )
+        .chain(
+            sessions
+                .iter()
+                .filter(|s| s.notes.is_some())
+                .map(|s| ("Has notes", s)),
+        );
+    let mut seen = HashSet::new();
+    let notable = candidates
+        .filter(|(_, s)| seen.insert(s.id))
+        .take(REPORT_NOTABLE_SESSIONS)
+        .map(|(reason, s)| {
+            let when = zoned_datetime(s.started_at, &settings.timezone)
+                .format("%a %Y-%m-%d %H:%M")
+                .to_string();
+            (reason, when, project_name(s.project_id), s.clone())
+        })
+        .collect();
+
+    Ok(Report {
+        period,
+        summary: compute_summary(conn, settings, range)?,
+        projects,
+        best_days,
+        notable,
+    })
+}
+
+fn render_report_markdown(report: &Report) -> String {
+    let summary = &report.summary;
+    let mut out = format!("# Focus report: {}\n\n## Totals\n\n", report.period);
+    out.push_str(&format!(
+        "- Focus time: {}\n- Pomodoros: {}\n- Daily average: {}\n- Interruptions: {}\n- Streak: {} days\n",
+        report_duration(summary.total_focus_sec),
+        summary.completed_pomodoros,
+        report_duration(summary.avg_daily_focus_sec),
+        summary.interruptions,
+        summary.streak_days,
+    ));
+
+    if !report.projects.is_empty() {
+        out.push_str("\n## Projects\n\n| Project | Focus | Pomodoros | Share |\n| --- | ---: | ---: | ---: |\n");
+        for (name, totals) in &report.projects {
+            let share = if summary.total_focus_sec > 0 {
+                totals.focus_seconds * 100 / summary.total_focus_sec
+            } else {
+                0
+            };
+            out.push_str(&format!(
+                "| {} | {} | {} | {share}% |\n",
+                name.replace('|', "\\|"),
+                report_duration(totals.focus_seconds),
+                totals.completed_pomodoros,
+            ));
+        }
+    }
+
+    if !report.best_days.is_empty() {
+        out.push_str("\n## Best days\n\n");
+        for (i, (day, totals)) in report.best_days.iter().enumerate() {
+            out.push_str(&format!(
+                "{}. {day}: {}, {} pomodoros\n",
+                i + 1,
+                report_duration(totals.focus_seconds),
+                totals.completed_pomodoros
+            ));
+        }
+    }
+
+    if !report.notable.is_empty() {
+        out.push_str("\n## Notable sessions\n\n");
+        for (reason, when, project, session) in &report.notable {
+            out.push_str(&format!(
+                "- **{reason}**: {when}, {}, {project}",
+                report_duration(session.duration_sec)
+            ));
+            if let Some(notes) = &session.notes {
+                out.push_str(&format!(" — {}", notes.replace('\n', " ")));
+            }
+            out.push('\n');
+        }
+    }
+    out
+}
+
+fn html_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn render_report_html(report: &Report) -> String {
+    let summary = &report.summary;
+    let title = html_escape(&format!("Focus report: {}", report.period));
+    let mut out = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{title}</title>\n</head>\n<body>\n<h1>{title}</h1>\n<h2>Totals</h2>\n<ul>\n"
+    );
+    for (label, value) in [
+        ("Focus time", report_duration(summary.total_focus_sec)),
+        ("Pomodoros", summary.completed_pomodoros.to_string()),
+        (
+            "Daily average",
+            report_duration(summary.avg_daily_focus_sec),
+        ),
+        ("Interruptions", summary.interruptions.to_string()),
+        ("Streak", format!("{} days", summary.streak_days)),
+    ] {
+        out.push_str(&format!("<li>{label}: {value}</li>\n"));
+    }
+    out.push_str("</ul>\n");
+
+    if !report.projects.is_empty() {
+        out.push_str("<h2>Projects</h2>\n<table>\n<tr><th>Project</th><th>Focus</th><th>Pomodoros</th><th>Share</th></tr>\n");
+        for (name, totals) in &report.projects {
+            let share = if summary.total_focus_sec > 0 {
+                totals.focus_seconds * 100 / summary.total_focus_sec
+            } else {
+                0
+            };
+            out.push_str(&format!(
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td>{share}%</td></tr>\n",
+                html_escape(name),
+                report_duration(totals.focus_seconds),
+                totals.completed_pomodoros,
+            ));
+        }
+        out.push_str("</table>\n");
+    }
+
+    if !report.best_days.is_empty() {
+        out.push_str("<h2>Best days</h2>\n<ol>\n");
+        for (day, totals) in &report.best_days {
+            out.push_str(&format!(
+                "<li>{day}: {}, {} pomodoros</li>\n",
+                report_duration(totals.focus_seconds),
+                totals.completed_pomodoros
+            ));
+        }
+        out.push_str("</ol>\n");
+    }
+
+    if !report.notable.is_empty() {
+        out.push_str("<h2>Notable sessions</h2>\n<ul>\n");
+        for (reason, when, project, session) in &report.notable {
+            out.push_str(&format!(
+                "<li><strong>{reason}</strong>: {when}, {}, {}",
+                report_duration(session.duration_sec),
+                html_escape(project)
+            ));
+            if let Some(notes) = &session.notes {
+                out.push_str(&format!(" — {}", html_escape(notes)));
+            }
+            out.push_str("</li>\n");
+        }
+        out.push_str("</ul>\n");
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+// Markdown by default; the content is ready to paste into a review note.
+#[tauri::command]
+fn report_generate(
+    range: AnalyticsRange,
+    format: Option<ReportFormat>,
+    state: State<'_, AppState>,
+) -> AppResult<ExportResult> {
+    let model = lock_model(&state)?;
+    let range = with_exclusions(range, &model.settings);
+    let report = build_report(&model.conn, &model.settings, &range)?;
+    let (content, extension) = match format.unwrap_or_default() {
+        ReportFormat::Markdown => (render_report_markdown(&report), "md"),
+        ReportFormat::Html => (render_report_html(&report), "html"),
+    };
+    Ok(ExportResult {
+        filename: format!("pomodoro-report-{}.{extension}", now_ts()),
+        content,
+    })
+}
+
+#[tauri::command]
+fn analytics_interruptions(
+    range: AnalyticsRange,
+    state: State<'_, AppState>,
+) -> AppResult<InterruptionAnalytics> {
+    let model = lock_model(&state)?;
+    let range = with_exclusions(range, &model.settings);
+    fetch_interruption_analytics(&model.conn, &range)
+}
+
+#[tauri::command]
+fn app_diagnostics(app: AppHandle, state: State<'_, AppState>) -> AppResult<AppDiagnostics> {
+    collect_diagnostics(&app, &state)
+}
+
+fn collect_diagnostics(app: &AppHandle, state: &AppState) -> AppResult<AppDiagnostics> {
+    let remote_port = state
+        .remote
+        .lock()
+        .map_err(|e| e.to_string())?
+        .server
+        .as_ref()
+        .map(|server| server.port);
+    let remote_endpoints =
+        endpoint_stats(&*state.remote_metrics.lock().map_err(|e| e.to_string())?);
+    Ok(AppDiagnostics {
+        version: app.package_info().version.to_string(),
+        remote_running: remote_port.is_some(),
+        remote_port,
+        remote_endpoints,
+    })
+}
+
+// Settings values that are fixed vocabularies rather than user text; every other string is
+// redacted from support bundles, so a new free-text setting is hidden by default.
+const SUPPORT_SETTINGS_KEPT: [&str; 7] = [
+    "theme",
+    "pauseBudgetAction",
+    "dndMode",
+    "timezone",
+    "remoteDetachAction",
+    "language",
+    "breathingPattern",
+];
+
+fn redacted_settings(settings: &AppSettings) -> AppResult<serde_json::Value> {
+    let mut json = serde_json::to_value(settings).map_err(|e| e.to_string())?;
+    if let Some(fields) = json.as_object_mut() {
+        for (key, value) in fields.iter_mut() {
+            let personal = value.as_str().is_some_and(|text| !text.is_empty());
+            if personal && !SUPPORT_SETTINGS_KEPT.contains(&key.as_str()) {
+                *value = serde_json::Value::String("[redacted]".to_string());
+            }
+        }
+    }
+    Ok(json)
+}
+
+// Left out of GET /api/settings: secrets, so a read-only token cannot recover them, and the
+// webhook URL and backup folder, which say where data leaves the machine.
+const REMOTE_SETTINGS_HIDDEN: [&str; 4] = [
+    "teamStatsKey",
+    "sessionWebhookSecret",
+    "sessionWebhookUrl",
+    "backupDirectory",
+];
+
+fn remote_settings_view(settings: &AppSettings) -> AppResult<serde_json::Value> {
+    let mut json = serde_json::to_value(settings).map_err(|e| e.to_string())?;
+    if let Some(fields) = json.as_object_mut() {
+        for key in REMOTE_SETTINGS_HIDDEN {
+            fields.remove(key);
+        }
+    }
+    Ok(json)
+}
+
+// PATCH /api/settings covers durations, alerts and the like. Remote access itself, secrets
+// and where data leaves the machine stay desktop-only: a phone could otherwise lock itself
+// out, widen its own scopes or redirect sessions and backups.
+fn check_remote_settings_patch(patch: &AppSettingsPatch) -> AppResult<()> {
+    let locked = [
+        (
+            "remoteControlEnabled",
+            patch.remote_control_enabled.is_some(),
+        ),
+        ("remoteControlPort", patch.remote_control_port.is_some()),
+        ("teamStatsEnabled", patch.team_stats_enabled.is_some()),
+        ("teamStatsKey", patch.team_stats_key.is_some()),
+        ("sessionWebhookUrl", patch.session_webhook_url.is_some()),
+        (
+            "sessionWebhookSecret",
+            patch.session_webhook_secret.is_some(),
+        ),
+        ("backupDirectory", patch.backup_directory.is_some()),
+        ("remoteMdnsEnabled", patch.remote_mdns_enabled.is_some()),
+        (
+            "remotePairingRequired",
+            patch.remote_pairing_required.is_some(),
+        ),
+        ("remoteReadOnly", patch.remote_read_only.is_some()),
+    ];
+    match locked.iter().find(|(_, set)| *set) {
+        Some((name, _)) => Err(format!("{name} can only be changed on the desktop")),
+        None => Ok(()),
+    }
+}
+
+const HEALTH_COUNTED_TABLES: [&str; 4] = ["sessions", "projects", "tags", "events"];
+// The worker ticks every second; a few missed ticks allow for a long save or a slow lock.
+const TIMER_WORKER_STALE_SECONDS: i64 = 10;
+
+fn health_row_counts(conn: &Connection) -> AppResult<BTreeMap<String, i64>> {
+    HEALTH_COUNTED_TABLES
+        .iter()
+        .map(|table| {
+            conn.query_row(&format!("SELECT COUNT(*) FROM {table}"), [], |row| {
+                row.get(0)
+            })
+            .map(|rows| (table.to_string(), rows))
+            .map_err(|e| e.to_string())
+        })
+        .collect()
+}
+
+fn remote_health(state: &AppState, version: &str, at: i64) -> RemoteHealth {
+    let last_tick_at = state.timer_heartbeat.load(Ordering::SeqCst);
+    let timer_worker_alive = at - last_tick_at <= TIMER_WORKER_STALE_SECONDS;
+    let (rows, read_only) = match state.model.lock() {
+        Ok(model) => (
+            health_row_counts(&model.conn).ok(),
+            model.read_only.is_some(),
         ),
-        Some(_) => write_response(
-            &mut stream,
-            "500 Internal Server Error",
-            "text/plain; charset=utf-8",
-            b"error",
+        Err(_) => (None, false),
+    };
+    let healthy = timer_worker_alive && !read_only && rows.is_some();
+    RemoteHealth {
+        status: if healthy { "ok" } else { "degraded" }.to_string(),
+        version: version.to_string(),
+        uptime_seconds: at - state.started_at,
+        timer_worker_alive,
+        last_tick_at,
+        read_only,
+        rows: rows.unwrap_or_default(),
+    }
+}
+
+fn db_stats(conn: &Connection) -> AppResult<DbStats> {
+    let pragma = |name: &str| -> AppResult<String> {
+        conn.query_row(&format!("PRAGMA {name}"), [], |row| {
+            row.get::<_, rusqlite::types::Value>(0)
+        })
+        .map(|value| match value {
+            Value::Integer(n) => n.to_string(),
+            Value::Text(text) => text,
+            other => format!("{other:?}"),
+        })
+        .map_err(|e| e.to_string())
+    };
+    let page_count: i64 = pragma("page_count")?.parse().unwrap_or(0);
+    let page_size: i64 = pragma("page_size")?.parse().unwrap_or(0);
+    let names: Vec<String> = {
+        let mut stmt = conn
+            .prepare(
+                "SELECT name FROM sqlite_master
+                 WHERE type = 'table' AND name NOT LIKE 'sqlite_%' ORDER BY name",
+            )
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map([], |row| row.get(0))
+            .map_err(|e| e.to_string())?;
+        rows.collect::<Result<_, _>>().map_err(|e| e.to_string())?
+    };
+    let mut tables = Vec::new();
+    for table in names {
+        let rows = conn
+            .query_row(&format!("SELECT COUNT(*) FROM \"{table}\""), [], |row| {
+                row.get(0)
+            })
+            .map_err(|e| e.to_string())?;
+        tables.push(TableStats { table, rows });
+    }
+    Ok(DbStats {
+        size_bytes: page_count * page_size,
+        journal_mode: pragma("journal_mode")?,
+        quick_check: pragma("quick_check")?,
+        tables,
+        integrity: check_database(conn)?,
+    })
+}
+
+// Everything in the bundle is counts, ids, timestamps or fixed vocabularies: no project, tag
+// or note text, tokens, secrets or URLs. The timer transition log stands in for app logs.
+fn build_support_bundle(
+    conn: &Connection,
+    settings: &AppSettings,
+    diagnostics: &AppDiagnostics,
+    now: i64,
+) -> AppResult<Vec<u8>> {
+    let manifest = serde_json::json!({
+        "createdAt": now,
+        "version": diagnostics.version,
+        "os": std::env::consts::OS,
+        "arch": std::env::consts::ARCH,
+    });
+    let events = fetch_timer_events(
+        conn,
+        &TimerEventQuery {
+            from: Some(now - 7 * 86_400),
+            to: None,
+            limit: Some(1000),
+        },
+    )?;
+    let files = [
+        ("manifest.json", manifest),
+        (
+            "diagnostics.json",
+            serde_json::to_value(diagnostics).map_err(|e| e.to_string())?,
         ),
-        None => write_response(
-            &mut stream,
-            "404 Not Found",
-            "text/plain; charset=utf-8",
-            b"not found",
+        ("settings.json", redacted_settings(settings)?),
+        (
+            "database.json",
+            serde_json::to_value(db_stats(conn)?).map_err(|e| e.to_string())?,
+        ),
+        (
+            "timer-events.json",
+            serde_json::to_value(events).map_err(|e| e.to_string())?,
         ),
+    ];
+
+    let mut zip = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    let file_options = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated);
+    for (name, content) in files {
+        let text = serde_json::to_string_pretty(&content).map_err(|e| e.to_string())?;
+        zip.start_file(name, file_options)
+            .map_err(|e| e.to_string())?;
+        zip.write_all(text.as_bytes()).map_err(|e| e.to_string())?;
+    }
+    let cursor = zip.finish().map_err(|e| e.to_string())?;
+    Ok(cursor.into_inner())
+}
+
+// Built on request only and never sent anywhere; the user attaches the zip themselves.
+#[tauri::command]
+fn support_bundle(app: AppHandle, state: State<'_, AppState>) -> AppResult<BinaryExportResult> {
+    let diagnostics = collect_diagnostics(&app, &state)?;
+    let model = lock_model(&state)?;
+    let now = now_ts();
+    let bundle = build_support_bundle(&model.conn, &model.settings, &diagnostics, now)?;
+    Ok(BinaryExportResult {
+        filename: format!("pomodoro-support-{now}.zip"),
+        content_base64: BASE64_STANDARD.encode(bundle),
+    })
+}
+
+#[tauri::command]
+fn db_self_check(state: State<'_, AppState>) -> AppResult<DbCheckReport> {
+    let model = lock_model(&state)?;
+    check_database(&model.conn)
+}
+
+#[tauri::command]
+fn db_repair(state: State<'_, AppState>) -> AppResult<DbCheckReport> {
+    let mut model = lock_model(&state)?;
+    repair_database(&mut model.conn)
+}
+
+#[tauri::command]
+fn db_read_only_status(state: State<'_, AppState>) -> AppResult<ReadOnlyStatus> {
+    let model = lock_model(&state)?;
+    Ok(read_only_status(&model, 0))
+}
+
+#[tauri::command]
+fn db_recovery_status(state: State<'_, AppState>) -> AppResult<Option<DbRecoveryReport>> {
+    let model = lock_model(&state)?;
+    Ok(model.db_recovery.clone())
+}
+
+#[tauri::command]
+fn db_recovery_dismiss(state: State<'_, AppState>) -> AppResult<()> {
+    let mut model = lock_model(&state)?;
+    model.db_recovery = None;
+    Ok(())
+}
+
+#[tauri::command]
+fn projects_list(state: State<'_, AppState>) -> AppResult<Vec<Project>> {
+    let model = lock_model(&state)?;
+    fetch_projects(&model.conn)
+}
+
+#[tauri::command]
+fn projects_upsert(app: AppHandle, input: ProjectInput) -> AppResult<Project> {
+    mutate(
+        &app,
+        "projects_upsert",
+        &[DataDomain::Projects],
+        |model, _| upsert_project(&model.conn, &input),
+    )
+}
+
+fn upsert_project(conn: &Connection, input: &ProjectInput) -> AppResult<Project> {
+    if input
+        .hourly_rate
+        .flatten()
+        .is_some_and(|rate| !rate.is_finite() || rate < 0.0)
+    {
+        return Err("hourly rate must be a non-negative number".to_string());
+    }
+    if input.estimated_pomodoros.is_some_and(|n| n < 0) {
+        return Err("estimated pomodoros must not be negative".to_string());
+    }
+    if let Some(sound) = input
+        .notification_override
+        .as_ref()
+        .and_then(|o| o.sound.as_deref())
+    {
+        if !PROJECT_SOUNDS.contains(&sound) {
+            return Err(format!("unknown sound: {sound}"));
+        }
+    }
+    let archived = input.archived.unwrap_or(false);
+    let billable = input.billable.map(|v| v as i64);
+    let pinned = input.pinned.map(|v| v as i64);
+    let override_set = input.notification_override.is_some();
+    let alerts = input.notification_override.clone().unwrap_or_default();
+    let notify_override = alerts.notify.map(|v| v as i64);
+    let estimate_set = input.estimated_pomodoros.is_some();
+    let estimate = input.estimated_pomodoros.filter(|n| *n > 0);
+    let id = if let Some(id) = input.id {
+        conn.execute(
+            "UPDATE projects SET name = ?1, color = ?2, archived = ?3,
+                 billable = COALESCE(?4, billable),
+                 hourly_rate = CASE WHEN ?5 THEN ?6 ELSE hourly_rate END,
+                 pinned = COALESCE(?7, pinned),
+                 notify_override = CASE WHEN ?8 THEN ?9 ELSE notify_override END,
+                 sound_override = CASE WHEN ?8 THEN ?10 ELSE sound_override END,
+                 estimated_pomodoros = CASE WHEN ?11 THEN ?12 ELSE estimated_pomodoros END
+             WHERE id = ?13",
+            params![
+                input.name,
+                input.color,
+                archived as i64,
+                billable,
+                input.hourly_rate.is_some(),
+                input.hourly_rate.flatten(),
+                pinned,
+                override_set,
+                notify_override,
+                alerts.sound,
+                estimate_set,
+                estimate,
+                id
+            ],
+        )
+        .map_err(|e| e.to_string())?;
+        id
+    } else {
+        conn.execute(
+            // New projects go to the end of the manual order.
+            "INSERT INTO projects (name, color, archived, billable, hourly_rate, pinned, sort_order,
+                 notify_override, sound_override, estimated_pomodoros, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, (SELECT COALESCE(MAX(sort_order), -1) + 1 FROM projects),
+                 ?7, ?8, ?9, ?10)",
+            params![
+                input.name,
+                input.color,
+                archived as i64,
+                billable.unwrap_or(0),
+                input.hourly_rate.flatten(),
+                pinned.unwrap_or(0),
+                notify_override,
+                alerts.sound,
+                estimate,
+                now_ts()
+            ],
+        )
+        .map_err(|e| e.to_string())?;
+        conn.last_insert_rowid()
+    };
+
+    fetch_project(conn, id)
+}
+
+#[tauri::command]
+fn days_off_list(
+    from: Option<String>,
+    to: Option<String>,
+    state: State<'_, AppState>,
+) -> AppResult<Vec<DayOff>> {
+    let model = lock_model(&state)?;
+    fetch_days_off(&model.conn, from.as_deref(), to.as_deref())
+}
+
+// Marking a day that came from an import turns it into a manual entry.
+#[tauri::command]
+fn days_off_set(app: AppHandle, day: String, name: Option<String>) -> AppResult<DayOff> {
+    mutate(&app, "days_off_set", &[DataDomain::DaysOff], |model, _| {
+        let day = parse_day(&day)?.format("%Y-%m-%d").to_string();
+        let name = name.map(|n| n.trim().to_string()).filter(|n| !n.is_empty());
+        model
+            .conn
+            .execute(
+                "INSERT INTO days_off (day, name, source) VALUES (?1, ?2, 'manual')
+                 ON CONFLICT(day) DO UPDATE SET name = excluded.name, source = 'manual'",
+                params![day, name],
+            )
+            .map_err(|e| e.to_string())?;
+        Ok(DayOff {
+            day,
+            name,
+            source: "manual".to_string(),
+        })
+    })
+}
+
+#[tauri::command]
+fn days_off_remove(app: AppHandle, days: Vec<String>) -> AppResult<i64> {
+    mutate(
+        &app,
+        "days_off_remove",
+        &[DataDomain::DaysOff],
+        |model, _| {
+            let mut removed = 0;
+            for day in days {
+                removed += model
+                    .conn
+                    .execute("DELETE FROM days_off WHERE day = ?1", params![day])
+                    .map_err(|e| e.to_string())? as i64;
+            }
+            Ok(removed)
+        },
+    )
+}
+
+#[tauri::command]
+fn days_off_import_ics(app: AppHandle, content: String) -> AppResult<DaysOffImportResult> {
+    let days = parse_ics_days_off(&content);
+    if days.is_empty() {
+        return Err("no events with a start date found in calendar".to_string());
+    }
+    mutate(
+        &app,
+        "days_off_import_ics",
+        &[DataDomain::DaysOff],
+        |model, _| import_days_off(&mut model.conn, &days),
+    )
+}
+
+#[tauri::command]
+fn tags_list(state: State<'_, AppState>) -> AppResult<Vec<Tag>> {
+    let model = lock_model(&state)?;
+    fetch_tags(&model.conn)
+}
+
+#[tauri::command]
+fn tags_list_with_stats(
+    range: Option<AnalyticsRange>,
+    state: State<'_, AppState>,
+) -> AppResult<Vec<TagWithStats>> {
+    let model = lock_model(&state)?;
+    fetch_tags_with_stats(&model.conn, &range.unwrap_or_default())
+}
+
+#[tauri::command]
+fn tags_upsert(app: AppHandle, input: TagInput) -> AppResult<Tag> {
+    mutate(&app, "tags_upsert", &[DataDomain::Tags], |model, _| {
+        upsert_tag(&model.conn, &input)
+    })
+}
+
+fn upsert_tag(conn: &Connection, input: &TagInput) -> AppResult<Tag> {
+    let id = if let Some(id) = input.id {
+        conn.execute(
+            "UPDATE tags SET name = ?1 WHERE id = ?2",
+            params![input.name, id],
+        )
+        .map_err(|e| e.to_string())?;
+        id
+    } else {
+        conn.execute(
+            "INSERT INTO tags (name, created_at) VALUES (?1, ?2)",
+            params![input.name, now_ts()],
+        )
+        .map_err(|e| e.to_string())?;
+        conn.last_insert_rowid()
+    };
+
+    conn.query_row(
+        "SELECT id, name FROM tags WHERE id = ?1",
+        params![id],
+        |row| {
+            Ok(Tag {
+                id: row.get(0)?,
+                name: row.get(1)?,
+            })
+        },
+    )
+    .map_err(|e| e.to_string())
+}
+
+fn id_name_map(conn: &Connection, table: &str) -> AppResult<BTreeMap<i64, String>> {
+    let mut stmt = conn
+        .prepare(&format!("SELECT id, name FROM {table}"))
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+        .map_err(|e| e.to_string())?;
+    rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+}
+
+// Header and rows of a session CSV, so exports can be built in memory or streamed to a file.
+struct SessionCsvWriter {
+    dialect: CsvDialect,
+    columns: Vec<CsvColumn>,
+    projects: BTreeMap<i64, Project>,
+    tag_names: BTreeMap<i64, String>,
+    separator: String,
+}
+
+impl SessionCsvWriter {
+    fn new(conn: &Connection, options: &CsvOptions, timezone: &str) -> AppResult<Self> {
+        let dialect = CsvDialect::from_options(options, timezone)?;
+        let mut columns = match &options.columns {
+            Some(names) if !names.is_empty() => names
+                .iter()
+                .map(|name| CsvColumn::from_header(name))
+                .collect::<AppResult<Vec<_>>>()?,
+            _ => CsvColumn::DEFAULT.to_vec(),
+        };
+        if options.local_timestamps.unwrap_or(false) {
+            for column in [
+                CsvColumn::StartedAtLocal,
+                CsvColumn::EndedAtLocal,
+                CsvColumn::Weekday,
+            ] {
+                if !columns.contains(&column) {
+                    columns.push(column);
+                }
+            }
+        }
+
+        // Names include trashed projects and tags so older sessions still resolve.
+        let needs_projects = columns.iter().any(|column| {
+            matches!(
+                column,
+                CsvColumn::ProjectName
+                    | CsvColumn::Billable
+                    | CsvColumn::HourlyRate
+                    | CsvColumn::Earnings
+            )
+        });
+        let projects = if needs_projects {
+            fetch_project_map(conn)?
+        } else {
+            BTreeMap::new()
+        };
+        let tag_names = if columns.contains(&CsvColumn::TagNames) {
+            id_name_map(conn, "tags")?
+        } else {
+            BTreeMap::new()
+        };
+
+        let separator = dialect.delimiter.to_string();
+        Ok(Self {
+            dialect,
+            columns,
+            projects,
+            tag_names,
+            separator,
+        })
+    }
+
+    fn header(&self) -> String {
+        let mut line = self
+            .columns
+            .iter()
+            .map(|column| column.header())
+            .collect::<Vec<_>>()
+            .join(&self.separator);
+        line.push('\n');
+        line
+    }
+
+    fn row(&self, s: &SessionRecord) -> String {
+        let dialect = &self.dialect;
+        let project = s.project_id.and_then(|id| self.projects.get(&id));
+        let fields: Vec<String> = self
+            .columns
+            .iter()
+            .map(|column| match column {
+                CsvColumn::Id => s.id.to_string(),
+                CsvColumn::StartedAt => dialect.timestamp(s.started_at),
+                CsvColumn::EndedAt => dialect.timestamp(s.ended_at),
+                CsvColumn::Phase => s.phase.as_db_value().to_string(),
+                CsvColumn::DurationSec => s.duration_sec.to_string(),
+                CsvColumn::Completed => s.completed.to_string(),
+                CsvColumn::Interruptions => s.interruptions.to_string(),
+                CsvColumn::ProjectId => s.project_id.map(|v| v.to_string()).unwrap_or_default(),
+                CsvColumn::TagIds => s
+                    .tag_ids
+                    .iter()
+                    .map(std::string::ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(";"),
+                CsvColumn::ProjectName => project.map(|p| p.name.clone()).unwrap_or_default(),
+                CsvColumn::TagNames => s
+                    .tag_ids
+                    .iter()
+                    .filter_map(|id| self.tag_names.get(id).cloned())
+                    .collect::<Vec<_>>()
+                    .join(";"),
+                CsvColumn::Notes => s.notes.clone().unwrap_or_default(),
+                CsvColumn::Quality => s.quality.map(|v| v.to_string()).unwrap_or_default(),
+                CsvColumn::Billable => project.is_some_and(|p| p.billable).to_string(),
+                CsvColumn::HourlyRate => project
+                    .and_then(|p| p.hourly_rate)
+                    .map(|rate| format!("{rate:.2}"))
+                    .unwrap_or_default(),
+                CsvColumn::Earnings => {
+                    let earnings: f64 = session_billing(s, &self.projects, None)
+                        .iter()
+                        .map(|(_, _, amount)| amount)
+                        .sum();
+                    format!("{:.2}", round_cents(earnings))
+                }
+                CsvColumn::StartedAtLocal => dialect.local(s.started_at),
+                CsvColumn::EndedAtLocal => dialect.local(s.ended_at),
+                CsvColumn::Weekday => dialect.weekday(s.started_at),
+            })
+            .map(|value| dialect.field(&value))
+            .collect();
+        let mut line = fields.join(&self.separator);
+        line.push('\n');
+        line
+    }
+}
+
+fn sessions_to_csv(
+    conn: &Connection,
+    sessions: &[SessionRecord],
+    options: &CsvOptions,
+    timezone: &str,
+) -> AppResult<String> {
+    let writer = SessionCsvWriter::new(conn, options, timezone)?;
+    let mut csv = writer.header();
+    for s in sessions {
+        csv.push_str(&writer.row(s));
+    }
+    Ok(csv)
+}
+
+#[tauri::command]
+fn export_csv(
+    range: ExportRange,
+    options: Option<CsvOptions>,
+    state: State<'_, AppState>,
+) -> AppResult<ExportResult> {
+    let model = lock_model(&state)?;
+    let sessions = fetch_sessions(
+        &model.conn,
+        &AnalyticsRange {
+            from: range.from,
+            to: range.to,
+            project_id: None,
+            tag_id: None,
+            granularity: None,
+            include_excluded: false,
+            phases: Vec::new(),
+            excluded: AnalyticsExclusions::default(),
+        },
+    )?;
+    let csv = sessions_to_csv(
+        &model.conn,
+        &sessions,
+        &options.unwrap_or_default(),
+        &model.settings.timezone,
+    )?;
+
+    Ok(ExportResult {
+        filename: format!("pomodoro-sessions-{}.csv", now_ts()),
+        content: csv,
+    })
+}
+
+// One file per table so the archive loads straight into SQL or pandas. Project and tag
+// tables include trashed rows, since exported sessions may still reference them.
+fn csv_bundle(conn: &Connection, sessions: &[SessionRecord]) -> AppResult<Vec<u8>> {
+    let dialect = CsvDialect::from_options(&CsvOptions::default(), "")?;
+    let table = |header: &[&str], rows: Vec<Vec<String>>| {
+        let mut csv = header.join(",");
+        csv.push('\n');
+        for row in rows {
+            let fields: Vec<String> = row.iter().map(|value| dialect.field(value)).collect();
+            csv.push_str(&fields.join(","));
+            csv.push('\n');
+        }
+        csv
+    };
+
+    let session_columns = [
+        "id",
+        "startedAt",
+        "endedAt",
+        "phase",
+        "durationSec",
+        "completed",
+        "interruptions",
+        "projectId",
+        "notes",
+        "quality",
+    ];
+    let sessions_csv = sessions_to_csv(
+        conn,
+        sessions,
+        &CsvOptions {
+            columns: Some(session_columns.iter().map(|c| c.to_string()).collect()),
+            ..CsvOptions::default()
+        },
+        "",
+    )?;
+    let projects_csv = table(
+        &["id", "name", "color", "archived", "billable", "hourlyRate"],
+        fetch_project_map(conn)?
+            .into_values()
+            .map(|p| {
+                vec![
+                    p.id.to_string(),
+                    p.name,
+                    p.color.unwrap_or_default(),
+                    p.archived.to_string(),
+                    p.billable.to_string(),
+                    p.hourly_rate.map(|r| r.to_string()).unwrap_or_default(),
+                ]
+            })
+            .collect(),
+    );
+    let tags_csv = table(
+        &["id", "name"],
+        id_name_map(conn, "tags")?
+            .into_iter()
+            .map(|(id, name)| vec![id.to_string(), name])
+            .collect(),
+    );
+    let session_tags_csv = table(
+        &["sessionId", "tagId"],
+        sessions
+            .iter()
+            .flat_map(|s| {
+                s.tag_ids
+                    .iter()
+                    .map(|tag_id| vec![s.id.to_string(), tag_id.to_string()])
+            })
+            .collect(),
+    );
+    let splits_csv = table(
+        &["sessionId", "projectId", "percent"],
+        sessions
+            .iter()
+            .flat_map(|s| {
+                s.project_splits.iter().map(|split| {
+                    vec![
+                        s.id.to_string(),
+                        split.project_id.to_string(),
+                        split.percent.to_string(),
+                    ]
+                })
+            })
+            .collect(),
+    );
+
+    let mut zip = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    let file_options = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated);
+    for (name, content) in [
+        ("sessions.csv", sessions_csv),
+        ("projects.csv", projects_csv),
+        ("tags.csv", tags_csv),
+        ("session_tags.csv", session_tags_csv),
+        ("session_project_splits.csv", splits_csv),
+    ] {
+        zip.start_file(name, file_options)
+            .map_err(|e| e.to_string())?;
+        zip.write_all(content.as_bytes())
+            .map_err(|e| e.to_string())?;
+    }
+    let cursor = zip.finish().map_err(|e| e.to_string())?;
+    Ok(cursor.into_inner())
+}
+
+#[tauri::command]
+fn export_ical(range: ExportRange, state: State<'_, AppState>) -> AppResult<ExportResult> {
+    let model = lock_model(&state)?;
+    let sessions = fetch_sessions(
+        &model.conn,
+        &AnalyticsRange {
+            from: range.from,
+            to: range.to,
+            phases: vec![TimerPhase::Focus],
+            ..AnalyticsRange::default()
+        },
+    )?;
+
+    Ok(ExportResult {
+        filename: format!("pomodoro-sessions-{}.ics", now_ts()),
+        content: sessions_to_ics(&model.conn, &sessions, now_ts())?,
+    })
+}
+
+#[tauri::command]
+fn export_csv_bundle(
+    range: ExportRange,
+    state: State<'_, AppState>,
+) -> AppResult<BinaryExportResult> {
+    let model = lock_model(&state)?;
+    let sessions = fetch_sessions(
+        &model.conn,
+        &AnalyticsRange {
+            from: range.from,
+            to: range.to,
+            project_id: None,
+            tag_id: None,
+            granularity: None,
+            include_excluded: false,
+            phases: Vec::new(),
+            excluded: AnalyticsExclusions::default(),
+        },
+    )?;
+    let bundle = csv_bundle(&model.conn, &sessions)?;
+
+    Ok(BinaryExportResult {
+        filename: format!("pomodoro-export-{}.zip", now_ts()),
+        content_base64: BASE64_STANDARD.encode(bundle),
+    })
+}
+
+fn build_backup_payload(
+    conn: &Connection,
+    settings: &AppSettings,
+    range: &ExportRange,
+) -> AppResult<serde_json::Value> {
+    let sessions = fetch_sessions(
+        conn,
+        &AnalyticsRange {
+            from: range.from,
+            to: range.to,
+            project_id: None,
+            tag_id: None,
+            granularity: None,
+            include_excluded: false,
+            phases: Vec::new(),
+            excluded: AnalyticsExclusions::default(),
+        },
+    )?;
+    let projects = fetch_projects(conn)?;
+    let tags = fetch_tags(conn)?;
+
+    Ok(serde_json::json!({
+      "schemaVersion": BACKUP_SCHEMA_VERSION,
+      "exportedAt": now_ts(),
+      "settings": settings,
+      "projects": projects,
+      "tags": tags,
+      "sessions": sessions
+    }))
+}
+
+// Read-only convenience fields; import ignores them.
+fn add_local_timestamps(payload: &mut serde_json::Value, timezone: &str) {
+    let Some(sessions) = payload
+        .get_mut("sessions")
+        .and_then(|sessions| sessions.as_array_mut())
+    else {
+        return;
+    };
+    for session in sessions.iter_mut() {
+        add_session_local_timestamps(session, timezone);
+    }
+}
+
+fn add_session_local_timestamps(session: &mut serde_json::Value, timezone: &str) {
+    let Some(session) = session.as_object_mut() else {
+        return;
+    };
+    let started_at = session.get("startedAt").and_then(|v| v.as_i64());
+    let ended_at = session.get("endedAt").and_then(|v| v.as_i64());
+    if let (Some(started_at), Some(ended_at)) = (started_at, ended_at) {
+        let started = zoned_datetime(started_at, timezone);
+        let ended = zoned_datetime(ended_at, timezone);
+        session.insert("startedAtLocal".into(), started.to_rfc3339().into());
+        session.insert("endedAtLocal".into(), ended.to_rfc3339().into());
+        session.insert("weekday".into(), started.format("%A").to_string().into());
+    }
+}
+
+#[tauri::command]
+fn export_json(
+    range: ExportRange,
+    options: Option<JsonExportOptions>,
+    state: State<'_, AppState>,
+) -> AppResult<ExportResult> {
+    let model = lock_model(&state)?;
+    let mut payload = build_backup_payload(&model.conn, &model.settings, &range)?;
+    if options
+        .unwrap_or_default()
+        .local_timestamps
+        .unwrap_or(false)
+    {
+        add_local_timestamps(&mut payload, &model.settings.timezone);
+    }
+
+    Ok(ExportResult {
+        filename: format!("pomodoro-backup-{}.json", now_ts()),
+        content: serde_json::to_string_pretty(&payload).map_err(|e| e.to_string())?,
+    })
+}
+
+const EXPORT_PAGE_SIZE: i64 = 500;
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FileExportResult {
+    path: String,
+    rows: usize,
+}
+
+// Feeds every session page to `write` and returns the number of sessions written.
+// `next_page` maps a cursor to a page and the cursor after it, the way fetch_session_keyset does.
+fn stream_session_pages(
+    mut next_page: impl FnMut(Option<&str>) -> AppResult<(Vec<SessionRecord>, Option<String>)>,
+    mut write: impl FnMut(&SessionRecord) -> AppResult<()>,
+) -> AppResult<usize> {
+    let mut cursor: Option<String> = None;
+    let mut rows = 0;
+    loop {
+        let (sessions, next_cursor) = next_page(cursor.as_deref())?;
+        for session in &sessions {
+            write(session)?;
+        }
+        rows += sessions.len();
+        match next_cursor {
+            Some(next) => cursor = Some(next),
+            None => return Ok(rows),
+        }
+    }
+}
+
+fn write_sessions_csv(
+    out: &mut impl Write,
+    writer: &SessionCsvWriter,
+    next_page: impl FnMut(Option<&str>) -> AppResult<(Vec<SessionRecord>, Option<String>)>,
+) -> AppResult<usize> {
+    out.write_all(writer.header().as_bytes())
+        .map_err(|e| e.to_string())?;
+    stream_session_pages(next_page, |session| {
+        out.write_all(writer.row(session).as_bytes())
+            .map_err(|e| e.to_string())
+    })
+}
+
+// Same document as export_json, minus the indentation, written one session at a time.
+fn write_sessions_json(
+    out: &mut impl Write,
+    header: &serde_json::Value,
+    local_timezone: Option<&str>,
+    next_page: impl FnMut(Option<&str>) -> AppResult<(Vec<SessionRecord>, Option<String>)>,
+) -> AppResult<usize> {
+    let mut head = serde_json::to_string(header).map_err(|e| e.to_string())?;
+    // Reopen the (non-empty) header object to append the sessions array.
+    head.pop();
+    head.push_str(",\"sessions\":[");
+    out.write_all(head.as_bytes()).map_err(|e| e.to_string())?;
+
+    let mut first = true;
+    let rows = stream_session_pages(next_page, |session| {
+        let mut value = serde_json::to_value(session).map_err(|e| e.to_string())?;
+        if let Some(timezone) = local_timezone {
+            add_session_local_timestamps(&mut value, timezone);
+        }
+        if !first {
+            out.write_all(b",").map_err(|e| e.to_string())?;
+        }
+        first = false;
+        serde_json::to_writer(&mut *out, &value).map_err(|e| e.to_string())
+    })?;
+    out.write_all(b"]}").map_err(|e| e.to_string())?;
+    Ok(rows)
+}
+
+// Blocks until the dialog closes, so callers must be async commands (off the main thread).
+fn pick_save_path(
+    app: &AppHandle,
+    filename: &str,
+    filter_name: &str,
+    extension: &str,
+) -> AppResult<Option<std::path::PathBuf>> {
+    let Some(picked) = app
+        .dialog()
+        .file()
+        .set_file_name(filename)
+        .add_filter(filter_name, &[extension])
+        .blocking_save_file()
+    else {
+        return Ok(None);
+    };
+    picked.into_path().map(Some).map_err(|e| e.to_string())
+}
+
+// Streams into `path`, taking the model lock once per page so the timer keeps running
+// during long exports. A failed export removes the half-written file.
+fn stream_export_to_file(
+    app: &AppHandle,
+    path: std::path::PathBuf,
+    range: &ExportRange,
+    write: impl FnOnce(
+        &mut std::io::BufWriter<fs::File>,
+        &mut dyn FnMut(Option<&str>) -> AppResult<(Vec<SessionRecord>, Option<String>)>,
+    ) -> AppResult<usize>,
+) -> AppResult<FileExportResult> {
+    let state = app.state::<AppState>();
+    let (filter, values) = build_sessions_filter(&AnalyticsRange {
+        from: range.from,
+        to: range.to,
+        ..AnalyticsRange::default()
+    });
+    let mut next_page = |cursor: Option<&str>| {
+        let model = lock_model(&state)?;
+        fetch_session_keyset(
+            &model.conn,
+            &filter,
+            values.clone(),
+            cursor,
+            EXPORT_PAGE_SIZE,
+        )
+    };
+
+    let file =
+        fs::File::create(&path).map_err(|e| format!("cannot create {}: {e}", path.display()))?;
+    let mut out = std::io::BufWriter::new(file);
+    let result = write(&mut out, &mut next_page)
+        .and_then(|rows| out.flush().map(|_| rows).map_err(|e| e.to_string()));
+    match result {
+        Ok(rows) => Ok(FileExportResult {
+            path: path.to_string_lossy().to_string(),
+            rows,
+        }),
+        Err(error) => {
+            drop(out);
+            let _ = fs::remove_file(&path);
+            Err(error)
+        }
+    }
+}
+
+// Resolves to None when the save dialog is cancelled.
+#[tauri::command]
+async fn export_csv_to_file(
+    app: AppHandle,
+    range: ExportRange,
+    options: Option<CsvOptions>,
+) -> AppResult<Option<FileExportResult>> {
+    let filename = format!("pomodoro-sessions-{}.csv", now_ts());
+    let Some(path) = pick_save_path(&app, &filename, "CSV", "csv")? else {
+        return Ok(None);
+    };
+    let writer = {
+        let state = app.state::<AppState>();
+        let model = lock_model(&state)?;
+        SessionCsvWriter::new(
+            &model.conn,
+            &options.unwrap_or_default(),
+            &model.settings.timezone,
+        )?
+    };
+    stream_export_to_file(&app, path, &range, |out, next_page| {
+        write_sessions_csv(out, &writer, next_page)
+    })
+    .map(Some)
+}
+
+// Resolves to None when the save dialog is cancelled.
+#[tauri::command]
+async fn export_json_to_file(
+    app: AppHandle,
+    range: ExportRange,
+    options: Option<JsonExportOptions>,
+) -> AppResult<Option<FileExportResult>> {
+    let filename = format!("pomodoro-backup-{}.json", now_ts());
+    let Some(path) = pick_save_path(&app, &filename, "JSON", "json")? else {
+        return Ok(None);
+    };
+    let (header, timezone) = {
+        let state = app.state::<AppState>();
+        let model = lock_model(&state)?;
+        let header = serde_json::json!({
+          "schemaVersion": BACKUP_SCHEMA_VERSION,
+          "exportedAt": now_ts(),
+          "settings": model.settings,
+          "projects": fetch_projects(&model.conn)?,
+          "tags": fetch_tags(&model.conn)?,
+        });
+        (header, model.settings.timezone.clone())
+    };
+    let local_timezone = options
+        .unwrap_or_default()
+        .local_timestamps
+        .unwrap_or(false)
+        .then_some(timezone.as_str());
+    stream_export_to_file(&app, path, &range, |out, next_page| {
+        write_sessions_json(out, &header, local_timezone, next_page)
+    })
+    .map(Some)
+}
+
+// Minimal RFC 4180 reader: quoted fields may contain the delimiter, quotes ("") and newlines.
+fn parse_csv_records(content: &str, delimiter: char) -> Vec<Vec<String>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = content.chars().peekable();
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    field.push('"');
+                    chars.next();
+                }
+                '"' => in_quotes = false,
+                _ => field.push(c),
+            }
+        } else if c == '"' {
+            in_quotes = true;
+        } else if c == delimiter {
+            record.push(std::mem::take(&mut field));
+        } else if c == '\n' || c == '\r' {
+            if c == '\r' && chars.peek() == Some(&'\n') {
+                chars.next();
+            }
+            record.push(std::mem::take(&mut field));
+            records.push(std::mem::take(&mut record));
+        } else {
+            field.push(c);
+        }
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    records
+        .into_iter()
+        .filter(|r| !(r.len() == 1 && r[0].trim().is_empty()))
+        .collect()
+}
+
+// Accepts both epoch seconds and RFC 3339, matching the two export date formats.
+fn parse_import_timestamp(value: &str) -> Result<i64, String> {
+    let value = value.trim();
+    value
+        .parse::<i64>()
+        .or_else(|_| chrono::DateTime::parse_from_rfc3339(value).map(|dt| dt.timestamp()))
+        .map_err(|_| format!("invalid timestamp: {value}"))
+}
+
+fn parse_csv_import(content: &str, mapping: &CsvColumnMapping) -> AppResult<ImportBatch> {
+    let header_line = content.lines().next().unwrap_or_default();
+    let delimiter = [',', ';', '\t']
+        .into_iter()
+        .max_by_key(|d| header_line.matches(*d).count())
+        .unwrap_or(',');
+    let mut records = parse_csv_records(content.trim_start_matches('\u{feff}'), delimiter);
+    if records.is_empty() {
+        return Err("CSV file is empty".to_string());
+    }
+    let header = records.remove(0);
+    let index = |name: &str| header.iter().position(|h| h.trim() == name.trim());
+    let required = [
+        Some(&mapping.started_at),
+        Some(&mapping.ended_at),
+        mapping.phase.as_ref(),
+        mapping.duration_sec.as_ref(),
+    ];
+    if let Some(missing) = required
+        .into_iter()
+        .flatten()
+        .find(|name| index(name).is_none())
+    {
+        return Err(format!("CSV file is missing the {missing} column"));
+    }
+    let column = |record: &[String], name: Option<&String>| -> Option<String> {
+        name.and_then(|name| index(name))
+            .and_then(|i| record.get(i))
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty())
+    };
+    let tag_separator = mapping.tag_separator.as_deref().unwrap_or(";");
+
+    let mut batch = ImportBatch {
+        sessions: Vec::new(),
+        project_names: Vec::new(),
+        tag_names: Vec::new(),
+        errors: Vec::new(),
+    };
+    for (i, record) in records.iter().enumerate() {
+        let row = i + 1;
+        let parsed = (|| -> Result<ImportedSession, String> {
+            let required =
+                |name: &String| column(record, Some(name)).ok_or(format!("{name} is empty"));
+            let number = |name: &str, value: String| {
+                value
+                    .parse::<i64>()
+                    .map_err(|_| format!("{name} is not a number: {value}"))
+            };
+            let started_at = parse_import_timestamp(&required(&mapping.started_at)?)?;
+            let ended_at = parse_import_timestamp(&required(&mapping.ended_at)?)?;
+            Ok(ImportedSession {
+                row,
+                started_at,
+                ended_at,
+                phase: match &mapping.phase {
+                    Some(name) => TimerPhase::from_db_value(&required(name)?)?,
+                    None => TimerPhase::Focus,
+                },
+                duration_sec: match &mapping.duration_sec {
+                    Some(name) => number(name, required(name)?)?,
+                    None => ended_at - started_at,
+                },
+                completed: match column(record, mapping.completed.as_ref()) {
+                    Some(value) => match value.to_lowercase().as_str() {
+                        "1" | "true" | "yes" => true,
+                        "0" | "false" | "no" => false,
+                        _ => return Err(format!("completed is not a boolean: {value}")),
+                    },
+                    None => true,
+                },
+                interruptions: column(record, mapping.interruptions.as_ref())
+                    .map(|v| number("interruptions", v))
+                    .transpose()?
+                    .unwrap_or(0),
+                project_name: column(record, mapping.project_name.as_ref()),
+                tag_names: column(record, mapping.tag_names.as_ref())
+                    .map(|names| {
+                        names
+                            .split(tag_separator)
+                            .map(str::trim)
+                            .filter(|n| !n.is_empty())
+                            .map(str::to_string)
+                            .collect()
+                    })
+                    .unwrap_or_default(),
+                quality: column(record, mapping.quality.as_ref())
+                    .map(|v| number("quality", v))
+                    .transpose()?,
+            })
+        })();
+        match parsed {
+            Ok(session) => {
+                batch.project_names.extend(session.project_name.clone());
+                batch.tag_names.extend(session.tag_names.iter().cloned());
+                batch.sessions.push(session);
+            }
+            Err(message) => batch.errors.push(ImportRowError { row, message }),
+        }
+    }
+    Ok(batch)
+}
+
+fn parse_json_import(content: &str) -> AppResult<ImportBatch> {
+    let payload: BackupPayload = serde_json::from_str(content).map_err(|e| e.to_string())?;
+    let project_names: BTreeMap<i64, String> = payload
+        .projects
+        .iter()
+        .map(|p| (p.id, p.name.clone()))
+        .collect();
+    let tag_names: BTreeMap<i64, String> = payload
+        .tags
+        .iter()
+        .map(|t| (t.id, t.name.clone()))
+        .collect();
+
+    let mut batch = ImportBatch {
+        sessions: Vec::new(),
+        project_names: project_names.values().cloned().collect(),
+        tag_names: tag_names.values().cloned().collect(),
+        errors: Vec::new(),
+    };
+    for (i, s) in payload.sessions.into_iter().enumerate() {
+        let row = i + 1;
+        let project_name = match s.project_id {
+            Some(id) => match project_names.get(&id) {
+                Some(name) => Some(name.clone()),
+                None => {
+                    batch.errors.push(ImportRowError {
+                        row,
+                        message: format!("unknown projectId {id}"),
+                    });
+                    continue;
+                }
+            },
+            None => None,
+        };
+        let Some(tags) = s
+            .tag_ids
+            .iter()
+            .map(|id| tag_names.get(id).cloned().ok_or(*id))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|id| {
+                batch.errors.push(ImportRowError {
+                    row,
+                    message: format!("unknown tagId {id}"),
+                })
+            })
+            .ok()
+        else {
+            continue;
+        };
+        batch.sessions.push(ImportedSession {
+            row,
+            started_at: s.started_at,
+            ended_at: s.ended_at,
+            phase: s.phase,
+            duration_sec: s.duration_sec,
+            completed: s.completed,
+            interruptions: s.interruptions,
+            project_name,
+            tag_names: tags,
+            quality: s.quality,
+        });
+    }
+    Ok(batch)
+}
+
+fn parse_import(format: &str, content: &str) -> AppResult<ImportBatch> {
+    match format {
+        "csv" => parse_csv_import(content, &CsvColumnMapping::pulse_export()),
+        "json" => parse_json_import(content),
+        other => Err(format!("unknown import format: {other}")),
+    }
+}
+
+// Names not yet in the database, deduplicated case-insensitively, in first-seen order.
+fn missing_names(names: &[String], existing: &HashSet<String>) -> Vec<String> {
+    let mut seen = HashSet::new();
+    names
+        .iter()
+        .map(|name| name.trim())
+        .filter(|name| !name.is_empty())
+        .filter(|name| {
+            let key = name.to_lowercase();
+            !existing.contains(&key) && seen.insert(key)
+        })
+        .map(str::to_string)
+        .collect()
+}
+
+// Writes parsed rows in one transaction, creating missing projects and tags by name. Rows
+// that fail validation are reported next to the batch's parse errors and skipped.
+fn import_sessions(
+    conn: &mut Connection,
+    mut batch: ImportBatch,
+    now: i64,
+) -> AppResult<CsvImportResult> {
+    let tx = conn.savepoint().map_err(|e| e.to_string())?;
+    let mut result = CsvImportResult {
+        imported: 0,
+        duplicates: 0,
+        projects_created: Vec::new(),
+        tags_created: Vec::new(),
+        errors: Vec::new(),
+    };
+    let mut seen = HashSet::new();
+    for session in &batch.sessions {
+        if let Err(error) = check_session_fields(
+            session.started_at,
+            session.ended_at,
+            session.duration_sec,
+            session.interruptions,
+            session.quality,
+        ) {
+            batch.errors.push(ImportRowError {
+                row: session.row,
+                message: error.to_string(),
+            });
+            continue;
+        }
+        let phase = session.phase.as_db_value();
+        let exists = tx
+            .prepare_cached(
+                "SELECT 1 FROM sessions WHERE deleted_at IS NULL AND started_at = ?1 AND phase = ?2",
+            )
+            .and_then(|mut stmt| stmt.exists(params![session.started_at, phase]))
+            .map_err(|e| e.to_string())?;
+        if !seen.insert((session.started_at, phase)) || exists {
+            result.duplicates += 1;
+            continue;
+        }
+        // Earlier rows are already inserted, so this also catches overlaps within the file.
+        if let Some(id) = find_overlap(&tx, session.started_at, session.ended_at, None)? {
+            batch.errors.push(ImportRowError {
+                row: session.row,
+                message: SessionValidationError::Overlap(id).to_string(),
+            });
+            continue;
+        }
+
+        let project_id = match &session.project_name {
+            Some(name) => Some(match find_named(&tx, "projects", name)? {
+                Some(id) => id,
+                None => {
+                    tx.execute(
+                        "INSERT INTO projects (name, sort_order, created_at)
+                         VALUES (?1, (SELECT COALESCE(MAX(sort_order), -1) + 1 FROM projects), ?2)",
+                        params![name, now],
+                    )
+                    .map_err(|e| e.to_string())?;
+                    result.projects_created.push(name.clone());
+                    tx.last_insert_rowid()
+                }
+            }),
+            None => None,
+        };
+        tx.execute(
+            "INSERT INTO sessions (started_at, ended_at, phase, duration_sec, completed, interruptions, project_id, quality)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                session.started_at,
+                session.ended_at,
+                phase,
+                session.duration_sec,
+                session.completed as i64,
+                session.interruptions,
+                project_id,
+                session.quality,
+            ],
+        )
+        .map_err(|e| e.to_string())?;
+        let session_id = tx.last_insert_rowid();
+        for name in &session.tag_names {
+            let tag_id = match find_named(&tx, "tags", name)? {
+                Some(id) => id,
+                None => {
+                    tx.execute(
+                        "INSERT INTO tags (name, created_at) VALUES (?1, ?2)",
+                        params![name, now],
+                    )
+                    .map_err(|e| e.to_string())?;
+                    result.tags_created.push(name.clone());
+                    tx.last_insert_rowid()
+                }
+            };
+            tx.execute(
+                "INSERT OR IGNORE INTO session_tags (session_id, tag_id) VALUES (?1, ?2)",
+                params![session_id, tag_id],
+            )
+            .map_err(|e| e.to_string())?;
+        }
+        result.imported += 1;
+    }
+    tx.commit().map_err(|e| e.to_string())?;
+
+    batch.errors.sort_by_key(|error| error.row);
+    result.errors = batch.errors;
+    Ok(result)
+}
+
+fn preview_import(conn: &Connection, format: &str, content: &str) -> AppResult<ImportPreview> {
+    let mut batch = parse_import(format, content)?;
+    let total_rows = batch.sessions.len() + batch.errors.len();
+
+    let mut valid = Vec::new();
+    for session in batch.sessions {
+        match check_session_fields(
+            session.started_at,
+            session.ended_at,
+            session.duration_sec,
+            session.interruptions,
+            session.quality,
+        ) {
+            Ok(()) => valid.push(session),
+            Err(error) => batch.errors.push(ImportRowError {
+                row: session.row,
+                message: error.to_string(),
+            }),
+        }
+    }
+
+    let mut exists = conn
+        .prepare(
+            "SELECT 1 FROM sessions WHERE deleted_at IS NULL AND started_at = ?1 AND phase = ?2",
+        )
+        .map_err(|e| e.to_string())?;
+    let mut seen = HashSet::new();
+    let mut duplicate_rows = 0;
+    let mut overlapping = HashSet::new();
+    for session in &valid {
+        let in_file = !seen.insert((session.started_at, session.phase.as_db_value()));
+        let in_db = exists
+            .exists(params![session.started_at, session.phase.as_db_value()])
+            .map_err(|e| e.to_string())?;
+        if in_file || in_db {
+            duplicate_rows += 1;
+        } else if let Some(id) = find_overlap(conn, session.started_at, session.ended_at, None)? {
+            overlapping.insert(session.row);
+            batch.errors.push(ImportRowError {
+                row: session.row,
+                message: SessionValidationError::Overlap(id).to_string(),
+            });
+        }
+    }
+    valid.retain(|session| !overlapping.contains(&session.row));
+    batch.errors.sort_by_key(|error| error.row);
+
+    let existing_projects: HashSet<String> = fetch_projects(conn)?
+        .into_iter()
+        .map(|p| p.name.to_lowercase())
+        .collect();
+    let existing_tags: HashSet<String> = fetch_tags(conn)?
+        .into_iter()
+        .map(|t| t.name.to_lowercase())
+        .collect();
+
+    Ok(ImportPreview {
+        format: format.to_string(),
+        total_rows,
+        valid_rows: valid.len(),
+        duplicate_rows,
+        projects_to_create: missing_names(&batch.project_names, &existing_projects),
+        tags_to_create: missing_names(&batch.tag_names, &existing_tags),
+        errors: batch.errors,
+    })
+}
+
+// Without a mapping the file must use export_csv's column names.
+#[tauri::command]
+fn import_csv(
+    app: AppHandle,
+    content: String,
+    mapping: Option<CsvColumnMapping>,
+) -> AppResult<CsvImportResult> {
+    let mapping = mapping.unwrap_or_else(CsvColumnMapping::pulse_export);
+    let batch = parse_csv_import(&content, &mapping)?;
+    mutate(
+        &app,
+        "import_csv",
+        &[DataDomain::Sessions, DataDomain::Projects, DataDomain::Tags],
+        |model, _| import_sessions(&mut model.conn, batch, now_ts()),
+    )
+}
+
+// Replace mode also resets the timer, whose project and tags may no longer exist, and gives
+// restored settings a team key when the backup had none.
+#[tauri::command]
+fn import_json(app: AppHandle, content: String, mode: ImportMode) -> AppResult<JsonImportResult> {
+    let backup: BackupPayload =
+        serde_json::from_str(&content).map_err(|e| format!("invalid backup file: {e}"))?;
+    mutate(
+        &app,
+        "import_json",
+        &[DataDomain::Sessions, DataDomain::Projects, DataDomain::Tags],
+        |model, mutation| {
+            let result = import_backup(&mut model.conn, &backup, mode, now_ts())?;
+            if mode == ImportMode::Replace {
+                if result.settings_restored {
+                    model.settings = load_json_setting(&model.conn, APP_SETTINGS_KEY)?
+                        .unwrap_or_else(|| model.settings.clone());
+                    ensure_team_stats_key(&mut model.settings);
+                    save_json_setting(&model.conn, APP_SETTINGS_KEY, &model.settings)?;
+                    mutation.touch(DataDomain::Settings);
+                }
+                model.timer = TimerState::default_with_settings(&model.settings);
+                mutation.touch(DataDomain::Timer);
+            }
+            Ok(result)
+        },
+    )
+}
+
+// Dry run: parses and validates without writing, so the UI can confirm before importing.
+#[tauri::command]
+fn import_preview(
+    format: String,
+    content: String,
+    state: State<'_, AppState>,
+) -> AppResult<ImportPreview> {
+    let model = lock_model(&state)?;
+    preview_import(&model.conn, &format, &content)
+}
+
+#[tauri::command]
+fn backup_now(app: AppHandle) -> AppResult<LocalBackup> {
+    run_local_backup(&app)
+}
+
+#[tauri::command]
+fn backup_list(app: AppHandle, state: State<'_, AppState>) -> AppResult<Vec<LocalBackup>> {
+    let settings = lock_model(&state)?.settings.clone();
+    list_local_backups(&local_backup_dir(&app, &settings)?)
+}
+
+#[tauri::command]
+fn cloud_backup_get(state: State<'_, AppState>) -> AppResult<CloudBackupStatus> {
+    let model = lock_model(&state)?;
+    Ok(load_cloud_backup_config(&model.conn)?.status())
+}
+
+#[tauri::command]
+fn cloud_backup_update(
+    app: AppHandle,
+    patch: CloudBackupConfigPatch,
+) -> AppResult<CloudBackupStatus> {
+    // The WebDAV config is not synced app data, so it is logged without a domain.
+    mutate(&app, "cloud_backup_update", &[], |model, _| {
+        let mut config = load_cloud_backup_config(&model.conn)?;
+
+        if let Some(v) = patch.enabled {
+            config.enabled = v;
+        }
+        if let Some(v) = patch.webdav_url {
+            config.webdav_url = v.trim().to_string();
+        }
+        if let Some(v) = patch.username {
+            config.username = v;
+        }
+        if let Some(v) = patch.password {
+            config.password = v;
+        }
+        if let Some(v) = patch.passphrase {
+            config.passphrase = v;
+        }
+        if let Some(v) = patch.interval_hours {
+            config.interval_hours = v.clamp(0, 24 * 30);
+        }
+
+        save_json_setting(&model.conn, CLOUD_BACKUP_KEY, &config)?;
+        Ok(config.status())
+    })
+}
+
+#[tauri::command]
+fn cloud_backup_now(app: AppHandle) -> AppResult<CloudBackupStatus> {
+    run_cloud_backup(&app)
+}
+
+#[tauri::command]
+fn cloud_backup_restore(app: AppHandle, state: State<'_, AppState>) -> AppResult<ResetAllResult> {
+    let config = {
+        let model = lock_model(&state)?;
+        load_cloud_backup_config(&model.conn)?
+    };
+
+    let (encrypted, _) = webdav_get(&config, CLOUD_BACKUP_FILENAME)?
+        .ok_or_else(|| "no cloud backup found on the server".to_string())?;
+    let plaintext = decrypt_backup(&encrypted, &config.passphrase)?;
+    let backup = serde_json::from_slice::<BackupPayload>(&plaintext)
+        .map_err(|e| format!("invalid backup payload: {e}"))?;
+
+    apply_restored_backup(&app, "cloud_backup_restore", &backup, |_| Ok(()))
+}
+
+#[tauri::command]
+fn sync_push(app: AppHandle, force: Option<bool>) -> AppResult<SyncOutcome> {
+    run_sync_push(&app, force.unwrap_or(false))
+}
+
+#[tauri::command]
+fn sync_pull(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    force: Option<bool>,
+) -> AppResult<SyncOutcome> {
+    let config = {
+        let model = lock_model(&state)?;
+        load_cloud_backup_config(&model.conn)?
+    };
+    if config.passphrase.is_empty() {
+        return Err(SYNC_PASSPHRASE_REQUIRED.to_string());
+    }
+
+    let (envelope, _) = fetch_sync_envelope(&config)?
+        .ok_or_else(|| "no sync data found on the server".to_string())?;
+    let backup = decode_sync_envelope(&envelope, &config.passphrase)?;
+    // Checked after the download, as close to the restore as possible.
+    let local_changes = {
+        let model = lock_model(&state)?;
+        has_unsynced_changes(&model.conn, config.synced_mutation_id)?
+    };
+    if let Some(outcome) = sync_pull_blocker(
+        &config,
+        envelope.revision,
+        local_changes,
+        force.unwrap_or(false),
+    ) {
+        return Ok(outcome);
+    }
+    apply_restored_backup(&app, "sync_pull", &backup, |conn| {
+        let mut config = load_cloud_backup_config(conn)?;
+        config.sync_revision = envelope.revision;
+        config.synced_mutation_id = last_mutation_id(conn)?;
+        config.last_sync_at = Some(now_ts());
+        save_json_setting(conn, CLOUD_BACKUP_KEY, &config)
+    })?;
+
+    Ok(SyncOutcome {
+        status: "pulled".to_string(),
+        local_revision: envelope.revision,
+        remote_revision: envelope.revision,
+    })
+}
+
+#[tauri::command]
+fn settings_get(state: State<'_, AppState>) -> AppResult<AppSettings> {
+    let model = lock_model(&state)?;
+    Ok(model.settings.clone())
+}
+
+#[tauri::command]
+fn settings_update(app: AppHandle, patch: AppSettingsPatch) -> AppResult<AppSettings> {
+    mutate(
+        &app,
+        "settings_update",
+        &[DataDomain::Settings, DataDomain::Timer],
+        |model, _| {
+            if let Some(v) = patch.focus_min {
+                model.settings.focus_min = v;
+            }
+            if let Some(v) = patch.short_break_min {
+                model.settings.short_break_min = v;
+            }
+            if let Some(v) = patch.long_break_min {
+                model.settings.long_break_min = v;
+            }
+            if let Some(v) = patch.long_break_every {
+                model.settings.long_break_every = v;
+            }
+            if let Some(v) = patch.theme {
+                model.settings.theme = v.trim().to_lowercase();
+            }
+            if let Some(v) = patch.sound_enabled {
+                model.settings.sound_enabled = v;
+            }
+            if let Some(v) = patch.notifications_enabled {
+                model.settings.notifications_enabled = v;
+            }
+            if let Some(v) = patch.remote_control_enabled {
+                model.settings.remote_control_enabled = v;
+            }
+            if let Some(v) = patch.remote_control_port {
+                model.settings.remote_control_port = v;
+            }
+            if let Some(v) = patch.cycle_reset_at_midnight {
+                model.settings.cycle_reset_at_midnight = v;
+            }
+            if let Some(v) = patch.cycle_reset_idle_min {
+                model.settings.cycle_reset_idle_min = v;
+            }
+            if let Some(v) = patch.max_pauses_per_focus {
+                model.settings.max_pauses_per_focus = v;
+            }
+            if let Some(v) = patch.pause_budget_action {
+                model.settings.pause_budget_action = v.trim().to_lowercase();
+            }
+            if let Some(v) = patch.team_stats_enabled {
+                model.settings.team_stats_enabled = v;
+            }
+            if let Some(v) = patch.team_stats_key {
+                model.settings.team_stats_key = v.trim().to_string();
+            }
+            if let Some(v) = patch.dnd_mode {
+                model.settings.dnd_mode = v.trim().to_lowercase();
+            }
+            if let Some(v) = patch.dnd_break_end_bypass {
+                model.settings.dnd_break_end_bypass = v;
+            }
+            if let Some(v) = patch.timezone {
+                model.settings.timezone = v.trim().to_string();
+            }
+            if let Some(v) = patch.auto_archive_days {
+                model.settings.auto_archive_days = v;
+            }
+            if let Some(v) = patch.remote_detach_action {
+                model.settings.remote_detach_action = v;
+            }
+            if let Some(v) = patch.language {
+                model.settings.language = v;
+            }
+            if let Some(v) = patch.work_day_start_min {
+                model.settings.work_day_start_min = v;
+            }
+            if let Some(v) = patch.work_day_end_min {
+                model.settings.work_day_end_min = v;
+            }
+            if let Some(v) = patch.gap_min_minutes {
+                model.settings.gap_min_minutes = v;
+            }
+            if let Some(v) = patch.skip_days_off {
+                model.settings.skip_days_off = v;
+            }
+            if let Some(v) = patch.streak_min_pomodoros {
+                model.settings.streak_min_pomodoros = v;
+            }
+            if let Some(v) = patch.streak_skip_weekends {
+                model.settings.streak_skip_weekends = v;
+            }
+            if let Some(v) = patch.streak_freeze_tokens {
+                model.settings.streak_freeze_tokens = v;
+            }
+            if let Some(v) = patch.session_webhook_url {
+                model.settings.session_webhook_url = v;
+            }
+            if let Some(v) = patch.session_webhook_secret {
+                model.settings.session_webhook_secret = v;
+            }
+            if let Some(v) = patch.breathing_pattern {
+                model.settings.breathing_pattern = v;
+            }
+            if let Some(v) = patch.breathing_custom {
+                model.settings.breathing_custom = v;
+            }
+            if let Some(v) = patch.music_enabled {
+                model.settings.music_enabled = v;
+            }
+            if let Some(v) = patch.music_shuffle {
+                model.settings.music_shuffle = v;
+            }
+            if let Some(v) = patch.break_dim_enabled {
+                model.settings.break_dim_enabled = v;
+            }
+            if let Some(v) = patch.break_dim_percent {
+                model.settings.break_dim_percent = v;
+            }
+            if let Some(v) = patch.analytics_excluded_project_ids {
+                model.settings.analytics_excluded_project_ids = v;
+            }
+            if let Some(v) = patch.analytics_excluded_tag_ids {
+                model.settings.analytics_excluded_tag_ids = v;
+            }
+            if let Some(v) = patch.daily_goal_pomodoros {
+                model.settings.daily_goal_pomodoros = v;
+            }
+            if let Some(v) = patch.weekly_goal_minutes {
+                model.settings.weekly_goal_minutes = v;
+            }
+            if let Some(v) = patch.goal_notification {
+                model.settings.goal_notification = v;
+            }
+            if let Some(v) = patch.tray_title_seconds {
+                model.settings.tray_title_seconds = v;
+            }
+            if let Some(v) = patch.backup_enabled {
+                model.settings.backup_enabled = v;
+            }
+            if let Some(v) = patch.backup_directory {
+                model.settings.backup_directory = v.trim().to_string();
+            }
+            if let Some(v) = patch.backup_interval_hours {
+                model.settings.backup_interval_hours = v;
+            }
+            if let Some(v) = patch.backup_keep {
+                model.settings.backup_keep = v;
+            }
+            if let Some(v) = patch.backup_format {
+                model.settings.backup_format = v;
+            }
+            if let Some(v) = patch.remote_mdns_enabled {
+                model.settings.remote_mdns_enabled = v;
+            }
+            if let Some(v) = patch.remote_pairing_required {
+                model.settings.remote_pairing_required = v;
+            }
+            if let Some(v) = patch.remote_read_only {
+                model.settings.remote_read_only = v;
+            }
+
+            model.settings = normalize_settings(model.settings.clone());
+            ensure_team_stats_key(&mut model.settings);
+            save_json_setting(&model.conn, APP_SETTINGS_KEY, &model.settings)?;
+
+            let settings = model.settings.clone();
+            sync_pause_budget(&mut model.timer, &settings);
+
+            // Keep the current phase duration in sync if timer is idle.
+            if !model.timer.is_running {
+                model.timer.phase_total_seconds = model
+                    .settings
+                    .duration_for_phase_seconds(&model.timer.phase);
+                model.timer.remaining_seconds = model.timer.phase_total_seconds;
+                model.timer.started_at = None;
+                model.timer.target_ends_at = None;
+                model.timer.break_extension_seconds = 0;
+            }
+
+            Ok(model.settings.clone())
+        },
+    )
+}
+
+#[tauri::command]
+fn reset_all_data(app: AppHandle) -> AppResult<ResetAllResult> {
+    mutate(&app, "reset_all_data", &DataDomain::ALL, |model, _| {
+        {
+            let tx = model.conn.savepoint().map_err(|e| e.to_string())?;
+            tx.execute("DELETE FROM events", [])
+                .map_err(|e| e.to_string())?;
+            tx.execute("DELETE FROM session_project_splits", [])
+                .map_err(|e| e.to_string())?;
+            tx.execute("DELETE FROM session_tags", [])
+                .map_err(|e| e.to_string())?;
+            tx.execute("DELETE FROM sessions", [])
+                .map_err(|e| e.to_string())?;
+            tx.execute("DELETE FROM projects", [])
+                .map_err(|e| e.to_string())?;
+            tx.execute("DELETE FROM tags", [])
+                .map_err(|e| e.to_string())?;
+            tx.execute("DELETE FROM settings", [])
+                .map_err(|e| e.to_string())?;
+            tx.execute("DELETE FROM days_off", [])
+                .map_err(|e| e.to_string())?;
+            tx.execute("DELETE FROM streak_freezes", [])
+                .map_err(|e| e.to_string())?;
+            tx.execute("DELETE FROM plan_items", [])
+                .map_err(|e| e.to_string())?;
+            // Like the settings, remote access starts over with a fresh Default token.
+            tx.execute("DELETE FROM remote_tokens", [])
+                .map_err(|e| e.to_string())?;
+            tx.execute("DELETE FROM remote_devices", [])
+                .map_err(|e| e.to_string())?;
+            tx.execute("DELETE FROM remote_audit", [])
+                .map_err(|e| e.to_string())?;
+            tx.execute("DELETE FROM push_subscriptions", [])
+                .map_err(|e| e.to_string())?;
+            migrate_remote_token(&tx, None, now_ts())?;
+            tx.execute(
+                "DELETE FROM sqlite_sequence WHERE name IN ('projects', 'tags', 'sessions', 'events', 'session_revisions')",
+                [],
+            )
+            .map_err(|e| e.to_string())?;
+            tx.commit().map_err(|e| e.to_string())?;
+        }
+
+        model.settings = normalize_settings(AppSettings::default());
+        ensure_team_stats_key(&mut model.settings);
+        model.timer = TimerState::default_with_settings(&model.settings);
+        save_json_setting(&model.conn, APP_SETTINGS_KEY, &model.settings)?;
+
+        Ok(ResetAllResult {
+            settings: model.settings.clone(),
+            timer: model.timer.clone(),
+        })
+    })
+}
+
+// Picks up edits made to the SQLite file by other tools: settings and the timer are re-read
+// from the database instead of being kept from memory, and integrity is re-checked.
+fn reload_model(model: &mut AppModel) -> AppResult<DbCheckReport> {
+    let settings = load_or_create_settings(&model.conn)?;
+    model.timer = load_or_create_timer(&model.conn, &settings)?;
+    model.settings = settings;
+    model.queued_notification = None;
+    check_database(&model.conn)
+}
+
+#[tauri::command]
+fn db_reconcile(app: AppHandle, state: State<'_, AppState>) -> AppResult<ReconcileResult> {
+    let (settings, timer, integrity) = {
+        let mut model = lock_model(&state)?;
+        let integrity = reload_model(&mut model)?;
+        (model.settings.clone(), model.timer.clone(), integrity)
+    };
+
+    apply_settings_outside_model(&app, &settings)?;
+    emit_timer_state(&app, &timer);
+    Ok(ReconcileResult {
+        settings,
+        timer,
+        integrity,
+    })
+}
+
+// A file database_import accepts must have these; init_database adds anything newer.
+const DATABASE_IMPORT_TABLES: &[&str] = &["settings", "projects", "tags", "sessions"];
+
+// VACUUM INTO writes a consistent copy without blocking other readers, and fails on an
+// existing target, so it goes to a sibling first and replaces `target` only once complete.
+fn vacuum_into(conn: &Connection, target: &std::path::Path) -> AppResult<u64> {
+    let partial = target.with_extension("partial");
+    let _ = fs::remove_file(&partial);
+    conn.execute("VACUUM INTO ?1", params![partial.to_string_lossy()])
+        .map_err(|e| e.to_string())?;
+    fs::rename(&partial, target).map_err(|e| e.to_string())?;
+    Ok(fs::metadata(target).map(|meta| meta.len()).unwrap_or(0))
+}
+
+// Settings rows holding credentials: the WebDAV password and the cloud backup passphrase.
+const PRIVATE_SETTING_KEYS: &[&str] = &[CLOUD_BACKUP_KEY];
+
+// vacuum_into without PRIVATE_SETTING_KEYS, for copies that leave the app: database exports
+// and sqlite backups go to folders the user picks, often synced ones. The rows are dropped in
+// a sibling before `target` is replaced, and the VACUUM leaves no trace of them in free pages.
+fn export_database_file(conn: &Connection, target: &std::path::Path) -> AppResult<u64> {
+    let staged = target.with_extension("staged");
+    let stripped = vacuum_into(conn, &staged).and_then(|_| {
+        let copy = Connection::open(&staged).map_err(|e| e.to_string())?;
+        let marks = vec!["?"; PRIVATE_SETTING_KEYS.len()].join(", ");
+        copy.execute(
+            &format!("DELETE FROM settings WHERE key IN ({marks})"),
+            rusqlite::params_from_iter(PRIVATE_SETTING_KEYS),
+        )
+        .map_err(|e| e.to_string())?;
+        copy.execute_batch("VACUUM").map_err(|e| e.to_string())
+    });
+    if let Err(error) =
+        stripped.and_then(|_| fs::rename(&staged, target).map_err(|e| e.to_string()))
+    {
+        let _ = fs::remove_file(&staged);
+        return Err(error);
+    }
+    Ok(fs::metadata(target).map(|meta| meta.len()).unwrap_or(0))
+}
+
+// Checks `source` read-only (integrity and the core tables) and copies it to `staging`,
+// which folds in any WAL content and leaves the user's file untouched by migrations.
+fn stage_database_import(source: &std::path::Path, staging: &std::path::Path) -> AppResult<()> {
+    let conn = Connection::open_with_flags(source, rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|e| format!("cannot open {}: {e}", source.display()))?;
+    let check: String = conn
+        .query_row("PRAGMA integrity_check", [], |row| row.get(0))
+        .map_err(|e| format!("not a SQLite database: {e}"))?;
+    if check != "ok" {
+        return Err(format!("database integrity check failed: {check}"));
+    }
+    let mut missing = Vec::new();
+    for table in DATABASE_IMPORT_TABLES {
+        let found: bool = conn
+            .query_row(
+                "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1)",
+                [table],
+                |row| row.get(0),
+            )
+            .map_err(|e| e.to_string())?;
+        if !found {
+            missing.push(*table);
+        }
+    }
+    if !missing.is_empty() {
+        return Err(format!(
+            "not a Pomodoro Pulse database (missing {})",
+            missing.join(", ")
+        ));
+    }
+    vacuum_into(&conn, staging).map(|_| ())
+}
+
+// Closes `conn`, sets its file aside as `*.before-import-<now>.db`, moves `staging` into its
+// place and reopens it. If the new file does not open, the old one is put back, or reopened
+// where it was set aside if it cannot be. Returns the path the old file was kept at.
+fn swap_database_file(
+    conn: &mut Connection,
+    staging: &std::path::Path,
+    now: i64,
+) -> AppResult<String> {
+    let db_path = conn
+        .path()
+        .filter(|path| !path.is_empty())
+        .map(std::path::PathBuf::from)
+        .ok_or_else(|| "the database is not backed by a file".to_string())?;
+    let backup = db_path.with_extension(format!("before-import-{now}.db"));
+
+    // Closing the last connection checkpoints the WAL, so the file set aside is complete. The
+    // placeholder is read-only: if no file can be reopened below, writes fail instead of
+    // landing in memory and vanishing on quit.
+    let placeholder =
+        Connection::open_in_memory_with_flags(rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY)
+            .map_err(|e| e.to_string())?;
+    drop(std::mem::replace(conn, placeholder));
+    if let Err(error) = fs::rename(&db_path, &backup) {
+        *conn = open_database(&db_path)?.0;
+        return Err(error.to_string());
+    }
+    for suffix in ["-wal", "-shm"] {
+        let _ = fs::remove_file(format!("{}{suffix}", db_path.display()));
+    }
+
+    let reopened = fs::rename(staging, &db_path)
+        .map_err(|e| e.to_string())
+        .and_then(|_| open_database(&db_path).map_err(String::from))
+        .and_then(|(new_conn, blocked)| match blocked {
+            None => Ok(new_conn),
+            Some(reason) => Err(reason),
+        });
+    match reopened {
+        Ok(new_conn) => {
+            *conn = new_conn;
+            Ok(backup.to_string_lossy().into_owned())
+        }
+        Err(error) => {
+            let _ = fs::remove_file(&db_path);
+            // When the old file cannot move back, the app keeps running on it where it is.
+            let restored = match fs::rename(&backup, &db_path) {
+                Ok(()) => db_path,
+                Err(_) => backup,
+            };
+            *conn = open_database(&restored)
+                .map_err(|reopen| {
+                    format!(
+                        "imported database could not be opened ({error}), and neither could the \
+                     previous one at {} ({}); restart the app",
+                        restored.display(),
+                        String::from(reopen)
+                    )
+                })?
+                .0;
+            Err(format!("imported database could not be opened: {error}"))
+        }
+    }
+}
+
+// Resolves to None when the save dialog is cancelled.
+#[tauri::command]
+async fn database_export(app: AppHandle) -> AppResult<Option<DatabaseExportResult>> {
+    let filename = format!("pomodoro-{}.db", now_ts());
+    let Some(path) = pick_save_path(&app, &filename, "SQLite database", "db")? else {
+        return Ok(None);
+    };
+    let state = app.state::<AppState>();
+    let model = lock_model(&state)?;
+    let size_bytes = export_database_file(&model.conn, &path)?;
+    Ok(Some(DatabaseExportResult {
+        path: path.to_string_lossy().to_string(),
+        size_bytes,
+    }))
+}
+
+// Replaces all local data with another Pomodoro Pulse database file and reloads settings and
+// the timer from it. Resolves to None when the file picker is cancelled.
+#[tauri::command]
+async fn database_import(app: AppHandle) -> AppResult<Option<DatabaseImportResult>> {
+    let Some(picked) = app
+        .dialog()
+        .file()
+        .add_filter("SQLite database", &["db", "sqlite", "sqlite3"])
+        .blocking_pick_file()
+    else {
+        return Ok(None);
+    };
+    let source = picked.into_path().map_err(|e| e.to_string())?;
+    let app_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    let staging = app_dir.join("pomodoro.importing.db");
+    stage_database_import(&source, &staging)?;
+
+    let result = mutate(&app, "database_import", &DataDomain::ALL, |model, _| {
+        if model.read_only.is_some() {
+            return Err("cannot import while the database is read-only".to_string());
+        }
+        let backup_path = swap_database_file(&mut model.conn, &staging, now_ts())?;
+        let integrity = reload_model(model)?;
+        Ok(DatabaseImportResult {
+            settings: model.settings.clone(),
+            timer: model.timer.clone(),
+            backup_path,
+            tables: db_stats(&model.conn)?.tables,
+            integrity,
+        })
+    });
+    let _ = fs::remove_file(&staging);
+    result.map(Some)
+}
+
+#[tauri::command]
+fn session_history(
+    range: AnalyticsRange,
+    state: State<'_, AppState>,
+) -> AppResult<Vec<SessionRecord>> {
+    let model = lock_model(&state)?;
+    fetch_sessions(&model.conn, &range)
+}
+
+#[tauri::command]
+fn session_set_notes(
+    app: AppHandle,
+    session_id: i64,
+    notes: Option<String>,
+) -> AppResult<SessionRecord> {
+    mutate(
+        &app,
+        "session_set_notes",
+        &[DataDomain::Sessions],
+        |model, _| {
+            let notes = notes
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty());
+            {
+                let tx = model.conn.savepoint().map_err(|e| e.to_string())?;
+                record_session_revision(&tx, &fetch_session(&tx, session_id)?, "notes", now_ts())?;
+                tx.execute(
+                    "UPDATE sessions SET notes = ?1 WHERE id = ?2",
+                    params![notes, session_id],
+                )
+                .map_err(|e| e.to_string())?;
+                tx.commit().map_err(|e| e.to_string())?;
+            }
+
+            fetch_session(&model.conn, session_id)
+        },
+    )
+}
+
+#[tauri::command]
+fn sessions_reassign_project(
+    app: AppHandle,
+    filter: AnalyticsRange,
+    project_id: Option<i64>,
+) -> AppResult<BulkUpdateResult> {
+    mutate(
+        &app,
+        "sessions_reassign_project",
+        &[DataDomain::Sessions],
+        |model, _| {
+            let updated = reassign_sessions_project(&mut model.conn, &filter, project_id)?;
+            Ok(BulkUpdateResult { updated })
+        },
+    )
+}
+
+#[tauri::command]
+fn sessions_bulk_tag(
+    app: AppHandle,
+    filter: BulkTagFilter,
+    add_tag_ids: Vec<i64>,
+    remove_tag_ids: Vec<i64>,
+) -> AppResult<BulkUpdateResult> {
+    mutate(
+        &app,
+        "sessions_bulk_tag",
+        &[DataDomain::Sessions],
+        |model, _| {
+            let updated =
+                bulk_tag_sessions(&mut model.conn, &filter, &add_tag_ids, &remove_tag_ids)?;
+            Ok(BulkUpdateResult { updated })
+        },
+    )
+}
+
+#[tauri::command]
+fn sessions_delete(app: AppHandle, session_ids: Vec<i64>) -> AppResult<BulkUpdateResult> {
+    mutate(
+        &app,
+        "sessions_delete",
+        &[DataDomain::Sessions],
+        |model, _| {
+            let updated = set_trashed(&model.conn, "sessions", &session_ids, Some(now_ts()))?;
+            Ok(BulkUpdateResult { updated })
+        },
+    )
+}
+
+#[tauri::command]
+fn sessions_restore(app: AppHandle, session_ids: Vec<i64>) -> AppResult<BulkUpdateResult> {
+    mutate(
+        &app,
+        "sessions_restore",
+        &[DataDomain::Sessions],
+        |model, _| {
+            let updated = set_trashed(&model.conn, "sessions", &session_ids, None)?;
+            Ok(BulkUpdateResult { updated })
+        },
+    )
+}
+
+#[tauri::command]
+fn projects_delete(
+    app: AppHandle,
+    project_id: i64,
+    force: Option<bool>,
+) -> AppResult<DeleteResult> {
+    mutate(
+        &app,
+        "projects_delete",
+        &[DataDomain::Projects, DataDomain::Sessions],
+        |model, mutation| {
+            let result = delete_project(&mut model.conn, project_id, force.unwrap_or(false))?;
+            if model.timer.current_project_id == Some(project_id) {
+                model.timer.current_project_id = None;
+                mutation.touch(DataDomain::Timer);
+            }
+            Ok(result)
+        },
+    )
+}
+
+#[tauri::command]
+fn projects_merge(
+    app: AppHandle,
+    source_id: i64,
+    target_id: i64,
+    delete_source: Option<bool>,
+) -> AppResult<MergeResult> {
+    mutate(
+        &app,
+        "projects_merge",
+        &[DataDomain::Projects, DataDomain::Sessions],
+        |model, mutation| {
+            let result = merge_projects(
+                &mut model.conn,
+                source_id,
+                target_id,
+                delete_source.unwrap_or(false),
+            )?;
+            if model.timer.current_project_id == Some(source_id) {
+                model.timer.current_project_id = Some(target_id);
+                mutation.touch(DataDomain::Timer);
+            }
+            Ok(result)
+        },
+    )
+}
+
+#[tauri::command]
+fn projects_reorder(app: AppHandle, project_ids: Vec<i64>) -> AppResult<Vec<Project>> {
+    mutate(
+        &app,
+        "projects_reorder",
+        &[DataDomain::Projects],
+        |model, _| {
+            reorder_projects(&mut model.conn, &project_ids)?;
+            fetch_projects(&model.conn)
+        },
+    )
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct AutoArchiveResult {
+    archived: Vec<Project>,
+}
+
+// Emits projects://auto-archived when anything changed, so the UI can offer an undo
+// (projects_unarchive with the listed ids).
+fn run_auto_archive(app: &AppHandle, days: Option<i64>) -> AppResult<AutoArchiveResult> {
+    let archived = mutate(
+        app,
+        "projects_auto_archive",
+        &[DataDomain::Projects],
+        |model, _| {
+            let days = days.unwrap_or(model.settings.auto_archive_days);
+            let keep_id = model.timer.current_project_id;
+            auto_archive_projects(&mut model.conn, days, keep_id, now_ts())
+        },
+    )?;
+    let result = AutoArchiveResult { archived };
+    if !result.archived.is_empty() {
+        let _ = app.emit("projects://auto-archived", &result);
+    }
+    Ok(result)
+}
+
+// `days` overrides the auto_archive_days setting for a one-off run.
+#[tauri::command]
+fn projects_auto_archive(app: AppHandle, days: Option<i64>) -> AppResult<AutoArchiveResult> {
+    run_auto_archive(&app, days)
+}
+
+#[tauri::command]
+fn projects_unarchive(app: AppHandle, project_ids: Vec<i64>) -> AppResult<BulkUpdateResult> {
+    mutate(
+        &app,
+        "projects_unarchive",
+        &[DataDomain::Projects],
+        |model, _| {
+            let mut updated = 0;
+            for id in project_ids {
+                updated += model
+                    .conn
+                    .execute(
+                        "UPDATE projects SET archived = 0 WHERE id = ?1 AND archived = 1",
+                        params![id],
+                    )
+                    .map_err(|e| e.to_string())? as i64;
+            }
+            Ok(BulkUpdateResult { updated })
+        },
+    )
+}
+
+#[tauri::command]
+fn projects_restore(app: AppHandle, project_id: i64) -> AppResult<BulkUpdateResult> {
+    mutate(
+        &app,
+        "projects_restore",
+        &[DataDomain::Projects],
+        |model, _| {
+            let updated = set_trashed(&model.conn, "projects", &[project_id], None)?;
+            Ok(BulkUpdateResult { updated })
+        },
+    )
+}
+
+#[tauri::command]
+fn tags_delete(app: AppHandle, tag_id: i64, force: Option<bool>) -> AppResult<DeleteResult> {
+    mutate(
+        &app,
+        "tags_delete",
+        &[DataDomain::Tags, DataDomain::Sessions],
+        |model, mutation| {
+            let result = delete_tag(&mut model.conn, tag_id, force.unwrap_or(false))?;
+            if model.timer.current_tag_ids.contains(&tag_id) {
+                model.timer.current_tag_ids.retain(|id| *id != tag_id);
+                mutation.touch(DataDomain::Timer);
+            }
+            Ok(result)
+        },
+    )
+}
+
+#[tauri::command]
+fn tags_merge(app: AppHandle, source_ids: Vec<i64>, target_id: i64) -> AppResult<MergeResult> {
+    mutate(
+        &app,
+        "tags_merge",
+        &[DataDomain::Tags, DataDomain::Sessions],
+        |model, mutation| {
+            let result = merge_tags(&mut model.conn, &source_ids, target_id)?;
+            let tag_ids = &mut model.timer.current_tag_ids;
+            if tag_ids.iter().any(|id| source_ids.contains(id)) {
+                tag_ids.retain(|id| !source_ids.contains(id) && *id != target_id);
+                tag_ids.push(target_id);
+                mutation.touch(DataDomain::Timer);
+            }
+            Ok(result)
+        },
+    )
+}
+
+#[tauri::command]
+fn tags_restore(app: AppHandle, tag_id: i64) -> AppResult<BulkUpdateResult> {
+    mutate(&app, "tags_restore", &[DataDomain::Tags], |model, _| {
+        let updated = set_trashed(&model.conn, "tags", &[tag_id], None)?;
+        Ok(BulkUpdateResult { updated })
+    })
+}
+
+#[tauri::command]
+fn trash_list(state: State<'_, AppState>) -> AppResult<TrashList> {
+    let model = lock_model(&state)?;
+    fetch_trash(&model.conn)
+}
+
+#[tauri::command]
+fn session_set_project_splits(
+    app: AppHandle,
+    session_id: i64,
+    splits: Vec<ProjectSplit>,
+) -> AppResult<SessionRecord> {
+    mutate(
+        &app,
+        "session_set_project_splits",
+        &[DataDomain::Sessions],
+        |model, _| {
+            write_session_splits(&mut model.conn, session_id, &splits)?;
+
+            fetch_session(&model.conn, session_id)
+        },
+    )
+}
+
+#[tauri::command]
+fn sessions_merge(app: AppHandle, first_id: i64, second_id: i64) -> AppResult<SessionRecord> {
+    mutate(
+        &app,
+        "sessions_merge",
+        &[DataDomain::Sessions],
+        |model, _| {
+            let merged_id = merge_sessions(&mut model.conn, first_id, second_id)?;
+            fetch_session(&model.conn, merged_id)
+        },
+    )
+}
+
+#[tauri::command]
+fn sessions_split(app: AppHandle, session_id: i64, at: i64) -> AppResult<Vec<SessionRecord>> {
+    mutate(
+        &app,
+        "sessions_split",
+        &[DataDomain::Sessions],
+        |model, _| {
+            let (first_id, second_id) = split_session(&mut model.conn, session_id, at)?;
+            Ok(vec![
+                fetch_session(&model.conn, first_id)?,
+                fetch_session(&model.conn, second_id)?,
+            ])
+        },
+    )
+}
+
+#[tauri::command]
+fn session_update(
+    app: AppHandle,
+    session_id: i64,
+    update: SessionUpdate,
+) -> AppResult<SessionRecord> {
+    mutate(
+        &app,
+        "session_update",
+        &[DataDomain::Sessions],
+        |model, _| {
+            update_session(&mut model.conn, session_id, &update)?;
+            fetch_session(&model.conn, session_id)
+        },
+    )
+}
+
+#[tauri::command]
+fn session_revisions_list(
+    session_id: i64,
+    state: State<'_, AppState>,
+) -> AppResult<Vec<SessionRevision>> {
+    let model = lock_model(&state)?;
+    fetch_session_revisions(&model.conn, session_id)
+}
+
+#[tauri::command]
+fn session_revision_revert(
+    app: AppHandle,
+    revision_id: i64,
+    allow_overlap: Option<bool>,
+) -> AppResult<SessionRecord> {
+    mutate(
+        &app,
+        "session_revision_revert",
+        &[DataDomain::Sessions],
+        |model, _| {
+            let session_id = revert_session_revision(
+                &mut model.conn,
+                revision_id,
+                allow_overlap.unwrap_or(false),
+            )?;
+            fetch_session(&model.conn, session_id)
+        },
+    )
+}
+
+#[tauri::command]
+fn session_rate(app: AppHandle, session_id: i64, quality: Option<i64>) -> AppResult<SessionRecord> {
+    mutate(&app, "session_rate", &[DataDomain::Sessions], |model, _| {
+        rate_session(&mut model.conn, session_id, quality)?;
+        fetch_session(&model.conn, session_id)
+    })
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DndStatus {
+    active: bool,
+    queued: bool,
+}
+
+#[tauri::command]
+fn dnd_status(state: State<'_, AppState>) -> AppResult<DndStatus> {
+    let model = lock_model(&state)?;
+    Ok(DndStatus {
+        active: dnd_active(),
+        queued: model.queued_notification.is_some(),
+    })
+}
+
+#[tauri::command]
+fn timer_events_list(
+    query: TimerEventQuery,
+    state: State<'_, AppState>,
+) -> AppResult<Vec<TimerEvent>> {
+    let model = lock_model(&state)?;
+    fetch_timer_events(&model.conn, &query)
+}
+
+#[tauri::command]
+fn sessions_search(
+    query: SessionSearchQuery,
+    state: State<'_, AppState>,
+) -> AppResult<Vec<SessionRecord>> {
+    let model = lock_model(&state)?;
+    search_sessions(&model.conn, &query)
+}
+
+#[tauri::command]
+fn session_history_page(query: SessionQuery, state: State<'_, AppState>) -> AppResult<SessionPage> {
+    let model = lock_model(&state)?;
+    fetch_sessions_page(&model.conn, &query)
+}
+
+// Public hosts used only to pick a route: connecting a UDP socket sends nothing, and its local
+// address is the one this machine would reach the network from.
+const LOCAL_IP_PROBES: [(&str, &str); 2] = [
+    ("0.0.0.0:0", "8.8.8.8:80"),
+    ("[::]:0", "[2001:4860:4860::8888]:80"),
+];
+
+fn local_ip_candidates() -> Vec<IpAddr> {
+    LOCAL_IP_PROBES
+        .iter()
+        .filter_map(|(bind, target)| {
+            let socket = UdpSocket::bind(bind).ok()?;
+            socket.connect(target).ok()?;
+            Some(socket.local_addr().ok()?.ip())
+        })
+        .collect()
+}
+
+// IPv4 first, then IPv6; on an IPv6-only network only the IPv6 address comes back.
+#[tauri::command]
+fn get_local_ip() -> Result<Vec<String>, String> {
+    let ips = local_ip_candidates();
+    if ips.is_empty() {
+        return Err("no network route to find a local address".to_string());
+    }
+    Ok(ips.iter().map(IpAddr::to_string).collect())
+}
+
+// IPv6 literals need brackets in a URL.
+fn url_host(host: &str) -> String {
+    if host.contains(':') {
+        format!("[{host}]")
+    } else {
+        host.to_string()
+    }
+}
+
+// The Mac App Store build of Tailscale ships its CLI inside the app bundle, off PATH.
+const TAILSCALE_CLI_CANDIDATES: [&str; 2] = [
+    "tailscale",
+    "/Applications/Tailscale.app/Contents/MacOS/Tailscale",
+];
+
+// Hostname and first tailnet IP of this machine, when Tailscale is up. The DNS name comes
+// back fully qualified with a trailing dot.
+fn parse_tailscale_status(json: &serde_json::Value) -> Option<TailscaleHost> {
+    if json.get("BackendState")?.as_str()? != "Running" {
+        return None;
+    }
+    let own = json.get("Self")?;
+    let dns_name = own
+        .get("DNSName")
+        .and_then(|name| name.as_str())
+        .map(|name| name.trim_end_matches('.').to_string())
+        .filter(|name| !name.is_empty());
+    let ip = own
+        .get("TailscaleIPs")
+        .and_then(|ips| ips.as_array())
+        .and_then(|ips| {
+            ips.iter()
+                .find_map(|ip| ip.as_str().filter(|ip| !ip.contains(':')))
+        })
+        .map(str::to_string);
+    if dns_name.is_none() && ip.is_none() {
+        return None;
+    }
+    Some(TailscaleHost { dns_name, ip })
+}
+
+fn tailscale_host() -> Option<TailscaleHost> {
+    TAILSCALE_CLI_CANDIDATES.iter().find_map(|cli| {
+        let output = probe_output(cli, &["status", "--json"])?;
+        parse_tailscale_status(&serde_json::from_str(&output).ok()?)
+    })
+}
+
+// Tailnet traffic is end-to-end encrypted by WireGuard, so the plain-HTTP remote page is safe
+// to open off-LAN through the MagicDNS name without forwarding any port.
+fn remote_discovery(
+    settings: &AppSettings,
+    lan_ips: Vec<String>,
+    tailscale: Option<TailscaleHost>,
+) -> RemoteDiscoveryInfo {
+    let url = |host: &str| {
+        format!(
+            "http://{}:{}/",
+            url_host(host),
+            settings.remote_control_port
+        )
+    };
+    let lan_urls: Vec<String> = lan_ips.iter().map(|ip| url(ip)).collect();
+    let lan_ip = lan_ips.into_iter().next();
+    let lan_url = lan_urls.first().cloned();
+    let tailscale_url = tailscale
+        .as_ref()
+        .and_then(|host| host.dns_name.as_deref().or(host.ip.as_deref()))
+        .map(url);
+    RemoteDiscoveryInfo {
+        enabled: settings.remote_control_enabled,
+        port: settings.remote_control_port,
+        lan_ip,
+        lan_url,
+        lan_urls,
+        tailscale,
+        tailscale_url,
+    }
+}
+
+#[tauri::command]
+fn remote_status(state: State<'_, AppState>) -> AppResult<RemoteStatus> {
+    let remote = state.remote.lock().map_err(|e| e.to_string())?;
+    Ok(remote.status.clone())
+}
+
+#[tauri::command]
+fn remote_discovery_info(state: State<'_, AppState>) -> AppResult<RemoteDiscoveryInfo> {
+    let mut settings = lock_model(&state)?.settings.clone();
+    // URLs point at the port actually listening, which a fallback may have moved.
+    if let Some(port) = state.remote.lock().map_err(|e| e.to_string())?.status.port {
+        settings.remote_control_port = i64::from(port);
+    }
+    Ok(remote_discovery(
+        &settings,
+        get_local_ip().unwrap_or_default(),
+        tailscale_host(),
+    ))
+}
+
+#[tauri::command]
+fn remote_tokens_list(state: State<'_, AppState>) -> AppResult<Vec<RemoteToken>> {
+    let model = lock_model(&state)?;
+    fetch_remote_tokens(&model.conn)
+}
+
+#[tauri::command]
+fn remote_tokens_create(
+    app: AppHandle,
+    name: String,
+    scopes: Vec<RemoteScope>,
+) -> AppResult<RemoteToken> {
+    mutate(
+        &app,
+        "remote_tokens_create",
+        &[DataDomain::Remote],
+        |model, _| {
+            create_remote_token(
+                &model.conn,
+                &name,
+                generate_remote_token(),
+                &scopes,
+                now_ts(),
+            )
+        },
+    )
+}
+
+#[tauri::command]
+fn remote_security_log(state: State<'_, AppState>) -> AppResult<RemoteSecurityLog> {
+    let guard = state.remote_guard.lock().map_err(|e| e.to_string())?;
+    Ok(guard.snapshot(now_ts()))
+}
+
+// Open WebSocket and SSE streams on the token close within a second. Devices paired with
+// the token and their push subscriptions go too.
+#[tauri::command]
+fn remote_tokens_revoke(app: AppHandle, id: i64) -> AppResult<bool> {
+    mutate(
+        &app,
+        "remote_tokens_revoke",
+        &[DataDomain::Remote],
+        |model, _| {
+            let removed = model
+                .conn
+                .execute("DELETE FROM remote_tokens WHERE id = ?1", params![id])
+                .map_err(|e| e.to_string())?;
+            for table in ["remote_devices", "push_subscriptions"] {
+                model
+                    .conn
+                    .execute(
+                        &format!("DELETE FROM {table} WHERE token_id = ?1"),
+                        params![id],
+                    )
+                    .map_err(|e| e.to_string())?;
+            }
+            Ok(removed > 0)
+        },
+    )
+}
+
+#[tauri::command]
+fn remote_audit_list(
+    limit: Option<i64>,
+    state: State<'_, AppState>,
+) -> AppResult<Vec<RemoteAuditEntry>> {
+    let model = lock_model(&state)?;
+    fetch_remote_audit(&model.conn, limit.unwrap_or(100).clamp(1, REMOTE_AUDIT_MAX))
+}
+
+#[tauri::command]
+fn remote_devices_list(state: State<'_, AppState>) -> AppResult<Vec<RemoteDevice>> {
+    let model = lock_model(&state)?;
+    fetch_remote_devices(&model.conn)
+}
+
+#[tauri::command]
+fn remote_devices_decide(app: AppHandle, id: i64, approved: bool) -> AppResult<bool> {
+    mutate(
+        &app,
+        "remote_devices_decide",
+        &[DataDomain::Remote],
+        |model, _| decide_remote_device(&model.conn, id, approved, now_ts()),
+    )
+}
+
+// A removed device is asked about again the next time it connects.
+#[tauri::command]
+fn remote_devices_remove(app: AppHandle, id: i64) -> AppResult<bool> {
+    mutate(
+        &app,
+        "remote_devices_remove",
+        &[DataDomain::Remote],
+        |model, _| {
+            let removed = model
+                .conn
+                .execute("DELETE FROM remote_devices WHERE id = ?1", params![id])
+                .map_err(|e| e.to_string())?;
+            Ok(removed > 0)
+        },
+    )
+}
+
+// Asks on the desktop whether a new device may use the remote. Closing the dialog rejects it;
+// Settings -> Remote Devices can change the answer later. One dialog per token at a time: the
+// next pending device of the token is asked about once this one is answered.
+fn prompt_remote_pairing(app: &AppHandle, device: RemoteDevice) {
+    let _ = app.emit("remote://pairing-request", &device);
+    let state = app.state::<AppState>();
+    match state.remote_prompts.lock() {
+        Ok(mut open) if open.insert(device.token_id) => {}
+        _ => return,
+    }
+    let message = format!(
+        "{} at {} is using the token \"{}\". Allow it to use the remote?",
+        device.label,
+        device.ip,
+        device.token_name.as_deref().unwrap_or("unknown")
+    );
+    let handle = app.clone();
+    app.dialog()
+        .message(message)
+        .title("New remote device")
+        .buttons(tauri_plugin_dialog::MessageDialogButtons::OkCancelCustom(
+            "Allow".to_string(),
+            "Reject".to_string(),
+        ))
+        .show(move |approved| {
+            let state = handle.state::<AppState>();
+            let decided = state
+                .model
+                .lock()
+                .map_err(|e| e.to_string())
+                .and_then(|model| decide_remote_device(&model.conn, device.id, approved, now_ts()));
+            if decided.is_ok() {
+                let _ = handle.emit("remote://pairing-decided", device.id);
+            }
+            if let Ok(mut open) = state.remote_prompts.lock() {
+                open.remove(&device.token_id);
+            }
+            let next = state
+                .model
+                .lock()
+                .map_err(|e| e.to_string())
+                .and_then(|model| next_pending_device(&model.conn, device.token_id));
+            if let Ok(Some(next)) = next {
+                prompt_remote_pairing(&handle, next);
+            }
+        });
+}
+
+#[cfg_attr(mobile, tauri::mobile_entry_point)]
+pub fn run() {
+    tauri::Builder::default()
+        .plugin(tauri_plugin_opener::init())
+        .plugin(tauri_plugin_dialog::init())
+        .plugin(tauri_plugin_fs::init())
+        .plugin(tauri_plugin_notification::init())
+        .setup(|app| {
+            let app_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+            fs::create_dir_all(&app_dir).map_err(|e| e.to_string())?;
+            let db_path = app_dir.join("pomodoro.db");
+            let (conn, read_only, db_recovery) = match open_database(&db_path) {
+                Ok((conn, blocked)) => (
+                    conn,
+                    blocked.map(|reason| read_only_mode(reason, now_ts())),
+                    None,
+                ),
+                Err(DbOpenError::Corrupt(error)) => {
+                    eprintln!("database failed to open, recovering: {error}");
+                    let (conn, report) = recover_database(&db_path, error, now_ts())?;
+                    (conn, None, Some(report))
+                }
+                Err(DbOpenError::Failed(error)) => {
+                    return Err(format!("database failed to open: {error}").into());
+                }
+            };
+
+            let settings = load_or_create_settings(&conn)?;
+            let timer = load_or_create_timer(&conn, &settings)?;
+            let screen_dim = load_screen_dim(&conn);
+            let tray = TrayTitleState {
+                language: settings.language.clone(),
+                seconds: settings.tray_title_seconds,
+                shown: None,
+            };
+
+            app.manage(AppState {
+                model: Mutex::new(AppModel {
+                    conn,
+                    settings,
+                    timer,
+                    queued_notification: None,
+                    console_detached: false,
+                    records_cache: None,
+                    breathing_emitted: None,
+                    music_playing: None,
+                    screen_dim,
+                    db_recovery: db_recovery.clone(),
+                    read_only,
+                }),
+                remote: Mutex::new(RemoteControlState {
+                    server: None,
+                    status: RemoteStatus::stopped(),
+                }),
+                tray: Mutex::new(tray),
+                remote_metrics: Mutex::new(BTreeMap::new()),
+                remote_events: Mutex::new(RemoteBroadcast::default()),
+                remote_guard: Mutex::new(RemoteGuard::default()),
+                remote_prompts: Mutex::new(BTreeSet::new()),
+                remote_websockets: Arc::new(AtomicUsize::new(0)),
+                remote_event_streams: Arc::new(AtomicUsize::new(0)),
+                started_at: now_ts(),
+                timer_heartbeat: std::sync::atomic::AtomicI64::new(now_ts()),
+            });
+            forward_remote_events(app.handle());
+
+            setup_tray(app.handle())?;
+
+            {
+                let state = app.state::<AppState>();
+                let model = state.model.lock().map_err(|e| e.to_string())?;
+                update_tray_title(app.handle(), &model.timer);
+            }
+
+            // The window may not be listening yet; it can also call db_recovery_status and
+            // db_self_check itself.
+            if let Some(report) = &db_recovery {
+                let _ = app.emit("db://recovered", report);
+            }
+            {
+                let state = app.state::<AppState>();
+                let model = state.model.lock().map_err(|e| e.to_string())?;
+                if model.read_only.is_some() {
+                    let _ = app.emit("db://read-only", read_only_status(&model, 0));
+                    show_notification(
+                        app.handle(),
+                        &localize(&model.settings.language, Message::DatabaseReadOnly, &[]),
+                    );
+                }
+            }
+            {
+                let state = app.state::<AppState>();
+                let model = state.model.lock().map_err(|e| e.to_string())?;
+                match check_database(&model.conn) {
+                    Ok(report) if !report.is_clean() => {
+                        let _ = app.emit("db://self-check", &report);
+                    }
+                    Ok(_) => {}
+                    Err(error) => eprintln!("database self-check failed: {error}"),
+                }
+            }
+
+            spawn_timer_worker(app.handle().clone());
+            spawn_cloud_backup_worker(app.handle().clone());
+            spawn_local_backup_worker(app.handle().clone());
+            spawn_maintenance_worker(app.handle().clone());
+            spawn_data_watcher(app.handle().clone());
+
+            // Remote control server (optional; disabled by default).
+            {
+                let state = app.state::<AppState>();
+                let model = state.model.lock().map_err(|e| e.to_string())?;
+                if let Err(error) = remote_apply(app.handle(), &model.settings) {
+                    eprintln!("remote control startup warning: {error}");
+                }
+            }
+            Ok(())
+        })
+        .invoke_handler(tauri::generate_handler![
+            timer_start,
+            timer_pause,
+            timer_resume,
+            timer_skip,
+            timer_get_state,
+            timer_set_context,
+            timer_postpone_long_break,
+            break_extend,
+            session_complete,
+            sessions_find_gaps,
+            analytics_get_summary,
+            analytics_get_timeseries,
+            analytics_get_hourly,
+            analytics_get_weekday,
+            analytics_interruptions,
+            analytics_start_times,
+            analytics_estimation_accuracy,
+            report_generate,
+            analytics_distribution,
+            analytics_records,
+            goals_progress,
+            experiment_start,
+            experiment_stop,
+            experiment_get_report,
+            breathing_current,
+            plan_list,
+            plan_add,
+            plan_remove,
+            plan_start_next,
+            music_list,
+            music_add,
+            music_remove,
+            music_set_volume,
+            music_next,
+            music_track_data,
+            app_diagnostics,
+            support_bundle,
+            db_self_check,
+            db_repair,
+            db_read_only_status,
+            db_recovery_status,
+            db_recovery_dismiss,
+            db_reconcile,
+            analytics_break_usage,
+            projects_list,
+            projects_upsert,
+            tags_list,
+            days_off_list,
+            days_off_set,
+            days_off_remove,
+            days_off_import_ics,
+            tags_list_with_stats,
+            tags_upsert,
+            export_csv,
+            export_csv_bundle,
+            export_ical,
+            export_json,
+            export_csv_to_file,
+            export_json_to_file,
+            database_export,
+            database_import,
+            import_preview,
+            import_json,
+            import_csv,
+            backup_now,
+            backup_list,
+            cloud_backup_get,
+            cloud_backup_update,
+            cloud_backup_now,
+            cloud_backup_restore,
+            sync_push,
+            sync_pull,
+            settings_get,
+            settings_update,
+            reset_all_data,
+            session_history,
+            session_history_page,
+            session_set_notes,
+            sessions_search,
+            sessions_reassign_project,
+            session_set_project_splits,
+            sessions_merge,
+            sessions_split,
+            timer_events_list,
+            session_rate,
+            session_update,
+            session_revisions_list,
+            session_revision_revert,
+            analytics_quality,
+            sessions_bulk_tag,
+            sessions_delete,
+            sessions_restore,
+            projects_delete,
+            projects_restore,
+            tags_delete,
+            tags_restore,
+            tags_merge,
+            projects_merge,
+            projects_reorder,
+            projects_auto_archive,
+            projects_unarchive,
+            analytics_billing,
+            trash_list,
+            focus_length_recommend,
+            dnd_status,
+            get_local_ip,
+            remote_discovery_info,
+            remote_status,
+            remote_tokens_list,
+            remote_tokens_create,
+            remote_tokens_revoke,
+            remote_audit_list,
+            remote_devices_list,
+            remote_devices_decide,
+            remote_devices_remove,
+            remote_security_log,
+        ])
+        .build(tauri::generate_context!())
+        .expect("error while running tauri application")
+        .run(|app, event| {
+            // Never leave the display dimmed after quitting mid-break.
+            if let tauri::RunEvent::Exit = event {
+                if let Ok(mut model) = app.state::<AppState>().model.lock() {
+                    screen_dim_restore(&mut model);
+                }
+            }
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Completed pomodoros per day with focus time, as fetch_summary_days rolls them up.
+    fn focus_days(sessions: &[SessionRecord]) -> BTreeMap<String, i64> {
+        let mut days: BTreeMap<String, i64> = BTreeMap::new();
+        for session in sessions {
+            if session.phase == TimerPhase::Focus && session.duration_sec > 0 {
+                *days.entry(day_key(session.ended_at)).or_default() += session.completed as i64;
+            }
+        }
+        days
+    }
+
+    fn sample_settings() -> AppSettings {
+        AppSettings {
+            focus_min: 25,
+            short_break_min: 5,
+            long_break_min: 15,
+            long_break_every: 4,
+            theme: "light".to_string(),
+            sound_enabled: true,
+            notifications_enabled: true,
+            remote_control_enabled: false,
+            remote_control_port: 48484,
+            cycle_reset_at_midnight: true,
+            cycle_reset_idle_min: 0,
+            max_pauses_per_focus: 0,
+            pause_budget_action: "reject".to_string(),
+            team_stats_enabled: false,
+            team_stats_key: "teamkey".to_string(),
+            dnd_mode: "bypass".to_string(),
+            dnd_break_end_bypass: true,
+            timezone: String::new(),
+            auto_archive_days: 0,
+            remote_detach_action: "ignore".to_string(),
+            language: "en".to_string(),
+            work_day_start_min: 540,
+            work_day_end_min: 1080,
+            gap_min_minutes: 30,
+            skip_days_off: false,
+            streak_min_pomodoros: 0,
+            streak_skip_weekends: false,
+            streak_freeze_tokens: 0,
+            session_webhook_url: String::new(),
+            session_webhook_secret: String::new(),
+            breathing_pattern: "off".to_string(),
+            breathing_custom: vec![4, 4, 4, 4],
+            music_enabled: false,
+            music_shuffle: true,
+            break_dim_enabled: false,
+            break_dim_percent: 30,
+            analytics_excluded_project_ids: Vec::new(),
+            analytics_excluded_tag_ids: Vec::new(),
+            daily_goal_pomodoros: 0,
+            weekly_goal_minutes: 0,
+            goal_notification: true,
+            tray_title_seconds: false,
+            backup_enabled: false,
+            backup_directory: String::new(),
+            backup_interval_hours: 24,
+            backup_keep: 7,
+            backup_format: "json".to_string(),
+            remote_mdns_enabled: true,
+            remote_pairing_required: true,
+            remote_read_only: false,
+        }
+    }
+
+    #[test]
+    fn advances_to_long_break_every_fourth_cycle() {
+        let settings = sample_settings();
+        let mut timer = TimerState::default_with_settings(&settings);
+
+        timer.phase = TimerPhase::Focus;
+        timer.cycle_index = 3;
+        advance_timer(&mut timer, &settings);
+
+        assert_eq!(timer.phase, TimerPhase::LongBreak);
+        assert_eq!(timer.cycle_index, 4);
+    }
+
+    #[test]
+    fn postponed_long_break_follows_next_focus() {
+        let settings = sample_settings();
+        let mut timer = TimerState::default_with_settings(&settings);
+        timer.cycle_index = 3;
+
+        postpone_long_break(&mut timer, &settings).expect("long break is due next");
+        advance_timer(&mut timer, &settings);
+        assert_eq!(timer.phase, TimerPhase::ShortBreak);
+
+        advance_timer(&mut timer, &settings);
+        assert_eq!(timer.phase, TimerPhase::Focus);
+        advance_timer(&mut timer, &settings);
+        assert_eq!(timer.phase, TimerPhase::LongBreak);
+        assert_eq!(timer.cycle_index, 5);
+
+        // Postponing a long break that is already queued but not started.
+        timer.cycle_index = 8;
+        timer.phase = TimerPhase::LongBreak;
+        postpone_long_break(&mut timer, &settings).expect("long break not started");
+        assert_eq!(timer.phase, TimerPhase::ShortBreak);
+        assert_eq!(timer.remaining_seconds, 5 * 60);
+        advance_timer(&mut timer, &settings);
+        advance_timer(&mut timer, &settings);
+        assert_eq!(timer.phase, TimerPhase::LongBreak);
+    }
+
+    #[test]
+    fn break_extension_is_tracked_and_survives_reload() {
+        let settings = sample_settings();
+        let mut timer = TimerState::default_with_settings(&settings);
+        assert!(extend_break(&mut timer, 5).is_err());
+
+        advance_timer(&mut timer, &settings);
+        assert_eq!(timer.phase, TimerPhase::ShortBreak);
+        extend_break(&mut timer, 3).expect("extend short break");
+        assert_eq!(timer.break_extension_seconds, 180);
+        assert_eq!(timer.phase_total_seconds, 5 * 60 + 180);
+
+        timer.remaining_seconds = 7 * 60;
+        let timer = normalize_timer_state(timer, &settings);
+        assert_eq!(timer.phase_total_seconds, 8 * 60);
+        assert_eq!(timer.remaining_seconds, 7 * 60);
+
+        // Skipped after one minute: none of the extension was used.
+        assert_eq!(session_from_timer(&timer, false, 1_000).extended_sec, 0);
+        let mut late = timer.clone();
+        late.remaining_seconds = 60;
+        assert_eq!(session_from_timer(&late, false, 1_000).extended_sec, 120);
+        assert_eq!(session_from_timer(&late, true, 1_000).extended_sec, 180);
+    }
+
+    #[test]
+    fn cycle_resets_on_new_day_or_after_idle_gap() {
+        let mut settings = sample_settings();
+        let now = now_ts();
+        let day = 86_400;
+
+        let mut timer = TimerState::default_with_settings(&settings);
+        timer.cycle_index = 3;
+        timer.cycle_anchor_date = Some(day_key(now - 2 * day));
+        apply_cycle_reset(&mut timer, &settings, now);
+        assert_eq!(timer.cycle_index, 0);
+        assert_eq!(timer.cycle_anchor_date, Some(day_key(now)));
+
+        settings.cycle_reset_at_midnight = false;
+        settings.cycle_reset_idle_min = 30;
+        timer.cycle_index = 2;
+        timer.last_active_at = Some(now - 10 * 60);
+        apply_cycle_reset(&mut timer, &settings, now);
+        assert_eq!(timer.cycle_index, 2);
+
+        timer.last_active_at = Some(now - 45 * 60);
+        apply_cycle_reset(&mut timer, &settings, now);
+        assert_eq!(timer.cycle_index, 0);
+
+        // A phase already in progress keeps its cycle position.
+        timer.cycle_index = 2;
+        timer.started_at = Some(now - 60);
+        apply_cycle_reset(&mut timer, &settings, now);
+        assert_eq!(timer.cycle_index, 2);
+    }
+
+    #[test]
+    fn pause_budget_rejects_or_forfeits_completion() {
+        let mut settings = sample_settings();
+        settings.max_pauses_per_focus = 1;
+        let now = now_ts();
+
+        let mut timer = TimerState::default_with_settings(&settings);
+        assert_eq!(timer.pauses_remaining, Some(1));
+        timer.is_running = true;
+        pause_timer(&mut timer, &settings, now).expect("first pause is within budget");
+        assert_eq!(timer.pauses_remaining, Some(0));
+
+        timer.is_running = true;
+        assert!(pause_timer(&mut timer, &settings, now).is_err());
+        assert!(timer.is_running);
+
+        settings.pause_budget_action = "mark_incomplete".to_string();
+        pause_timer(&mut timer, &settings, now).expect("pause is allowed but forfeits");
+        assert!(!timer.is_running);
+        assert!(timer.pause_budget_exceeded);
+
+        advance_timer(&mut timer, &settings);
+        assert!(!timer.pause_budget_exceeded);
+        assert_eq!(timer.pauses_remaining, None);
+    }
+
+    #[test]
+    fn backup_encryption_round_trips_and_rejects_wrong_passphrase() {
+        let plaintext = br#"{"sessions":[]}"#;
+        let encrypted = encrypt_backup(plaintext, "correct horse").expect("encrypt");
+
+        assert!(encrypted.starts_with(CLOUD_BACKUP_MAGIC));
+        assert_eq!(
+            decrypt_backup(&encrypted, "correct horse").expect("decrypt"),
+            plaintext
+        );
+        assert!(decrypt_backup(&encrypted, "wrong").is_err());
+        assert!(encrypt_backup(plaintext, "").is_err());
+    }
+
+    #[test]
+    fn sync_envelope_round_trips_encrypted_only() {
+        let conn = test_conn();
+        insert_focus_session(&conn, 1_700_000_000, 1500);
+        let mut settings = sample_settings();
+        settings.team_stats_key = "team-secret-key".to_string();
+        let all = ExportRange {
+            from: None,
+            to: None,
+        };
+        let payload = build_backup_payload(&conn, &settings, &all).expect("payload");
+
+        assert!(encode_sync_envelope(&payload, 1, "mac", "", 100).is_err());
+        let envelope =
+            encode_sync_envelope(&payload, 3, "mac", "correct horse", 100).expect("encode");
+        let body = serde_json::to_string(&envelope).expect("serialize");
+        assert!(!body.contains("team-secret-key"));
+
+        let stored: SyncEnvelope = serde_json::from_str(&body).expect("parse");
+        assert_eq!(stored.revision, 3);
+        assert!(decode_sync_envelope(&stored, "wrong").is_err());
+        let backup = decode_sync_envelope(&stored, "correct horse").expect("decode");
+        assert_eq!(backup.sessions.len(), 1);
+        assert_eq!(
+            backup.settings.expect("settings").team_stats_key,
+            "team-secret-key"
+        );
+    }
+
+    #[test]
+    fn sync_pull_refuses_to_overwrite_unsynced_edits() {
+        let conn = test_conn();
+        let log = |domains: &str| {
+            conn.execute(
+                "INSERT INTO mutation_log (command, domains, at) VALUES ('test', ?1, 0)",
+                [domains],
+            )
+            .expect("log");
+        };
+        log("sessions");
+        let config = CloudBackupConfig {
+            sync_revision: 4,
+            synced_mutation_id: last_mutation_id(&conn).expect("last id"),
+            ..CloudBackupConfig::default()
+        };
+
+        // Timer ticks are not data edits.
+        log("timer");
+        let local = has_unsynced_changes(&conn, config.synced_mutation_id).expect("changes");
+        assert!(!local);
+        assert!(sync_pull_blocker(&config, 5, local, false).is_none());
+        assert_eq!(
+            sync_pull_blocker(&config, 4, local, false).map(|o| o.status),
+            Some("up_to_date".to_string())
+        );
+
+        log("projects,timer");
+        let local = has_unsynced_changes(&conn, config.synced_mutation_id).expect("changes");
+        assert!(local);
+        let blocked = sync_pull_blocker(&config, 5, local, false).expect("conflict");
+        assert_eq!(blocked.status, "conflict");
+        assert_eq!((blocked.local_revision, blocked.remote_revision), (4, 5));
+        assert!(sync_pull_blocker(&config, 5, local, true).is_none());
+    }
+
+    fn test_conn() -> Connection {
+        let conn = Connection::open_in_memory().expect("open in-memory db");
+        init_database(&conn).expect("init schema");
+        conn
+    }
+
+    fn insert_focus_session(conn: &Connection, ended_at: i64, duration_sec: i64) -> i64 {
+        conn.execute(
+            "INSERT INTO sessions (started_at, ended_at, phase, duration_sec, completed, interruptions, project_id)
+             VALUES (?1, ?2, 'focus', ?3, 1, 0, NULL)",
+            params![ended_at - duration_sec, ended_at, duration_sec],
+        )
+        .expect("insert session");
+        conn.last_insert_rowid()
+    }
+
+    #[test]
+    fn session_pages_walk_all_rows_with_cursor() {
+        let conn = test_conn();
+        let base = 1_700_000_000;
+        for i in 0..5 {
+            insert_focus_session(&conn, base + i * 100, 60);
+        }
+        // Same ended_at as the newest row to exercise the id tie-breaker.
+        insert_focus_session(&conn, base + 400, 60);
+
+        let mut query = SessionQuery {
+            range: AnalyticsRange {
+                from: None,
+                to: None,
+                project_id: None,
+                tag_id: None,
+                granularity: None,
+                include_excluded: false,
+                phases: Vec::new(),
+                excluded: AnalyticsExclusions::default(),
+            },
+            limit: Some(4),
+            cursor: None,
+        };
+        let first = fetch_sessions_page(&conn, &query).expect("first page");
+        assert_eq!(first.total, 6);
+        assert_eq!(first.sessions.len(), 4);
+        assert!(first.next_cursor.is_some());
+
+        query.cursor = first.next_cursor.clone();
+        let second = fetch_sessions_page(&conn, &query).expect("second page");
+        assert_eq!(second.sessions.len(), 2);
+        assert!(second.next_cursor.is_none());
+
+        let mut ids: Vec<i64> = first
+            .sessions
+            .iter()
+            .chain(second.sessions.iter())
+            .map(|s| s.id)
+            .collect();
+        ids.sort();
+        ids.dedup();
+        assert_eq!(ids.len(), 6);
+    }
+
+    #[test]
+    fn notes_search_matches_prefixes_and_follows_updates() {
+        let conn = test_conn();
+        let first = insert_focus_session(&conn, 1_700_000_000, 1500);
+        let second = insert_focus_session(&conn, 1_700_003_000, 1500);
+        conn.execute(
+            "UPDATE sessions SET notes = 'Drafted the \"intro\" chapter' WHERE id = ?1",
+            params![first],
+        )
+        .unwrap();
+        conn.execute(
+            "UPDATE sessions SET notes = 'Code review' WHERE id = ?1",
+            params![second],
+        )
+        .unwrap();
+
+        let mut query = SessionSearchQuery {
+            range: AnalyticsRange {
+                from: None,
+                to: None,
+                project_id: None,
+                tag_id: None,
+                granularity: None,
+                include_excluded: false,
+                phases: Vec::new(),
+                excluded: AnalyticsExclusions::default(),
+            },
+            text: "draft \"intro".to_string(),
+            limit: None,
+        };
+        let found = search_sessions(&conn, &query).expect("search");
+        assert_eq!(found.iter().map(|s| s.id).collect::<Vec<_>>(), vec![first]);
+
+        conn.execute(
+            "UPDATE sessions SET notes = NULL WHERE id = ?1",
+            params![first],
+        )
+        .unwrap();
+        assert!(search_sessions(&conn, &query).unwrap().is_empty());
+
+        query.text = "review".to_string();
+        assert_eq!(search_sessions(&conn, &query).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn reassign_project_moves_only_matching_sessions() {
+        let mut conn = test_conn();
+        for name in ["Old", "New"] {
+            conn.execute(
+                "INSERT INTO projects (name, archived, created_at) VALUES (?1, 0, 0)",
+                params![name],
+            )
+            .unwrap();
+        }
+        let early = insert_focus_session(&conn, 1_700_000_000, 1500);
+        let late = insert_focus_session(&conn, 1_700_100_000, 1500);
+        conn.execute("UPDATE sessions SET project_id = 1", [])
+            .unwrap();
+
+        let filter = AnalyticsRange {
+            from: Some(1_700_050_000),
+            to: None,
+            project_id: Some(1),
+            tag_id: None,
+            granularity: None,
+            include_excluded: false,
+            phases: Vec::new(),
+            excluded: AnalyticsExclusions::default(),
+        };
+        // Only a split credits the old project here; the session still moves, splits and all.
+        let split = insert_focus_session(&conn, 1_700_200_000, 1500);
+        conn.execute(
+            "INSERT INTO session_project_splits (session_id, project_id, percent)
+             VALUES (?1, 1, 40), (?1, 2, 60)",
+            params![split],
+        )
+        .unwrap();
+        let updated = reassign_sessions_project(&mut conn, &filter, Some(2)).expect("reassign");
+        assert_eq!(updated, 2);
+        assert!(fetch_session(&conn, split)
+            .unwrap()
+            .project_splits
+            .is_empty());
+
+        let project_of = |id: i64| -> Option<i64> {
+            conn.query_row(
+                "SELECT project_id FROM sessions WHERE id = ?1",
+                params![id],
+                |row| row.get(0),
+            )
+            .unwrap()
+        };
+        assert_eq!(project_of(early), Some(1));
+        assert_eq!(project_of(late), Some(2));
+        assert_eq!(project_of(split), Some(2));
+
+        // A project in the trash is no target.
+        conn.execute("UPDATE projects SET deleted_at = 1 WHERE id = 1", [])
+            .unwrap();
+        assert_eq!(
+            reassign_sessions_project(&mut conn, &AnalyticsRange::default(), Some(1)),
+            Err("project 1 not found".to_string())
+        );
+        assert_eq!(fetch_session(&conn, late).unwrap().project_id, Some(2));
+    }
+
+    #[test]
+    fn bulk_tag_updates_only_matching_untagged_sessions() {
+        let mut conn = test_conn();
+        conn.execute_batch(
+            "INSERT INTO projects (name, archived, created_at) VALUES ('Client', 0, 0);
+             INSERT INTO tags (name, created_at) VALUES ('billing', 0), ('draft', 0);",
+        )
+        .unwrap();
+        let untagged = insert_focus_session(&conn, 1_700_100_000, 1500);
+        let tagged = insert_focus_session(&conn, 1_700_200_000, 1500);
+        let other_project = insert_focus_session(&conn, 1_700_300_000, 1500);
+        conn.execute(
+            "UPDATE sessions SET project_id = 1 WHERE id IN (?1, ?2)",
+            params![untagged, tagged],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO session_tags (session_id, tag_id) VALUES (?1, 2)",
+            params![tagged],
+        )
+        .unwrap();
+
+        let filter = BulkTagFilter {
+            range: AnalyticsRange {
+                project_id: Some(1),
+                ..Default::default()
+            },
+            untagged: true,
+        };
+        assert_eq!(
+            bulk_tag_sessions(&mut conn, &filter, &[1], &[]).expect("tag"),
+            1
+        );
+        let tags_of = |conn: &Connection, id| fetch_session(conn, id).unwrap().tag_ids;
+        assert_eq!(tags_of(&conn, untagged), vec![1]);
+        assert_eq!(tags_of(&conn, tagged), vec![2]);
+        assert!(tags_of(&conn, other_project).is_empty());
+
+        let all_client = BulkTagFilter {
+            range: AnalyticsRange {
+                project_id: Some(1),
+                ..Default::default()
+            },
+            untagged: false,
+        };
+        assert_eq!(
+            bulk_tag_sessions(&mut conn, &all_client, &[1], &[2]).expect("retag"),
+            1
+        );
+        assert_eq!(tags_of(&conn, tagged), vec![1]);
+        let revisions = fetch_session_revisions(&conn, tagged).expect("revisions");
+        assert_eq!(revisions.len(), 1);
+
+        assert!(bulk_tag_sessions(&mut conn, &all_client, &[9], &[]).is_err());
+        assert!(bulk_tag_sessions(&mut conn, &all_client, &[], &[]).is_err());
+    }
+
+    #[test]
+    fn split_sessions_are_pro_rated_per_project() {
+        let mut conn = test_conn();
+        for name in ["Pairing A", "Pairing B"] {
+            conn.execute(
+                "INSERT INTO projects (name, archived, created_at) VALUES (?1, 0, 0)",
+                params![name],
+            )
+            .unwrap();
+        }
+        let id = insert_focus_session(&conn, 1_700_000_000, 1500);
+
+        let bad = [ProjectSplit {
+            project_id: 1,
+            percent: 70,
+        }];
+        assert!(write_session_splits(&mut conn, id, &bad).is_err());
+
+        let splits = [
+            ProjectSplit {
+                project_id: 1,
+                percent: 40,
+            },
+            ProjectSplit {
+                project_id: 2,
+                percent: 60,
+            },
+        ];
+        write_session_splits(&mut conn, id, &splits).expect("write splits");
+
+        let range = AnalyticsRange {
+            from: None,
+            to: None,
+            project_id: Some(1),
+            tag_id: None,
+            granularity: None,
+            include_excluded: false,
+            phases: Vec::new(),
+            excluded: AnalyticsExclusions::default(),
+        };
+        let sessions = fetch_sessions(&conn, &range).expect("fetch");
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].project_id, Some(2));
+        let credited = |project_id| {
+            let range = AnalyticsRange {
+                project_id,
+                ..Default::default()
+            };
+            focus_totals_by::<i64>(&conn, &range, "0").expect("totals")[&0].focus_seconds
+        };
+        assert_eq!(credited(Some(1)), 600);
+        assert_eq!(credited(Some(2)), 900);
+        assert_eq!(credited(None), 1500);
+    }
+
+    #[test]
+    fn split_then_merge_restores_duration_and_tags() {
+        let mut conn = test_conn();
+        conn.execute("INSERT INTO tags (name, created_at) VALUES ('deep', 0)", [])
+            .unwrap();
+        let id = insert_focus_session(&conn, 1_700_001_000, 1000);
+        conn.execute(
+            "INSERT INTO session_tags (session_id, tag_id) VALUES (?1, 1)",
+            params![id],
+        )
+        .unwrap();
+
+        let (first, second) = split_session(&mut conn, id, 1_700_000_400).expect("split");
+        let a = fetch_session(&conn, first).unwrap();
+        let b = fetch_session(&conn, second).unwrap();
+        assert_eq!((a.duration_sec, b.duration_sec), (400, 600));
+        assert_eq!(b.tag_ids, vec![1]);
+        assert!(!a.completed && b.completed);
+
+        let merged = merge_sessions(&mut conn, second, first).expect("merge");
+        let m = fetch_session(&conn, merged).unwrap();
+        assert_eq!(merged, first);
+        assert_eq!(m.duration_sec, 1000);
+        assert_eq!((m.started_at, m.ended_at), (1_700_000_000, 1_700_001_000));
+        assert_eq!(m.tag_ids, vec![1]);
+        assert!(fetch_session(&conn, second).is_err());
+    }
+
+    #[test]
+    fn merge_rejects_overlaps_and_gaps_and_blends_project_splits() {
+        let mut conn = test_conn();
+        conn.execute_batch(
+            "INSERT INTO projects (name, archived, created_at) VALUES ('A', 0, 0);
+             INSERT INTO projects (name, archived, created_at) VALUES ('B', 0, 0);",
+        )
+        .unwrap();
+        let first = insert_focus_session(&conn, 1_700_001_000, 1000);
+        conn.execute(
+            "UPDATE sessions SET project_id = 1 WHERE id = ?1",
+            params![first],
+        )
+        .unwrap();
+        let second = insert_focus_session(&conn, 1_700_002_000, 1000);
+        write_session_splits(
+            &mut conn,
+            second,
+            &[
+                ProjectSplit {
+                    project_id: 1,
+                    percent: 40,
+                },
+                ProjectSplit {
+                    project_id: 2,
+                    percent: 60,
+                },
+            ],
+        )
+        .unwrap();
+
+        let merged = merge_sessions(&mut conn, first, second).expect("merge");
+        let m = fetch_session(&conn, merged).unwrap();
+        assert_eq!(m.project_id, Some(1));
+        let shares: Vec<(i64, i64)> = m
+            .project_splits
+            .iter()
+            .map(|split| (split.project_id, split.percent))
+            .collect();
+        assert_eq!(shares, vec![(1, 70), (2, 30)]);
+
+        let overlapping = insert_focus_session(&conn, 1_700_002_500, 1000);
+        assert_eq!(
+            merge_sessions(&mut conn, merged, overlapping),
+            Err("sessions overlap".to_string())
+        );
+
+        // A session straddling the gap keeps the two apart even though it isn't wholly inside it.
+        let early = insert_focus_session(&conn, 1_700_010_000, 1000);
+        let late = insert_focus_session(&conn, 1_700_012_000, 500);
+        insert_focus_session(&conn, 1_700_011_700, 500);
+        assert_eq!(
+            merge_sessions(&mut conn, early, late),
+            Err("sessions are not adjacent".to_string())
+        );
+    }
+
+    #[test]
+    fn health_export_cursor_keeps_sessions_ending_in_the_same_second() {
+        let conn = test_conn();
+        let ids = |export: &HealthExport| {
+            export
+                .entries
+                .iter()
+                .map(|e| e.session_id)
+                .collect::<Vec<_>>()
+        };
+        let first = insert_focus_session(&conn, 1_700_000_000, 600);
+        let second = insert_focus_session(&conn, 1_700_000_000, 300);
+        let export = health_export(&conn, None, "focus").unwrap();
+        assert_eq!(ids(&export), vec![first, second]);
+        assert_eq!(export.next_since, Some(1_700_000_000));
+        let cursor = export.next_cursor.unwrap();
+        assert_eq!(cursor, format!("1700000000:{second}"));
+
+        let third = insert_focus_session(&conn, 1_700_000_000, 100);
+        let after = parse_session_cursor(&cursor).unwrap();
+        let export = health_export(&conn, Some(after), "focus").unwrap();
+        assert_eq!(ids(&export), vec![third]);
+        assert!(!export.has_more);
+        let export = health_export(&conn, Some(after), "breaks").unwrap();
+        assert!(export.entries.is_empty());
+        assert_eq!(export.next_cursor, Some(cursor));
+
+        // A long backlog comes back a page at a time, oldest first.
+        for i in 0..HEALTH_EXPORT_PAGE_SIZE {
+            insert_focus_session(&conn, 1_700_001_000 + i, 60);
+        }
+        let page = health_export(&conn, None, "focus").unwrap();
+        assert_eq!(page.entries.len() as i64, HEALTH_EXPORT_PAGE_SIZE);
+        assert!(page.has_more);
+        assert_eq!(page.entries[0].session_id, first);
+        let after = parse_session_cursor(&page.next_cursor.unwrap()).unwrap();
+        let rest = health_export(&conn, Some(after), "focus").unwrap();
+        assert_eq!(rest.entries.len(), 3);
+        assert!(!rest.has_more);
+    }
+
+    #[test]
+    fn team_daily_totals_only_count_recent_focus() {
+        let conn = test_conn();
+        let now = 1_700_100_000;
+        insert_focus_session(&conn, now - 100, 1500);
+        insert_focus_session(&conn, now - 40 * 86_400, 1500);
+        conn.execute(
+            "INSERT INTO sessions (started_at, ended_at, phase, duration_sec, completed, interruptions)
+             VALUES (?1, ?2, 'short_break', 300, 1, 0)",
+            params![now - 400, now - 100],
+        )
+        .unwrap();
+
+        let days = team_daily_totals(&conn, 30, now).unwrap();
+        assert_eq!(days.len(), 1);
+        assert_eq!(days[0].focus_seconds, 1500);
+        assert_eq!(days[0].completed_pomodoros, 1);
+    }
+
+    #[test]
+    fn validate_new_session_rejects_bad_ranges_and_overlaps() {
+        let conn = test_conn();
+        let existing = insert_focus_session(&conn, 1_700_001_500, 1500);
+        let request = |started_at: i64, ended_at: i64, duration_sec: i64| CompleteSessionRequest {
+            started_at,
+            ended_at,
+            phase: TimerPhase::Focus,
+            duration_sec,
+            completed: true,
+            interruptions: 0,
+            project_id: None,
+            tag_ids: None,
+            notes: None,
+            quality: None,
+            allow_overlap: None,
+        };
+
+        let check =
+            |payload: CompleteSessionRequest| validate_new_session(&conn, &payload).unwrap();
+        assert_eq!(
+            check(request(1_700_010_000, 1_700_010_000, 0)),
+            Err(SessionValidationError::InvalidRange)
+        );
+        assert_eq!(
+            check(request(1_700_010_000, 1_700_010_100, 200)),
+            Err(SessionValidationError::DurationExceedsWindow)
+        );
+        assert_eq!(
+            check(request(1_700_001_000, 1_700_002_000, 600)),
+            Err(SessionValidationError::Overlap(existing))
+        );
+        assert_eq!(check(request(1_700_001_500, 1_700_002_000, 500)), Ok(()));
+
+        let mut forced = request(1_700_001_000, 1_700_002_000, 600);
+        forced.allow_overlap = Some(true);
+        assert_eq!(check(forced), Ok(()));
+        assert_eq!(
+            SessionValidationError::Overlap(existing).to_string(),
+            format!("overlap: overlaps existing session {existing}")
+        );
+    }
+
+    #[test]
+    fn timer_events_are_listed_in_time_order_within_range() {
+        let conn = test_conn();
+        let mut timer = TimerState::default_with_settings(&sample_settings());
+        assert_eq!(run_event_kind(&timer), TimerEventKind::Started);
+        record_timer_event(&conn, TimerEventKind::Started, &timer, None, 100).unwrap();
+        timer.started_at = Some(100);
+        record_timer_event(&conn, TimerEventKind::Paused, &timer, None, 200).unwrap();
+        assert_eq!(run_event_kind(&timer), TimerEventKind::Resumed);
+        record_timer_event(&conn, TimerEventKind::Completed, &timer, Some(7), 300).unwrap();
+
+        let events = fetch_timer_events(
+            &conn,
+            &TimerEventQuery {
+                from: Some(150),
+                to: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+        let kinds: Vec<_> = events.iter().map(|e| e.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![TimerEventKind::Paused, TimerEventKind::Completed]
+        );
+        assert_eq!(events[1].session_id, Some(7));
+    }
+
+    #[test]
+    fn quality_breakdown_averages_rated_focus_sessions() {
+        let mut conn = test_conn();
+        let first = insert_focus_session(&conn, 1_700_001_500, 1500);
+        let second = insert_focus_session(&conn, 1_700_003_500, 1500);
+        insert_focus_session(&conn, 1_700_005_500, 1500);
+        rate_session(&mut conn, first, Some(5)).unwrap();
+        rate_session(&mut conn, second, Some(2)).unwrap();
+        assert!(rate_session(&mut conn, first, Some(6))
+            .unwrap_err()
+            .starts_with("invalid_quality"));
+
+        let sessions = fetch_sessions(
+            &conn,
+            &AnalyticsRange {
+                from: None,
+                to: None,
+                project_id: None,
+                tag_id: None,
+                granularity: None,
+                include_excluded: false,
+                phases: Vec::new(),
+                excluded: AnalyticsExclusions::default(),
+            },
+        )
+        .unwrap();
+        let breakdown = quality_breakdown(&sessions);
+        assert_eq!(breakdown.by_project.len(), 1);
+        assert_eq!(breakdown.by_project[0].rated_sessions, 2);
+        assert!((breakdown.by_project[0].avg_quality - 3.5).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn trashed_sessions_are_hidden_restorable_and_purged() {
+        let mut conn = test_conn();
+        let kept = insert_focus_session(&conn, 1_700_001_500, 1500);
+        let trashed = insert_focus_session(&conn, 1_700_003_500, 1500);
+        let all = AnalyticsRange {
+            from: None,
+            to: None,
+            project_id: None,
+            tag_id: None,
+            granularity: None,
+            include_excluded: false,
+            phases: Vec::new(),
+            excluded: AnalyticsExclusions::default(),
+        };
+
+        let now = 1_700_010_000;
+        assert_eq!(
+            set_trashed(&conn, "sessions", &[trashed], Some(now)).unwrap(),
+            1
+        );
+        assert_eq!(
+            set_trashed(&conn, "sessions", &[trashed], Some(now)).unwrap(),
+            0
+        );
+        let visible: Vec<_> = fetch_sessions(&conn, &all)
+            .unwrap()
+            .iter()
+            .map(|s| s.id)
+            .collect();
+        assert_eq!(visible, vec![kept]);
+        assert_eq!(fetch_trash(&conn).unwrap().sessions[0].item.id, trashed);
+
+        set_trashed(&conn, "sessions", &[trashed], None).unwrap();
+        assert_eq!(fetch_sessions(&conn, &all).unwrap().len(), 2);
+
+        set_trashed(&conn, "sessions", &[trashed], Some(now)).unwrap();
+        assert_eq!(purge_trash(&mut conn, now + 86_400).unwrap(), 0);
+        assert_eq!(
+            purge_trash(&mut conn, now + TRASH_RETENTION_SECONDS).unwrap(),
+            1
+        );
+        assert!(fetch_session(&conn, trashed).is_err());
+        assert!(fetch_trash(&conn).unwrap().sessions.is_empty());
+    }
+
+    #[test]
+    fn trashed_names_can_be_reused_and_old_unique_columns_migrate() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE projects (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                name TEXT NOT NULL UNIQUE,
+                color TEXT,
+                archived INTEGER NOT NULL DEFAULT 0,
+                created_at INTEGER NOT NULL
+            );
+            CREATE TABLE tags (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                name TEXT NOT NULL UNIQUE,
+                created_at INTEGER NOT NULL
+            );
+            INSERT INTO projects (name, created_at) VALUES ('Client', 0);
+            INSERT INTO tags (name, created_at) VALUES ('deep', 0);",
+        )
+        .unwrap();
+        init_database(&conn).expect("migrate");
+        conn.execute(
+            "INSERT INTO sessions (started_at, ended_at, phase, duration_sec, completed, interruptions, project_id)
+             VALUES (0, 1500, 'focus', 1500, 1, 0, 1)",
+            [],
+        )
+        .unwrap();
+
+        for table in ["projects", "tags"] {
+            set_trashed(&conn, table, &[1], Some(100)).unwrap();
+            let name = if table == "projects" {
+                "Client"
+            } else {
+                "deep"
+            };
+            conn.execute(
+                &format!("INSERT INTO {table} (name, created_at) VALUES (?1, 200)"),
+                params![name],
+            )
+            .expect("a trashed name is free again");
+            assert!(conn
+                .execute(
+                    &format!("INSERT INTO {table} (name, created_at) VALUES (?1, 300)"),
+                    params![name],
+                )
+                .is_err());
+            // Restoring would make two live rows share the name.
+            let error = set_trashed(&conn, table, &[1], None).unwrap_err();
+            assert!(error.contains("rename it first"), "{error}");
+        }
+        let project_id: Option<i64> = conn
+            .query_row("SELECT project_id FROM sessions", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(project_id, Some(1));
+    }
+
+    #[test]
+    fn recommends_longest_length_that_is_usually_finished() {
+        let conn = test_conn();
+        let mut ended_at = 1_700_000_000;
+        let mut log = |planned_min: i64, completed: bool| {
+            ended_at += 3600;
+            conn.execute(
+                "INSERT INTO sessions (started_at, ended_at, phase, duration_sec, completed, interruptions, planned_sec)
+                 VALUES (?1, ?2, 'focus', ?3, ?4, 0, ?5)",
+                params![ended_at - 600, ended_at, 600, completed as i64, planned_min * 60],
+            )
+            .unwrap();
+        };
+        for i in 0..10 {
+            log(20, i != 0);
+            log(30, i % 2 == 0);
+        }
+        for _ in 0..3 {
+            log(45, true);
+        }
+
+        let all = AnalyticsRange {
+            from: None,
+            to: None,
+            project_id: None,
+            tag_id: None,
+            granularity: None,
+            include_excluded: false,
+            phases: Vec::new(),
+            excluded: AnalyticsExclusions::default(),
+        };
+        let buckets = focus_length_buckets(&conn, &all).unwrap();
+        let minutes: Vec<_> = buckets.iter().map(|b| b.minutes).collect();
+        assert_eq!(minutes, vec![20, 30, 45]);
+        assert!((buckets[0].completion_rate - 0.9).abs() < 1e-9);
+        assert_eq!(recommend_focus_length(&buckets), Some(20));
+    }
+
+    #[test]
+    fn deleting_a_project_with_history_requires_force() {
+        let mut conn = test_conn();
+        conn.execute(
+            "INSERT INTO projects (name, created_at) VALUES ('Thesis', 0)",
+            [],
+        )
+        .unwrap();
+        let session = insert_focus_session(&conn, 1_700_001_500, 1500);
+        conn.execute(
+            "UPDATE sessions SET project_id = 1 WHERE id = ?1",
+            params![session],
+        )
+        .unwrap();
+
+        let refused = delete_project(&mut conn, 1, false).unwrap_err();
+        assert!(refused.starts_with("has_history"));
+        assert!(fetch_trash(&conn).unwrap().projects.is_empty());
+
+        let result = delete_project(&mut conn, 1, true).unwrap();
+        assert!(result.deleted);
+        assert_eq!(result.detached_sessions, 1);
+        assert_eq!(fetch_session(&conn, session).unwrap().project_id, None);
+        assert_eq!(fetch_trash(&conn).unwrap().projects.len(), 1);
+        assert!(fetch_projects(&conn).unwrap().is_empty());
+    }
+
+    #[test]
+    fn merging_tags_moves_sessions_without_duplicates() {
+        let mut conn = test_conn();
+        for name in ["writing", "Writing", "writng"] {
+            conn.execute(
+                "INSERT INTO tags (name, created_at) VALUES (?1, 0)",
+                params![name],
+            )
+            .unwrap();
+        }
+        let both = insert_focus_session(&conn, 1_700_001_500, 1500);
+        let typo = insert_focus_session(&conn, 1_700_003_500, 1500);
+        for (session_id, tag_id) in [(both, 1), (both, 2), (typo, 3)] {
+            conn.execute(
+                "INSERT INTO session_tags (session_id, tag_id) VALUES (?1, ?2)",
+                params![session_id, tag_id],
+            )
+            .unwrap();
+        }
+
+        let result = merge_tags(&mut conn, &[2, 3], 1).unwrap();
+        assert_eq!(result.removed, 2);
+        assert_eq!(result.moved_sessions, 1);
+        assert_eq!(fetch_session(&conn, both).unwrap().tag_ids, vec![1]);
+        assert_eq!(fetch_session(&conn, typo).unwrap().tag_ids, vec![1]);
+        assert_eq!(fetch_tags(&conn).unwrap().len(), 1);
+        assert!(merge_tags(&mut conn, &[1], 1).is_err());
+    }
+
+    #[test]
+    fn dnd_mode_decides_notifications_but_break_end_can_bypass() {
+        let mut settings = sample_settings();
+        settings.dnd_mode = "queue".to_string();
+        let focus = TimerPhase::Focus;
+        let short_break = TimerPhase::ShortBreak;
+
+        assert_eq!(
+            notification_action(&settings, &focus, false),
+            NotificationAction::Show
+        );
+        assert_eq!(
+            notification_action(&settings, &focus, true),
+            NotificationAction::Queue
+        );
+        assert_eq!(
+            notification_action(&settings, &short_break, true),
+            NotificationAction::Show
+        );
+
+        settings.dnd_mode = "suppress".to_string();
+        settings.dnd_break_end_bypass = false;
+        assert_eq!(
+            notification_action(&settings, &short_break, true),
+            NotificationAction::Drop
+        );
+
+        let on = serde_json::json!({ "data": [{ "storeAssertionRecords": [{ "assertionDetails": {} }] }] });
+        let off = serde_json::json!({ "data": [{}] });
+        assert!(focus_assertions_active(&on));
+        assert!(!focus_assertions_active(&off));
+    }
+
+    #[test]
+    fn merging_projects_moves_sessions_and_split_shares() {
+        let mut conn = test_conn();
+        conn.execute(
+            "INSERT INTO projects (name, color, created_at) VALUES ('Old', '#f00', 0), ('New', NULL, 0)",
+            [],
+        )
+        .unwrap();
+        let plain = insert_focus_session(&conn, 1_700_001_500, 1500);
+        let split = insert_focus_session(&conn, 1_700_003_500, 1500);
+        conn.execute(
+            "UPDATE sessions SET project_id = 1 WHERE id = ?1",
+            params![plain],
+        )
+        .unwrap();
+        write_session_splits(
+            &mut conn,
+            split,
+            &[
+                ProjectSplit {
+                    project_id: 1,
+                    percent: 40,
+                },
+                ProjectSplit {
+                    project_id: 2,
+                    percent: 60,
+                },
+            ],
+        )
+        .unwrap();
+
+        let result = merge_projects(&mut conn, 1, 2, false).unwrap();
+        assert_eq!(result.removed, 0);
+        assert_eq!(fetch_session(&conn, plain).unwrap().project_id, Some(2));
+        let merged = fetch_session(&conn, split).unwrap();
+        assert_eq!(merged.project_splits.len(), 1);
+        assert_eq!(merged.project_splits[0].percent, 100);
+
+        let projects = fetch_projects(&conn).unwrap();
+        let old = projects.iter().find(|p| p.id == 1).unwrap();
+        let new = projects.iter().find(|p| p.id == 2).unwrap();
+        assert!(old.archived);
+        assert_eq!(new.color.as_deref(), Some("#f00"));
+    }
+
+    #[test]
+    fn csv_export_honours_dialect_and_columns() {
+        let conn = test_conn();
+        conn.execute(
+            "INSERT INTO tags (name, created_at) VALUES ('a', 0), ('b', 0)",
+            [],
+        )
+        .unwrap();
+        let id = insert_focus_session(&conn, 1_700_001_500, 1500);
+        conn.execute(
+            "INSERT INTO session_tags (session_id, tag_id) VALUES (?1, 1), (?1, 2)",
+            params![id],
+        )
+        .unwrap();
+        conn.execute(
+            "UPDATE sessions SET notes = 'said \"hi\"' WHERE id = ?1",
+            params![id],
+        )
+        .unwrap();
+        let sessions = vec![fetch_session(&conn, id).unwrap()];
+
+        let default = sessions_to_csv(&conn, &sessions, &CsvOptions::default(), "").unwrap();
+        assert_eq!(
+            default,
+            format!("id,startedAt,endedAt,phase,durationSec,completed,interruptions,projectId,tagIds\n{id},1700000000,1700001500,focus,1500,true,0,,1;2\n")
+        );
+
+        let options = CsvOptions {
+            delimiter: Some("semicolon".to_string()),
+            date_format: None,
+            columns: Some(vec![
+                "id".to_string(),
+                "tagNames".to_string(),
+                "notes".to_string(),
+            ]),
+            local_timestamps: None,
+        };
+        let custom = sessions_to_csv(&conn, &sessions, &options, "").unwrap();
+        assert_eq!(
+            custom,
+            format!("id;tagNames;notes\n{id};\"a;b\";\"said \"\"hi\"\"\"\n")
+        );
+
+        let bad = CsvOptions {
+            columns: Some(vec!["secret".to_string()]),
+            ..CsvOptions::default()
+        };
+        assert!(sessions_to_csv(&conn, &sessions, &bad, "").is_err());
+
+        let local = CsvOptions {
+            columns: Some(vec!["id".to_string()]),
+            local_timestamps: Some(true),
+            ..CsvOptions::default()
+        };
+        let zoned = sessions_to_csv(&conn, &sessions, &local, "Asia/Tokyo").unwrap();
+        assert_eq!(
+            zoned,
+            format!("id,startedAtLocal,endedAtLocal,weekday\n{id},2023-11-15T07:13:20+09:00,2023-11-15T07:38:20+09:00,Wednesday\n")
+        );
+    }
+
+    #[test]
+    fn csv_bundle_keeps_relational_tables() {
+        let conn = test_conn();
+        conn.execute(
+            "INSERT INTO projects (name, created_at) VALUES ('Work', 0)",
+            [],
+        )
+        .unwrap();
+        conn.execute("INSERT INTO tags (name, created_at) VALUES ('deep', 0)", [])
+            .unwrap();
+        let id = insert_focus_session(&conn, 1_700_001_500, 1500);
+        conn.execute(
+            "INSERT INTO session_tags (session_id, tag_id) VALUES (?1, 1)",
+            params![id],
+        )
+        .unwrap();
+        let sessions = vec![fetch_session(&conn, id).unwrap()];
+
+        let bundle = csv_bundle(&conn, &sessions).unwrap();
+        let mut archive = zip::ZipArchive::new(std::io::Cursor::new(bundle)).unwrap();
+        let mut names: Vec<_> = archive.file_names().map(str::to_string).collect();
+        names.sort();
+        assert_eq!(
+            names,
+            [
+                "projects.csv",
+                "session_project_splits.csv",
+                "session_tags.csv",
+                "sessions.csv",
+                "tags.csv"
+            ]
+        );
+        let mut session_tags = String::new();
+        archive
+            .by_name("session_tags.csv")
+            .unwrap()
+            .read_to_string(&mut session_tags)
+            .unwrap();
+        assert_eq!(session_tags, format!("sessionId,tagId\n{id},1\n"));
+        let mut projects = String::new();
+        archive
+            .by_name("projects.csv")
+            .unwrap()
+            .read_to_string(&mut projects)
+            .unwrap();
+        assert_eq!(
+            projects,
+            "id,name,color,archived,billable,hourlyRate\n1,Work,,false,false,\n"
+        );
+    }
+
+    #[test]
+    fn import_preview_reports_without_writing() {
+        let conn = test_conn();
+        conn.execute(
+            "INSERT INTO projects (name, created_at) VALUES ('Work', 0)",
+            [],
+        )
+        .unwrap();
+        insert_focus_session(&conn, 1_700_001_500, 1500);
+
+        let csv = "startedAt;endedAt;phase;durationSec;projectName;tagNames;notes\n\
+                   1700000000;1700001500;focus;1500;work;deep;\n\
+                   2023-11-15T10:00:00+00:00;2023-11-15T10:25:00+00:00;focus;1500;Client;\"deep; Read\";\"a;b\"\n\
+                   1700100000;1700099000;focus;60;;;\n\
+                   1700200000;1700201500;nap;1500;;;\n";
+        let preview = preview_import(&conn, "csv", csv).unwrap();
+        assert_eq!(preview.total_rows, 4);
+        assert_eq!(preview.valid_rows, 2);
+        assert_eq!(preview.duplicate_rows, 1);
+        assert_eq!(preview.projects_to_create, vec!["Client".to_string()]);
+        assert_eq!(
+            preview.tags_to_create,
+            vec!["deep".to_string(), "Read".to_string()]
+        );
+        assert_eq!(
+            preview.errors.iter().map(|e| e.row).collect::<Vec<_>>(),
+            vec![3, 4]
+        );
+        assert!(preview.errors[0].message.starts_with("invalid_range:"));
+
+        let json = r#"{"projects":[{"id":7,"name":"Work","color":null,"archived":false}],
+            "tags":[],
+            "sessions":[{"id":1,"startedAt":1,"endedAt":2,"phase":"focus","durationSec":1,
+                "completed":true,"interruptions":0,"projectId":9,"tagIds":[],"projectSplits":[],"notes":null}]}"#;
+        let preview = preview_import(&conn, "json", json).unwrap();
+        assert!(preview.projects_to_create.is_empty());
+        assert_eq!(preview.errors[0].message, "unknown projectId 9");
+
+        assert!(preview_import(&conn, "csv", "id,phase\n1,focus\n").is_err());
+        let count: i64 = conn
+            .query_row("SELECT COUNT(*) FROM sessions", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn tag_stats_count_sessions_in_range() {
+        let conn = test_conn();
+        conn.execute(
+            "INSERT INTO tags (name, created_at) VALUES ('a', 0), ('b', 0), ('c', 0)",
+            [],
+        )
+        .unwrap();
+        let old = insert_focus_session(&conn, 1_000, 600);
+        let recent = insert_focus_session(&conn, 10_000, 1500);
+        let other = insert_focus_session(&conn, 20_000, 900);
+        for (session, tag) in [(old, 1), (recent, 2), (other, 2), (recent, 1)] {
+            conn.execute(
+                "INSERT INTO session_tags (session_id, tag_id) VALUES (?1, ?2)",
+                params![session, tag],
+            )
+            .unwrap();
+        }
+
+        let stats = fetch_tags_with_stats(
+            &conn,
+            &AnalyticsRange {
+                from: Some(5_000),
+                ..AnalyticsRange::default()
+            },
+        )
+        .unwrap();
+        let summary: Vec<_> = stats
+            .iter()
+            .map(|s| (s.tag.name.as_str(), s.session_count, s.focus_seconds))
+            .collect();
+        assert_eq!(summary, vec![("b", 2, 2400), ("a", 1, 1500), ("c", 0, 0)]);
+    }
+
+    #[test]
+    fn projects_list_pinned_first_then_manual_order() {
+        let mut conn = test_conn();
+        conn.execute(
+            "INSERT INTO projects (name, created_at) VALUES ('a', 0), ('b', 0), ('c', 0), ('d', 0)",
+            [],
+        )
+        .unwrap();
+        reorder_projects(&mut conn, &[3, 1]).unwrap();
+        conn.execute("UPDATE projects SET pinned = 1 WHERE id = 4", [])
+            .unwrap();
+
+        let names: Vec<_> = fetch_projects(&conn)
+            .unwrap()
+            .into_iter()
+            .map(|p| p.name)
+            .collect();
+        assert_eq!(names, ["d", "c", "a", "b"]);
+        assert!(reorder_projects(&mut conn, &[99]).is_err());
+    }
+
+    #[test]
+    fn session_edits_keep_revisions_and_revert() {
+        let mut conn = test_conn();
+        conn.execute("INSERT INTO tags (name, created_at) VALUES ('a', 0)", [])
+            .unwrap();
+        let id = insert_focus_session(&conn, 10_000, 1500);
+        conn.execute(
+            "INSERT INTO session_tags (session_id, tag_id) VALUES (?1, 1)",
+            params![id],
+        )
+        .unwrap();
+        let other = insert_focus_session(&conn, 20_000, 1500);
+
+        update_session(
+            &mut conn,
+            id,
+            &SessionUpdate {
+                duration_sec: Some(1200),
+                interruptions: Some(2),
+                ..SessionUpdate::default()
+            },
+        )
+        .unwrap();
+        conn.execute(
+            "DELETE FROM session_tags WHERE session_id = ?1",
+            params![id],
+        )
+        .unwrap();
+        rate_session(&mut conn, id, Some(4)).unwrap();
+        let overlap = update_session(
+            &mut conn,
+            id,
+            &SessionUpdate {
+                ended_at: Some(19_000),
+                ..SessionUpdate::default()
+            },
+        )
+        .unwrap_err();
+        assert_eq!(
+            overlap,
+            format!("overlap: overlaps existing session {other}")
+        );
+
+        let revisions = fetch_session_revisions(&conn, id).unwrap();
+        let sources: Vec<_> = revisions.iter().map(|r| r.source.as_str()).collect();
+        assert_eq!(sources, ["quality", "update"]);
+        assert_eq!(revisions[1].session.duration_sec, 1500);
+
+        revert_session_revision(&mut conn, revisions[1].id, false).unwrap();
+        let restored = fetch_session(&conn, id).unwrap();
+        assert_eq!(restored.duration_sec, 1500);
+        assert_eq!(restored.interruptions, 0);
+        assert_eq!(restored.quality, None);
+        assert_eq!(restored.tag_ids, vec![1]);
+        assert_eq!(
+            fetch_session_revisions(&conn, id).unwrap()[0].source,
+            "revert"
+        );
+
+        // Another session took the old slot since: reverting needs allow_overlap.
+        let move_to = |conn: &mut Connection, session: i64, started_at: i64| {
+            update_session(
+                conn,
+                session,
+                &SessionUpdate {
+                    started_at: Some(started_at),
+                    ended_at: Some(started_at + 1500),
+                    ..SessionUpdate::default()
+                },
+            )
+            .unwrap();
+        };
+        move_to(&mut conn, id, 30_000);
+        move_to(&mut conn, other, 8_500);
+        let moved = fetch_session_revisions(&conn, id).unwrap()[0].id;
+        assert_eq!(
+            revert_session_revision(&mut conn, moved, false).unwrap_err(),
+            format!("overlap: overlaps existing session {other}")
+        );
+        assert_eq!(fetch_session(&conn, id).unwrap().started_at, 30_000);
+        revert_session_revision(&mut conn, moved, true).unwrap();
+        assert_eq!(fetch_session(&conn, id).unwrap().started_at, 8_500);
+    }
+
+    #[test]
+    fn project_input_tells_a_cleared_rate_from_a_missing_one() {
+        let parse = |json: &str| {
+            serde_json::from_str::<ProjectInput>(json)
+                .unwrap()
+                .hourly_rate
+        };
+        assert_eq!(parse(r#"{"name":"A"}"#), None);
+        assert_eq!(parse(r#"{"name":"A","hourlyRate":null}"#), Some(None));
+        assert_eq!(parse(r#"{"name":"A","hourlyRate":80}"#), Some(Some(80.0)));
+    }
+
+    #[test]
+    fn project_overrides_win_over_global_alerts() {
+        let settings = sample_settings();
+        let mut project = Project {
+            id: 1,
+            name: "Client calls".to_string(),
+            color: None,
+            archived: false,
+            billable: false,
+            hourly_rate: None,
+            pinned: false,
+            sort_order: 0,
+            notification_override: NotificationOverride::default(),
+            estimated_pomodoros: None,
+        };
+        assert_eq!(
+            resolve_phase_alerts(&settings, None),
+            (true, Some("chime".to_string()))
+        );
+        assert_eq!(
+            resolve_phase_alerts(&settings, Some(&project)),
+            (true, Some("chime".to_string()))
+        );
+
+        project.notification_override.sound = Some("none".to_string());
+        project.notification_override.notify = Some(false);
+        assert_eq!(
+            resolve_phase_alerts(&settings, Some(&project)),
+            (false, None)
+        );
+
+        let quiet = AppSettings {
+            sound_enabled: false,
+            ..sample_settings()
+        };
+        project.notification_override = NotificationOverride {
+            notify: None,
+            sound: Some("gong".to_string()),
+        };
+        assert_eq!(
+            resolve_phase_alerts(&quiet, Some(&project)),
+            (true, Some("gong".to_string()))
+        );
+    }
+
+    #[test]
+    fn auto_archive_skips_recent_pinned_and_current_projects() {
+        let mut conn = test_conn();
+        let day = 86_400;
+        let now = 100 * day;
+        conn.execute(
+            "INSERT INTO projects (name, pinned, created_at) VALUES
+                 ('stale', 0, 0), ('recent', 0, 0), ('pinned', 1, 0), ('current', 0, 0),
+                 ('new', 0, ?1), ('split', 0, 0)",
+            params![now - day],
+        )
+        .unwrap();
+        let old = insert_focus_session(&conn, 10 * day, 1500);
+        conn.execute(
+            "UPDATE sessions SET project_id = 1 WHERE id = ?1",
+            params![old],
+        )
+        .unwrap();
+        let recent = insert_focus_session(&conn, now - 5 * day, 1500);
+        conn.execute(
+            "UPDATE sessions SET project_id = 2 WHERE id = ?1",
+            params![recent],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO session_project_splits (session_id, project_id, percent) VALUES (?1, 6, 100)",
+            params![recent],
+        )
+        .unwrap();
+
+        let archived = auto_archive_projects(&mut conn, 30, Some(4), now).unwrap();
+        let names: Vec<_> = archived.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["stale"]);
+        assert!(archived[0].archived);
+        assert!(auto_archive_projects(&mut conn, 0, None, now)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn planned_flag_is_recorded_for_focus_and_summarised() {
+        let conn = test_conn();
+        let settings = sample_settings();
+        let mut timer = TimerState::default_with_settings(&settings);
+        timer.current_planned = true;
+        let planned = record_session(&conn, &timer, true, 10_000).unwrap();
+        assert_eq!(planned.planned, Some(true));
+
+        advance_timer(&mut timer, &settings);
+        assert!(!timer.current_planned);
+        let short_break = record_session(&conn, &timer, true, 11_000).unwrap();
+        assert_eq!(short_break.planned, None);
+        advance_timer(&mut timer, &settings);
+        record_session(&conn, &timer, true, 20_000).unwrap();
+
+        assert_eq!(
+            fetch_session(&conn, planned.id).unwrap().planned,
+            Some(true)
+        );
+        let ratio = |conn: &Connection| {
+            compute_summary(conn, &settings, &AnalyticsRange::default())
+                .unwrap()
+                .planned_ratio
+        };
+        assert_eq!(ratio(&conn), Some(0.5));
+        assert_eq!(ratio(&test_conn()), None);
+    }
+
+    #[test]
+    fn plan_queue_starts_the_next_item_as_a_planned_focus() {
+        let conn = test_conn();
+        conn.execute_batch(
+            "INSERT INTO plan_items (title, project_id, tag_ids, created_at) VALUES
+                ('write', 7, '[3]', 0), ('review', NULL, '[]', 0);",
+        )
+        .unwrap();
+        let settings = sample_settings();
+        let timer = TimerState::default_with_settings(&settings);
+        let mut model = AppModel {
+            conn,
+            settings,
+            timer,
+            queued_notification: None,
+            console_detached: false,
+            records_cache: None,
+            breathing_emitted: None,
+            music_playing: None,
+            screen_dim: ScreenDim::default(),
+            db_recovery: None,
+            read_only: None,
+        };
+
+        let timer = start_next_plan_item(&mut model).unwrap();
+        assert!(timer.is_running && timer.current_planned);
+        assert_eq!(timer.current_project_id, Some(7));
+        assert_eq!(timer.current_tag_ids, vec![3]);
+        let left: Vec<_> = fetch_plan_items(&model.conn)
+            .unwrap()
+            .into_iter()
+            .map(|item| item.title)
+            .collect();
+        assert_eq!(left, ["review"]);
+        assert!(start_next_plan_item(&mut model).is_err());
+
+        model.timer = TimerState::default_with_settings(&model.settings);
+        let ad_hoc = start_timer(&mut model, None, false).unwrap();
+        assert!(!ad_hoc.current_planned);
+    }
+
+    #[test]
+    fn remote_detach_pauses_or_counts_interruption() {
+        let ioreg = r#"  | "IOConsoleUsers" = ({"kCGSSessionOnConsoleKey"=Yes,"kCGSSessionUserNameKey"="bob"},{"kCGSSessionOnConsoleKey"=No,"kCGSSessionUserNameKey"="alice"})"#;
+        assert_eq!(console_user_detached(ioreg, "alice"), Some(true));
+        assert_eq!(console_user_detached(ioreg, "bob"), Some(false));
+        assert_eq!(console_user_detached(ioreg, "carol"), None);
+
+        let mut settings = sample_settings();
+        settings.max_pauses_per_focus = 1;
+        let mut timer = TimerState::default_with_settings(&settings);
+        timer.is_running = true;
+        timer.interruptions = 1;
+
+        assert!(!apply_remote_detach(&mut timer, &settings, 100));
+        settings.remote_detach_action = "interruption".to_string();
+        assert!(apply_remote_detach(&mut timer, &settings, 100));
+        assert!(timer.is_running);
+        assert_eq!(timer.interruptions, 2);
+
+        settings.remote_detach_action = "pause".to_string();
+        assert!(apply_remote_detach(&mut timer, &settings, 100));
+        assert!(!timer.is_running);
+        assert!(timer.pause_budget_exceeded);
+        // Without a console probe the setting cannot take effect, so it is not kept.
+        let kept = normalize_settings(settings).remote_detach_action;
+        assert_eq!(kept == "pause", REMOTE_DETACH_SUPPORTED);
+    }
+
+    #[test]
+    fn hourly_and_weekday_distributions_bucket_local_time() {
+        let conn = test_conn();
+        let at = |hour| {
+            Local
+                .with_ymd_and_hms(2024, 3, 4, hour, 10, 0)
+                .single()
+                .expect("local time")
+                .timestamp()
+        };
+        insert_focus_session(&conn, at(9) + 1500, 1500);
+        insert_focus_session(&conn, at(9) + 3000, 1200);
+        let split = insert_focus_session(&conn, at(14) + 1000, 1000);
+        conn.execute(
+            "INSERT INTO projects (name, archived, created_at) VALUES ('A', 0, 0), ('B', 0, 0)",
+            [],
+        )
+        .expect("projects");
+        conn.execute(
+            "INSERT INTO session_project_splits (session_id, project_id, percent) VALUES (?1, 1, 40), (?1, 2, 60)",
+            params![split],
+        )
+        .expect("splits");
+
+        let all = fetch_hourly_distribution(&conn, &AnalyticsRange::default()).expect("hourly");
+        assert_eq!(all.len(), 24);
+        assert_eq!(
+            (all[9].focus_seconds, all[9].completed_pomodoros),
+            (2700, 2)
+        );
+        assert_eq!(all[14].focus_seconds, 1000);
+        assert_eq!(all[3].focus_seconds, 0);
+
+        let range = AnalyticsRange {
+            project_id: Some(1),
+            ..Default::default()
+        };
+        let project = fetch_hourly_distribution(&conn, &range).expect("hourly");
+        assert_eq!(project[14].focus_seconds, 400);
+        assert_eq!(project[9].focus_seconds, 0);
+
+        conn.execute(
+            "UPDATE sessions SET interruptions = 2 WHERE id = ?1",
+            params![split],
+        )
+        .expect("interruptions");
+        let weekdays =
+            fetch_weekday_distribution(&conn, &AnalyticsRange::default()).expect("weekday");
+        assert_eq!(weekdays.len(), 7);
+        // 2024-03-04 was a Monday.
+        assert_eq!(
+            weekdays[1],
+            WeekdayBucket {
+                weekday: 1,
+                focus_seconds: 3700,
+                completed_pomodoros: 3,
+                interruptions: 2,
+            }
+        );
+    }
+
+    #[test]
+    fn messages_pick_plural_forms_per_language() {
+        let today = |language, count: i64| {
+            localize(
+                language,
+                Message::PomodorosToday,
+                &[("count", &count.to_string())],
+            )
+        };
+        assert_eq!(today("en", 1), "1 pomodoro today");
+        assert_eq!(today("en", 3), "3 pomodoros today");
+        assert_eq!(today("ru", 1), "Сегодня 1 помидор");
+        assert_eq!(today("ru", 3), "Сегодня 3 помидора");
+        assert_eq!(today("ru", 5), "Сегодня 5 помидоров");
+        assert_eq!(today("ru", 11), "Сегодня 11 помидоров");
+        assert_eq!(today("ru", 21), "Сегодня 21 помидор");
+        assert_eq!(today("uk", 22), "Сьогодні 22 помідори");
+
+        let template = "{n, plural, =0 {no {what}} one {# {what}} other {# {what}s}}";
+        let format = |n: &str| format_message(template, "en", &[("n", n), ("what", "day")]);
+        assert_eq!(format("0"), "no day");
+        assert_eq!(format("1"), "1 day");
+        assert_eq!(format("2"), "2 days");
+        assert_eq!(format_message("{missing} {open", "en", &[]), " {open");
+    }
+
+    #[test]
+    fn gaps_are_holes_in_work_hours_between_sessions() {
+        let gap = |started_at, ended_at| SessionGap {
+            started_at,
+            ended_at,
+            duration_sec: ended_at - started_at,
+        };
+        let busy = vec![(1_500, 2_000), (50, 200), (1_800, 2_100), (2_150, 2_300)];
+        assert_eq!(
+            find_gaps(busy.clone(), 100, 3_000, 300),
+            vec![gap(200, 1_500), gap(2_300, 3_000)]
+        );
+        assert_eq!(find_gaps(busy, 100, 3_000, 1_500), Vec::new());
+        assert_eq!(find_gaps(Vec::new(), 100, 400, 300), vec![gap(100, 400)]);
+
+        let conn = test_conn();
+        let settings = sample_settings();
+        let (nine, six) = work_hours_window("2024-03-04", 540, 1080).expect("window");
+        assert_eq!(six - nine, 9 * 3600);
+        insert_focus_session(&conn, nine + 3600, 1500);
+        let timer = TimerState::default_with_settings(&settings);
+        let gaps = detect_session_gaps(&conn, &settings, &timer, "2024-03-04", 30, six + 3600)
+            .expect("gaps");
+        assert_eq!(gaps, vec![gap(nine, nine + 2100), gap(nine + 3600, six)]);
+        assert!(detect_session_gaps(&conn, &settings, &timer, "03/04/2024", 30, six).is_err());
+    }
+
+    #[test]
+    fn timeseries_groups_by_day_week_or_month() {
+        let conn = test_conn();
+        let at = |month, day| {
+            Local
+                .with_ymd_and_hms(2024, month, day, 12, 0, 0)
+                .single()
+                .expect("local time")
+                .timestamp()
+        };
+        // Sunday 3 March, Monday 4 March, Wednesday 6 March, Monday 1 April.
+        for (month, day) in [(3, 3), (3, 4), (3, 6), (4, 1)] {
+            insert_focus_session(&conn, at(month, day), 1500);
+        }
+        let series = |granularity| {
+            let range = AnalyticsRange {
+                granularity: Some(granularity),
+                ..Default::default()
+            };
+            fetch_timeseries(&conn, &range)
+                .expect("timeseries")
+                .into_iter()
+                .map(|p| (p.date, p.completed_pomodoros))
+                .collect::<Vec<_>>()
+        };
+        let point = |date: &str, count| (date.to_string(), count);
+
+        assert_eq!(series(Granularity::Day).len(), 4);
+        assert_eq!(
+            series(Granularity::Week),
+            vec![
+                point("2024-02-26", 1),
+                point("2024-03-04", 2),
+                point("2024-04-01", 1)
+            ]
+        );
+        assert_eq!(
+            series(Granularity::Month),
+            vec![point("2024-03-01", 3), point("2024-04-01", 1)]
+        );
+    }
+
+    #[test]
+    fn remote_today_sums_todays_focus_and_names_the_project() {
+        let conn = test_conn();
+        conn.execute(
+            "INSERT INTO projects (name, created_at) VALUES ('Thesis', 0)",
+            [],
+        )
+        .unwrap();
+        let settings = sample_settings();
+        let mut timer = TimerState::default_with_settings(&settings);
+        let (day_start, day_end) = local_day_bounds(1_700_000_000);
+        assert!(day_start <= 1_700_000_000 && 1_700_000_000 < day_end);
+        let now = day_start + 3_600;
+
+        insert_focus_session(&conn, now - 60, 1500);
+        let unfinished = insert_focus_session(&conn, now - 30, 600);
+        conn.execute(
+            "UPDATE sessions SET completed = 0 WHERE id = ?1",
+            params![unfinished],
+        )
+        .unwrap();
+        insert_focus_session(&conn, day_start - 1, 1500);
+        insert_focus_session(&conn, day_end, 1500);
+        conn.execute(
+            "INSERT INTO sessions (started_at, ended_at, phase, duration_sec, completed, interruptions)
+             VALUES (?1, ?2, 'short_break', 300, 1, 0)",
+            params![now - 300, now],
+        )
+        .unwrap();
+
+        let today = remote_today(&conn, &timer, now).unwrap();
+        assert_eq!((today.completed_pomodoros, today.focus_seconds), (1, 2100));
+        assert_eq!(today.project_name, None);
+
+        timer.current_project_id = Some(1);
+        let today = remote_today(&conn, &timer, now).unwrap();
+        assert_eq!(today.project_name.as_deref(), Some("Thesis"));
+    }
+
+    #[test]
+    fn remote_guard_locks_out_after_repeated_failures() {
+        let mut guard = RemoteGuard::default();
+        let ip: IpAddr = "192.168.1.20".parse().unwrap();
+        let other: IpAddr = "192.168.1.21".parse().unwrap();
+
+        for attempt in 0..REMOTE_LOCKOUT_FAILURES {
+            assert_eq!(guard.admit(ip, "GET /api/state", 100), Ok(()));
+            guard.record_failure(ip, "GET /api/state", 100 + attempt as i64);
+        }
+        let until = 100 + REMOTE_LOCKOUT_FAILURES as i64 - 1 + REMOTE_LOCKOUT_SECONDS;
+        assert_eq!(
+            guard.admit(ip, "GET /api/state", 200),
+            Err(RemoteRejection::LockedOut { until })
+        );
+        assert_eq!(guard.admit(other, "GET /api/state", 200), Ok(()));
+        assert_eq!(guard.admit(ip, "GET /api/state", until), Ok(()));
+
+        let log = guard.snapshot(200);
+        assert_eq!(log.unauthorized_total, REMOTE_LOCKOUT_FAILURES as u64);
+        assert_eq!(log.events[0].kind, "locked_out");
+        assert_eq!(log.events[1].kind, "unauthorized");
+        assert_eq!(
+            log.lockouts,
+            vec![RemoteLockout {
+                ip: "192.168.1.20".to_string(),
+                until
+            }]
+        );
+
+        // A success resets the count, so typos spread over time never lock anyone out.
+        guard.record_failure(other, "GET /api/state", 300);
+        guard.record_success(other);
+        for _ in 1..REMOTE_LOCKOUT_FAILURES {
+            guard.record_failure(other, "GET /api/state", 301);
+        }
+        assert_eq!(guard.admit(other, "GET /api/state", 302), Ok(()));
+    }
+
+    #[test]
+    fn remote_guard_rate_limits_per_window() {
+        let mut guard = RemoteGuard::default();
+        let ip: IpAddr = "10.0.0.5".parse().unwrap();
+        for _ in 0..REMOTE_RATE_LIMIT {
+            assert_eq!(guard.admit(ip, "GET /api/state", 1_000), Ok(()));
+        }
+        assert_eq!(
+            guard.admit(ip, "GET /api/state", 1_010),
+            Err(RemoteRejection::RateLimited)
+        );
+        assert_eq!(
+            guard.admit(ip, "GET /api/state", 1_020),
+            Err(RemoteRejection::RateLimited)
+        );
+        let log = guard.snapshot(1_020);
+        assert_eq!(log.rate_limited_total, 2);
+        assert_eq!(log.events.len(), 1);
+        assert_eq!(
+            guard.admit(ip, "GET /api/state", 1_000 + REMOTE_RATE_WINDOW_SECONDS),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn remote_guard_buckets_ipv6_by_prefix_and_stays_bounded() {
+        let key = |ip: &str| remote_client_key(ip.parse().unwrap());
+        assert_eq!(key("2001:db8:1:2::1"), key("2001:db8:1:2:ffff::9"));
+        assert_ne!(key("2001:db8:1:2::1"), key("2001:db8:1:3::1"));
+        assert_eq!(key("::ffff:192.168.1.20"), key("192.168.1.20"));
+
+        let mut guard = RemoteGuard::default();
+        for n in 0..REMOTE_RATE_LIMIT {
+            let ip: IpAddr = format!("2001:db8::{:x}", n + 1).parse().unwrap();
+            assert_eq!(guard.admit(ip, "GET /api/state", 1_000), Ok(()));
+        }
+        assert_eq!(
+            guard.admit("2001:db8::beef".parse().unwrap(), "GET /api/state", 1_000),
+            Err(RemoteRejection::RateLimited)
+        );
+
+        let mut guard = RemoteGuard::default();
+        for n in 0..REMOTE_GUARD_MAX_CLIENTS as u32 + 10 {
+            let ip = IpAddr::V4(std::net::Ipv4Addr::from(0x0a00_0000 + n));
+            guard.record_failure(ip, "GET /api/state", 1_000);
+        }
+        assert_eq!(guard.clients.len(), REMOTE_GUARD_MAX_CLIENTS);
+    }
+
+    #[test]
+    fn remote_metrics_average_latency_and_cap_routes() {
+        let mut metrics = BTreeMap::new();
+        record_remote_request(&mut metrics, "GET /api/state", Duration::from_millis(2));
+        record_remote_request(&mut metrics, "GET /api/state", Duration::from_millis(4));
+        record_remote_request(&mut metrics, "POST /api/toggle", Duration::from_millis(10));
+
+        let stats = endpoint_stats(&metrics);
+        assert_eq!(stats[0].route, "GET /api/state");
+        assert_eq!(stats[0].requests, 2);
+        assert!((stats[0].avg_latency_ms - 3.0).abs() < 1e-9);
+        assert!((stats[0].max_latency_ms - 4.0).abs() < 1e-9);
+        assert_eq!(stats[1].requests, 1);
+
+        for i in 0..100 {
+            record_remote_request(&mut metrics, &format!("GET /probe{i}"), Duration::ZERO);
+        }
+        assert_eq!(metrics.len(), REMOTE_METRICS_MAX_ROUTES);
+        assert_eq!(
+            metrics["other"].requests,
+            100 - (REMOTE_METRICS_MAX_ROUTES as u64 - 3)
+        );
+        record_remote_request(&mut metrics, "GET /api/state", Duration::ZERO);
+        assert_eq!(metrics["GET /api/state"].requests, 3);
+    }
+
+    #[test]
+    fn self_check_finds_and_repair_fixes_integrity_problems() {
+        let mut conn = test_conn();
+        let good = insert_focus_session(&conn, 10_000, 1500);
+        let long = insert_focus_session(&conn, 20_000, 1500);
+        let orphan_project = insert_focus_session(&conn, 30_000, 1500);
+        conn.execute_batch(&format!(
+            "PRAGMA foreign_keys = OFF;
+             UPDATE sessions SET duration_sec = 9000 WHERE id = {long};
+             UPDATE sessions SET project_id = 77 WHERE id = {orphan_project};
+             INSERT INTO tags (name, created_at) VALUES ('kept', 0);
+             INSERT INTO session_tags (session_id, tag_id) VALUES ({good}, 1), ({good}, 5), (999, 1);
+             PRAGMA foreign_keys = ON;"
+        ))
+        .expect("corrupt");
+
+        let report = check_database(&conn).expect("check");
+        assert_eq!(
+            report,
+            DbCheckReport {
+                orphaned_session_tags: 2,
+                impossible_duration_session_ids: vec![long],
+                dangling_project_session_ids: vec![orphan_project],
+            }
+        );
+
+        assert_eq!(repair_database(&mut conn).expect("repair"), report);
+        assert!(check_database(&conn).expect("check").is_clean());
+        assert_eq!(fetch_session(&conn, long).expect("long").duration_sec, 1500);
+        assert_eq!(fetch_session(&conn, good).expect("good").tag_ids, vec![1]);
+        assert_eq!(
+            fetch_session_revisions(&conn, long)
+                .expect("revisions")
+                .len(),
+            1
+        );
     }
-}
 
-fn remote_server_loop(app: AppHandle, listener: TcpListener, stop: Arc<AtomicBool>) {
-    while stop.load(Ordering::SeqCst) {
-        match listener.accept() {
-            Ok((stream, _)) => {
-                remote_handle_connection(&app, stream);
-            }
-            Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
-                thread::sleep(Duration::from_millis(50));
+    #[test]
+    fn reload_model_picks_up_external_settings_and_timer() {
+        let conn = test_conn();
+        let settings = sample_settings();
+        let timer = TimerState::default_with_settings(&settings);
+        let mut model = AppModel {
+            conn,
+            settings,
+            timer,
+            queued_notification: Some("stale".to_string()),
+            console_detached: false,
+            records_cache: None,
+            breathing_emitted: None,
+            music_playing: None,
+            screen_dim: ScreenDim::default(),
+            db_recovery: None,
+            read_only: None,
+        };
+
+        let mut external = model.settings.clone();
+        external.focus_min = 50;
+        external.long_break_every = 99;
+        save_json_setting(&model.conn, APP_SETTINGS_KEY, &external).expect("settings");
+        let mut external_timer = TimerState::default_with_settings(&external);
+        external_timer.interruptions = 3;
+        save_timer_state(&model.conn, &external_timer).expect("timer");
+
+        let integrity = reload_model(&mut model).expect("reload");
+        assert!(integrity.is_clean());
+        assert_eq!(model.settings.focus_min, 50);
+        assert_eq!(model.settings.long_break_every, 10);
+        assert_eq!(model.timer.interruptions, 3);
+        assert_eq!(model.timer.phase_total_seconds, 50 * 60);
+        assert!(model.queued_notification.is_none());
+    }
+
+    #[test]
+    fn data_watch_reports_only_other_connections_writes() {
+        let path = std::env::temp_dir().join(format!("pomodoro-watch-{}.db", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let app = Connection::open(&path).expect("open app db");
+        init_database(&app).expect("init schema");
+        let cli = Connection::open(&path).expect("open cli db");
+
+        let mut watch = DataWatch::default();
+        poll_external_changes(&app, &mut watch).expect("prime");
+        insert_focus_session(&app, 1_700_000_000, 1500);
+        save_timer_state(&app, &TimerState::default_with_settings(&sample_settings()))
+            .expect("timer");
+        assert!(poll_external_changes(&app, &mut watch)
+            .expect("own")
+            .is_empty());
+
+        cli.execute(
+            "INSERT INTO projects (name, archived, created_at) VALUES ('cli', 0, 0)",
+            [],
+        )
+        .expect("project");
+        cli.execute(
+            "INSERT INTO settings (key, value) VALUES ('app_settings', '{}')
+             ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+            [],
+        )
+        .expect("settings");
+        assert_eq!(
+            poll_external_changes(&app, &mut watch).expect("external"),
+            vec!["projects".to_string(), "settings".to_string()]
+        );
+        assert!(poll_external_changes(&app, &mut watch)
+            .expect("quiet")
+            .is_empty());
+
+        drop((app, cli));
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn records_find_bests_and_refresh_after_session_writes() {
+        let conn = test_conn();
+        let at = |day| {
+            Local
+                .with_ymd_and_hms(2024, 5, day, 15, 0, 0)
+                .single()
+                .expect("local time")
+                .timestamp()
+        };
+        for (day, duration) in [
+            (1, 1500),
+            (2, 1500),
+            (2, 1500),
+            (3, 600),
+            (7, 3000),
+            (8, 100),
+        ] {
+            insert_focus_session(&conn, at(day), duration);
+        }
+        conn.execute(
+            "UPDATE sessions SET interruptions = 1 WHERE duration_sec = 3000",
+            [],
+        )
+        .expect("interrupt");
+        let settings = sample_settings();
+        let timer = TimerState::default_with_settings(&settings);
+        let mut model = AppModel {
+            conn,
+            settings,
+            timer,
+            queued_notification: None,
+            console_detached: false,
+            records_cache: None,
+            breathing_emitted: None,
+            music_playing: None,
+            screen_dim: ScreenDim::default(),
+            db_recovery: None,
+            read_only: None,
+        };
+
+        let records = cached_records(&mut model).expect("records");
+        let day = |date: &str, value| {
+            Some(DayRecord {
+                date: date.to_string(),
+                value,
+            })
+        };
+        assert_eq!(records.longest_daily_focus, day("2024-05-02", 3000));
+        assert_eq!(records.most_pomodoros_day, day("2024-05-02", 2));
+        assert_eq!(records.longest_focus_block, day("2024-05-01", 1500));
+        assert_eq!(
+            records.longest_streak,
+            Some(StreakRecord {
+                days: 3,
+                start_date: "2024-05-01".to_string(),
+                end_date: "2024-05-03".to_string(),
+            })
+        );
+
+        insert_focus_session(&model.conn, at(9), 5000);
+        let records = cached_records(&mut model).expect("records");
+        assert_eq!(records.longest_focus_block, day("2024-05-09", 5000));
+        assert_eq!(records.longest_streak.map(|s| s.days), Some(3));
+
+        // Frozen days bridge the gap like they do for the current streak.
+        let frozen = ["2024-05-04", "2024-05-05", "2024-05-06"].map(str::to_string);
+        save_streak_freezes(&model.conn, &frozen, 0).unwrap();
+        let records = cached_records(&mut model).expect("records");
+        assert_eq!(
+            records.longest_streak,
+            Some(StreakRecord {
+                days: 6,
+                start_date: "2024-05-01".to_string(),
+                end_date: "2024-05-09".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn days_off_import_from_ics_and_bridge_streaks() {
+        let ics = "BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nDTSTART;VALUE=DATE:20241224\r\nDTEND;VALUE=DATE:20241227\r\nSUMMARY:Winter\r\n  break\\, home\r\nEND:VEVENT\r\nBEGIN:VEVENT\r\nDTSTART:20250101T090000Z\r\nDTEND:20250101T170000Z\r\nSUMMARY:New Year\r\nEND:VEVENT\r\nBEGIN:VEVENT\r\nSUMMARY:No date\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n";
+        let days = parse_ics_days_off(ics);
+        let dates: Vec<&str> = days.iter().map(|d| d.day.as_str()).collect();
+        assert_eq!(
+            dates,
+            ["2024-12-24", "2024-12-25", "2024-12-26", "2025-01-01"]
+        );
+        assert_eq!(days[0].name.as_deref(), Some("Winter break, home"));
+
+        let mut conn = test_conn();
+        conn.execute(
+            "INSERT INTO days_off (day, name, source) VALUES ('2024-12-25', 'Christmas', 'manual')",
+            [],
+        )
+        .expect("manual day");
+        let result = import_days_off(&mut conn, &days).expect("import");
+        assert_eq!((result.imported, result.skipped), (3, 1));
+        let kept = fetch_days_off(&conn, Some("2024-12-25"), Some("2024-12-25")).expect("list");
+        assert_eq!(kept[0].source, "manual");
+
+        let today = Local::now().date_naive();
+        let focus_on = |days_ago: u64| SessionRecord {
+            id: days_ago as i64,
+            started_at: 0,
+            ended_at: Local
+                .from_local_datetime(
+                    &(today - chrono::Days::new(days_ago))
+                        .and_hms_opt(12, 0, 0)
+                        .expect("noon"),
+                )
+                .earliest()
+                .expect("local noon")
+                .timestamp(),
+            phase: TimerPhase::Focus,
+            duration_sec: 1500,
+            completed: true,
+            interruptions: 0,
+            project_id: None,
+            tag_ids: vec![],
+            notes: None,
+            extended_sec: 0,
+            project_splits: vec![],
+            quality: None,
+            planned: None,
+        };
+        let sessions = vec![focus_on(0), focus_on(3)];
+        let off: HashSet<String> = [1, 2]
+            .iter()
+            .map(|n| {
+                (today - chrono::Days::new(*n))
+                    .format("%Y-%m-%d")
+                    .to_string()
+            })
+            .collect();
+        let rules = StreakRules::default();
+        assert_eq!(
+            calculate_streak_days(
+                &focus_days(&sessions),
+                &HashSet::new(),
+                &HashSet::new(),
+                &rules,
+                None
+            )
+            .days,
+            1
+        );
+        assert_eq!(
+            calculate_streak_days(&focus_days(&sessions), &off, &HashSet::new(), &rules, None).days,
+            2
+        );
+    }
+
+    #[test]
+    fn streak_rules_apply_threshold_weekends_and_freezes() {
+        let today = Local::now().date_naive();
+        let focus = |days_ago: u64, completed: bool| SessionRecord {
+            id: 0,
+            started_at: 0,
+            ended_at: Local
+                .from_local_datetime(
+                    &(today - chrono::Days::new(days_ago))
+                        .and_hms_opt(12, 0, 0)
+                        .expect("noon"),
+                )
+                .earliest()
+                .expect("local noon")
+                .timestamp(),
+            phase: TimerPhase::Focus,
+            duration_sec: 1500,
+            completed,
+            interruptions: 0,
+            project_id: None,
+            tag_ids: vec![],
+            notes: None,
+            extended_sec: 0,
+            project_splits: vec![],
+            quality: None,
+            planned: None,
+        };
+        let none = HashSet::new();
+
+        let sessions = vec![
+            focus(0, true),
+            focus(0, true),
+            focus(1, true),
+            focus(2, true),
+            focus(2, true),
+        ];
+        let mut rules = StreakRules {
+            min_pomodoros: 2,
+            ..Default::default()
+        };
+        assert_eq!(
+            calculate_streak_days(
+                &focus_days(&sessions),
+                &none,
+                &none,
+                &StreakRules::default(),
+                None
+            )
+            .days,
+            3
+        );
+        assert_eq!(
+            calculate_streak_days(&focus_days(&sessions), &none, &none, &rules, None).days,
+            1
+        );
+        rules.freeze_tokens = 1;
+        let walk = calculate_streak_days(&focus_days(&sessions), &none, &none, &rules, None);
+        let yesterday = (today - chrono::Days::new(1))
+            .format("%Y-%m-%d")
+            .to_string();
+        assert_eq!(walk.days, 2);
+        assert_eq!(walk.newly_frozen, vec![yesterday.clone()]);
+        // Once saved, the spent token keeps bridging its day but is not there to spend again.
+        let frozen: HashSet<String> = [yesterday].into_iter().collect();
+        assert_eq!(
+            calculate_streak_days(&focus_days(&sessions), &none, &frozen, &rules, None),
+            StreakCount {
+                days: 2,
+                newly_frozen: Vec::new(),
             }
-            Err(_) => {
-                thread::sleep(Duration::from_millis(200));
+        );
+        // An unfinished today is no miss, and the walk stops where the data starts.
+        let earlier = vec![focus(1, true), focus(1, true)];
+        let since = Some(today - chrono::Days::new(1));
+        assert_eq!(
+            calculate_streak_days(&focus_days(&earlier), &none, &none, &rules, since),
+            StreakCount {
+                days: 1,
+                newly_frozen: Vec::new(),
             }
+        );
+
+        let is_weekend = |days_ago: u64| {
+            matches!(
+                (today - chrono::Days::new(days_ago)).weekday(),
+                chrono::Weekday::Sat | chrono::Weekday::Sun
+            )
+        };
+        let weekdays: Vec<u64> = (0..14).filter(|d| !is_weekend(*d)).collect();
+        let sessions: Vec<SessionRecord> = weekdays.iter().map(|d| focus(*d, false)).collect();
+        let rules = StreakRules {
+            skip_weekends: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            calculate_streak_days(&focus_days(&sessions), &none, &none, &rules, None).days,
+            weekdays.len() as i64
+        );
+    }
+
+    #[test]
+    fn streak_freezes_are_spent_on_the_full_history_only() {
+        let conn = test_conn();
+        let mut settings = sample_settings();
+        settings.streak_freeze_tokens = 1;
+        let noon = |days_ago: u64| {
+            let date = Local::now().date_naive() - chrono::Days::new(days_ago);
+            Local
+                .from_local_datetime(&date.and_hms_opt(12, 0, 0).unwrap())
+                .earliest()
+                .unwrap()
+                .timestamp()
+        };
+        insert_focus_session(&conn, noon(0), 1500);
+        insert_focus_session(&conn, noon(2), 1500);
+
+        // A range that ended before yesterday reads the streak without spending a token.
+        let past = AnalyticsRange {
+            to: Some(noon(2)),
+            ..Default::default()
+        };
+        compute_summary(&conn, &settings, &past).unwrap();
+        assert!(fetch_streak_freezes(&conn).unwrap().is_empty());
+
+        spend_streak_freezes(&conn, &settings, noon(0)).unwrap();
+        let yesterday = (Local::now().date_naive() - chrono::Days::new(1))
+            .format("%Y-%m-%d")
+            .to_string();
+        assert_eq!(
+            fetch_streak_freezes(&conn).unwrap(),
+            [yesterday].into_iter().collect::<HashSet<_>>()
+        );
+        let summary = compute_summary(&conn, &settings, &AnalyticsRange::default()).unwrap();
+        assert_eq!(summary.streak_days, 2);
+    }
+
+    #[test]
+    fn session_webhook_payload_resolves_names() {
+        let conn = test_conn();
+        conn.execute_batch(
+            "INSERT INTO projects (name, archived, created_at) VALUES ('Thesis', 0, 0);
+             INSERT INTO tags (name, created_at) VALUES ('deep', 0), ('reading', 0);",
+        )
+        .expect("names");
+        let id = insert_focus_session(&conn, 1_700_000_000, 1500);
+        conn.execute_batch(&format!(
+            "UPDATE sessions SET project_id = 1 WHERE id = {id};
+             INSERT INTO session_tags (session_id, tag_id) VALUES ({id}, 2), ({id}, 1);"
+        ))
+        .expect("links");
+
+        let session = fetch_session(&conn, id).expect("session");
+        let payload = session_webhook_payload(&conn, &session).expect("payload");
+        assert_eq!(payload.event, "session.completed");
+        assert_eq!(payload.project_name.as_deref(), Some("Thesis"));
+        assert_eq!(payload.tag_names, ["deep", "reading"]);
+        let json = serde_json::to_value(&payload).expect("json");
+        assert_eq!(json["session"]["durationSec"], 1500);
+        assert_eq!(json["tagNames"][1], "reading");
+
+        let mut settings = sample_settings();
+        settings.session_webhook_url = " ftp://example.com ".to_string();
+        assert_eq!(normalize_settings(settings).session_webhook_url, "");
+    }
+
+    #[test]
+    fn breathing_steps_follow_the_break_clock() {
+        let step = |timings, elapsed| {
+            breathing_step_at(timings, elapsed, 1_000).map(|s| (s.step, s.ends_at, s.cycle))
+        };
+        let name = |n: &str| n.to_string();
+        assert_eq!(step([4, 4, 4, 4], 0), Some((name("inhale"), 1_004, 0)));
+        assert_eq!(step([4, 4, 4, 4], 6), Some((name("hold"), 1_002, 0)));
+        assert_eq!(step([4, 4, 4, 4], 17), Some((name("inhale"), 1_003, 1)));
+        assert_eq!(step([5, 0, 5, 0], 5), Some((name("exhale"), 1_005, 0)));
+
+        let mut settings = sample_settings();
+        let mut timer = TimerState::default_with_settings(&settings);
+        timer.phase = TimerPhase::ShortBreak;
+        timer.phase_total_seconds = 300;
+        timer.is_running = true;
+        timer.target_ends_at = Some(1_000 + 290);
+        assert_eq!(current_breathing_step(&settings, &timer, 1_000), None);
+
+        settings.breathing_pattern = "custom".to_string();
+        settings.breathing_custom = vec![0, 99, 3];
+        let settings = normalize_settings(settings);
+        assert_eq!(settings.breathing_custom, vec![4, 4, 4, 4]);
+        let current = current_breathing_step(&settings, &timer, 1_000).expect("step");
+        assert_eq!((current.step.as_str(), current.ends_at), ("exhale", 1_002));
+    }
+
+    #[test]
+    fn music_follows_focus_and_walks_the_playlist() {
+        let conn = test_conn();
+        conn.execute_batch(
+            "INSERT INTO music_tracks (path, title, created_at) VALUES
+                ('/m/a.mp3', 'a', 0), ('/m/b.flac', 'b', 0), ('/m/c.ogg', 'c', 0);",
+        )
+        .expect("tracks");
+        let mut settings = sample_settings();
+        settings.music_enabled = true;
+        settings.music_shuffle = false;
+        let timer = TimerState::default_with_settings(&settings);
+        let mut model = AppModel {
+            conn,
+            settings,
+            timer,
+            queued_notification: None,
+            console_detached: false,
+            records_cache: None,
+            breathing_emitted: None,
+            music_playing: None,
+            screen_dim: ScreenDim::default(),
+            db_recovery: None,
+            read_only: None,
+        };
+        let played = |event: Option<MusicEvent>| match event {
+            Some(MusicEvent::Play(play)) => Some(play.track.title),
+            _ => None,
+        };
+
+        assert_eq!(music_sync(&mut model).expect("idle"), None);
+        model.timer.is_running = true;
+        assert_eq!(
+            played(music_sync(&mut model).expect("start")),
+            Some("a".to_string())
+        );
+        assert_eq!(music_sync(&mut model).expect("steady"), None);
+        assert_eq!(
+            played(music_play_next(&mut model).expect("next")),
+            Some("b".to_string())
+        );
+        assert_eq!(
+            played(music_play_next(&mut model).expect("next")),
+            Some("c".to_string())
+        );
+        assert_eq!(
+            played(music_play_next(&mut model).expect("wrap")),
+            Some("a".to_string())
+        );
+
+        model.timer.phase = TimerPhase::ShortBreak;
+        assert_eq!(
+            music_sync(&mut model).expect("break"),
+            Some(MusicEvent::Stop)
+        );
+        assert_eq!(model.music_playing, None);
+
+        let tracks = fetch_music_tracks(&model.conn).expect("tracks");
+        for _ in 0..20 {
+            let next = pick_next_track(&tracks, Some(2), true).expect("shuffled");
+            assert_ne!(next.id, 2);
         }
+        assert_eq!(music_mime("/x/Song.MP3"), Some("audio/mpeg"));
+        assert_eq!(music_mime("/x/notes.txt"), None);
     }
-}
 
-fn spawn_timer_worker(app: AppHandle) {
-    thread::spawn(move || loop {
-        thread::sleep(Duration::from_secs(1));
+    #[test]
+    fn interruption_analytics_rates_hours_weekdays_and_trend() {
+        let conn = test_conn();
+        let at = |day, hour| {
+            Local
+                .with_ymd_and_hms(2024, 3, day, hour, 10, 0)
+                .single()
+                .expect("local time")
+                .timestamp()
+        };
+        let monday = insert_focus_session(&conn, at(4, 9) + 1500, 1500);
+        insert_focus_session(&conn, at(4, 9) + 3000, 1200);
+        let tuesday = insert_focus_session(&conn, at(5, 15) + 1500, 1500);
+        conn.execute(
+            "UPDATE sessions SET interruptions = 3 WHERE id = ?1",
+            params![monday],
+        )
+        .expect("monday");
+        conn.execute(
+            "UPDATE sessions SET interruptions = 1 WHERE id = ?1",
+            params![tuesday],
+        )
+        .expect("tuesday");
 
-        let mut should_emit = false;
-        let mut emit_state: Option<TimerState> = None;
-        let mut session_event: Option<SessionRecord> = None;
-        let mut phase_event: Option<PhaseCompletedEvent> = None;
+        let report =
+            fetch_interruption_analytics(&conn, &AnalyticsRange::default()).expect("report");
+        assert_eq!(report.by_hour.len(), 24);
+        assert_eq!(
+            report.by_hour[9],
+            InterruptionBucket {
+                bucket: 9,
+                interruptions: 3,
+                completed_pomodoros: 2,
+                per_pomodoro: 1.5,
+            }
+        );
+        assert_eq!(report.by_hour[15].per_pomodoro, 1.0);
+        assert_eq!(report.by_hour[0].per_pomodoro, 0.0);
+        assert_eq!(report.by_weekday[1].interruptions, 3);
+        assert_eq!(report.by_weekday[2].interruptions, 1);
+        assert!((report.average_per_pomodoro - 4.0 / 3.0).abs() < 1e-9);
+        let trend: Vec<(&str, i64)> = report
+            .trend
+            .iter()
+            .map(|p| (p.date.as_str(), p.interruptions))
+            .collect();
+        assert_eq!(trend, vec![("2024-03-04", 3), ("2024-03-05", 1)]);
+    }
 
-        {
-            let state = app.state::<AppState>();
-            let lock = state.model.lock();
-            let mut model = match lock {
-                Ok(guard) => guard,
-                Err(_) => continue,
-            };
+    #[test]
+    fn screen_dim_lowers_only_brighter_displays_and_restores_once() {
+        let mut settings = sample_settings();
+        settings.break_dim_enabled = true;
+        let mut timer = TimerState::default_with_settings(&settings);
+        timer.is_running = true;
+        assert!(!wants_screen_dim(&settings, &timer));
+        timer.phase = TimerPhase::ShortBreak;
+        assert!(wants_screen_dim(&settings, &timer));
 
-            if !model.timer.is_running {
-                continue;
+        let written = std::cell::RefCell::new(Vec::new());
+        let write = |level| {
+            written.borrow_mut().push(level);
+            true
+        };
+        let dimmed = screen_dim_step(ScreenDim::default(), true, 30, || Some(80), write);
+        assert_eq!(
+            dimmed,
+            ScreenDim {
+                active: true,
+                restore: Some(80)
             }
+        );
+        let steady = screen_dim_step(dimmed, true, 30, || panic!("probed twice"), write);
+        assert_eq!(steady, dimmed);
+        let restored = screen_dim_step(steady, false, 30, || None, write);
+        assert_eq!(restored, ScreenDim::default());
+        assert_eq!(*written.borrow(), vec![30, 80]);
 
-            let before = model.timer.remaining_seconds;
-            refresh_remaining(&mut model.timer);
+        // Already dimmer than the target, or no readable display: nothing to restore.
+        let dark = screen_dim_step(ScreenDim::default(), true, 30, || Some(20), write);
+        assert_eq!(dark.restore, None);
+        let unsupported = screen_dim_step(ScreenDim::default(), true, 30, || None, write);
+        assert!(unsupported.active);
+        screen_dim_step(unsupported, false, 30, || None, write);
+        assert_eq!(*written.borrow(), vec![30, 80]);
 
-            if model.timer.remaining_seconds <= 0 {
-                if let Ok((session, phase, timer)) = complete_and_advance(&app, &mut model, true) {
-                    session_event = Some(session);
-                    phase_event = Some(phase);
-                    emit_state = Some(timer);
-                    should_emit = true;
+        // The level to restore outlives the process.
+        let conn = test_conn();
+        assert_eq!(load_screen_dim(&conn), ScreenDim::default());
+        let mut model = AppModel {
+            conn,
+            settings,
+            timer,
+            queued_notification: None,
+            console_detached: false,
+            records_cache: None,
+            breathing_emitted: None,
+            music_playing: None,
+            screen_dim: ScreenDim::default(),
+            db_recovery: None,
+            read_only: None,
+        };
+        assert_eq!(
+            screen_dim_job(&model),
+            Some((ScreenDim::default(), true, model.settings.break_dim_percent))
+        );
+        set_screen_dim(&mut model, dimmed);
+        assert_eq!(screen_dim_job(&model), None);
+        assert_eq!(load_screen_dim(&model.conn), dimmed);
+        set_screen_dim(&mut model, ScreenDim::default());
+        assert_eq!(load_screen_dim(&model.conn), ScreenDim::default());
+    }
+
+    #[test]
+    fn break_compliance_separates_taken_cut_short_and_skipped() {
+        let conn = test_conn();
+        let insert_break = |ended_at: i64, duration: i64, completed: bool| {
+            conn.execute(
+                "INSERT INTO sessions (started_at, ended_at, phase, duration_sec, completed, interruptions, planned_sec)
+                 VALUES (?1, ?2, 'short_break', ?3, ?4, 0, 300)",
+                params![ended_at - duration, ended_at, duration, completed as i64],
+            )
+            .expect("break");
+        };
+        insert_focus_session(&conn, 1_000, 1500);
+        insert_break(2_000, 300, true);
+        insert_break(3_000, 300, true);
+        insert_break(4_000, 120, false);
+        insert_break(5_000, 5, false);
+
+        let compliance =
+            fetch_break_compliance(&conn, &AnalyticsRange::default()).expect("compliance");
+        assert_eq!(
+            (
+                compliance.breaks,
+                compliance.taken,
+                compliance.cut_short,
+                compliance.skipped
+            ),
+            (4, 2, 1, 1)
+        );
+        assert_eq!(compliance.compliance_rate, Some(0.5));
+        assert_eq!(compliance.taken_ratio, Some(725.0 / 1200.0));
+
+        // A project filter narrows focus totals, not breaks.
+        let range = AnalyticsRange {
+            from: Some(3_500),
+            project_id: Some(7),
+            ..Default::default()
+        };
+        let late = fetch_break_compliance(&conn, &range).expect("compliance");
+        assert_eq!((late.breaks, late.cut_short, late.skipped), (2, 1, 1));
+        assert_eq!(late.compliance_rate, Some(0.0));
+        assert_eq!(
+            fetch_break_compliance(
+                &conn,
+                &AnalyticsRange {
+                    from: Some(9_000),
+                    ..Default::default()
                 }
-            } else if model.timer.remaining_seconds != before {
-                let _ = save_timer_state(&model.conn, &model.timer);
-                emit_state = Some(model.timer.clone());
-                should_emit = true;
-            }
-        }
+            )
+            .expect("empty"),
+            BreakCompliance::default()
+        );
+    }
 
-        if should_emit {
-            if let Some(session) = session_event {
-                let _ = app.emit("session://completed", &session);
-            }
-            if let Some(phase) = phase_event {
-                let _ = app.emit("timer://phase-completed", &phase);
+    #[test]
+    fn tailscale_status_advertises_magicdns_url() {
+        let status = serde_json::json!({
+            "BackendState": "Running",
+            "Self": {
+                "DNSName": "laptop.tail1234.ts.net.",
+                "TailscaleIPs": ["fd7a:115c:a1e0::1", "100.101.102.103"]
             }
-            if let Some(timer) = emit_state {
-                emit_timer_state(&app, &timer);
+        });
+        let host = parse_tailscale_status(&status).expect("host");
+        assert_eq!(
+            host,
+            TailscaleHost {
+                dns_name: Some("laptop.tail1234.ts.net".to_string()),
+                ip: Some("100.101.102.103".to_string()),
             }
+        );
+        let stopped = serde_json::json!({"BackendState": "Stopped", "Self": status["Self"]});
+        assert_eq!(parse_tailscale_status(&stopped), None);
+
+        let mut settings = sample_settings();
+        settings.remote_control_port = 4545;
+        let lan_ips = vec!["192.168.1.5".to_string(), "2001:db8::5".to_string()];
+        let info = remote_discovery(&settings, lan_ips, Some(host));
+        assert_eq!(info.lan_url.as_deref(), Some("http://192.168.1.5:4545/"));
+        assert_eq!(
+            info.lan_urls,
+            vec!["http://192.168.1.5:4545/", "http://[2001:db8::5]:4545/"]
+        );
+        assert_eq!(
+            info.tailscale_url.as_deref(),
+            Some("http://laptop.tail1234.ts.net:4545/")
+        );
+        let ip_only = TailscaleHost {
+            dns_name: None,
+            ip: Some("100.64.0.9".to_string()),
+        };
+        let info = remote_discovery(&settings, Vec::new(), Some(ip_only));
+        assert_eq!(
+            info.tailscale_url.as_deref(),
+            Some("http://100.64.0.9:4545/?token=tok")
+        );
+        assert_eq!(
+            remote_discovery(&settings, Vec::new(), None).tailscale_url,
+            None
+        );
+    }
+
+    #[test]
+    fn exclusion_rules_hide_projects_and_tags_unless_included() {
+        let conn = test_conn();
+        conn.execute_batch(
+            "INSERT INTO projects (name, archived, created_at) VALUES ('Testing', 0, 0), ('Work', 0, 0);
+             INSERT INTO tags (name, created_at) VALUES ('personal', 0);",
+        )
+        .expect("fixtures");
+        let testing = insert_focus_session(&conn, 10_000, 1500);
+        let personal = insert_focus_session(&conn, 20_000, 1200);
+        let plain = insert_focus_session(&conn, 30_000, 900);
+        conn.execute(
+            "UPDATE sessions SET project_id = 1 WHERE id = ?1",
+            params![testing],
+        )
+        .expect("project");
+        conn.execute(
+            "INSERT INTO session_tags (session_id, tag_id) VALUES (?1, 1)",
+            params![personal],
+        )
+        .expect("tag");
+        let mut settings = sample_settings();
+        settings.analytics_excluded_project_ids = vec![1];
+        settings.analytics_excluded_tag_ids = vec![1];
+
+        let ids = |range: AnalyticsRange| -> Vec<i64> {
+            let range = with_exclusions(range, &settings);
+            let mut ids: Vec<i64> = fetch_sessions(&conn, &range)
+                .expect("sessions")
+                .iter()
+                .map(|s| s.id)
+                .collect();
+            ids.sort_unstable();
+            ids
+        };
+        assert_eq!(ids(AnalyticsRange::default()), vec![plain]);
+        let explicit = AnalyticsRange {
+            project_id: Some(1),
+            ..Default::default()
+        };
+        assert_eq!(ids(explicit), vec![testing]);
+        let everything = AnalyticsRange {
+            include_excluded: true,
+            ..Default::default()
+        };
+        assert_eq!(ids(everything), vec![testing, personal, plain]);
+
+        let timer = TimerState::default_with_settings(&settings);
+        let mut model = AppModel {
+            conn,
+            settings,
+            timer,
+            queued_notification: None,
+            console_detached: false,
+            records_cache: None,
+            breathing_emitted: None,
+            music_playing: None,
+            screen_dim: ScreenDim::default(),
+            db_recovery: None,
+            read_only: None,
+        };
+        let block = |model: &mut AppModel| {
+            cached_records(model)
+                .expect("records")
+                .longest_focus_block
+                .map(|record| record.value)
+        };
+        assert_eq!(block(&mut model), Some(900));
+        model.settings.analytics_excluded_project_ids.clear();
+        assert_eq!(block(&mut model), Some(1500));
+    }
+
+    #[test]
+    fn goals_track_today_and_this_week_and_fire_once() {
+        let conn = test_conn();
+        let at = |day, hour| {
+            Local
+                .with_ymd_and_hms(2024, 5, day, hour, 0, 0)
+                .single()
+                .expect("local time")
+                .timestamp()
+        };
+        // 2024-05-06 was a Monday; the Sunday before belongs to the previous week.
+        insert_focus_session(&conn, at(5, 10), 3000);
+        insert_focus_session(&conn, at(6, 10), 1500);
+        insert_focus_session(&conn, at(8, 9), 1500);
+        let second_today = at(8, 10);
+        insert_focus_session(&conn, second_today, 1500);
+        let mut settings = sample_settings();
+        settings.daily_goal_pomodoros = 2;
+        settings.weekly_goal_minutes = 90;
+
+        let now = at(8, 18);
+        let progress = goals_progress_at(&conn, &settings, now, now).expect("progress");
+        let daily = progress.daily_pomodoros.expect("daily");
+        assert_eq!((daily.value, daily.reached), (2, true));
+        assert_eq!(daily.period_start, at(8, 0));
+        let weekly = progress.weekly_minutes.expect("weekly");
+        assert_eq!((weekly.value, weekly.reached), (75, false));
+        assert_eq!(weekly.period_start, at(6, 0));
+
+        let reached = newly_reached_goals(&conn, &settings, second_today).expect("reached");
+        assert_eq!(
+            reached,
+            vec![GoalReachedEvent {
+                goal: "daily".to_string(),
+                target: 2,
+                value: 2,
+            }]
+        );
+        assert_eq!(
+            goal_reached_message("en", &reached[0]),
+            "Daily goal reached: 2 pomodoros"
+        );
+        let third = insert_focus_session(&conn, at(8, 12), 900);
+        let later = fetch_session(&conn, third).expect("session").ended_at;
+        let reached = newly_reached_goals(&conn, &settings, later).expect("reached");
+        assert_eq!(
+            reached.iter().map(|g| g.goal.as_str()).collect::<Vec<_>>(),
+            ["weekly"]
+        );
+
+        settings.daily_goal_pomodoros = 0;
+        settings.weekly_goal_minutes = 0;
+        assert_eq!(
+            goals_progress_at(&conn, &settings, now, now).expect("off"),
+            GoalsProgress::default()
+        );
+    }
+
+    #[test]
+    fn experiment_alternates_lengths_and_reports_each_arm() {
+        let conn = test_conn();
+        conn.execute(
+            "INSERT INTO experiments (focus_a_min, focus_b_min, started_at, ends_at) VALUES (25, 50, 1000, 100000)",
+            [],
+        )
+        .expect("experiment");
+        let experiment = active_experiment(&conn, 2000)
+            .expect("query")
+            .expect("active");
+        assert_eq!(active_experiment(&conn, 100_000).expect("query"), None);
+
+        let settings = sample_settings();
+        let mut timer = TimerState::default_with_settings(&settings);
+        timer.phase_total_seconds = 1200;
+        timer.remaining_seconds = 1200;
+        assert!(apply_experiment_length(&conn, &mut timer, 2000).expect("apply"));
+        assert_eq!(timer.remaining_seconds, 25 * 60);
+
+        let insert = |ended_at: i64, minutes: i64, completed: bool, interruptions: i64| {
+            let duration = if completed { minutes * 60 } else { 600 };
+            conn.execute(
+                "INSERT INTO sessions (started_at, ended_at, phase, duration_sec, completed, interruptions, planned_sec)
+                 VALUES (?1, ?2, 'focus', ?3, ?4, ?5, ?6)",
+                params![ended_at - duration, ended_at, duration, completed as i64, interruptions, minutes * 60],
+            )
+            .expect("session");
+        };
+        insert(5_000, 25, true, 0);
+        assert_eq!(experiment_focus_min(&conn, &experiment).expect("next"), 50);
+        insert(10_000, 50, false, 3);
+        insert(20_000, 25, true, 1);
+        insert(30_000, 50, true, 1);
+        // Before the experiment began: ignored.
+        insert(900, 25, false, 4);
+
+        let report = experiment_report(&conn, experiment, 40_000).expect("report");
+        assert!(report.active);
+        let a = &report.arms[0];
+        assert_eq!((a.focus_min, a.sessions, a.completed), (25, 2, 2));
+        assert_eq!(a.completion_rate, Some(1.0));
+        assert_eq!(a.interruptions_per_session, Some(0.5));
+        let b = &report.arms[1];
+        assert_eq!(
+            (b.focus_min, b.sessions, b.completed, b.interruptions),
+            (50, 2, 1, 4)
+        );
+        assert_eq!(b.focus_seconds, 600 + 3000);
+        assert_eq!(b.avg_focus_seconds, Some(1800.0));
+
+        timer.is_running = true;
+        timer.remaining_seconds -= 1;
+        assert!(!apply_experiment_length(&conn, &mut timer, 40_000).expect("running"));
+    }
+
+    #[test]
+    fn start_time_stats_track_median_and_first_starts_per_week() {
+        let conn = test_conn();
+        let start = |day, hour, minute| {
+            Local
+                .with_ymd_and_hms(2024, 3, day, hour, minute, 0)
+                .single()
+                .expect("local time")
+                .timestamp()
+        };
+        // Week of Monday 2024-03-04, then the following week starting later.
+        for (day, hour, minute) in [(4, 8, 30), (4, 13, 0), (5, 9, 30), (6, 7, 45)] {
+            insert_focus_session(&conn, start(day, hour, minute) + 1500, 1500);
         }
-    });
-}
+        for (day, hour, minute) in [(11, 10, 0), (12, 11, 15)] {
+            insert_focus_session(&conn, start(day, hour, minute) + 1500, 1500);
+        }
+        let range = AnalyticsRange {
+            granularity: Some(Granularity::Week),
+            ..Default::default()
+        };
+        let weeks = fetch_start_time_stats(&conn, &range).expect("stats");
+        assert_eq!(weeks.len(), 2);
+        assert_eq!(
+            weeks[0],
+            StartTimeStats {
+                period: "2024-03-04".to_string(),
+                sessions: 4,
+                days: 3,
+                median_start_min: 8 * 60 + 30,
+                earliest_start_min: 7 * 60 + 45,
+                latest_start_min: 13 * 60,
+                median_first_start_min: 8 * 60 + 30,
+            }
+        );
+        assert_eq!(weeks[1].median_first_start_min, 10 * 60);
+        assert_eq!(weeks[1].latest_start_min, 11 * 60 + 15);
+    }
 
-fn build_sessions_query(range: &AnalyticsRange) -> (String, Vec<Value>) {
-    let mut query = String::from(
-        "SELECT id, started_at, ended_at, phase, duration_sec, completed, interruptions, project_id FROM sessions WHERE 1 = 1",
-    );
-    let mut values: Vec<Value> = Vec::new();
+    #[test]
+    fn support_bundle_redacts_personal_text() {
+        let conn = test_conn();
+        conn.execute(
+            "INSERT INTO projects (name, archived, created_at) VALUES ('Secret client', 0, 0)",
+            [],
+        )
+        .expect("project");
+        let session = insert_focus_session(&conn, 5_000, 1500);
+        conn.execute(
+            "UPDATE sessions SET notes = 'call the lawyer' WHERE id = ?1",
+            params![session],
+        )
+        .expect("notes");
+        let mut settings = sample_settings();
+        settings.team_stats_key = "tok-123".to_string();
+        settings.session_webhook_url = "https://hooks.example.com/abc".to_string();
+        settings.language = "uk".to_string();
+        let diagnostics = AppDiagnostics {
+            version: "1.2.3".to_string(),
+            remote_running: false,
+            remote_port: None,
+            remote_endpoints: Vec::new(),
+        };
 
-    if let Some(from) = range.from {
-        query.push_str(" AND ended_at >= ?");
-        values.push(Value::Integer(from));
+        let bundle = build_support_bundle(&conn, &settings, &diagnostics, 10_000).expect("bundle");
+        let mut archive = zip::ZipArchive::new(std::io::Cursor::new(bundle)).unwrap();
+        let mut names: Vec<String> = archive.file_names().map(str::to_string).collect();
+        names.sort();
+        assert_eq!(
+            names,
+            [
+                "database.json",
+                "diagnostics.json",
+                "manifest.json",
+                "settings.json",
+                "timer-events.json"
+            ]
+        );
+        let mut everything = String::new();
+        for name in &names {
+            archive
+                .by_name(name)
+                .unwrap()
+                .read_to_string(&mut everything)
+                .unwrap();
+        }
+        for secret in ["tok-123", "hooks.example.com", "Secret client", "lawyer"] {
+            assert!(!everything.contains(secret), "{secret} leaked");
+        }
+
+        let redacted = redacted_settings(&settings).expect("settings");
+        assert_eq!(redacted["teamStatsKey"], "[redacted]");
+        assert_eq!(redacted["language"], "uk");
+        let stats = db_stats(&conn).expect("stats");
+        assert_eq!(stats.quick_check, "ok");
+        let sessions = stats
+            .tables
+            .iter()
+            .find(|t| t.table == "sessions")
+            .expect("table");
+        assert_eq!(sessions.rows, 1);
     }
-    if let Some(to) = range.to {
-        query.push_str(" AND ended_at <= ?");
-        values.push(Value::Integer(to));
+
+    #[test]
+    fn distribution_buckets_durations_and_same_day_gaps() {
+        let conn = test_conn();
+        let at = |day, hour, minute| {
+            Local
+                .with_ymd_and_hms(2024, 3, day, hour, minute, 0)
+                .single()
+                .expect("local time")
+                .timestamp()
+        };
+        // Day one: 25, 25 and 50 minute focus with 5 and 35 minute gaps. Day two: one 10 minute
+        // session, so the overnight gap is not counted.
+        insert_focus_session(&conn, at(4, 9, 25), 1500);
+        insert_focus_session(&conn, at(4, 9, 55), 1500);
+        insert_focus_session(&conn, at(4, 11, 20), 3000);
+        insert_focus_session(&conn, at(5, 8, 10), 600);
+
+        let report =
+            fetch_session_distribution(&conn, &AnalyticsRange::default()).expect("distribution");
+        let durations = &report.durations;
+        assert_eq!(durations.count, 4);
+        assert_eq!(
+            (durations.p50_sec, durations.p90_sec),
+            (Some(1500), Some(3000))
+        );
+        assert_eq!(durations.buckets.len(), 11);
+        assert_eq!(durations.buckets[2].count, 1);
+        assert_eq!(durations.buckets[5].count, 2);
+        assert_eq!(
+            durations.buckets[10],
+            DistributionBucket {
+                from_sec: 3000,
+                to_sec: Some(3300),
+                count: 1,
+            }
+        );
+
+        let gaps = &report.gaps;
+        assert_eq!(gaps.count, 2);
+        assert_eq!((gaps.p50_sec, gaps.p90_sec), (Some(300), Some(2100)));
+        assert_eq!(
+            gaps.buckets.iter().map(|b| b.count).collect::<Vec<_>>(),
+            [1, 0, 1]
+        );
+
+        let empty = fetch_session_distribution(
+            &conn,
+            &AnalyticsRange {
+                from: Some(at(6, 0, 0)),
+                ..Default::default()
+            },
+        )
+        .expect("empty");
+        assert_eq!((empty.durations.count, empty.durations.p50_sec), (0, None));
+        assert!(empty.gaps.buckets.is_empty());
     }
-    if let Some(project_id) = range.project_id {
-        query.push_str(" AND project_id = ?");
-        values.push(Value::Integer(project_id));
+
+    #[test]
+    fn supervised_worker_restarts_after_panics() {
+        let runs = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let counter = Arc::clone(&runs);
+        let panics = std::cell::RefCell::new(Vec::new());
+        supervise_worker(
+            "test",
+            Duration::ZERO,
+            move || {
+                if counter.fetch_add(1, Ordering::SeqCst) < 2 {
+                    panic!("tick failed");
+                }
+            },
+            |restarts, message| panics.borrow_mut().push((restarts, message)),
+        );
+
+        assert_eq!(runs.load(Ordering::SeqCst), 3);
+        assert_eq!(
+            panics.into_inner(),
+            vec![
+                (1, "tick failed".to_string()),
+                (2, "tick failed".to_string())
+            ]
+        );
     }
-    if let Some(tag_id) = range.tag_id {
-        query.push_str(" AND EXISTS (SELECT 1 FROM session_tags st WHERE st.session_id = sessions.id AND st.tag_id = ?)");
-        values.push(Value::Integer(tag_id));
+
+    #[test]
+    fn failed_migration_is_not_treated_as_corruption() {
+        let path = std::env::temp_dir().join(format!("pomodoro-migrate-{}.db", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let old = Connection::open(&path).expect("open db");
+        old.execute_batch("CREATE TABLE sessions (x INTEGER); INSERT INTO sessions VALUES (7);")
+            .expect("incompatible schema");
+        drop(old);
+
+        let error = open_database(&path).expect_err("migration must fail");
+        assert!(matches!(error, DbOpenError::Failed(_)), "{error:?}");
+        // The file is still there, data intact, for the user or a newer version to open.
+        let conn = Connection::open(&path).expect("reopen");
+        let x: i64 = conn
+            .query_row("SELECT x FROM sessions", [], |row| row.get(0))
+            .expect("row kept");
+        assert_eq!(x, 7);
+        drop(conn);
+        let _ = fs::remove_file(&path);
     }
 
-    query.push_str(" ORDER BY ended_at DESC");
+    #[test]
+    fn corrupt_database_is_moved_aside_and_rebuilt() {
+        let dir = std::env::temp_dir().join(format!("pomodoro-recover-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).expect("temp dir");
 
-    (query, values)
-}
+        let garbage = dir.join("garbage.db");
+        fs::write(&garbage, b"this is not a database file at all").expect("write garbage");
+        let DbOpenError::Corrupt(error) =
+            open_database(&garbage).expect_err("garbage must not open")
+        else {
+            panic!("garbage must count as corrupt");
+        };
+        let (conn, report) = recover_database(&garbage, error, 1_700_000_000).expect("recover");
+        assert_eq!(report.mode, DbRecoveryMode::Fresh);
+        assert_eq!(
+            fs::read(&report.backup_path).expect("backup kept"),
+            b"this is not a database file at all"
+        );
+        assert!(report
+            .tables
+            .iter()
+            .any(|t| t.table == "sessions" && t.rows == 0));
+        drop(conn);
+        assert!(open_database(&garbage).is_ok());
 
-fn read_session_tags(conn: &Connection, session_id: i64) -> AppResult<Vec<i64>> {
-    let mut stmt = conn
-        .prepare("SELECT tag_id FROM session_tags WHERE session_id = ?1 ORDER BY tag_id")
-        .map_err(|e| e.to_string())?;
-    let rows = stmt
-        .query_map(params![session_id], |row| row.get::<_, i64>(0))
-        .map_err(|e| e.to_string())?;
+        let readable = dir.join("readable.db");
+        let old = Connection::open(&readable).expect("open old db");
+        init_database(&old).expect("init schema");
+        let id = insert_focus_session(&old, 1_700_000_000, 1500);
+        drop(old);
+        let conn = Connection::open(dir.join("rebuilt.db")).expect("open new db");
+        init_database(&conn).expect("init schema");
+        let failed = copy_readable_tables(&conn, &readable.to_string_lossy()).expect("copy");
+        assert!(failed.is_empty(), "{failed:?}");
+        let copied: i64 = conn
+            .query_row("SELECT id FROM sessions", [], |row| row.get(0))
+            .expect("copied session");
+        assert_eq!(copied, id);
 
-    let mut tags = Vec::new();
-    for row in rows {
-        tags.push(row.map_err(|e| e.to_string())?);
+        let _ = fs::remove_dir_all(&dir);
     }
-    Ok(tags)
-}
-
-fn phase_from_db(value: String) -> AppResult<TimerPhase> {
-    TimerPhase::from_db_value(&value)
-}
 
-fn fetch_projects(conn: &Connection) -> AppResult<Vec<Project>> {
-    let mut stmt = conn
-        .prepare("SELECT id, name, color, archived FROM projects ORDER BY archived ASC, name ASC")
-        .map_err(|e| e.to_string())?;
+    #[test]
+    fn summary_aggregates_in_sql_with_split_shares_and_batched_tags() {
+        let mut conn = test_conn();
+        let settings = sample_settings();
+        let now = Local::now().timestamp();
+        let a = insert_focus_session(&conn, now - 2 * 86_400, 1500);
+        let b = insert_focus_session(&conn, now, 1200);
+        conn.execute(
+            "INSERT INTO projects (name, created_at) VALUES ('A', 0), ('B', 0)",
+            [],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO tags (name, created_at) VALUES ('x', 0), ('y', 0)",
+            [],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO session_tags (session_id, tag_id) VALUES (?1, 2), (?1, 1), (?2, 2)",
+            params![a, b],
+        )
+        .unwrap();
+        write_session_splits(
+            &mut conn,
+            a,
+            &[
+                ProjectSplit {
+                    project_id: 1,
+                    percent: 60,
+                },
+                ProjectSplit {
+                    project_id: 2,
+                    percent: 40,
+                },
+            ],
+        )
+        .unwrap();
+        conn.execute(
+            "UPDATE sessions SET project_id = 1 WHERE id = ?1",
+            params![b],
+        )
+        .unwrap();
 
-    let rows = stmt
-        .query_map([], |row| {
-            Ok(Project {
-                id: row.get(0)?,
-                name: row.get(1)?,
-                color: row.get(2)?,
-                archived: row.get::<_, i64>(3)? == 1,
-            })
-        })
-        .map_err(|e| e.to_string())?;
+        let all = compute_summary(&conn, &settings, &AnalyticsRange::default()).unwrap();
+        assert_eq!(all.total_focus_sec, 2700);
+        assert_eq!(all.completed_pomodoros, 2);
+        assert_eq!(all.avg_daily_focus_sec, 1350);
+        let project_b = AnalyticsRange {
+            project_id: Some(2),
+            ..Default::default()
+        };
+        let b_only = compute_summary(&conn, &settings, &project_b).unwrap();
+        assert_eq!(b_only.total_focus_sec, 600);
 
-    let mut projects = Vec::new();
-    for row in rows {
-        projects.push(row.map_err(|e| e.to_string())?);
+        let sessions = fetch_sessions(&conn, &AnalyticsRange::default()).unwrap();
+        let by_id: BTreeMap<i64, &SessionRecord> = sessions.iter().map(|s| (s.id, s)).collect();
+        assert_eq!(by_id[&a].tag_ids, vec![1, 2]);
+        assert_eq!(by_id[&a].project_splits.len(), 2);
+        assert_eq!(by_id[&a].project_splits[0].percent, 60);
+        assert_eq!(by_id[&b].tag_ids, vec![2]);
+        assert!(by_id[&b].project_splits.is_empty());
     }
 
-    Ok(projects)
-}
+    #[test]
+    fn read_only_mode_queues_writes_until_the_lock_is_released() {
+        let path = std::env::temp_dir().join(format!("pomodoro-locked-{}.db", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let other = Connection::open(&path).expect("open other db");
+        init_database(&other).expect("init schema");
+        other
+            .execute_batch("BEGIN IMMEDIATE")
+            .expect("hold write lock");
 
-fn fetch_tags(conn: &Connection) -> AppResult<Vec<Tag>> {
-    let mut stmt = conn
-        .prepare("SELECT id, name FROM tags ORDER BY name ASC")
-        .map_err(|e| e.to_string())?;
+        let conn = Connection::open(&path).expect("open app db");
+        conn.busy_timeout(Duration::ZERO).expect("busy timeout");
+        let reason = write_blocked(&conn).expect("writes are blocked");
+        let settings = sample_settings();
+        let timer = TimerState::default_with_settings(&settings);
+        let mut model = AppModel {
+            conn,
+            settings,
+            timer,
+            queued_notification: None,
+            console_detached: false,
+            records_cache: None,
+            breathing_emitted: None,
+            music_playing: None,
+            screen_dim: ScreenDim::default(),
+            db_recovery: None,
+            read_only: Some(read_only_mode(reason, 1_000)),
+        };
 
-    let rows = stmt
-        .query_map([], |row| {
-            Ok(Tag {
-                id: row.get(0)?,
-                name: row.get(1)?,
-            })
-        })
-        .map_err(|e| e.to_string())?;
+        model.timer.is_running = true;
+        log_timer_event(&mut model, TimerEventKind::Started, None, 1_000).unwrap();
+        persist_timer(&mut model).unwrap();
+        let session = session_from_timer(&model.timer, true, 2_500);
+        if let Some(mode) = &mut model.read_only {
+            mode.writes.push(PendingWrite::Session {
+                session,
+                timer: model.timer.clone(),
+                event: TimerEventKind::Completed,
+            });
+        }
+        assert_eq!(read_only_status(&model, 0).pending_sessions, 1);
 
-    let mut tags = Vec::new();
-    for row in rows {
-        tags.push(row.map_err(|e| e.to_string())?);
+        assert_eq!(flush_read_only(&mut model, 1_010).unwrap(), None);
+        assert_eq!(flush_read_only(&mut model, 1_100).unwrap(), None);
+        assert!(model.read_only.is_some());
+
+        other.execute_batch("ROLLBACK").expect("release lock");
+        assert_eq!(flush_read_only(&mut model, 1_200).unwrap(), Some(1));
+        assert!(model.read_only.is_none());
+        let (sessions, events): (i64, i64) = other
+            .query_row(
+                "SELECT (SELECT COUNT(*) FROM sessions), (SELECT COUNT(*) FROM events)",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!((sessions, events), (1, 2));
+        assert!(
+            load_or_create_timer(&other, &model.settings)
+                .unwrap()
+                .is_running
+        );
+
+        drop(model);
+        let _ = fs::remove_file(&path);
     }
 
-    Ok(tags)
-}
+    #[test]
+    fn estimation_accuracy_compares_credited_pomodoros_with_estimates() {
+        let mut conn = test_conn();
+        conn.execute(
+            "INSERT INTO projects (name, created_at, estimated_pomodoros)
+             VALUES ('Site', 0, 4), ('App', 0, 2), ('Unplanned', 0, NULL), ('Fresh', 0, 3)",
+            [],
+        )
+        .unwrap();
+        let day = 1_700_000_000;
+        for (ended_at, project) in [(day, 1), (day + 100, 1), (day + 200, 2), (day + 300, 3)] {
+            let id = insert_focus_session(&conn, ended_at, 1500);
+            conn.execute(
+                "UPDATE sessions SET project_id = ?1 WHERE id = ?2",
+                params![project, id],
+            )
+            .unwrap();
+        }
+        let split = insert_focus_session(&conn, day + 400, 1500);
+        write_session_splits(
+            &mut conn,
+            split,
+            &[
+                ProjectSplit {
+                    project_id: 2,
+                    percent: 50,
+                },
+                ProjectSplit {
+                    project_id: 1,
+                    percent: 50,
+                },
+            ],
+        )
+        .unwrap();
 
-fn fetch_sessions(conn: &Connection, range: &AnalyticsRange) -> AppResult<Vec<SessionRecord>> {
-    let (query, values) = build_sessions_query(range);
-    let mut stmt = conn.prepare(&query).map_err(|e| e.to_string())?;
+        let report = fetch_estimation_accuracy(&conn, &AnalyticsRange::default()).unwrap();
+        let by_name: BTreeMap<&str, &ProjectEstimate> = report
+            .projects
+            .iter()
+            .map(|p| (p.name.as_str(), p))
+            .collect();
+        assert_eq!(by_name.len(), 3);
+        assert_eq!(by_name["Site"].actual_pomodoros, 2.5);
+        assert_eq!(by_name["Site"].ratio, 0.625);
+        assert_eq!(by_name["App"].actual_pomodoros, 1.5);
+        assert_eq!(by_name["Fresh"].actual_pomodoros, 0.0);
+        assert_eq!(by_name["Fresh"].last_pomodoro_at, None);
+        assert_eq!(report.mean_ratio, Some((0.625 + 0.75) / 2.0));
+        assert_eq!(report.trend.len(), 1);
+        assert_eq!(report.trend[0].overestimated, 2);
 
-    let rows = stmt
-        .query_map(rusqlite::params_from_iter(values), |row| {
-            Ok((
-                row.get::<_, i64>(0)?,
-                row.get::<_, i64>(1)?,
-                row.get::<_, i64>(2)?,
-                row.get::<_, String>(3)?,
-                row.get::<_, i64>(4)?,
-                row.get::<_, i64>(5)?,
-                row.get::<_, i64>(6)?,
-                row.get::<_, Option<i64>>(7)?,
-            ))
-        })
-        .map_err(|e| e.to_string())?;
+        // `to` is inclusive, like the other analytics ranges.
+        let until_last = AnalyticsRange {
+            to: Some(day + 400),
+            ..Default::default()
+        };
+        let report = fetch_estimation_accuracy(&conn, &until_last).unwrap();
+        assert_eq!(report.projects.len(), 3);
+        let before_last = AnalyticsRange {
+            to: Some(day + 399),
+            ..Default::default()
+        };
+        let report = fetch_estimation_accuracy(&conn, &before_last).unwrap();
+        assert_eq!(report.projects.len(), 1);
 
-    let mut sessions = Vec::new();
-    for row in rows {
-        let (
-            id,
-            started_at,
-            ended_at,
-            phase_raw,
-            duration_sec,
-            completed,
-            interruptions,
-            project_id,
-        ) = row.map_err(|e| e.to_string())?;
-        sessions.push(SessionRecord {
-            id,
-            started_at,
-            ended_at,
-            phase: phase_from_db(phase_raw)?,
-            duration_sec,
-            completed: completed == 1,
-            interruptions,
-            project_id,
-            tag_ids: read_session_tags(conn, id)?,
-        });
+        let mut excluded = AnalyticsRange::default();
+        excluded.excluded.project_ids = vec![1];
+        let report = fetch_estimation_accuracy(&conn, &excluded).unwrap();
+        assert!(report.projects.iter().all(|p| p.project_id != 1));
     }
 
-    Ok(sessions)
-}
+    #[test]
+    fn remote_routes_map_to_scopes() {
+        assert_eq!(remote_route_scope("GET", "/api/state"), RemoteScope::Read);
+        assert_eq!(
+            remote_route_scope("get", "/api/sessions"),
+            RemoteScope::Read
+        );
+        assert_eq!(remote_route_scope("POST", "/api/skip"), RemoteScope::Timer);
+        assert_eq!(
+            remote_route_scope("POST", "/api/toggle"),
+            RemoteScope::Timer
+        );
+        assert_eq!(
+            remote_route_scope("DELETE", "/api/sessions/4"),
+            RemoteScope::Write
+        );
+        assert_eq!(
+            remote_route_scope("POST", "/api/unknown"),
+            RemoteScope::Write
+        );
 
-fn day_key(timestamp: i64) -> String {
-    let dt = Local
-        .timestamp_opt(timestamp, 0)
-        .single()
-        .unwrap_or_else(|| Local::now());
-    format!("{:04}-{:02}-{:02}", dt.year(), dt.month(), dt.day())
-}
+        assert_eq!(
+            parse_remote_scopes(&format_remote_scopes(&[
+                RemoteScope::Read,
+                RemoteScope::Timer,
+                RemoteScope::Read
+            ])),
+            vec![RemoteScope::Timer, RemoteScope::Read]
+        );
+    }
 
-fn calculate_streak_days(sessions: &[SessionRecord]) -> i64 {
-    let mut focus_days = HashSet::new();
-    for session in sessions {
-        if session.phase == TimerPhase::Focus && session.duration_sec > 0 {
-            focus_days.insert(day_key(session.ended_at));
+    #[test]
+    fn read_only_remote_refuses_everything_but_reads() {
+        for (method, path) in [
+            ("POST", "/api/toggle"),
+            ("POST", "/api/skip"),
+            ("PATCH", "/api/settings"),
+            ("DELETE", "/api/sessions/4"),
+            ("POST", "/api/projects"),
+            ("POST", "/api/local/toggle"),
+            ("POST", "/api/local/start"),
+        ] {
+            assert!(!remote_read_only_allows(method, path), "{method} {path}");
+        }
+        for (method, path) in [
+            ("GET", "/api/state"),
+            ("get", "/api/sessions"),
+            ("GET", "/api/local/state"),
+            ("GET", "/ws"),
+            ("GET", "/api/events"),
+            ("POST", "/api/push/subscribe"),
+        ] {
+            assert!(remote_read_only_allows(method, path), "{method} {path}");
         }
     }
 
-    let mut streak = 0;
-    let mut current = Local::now().date_naive();
-    loop {
-        let key = current.format("%Y-%m-%d").to_string();
-        if focus_days.contains(&key) {
-            streak += 1;
-            match current.pred_opt() {
-                Some(prev) => current = prev,
-                None => break,
-            }
-        } else {
-            break;
+    #[test]
+    fn remote_project_and_tag_routes_read_and_upsert() {
+        for path in ["/api/projects", "/api/tags"] {
+            assert_eq!(remote_route_scope("GET", path), RemoteScope::Read);
+            assert_eq!(remote_route_scope("POST", path), RemoteScope::Write);
         }
+
+        let conn = test_conn();
+        // The POST bodies are the camelCase inputs the app itself sends.
+        let input: ProjectInput =
+            serde_json::from_slice(br#"{"name":"Client","hourlyRate":80,"billable":true}"#)
+                .unwrap();
+        let project = upsert_project(&conn, &input).unwrap();
+        assert_eq!(project.name, "Client");
+        assert_eq!(project.hourly_rate, Some(80.0));
+        assert!(project.billable);
+
+        let rename: ProjectInput =
+            serde_json::from_value(serde_json::json!({ "id": project.id, "name": "Client B" }))
+                .unwrap();
+        let renamed = upsert_project(&conn, &rename).unwrap();
+        assert_eq!(renamed.id, project.id);
+        assert_eq!(renamed.hourly_rate, Some(80.0));
+
+        let bad_rate: ProjectInput =
+            serde_json::from_slice(br#"{"name":"Other","hourlyRate":-5}"#).unwrap();
+        assert!(upsert_project(&conn, &bad_rate).is_err());
+        assert!(serde_json::from_slice::<ProjectInput>(br#"{"color":"red"}"#).is_err());
+
+        let tag = upsert_tag(
+            &conn,
+            &serde_json::from_slice::<TagInput>(br#"{"name":"deep"}"#).unwrap(),
+        )
+        .unwrap();
+        let retagged = upsert_tag(
+            &conn,
+            &TagInput {
+                id: Some(tag.id),
+                name: "deep work".to_string(),
+            },
+        )
+        .unwrap();
+        assert_eq!(retagged.id, tag.id);
+
+        let projects = fetch_projects(&conn).unwrap();
+        assert_eq!(projects.len(), 1);
+        assert_eq!(projects[0].name, "Client B");
+        let listed = serde_json::to_value(&projects).unwrap();
+        assert_eq!(listed[0]["hourlyRate"], 80.0);
+        let tags = fetch_tags(&conn).unwrap();
+        assert_eq!(tags.len(), 1);
+        assert_eq!(tags[0].name, "deep work");
     }
 
-    streak
-}
+    #[test]
+    fn web_push_keys_persist_and_subscriptions_round_trip() {
+        let conn = test_conn();
+        let keys = load_or_create_vapid_keys(&conn).unwrap();
+        // An uncompressed P-256 point is 65 bytes, the scalar 32.
+        assert_eq!(keys.public_key.len(), 87);
+        assert_eq!(keys.private_key.len(), 43);
+        assert_eq!(load_or_create_vapid_keys(&conn).unwrap(), keys);
 
-#[tauri::command]
-fn timer_start(
-    app: AppHandle,
-    state: State<'_, AppState>,
-    payload: Option<StartTimerRequest>,
-) -> AppResult<TimerState> {
-    timer_start_inner(&app, state.inner(), payload)
-}
+        use p256::elliptic_curve::sec1::ToEncodedPoint;
+        let encode = |bytes: &[u8]| base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(bytes);
+        let browser = p256::SecretKey::random(&mut rand::rngs::OsRng);
+        let input = PushSubscriptionInput {
+            endpoint: "https://fcm.googleapis.com/fcm/send/abc".to_string(),
+            keys: PushSubscriptionKeys {
+                p256dh: encode(browser.public_key().to_encoded_point(false).as_bytes()),
+                auth: encode(&[7u8; 16]),
+            },
+        };
+        save_push_subscription(&conn, &input, 1, 100).unwrap();
+        // Subscribing again refreshes the same row.
+        save_push_subscription(&conn, &input, 2, 200).unwrap();
+        assert_eq!(
+            fetch_push_subscriptions(&conn).unwrap(),
+            vec![input.clone()]
+        );
+        for endpoint in [
+            "http://fcm.googleapis.com/fcm/send/abc",
+            "https://192.168.1.1/admin",
+            "https://fcm.googleapis.com.evil.test/abc",
+            "https://fcm.googleapis.com@192.168.1.1/abc",
+            "https://fcm.googleapis.com:8080/abc",
+        ] {
+            let rejected = PushSubscriptionInput {
+                endpoint: endpoint.to_string(),
+                ..input.clone()
+            };
+            assert!(save_push_subscription(&conn, &rejected, 1, 100).is_err());
+        }
+        assert!(push_endpoint_allowed("https://web.push.apple.com/QGuQ"));
+        assert!(push_endpoint_allowed(
+            "https://wns2-by3p.notify.windows.com/w/?token=x"
+        ));
+        assert!(push_endpoint_allowed(
+            "https://updates.push.services.mozilla.com:443/wpush/v2/x"
+        ));
+        for n in 0..MAX_PUSH_SUBSCRIPTIONS_PER_TOKEN {
+            let extra = PushSubscriptionInput {
+                endpoint: format!("https://fcm.googleapis.com/fcm/send/{n}"),
+                ..input.clone()
+            };
+            save_push_subscription(&conn, &extra, 3, 100).unwrap();
+        }
+        let over = PushSubscriptionInput {
+            endpoint: "https://fcm.googleapis.com/fcm/send/over".to_string(),
+            ..input.clone()
+        };
+        assert!(save_push_subscription(&conn, &over, 3, 100).is_err());
+        // Refreshing one the token already has is not a new subscription.
+        let again = PushSubscriptionInput {
+            endpoint: "https://fcm.googleapis.com/fcm/send/0".to_string(),
+            ..input.clone()
+        };
+        save_push_subscription(&conn, &again, 3, 100).unwrap();
+        conn.execute("DELETE FROM push_subscriptions WHERE token_id = 3", [])
+            .unwrap();
 
-#[tauri::command]
-fn timer_pause(app: AppHandle, state: State<'_, AppState>) -> AppResult<TimerState> {
-    timer_pause_inner(&app, state.inner())
-}
+        let payload = phase_push_payload(&TimerPhase::Focus, &TimerPhase::ShortBreak);
+        assert_eq!(payload["title"], "Focus complete");
+        assert_eq!(payload["body"], "Up next: a short break.");
+        let message = build_web_push(&keys, &input, payload.to_string().as_bytes()).unwrap();
+        assert_eq!(message.ttl, WEB_PUSH_TTL_SECONDS);
+        assert!(message.payload.is_some());
 
-#[tauri::command]
-fn timer_resume(
-    app: AppHandle,
-    state: State<'_, AppState>,
-    payload: Option<StartTimerRequest>,
-) -> AppResult<TimerState> {
-    timer_resume_inner(&app, state.inner(), payload)
-}
+        // The subscription now belongs to token 2; token 1 cannot remove it.
+        assert!(!delete_push_subscription(&conn, &input.endpoint, 1).unwrap());
+        assert!(delete_push_subscription(&conn, &input.endpoint, 2).unwrap());
+        assert!(fetch_push_subscriptions(&conn).unwrap().is_empty());
+        assert_eq!(
+            remote_route_scope("POST", "/api/push/subscribe"),
+            RemoteScope::Read
+        );
+    }
 
-#[tauri::command]
-fn timer_skip(app: AppHandle, state: State<'_, AppState>) -> AppResult<TimerState> {
-    timer_skip_inner(&app, state.inner())
-}
+    #[test]
+    fn remote_assets_are_bundled_and_fall_back_to_the_inline_page() {
+        let bundled = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("remote");
+        for (_, file, _) in REMOTE_ASSETS {
+            assert!(bundled.join(file).is_file(), "missing remote/{file}");
+        }
+        let manifest: serde_json::Value =
+            serde_json::from_str(include_str!("../remote/manifest.json")).unwrap();
+        assert_eq!(manifest["start_url"], "/");
+        for icon in manifest["icons"].as_array().unwrap() {
+            let src = icon["src"].as_str().unwrap();
+            assert!(REMOTE_ASSETS.iter().any(|(route, _, _)| *route == src));
+        }
 
-#[tauri::command]
-fn timer_get_state(state: State<'_, AppState>) -> AppResult<TimerState> {
-    timer_get_state_inner(state.inner())
-}
+        let (page, content_type) = load_remote_asset(Some(&bundled), "/").unwrap();
+        assert_eq!(content_type, "text/html; charset=utf-8");
+        assert!(String::from_utf8(page).unwrap().contains("/manifest.json"));
+        let (worker, _) = load_remote_asset(Some(&bundled), "/sw.js").unwrap();
+        assert!(String::from_utf8(worker).unwrap().contains("\"push\""));
 
-#[tauri::command]
-fn timer_set_context(
-    app: AppHandle,
-    state: State<'_, AppState>,
-    payload: StartTimerRequest,
-) -> AppResult<TimerState> {
-    let timer = {
-        let mut model = lock_model(&state)?;
-        refresh_remaining(&mut model.timer);
+        let empty =
+            std::env::temp_dir().join(format!("pomodoro-remote-assets-{}", std::process::id()));
+        fs::create_dir_all(&empty).unwrap();
+        let (fallback, _) = load_remote_asset(Some(&empty), "/").unwrap();
+        assert_eq!(fallback, REMOTE_FALLBACK_HTML.as_bytes());
+        assert_eq!(
+            load_remote_asset(None, "/").unwrap().0,
+            REMOTE_FALLBACK_HTML.as_bytes()
+        );
+        assert!(load_remote_asset(Some(&empty), "/manifest.json").is_none());
+        assert!(load_remote_asset(Some(&bundled), "/index.html").is_none());
+        fs::remove_dir_all(&empty).ok();
+    }
 
-        if let Some(project_id) = payload.project_id {
-            model.timer.current_project_id = project_id;
+    #[test]
+    fn remote_audit_keeps_the_newest_entries() {
+        let conn = test_conn();
+        let phone = create_remote_token(
+            &conn,
+            "Phone",
+            "phone-token".to_string(),
+            &[RemoteScope::Read],
+            100,
+        )
+        .unwrap();
+        let name = remote_token_name(&conn, phone.id).unwrap();
+        for n in 0..REMOTE_AUDIT_MAX + 5 {
+            let at = n * (REMOTE_AUDIT_COLLAPSE_SECONDS + 1);
+            record_remote_audit(&conn, at, "10.0.0.5", "GET /api/state", &name).unwrap();
         }
-        if let Some(tag_ids) = payload.tag_ids {
-            model.timer.current_tag_ids = tag_ids;
+        record_remote_audit(
+            &conn,
+            9_999_999,
+            "10.0.0.6",
+            "GET /api/team/daily",
+            REMOTE_AUDIT_TEAM_KEY,
+        )
+        .unwrap();
+
+        let count: i64 = conn
+            .query_row("SELECT COUNT(*) FROM remote_audit", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, REMOTE_AUDIT_MAX);
+        let latest = fetch_remote_audit(&conn, 2).unwrap();
+        assert_eq!(latest.len(), 2);
+        assert_eq!(latest[0].at, 9_999_999);
+        assert_eq!(latest[0].token_name, "team key");
+        assert_eq!(latest[1].token_name, "Phone");
+        assert_eq!(latest[1].route, "GET /api/state");
+        assert!(remote_token_name(&conn, phone.id + 1).is_err());
+
+        // Polling keeps extending one entry; a pause longer than the window starts a new one.
+        let poll = |at| {
+            record_remote_audit(&conn, at, "10.0.0.7", "GET /api/state", &name).unwrap();
+        };
+        for at in (10_000_000..10_000_300).step_by(30) {
+            poll(at);
         }
+        let latest = fetch_remote_audit(&conn, 1).unwrap();
+        assert_eq!(
+            (latest[0].at, latest[0].last_at, latest[0].hits),
+            (10_000_000, 10_000_270, 10)
+        );
+        poll(10_000_400);
+        let latest = fetch_remote_audit(&conn, 2).unwrap();
+        assert_eq!((latest[0].at, latest[0].hits), (10_000_400, 1));
+        assert_eq!(latest[1].hits, 10);
+    }
 
-        save_timer_state(&model.conn, &model.timer)?;
-        model.timer.clone()
-    };
+    #[test]
+    fn remote_devices_pair_once_and_follow_decisions() {
+        let conn = test_conn();
+        let phone = create_remote_token(
+            &conn,
+            "Phone",
+            "phone-token".to_string(),
+            &[RemoteScope::Timer],
+            100,
+        )
+        .unwrap();
+        let safari = "Mozilla/5.0 (iPhone; CPU iPhone OS 17_0 like Mac OS X) Version/17.0 Mobile/15E148 Safari/604.1";
+        assert_eq!(remote_device_label(safari), "iPhone (Safari)");
+        assert_eq!(remote_device_label("curl/8.4.0"), "curl");
+        assert_eq!(remote_device_label(""), "Unknown device");
 
-    emit_timer_state(&app, &timer);
-    Ok(timer)
-}
+        let fingerprint = remote_device_fingerprint(phone.id, Some("abc"), safari);
+        // The page's device id wins over the User-Agent; another token is another device.
+        assert_eq!(
+            fingerprint,
+            remote_device_fingerprint(phone.id, Some("abc"), "other")
+        );
+        assert_ne!(
+            fingerprint,
+            remote_device_fingerprint(phone.id + 1, Some("abc"), safari)
+        );
+        assert_ne!(
+            fingerprint,
+            remote_device_fingerprint(phone.id, None, safari)
+        );
 
-#[tauri::command]
-fn session_complete(
-    payload: CompleteSessionRequest,
-    state: State<'_, AppState>,
-) -> AppResult<SessionRecord> {
-    let model = lock_model(&state)?;
+        let (status, new_device) = check_remote_device(
+            &conn,
+            phone.id,
+            &fingerprint,
+            "iPhone (Safari)",
+            "10.0.0.5",
+            200,
+        )
+        .unwrap();
+        assert_eq!(status, RemoteDeviceStatus::Pending);
+        let device = new_device.expect("first sight creates the device");
+        assert_eq!(device.token_name.as_deref(), Some("Phone"));
+        assert_eq!(device.ip, "10.0.0.5");
 
-    model
-        .conn
-        .execute(
-            "INSERT INTO sessions (started_at, ended_at, phase, duration_sec, completed, interruptions, project_id)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
-            params![
-                payload.started_at,
-                payload.ended_at,
-                payload.phase.as_db_value(),
-                payload.duration_sec,
-                payload.completed as i64,
-                payload.interruptions,
-                payload.project_id,
-            ],
+        // Asked about once: later requests find it still pending.
+        let (status, new_device) = check_remote_device(
+            &conn,
+            phone.id,
+            &fingerprint,
+            "iPhone (Safari)",
+            "10.0.0.5",
+            210,
         )
-        .map_err(|e| e.to_string())?;
+        .unwrap();
+        assert_eq!((status, new_device), (RemoteDeviceStatus::Pending, None));
 
-    let id = model.conn.last_insert_rowid();
-    let tag_ids = payload.tag_ids.unwrap_or_default();
-    for tag_id in &tag_ids {
-        model
-            .conn
-            .execute(
-                "INSERT OR IGNORE INTO session_tags (session_id, tag_id) VALUES (?1, ?2)",
-                params![id, tag_id],
-            )
-            .map_err(|e| e.to_string())?;
+        assert!(decide_remote_device(&conn, device.id, true, 220).unwrap());
+        let (status, _) = check_remote_device(
+            &conn,
+            phone.id,
+            &fingerprint,
+            "iPhone (Safari)",
+            "10.0.0.9",
+            300,
+        )
+        .unwrap();
+        assert_eq!(status, RemoteDeviceStatus::Approved);
+        let listed = fetch_remote_devices(&conn).unwrap();
+        assert_eq!(listed[0].status, RemoteDeviceStatus::Approved);
+        assert_eq!(listed[0].decided_at, Some(220));
+        assert_eq!(listed[0].last_seen_at, Some(300));
+        assert_eq!(listed[0].ip, "10.0.0.9");
+
+        decide_remote_device(&conn, device.id, false, 400).unwrap();
+        let (status, _) = check_remote_device(
+            &conn,
+            phone.id,
+            &fingerprint,
+            "iPhone (Safari)",
+            "10.0.0.9",
+            500,
+        )
+        .unwrap();
+        assert_eq!(status, RemoteDeviceStatus::Rejected);
+        assert!(!decide_remote_device(&conn, device.id + 1, true, 500).unwrap());
+    }
+
+    #[test]
+    fn remote_devices_pending_per_token_are_capped() {
+        let conn = test_conn();
+        let phone = create_remote_token(
+            &conn,
+            "Phone",
+            "phone-token".to_string(),
+            &[RemoteScope::Timer],
+            100,
+        )
+        .unwrap();
+        let check = |n: i64| {
+            let fingerprint = remote_device_fingerprint(phone.id, Some(&n.to_string()), "");
+            check_remote_device(&conn, phone.id, &fingerprint, "x", "10.0.0.5", 200 + n).unwrap()
+        };
+        for n in 0..MAX_PENDING_DEVICES_PER_TOKEN {
+            assert!(check(n).1.is_some());
+        }
+        assert_eq!(check(99), (RemoteDeviceStatus::Pending, None));
+        assert_eq!(
+            fetch_remote_devices(&conn).unwrap().len() as i64,
+            MAX_PENDING_DEVICES_PER_TOKEN
+        );
+
+        let first = next_pending_device(&conn, phone.id).unwrap().unwrap();
+        decide_remote_device(&conn, first.id, false, 300).unwrap();
+        assert_ne!(
+            next_pending_device(&conn, phone.id).unwrap().unwrap().id,
+            first.id
+        );
+        assert!(check(99).1.is_some());
+        assert!(next_pending_device(&conn, phone.id + 1).unwrap().is_none());
     }
 
-    Ok(SessionRecord {
-        id,
-        started_at: payload.started_at,
-        ended_at: payload.ended_at,
-        phase: payload.phase,
-        duration_sec: payload.duration_sec,
-        completed: payload.completed,
-        interruptions: payload.interruptions,
-        project_id: payload.project_id,
-        tag_ids,
-    })
-}
+    #[test]
+    fn remote_tokens_migrate_from_settings_and_track_use() {
+        let conn = test_conn();
+        let legacy = serde_json::json!({
+            "focusMin": 30,
+            "remoteControlToken": "legacy-token",
+            "remoteTokenScopes": ["read"],
+        });
+        migrate_remote_token(&conn, Some(&legacy), 100).unwrap();
+        // Settings already saved without the field: nothing to migrate.
+        migrate_remote_token(&conn, Some(&serde_json::json!({"focusMin": 30})), 200).unwrap();
+        let tokens = fetch_remote_tokens(&conn).unwrap();
+        assert_eq!(tokens.len(), 1);
+        assert_eq!(tokens[0].name, "Default");
+        assert_eq!(tokens[0].token, None);
+        assert_eq!(tokens[0].scopes, vec![RemoteScope::Read]);
+        let stored: String = conn
+            .query_row("SELECT token_hash FROM remote_tokens", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(stored, hash_remote_token("legacy-token"));
+        assert_ne!(stored, "legacy-token");
+        assert!(lookup_remote_token(&conn, "legacy-token")
+            .unwrap()
+            .is_some());
 
-#[tauri::command]
-fn analytics_get_summary(
-    range: AnalyticsRange,
-    state: State<'_, AppState>,
-) -> AppResult<AnalyticsSummary> {
-    let model = lock_model(&state)?;
-    let sessions = fetch_sessions(&model.conn, &range)?;
+        let phone = create_remote_token(
+            &conn,
+            " Phone ",
+            "phone-token".to_string(),
+            &[RemoteScope::Timer, RemoteScope::Read],
+            300,
+        )
+        .unwrap();
+        assert_eq!(phone.name, "Phone");
+        assert_eq!(phone.token.as_deref(), Some("phone-token"));
+        assert!(create_remote_token(&conn, "Empty", "x".to_string(), &[], 300).is_err());
+        assert_eq!(
+            lookup_remote_token(&conn, "phone-token").unwrap(),
+            Some((phone.id, vec![RemoteScope::Timer, RemoteScope::Read]))
+        );
+        assert_eq!(lookup_remote_token(&conn, "").unwrap(), None);
+        assert_eq!(lookup_remote_token(&conn, "phone").unwrap(), None);
 
-    let mut total_focus_sec = 0;
-    let mut completed_pomodoros = 0;
-    let mut interruptions = 0;
-    let mut days_with_focus = HashSet::new();
+        touch_remote_token(&conn, phone.id, 1_000).unwrap();
+        touch_remote_token(&conn, phone.id, 1_030).unwrap();
+        let last_used = |conn: &Connection| fetch_remote_tokens(conn).unwrap()[1].last_used_at;
+        assert_eq!(last_used(&conn), Some(1_000));
+        touch_remote_token(&conn, phone.id, 1_060).unwrap();
+        assert_eq!(last_used(&conn), Some(1_060));
 
-    for session in &sessions {
-        if session.phase == TimerPhase::Focus {
-            total_focus_sec += session.duration_sec;
-            interruptions += session.interruptions;
-            if session.completed {
-                completed_pomodoros += 1;
-            }
-            if session.duration_sec > 0 {
-                days_with_focus.insert(day_key(session.ended_at));
-            }
-        }
+        // A new install has no old token to carry over.
+        let fresh = test_conn();
+        migrate_remote_token(&fresh, None, 0).unwrap();
+        assert!(fetch_remote_tokens(&fresh).unwrap().is_empty());
     }
 
-    let avg_daily_focus_sec = if days_with_focus.is_empty() {
-        0
-    } else {
-        total_focus_sec / days_with_focus.len() as i64
-    };
+    #[test]
+    fn session_history_filters_by_phase() {
+        let conn = test_conn();
+        let focus = insert_focus_session(&conn, 1_700_000_000, 1500);
+        conn.execute(
+            "INSERT INTO sessions (started_at, ended_at, phase, duration_sec, completed, interruptions)
+             VALUES (?1, ?2, 'short_break', 300, 1, 0), (?3, ?4, 'long_break', 900, 1, 0)",
+            params![1_700_000_000, 1_700_000_300, 1_700_001_000, 1_700_001_900],
+        )
+        .expect("insert breaks");
 
-    Ok(AnalyticsSummary {
-        total_focus_sec,
-        completed_pomodoros,
-        streak_days: calculate_streak_days(&sessions),
-        interruptions,
-        avg_daily_focus_sec,
-    })
-}
+        let ids = |phases: Vec<TimerPhase>| -> Vec<i64> {
+            let range = AnalyticsRange {
+                phases,
+                ..AnalyticsRange::default()
+            };
+            fetch_sessions(&conn, &range)
+                .expect("history")
+                .iter()
+                .map(|s| s.id)
+                .collect()
+        };
 
-#[tauri::command]
-fn analytics_get_timeseries(
-    range: AnalyticsRange,
-    state: State<'_, AppState>,
-) -> AppResult<Vec<TimeseriesPoint>> {
-    let model = lock_model(&state)?;
-    let sessions = fetch_sessions(&model.conn, &range)?;
+        assert_eq!(ids(vec![TimerPhase::Focus]), vec![focus]);
+        assert_eq!(
+            ids(vec![TimerPhase::ShortBreak, TimerPhase::LongBreak]).len(),
+            2
+        );
+        assert_eq!(ids(Vec::new()).len(), 3);
+    }
 
-    let mut by_day: BTreeMap<String, TimeseriesPoint> = BTreeMap::new();
+    #[test]
+    fn tray_countdown_ticks_per_minute_until_the_last() {
+        assert_eq!(tray_countdown(1500, false), "25m");
+        assert_eq!(tray_countdown(1441, false), "25m");
+        assert_eq!(tray_countdown(1440, false), "24m");
+        assert_eq!(tray_countdown(60, false), "1m");
+        assert_eq!(tray_countdown(59, false), "00:59");
+        assert_eq!(tray_countdown(1441, true), "24:01");
+    }
 
-    for session in sessions {
-        if session.phase != TimerPhase::Focus {
-            continue;
-        }
+    #[test]
+    fn finish_mutation_persists_timer_drops_cache_and_audits() {
+        let conn = test_conn();
+        let settings = sample_settings();
+        let timer = TimerState::default_with_settings(&settings);
+        let mut model = AppModel {
+            conn,
+            settings,
+            timer,
+            queued_notification: None,
+            console_detached: false,
+            records_cache: Some((
+                1,
+                0,
+                AnalyticsExclusions::default(),
+                AnalyticsRecords {
+                    longest_daily_focus: None,
+                    most_pomodoros_day: None,
+                    longest_streak: None,
+                    longest_focus_block: None,
+                },
+            )),
+            breathing_emitted: None,
+            music_playing: None,
+            screen_dim: ScreenDim::default(),
+            db_recovery: None,
+            read_only: None,
+        };
 
-        let key = day_key(session.ended_at);
-        let entry = by_day.entry(key.clone()).or_insert(TimeseriesPoint {
-            date: key,
-            focus_seconds: 0,
-            completed_pomodoros: 0,
-            interruptions: 0,
-        });
+        let mut mutation = Mutation::default();
+        mutation.touch(DataDomain::Projects);
+        model.timer.current_project_id = Some(7);
+        mutation.touch(DataDomain::Timer);
+        finish_mutation(&mut model, "projects_delete", &mutation, 1_700_000_000).expect("finish");
 
-        entry.focus_seconds += session.duration_sec;
-        entry.interruptions += session.interruptions;
-        if session.completed {
-            entry.completed_pomodoros += 1;
-        }
+        assert!(model.records_cache.is_none());
+        let saved = load_json_setting::<TimerState>(&model.conn, TIMER_STATE_KEY)
+            .expect("load")
+            .expect("timer saved");
+        assert_eq!(saved.current_project_id, Some(7));
+        let logged: (String, String, i64) = model
+            .conn
+            .query_row("SELECT command, domains, at FROM mutation_log", [], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+            })
+            .expect("logged");
+        assert_eq!(
+            logged,
+            (
+                "projects_delete".to_string(),
+                "projects,timer".to_string(),
+                1_700_000_000
+            )
+        );
     }
 
-    Ok(by_day.into_values().collect())
-}
-
-#[tauri::command]
-fn projects_list(state: State<'_, AppState>) -> AppResult<Vec<Project>> {
-    let model = lock_model(&state)?;
-    fetch_projects(&model.conn)
-}
+    #[test]
+    fn mutate_model_commits_the_change_with_its_log_row_or_neither() {
+        let settings = sample_settings();
+        let timer = TimerState::default_with_settings(&settings);
+        let mut model = AppModel {
+            conn: test_conn(),
+            settings,
+            timer,
+            queued_notification: None,
+            console_detached: false,
+            records_cache: None,
+            breathing_emitted: None,
+            music_playing: None,
+            screen_dim: ScreenDim::default(),
+            db_recovery: None,
+            read_only: None,
+        };
+        let count = |conn: &Connection, table: &str| -> i64 {
+            conn.query_row(&format!("SELECT COUNT(*) FROM {table}"), [], |row| {
+                row.get(0)
+            })
+            .expect("count")
+        };
 
-#[tauri::command]
-fn projects_upsert(input: ProjectInput, state: State<'_, AppState>) -> AppResult<Project> {
-    let model = lock_model(&state)?;
+        let failed = mutate_model(
+            &mut model,
+            "session_complete",
+            &[DataDomain::Sessions],
+            |model, _| -> AppResult<()> {
+                insert_focus_session(&model.conn, 1_700_000_000, 1500);
+                Err("validation failed".to_string())
+            },
+        );
+        assert_eq!(failed.err().as_deref(), Some("validation failed"));
+        assert_eq!(count(&model.conn, "sessions"), 0);
+        assert_eq!(count(&model.conn, "mutation_log"), 0);
+        assert!(model.conn.is_autocommit());
 
-    let archived = input.archived.unwrap_or(false);
-    let id = if let Some(id) = input.id {
-        model
+        // Functions with their own transaction nest as savepoints.
+        let (id, mutation) = mutate_model(
+            &mut model,
+            "session_complete",
+            &[DataDomain::Sessions],
+            |model, _| {
+                let tx = model.conn.savepoint().map_err(|e| e.to_string())?;
+                let id = insert_focus_session(&tx, 1_700_000_000, 1500);
+                tx.commit().map_err(|e| e.to_string())?;
+                Ok(id)
+            },
+        )
+        .expect("committed");
+        assert!(mutation.touches(DataDomain::Sessions));
+        assert!(model.conn.is_autocommit());
+        assert_eq!(count(&model.conn, "sessions"), 1);
+        let logged: (String, String) = model
             .conn
+            .query_row("SELECT command, domains FROM mutation_log", [], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })
+            .expect("logged");
+        assert_eq!(
+            logged,
+            ("session_complete".to_string(), "sessions".to_string())
+        );
+        assert!(id > 0);
+    }
+
+    #[test]
+    fn import_backup_remaps_ids_and_merges_or_replaces() {
+        let source = test_conn();
+        source
             .execute(
-                "UPDATE projects SET name = ?1, color = ?2, archived = ?3 WHERE id = ?4",
-                params![input.name, input.color, archived as i64, id],
+                "INSERT INTO projects (id, name, created_at) VALUES (40, 'Work', 0), (41, 'Side', 0)",
+                [],
             )
-            .map_err(|e| e.to_string())?;
-        id
-    } else {
-        model
-            .conn
+            .unwrap();
+        source
             .execute(
-                "INSERT INTO projects (name, color, archived, created_at) VALUES (?1, ?2, ?3, ?4)",
-                params![input.name, input.color, archived as i64, now_ts()],
+                "INSERT INTO tags (id, name, created_at) VALUES (50, 'deep', 0)",
+                [],
             )
-            .map_err(|e| e.to_string())?;
-        model.conn.last_insert_rowid()
-    };
-
-    let project = model
-        .conn
-        .query_row(
-            "SELECT id, name, color, archived FROM projects WHERE id = ?1",
-            params![id],
-            |row| {
-                Ok(Project {
-                    id: row.get(0)?,
-                    name: row.get(1)?,
-                    color: row.get(2)?,
-                    archived: row.get::<_, i64>(3)? == 1,
-                })
+            .unwrap();
+        insert_focus_session(&source, 1_700_001_500, 1500);
+        let second = insert_focus_session(&source, 1_700_005_000, 1500);
+        source
+            .execute(
+                "UPDATE sessions SET project_id = 41 WHERE id = ?1",
+                params![second],
+            )
+            .unwrap();
+        source
+            .execute(
+                "INSERT INTO session_tags (session_id, tag_id) VALUES (?1, 50)",
+                params![second],
+            )
+            .unwrap();
+        let export = build_backup_payload(
+            &source,
+            &sample_settings(),
+            &ExportRange {
+                from: None,
+                to: None,
             },
         )
-        .map_err(|e| e.to_string())?;
+        .unwrap();
+        assert_eq!(export["schemaVersion"], BACKUP_SCHEMA_VERSION);
+        let backup: BackupPayload = serde_json::from_value(export).unwrap();
 
-    Ok(project)
-}
+        let mut target = test_conn();
+        target
+            .execute(
+                "INSERT INTO projects (id, name, created_at) VALUES (3, 'work', 0)",
+                [],
+            )
+            .unwrap();
+        insert_focus_session(&target, 1_700_001_500, 1500);
 
-#[tauri::command]
-fn tags_list(state: State<'_, AppState>) -> AppResult<Vec<Tag>> {
-    let model = lock_model(&state)?;
-    fetch_tags(&model.conn)
-}
+        let merged = import_backup(&mut target, &backup, ImportMode::Merge, 10).unwrap();
+        assert_eq!(
+            (
+                merged.projects_created,
+                merged.tags_created,
+                merged.sessions_imported,
+                merged.sessions_skipped,
+                merged.settings_restored
+            ),
+            (1, 1, 1, 1, false)
+        );
+        let (project, tag): (String, String) = target
+            .query_row(
+                "SELECT p.name, t.name FROM sessions s
+                 JOIN projects p ON p.id = s.project_id
+                 JOIN session_tags st ON st.session_id = s.id
+                 JOIN tags t ON t.id = st.tag_id
+                 WHERE s.started_at = ?1",
+                params![1_700_005_000 - 1500],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!((project.as_str(), tag.as_str()), ("Side", "deep"));
 
-#[tauri::command]
-fn tags_upsert(input: TagInput, state: State<'_, AppState>) -> AppResult<Tag> {
-    let model = lock_model(&state)?;
+        let replaced = import_backup(&mut target, &backup, ImportMode::Replace, 10).unwrap();
+        assert_eq!(
+            (replaced.projects_created, replaced.sessions_imported),
+            (2, 2)
+        );
+        assert!(replaced.settings_restored);
+        let projects: i64 = target
+            .query_row("SELECT COUNT(*) FROM projects", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(projects, 2);
 
-    let id = if let Some(id) = input.id {
-        model
-            .conn
-            .execute(
-                "UPDATE tags SET name = ?1 WHERE id = ?2",
-                params![input.name, id],
+        let future = BackupPayload {
+            schema_version: Some(BACKUP_SCHEMA_VERSION + 1),
+            ..BackupPayload::default()
+        };
+        assert!(import_backup(&mut target, &future, ImportMode::Merge, 10)
+            .unwrap_err()
+            .contains("unsupported backup schema version"));
+    }
+
+    #[test]
+    fn import_csv_maps_columns_and_creates_names() {
+        let mut conn = test_conn();
+        conn.execute(
+            "INSERT INTO projects (name, created_at) VALUES ('Work', 0)",
+            [],
+        )
+        .unwrap();
+        let csv = "Start,End,Client,Labels\n\
+                   2023-11-15T10:00:00+00:00,2023-11-15T10:25:00+00:00,work,deep|Read\n\
+                   2023-11-15T11:00:00+00:00,2023-11-15T11:50:00+00:00,Acme,read\n\
+                   2023-11-15T11:00:00+00:00,2023-11-15T11:50:00+00:00,Acme,\n\
+                   2023-11-15T13:00:00+00:00,2023-11-15T12:00:00+00:00,,\n\
+                   soon,2023-11-15T12:00:00+00:00,,\n";
+        let mapping = CsvColumnMapping {
+            started_at: "Start".to_string(),
+            ended_at: "End".to_string(),
+            project_name: Some("Client".to_string()),
+            tag_names: Some("Labels".to_string()),
+            tag_separator: Some("|".to_string()),
+            ..CsvColumnMapping::default()
+        };
+        let batch = parse_csv_import(csv, &mapping).unwrap();
+        let result = import_sessions(&mut conn, batch, 10).unwrap();
+
+        assert_eq!((result.imported, result.duplicates), (2, 1));
+        assert_eq!(result.projects_created, vec!["Acme".to_string()]);
+        assert_eq!(
+            result.tags_created,
+            vec!["deep".to_string(), "Read".to_string()]
+        );
+        assert_eq!(
+            result.errors.iter().map(|e| e.row).collect::<Vec<_>>(),
+            vec![4, 5]
+        );
+        let (phase, duration, project): (String, i64, String) = conn
+            .query_row(
+                "SELECT s.phase, s.duration_sec, p.name FROM sessions s JOIN projects p ON p.id = s.project_id
+                 ORDER BY s.started_at LIMIT 1",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
             )
-            .map_err(|e| e.to_string())?;
-        id
-    } else {
-        model
-            .conn
-            .execute(
-                "INSERT INTO tags (name, created_at) VALUES (?1, ?2)",
-                params![input.name, now_ts()],
+            .unwrap();
+        assert_eq!(
+            (phase.as_str(), duration, project.as_str()),
+            ("focus", 1500, "Work")
+        );
+        let read_uses: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM session_tags st JOIN tags t ON t.id = st.tag_id WHERE t.name = 'Read'",
+                [],
+                |row| row.get(0),
             )
-            .map_err(|e| e.to_string())?;
-        model.conn.last_insert_rowid()
-    };
+            .unwrap();
+        assert_eq!(read_uses, 2);
 
-    let tag = model
-        .conn
-        .query_row(
-            "SELECT id, name FROM tags WHERE id = ?1",
-            params![id],
-            |row| {
-                Ok(Tag {
-                    id: row.get(0)?,
-                    name: row.get(1)?,
-                })
-            },
-        )
-        .map_err(|e| e.to_string())?;
+        let exported = "startedAt,endedAt,phase,durationSec,completed\n\
+                        1700000000,1700000600,short_break,600,false\n\
+                        1700000300,1700000900,focus,600,true\n";
+        let batch = parse_csv_import(exported, &CsvColumnMapping::pulse_export()).unwrap();
+        let result = import_sessions(&mut conn, batch, 10).unwrap();
+        assert_eq!(result.imported, 1);
+        let (break_id, completed): (i64, bool) = conn
+            .query_row(
+                "SELECT id, completed FROM sessions WHERE started_at = 1700000000",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert!(!completed);
+        assert_eq!(
+            result.errors,
+            vec![ImportRowError {
+                row: 2,
+                message: SessionValidationError::Overlap(break_id).to_string(),
+            }]
+        );
 
-    Ok(tag)
-}
+        let missing = CsvColumnMapping {
+            duration_sec: Some("Minutes".to_string()),
+            ..mapping
+        };
+        assert_eq!(
+            parse_csv_import(csv, &missing).err().unwrap(),
+            "CSV file is missing the Minutes column"
+        );
+    }
 
-#[tauri::command]
-fn export_csv(range: ExportRange, state: State<'_, AppState>) -> AppResult<ExportResult> {
-    let model = lock_model(&state)?;
-    let sessions = fetch_sessions(
-        &model.conn,
-        &AnalyticsRange {
-            from: range.from,
-            to: range.to,
-            project_id: None,
-            tag_id: None,
-        },
-    )?;
+    #[test]
+    fn ical_export_has_one_event_per_focus_session() {
+        let conn = test_conn();
+        conn.execute(
+            "INSERT INTO projects (id, name, created_at) VALUES (1, 'Client, Inc; EU', 0)",
+            [],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO tags (id, name, created_at) VALUES (2, 'deep', 0)",
+            [],
+        )
+        .unwrap();
+        let focus = insert_focus_session(&conn, 1_700_001_500, 1500);
+        conn.execute(
+            "UPDATE sessions SET project_id = 1, notes = ?2 WHERE id = ?1",
+            params![focus, "x".repeat(100)],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO session_tags (session_id, tag_id) VALUES (?1, 2)",
+            params![focus],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO sessions (started_at, ended_at, phase, duration_sec, completed, interruptions)
+             VALUES (1700001500, 1700001800, 'short_break', 300, 1, 0)",
+            [],
+        )
+        .unwrap();
 
-    let mut csv = String::from(
-        "id,startedAt,endedAt,phase,durationSec,completed,interruptions,projectId,tagIds\n",
-    );
+        let sessions = fetch_sessions(&conn, &AnalyticsRange::default()).unwrap();
+        let ics = sessions_to_ics(&conn, &sessions, 1_700_002_000).unwrap();
 
-    for s in sessions {
-        let tag_ids = s
-            .tag_ids
-            .iter()
-            .map(std::string::ToString::to_string)
-            .collect::<Vec<_>>()
-            .join(";");
-        csv.push_str(&format!(
-            "{},{},{},{},{},{},{},{},{}\n",
-            s.id,
-            s.started_at,
-            s.ended_at,
-            s.phase.as_db_value(),
-            s.duration_sec,
-            s.completed,
-            s.interruptions,
-            s.project_id
-                .map(|v| v.to_string())
-                .unwrap_or_else(|| "".to_string()),
-            tag_ids
-        ));
+        assert!(ics.starts_with("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n"));
+        assert!(ics.ends_with("END:VCALENDAR\r\n"));
+        assert_eq!(ics.matches("BEGIN:VEVENT").count(), 1);
+        assert!(ics.contains("DTSTART:20231114T221320Z\r\n"));
+        assert!(ics.contains("DTEND:20231114T223820Z\r\n"));
+        assert!(ics.contains("SUMMARY:Focus: Client\\, Inc\\; EU\r\n"));
+        assert!(ics.lines().all(|line| line.len() <= 75));
+        let unfolded = ics.replace("\r\n ", "");
+        assert!(unfolded.contains(&format!("DESCRIPTION:Tags: deep\\n{}", "x".repeat(100))));
     }
 
-    Ok(ExportResult {
-        filename: format!("pomodoro-sessions-{}.csv", now_ts()),
-        content: csv,
-    })
-}
+    #[test]
+    fn report_lists_totals_projects_days_and_notable_sessions() {
+        let conn = test_conn();
+        conn.execute(
+            "INSERT INTO projects (id, name, created_at) VALUES (1, 'R&D <core>', 0)",
+            [],
+        )
+        .unwrap();
+        let long = insert_focus_session(&conn, 1_700_003_000, 3000);
+        conn.execute(
+            "UPDATE sessions SET project_id = 1, notes = 'shipped the parser' WHERE id = ?1",
+            params![long],
+        )
+        .unwrap();
+        insert_focus_session(&conn, 1_700_100_000, 1500);
 
-#[tauri::command]
-fn export_json(range: ExportRange, state: State<'_, AppState>) -> AppResult<ExportResult> {
-    let model = lock_model(&state)?;
+        let report = build_report(&conn, &sample_settings(), &AnalyticsRange::default()).unwrap();
+        assert_eq!(report.summary.total_focus_sec, 4500);
+        assert_eq!(report.projects[0].0, "R&D <core>");
+        assert_eq!(report.projects[1].0, "No project");
+        assert_eq!(report.best_days[0].1.focus_seconds, 3000);
+        assert_eq!(report.notable.len(), 1);
+        assert_eq!(report.notable[0].0, "Longest session");
 
-    let sessions = fetch_sessions(
-        &model.conn,
-        &AnalyticsRange {
-            from: range.from,
-            to: range.to,
-            project_id: None,
-            tag_id: None,
-        },
-    )?;
-    let projects = fetch_projects(&model.conn)?;
-    let tags = fetch_tags(&model.conn)?;
+        let markdown = render_report_markdown(&report);
+        assert!(markdown.starts_with("# Focus report: all time\n"));
+        assert!(markdown.contains("- Focus time: 1h 15m\n"));
+        assert!(markdown.contains("| R&D <core> | 50m | 1 | 66% |\n"));
+        assert!(markdown.contains("— shipped the parser\n"));
 
-    let payload = serde_json::json!({
-      "exportedAt": now_ts(),
-      "settings": model.settings,
-      "projects": projects,
-      "tags": tags,
-      "sessions": sessions
-    });
+        let html = render_report_html(&report);
+        assert!(html.contains("<td>R&amp;D &lt;core&gt;</td>"));
+        assert!(!html.contains("<core>"));
+    }
 
-    Ok(ExportResult {
-        filename: format!("pomodoro-backup-{}.json", now_ts()),
-        content: serde_json::to_string_pretty(&payload).map_err(|e| e.to_string())?,
-    })
-}
+    #[test]
+    fn local_backups_are_written_listed_and_pruned() {
+        let conn = test_conn();
+        insert_focus_session(&conn, 1_700_000_000, 1500);
+        let dir = std::env::temp_dir().join(format!("pomodoro-backups-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let mut settings = sample_settings();
+        settings.backup_enabled = true;
+        settings.backup_interval_hours = 24;
+        let config = CloudBackupConfig {
+            password: "webdav-secret".to_string(),
+            ..Default::default()
+        };
+        save_json_setting(&conn, CLOUD_BACKUP_KEY, &config).unwrap();
 
-#[tauri::command]
-fn settings_get(state: State<'_, AppState>) -> AppResult<AppSettings> {
-    let model = lock_model(&state)?;
-    Ok(model.settings.clone())
-}
+        assert!(local_backup_due(&settings, &[], 1_700_000_000));
+        for (i, format) in ["json", "sqlite", "json"].iter().enumerate() {
+            settings.backup_format = format.to_string();
+            let content = snapshot_local_backup(&conn, &settings).unwrap();
+            write_local_backup(&dir, format, &content, 1_700_000_000 + i as i64 * 3600).unwrap();
+        }
+        fs::write(dir.join("notes.txt"), "keep me").unwrap();
 
-#[tauri::command]
-fn settings_update(
-    app: AppHandle,
-    patch: AppSettingsPatch,
-    state: State<'_, AppState>,
-) -> AppResult<AppSettings> {
-    let (settings, timer) = {
-        let mut model = lock_model(&state)?;
+        let backups = list_local_backups(&dir).unwrap();
+        assert_eq!(backups.len(), 3);
+        assert_eq!(backups[0].created_at, 1_700_007_200);
+        assert_eq!(backups[1].format, "sqlite");
+        assert!(backups.iter().all(|b| b.size_bytes > 0));
 
-        if let Some(v) = patch.focus_min {
-            model.settings.focus_min = v;
-        }
-        if let Some(v) = patch.short_break_min {
-            model.settings.short_break_min = v;
-        }
-        if let Some(v) = patch.long_break_min {
-            model.settings.long_break_min = v;
-        }
-        if let Some(v) = patch.long_break_every {
-            model.settings.long_break_every = v;
-        }
-        if let Some(v) = patch.theme {
-            model.settings.theme = v.trim().to_lowercase();
-        }
-        if let Some(v) = patch.sound_enabled {
-            model.settings.sound_enabled = v;
-        }
-        if let Some(v) = patch.notifications_enabled {
-            model.settings.notifications_enabled = v;
-        }
-        if let Some(v) = patch.remote_control_enabled {
-            model.settings.remote_control_enabled = v;
-        }
-        if let Some(v) = patch.remote_control_port {
-            model.settings.remote_control_port = v;
-        }
-        if let Some(v) = patch.remote_control_token {
-            model.settings.remote_control_token = v;
-        }
+        let json: serde_json::Value =
+            serde_json::from_slice(&fs::read(&backups[0].path).unwrap()).unwrap();
+        assert_eq!(json["sessions"].as_array().unwrap().len(), 1);
+        let copy = Connection::open(&backups[1].path).unwrap();
+        let count: i64 = copy
+            .query_row("SELECT COUNT(*) FROM sessions", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 1);
+        assert!(
+            load_json_setting::<CloudBackupConfig>(&copy, CLOUD_BACKUP_KEY)
+                .unwrap()
+                .is_none()
+        );
+        drop(copy);
+
+        assert!(!local_backup_due(&settings, &backups, 1_700_007_200 + 3600));
+        assert!(local_backup_due(
+            &settings,
+            &backups,
+            1_700_007_200 + 24 * 3600
+        ));
+        settings.backup_enabled = false;
+        assert!(!local_backup_due(
+            &settings,
+            &backups,
+            1_700_007_200 + 24 * 3600
+        ));
 
-        model.settings = normalize_settings(model.settings.clone());
-        if model.settings.remote_control_token.trim().is_empty() {
-            ensure_remote_token(&mut model.settings);
-        }
-        save_json_setting(&model.conn, APP_SETTINGS_KEY, &model.settings)?;
+        assert_eq!(prune_local_backups(&dir, 2).unwrap(), 1);
+        let left = list_local_backups(&dir).unwrap();
+        assert_eq!(left.len(), 2);
+        assert_eq!(left[1].created_at, 1_700_003_600);
+        assert!(dir.join("notes.txt").exists());
 
-        // Keep the current phase duration in sync if timer is idle.
-        if !model.timer.is_running {
-            model.timer.phase_total_seconds = model
-                .settings
-                .duration_for_phase_seconds(&model.timer.phase);
-            model.timer.remaining_seconds = model.timer.phase_total_seconds;
-            model.timer.started_at = None;
-            model.timer.target_ends_at = None;
-            save_timer_state(&model.conn, &model.timer)?;
-        }
+        // Two backups in the same second both land.
+        let second = write_local_backup(&dir, "json", b"{}", 1_700_007_200).unwrap();
+        assert_eq!(
+            second.filename,
+            format!("{LOCAL_BACKUP_PREFIX}1700007200-2.json")
+        );
+        assert_eq!(list_local_backups(&dir).unwrap().len(), 3);
+        fs::remove_dir_all(&dir).unwrap();
+    }
 
-        (model.settings.clone(), model.timer.clone())
-    };
+    #[test]
+    fn streamed_exports_match_in_memory_exports() {
+        let conn = test_conn();
+        for i in 0..5 {
+            insert_focus_session(&conn, 1_700_000_000 + i * 3600, 1500);
+        }
+        let range = AnalyticsRange::default();
+        let (filter, values) = build_sessions_filter(&range);
+        let mut pages = 0;
+        let mut next_page = |cursor: Option<&str>| {
+            pages += 1;
+            fetch_session_keyset(&conn, &filter, values.clone(), cursor, 2)
+        };
 
-    // Start/stop/restart remote control server based on settings.
-    remote_apply(&app, &settings)?;
+        let options = CsvOptions::default();
+        let writer = SessionCsvWriter::new(&conn, &options, "UTC").unwrap();
+        let mut csv = Vec::new();
+        assert_eq!(
+            write_sessions_csv(&mut csv, &writer, &mut next_page).unwrap(),
+            5
+        );
+        let sessions = fetch_sessions(&conn, &range).unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            sessions_to_csv(&conn, &sessions, &options, "UTC").unwrap()
+        );
+        assert_eq!(pages, 3);
 
-    emit_timer_state(&app, &timer);
-    Ok(settings)
-}
+        let header = serde_json::json!({ "schemaVersion": BACKUP_SCHEMA_VERSION, "tags": [] });
+        let mut json = Vec::new();
+        let rows = write_sessions_json(&mut json, &header, Some("UTC"), |cursor| {
+            fetch_session_keyset(&conn, &filter, values.clone(), cursor, 2)
+        })
+        .unwrap();
+        assert_eq!(rows, 5);
+        let parsed: serde_json::Value = serde_json::from_slice(&json).unwrap();
+        assert_eq!(parsed["schemaVersion"], BACKUP_SCHEMA_VERSION);
+        let streamed = parsed["sessions"].as_array().unwrap();
+        assert_eq!(streamed.len(), 5);
+        assert_eq!(streamed[0]["id"], sessions[0].id);
+        assert_eq!(streamed[4]["endedAtLocal"], "2023-11-14T22:13:20+00:00");
+    }
 
-#[tauri::command]
-fn reset_all_data(app: AppHandle, state: State<'_, AppState>) -> AppResult<ResetAllResult> {
-    let (settings, timer) = {
-        let mut model = lock_model(&state)?;
+    #[test]
+    fn database_import_validates_and_swaps_the_file() {
+        let dir = std::env::temp_dir().join(format!("pomodoro-dbimport-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let live = dir.join("pomodoro.db");
+        let mut conn = open_database(&live).unwrap().0;
+        insert_focus_session(&conn, 1_700_000_000, 1500);
 
+        let other = dir.join("other.db");
         {
-            let tx = model.conn.transaction().map_err(|e| e.to_string())?;
-            tx.execute("DELETE FROM session_tags", [])
-                .map_err(|e| e.to_string())?;
-            tx.execute("DELETE FROM sessions", [])
-                .map_err(|e| e.to_string())?;
-            tx.execute("DELETE FROM projects", [])
-                .map_err(|e| e.to_string())?;
-            tx.execute("DELETE FROM tags", [])
-                .map_err(|e| e.to_string())?;
-            tx.execute("DELETE FROM settings", [])
-                .map_err(|e| e.to_string())?;
-            tx.execute(
-                "DELETE FROM sqlite_sequence WHERE name IN ('projects', 'tags', 'sessions')",
-                [],
-            )
-            .map_err(|e| e.to_string())?;
-            tx.commit().map_err(|e| e.to_string())?;
+            let source = open_database(&other).unwrap().0;
+            insert_focus_session(&source, 1_700_000_000, 1500);
+            insert_focus_session(&source, 1_700_003_600, 1500);
         }
+        let foreign = dir.join("foreign.db");
+        Connection::open(&foreign)
+            .unwrap()
+            .execute_batch("CREATE TABLE notes (body TEXT)")
+            .unwrap();
+        fs::write(dir.join("garbage.db"), "not a database at all").unwrap();
 
-        model.settings = normalize_settings(AppSettings::default());
-        ensure_remote_token(&mut model.settings);
-        model.timer = TimerState::default_with_settings(&model.settings);
-        save_json_setting(&model.conn, APP_SETTINGS_KEY, &model.settings)?;
-        save_timer_state(&model.conn, &model.timer)?;
-
-        (model.settings.clone(), model.timer.clone())
-    };
+        let staging = dir.join("pomodoro.importing.db");
+        let error = stage_database_import(&foreign, &staging).unwrap_err();
+        assert!(
+            error.contains("missing settings, projects, tags, sessions"),
+            "{error}"
+        );
+        assert!(stage_database_import(&dir.join("garbage.db"), &staging).is_err());
+        assert!(!staging.exists());
 
-    remote_apply(&app, &settings)?;
-    emit_timer_state(&app, &timer);
-    Ok(ResetAllResult { settings, timer })
-}
+        stage_database_import(&other, &staging).unwrap();
+        let backup = swap_database_file(&mut conn, &staging, 1_700_010_000).unwrap();
+        let count = |conn: &Connection| -> i64 {
+            conn.query_row("SELECT COUNT(*) FROM sessions", [], |row| row.get(0))
+                .unwrap()
+        };
+        assert_eq!(count(&conn), 2);
+        assert!(!staging.exists());
+        assert!(backup.ends_with("pomodoro.before-import-1700010000.db"));
+        assert_eq!(count(&Connection::open(&backup).unwrap()), 1);
 
-#[tauri::command]
-fn session_history(
-    range: AnalyticsRange,
-    state: State<'_, AppState>,
-) -> AppResult<Vec<SessionRecord>> {
-    let model = lock_model(&state)?;
-    fetch_sessions(&model.conn, &range)
-}
+        // A failed swap puts the current file back and keeps working on it.
+        assert!(swap_database_file(&mut conn, &dir.join("missing.db"), 1_700_020_000).is_err());
+        assert_eq!(count(&conn), 2);
+        assert!(conn.path().is_some_and(|path| !path.is_empty()));
 
-#[tauri::command]
-fn get_local_ip() -> Result<String, String> {
-    let socket = UdpSocket::bind("0.0.0.0:0").map_err(|e| e.to_string())?;
-    socket.connect("8.8.8.8:80").map_err(|e| e.to_string())?;
-    Ok(socket
-        .local_addr()
-        .map_err(|e| e.to_string())?
-        .ip()
-        .to_string())
-}
+        let exported = dir.join("exported.db");
+        fs::write(&exported, "stale").unwrap();
+        assert!(vacuum_into(&conn, &exported).unwrap() > 0);
+        assert_eq!(count(&Connection::open(&exported).unwrap()), 2);
 
-#[cfg_attr(mobile, tauri::mobile_entry_point)]
-pub fn run() {
-    tauri::Builder::default()
-        .plugin(tauri_plugin_opener::init())
-        .plugin(tauri_plugin_dialog::init())
-        .plugin(tauri_plugin_fs::init())
-        .plugin(tauri_plugin_notification::init())
-        .setup(|app| {
-            let app_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
-            fs::create_dir_all(&app_dir).map_err(|e| e.to_string())?;
-            let db_path = app_dir.join("pomodoro.db");
-            let conn = Connection::open(db_path).map_err(|e| e.to_string())?;
+        // Exports leave the cloud backup credentials behind.
+        let config = CloudBackupConfig {
+            password: "webdav-secret".to_string(),
+            passphrase: "correct horse".to_string(),
+            ..Default::default()
+        };
+        save_json_setting(&conn, CLOUD_BACKUP_KEY, &config).unwrap();
+        assert!(export_database_file(&conn, &exported).unwrap() > 0);
+        let copy = Connection::open(&exported).unwrap();
+        assert_eq!(count(&copy), 2);
+        assert!(
+            load_json_setting::<CloudBackupConfig>(&copy, CLOUD_BACKUP_KEY)
+                .unwrap()
+                .is_none()
+        );
+        drop(copy);
+        let bytes = fs::read(&exported).unwrap();
+        assert!(!bytes.windows(13).any(|w| w == b"webdav-secret"));
+        assert!(!dir.join("exported.staged").exists());
+        drop(conn);
+        fs::remove_dir_all(&dir).unwrap();
+    }
 
-            init_database(&conn)?;
-            let settings = load_or_create_settings(&conn)?;
-            let timer = load_or_create_timer(&conn, &settings)?;
+    #[test]
+    fn websocket_handshake_and_frames_follow_rfc_6455() {
+        // The example from RFC 6455 section 1.3.
+        assert_eq!(
+            websocket_accept_key("dGhlIHNhbXBsZSBub25jZQ=="),
+            "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+        );
 
-            app.manage(AppState {
-                model: Mutex::new(AppModel {
-                    conn,
-                    settings,
-                    timer,
-                }),
-                remote: Mutex::new(RemoteControlState { server: None }),
-            });
+        assert_eq!(websocket_frame(0x1, b"hi"), vec![0x81, 2, b'h', b'i']);
+        let long = websocket_frame(0x1, &[0u8; 300]);
+        assert_eq!(&long[..4], &[0x81, 126, 1, 44]);
 
-            setup_tray(app.handle())?;
+        // A masked client ping carrying "ok", followed by the start of another frame.
+        let mask = [1u8, 2, 3, 4];
+        let mut buf = vec![0x89, 0x82];
+        buf.extend_from_slice(&mask);
+        buf.extend_from_slice(&[b'o' ^ 1, b'k' ^ 2]);
+        buf.push(0x88);
+        let (opcode, payload, used) = parse_websocket_frame(&buf).unwrap().unwrap();
+        assert_eq!(
+            (opcode, payload.as_slice(), used),
+            (0x9, b"ok".as_slice(), 8)
+        );
+        assert_eq!(parse_websocket_frame(&buf[used..]).unwrap(), None);
+        assert!(parse_websocket_frame(&[0x81, 0x02, b'h', b'i']).is_err());
 
-            {
-                let state = app.state::<AppState>();
-                let model = state.model.lock().map_err(|e| e.to_string())?;
-                update_tray_title(app.handle(), &model.timer);
-            }
+        let message = websocket_event_message("timer://state", "{\"isRunning\":true}");
+        let parsed: serde_json::Value = serde_json::from_str(&message).unwrap();
+        assert_eq!(parsed["event"], "timer://state");
+        assert_eq!(parsed["payload"]["isRunning"], true);
+    }
 
-            spawn_timer_worker(app.handle().clone());
+    #[test]
+    fn stream_slots_cap_open_sessions_until_dropped() {
+        let open = Arc::new(AtomicUsize::new(0));
+        let first = StreamSlot::acquire(&open, 2).unwrap();
+        let second = StreamSlot::acquire(&open, 2).unwrap();
+        assert!(StreamSlot::acquire(&open, 2).is_none());
+        drop(first);
+        let third = StreamSlot::acquire(&open, 2);
+        assert!(third.is_some());
+        drop((second, third));
+        assert_eq!(open.load(Ordering::SeqCst), 0);
+    }
 
-            // Remote control server (optional; disabled by default).
-            {
-                let state = app.state::<AppState>();
-                let model = state.model.lock().map_err(|e| e.to_string())?;
-                if let Err(error) = remote_apply(app.handle(), &model.settings) {
-                    eprintln!("remote control startup warning: {error}");
-                }
-            }
-            Ok(())
-        })
-        .invoke_handler(tauri::generate_handler![
-            timer_start,
-            timer_pause,
-            timer_resume,
-            timer_skip,
-            timer_get_state,
-            timer_set_context,
-            session_complete,
-            analytics_get_summary,
-            analytics_get_timeseries,
-            projects_list,
-            projects_upsert,
-            tags_list,
-            tags_upsert,
-            export_csv,
-            export_json,
-            settings_get,
-            settings_update,
-            reset_all_data,
-            session_history,
-            get_local_ip,
-        ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
-}
+    #[test]
+    fn remote_pool_turns_away_overflow_and_drains_the_queue_on_stop() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.set_nonblocking(true).unwrap();
+        let addr = listener.local_addr().unwrap();
+        let stop = Arc::new(AtomicBool::new(true));
+        let (started_tx, started_rx) = std::sync::mpsc::channel::<()>();
+        let (gate_tx, gate_rx) = std::sync::mpsc::channel::<()>();
+        let started_tx = Mutex::new(started_tx);
+        let gate_rx = Mutex::new(gate_rx);
+        let served = Arc::new(AtomicUsize::new(0));
+        let served_count = served.clone();
+        let running = stop.clone();
+        // One worker and room for one waiting connection.
+        let server = thread::spawn(move || {
+            remote_accept_loop(vec![listener], running, 1, 1, move |mut stream, _, _| {
+                let _ = started_tx.lock().unwrap().send(());
+                let _ = gate_rx.lock().unwrap().recv();
+                served_count.fetch_add(1, Ordering::SeqCst);
+                let _ = stream.write_all(b"ok");
+            })
+        });
+        let read_all = |stream: &mut std::net::TcpStream| {
+            stream
+                .set_read_timeout(Some(Duration::from_secs(5)))
+                .unwrap();
+            let mut text = String::new();
+            let _ = stream.read_to_string(&mut text);
+            text
+        };
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+        let mut busy_worker = std::net::TcpStream::connect(addr).unwrap();
+        started_rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        let mut waiting = std::net::TcpStream::connect(addr).unwrap();
+        thread::sleep(Duration::from_millis(300));
+        let mut overflow = std::net::TcpStream::connect(addr).unwrap();
+        let turned_away = read_all(&mut overflow);
+        assert!(turned_away.starts_with("HTTP/1.1 503"), "{turned_away}");
+        assert!(turned_away.ends_with("busy"));
 
-    fn sample_settings() -> AppSettings {
-        AppSettings {
-            focus_min: 25,
-            short_break_min: 5,
-            long_break_min: 15,
-            long_break_every: 4,
-            theme: "light".to_string(),
-            sound_enabled: true,
-            notifications_enabled: true,
-            remote_control_enabled: false,
-            remote_control_port: 48484,
-            remote_control_token: "testtoken".to_string(),
-        }
+        // Stopping still serves the connection already queued before the loop returns.
+        stop.store(false, Ordering::SeqCst);
+        gate_tx.send(()).unwrap();
+        gate_tx.send(()).unwrap();
+        server.join().unwrap();
+        assert_eq!(served.load(Ordering::SeqCst), 2);
+        assert_eq!(read_all(&mut busy_worker), "ok");
+        assert_eq!(read_all(&mut waiting), "ok");
     }
 
     #[test]
-    fn advances_to_long_break_every_fourth_cycle() {
-        let settings = sample_settings();
-        let mut timer = TimerState::default_with_settings(&settings);
+    fn remote_broadcast_fans_out_and_drops_closed_subscribers() {
+        let mut broadcast = RemoteBroadcast::default();
+        let first = broadcast.subscribe();
+        let second = broadcast.subscribe();
+        let event = |payload: &str| RemoteEvent {
+            name: "timer://state",
+            payload: payload.to_string(),
+        };
 
-        timer.phase = TimerPhase::Focus;
-        timer.cycle_index = 3;
-        advance_timer(&mut timer, &settings);
+        broadcast.publish(event("{\"a\":1}"));
+        assert_eq!(first.try_recv().unwrap().payload, "{\"a\":1}");
+        assert_eq!(second.try_recv().unwrap().payload, "{\"a\":1}");
 
-        assert_eq!(timer.phase, TimerPhase::LongBreak);
-        assert_eq!(timer.cycle_index, 4);
+        drop(first);
+        broadcast.publish(event("{\"a\":2}"));
+        assert_eq!(broadcast.subscribers.len(), 1);
+        assert_eq!(second.try_recv().unwrap().payload, "{\"a\":2}");
+
+        assert_eq!(
+            sse_message("timer://state", "{\"a\":1}"),
+            "event: timer://state\ndata: {\"a\":1}\n\n"
+        );
+        assert_eq!(
+            sse_message("x", "one\ntwo"),
+            "event: x\ndata: one\ndata: two\n\n"
+        );
+    }
+
+    #[test]
+    fn billing_report_prices_billable_shares_only() {
+        let mut conn = test_conn();
+        conn.execute(
+            "INSERT INTO projects (name, billable, hourly_rate, created_at)
+             VALUES ('Client', 1, 90.0, 0), ('Internal', 0, 50.0, 0)",
+            [],
+        )
+        .unwrap();
+        let whole = insert_focus_session(&conn, 1_700_003_600, 3600);
+        conn.execute(
+            "UPDATE sessions SET project_id = 1 WHERE id = ?1",
+            params![whole],
+        )
+        .unwrap();
+        let split = insert_focus_session(&conn, 1_700_010_000, 1800);
+        write_session_splits(
+            &mut conn,
+            split,
+            &[
+                ProjectSplit {
+                    project_id: 1,
+                    percent: 50,
+                },
+                ProjectSplit {
+                    project_id: 2,
+                    percent: 50,
+                },
+            ],
+        )
+        .unwrap();
+
+        let sessions = vec![
+            fetch_session(&conn, whole).unwrap(),
+            fetch_session(&conn, split).unwrap(),
+        ];
+        let projects = fetch_project_map(&conn).unwrap();
+        let report = billing_report(&sessions, &projects, None, "month").unwrap();
+        assert_eq!(report.by_project.len(), 1);
+        assert_eq!(report.total_billable_seconds, 3600 + 900);
+        assert!((report.total_earnings - 112.5).abs() < 1e-9);
+        assert_eq!(report.by_period.len(), 1);
+        assert!(billing_report(&sessions, &projects, None, "decade").is_err());
     }
 
     #[test]
@@ -2104,6 +20615,11 @@ mod tests {
                 interruptions: 0,
                 project_id: None,
                 tag_ids: vec![],
+                notes: None,
+                extended_sec: 0,
+                project_splits: vec![],
+                quality: None,
+                planned: None,
             },
             SessionRecord {
                 id: 2,
@@ -2115,10 +20631,283 @@ mod tests {
                 interruptions: 0,
                 project_id: None,
                 tag_ids: vec![],
+                notes: None,
+                extended_sec: 0,
+                project_splits: vec![],
+                quality: None,
+                planned: None,
             },
         ];
 
-        assert!(calculate_streak_days(&sessions) >= 2);
+        assert!(
+            calculate_streak_days(
+                &focus_days(&sessions),
+                &HashSet::new(),
+                &HashSet::new(),
+                &StreakRules::default(),
+                None
+            )
+            .days
+                >= 2
+        );
+    }
+
+    #[test]
+    fn loopback_host_header_detection() {
+        assert!(is_loopback_host("127.0.0.1:48484"));
+        assert!(is_loopback_host("localhost:48484"));
+        assert!(is_loopback_host("[::1]:48484"));
+        assert!(!is_loopback_host("192.168.1.10:48484"));
+        assert!(!is_loopback_host("evil.example:48484"));
+    }
+
+    #[test]
+    fn mdns_stays_off_for_settings_saved_before_the_option() {
+        assert!(AppSettings::default().remote_mdns_enabled);
+        let saved: AppSettings = serde_json::from_str(r#"{"remoteControlEnabled":true}"#).unwrap();
+        assert!(saved.remote_control_enabled);
+        assert!(!saved.remote_mdns_enabled);
+    }
+
+    #[test]
+    fn remote_settings_hide_secrets_and_lock_remote_access() {
+        let settings = sample_settings();
+        let view = remote_settings_view(&settings).unwrap();
+        assert!(view.get("teamStatsKey").is_none());
+        assert!(view.get("sessionWebhookUrl").is_none());
+        assert!(view.get("backupDirectory").is_none());
+        assert_eq!(view["focusMin"], settings.focus_min);
+
+        let durations = AppSettingsPatch {
+            focus_min: Some(50),
+            notifications_enabled: Some(false),
+            ..Default::default()
+        };
+        assert!(check_remote_settings_patch(&durations).is_ok());
+        let port = AppSettingsPatch {
+            remote_control_port: Some(9000),
+            ..Default::default()
+        };
+        assert_eq!(
+            check_remote_settings_patch(&port).unwrap_err(),
+            "remoteControlPort can only be changed on the desktop"
+        );
+        // A read-only display must not be able to switch itself back to control.
+        assert_eq!(view["remoteReadOnly"], false);
+        let unlock = AppSettingsPatch {
+            remote_read_only: Some(false),
+            ..Default::default()
+        };
+        assert_eq!(
+            check_remote_settings_patch(&unlock).unwrap_err(),
+            "remoteReadOnly can only be changed on the desktop"
+        );
+    }
+
+    #[test]
+    fn mdns_host_label_is_a_dns_label() {
+        assert_eq!(
+            mdns_host_label("Studio's MacBook Pro"),
+            "Studio-s-MacBook-Pro"
+        );
+        assert_eq!(mdns_host_label("  ноутбук  "), "pomodoro");
+        assert_eq!(mdns_host_label(&"a".repeat(80)).len(), 63);
+    }
+
+    #[test]
+    fn negotiate_encoding_prefers_gzip_and_honours_q_zero() {
+        assert_eq!(negotiate_encoding(None), None);
+        assert_eq!(negotiate_encoding(Some("identity")), None);
+        assert_eq!(
+            negotiate_encoding(Some("deflate, gzip;q=0.8")),
+            Some(ContentEncoding::Gzip)
+        );
+        assert_eq!(
+            negotiate_encoding(Some("gzip;q=0, deflate")),
+            Some(ContentEncoding::Deflate)
+        );
+        assert_eq!(
+            negotiate_encoding(Some("GZIP")),
+            Some(ContentEncoding::Gzip)
+        );
+        assert_eq!(negotiate_encoding(Some("*")), Some(ContentEncoding::Gzip));
+        assert_eq!(
+            negotiate_encoding(Some("gzip;q=0, *")),
+            Some(ContentEncoding::Deflate)
+        );
+        assert_eq!(negotiate_encoding(Some("gzip;q=0, deflate;q=0, *")), None);
+    }
+
+    #[test]
+    fn remote_start_body_is_optional_and_carries_duration() {
+        assert!(parse_optional_body::<StartTimerRequest>(b"")
+            .unwrap()
+            .is_none());
+        assert!(parse_optional_body::<StartTimerRequest>(b" \r\n")
+            .unwrap()
+            .is_none());
+        let payload = parse_optional_body::<StartTimerRequest>(
+            br#"{"projectId": 3, "tagIds": [1, 2], "durationMinutes": 50}"#,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(payload.project_id, Some(Some(3)));
+        assert_eq!(payload.tag_ids, Some(vec![1, 2]));
+        assert_eq!(payload.duration_minutes, Some(50));
+        assert!(parse_optional_body::<StartTimerRequest>(b"{").is_err());
+        assert_eq!(
+            remote_route_scope("POST", "/api/context"),
+            RemoteScope::Timer
+        );
+
+        let settings = sample_settings();
+        let timer = TimerState::default_with_settings(&settings);
+        let mut model = AppModel {
+            conn: test_conn(),
+            settings,
+            timer,
+            queued_notification: None,
+            console_detached: false,
+            records_cache: None,
+            breathing_emitted: None,
+            music_playing: None,
+            screen_dim: ScreenDim::default(),
+            db_recovery: None,
+            read_only: None,
+        };
+        let with_minutes = |minutes: i64| {
+            Some(StartTimerRequest {
+                project_id: None,
+                tag_ids: None,
+                duration_minutes: Some(minutes),
+            })
+        };
+        for minutes in [0, MAX_CUSTOM_DURATION_MIN + 1] {
+            assert_eq!(
+                start_timer(&mut model, with_minutes(minutes), false).unwrap_err(),
+                format!("durationMinutes must be between 1 and {MAX_CUSTOM_DURATION_MIN}")
+            );
+        }
+        assert!(!model.timer.is_running);
+
+        let started = start_timer(&mut model, with_minutes(50), false).unwrap();
+        assert!(started.is_running);
+        assert_eq!(started.phase, TimerPhase::Focus);
+        assert_eq!(started.phase_total_seconds, 50 * 60);
+        assert_eq!(started.remaining_seconds, 50 * 60);
+        // Resuming keeps the length the phase started with.
+        model.timer.is_running = false;
+        let resumed = start_timer(&mut model, with_minutes(10), false).unwrap();
+        assert_eq!(resumed.phase_total_seconds, 50 * 60);
+    }
+
+    #[test]
+    fn keep_alive_follows_http_version_defaults() {
+        assert!(wants_keep_alive(Some(1), None));
+        assert!(wants_keep_alive(Some(1), Some("keep-alive")));
+        assert!(!wants_keep_alive(Some(1), Some("Close")));
+        assert!(!wants_keep_alive(Some(1), Some("TE, close")));
+        assert!(!wants_keep_alive(Some(0), None));
+        assert!(wants_keep_alive(Some(0), Some("Keep-Alive")));
+    }
+
+    #[test]
+    fn compress_body_round_trips() {
+        let body = serde_json::to_vec(&vec!["focus session"; 200]).unwrap();
+
+        let gzipped = compress_body(&body, ContentEncoding::Gzip).unwrap();
+        assert!(gzipped.len() < body.len());
+        let mut out = Vec::new();
+        flate2::read::GzDecoder::new(gzipped.as_slice())
+            .read_to_end(&mut out)
+            .unwrap();
+        assert_eq!(out, body);
+
+        let deflated = compress_body(&body, ContentEncoding::Deflate).unwrap();
+        let mut out = Vec::new();
+        flate2::read::ZlibDecoder::new(deflated.as_slice())
+            .read_to_end(&mut out)
+            .unwrap();
+        assert_eq!(out, body);
+    }
+
+    #[test]
+    fn health_counts_rows_per_table() {
+        let conn = test_conn();
+        insert_focus_session(&conn, 1_700_000_000, 1500);
+        insert_focus_session(&conn, 1_700_000_100, 1500);
+        let rows = health_row_counts(&conn).unwrap();
+        assert_eq!(rows["sessions"], 2);
+        assert_eq!(rows["projects"], 0);
+        assert_eq!(rows.len(), HEALTH_COUNTED_TABLES.len());
+    }
+
+    #[test]
+    fn remote_openapi_documents_every_route_and_resolves_refs() {
+        let spec = remote_openapi("1.2.3");
+        assert_eq!(spec["info"]["version"], "1.2.3");
+        for route in REMOTE_API_ROUTES {
+            let operation =
+                &spec["paths"][format!("/api/v1{}", route.path)][route.method.to_ascii_lowercase()];
+            assert_eq!(operation["summary"], route.summary, "{}", route.path);
+        }
+        assert_eq!(
+            spec["paths"]["/api/v1/start"]["post"]["x-pomodoro-scope"],
+            "timer"
+        );
+        assert_eq!(
+            spec["paths"]["/api/v1/settings"]["patch"]["x-pomodoro-scope"],
+            "write"
+        );
+
+        fn refs(value: &serde_json::Value, out: &mut Vec<String>) {
+            match value {
+                serde_json::Value::Object(map) => {
+                    for (key, value) in map {
+                        match (key.as_str(), value.as_str()) {
+                            ("$ref", Some(target)) => out.push(target.to_string()),
+                            _ => refs(value, out),
+                        }
+                    }
+                }
+                serde_json::Value::Array(items) => items.iter().for_each(|v| refs(v, out)),
+                _ => {}
+            }
+        }
+        let mut found = Vec::new();
+        refs(&spec, &mut found);
+        assert!(!found.is_empty());
+        for target in found {
+            let pointer = target.trim_start_matches('#');
+            assert!(spec.pointer(pointer).is_some(), "dangling {target}");
+        }
+    }
+
+    #[test]
+    fn remote_list_query_follows_shared_conventions() {
+        let query =
+            parse_remote_list_query("from=10&to=20&limit=5&cursor=15%3A3&projectId=2&token=x")
+                .unwrap();
+        assert_eq!(query.range.from, Some(10));
+        assert_eq!(query.range.to, Some(20));
+        assert_eq!(query.range.project_id, Some(2));
+        assert_eq!(query.range.tag_id, None);
+        assert_eq!(query.limit, Some(5));
+        assert_eq!(query.cursor.as_deref(), Some("15:3"));
+
+        let empty = parse_remote_list_query("").unwrap();
+        assert!(empty.limit.is_none() && empty.cursor.is_none());
+        assert!(parse_remote_list_query("limit=ten").is_err());
+        assert_eq!(
+            parse_remote_list_query("granularity=week")
+                .unwrap()
+                .range
+                .granularity,
+            Some(Granularity::Week)
+        );
+        assert!(empty.range.granularity.is_none());
+        assert!(parse_remote_list_query("granularity=year").is_err());
+        assert_eq!(decode_query_value("a+b%2Cc%"), "a b,c%");
     }
 
     #[test]
@@ -2148,4 +20937,35 @@ mod tests {
         let err = bind_remote_listener(port).expect_err("expected occupied port bind failure");
         assert!(err.contains("bind failed"));
     }
+
+    #[test]
+    fn remote_ipv6_listener_shares_the_ipv4_port() {
+        let ipv4 = bind_remote_listener(0).expect("expected bind to succeed");
+        let port = ipv4.local_addr().unwrap().port();
+        // Sandboxes without IPv6 simply get no second listener.
+        let Some(ipv6) = bind_remote_listener_v6(port) else {
+            return;
+        };
+        assert!(ipv6.local_addr().unwrap().is_ipv6());
+        assert!(std::net::TcpStream::connect(("::1", port)).is_ok());
+        assert!(std::net::TcpStream::connect(("127.0.0.1", port)).is_ok());
+    }
+
+    #[test]
+    fn remote_listener_falls_back_to_a_following_port() {
+        let occupied =
+            TcpListener::bind("0.0.0.0:0").expect("failed to reserve an occupied test port");
+        let port = occupied
+            .local_addr()
+            .expect("failed to get occupied local addr")
+            .port();
+
+        let (listeners, bound) =
+            bind_remote_listener_with_fallback(port).expect("expected a fallback port");
+        assert!(bound > port && bound <= port.saturating_add(REMOTE_PORT_FALLBACKS));
+        assert!(listeners[0].local_addr().unwrap().is_ipv4());
+        for listener in &listeners {
+            assert_eq!(listener.local_addr().unwrap().port(), bound);
+        }
+    }
 }
@@ -10,8 +10,16 @@ import { useQuery, useQueryClient } from "@tanstack/react-query";
 import {
   analyticsGetSummary,
   analyticsGetTimeseries,
+  backupNow,
+  databaseExport,
+  databaseImport,
+  dbReadOnlyStatus,
   exportCsv,
   exportJson,
+  exportIcal,
+  reportGenerate,
+  importJson,
+  pickTextFile,
   projectsList,
   projectsUpsert,
   // sessionHistory, // Using historyQuery still
@@ -27,18 +35,31 @@ import {
   timerSkip,
   timerStart,
   timerSetContext,
+  planAdd,
+  planList,
+  planRemove,
+  planStartNext,
 } from "./api";
 import type {
   AnalyticsRange,
   AppSettings,
   AppTheme,
+  ImportMode,
+  DataChangedEvent,
+  DataDomain,
+  GoalReachedEvent,
+  MusicPlayEvent,
   PhaseCompletedEvent,
+  ProjectSound,
+  ReadOnlyStatus,
   SessionRecord,
   TimerPhase,
   TimerState,
+  WorkerRestartedEvent,
 } from "./types";
 import "./App.css";
 import { buildAnalyticsRange, statsDaysForPeriod } from "./lib/analyticsRange";
+import { playMusic, stopMusic } from "./lib/musicPlayer";
 
 // Components
 import TimerDisplay from "./components/TimerDisplay";
@@ -47,11 +68,14 @@ import StatsOverview from "./components/StatsOverview";
 import SettingsPanel from "./components/SettingsPanel";
 import HistoryList from "./components/HistoryList";
 import TitleBar from "./components/TitleBar";
+import DbRecoveryDialog from "./components/DbRecoveryDialog";
+import CsvImportDialog from "./components/CsvImportDialog";
 import Sidebar from "./components/Sidebar";
 
 import StatsChart from "./components/StatsChart";
 import { Input } from "@/components/ui/input";
 import { Button } from "@/components/ui/button";
+import { Switch } from "@/components/ui/switch";
 import {
   Select,
   SelectContent,
@@ -61,6 +85,20 @@ import {
 } from "@/components/ui/select";
 import { toast } from "sonner";
 
+// Queries to refetch when data://changed reports a domain.
+const DOMAIN_QUERIES: Record<DataDomain, string[]> = {
+  sessions: ["summary", "series", "history", "history-stats", "goals"],
+  projects: ["projects"],
+  tags: ["tags"],
+  settings: ["settings"],
+  timer: [],
+  days_off: ["summary", "goals"],
+  music: [],
+  experiments: [],
+  remote: [],
+  plan: ["plan"],
+};
+
 function phaseLabel(phase: TimerPhase) {
   switch (phase) {
     case "focus":
@@ -75,24 +113,30 @@ function phaseLabel(phase: TimerPhase) {
 }
 
 function toErrorMessage(error: unknown) {
-  return error instanceof Error ? error.message : String(error);
+  const message = error instanceof Error ? error.message : String(error);
+  if (/database is locked|readonly database|database file is read-only/i.test(message)) {
+    return "The database is in use by another process, so this change can't be saved right now.";
+  }
+  return message;
 }
 
-function playTone() {
+function playTone(sound: ProjectSound) {
   const context = new AudioContext();
   const oscillator = context.createOscillator();
   const gain = context.createGain();
+  const gong = sound === "gong";
+  const length = gong ? 2.5 : 0.4;
 
   oscillator.connect(gain);
   gain.connect(context.destination);
 
-  oscillator.type = "triangle";
-  oscillator.frequency.value = 880;
-  gain.gain.setValueAtTime(0.15, context.currentTime);
-  gain.gain.exponentialRampToValueAtTime(0.001, context.currentTime + 0.4);
+  oscillator.type = gong ? "sine" : "triangle";
+  oscillator.frequency.value = gong ? 110 : 880;
+  gain.gain.setValueAtTime(gong ? 0.3 : 0.15, context.currentTime);
+  gain.gain.exponentialRampToValueAtTime(0.001, context.currentTime + length);
 
   oscillator.start();
-  oscillator.stop(context.currentTime + 0.4);
+  oscillator.stop(context.currentTime + length);
 }
 
 export default function App() {
@@ -102,12 +146,15 @@ export default function App() {
   const [selectedProjectId, setSelectedProjectId] = useState<number | null>(null);
   const [selectedTagIds, setSelectedTagIds] = useState<number[]>([]);
   const [rangeDays, setRangeDays] = useState(14);
+  const [historyFocusOnly, setHistoryFocusOnly] = useState(false);
   const [settingsDraft, setSettingsDraft] = useState<AppSettings | null>(null);
   const [newProjectName, setNewProjectName] = useState("");
   const [newProjectColor, setNewProjectColor] = useState("#f97316");
   const [newTagName, setNewTagName] = useState("");
+  const [newPlanTitle, setNewPlanTitle] = useState("");
   const [statusMessage, setStatusMessage] = useState("");
   const [actionBusy, setActionBusy] = useState(false);
+  const [csvImportContent, setCsvImportContent] = useState<string | null>(null);
 
   // Navigation State
   const [activeTab, setActiveTab] = useState<"timer" | "stats" | "settings">("timer");
@@ -118,8 +165,11 @@ export default function App() {
     [statsPeriod],
   );
   const historyRange: AnalyticsRange = useMemo(
-    () => buildAnalyticsRange(rangeDays),
-    [rangeDays],
+    () => ({
+      ...buildAnalyticsRange(rangeDays),
+      phases: historyFocusOnly ? ["focus"] : undefined,
+    }),
+    [rangeDays, historyFocusOnly],
   );
 
   const settingsQuery = useQuery({
@@ -137,6 +187,11 @@ export default function App() {
     queryFn: tagsList,
   });
 
+  const planQuery = useQuery({
+    queryKey: ["plan"],
+    queryFn: planList,
+  });
+
   const summaryQuery = useQuery({
     queryKey: ["summary", statsRange],
     queryFn: () => analyticsGetSummary(statsRange),
@@ -194,6 +249,12 @@ export default function App() {
     let unlistenState: (() => void) | undefined;
     let unlistenPhase: (() => void) | undefined;
     let unlistenSession: (() => void) | undefined;
+    let unlistenMusicPlay: (() => void) | undefined;
+    let unlistenMusicStop: (() => void) | undefined;
+    let unlistenGoal: (() => void) | undefined;
+    let unlistenWorker: (() => void) | undefined;
+    let unlistenReadOnly: (() => void) | undefined;
+    let unlistenData: (() => void) | undefined;
     // Window close is now handled by TitleBar, but we can keep listener if needed for other things?
     // Actually standard close behavior is fine since we use hide() in TitleBar.
 
@@ -205,7 +266,7 @@ export default function App() {
       unlistenPhase = await listen<PhaseCompletedEvent>(
         "timer://phase-completed",
         async (event) => {
-          if (settingsDraft?.notificationsEnabled) {
+          if (event.payload.notify) {
             let granted = await isPermissionGranted();
             if (!granted) {
               granted = (await requestPermission()) === "granted";
@@ -219,8 +280,8 @@ export default function App() {
               });
             }
           }
-          if (settingsDraft?.soundEnabled) {
-            playTone();
+          if (event.payload.sound && event.payload.sound !== "none") {
+            playTone(event.payload.sound);
           }
 
           queryClient.invalidateQueries({ queryKey: ["summary"] });
@@ -236,6 +297,54 @@ export default function App() {
         queryClient.invalidateQueries({ queryKey: ["history"] });
         queryClient.invalidateQueries({ queryKey: ["history-stats"] });
       });
+
+      unlistenMusicPlay = await listen<MusicPlayEvent>("music://play", (event) => {
+        playMusic(event.payload).catch((error) => {
+          reportActionError(`Failed to play ${event.payload.track.title}.`, error);
+        });
+      });
+      unlistenMusicStop = await listen("music://stop", () => stopMusic());
+
+      unlistenGoal = await listen<GoalReachedEvent>("goal://reached", (event) => {
+        const { goal, value } = event.payload;
+        toast.success(
+          goal === "daily"
+            ? `Daily goal reached: ${value} pomodoros`
+            : `Weekly goal reached: ${value} min`,
+        );
+        queryClient.invalidateQueries({ queryKey: ["goals"] });
+      });
+
+      unlistenData = await listen<DataChangedEvent>("data://changed", (event) => {
+        const keys = new Set(event.payload.domains.flatMap((domain) => DOMAIN_QUERIES[domain] ?? []));
+        keys.forEach((key) => queryClient.invalidateQueries({ queryKey: [key] }));
+      });
+
+      unlistenWorker = await listen<WorkerRestartedEvent>("system://worker-restarted", (event) => {
+        toast.error(`Timer worker restarted after an error: ${event.payload.message}`);
+      });
+
+      const showReadOnly = (status: ReadOnlyStatus) => {
+        if (status.readOnly) {
+          toast.warning("Read-only mode", {
+            id: "db-read-only",
+            description:
+              "The database is in use by another process. The timer keeps running; sessions are saved once it is free.",
+            duration: Infinity,
+          });
+        } else {
+          toast.dismiss("db-read-only");
+          toast.success(`Database writable again: ${status.flushedSessions} queued sessions saved.`);
+          refreshAll();
+        }
+      };
+      unlistenReadOnly = await listen<ReadOnlyStatus>("db://read-only", (event) =>
+        showReadOnly(event.payload),
+      );
+      // The startup event can fire before this listener exists.
+      dbReadOnlyStatus()
+        .then((status) => status.readOnly && showReadOnly(status))
+        .catch(() => undefined);
     }
 
     setupListeners().catch((error) => {
@@ -246,8 +355,15 @@ export default function App() {
       unlistenState?.();
       unlistenPhase?.();
       unlistenSession?.();
+      unlistenMusicPlay?.();
+      unlistenMusicStop?.();
+      unlistenGoal?.();
+      unlistenWorker?.();
+      unlistenReadOnly?.();
+      unlistenData?.();
+      stopMusic();
     };
-  }, [queryClient, settingsDraft?.notificationsEnabled, settingsDraft?.soundEnabled]);
+  }, [queryClient]);
 
   async function refreshAll() {
     await Promise.all([
@@ -336,6 +452,45 @@ export default function App() {
     }
   }
 
+  // The queued item carries the project and tags picked when it was added.
+  async function onAddPlanItem() {
+    const title = newPlanTitle.trim();
+    if (!title) {
+      return;
+    }
+    try {
+      await planAdd(title, selectedProjectId, selectedTagIds);
+      setNewPlanTitle("");
+      await queryClient.invalidateQueries({ queryKey: ["plan"] });
+    } catch (error) {
+      reportActionError("Failed to add plan item.", error);
+    }
+  }
+
+  async function onRemovePlanItem(id: number) {
+    try {
+      await planRemove(id);
+      await queryClient.invalidateQueries({ queryKey: ["plan"] });
+    } catch (error) {
+      reportActionError("Failed to remove plan item.", error);
+    }
+  }
+
+  async function onStartNextPlanned() {
+    setActionBusy(true);
+    try {
+      const next = await planStartNext();
+      setTimer(next);
+      setSelectedProjectId(next.currentProjectId ?? null);
+      setSelectedTagIds(next.currentTagIds ?? []);
+      await queryClient.invalidateQueries({ queryKey: ["plan"] });
+    } catch (error) {
+      reportActionError("Failed to start the next planned focus.", error);
+    } finally {
+      setActionBusy(false);
+    }
+  }
+
   async function onSaveSettings() {
     if (!settingsDraft) {
       return;
@@ -424,8 +579,11 @@ export default function App() {
   async function onExportCsv() {
     setStatusMessage("");
     try {
-      await exportCsv(historyRange);
-      setStatusMessage("CSV export saved.");
+      const result = await exportCsv(historyRange);
+      if (!result) {
+        return;
+      }
+      setStatusMessage(`Exported ${result.rows} sessions to ${result.path}.`);
       toast.success("CSV exported.", {
         position: "top-center",
         duration: 1500,
@@ -438,8 +596,11 @@ export default function App() {
   async function onExportJson() {
     setStatusMessage("");
     try {
-      await exportJson(historyRange);
-      setStatusMessage("JSON export saved.");
+      const result = await exportJson(historyRange);
+      if (!result) {
+        return;
+      }
+      setStatusMessage(`Exported ${result.rows} sessions to ${result.path}.`);
       toast.success("JSON exported.", {
         position: "top-center",
         duration: 1500,
@@ -449,6 +610,131 @@ export default function App() {
     }
   }
 
+  async function onImportJson(mode: ImportMode) {
+    if (
+      mode === "replace" &&
+      !(await window.confirm(
+        "Restoring replaces all sessions, projects, tags, and settings with the backup. Continue?",
+      ))
+    ) {
+      return;
+    }
+    setActionBusy(true);
+    setStatusMessage("");
+    try {
+      const result = await importJson(mode);
+      if (!result) {
+        return;
+      }
+      const skipped = result.sessionsSkipped ? `, ${result.sessionsSkipped} already present` : "";
+      setStatusMessage(`Imported ${result.sessionsImported} sessions${skipped}.`);
+      toast.success("Backup imported.", {
+        position: "top-center",
+        duration: 1500,
+      });
+    } catch (error) {
+      reportActionError("Failed to import backup.", error);
+    } finally {
+      setActionBusy(false);
+    }
+  }
+
+  async function onImportCsv() {
+    try {
+      setCsvImportContent(await pickTextFile("CSV", "csv"));
+    } catch (error) {
+      reportActionError("Failed to read CSV file.", error);
+    }
+  }
+
+  async function onBackupNow() {
+    setActionBusy(true);
+    setStatusMessage("");
+    try {
+      const backup = await backupNow();
+      setStatusMessage(`Backup written to ${backup.path}.`);
+      toast.success("Backup saved.", {
+        position: "top-center",
+        duration: 1500,
+      });
+    } catch (error) {
+      reportActionError("Failed to write backup.", error);
+    } finally {
+      setActionBusy(false);
+    }
+  }
+
+  async function onDatabaseExport() {
+    setStatusMessage("");
+    try {
+      const result = await databaseExport();
+      if (result) {
+        setStatusMessage(`Database copied to ${result.path}.`);
+      }
+    } catch (error) {
+      reportActionError("Failed to export database.", error);
+    }
+  }
+
+  async function onDatabaseImport() {
+    if (
+      !(await window.confirm(
+        "Importing a database replaces all sessions, projects, tags, and settings. The current database is kept next to it. Continue?",
+      ))
+    ) {
+      return;
+    }
+    setActionBusy(true);
+    setStatusMessage("");
+    try {
+      const result = await databaseImport();
+      if (!result) {
+        return;
+      }
+      setTimer(result.timer);
+      setSettingsDraft(result.settings);
+      setSelectedProjectId(result.timer.currentProjectId ?? null);
+      setSelectedTagIds(result.timer.currentTagIds ?? []);
+      await refreshAll();
+      setStatusMessage(`Database imported. The previous one was kept at ${result.backupPath}.`);
+      toast.success("Database imported.", {
+        position: "top-center",
+        duration: 1500,
+      });
+    } catch (error) {
+      reportActionError("Failed to import database.", error);
+    } finally {
+      setActionBusy(false);
+    }
+  }
+
+  async function onGenerateReport() {
+    setStatusMessage("");
+    try {
+      await reportGenerate(statsRange);
+      toast.success("Report saved.", {
+        position: "top-center",
+        duration: 1500,
+      });
+    } catch (error) {
+      reportActionError("Failed to generate report.", error);
+    }
+  }
+
+  async function onExportIcal() {
+    setStatusMessage("");
+    try {
+      await exportIcal(historyRange);
+      setStatusMessage("Calendar export saved.");
+      toast.success("Calendar exported.", {
+        position: "top-center",
+        duration: 1500,
+      });
+    } catch (error) {
+      reportActionError("Failed to export calendar.", error);
+    }
+  }
+
   async function onResetAllData() {
     const confirmed = await window.confirm(
       "This permanently deletes all sessions, projects, tags, and resets settings. Continue?",
@@ -494,6 +780,12 @@ export default function App() {
   return (
     <div className="flex h-screen w-full flex-col overflow-hidden bg-background text-foreground">
       <TitleBar />
+      <DbRecoveryDialog onRestored={refreshAll} />
+      <CsvImportDialog
+        content={csvImportContent}
+        onClose={() => setCsvImportContent(null)}
+        onImported={refreshAll}
+      />
 
       <div className="flex flex-1 overflow-hidden">
         <Sidebar activeTab={activeTab} onChange={setActiveTab} />
@@ -585,6 +877,42 @@ export default function App() {
                       </div>
                     </label>
                   </div>
+
+                  {/* Plan Queue */}
+                  <div className="flex flex-col gap-2">
+                    <span className="text-sm font-medium">Plan</span>
+                    <div className="flex gap-2">
+                      <Input
+                        value={newPlanTitle}
+                        placeholder="Next focus block"
+                        onChange={(event) => setNewPlanTitle(event.target.value)}
+                        onKeyDown={(event) => {
+                          if (event.key === "Enter") {
+                            void onAddPlanItem();
+                          }
+                        }}
+                      />
+                      <Button variant="outline" onClick={onAddPlanItem}>
+                        Add
+                      </Button>
+                    </div>
+                    {(planQuery.data ?? []).map((item) => (
+                      <div key={item.id} className="flex items-center justify-between text-sm">
+                        <span>{item.title}</span>
+                        <Button variant="ghost" size="sm" onClick={() => onRemovePlanItem(item.id)}>
+                          Remove
+                        </Button>
+                      </div>
+                    ))}
+                    {(planQuery.data ?? []).length > 0 && (
+                      <Button
+                        onClick={onStartNextPlanned}
+                        disabled={actionBusy || timer?.phase !== "focus" || timer?.startedAt != null}
+                      >
+                        Start next planned
+                      </Button>
+                    )}
+                  </div>
                 </section>
 
                 <div>
@@ -595,7 +923,12 @@ export default function App() {
 
             {activeTab === "stats" && (
               <div className="animate-in fade-in slide-in-from-bottom-4 duration-500 space-y-6">
-                <h2 className="text-2xl font-bold tracking-tight">Statistics</h2>
+                <div className="flex items-center justify-between">
+                  <h2 className="text-2xl font-bold tracking-tight">Statistics</h2>
+                  <Button variant="outline" size="sm" onClick={onGenerateReport} title="Save a Markdown report for this period">
+                    Report
+                  </Button>
+                </div>
                 <div className="rounded-xl border bg-card text-card-foreground shadow-sm">
                   <div className="p-6">
                     <StatsOverview summary={summaryQuery.data} />
@@ -617,7 +950,10 @@ export default function App() {
                   <div className="flex flex-row items-center justify-between p-6 pb-2">
                     <h3 className="text-lg font-semibold leading-none tracking-tight">History</h3>
                     <div className="flex items-center gap-2">
-
+                      <label className="flex items-center gap-2 text-sm text-muted-foreground">
+                        <Switch checked={historyFocusOnly} onCheckedChange={setHistoryFocusOnly} />
+                        Hide breaks
+                      </label>
                       <Select
                         value={rangeDays.toString()}
                         onValueChange={(value) => setRangeDays(Number(value))}
@@ -634,6 +970,7 @@ export default function App() {
                       <div className="flex gap-1">
                         <button className="inline-flex items-center justify-center rounded-md text-sm font-medium transition-colors focus-visible:outline-none focus-visible:ring-1 focus-visible:ring-ring disabled:pointer-events-none disabled:opacity-50 hover:bg-accent hover:text-accent-foreground h-9 w-9" onClick={onExportCsv} title="Export CSV">📄</button>
                         <button className="inline-flex items-center justify-center rounded-md text-sm font-medium transition-colors focus-visible:outline-none focus-visible:ring-1 focus-visible:ring-ring disabled:pointer-events-none disabled:opacity-50 hover:bg-accent hover:text-accent-foreground h-9 px-3" onClick={onExportJson} title="Export JSON">{ } JSON</button>
+                        <button className="inline-flex items-center justify-center rounded-md text-sm font-medium transition-colors focus-visible:outline-none focus-visible:ring-1 focus-visible:ring-ring disabled:pointer-events-none disabled:opacity-50 hover:bg-accent hover:text-accent-foreground h-9 w-9" onClick={onExportIcal} title="Export focus sessions to a calendar (.ics)">📅</button>
                       </div>
                     </div>
                   </div>
@@ -693,6 +1030,21 @@ export default function App() {
                   </div>
                 </div>
 
+                <div className="rounded-xl border bg-card text-card-foreground shadow-sm">
+                  <div className="flex flex-col space-y-1.5 p-6">
+                    <h4 className="text-lg font-semibold leading-none tracking-tight">Backup</h4>
+                    <p className="text-sm text-muted-foreground">Bring back a JSON export by merging it or replacing everything, import sessions from a CSV file, or write a backup to the backup folder now.</p>
+                  </div>
+                  <div className="flex gap-2 p-6 pt-0">
+                    <Button variant="secondary" onClick={() => onImportJson("merge")} disabled={actionBusy}>Merge JSON</Button>
+                    <Button variant="outline" onClick={() => onImportJson("replace")} disabled={actionBusy}>Restore JSON</Button>
+                    <Button variant="secondary" onClick={onImportCsv} disabled={actionBusy}>Import CSV</Button>
+                    <Button variant="outline" onClick={onBackupNow} disabled={actionBusy}>Back up now</Button>
+                    <Button variant="secondary" onClick={onDatabaseExport} disabled={actionBusy}>Export database</Button>
+                    <Button variant="outline" onClick={onDatabaseImport} disabled={actionBusy}>Import database</Button>
+                  </div>
+                </div>
+
                 <div className="rounded-xl border border-destructive/20 bg-destructive/10 text-destructive shadow-sm">
                   <div className="flex flex-col space-y-1.5 p-6">
                     <h4 className="text-lg font-semibold leading-none tracking-tight">Danger Zone</h4>
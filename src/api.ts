@@ -1,22 +1,88 @@
 import { invoke } from "@tauri-apps/api/core";
-import { save } from "@tauri-apps/plugin-dialog";
-import { writeTextFile } from "@tauri-apps/plugin-fs";
+import { open, save } from "@tauri-apps/plugin-dialog";
+import { readTextFile, writeFile, writeTextFile } from "@tauri-apps/plugin-fs";
 import type {
   AnalyticsRange,
+  AnalyticsRecords,
   AnalyticsSummary,
+  AppDiagnostics,
   AppSettings,
   AppSettingsPatch,
+  AutoArchiveResult,
+  BillingPeriod,
+  BillingReport,
+  BinaryExportResult,
+  BreakUsage,
+  BreathingStep,
+  BulkTagFilter,
+  BulkUpdateResult,
+  CloudBackupConfigPatch,
+  CloudBackupStatus,
+  CsvColumnMapping,
+  CsvImportResult,
+  CsvOptions,
+  DatabaseExportResult,
+  DatabaseImportResult,
+  DayOff,
+  DaysOffImportResult,
+  DbCheckReport,
+  DbRecoveryReport,
+  DeleteResult,
+  DndStatus,
+  EstimationAccuracy,
   ExportRange,
+  ExperimentReport,
   ExportResult,
+  FileExportResult,
+  FocusLengthRecommendation,
+  GoalsProgress,
+  HourlyBucket,
+  ImportFormat,
+  ImportMode,
+  ImportPreview,
+  InterruptionAnalytics,
+  JsonExportOptions,
+  JsonImportResult,
+  LocalBackup,
+  MergeResult,
+  MusicTrack,
+  MusicTrackData,
+  PlanItem,
   Project,
   ProjectInput,
+  ProjectSplit,
+  QualityBreakdown,
+  ReadOnlyStatus,
+  ReconcileResult,
+  RemoteDiscoveryInfo,
+  RemoteScope,
+  RemoteAuditEntry,
+  RemoteDevice,
+  RemoteSecurityLog,
+  RemoteStatus,
+  RemoteToken,
+  ReportFormat,
   ResetAllResult,
+  SessionDistribution,
+  SessionGap,
+  SessionPage,
+  SessionQuery,
   SessionRecord,
+  SessionRevision,
+  SessionSearchQuery,
+  SessionUpdate,
+  StartTimeStats,
   StartTimerRequest,
+  SyncOutcome,
   Tag,
   TagInput,
+  TagWithStats,
+  TimerEvent,
+  TimerEventQuery,
   TimerState,
   TimeseriesPoint,
+  TrashList,
+  WeekdayBucket,
 } from "./types";
 
 export async function timerStart(payload?: StartTimerRequest) {
@@ -43,6 +109,14 @@ export async function timerSetContext(payload: StartTimerRequest) {
   return invoke<TimerState>("timer_set_context", { payload });
 }
 
+export async function timerPostponeLongBreak() {
+  return invoke<TimerState>("timer_postpone_long_break");
+}
+
+export async function breakExtend(minutes: number) {
+  return invoke<TimerState>("break_extend", { minutes });
+}
+
 export async function analyticsGetSummary(range: AnalyticsRange) {
   return invoke<AnalyticsSummary>("analytics_get_summary", { range });
 }
@@ -51,10 +125,155 @@ export async function analyticsGetTimeseries(range: AnalyticsRange) {
   return invoke<TimeseriesPoint[]>("analytics_get_timeseries", { range });
 }
 
+export async function analyticsGetHourly(range: AnalyticsRange) {
+  return invoke<HourlyBucket[]>("analytics_get_hourly", { range });
+}
+
+export async function goalsProgress() {
+  return invoke<GoalsProgress>("goals_progress");
+}
+
+export async function analyticsRecords() {
+  return invoke<AnalyticsRecords>("analytics_records");
+}
+
+export async function analyticsGetWeekday(range: AnalyticsRange) {
+  return invoke<WeekdayBucket[]>("analytics_get_weekday", { range });
+}
+
+export async function analyticsInterruptions(range: AnalyticsRange) {
+  return invoke<InterruptionAnalytics>("analytics_interruptions", { range });
+}
+
+export async function analyticsDistribution(range: AnalyticsRange) {
+  return invoke<SessionDistribution>("analytics_distribution", { range });
+}
+
+export async function analyticsStartTimes(range: AnalyticsRange) {
+  return invoke<StartTimeStats[]>("analytics_start_times", { range });
+}
+
+export async function analyticsEstimationAccuracy(range: AnalyticsRange) {
+  return invoke<EstimationAccuracy>("analytics_estimation_accuracy", { range });
+}
+
+export async function analyticsBreakUsage(range: AnalyticsRange) {
+  return invoke<BreakUsage>("analytics_break_usage", { range });
+}
+
+export async function focusLengthRecommend(
+  range: AnalyticsRange,
+  apply = false,
+) {
+  return invoke<FocusLengthRecommendation>("focus_length_recommend", {
+    range,
+    apply,
+  });
+}
+
+export async function analyticsBilling(
+  range: AnalyticsRange,
+  period: BillingPeriod = "month",
+) {
+  return invoke<BillingReport>("analytics_billing", { range, period });
+}
+
+export async function analyticsQuality(range: AnalyticsRange) {
+  return invoke<QualityBreakdown>("analytics_quality", { range });
+}
+
+export async function sessionRate(sessionId: number, quality: number | null) {
+  return invoke<SessionRecord>("session_rate", { sessionId, quality });
+}
+
+export async function sessionUpdate(sessionId: number, update: SessionUpdate) {
+  return invoke<SessionRecord>("session_update", { sessionId, update });
+}
+
+export async function sessionRevisionsList(sessionId: number) {
+  return invoke<SessionRevision[]>("session_revisions_list", { sessionId });
+}
+
+export async function sessionRevisionRevert(revisionId: number, allowOverlap?: boolean) {
+  return invoke<SessionRecord>("session_revision_revert", { revisionId, allowOverlap });
+}
+
 export async function sessionHistory(range: AnalyticsRange) {
   return invoke<SessionRecord[]>("session_history", { range });
 }
 
+export async function sessionHistoryPage(query: SessionQuery) {
+  return invoke<SessionPage>("session_history_page", { query });
+}
+
+export async function sessionSetNotes(sessionId: number, notes: string | null) {
+  return invoke<SessionRecord>("session_set_notes", { sessionId, notes });
+}
+
+export async function sessionsSearch(query: SessionSearchQuery) {
+  return invoke<SessionRecord[]>("sessions_search", { query });
+}
+
+export async function sessionsFindGaps(date: string, minGapMin?: number) {
+  return invoke<SessionGap[]>("sessions_find_gaps", { date, minGapMin });
+}
+
+export async function timerEventsList(query: TimerEventQuery = {}) {
+  return invoke<TimerEvent[]>("timer_events_list", { query });
+}
+
+export async function sessionsReassignProject(
+  filter: AnalyticsRange,
+  projectId: number | null,
+) {
+  return invoke<BulkUpdateResult>("sessions_reassign_project", {
+    filter,
+    projectId,
+  });
+}
+
+export async function sessionsBulkTag(
+  filter: BulkTagFilter,
+  addTagIds: number[],
+  removeTagIds: number[],
+) {
+  return invoke<BulkUpdateResult>("sessions_bulk_tag", {
+    filter,
+    addTagIds,
+    removeTagIds,
+  });
+}
+
+export async function sessionSetProjectSplits(
+  sessionId: number,
+  splits: ProjectSplit[],
+) {
+  return invoke<SessionRecord>("session_set_project_splits", {
+    sessionId,
+    splits,
+  });
+}
+
+export async function sessionsMerge(firstId: number, secondId: number) {
+  return invoke<SessionRecord>("sessions_merge", { firstId, secondId });
+}
+
+export async function sessionsSplit(sessionId: number, at: number) {
+  return invoke<SessionRecord[]>("sessions_split", { sessionId, at });
+}
+
+export async function sessionsDelete(sessionIds: number[]) {
+  return invoke<BulkUpdateResult>("sessions_delete", { sessionIds });
+}
+
+export async function sessionsRestore(sessionIds: number[]) {
+  return invoke<BulkUpdateResult>("sessions_restore", { sessionIds });
+}
+
+export async function trashList() {
+  return invoke<TrashList>("trash_list");
+}
+
 export async function projectsList() {
   return invoke<Project[]>("projects_list");
 }
@@ -63,14 +282,82 @@ export async function projectsUpsert(input: ProjectInput) {
   return invoke<Project>("projects_upsert", { input });
 }
 
+export async function projectsDelete(projectId: number, force = false) {
+  return invoke<DeleteResult>("projects_delete", { projectId, force });
+}
+
+export async function projectsMerge(
+  sourceId: number,
+  targetId: number,
+  deleteSource = false,
+) {
+  return invoke<MergeResult>("projects_merge", {
+    sourceId,
+    targetId,
+    deleteSource,
+  });
+}
+
+export async function projectsReorder(projectIds: number[]) {
+  return invoke<Project[]>("projects_reorder", { projectIds });
+}
+
+export async function projectsAutoArchive(days?: number) {
+  return invoke<AutoArchiveResult>("projects_auto_archive", { days });
+}
+
+export async function projectsUnarchive(projectIds: number[]) {
+  return invoke<BulkUpdateResult>("projects_unarchive", { projectIds });
+}
+
+export async function projectsRestore(projectId: number) {
+  return invoke<BulkUpdateResult>("projects_restore", { projectId });
+}
+
 export async function tagsList() {
   return invoke<Tag[]>("tags_list");
 }
 
+export async function daysOffList(from?: string, to?: string) {
+  return invoke<DayOff[]>("days_off_list", { from, to });
+}
+
+export async function daysOffSet(day: string, name?: string) {
+  return invoke<DayOff>("days_off_set", { day, name });
+}
+
+export async function daysOffRemove(days: string[]) {
+  return invoke<number>("days_off_remove", { days });
+}
+
+export async function daysOffImportIcs(content: string) {
+  return invoke<DaysOffImportResult>("days_off_import_ics", { content });
+}
+
+export async function tagsListWithStats(range?: AnalyticsRange) {
+  return invoke<TagWithStats[]>("tags_list_with_stats", { range });
+}
+
 export async function tagsUpsert(input: TagInput) {
   return invoke<Tag>("tags_upsert", { input });
 }
 
+export async function tagsDelete(tagId: number, force = false) {
+  return invoke<DeleteResult>("tags_delete", { tagId, force });
+}
+
+export async function tagsMerge(sourceIds: number[], targetId: number) {
+  return invoke<MergeResult>("tags_merge", { sourceIds, targetId });
+}
+
+export async function tagsRestore(tagId: number) {
+  return invoke<BulkUpdateResult>("tags_restore", { tagId });
+}
+
+export async function dndStatus() {
+  return invoke<DndStatus>("dnd_status");
+}
+
 export async function settingsGet() {
   return invoke<AppSettings>("settings_get");
 }
@@ -79,21 +366,127 @@ export async function settingsUpdate(patch: AppSettingsPatch) {
   return invoke<AppSettings>("settings_update", { patch });
 }
 
+export async function backupNow() {
+  return invoke<LocalBackup>("backup_now");
+}
+
+export async function backupList() {
+  return invoke<LocalBackup[]>("backup_list");
+}
+
+export async function cloudBackupGet() {
+  return invoke<CloudBackupStatus>("cloud_backup_get");
+}
+
+export async function cloudBackupUpdate(patch: CloudBackupConfigPatch) {
+  return invoke<CloudBackupStatus>("cloud_backup_update", { patch });
+}
+
+export async function cloudBackupNow() {
+  return invoke<CloudBackupStatus>("cloud_backup_now");
+}
+
+export async function cloudBackupRestore() {
+  return invoke<ResetAllResult>("cloud_backup_restore");
+}
+
+export async function syncPush(force?: boolean) {
+  return invoke<SyncOutcome>("sync_push", { force });
+}
+
+export async function syncPull(force?: boolean) {
+  return invoke<SyncOutcome>("sync_pull", { force });
+}
+
 export async function resetAllData() {
   return invoke<ResetAllResult>("reset_all_data");
 }
 
 async function writeExport(
-  command: "export_csv" | "export_json",
+  command: "export_ical",
   range: ExportRange,
-  extension: "csv" | "json",
+  extension: "ics",
   filterName: string,
+  options?: CsvOptions | JsonExportOptions,
 ) {
-  const file = await invoke<ExportResult>(command, { range });
+  const file = await invoke<ExportResult>(command, { range, options });
+  const path = await save({
+    defaultPath: file.filename,
+    filters: [{ name: filterName, extensions: [extension] }],
+  });
+
+  if (!path) {
+    return;
+  }
+
+  await writeTextFile(path, file.content);
+}
+
+// The backend asks for the path and streams rows into the file; resolves to null when cancelled.
+export async function exportCsv(range: ExportRange, options?: CsvOptions) {
+  return invoke<FileExportResult | null>("export_csv_to_file", { range, options });
+}
+
+export async function exportCsvBundle(range: ExportRange) {
+  const file = await invoke<BinaryExportResult>("export_csv_bundle", { range });
   const path = await save({
     defaultPath: file.filename,
+    filters: [{ name: "ZIP", extensions: ["zip"] }],
+  });
+
+  if (!path) {
+    return;
+  }
+
+  const bytes = Uint8Array.from(atob(file.contentBase64), (c) => c.charCodeAt(0));
+  await writeFile(path, bytes);
+}
+
+// Resolves to null when the save dialog is cancelled.
+export async function exportJson(range: ExportRange, options?: JsonExportOptions) {
+  return invoke<FileExportResult | null>("export_json_to_file", { range, options });
+}
+
+// Resolves to null when the save dialog is cancelled.
+export async function databaseExport() {
+  return invoke<DatabaseExportResult | null>("database_export");
+}
+
+// Replaces all local data with the picked database file; resolves to null when cancelled.
+export async function databaseImport() {
+  return invoke<DatabaseImportResult | null>("database_import");
+}
+
+// Resolves to null when the file picker is cancelled.
+export async function pickTextFile(filterName: string, extension: string) {
+  const path = await open({
+    multiple: false,
     filters: [{ name: filterName, extensions: [extension] }],
   });
+  return path ? readTextFile(path) : null;
+}
+
+// Resolves to null when the file picker is cancelled.
+export async function importJson(mode: ImportMode) {
+  const content = await pickTextFile("JSON", "json");
+  if (content === null) {
+    return null;
+  }
+  return invoke<JsonImportResult>("import_json", { content, mode });
+}
+
+// Without a mapping the file must use export_csv's column names.
+export async function importCsv(content: string, mapping?: CsvColumnMapping) {
+  return invoke<CsvImportResult>("import_csv", { content, mapping });
+}
+
+export async function reportGenerate(range: AnalyticsRange, format: ReportFormat = "markdown") {
+  const file = await invoke<ExportResult>("report_generate", { range, format });
+  const extension = format === "html" ? "html" : "md";
+  const path = await save({
+    defaultPath: file.filename,
+    filters: [{ name: format === "html" ? "HTML" : "Markdown", extensions: [extension] }],
+  });
 
   if (!path) {
     return;
@@ -102,10 +495,149 @@ async function writeExport(
   await writeTextFile(path, file.content);
 }
 
-export async function exportCsv(range: ExportRange) {
-  await writeExport("export_csv", range, "csv", "CSV");
+// Focus sessions only, one calendar event each.
+export async function exportIcal(range: ExportRange) {
+  await writeExport("export_ical", range, "ics", "iCalendar");
+}
+
+export async function importPreview(format: ImportFormat, content: string) {
+  return invoke<ImportPreview>("import_preview", { format, content });
+}
+
+export async function appDiagnostics() {
+  return invoke<AppDiagnostics>("app_diagnostics");
+}
+
+export async function dbSelfCheck() {
+  return invoke<DbCheckReport>("db_self_check");
+}
+
+export async function dbRepair() {
+  return invoke<DbCheckReport>("db_repair");
+}
+
+export async function dbReadOnlyStatus() {
+  return invoke<ReadOnlyStatus>("db_read_only_status");
+}
+
+export async function dbRecoveryStatus() {
+  return invoke<DbRecoveryReport | null>("db_recovery_status");
+}
+
+export async function dbRecoveryDismiss() {
+  return invoke<void>("db_recovery_dismiss");
+}
+
+export async function dbReconcile() {
+  return invoke<ReconcileResult>("db_reconcile");
+}
+
+export async function breathingCurrent() {
+  return invoke<BreathingStep | null>("breathing_current");
+}
+
+export async function planList() {
+  return invoke<PlanItem[]>("plan_list");
+}
+
+export async function planAdd(title: string, projectId: number | null, tagIds: number[]) {
+  return invoke<PlanItem[]>("plan_add", { title, projectId, tagIds });
+}
+
+export async function planRemove(id: number) {
+  return invoke<PlanItem[]>("plan_remove", { id });
+}
+
+export async function planStartNext() {
+  return invoke<TimerState>("plan_start_next");
+}
+
+export async function musicList() {
+  return invoke<MusicTrack[]>("music_list");
+}
+
+export async function musicAdd(paths: string[]) {
+  return invoke<MusicTrack[]>("music_add", { paths });
+}
+
+export async function musicRemove(trackIds: number[]) {
+  return invoke<MusicTrack[]>("music_remove", { trackIds });
+}
+
+export async function musicSetVolume(trackId: number, volume: number) {
+  return invoke<MusicTrack>("music_set_volume", { trackId, volume });
+}
+
+export async function musicNext() {
+  return invoke<MusicTrack | null>("music_next");
+}
+
+export async function musicTrackData(trackId: number) {
+  return invoke<MusicTrackData>("music_track_data", { trackId });
+}
+
+export async function remoteDiscoveryInfo() {
+  return invoke<RemoteDiscoveryInfo>("remote_discovery_info");
+}
+
+export async function remoteStatus() {
+  return invoke<RemoteStatus>("remote_status");
+}
+
+export async function remoteTokensList() {
+  return invoke<RemoteToken[]>("remote_tokens_list");
+}
+
+export async function remoteTokensCreate(name: string, scopes: RemoteScope[]) {
+  return invoke<RemoteToken>("remote_tokens_create", { name, scopes });
+}
+
+export async function remoteTokensRevoke(id: number) {
+  return invoke<boolean>("remote_tokens_revoke", { id });
+}
+
+export async function remoteAuditList(limit?: number) {
+  return invoke<RemoteAuditEntry[]>("remote_audit_list", { limit });
+}
+
+export async function remoteDevicesList() {
+  return invoke<RemoteDevice[]>("remote_devices_list");
+}
+
+export async function remoteDevicesDecide(id: number, approved: boolean) {
+  return invoke<boolean>("remote_devices_decide", { id, approved });
+}
+
+export async function remoteDevicesRemove(id: number) {
+  return invoke<boolean>("remote_devices_remove", { id });
+}
+
+export async function remoteSecurityLog() {
+  return invoke<RemoteSecurityLog>("remote_security_log");
+}
+
+export async function experimentStart(
+  focusAMin: number,
+  focusBMin: number,
+  days: number,
+) {
+  return invoke<ExperimentReport>("experiment_start", {
+    focusAMin,
+    focusBMin,
+    days,
+  });
+}
+
+export async function experimentStop() {
+  return invoke<ExperimentReport>("experiment_stop");
+}
+
+export async function experimentGetReport(experimentId?: number) {
+  return invoke<ExperimentReport | null>("experiment_get_report", {
+    experimentId: experimentId ?? null,
+  });
 }
 
-export async function exportJson(range: ExportRange) {
-  await writeExport("export_json", range, "json", "JSON");
+export async function supportBundle() {
+  return invoke<BinaryExportResult>("support_bundle");
 }
@@ -0,0 +1,175 @@
+import { useEffect, useState } from "react";
+import { toast } from "sonner";
+import { importCsv } from "../api";
+import type { CsvColumnMapping } from "../types";
+import {
+    AlertDialog,
+    AlertDialogAction,
+    AlertDialogCancel,
+    AlertDialogContent,
+    AlertDialogDescription,
+    AlertDialogFooter,
+    AlertDialogHeader,
+    AlertDialogTitle,
+} from "@/components/ui/alert-dialog";
+import {
+    Select,
+    SelectContent,
+    SelectItem,
+    SelectTrigger,
+    SelectValue,
+} from "@/components/ui/select";
+
+interface CsvImportDialogProps {
+    // File content to map; the dialog is open while this is set.
+    content: string | null;
+    onClose: () => void;
+    onImported: () => void;
+}
+
+type MappedField = Exclude<keyof CsvColumnMapping, "tagSeparator">;
+
+const FIELDS: { key: MappedField; label: string; required?: boolean }[] = [
+    { key: "startedAt", label: "Start", required: true },
+    { key: "endedAt", label: "End", required: true },
+    { key: "durationSec", label: "Duration (seconds)" },
+    { key: "phase", label: "Phase" },
+    { key: "completed", label: "Completed" },
+    { key: "projectName", label: "Project" },
+    { key: "tagNames", label: "Tags" },
+];
+
+// Radix Select cannot hold an empty value.
+const NONE = "__none";
+
+// Same delimiter guess as the backend: whichever appears most in the header line.
+function readHeader(content: string) {
+    const line = content.replace(/^\uFEFF/, "").split(/\r?\n/, 1)[0] ?? "";
+    const delimiter = [",", ";", "\t"].reduce((best, d) =>
+        line.split(d).length > line.split(best).length ? d : best,
+    );
+    return line.split(delimiter).map((name) => name.trim().replace(/^"|"$/g, ""));
+}
+
+// Pre-selects a column whose name looks like the field, so export_csv files need no edits.
+function guessColumn(columns: string[], field: MappedField) {
+    const hints: Record<MappedField, string[]> = {
+        startedAt: ["startedat", "start", "begin", "from"],
+        endedAt: ["endedat", "end", "stop", "to"],
+        durationSec: ["durationsec", "duration", "seconds"],
+        phase: ["phase", "type"],
+        completed: ["completed", "done", "finished"],
+        projectName: ["projectname", "project", "client"],
+        tagNames: ["tagnames", "tags", "labels"],
+        interruptions: ["interruptions"],
+        quality: ["quality", "rating"],
+    };
+    const normalized = columns.map((c) => c.toLowerCase().replace(/[^a-z]/g, ""));
+    const index = hints[field].map((hint) => normalized.indexOf(hint)).find((i) => i >= 0);
+    return index === undefined ? undefined : columns[index];
+}
+
+export default function CsvImportDialog({ content, onClose, onImported }: CsvImportDialogProps) {
+    const [columns, setColumns] = useState<string[]>([]);
+    const [mapping, setMapping] = useState<Partial<CsvColumnMapping>>({});
+
+    useEffect(() => {
+        if (content === null) {
+            return;
+        }
+        const header = readHeader(content);
+        setColumns(header);
+        setMapping(
+            Object.fromEntries(FIELDS.map(({ key }) => [key, guessColumn(header, key)])) as Partial<
+                CsvColumnMapping
+            >,
+        );
+    }, [content]);
+
+    const ready = Boolean(mapping.startedAt && mapping.endedAt);
+
+    async function runImport() {
+        if (content === null || !mapping.startedAt || !mapping.endedAt) {
+            return;
+        }
+        try {
+            const result = await importCsv(content, {
+                ...mapping,
+                startedAt: mapping.startedAt,
+                endedAt: mapping.endedAt,
+            });
+            const created = [...result.projectsCreated, ...result.tagsCreated];
+            toast.success(`Imported ${result.imported} sessions`, {
+                description: [
+                    result.duplicates > 0 && `${result.duplicates} already present`,
+                    created.length > 0 && `created ${created.join(", ")}`,
+                ]
+                    .filter(Boolean)
+                    .join("; "),
+            });
+            if (result.errors.length > 0) {
+                toast.warning(`${result.errors.length} rows skipped`, {
+                    description: result.errors
+                        .slice(0, 5)
+                        .map((error) => `Row ${error.row}: ${error.message}`)
+                        .join("\n"),
+                });
+            }
+            onImported();
+            onClose();
+        } catch (error) {
+            toast.error(`CSV import failed: ${String(error)}`);
+        }
+    }
+
+    return (
+        <AlertDialog open={content !== null}>
+            <AlertDialogContent>
+                <AlertDialogHeader>
+                    <AlertDialogTitle>Import CSV</AlertDialogTitle>
+                    <AlertDialogDescription>
+                        Pick the column holding each field. Without a duration column it is end minus start;
+                        without a phase column every row is a focus session.
+                    </AlertDialogDescription>
+                </AlertDialogHeader>
+                <div className="grid grid-cols-2 items-center gap-2">
+                    {FIELDS.map(({ key, label, required }) => (
+                        <div key={key} className="contents">
+                            <label className="text-sm font-medium">
+                                {label}
+                                {required && " *"}
+                            </label>
+                            <Select
+                                value={mapping[key] ?? NONE}
+                                onValueChange={(value) =>
+                                    setMapping((current) => ({
+                                        ...current,
+                                        [key]: value === NONE ? undefined : value,
+                                    }))
+                                }
+                            >
+                                <SelectTrigger>
+                                    <SelectValue placeholder="Choose column" />
+                                </SelectTrigger>
+                                <SelectContent>
+                                    {!required && <SelectItem value={NONE}>Not in file</SelectItem>}
+                                    {columns.map((column) => (
+                                        <SelectItem key={column} value={column}>
+                                            {column}
+                                        </SelectItem>
+                                    ))}
+                                </SelectContent>
+                            </Select>
+                        </div>
+                    ))}
+                </div>
+                <AlertDialogFooter>
+                    <AlertDialogCancel onClick={onClose}>Cancel</AlertDialogCancel>
+                    <AlertDialogAction onClick={runImport} disabled={!ready}>
+                        Import
+                    </AlertDialogAction>
+                </AlertDialogFooter>
+            </AlertDialogContent>
+        </AlertDialog>
+    );
+}
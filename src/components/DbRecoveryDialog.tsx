@@ -0,0 +1,80 @@
+import { useEffect, useState } from "react";
+import { listen } from "@tauri-apps/api/event";
+import { revealItemInDir } from "@tauri-apps/plugin-opener";
+import { toast } from "sonner";
+import { cloudBackupRestore, dbRecoveryDismiss, dbRecoveryStatus } from "../api";
+import type { DbRecoveryReport } from "../types";
+import {
+    AlertDialog,
+    AlertDialogAction,
+    AlertDialogCancel,
+    AlertDialogContent,
+    AlertDialogDescription,
+    AlertDialogFooter,
+    AlertDialogHeader,
+    AlertDialogTitle,
+} from "@/components/ui/alert-dialog";
+
+interface DbRecoveryDialogProps {
+    onRestored: () => void;
+}
+
+const MODE_TEXT: Record<DbRecoveryReport["mode"], string> = {
+    recover: "It was rebuilt with SQLite's recovery tool. Recent changes may be missing.",
+    copy: "Every readable row was copied into a new database.",
+    fresh: "Nothing could be read, so the app started with an empty database.",
+};
+
+export default function DbRecoveryDialog({ onRestored }: DbRecoveryDialogProps) {
+    const [report, setReport] = useState<DbRecoveryReport | null>(null);
+
+    useEffect(() => {
+        let unlisten: (() => void) | undefined;
+        // The startup event can fire before this listener exists, so ask as well.
+        dbRecoveryStatus().then(setReport).catch(() => undefined);
+        listen<DbRecoveryReport>("db://recovered", (event) => setReport(event.payload)).then(
+            (fn) => (unlisten = fn),
+        );
+        return () => unlisten?.();
+    }, []);
+
+    async function dismiss() {
+        setReport(null);
+        await dbRecoveryDismiss();
+    }
+
+    async function restoreCloudBackup() {
+        try {
+            await cloudBackupRestore();
+            toast.success("Cloud backup restored.");
+            onRestored();
+            await dismiss();
+        } catch (error) {
+            toast.error(`Cloud restore failed: ${String(error)}`);
+        }
+    }
+
+    return (
+        <AlertDialog open={report !== null}>
+            <AlertDialogContent>
+                <AlertDialogHeader>
+                    <AlertDialogTitle>The database was damaged</AlertDialogTitle>
+                    <AlertDialogDescription>
+                        {report && MODE_TEXT[report.mode]}{" "}
+                        The original file was kept at {report?.backupPath}.
+                        {report && report.failedTables.length > 0 && (
+                            <> Unreadable tables: {report.failedTables.join(", ")}.</>
+                        )}
+                    </AlertDialogDescription>
+                </AlertDialogHeader>
+                <AlertDialogFooter>
+                    <AlertDialogCancel onClick={() => report && revealItemInDir(report.backupPath)}>
+                        Show damaged file
+                    </AlertDialogCancel>
+                    <AlertDialogCancel onClick={restoreCloudBackup}>Restore cloud backup</AlertDialogCancel>
+                    <AlertDialogAction onClick={dismiss}>Continue</AlertDialogAction>
+                </AlertDialogFooter>
+            </AlertDialogContent>
+        </AlertDialog>
+    );
+}
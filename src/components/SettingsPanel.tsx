@@ -1,12 +1,44 @@
 import { useEffect, useState } from "react";
-import { AppSettings } from "../types";
+import { format } from "date-fns";
+import {
+    AppSettings,
+    RemoteAuditEntry,
+    RemoteDevice,
+    RemoteScope,
+    RemoteSecurityLog,
+    RemoteStatus,
+    RemoteToken,
+} from "../types";
 import { Card, CardHeader, CardTitle, CardContent } from "@/components/ui/card";
 import { Input } from "@/components/ui/input";
 import { Button } from "@/components/ui/button";
 import { Switch } from "@/components/ui/switch";
+import { Select, SelectContent, SelectItem, SelectTrigger, SelectValue } from "@/components/ui/select";
 import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
 import { Tooltip, TooltipContent, TooltipTrigger } from "@/components/ui/tooltip";
 import { toast } from "sonner";
+import {
+    remoteAuditList,
+    remoteDevicesDecide,
+    remoteDevicesList,
+    remoteDevicesRemove,
+    remoteDiscoveryInfo,
+    remoteSecurityLog,
+    remoteStatus,
+    remoteTokensCreate,
+    remoteTokensList,
+    remoteTokensRevoke,
+} from "@/api";
+
+// The backend only probes the console session on macOS.
+const CONSOLE_DETACH_SUPPORTED = navigator.userAgent.includes("Macintosh");
+
+const REMOTE_SCOPES: { scope: RemoteScope; label: string }[] = [
+    { scope: "timer", label: "Timer control" },
+    { scope: "read", label: "Read state & analytics" },
+    { scope: "write", label: "Change data" },
+];
 
 interface SettingsPanelProps {
     settings: AppSettings | null;
@@ -16,25 +48,116 @@ interface SettingsPanelProps {
 
 export default function SettingsPanel({ settings, onUpdate, onSave }: SettingsPanelProps) {
     if (!settings) return null;
-    const [localIp, setLocalIp] = useState("YOUR_LOCAL_IP");
+    const [localIps, setLocalIps] = useState<string[]>(["YOUR_LOCAL_IP"]);
+    const [tailscaleHost, setTailscaleHost] = useState<string | null>(null);
+    const [tokens, setTokens] = useState<RemoteToken[]>([]);
+    const [newTokenName, setNewTokenName] = useState("");
+    const [newTokenScopes, setNewTokenScopes] = useState<RemoteScope[]>(["timer", "read"]);
+    const [securityLog, setSecurityLog] = useState<RemoteSecurityLog | null>(null);
+    const [devices, setDevices] = useState<RemoteDevice[]>([]);
+    const [serverStatus, setServerStatus] = useState<RemoteStatus | null>(null);
+    const [audit, setAudit] = useState<RemoteAuditEntry[]>([]);
 
     useEffect(() => {
         let active = true;
 
-        invoke<string>("get_local_ip")
-            .then((ip) => {
-                if (active && ip) {
-                    setLocalIp(ip);
+        // IPv4 first when the network has it; IPv6-only networks return just the IPv6 address.
+        invoke<string[]>("get_local_ip")
+            .then((ips) => {
+                if (active && ips.length > 0) {
+                    setLocalIps(ips);
+                }
+            })
+            .catch(() => {
+                if (active) {
+                    setLocalIps(["YOUR_LOCAL_IP"]);
+                }
+            });
+
+        remoteDiscoveryInfo()
+            .then((info) => {
+                if (active) {
+                    setTailscaleHost(info.tailscale?.dnsName ?? info.tailscale?.ip ?? null);
+                }
+            })
+            .catch(() => {
+                if (active) {
+                    setTailscaleHost(null);
+                }
+            });
+
+        remoteTokensList()
+            .then((list) => {
+                if (active) {
+                    setTokens(list);
+                }
+            })
+            .catch(() => {
+                if (active) {
+                    setTokens([]);
+                }
+            });
+
+        const loadDevices = () =>
+            remoteDevicesList()
+                .then((list) => {
+                    if (active) {
+                        setDevices(list);
+                    }
+                })
+                .catch(() => {
+                    if (active) {
+                        setDevices([]);
+                    }
+                });
+        void loadDevices();
+        // New devices and answers given in the desktop prompt show up without reopening Settings.
+        const unlisteners = ["remote://pairing-request", "remote://pairing-decided"].map((event) =>
+            listen(event, () => { void loadDevices(); }),
+        );
+        // The server may start, fall back to another port or fail after Save.
+        remoteStatus()
+            .then((status) => {
+                if (active) {
+                    setServerStatus(status);
+                }
+            })
+            .catch(() => undefined);
+        unlisteners.push(
+            listen<RemoteStatus>("remote://status", (event) => {
+                if (active) {
+                    setServerStatus(event.payload);
+                }
+            }),
+        );
+
+        remoteAuditList(20)
+            .then((entries) => {
+                if (active) {
+                    setAudit(entries);
+                }
+            })
+            .catch(() => {
+                if (active) {
+                    setAudit([]);
+                }
+            });
+
+        remoteSecurityLog()
+            .then((log) => {
+                if (active) {
+                    setSecurityLog(log);
                 }
             })
             .catch(() => {
                 if (active) {
-                    setLocalIp("YOUR_LOCAL_IP");
+                    setSecurityLog(null);
                 }
             });
 
         return () => {
             active = false;
+            unlisteners.forEach((pending) => { void pending.then((unlisten) => unlisten()); });
         };
     }, []);
 
@@ -42,13 +165,65 @@ export default function SettingsPanel({ settings, onUpdate, onSave }: SettingsPa
         onUpdate({ ...settings, [field]: value });
     };
 
-    const remoteUrl = `http://${localIp}:${settings.remoteControlPort}/?token=${settings.remoteControlToken}`;
+    const toggleNewTokenScope = (scope: RemoteScope, enabled: boolean) => {
+        const others = newTokenScopes.filter((s) => s !== scope);
+        setNewTokenScopes(enabled ? [...others, scope] : others);
+    };
+
+    const handleCreateToken = async () => {
+        try {
+            const created = await remoteTokensCreate(newTokenName, newTokenScopes);
+            setTokens((list) => [...list, created]);
+            setNewTokenName("");
+            toast.success(`Token "${created.name}" created. Copy its URL now: it cannot be shown again.`, {
+                position: "top-center",
+            });
+        } catch (error) {
+            toast.error(String(error), { position: "top-center" });
+        }
+    };
+
+    const handleDecideDevice = async (device: RemoteDevice, approved: boolean) => {
+        try {
+            await remoteDevicesDecide(device.id, approved);
+            const status = approved ? "approved" : "rejected";
+            setDevices((list) => list.map((d) => (d.id === device.id ? { ...d, status } : d)));
+        } catch (error) {
+            toast.error(String(error), { position: "top-center" });
+        }
+    };
+
+    const handleRemoveDevice = async (device: RemoteDevice) => {
+        try {
+            await remoteDevicesRemove(device.id);
+            setDevices((list) => list.filter((d) => d.id !== device.id));
+        } catch (error) {
+            toast.error(String(error), { position: "top-center" });
+        }
+    };
+
+    const handleRevokeToken = async (token: RemoteToken) => {
+        try {
+            await remoteTokensRevoke(token.id);
+            setTokens((list) => list.filter((t) => t.id !== token.id));
+            setDevices((list) => list.filter((d) => d.tokenId !== token.id));
+        } catch (error) {
+            toast.error(String(error), { position: "top-center" });
+        }
+    };
 
-    const handleCopyIP = async () => {
+    const listeningPort = serverStatus?.port ?? settings.remoteControlPort;
+    const urlFor = (ip: string) => `http://${ip.includes(":") ? `[${ip}]` : ip}:${listeningPort}/`;
+    const remoteUrl = urlFor(localIps[0]);
+    const ipv6Url = localIps.length > 1 ? urlFor(localIps[1]) : null;
+    const tailscaleUrl = tailscaleHost ? `http://${tailscaleHost}:${listeningPort}/` : null;
+    const withToken = (url: string, token: RemoteToken) => `${url}?token=${token.token ?? ""}`;
+
+    const handleCopyIP = async (url: string) => {
         if (!settings.remoteControlEnabled) return;
 
         try {
-            await navigator.clipboard.writeText(remoteUrl);
+            await navigator.clipboard.writeText(url);
             toast.success("Remote control URL copied to clipboard!", {
                 position: "top-center",
             });
@@ -155,6 +330,88 @@ export default function SettingsPanel({ settings, onUpdate, onSave }: SettingsPa
                             onCheckedChange={(checked) => handleChange("soundEnabled", checked)}
                         />
                     </div>
+                    <div className="flex items-center justify-between rounded-lg border p-3 shadow-sm">
+                        <div className="space-y-0.5">
+                            <label className="text-sm font-medium leading-none peer-disabled:cursor-not-allowed peer-disabled:opacity-70">
+                                Tray Countdown Seconds
+                            </label>
+                            <p className="text-xs text-muted-foreground">
+                                Off: the menu bar shows whole minutes and only counts seconds in the final minute.
+                            </p>
+                        </div>
+                        <Switch
+                            checked={settings.trayTitleSeconds}
+                            onCheckedChange={(checked) => handleChange("trayTitleSeconds", checked)}
+                        />
+                    </div>
+                </div>
+
+                <div className="space-y-4">
+                    <div className="flex items-center justify-between rounded-lg border p-3 shadow-sm">
+                        <div className="space-y-0.5">
+                            <label className="text-sm font-medium leading-none peer-disabled:cursor-not-allowed peer-disabled:opacity-70">
+                                Scheduled Backups
+                            </label>
+                            <p className="text-xs text-muted-foreground">
+                                Writes a backup to a folder on a schedule and keeps only the newest ones.
+                            </p>
+                        </div>
+                        <Switch
+                            checked={settings.backupEnabled}
+                            onCheckedChange={(checked) => handleChange("backupEnabled", checked)}
+                        />
+                    </div>
+
+                    <div className="space-y-2">
+                        <label className="text-sm font-medium leading-none">Backup Folder</label>
+                        <Input
+                            value={settings.backupDirectory}
+                            placeholder="App data folder / backups"
+                            onChange={(e) => handleChange("backupDirectory", e.target.value)}
+                            disabled={!settings.backupEnabled}
+                        />
+                    </div>
+
+                    <div className="grid grid-cols-3 gap-4">
+                        <div className="space-y-2">
+                            <label className="text-sm font-medium leading-none">Every (hours)</label>
+                            <Input
+                                type="number"
+                                min={1}
+                                max={720}
+                                value={settings.backupIntervalHours}
+                                onChange={(e) => handleChange("backupIntervalHours", Number(e.target.value))}
+                                disabled={!settings.backupEnabled}
+                            />
+                        </div>
+                        <div className="space-y-2">
+                            <label className="text-sm font-medium leading-none">Keep Last</label>
+                            <Input
+                                type="number"
+                                min={1}
+                                max={365}
+                                value={settings.backupKeep}
+                                onChange={(e) => handleChange("backupKeep", Number(e.target.value))}
+                                disabled={!settings.backupEnabled}
+                            />
+                        </div>
+                        <div className="space-y-2">
+                            <label className="text-sm font-medium leading-none">Format</label>
+                            <Select
+                                value={settings.backupFormat}
+                                onValueChange={(value) => handleChange("backupFormat", value)}
+                                disabled={!settings.backupEnabled}
+                            >
+                                <SelectTrigger>
+                                    <SelectValue />
+                                </SelectTrigger>
+                                <SelectContent>
+                                    <SelectItem value="json">JSON export</SelectItem>
+                                    <SelectItem value="sqlite">Database copy</SelectItem>
+                                </SelectContent>
+                            </Select>
+                        </div>
+                    </div>
                 </div>
 
                 <div className="space-y-4">
@@ -173,6 +430,60 @@ export default function SettingsPanel({ settings, onUpdate, onSave }: SettingsPa
                         />
                     </div>
 
+                    <div className="flex items-center justify-between rounded-lg border p-3 shadow-sm">
+                        <div className="space-y-0.5">
+                            <label className="text-sm font-medium leading-none peer-disabled:cursor-not-allowed peer-disabled:opacity-70">
+                                Advertise on the Network
+                            </label>
+                            <p className="text-xs text-muted-foreground">
+                                Lets devices on your LAN find the remote via Bonjour/mDNS instead of typing an IP address.
+                            </p>
+                        </div>
+                        <Switch
+                            checked={settings.remoteMdnsEnabled}
+                            onCheckedChange={(checked) => handleChange("remoteMdnsEnabled", checked)}
+                            disabled={!settings.remoteControlEnabled}
+                        />
+                    </div>
+
+                    <div className="flex items-center justify-between rounded-lg border p-3 shadow-sm">
+                        <div className="space-y-0.5">
+                            <label className="text-sm font-medium leading-none peer-disabled:cursor-not-allowed peer-disabled:opacity-70">
+                                Read-Only Remote
+                            </label>
+                            <p className="text-xs text-muted-foreground">
+                                Remote devices can watch the timer and analytics but not start, pause or change anything.
+                            </p>
+                        </div>
+                        <Switch
+                            checked={settings.remoteReadOnly}
+                            onCheckedChange={(checked) => handleChange("remoteReadOnly", checked)}
+                            disabled={!settings.remoteControlEnabled}
+                        />
+                    </div>
+
+                    {CONSOLE_DETACH_SUPPORTED && (
+                        <div className="space-y-2">
+                            <label className="text-sm font-medium leading-none">When the Console Session Detaches</label>
+                            <p className="text-xs text-muted-foreground">
+                                For remote-desktop logins: react when this Mac's session leaves the console or locks.
+                            </p>
+                            <Select
+                                value={settings.remoteDetachAction}
+                                onValueChange={(value) => handleChange("remoteDetachAction", value)}
+                            >
+                                <SelectTrigger>
+                                    <SelectValue />
+                                </SelectTrigger>
+                                <SelectContent>
+                                    <SelectItem value="ignore">Do nothing</SelectItem>
+                                    <SelectItem value="interruption">Count an interruption</SelectItem>
+                                    <SelectItem value="pause">Pause the focus</SelectItem>
+                                </SelectContent>
+                            </Select>
+                        </div>
+                    )}
+
                     <div className="grid grid-cols-2 gap-4">
                         <div className="space-y-2">
                             <label className="text-sm font-medium leading-none peer-disabled:cursor-not-allowed peer-disabled:opacity-70">
@@ -189,33 +500,193 @@ export default function SettingsPanel({ settings, onUpdate, onSave }: SettingsPa
                         </div>
                         <div className="space-y-2">
                             <label className="text-sm font-medium leading-none peer-disabled:cursor-not-allowed peer-disabled:opacity-70">
-                                Remote Token
+                                Remote URL
                             </label>
+                            <p className="text-xs text-muted-foreground">
+                                <span className="rounded px-1 text-xs font-mono bg-muted/50">
+                                    {settings.remoteControlEnabled ? remoteUrl : "Enable Remote Control to see URL"}
+                                </span>
+                            </p>
+                            {settings.remoteControlEnabled && serverStatus?.state === "failed" && (
+                                <p className="text-xs text-destructive">Server not running: {serverStatus.reason}</p>
+                            )}
+                            {settings.remoteControlEnabled && serverStatus?.state === "running" && serverStatus.reason && (
+                                <p className="text-xs text-amber-600">
+                                    {serverStatus.reason}; listening on port {serverStatus.port} instead.
+                                </p>
+                            )}
+                            {settings.remoteControlEnabled && ipv6Url && (
+                                <p className="text-xs text-muted-foreground">
+                                    Over IPv6:
+                                    <span className="ml-1 rounded px-1 text-xs font-mono bg-muted/50">{ipv6Url}</span>
+                                </p>
+                            )}
+                            {settings.remoteControlEnabled && tailscaleUrl && (
+                                <p className="text-xs text-muted-foreground">
+                                    Off-LAN via Tailscale:
+                                    <span className="ml-1 rounded px-1 text-xs font-mono bg-muted/50">{tailscaleUrl}</span>
+                                </p>
+                            )}
+                        </div>
+                    </div>
+
+                    <div className="space-y-3 rounded-lg border p-3 shadow-sm">
+                        <div className="space-y-0.5">
+                            <label className="text-sm font-medium leading-none">Remote Tokens</label>
+                            <p className="text-xs text-muted-foreground">
+                                Give each phone or script its own token, so you can revoke one without touching the others.
+                                Only a hash is kept, so copy a token's URL right after creating it.
+                            </p>
+                        </div>
+                        {tokens.length === 0 && (
+                            <p className="text-xs text-muted-foreground">No tokens: nothing can use the remote API.</p>
+                        )}
+                        {tokens.map((token) => (
+                            <div key={token.id} className="flex items-center justify-between gap-2 text-xs">
+                                <div className="space-y-0.5">
+                                    <p className="font-medium">{token.name}</p>
+                                    <p className="text-muted-foreground">
+                                        {token.scopes.join(", ")} ·{" "}
+                                        {token.lastUsedAt
+                                            ? `last used ${format(new Date(token.lastUsedAt * 1000), "yyyy-MM-dd HH:mm")}`
+                                            : "never used"}
+                                    </p>
+                                </div>
+                                <div className="flex gap-2">
+                                    {token.token && (
+                                        <>
+                                            <Tooltip>
+                                                <TooltipTrigger asChild>
+                                                    <Button
+                                                        size="sm"
+                                                        variant="outline"
+                                                        disabled={!settings.remoteControlEnabled}
+                                                        onClick={() => { void handleCopyIP(withToken(remoteUrl, token)); }}
+                                                    >
+                                                        Copy URL
+                                                    </Button>
+                                                </TooltipTrigger>
+                                                <TooltipContent>
+                                                    <p className="text-xs">Remote page URL with this token</p>
+                                                </TooltipContent>
+                                            </Tooltip>
+                                            {tailscaleUrl && (
+                                                <Button
+                                                    size="sm"
+                                                    variant="outline"
+                                                    disabled={!settings.remoteControlEnabled}
+                                                    onClick={() => { void handleCopyIP(withToken(tailscaleUrl, token)); }}
+                                                >
+                                                    Copy Tailscale URL
+                                                </Button>
+                                            )}
+                                        </>
+                                    )}
+                                    <Button size="sm" variant="destructive" onClick={() => { void handleRevokeToken(token); }}>
+                                        Revoke
+                                    </Button>
+                                </div>
+                            </div>
+                        ))}
+                        <div className="flex flex-wrap items-center gap-4 border-t pt-3">
                             <Input
-                                value={settings.remoteControlToken}
-                                onChange={(e) => handleChange("remoteControlToken", e.target.value)}
-                                disabled={!settings.remoteControlEnabled}
+                                className="w-40"
+                                placeholder="Token name"
+                                value={newTokenName}
+                                onChange={(e) => setNewTokenName(e.target.value)}
                             />
-                            <p className="text-xs text-muted-foreground">
-                                Remote URL: 
-                                <Tooltip>
-                                    <TooltipTrigger asChild>
-                                        <span
-                                            className={`ml-1 rounded px-1 text-xs font-mono bg-muted/50 ${settings.remoteControlEnabled ? "cursor-pointer" : "cursor-not-allowed opacity-70"}`}
-                                            onClick={settings.remoteControlEnabled ? () => { void handleCopyIP(); } : undefined}
-                                            aria-disabled={!settings.remoteControlEnabled}
-                                        >
-                                            {settings.remoteControlEnabled ? remoteUrl : "Enable Remote Control to see URL"}
-                                        </span>
-                                    </TooltipTrigger>
-                                    <TooltipContent>
-                                        <p className="text-xs">
-                                            {settings.remoteControlEnabled ? "Click to copy" : "Enable Remote Control to see URL"}
+                            {REMOTE_SCOPES.map(({ scope, label }) => (
+                                <label key={scope} className="flex items-center gap-2 text-xs">
+                                    <Switch
+                                        checked={newTokenScopes.includes(scope)}
+                                        onCheckedChange={(checked) => toggleNewTokenScope(scope, checked)}
+                                    />
+                                    {label}
+                                </label>
+                            ))}
+                            <Button
+                                size="sm"
+                                disabled={!newTokenName.trim() || newTokenScopes.length === 0}
+                                onClick={() => { void handleCreateToken(); }}
+                            >
+                                Create Token
+                            </Button>
+                        </div>
+                        <div className="space-y-2 border-t pt-3">
+                            <div className="flex items-center justify-between">
+                                <div className="space-y-0.5">
+                                    <label className="text-sm font-medium leading-none">Remote Devices</label>
+                                    <p className="text-xs text-muted-foreground">
+                                        Each new phone or script has to be allowed here (or in the prompt) before its token works.
+                                    </p>
+                                </div>
+                                <Switch
+                                    checked={settings.remotePairingRequired}
+                                    onCheckedChange={(checked) => handleChange("remotePairingRequired", checked)}
+                                />
+                            </div>
+                            {devices.map((device) => (
+                                <div key={device.id} className="flex items-center justify-between gap-2 text-xs">
+                                    <div className="space-y-0.5">
+                                        <p className="font-medium">
+                                            {device.label}
+                                            {device.status === "pending" && <span className="ml-2 text-amber-600">waiting for approval</span>}
+                                            {device.status === "rejected" && <span className="ml-2 text-destructive">rejected</span>}
                                         </p>
-                                    </TooltipContent>
-                                </Tooltip>
-                            </p>
+                                        <p className="text-muted-foreground">
+                                            {device.ip} · token {device.tokenName ?? "removed"} ·{" "}
+                                            {device.lastSeenAt
+                                                ? `last seen ${format(new Date(device.lastSeenAt * 1000), "yyyy-MM-dd HH:mm")}`
+                                                : `first seen ${format(new Date(device.createdAt * 1000), "yyyy-MM-dd HH:mm")}`}
+                                        </p>
+                                    </div>
+                                    <div className="flex gap-2">
+                                        {device.status !== "approved" && (
+                                            <Button size="sm" variant="outline" onClick={() => { void handleDecideDevice(device, true); }}>
+                                                Allow
+                                            </Button>
+                                        )}
+                                        {device.status !== "rejected" && (
+                                            <Button size="sm" variant="outline" onClick={() => { void handleDecideDevice(device, false); }}>
+                                                Reject
+                                            </Button>
+                                        )}
+                                        <Button size="sm" variant="destructive" onClick={() => { void handleRemoveDevice(device); }}>
+                                            Forget
+                                        </Button>
+                                    </div>
+                                </div>
+                            ))}
                         </div>
+                        {audit.length > 0 && (
+                            <div className="space-y-1 border-t pt-3 text-xs">
+                                <p className="font-medium">Recent remote requests</p>
+                                {audit.map((entry) => (
+                                    <p key={entry.id} className="font-mono text-muted-foreground">
+                                        {format(new Date(entry.lastAt * 1000), "yyyy-MM-dd HH:mm:ss")} {entry.ip} {entry.route} ({entry.tokenName})
+                                        {entry.hits > 1 && ` ×${entry.hits} since ${format(new Date(entry.at * 1000), "HH:mm:ss")}`}
+                                    </p>
+                                ))}
+                            </div>
+                        )}
+                        {securityLog && securityLog.events.length > 0 && (
+                            <div className="space-y-1 border-t pt-3 text-xs">
+                                <p className="font-medium">
+                                    Failed attempts: {securityLog.unauthorizedTotal} unauthorized,{" "}
+                                    {securityLog.rateLimitedTotal} rate limited since app start
+                                </p>
+                                {securityLog.lockouts.map((lockout) => (
+                                    <p key={lockout.ip} className="text-destructive">
+                                        {lockout.ip} locked out until {format(new Date(lockout.until * 1000), "HH:mm")}
+                                    </p>
+                                ))}
+                                {securityLog.events.slice(0, 5).map((event) => (
+                                    <p key={`${event.at}-${event.ip}-${event.kind}`} className="font-mono text-muted-foreground">
+                                        {format(new Date(event.at * 1000), "yyyy-MM-dd HH:mm:ss")} {event.ip} {event.route} ({event.kind})
+                                    </p>
+                                ))}
+                            </div>
+                        )}
                     </div>
                 </div>
 
@@ -0,0 +1,63 @@
+import { musicNext, musicTrackData } from "@/api";
+import type { MusicPlayEvent } from "@/types";
+
+// Plays the backend playlist through Web Audio: decoded buffers loop without a gap, and a
+// finished track asks the backend for the next one.
+let context: AudioContext | undefined;
+let current: { source: AudioBufferSourceNode; gain: GainNode } | undefined;
+// Bumped by every play and stop, so a playMusic still fetching or decoding when a newer call
+// arrives drops its track instead of starting over the newer one.
+let generation = 0;
+
+function decodeBase64(content: string): ArrayBuffer {
+  const binary = atob(content);
+  const bytes = new Uint8Array(binary.length);
+  for (let i = 0; i < binary.length; i += 1) {
+    bytes[i] = binary.charCodeAt(i);
+  }
+  return bytes.buffer;
+}
+
+function stopCurrent() {
+  if (!current) {
+    return;
+  }
+  current.source.onended = null;
+  current.source.stop();
+  current.source.disconnect();
+  current.gain.disconnect();
+  current = undefined;
+}
+
+export function stopMusic() {
+  generation += 1;
+  stopCurrent();
+}
+
+export async function playMusic(event: MusicPlayEvent) {
+  generation += 1;
+  const token = generation;
+  const data = await musicTrackData(event.track.id);
+  if (token !== generation) {
+    return;
+  }
+  context ??= new AudioContext();
+  const buffer = await context.decodeAudioData(decodeBase64(data.contentBase64));
+  if (token !== generation) {
+    return;
+  }
+
+  stopCurrent();
+  const source = context.createBufferSource();
+  const gain = context.createGain();
+  source.buffer = buffer;
+  source.loop = event.loopTrack;
+  gain.gain.value = event.track.volume;
+  source.connect(gain).connect(context.destination);
+  source.onended = () => {
+    current = undefined;
+    void musicNext();
+  };
+  source.start();
+  current = { source, gain };
+}
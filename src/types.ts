@@ -1,5 +1,14 @@
 export type TimerPhase = "focus" | "short_break" | "long_break";
 export type AppTheme = "light" | "dark";
+export type PauseBudgetAction = "reject" | "mark_incomplete";
+export type DndMode = "bypass" | "suppress" | "queue";
+export type Granularity = "day" | "week" | "month";
+export type BreathingPattern = "off" | "box" | "relax" | "coherent" | "custom";
+export type Language = "en" | "ru" | "uk";
+export type RemoteDetachAction = "ignore" | "interruption" | "pause";
+
+// Remote API endpoint groups a token can be limited to.
+export type RemoteScope = "timer" | "read" | "write";
 
 export interface AppSettings {
   focusMin: number;
@@ -11,7 +20,47 @@ export interface AppSettings {
   notificationsEnabled: boolean;
   remoteControlEnabled: boolean;
   remoteControlPort: number;
-  remoteControlToken: string;
+  cycleResetAtMidnight: boolean;
+  cycleResetIdleMin: number;
+  maxPausesPerFocus: number;
+  pauseBudgetAction: PauseBudgetAction;
+  teamStatsEnabled: boolean;
+  teamStatsKey: string;
+  dndMode: DndMode;
+  dndBreakEndBypass: boolean;
+  timezone: string;
+  autoArchiveDays: number;
+  remoteDetachAction: RemoteDetachAction;
+  language: Language;
+  workDayStartMin: number;
+  workDayEndMin: number;
+  gapMinMinutes: number;
+  skipDaysOff: boolean;
+  streakMinPomodoros: number;
+  streakSkipWeekends: boolean;
+  streakFreezeTokens: number;
+  sessionWebhookUrl: string;
+  sessionWebhookSecret: string;
+  breathingPattern: BreathingPattern;
+  breathingCustom: number[];
+  musicEnabled: boolean;
+  musicShuffle: boolean;
+  breakDimEnabled: boolean;
+  breakDimPercent: number;
+  analyticsExcludedProjectIds: number[];
+  analyticsExcludedTagIds: number[];
+  dailyGoalPomodoros: number;
+  weeklyGoalMinutes: number;
+  goalNotification: boolean;
+  trayTitleSeconds: boolean;
+  backupEnabled: boolean;
+  backupDirectory: string;
+  backupIntervalHours: number;
+  backupKeep: number;
+  backupFormat: "json" | "sqlite";
+  remoteMdnsEnabled: boolean;
+  remotePairingRequired: boolean;
+  remoteReadOnly: boolean;
 }
 
 export interface AppSettingsPatch {
@@ -24,7 +73,47 @@ export interface AppSettingsPatch {
   notificationsEnabled?: boolean;
   remoteControlEnabled?: boolean;
   remoteControlPort?: number;
-  remoteControlToken?: string;
+  cycleResetAtMidnight?: boolean;
+  cycleResetIdleMin?: number;
+  maxPausesPerFocus?: number;
+  pauseBudgetAction?: PauseBudgetAction;
+  teamStatsEnabled?: boolean;
+  teamStatsKey?: string;
+  dndMode?: DndMode;
+  dndBreakEndBypass?: boolean;
+  timezone?: string;
+  autoArchiveDays?: number;
+  remoteDetachAction?: RemoteDetachAction;
+  language?: Language;
+  workDayStartMin?: number;
+  workDayEndMin?: number;
+  gapMinMinutes?: number;
+  skipDaysOff?: boolean;
+  streakMinPomodoros?: number;
+  streakSkipWeekends?: boolean;
+  streakFreezeTokens?: number;
+  sessionWebhookUrl?: string;
+  sessionWebhookSecret?: string;
+  breathingPattern?: BreathingPattern;
+  breathingCustom?: number[];
+  musicEnabled?: boolean;
+  musicShuffle?: boolean;
+  breakDimEnabled?: boolean;
+  breakDimPercent?: number;
+  analyticsExcludedProjectIds?: number[];
+  analyticsExcludedTagIds?: number[];
+  dailyGoalPomodoros?: number;
+  weeklyGoalMinutes?: number;
+  goalNotification?: boolean;
+  trayTitleSeconds?: boolean;
+  backupEnabled?: boolean;
+  backupDirectory?: string;
+  backupIntervalHours?: number;
+  backupKeep?: number;
+  backupFormat?: "json" | "sqlite";
+  remoteMdnsEnabled?: boolean;
+  remotePairingRequired?: boolean;
+  remoteReadOnly?: boolean;
 }
 
 export interface TimerState {
@@ -38,11 +127,20 @@ export interface TimerState {
   currentProjectId: number | null;
   currentTagIds: number[];
   targetEndsAt: number | null;
+  cycleAnchorDate: string | null;
+  lastActiveAt: number | null;
+  pausesRemaining: number | null;
+  pauseBudgetExceeded: boolean;
+  longBreakPostponed: boolean;
+  longBreakRequeued: boolean;
+  breakExtensionSeconds: number;
+  currentPlanned: boolean;
 }
 
 export interface StartTimerRequest {
   projectId?: number | null;
   tagIds?: number[];
+  durationMinutes?: number;
 }
 
 export interface SessionRecord {
@@ -55,6 +153,33 @@ export interface SessionRecord {
   interruptions: number;
   projectId: number | null;
   tagIds: number[];
+  notes?: string | null;
+  extendedSec: number;
+  projectSplits: ProjectSplit[];
+  quality: number | null;
+  planned: boolean | null;
+}
+
+export interface SessionUpdate {
+  startedAt?: number;
+  endedAt?: number;
+  durationSec?: number;
+  completed?: boolean;
+  interruptions?: number;
+  allowOverlap?: boolean;
+}
+
+export interface SessionRevision {
+  id: number;
+  sessionId: number;
+  revisedAt: number;
+  source: string;
+  session: SessionRecord;
+}
+
+export interface ProjectSplit {
+  projectId: number;
+  percent: number;
 }
 
 export interface AnalyticsRange {
@@ -62,6 +187,50 @@ export interface AnalyticsRange {
   to?: number;
   projectId?: number;
   tagId?: number;
+  granularity?: Granularity;
+  // Count sessions hidden by the analytics exclusion rules in settings.
+  includeExcluded?: boolean;
+  // Only sessions in these phases; omit for every phase.
+  phases?: TimerPhase[];
+}
+
+export interface SessionQuery extends AnalyticsRange {
+  limit?: number;
+  cursor?: string | null;
+}
+
+export interface SessionSearchQuery extends AnalyticsRange {
+  text: string;
+  limit?: number;
+}
+
+export type TimerEventKind =
+  | "started"
+  | "paused"
+  | "resumed"
+  | "skipped"
+  | "completed";
+
+export interface TimerEvent {
+  id: number;
+  at: number;
+  kind: TimerEventKind;
+  phase: TimerPhase;
+  cycleIndex: number;
+  remainingSeconds: number;
+  sessionId: number | null;
+}
+
+export interface TimerEventQuery {
+  from?: number;
+  to?: number;
+  limit?: number;
+}
+
+export interface SessionPage {
+  total: number;
+  sessions: SessionRecord[];
+  nextCursor: string | null;
 }
 
 export interface AnalyticsSummary {
@@ -70,6 +239,125 @@ export interface AnalyticsSummary {
   streakDays: number;
   interruptions: number;
   avgDailyFocusSec: number;
+  plannedRatio: number | null;
+  breakCompliance: BreakCompliance;
+}
+
+export interface BreakCompliance {
+  breaks: number;
+  taken: number;
+  cutShort: number;
+  skipped: number;
+  takenRatio: number | null;
+  complianceRate: number | null;
+}
+
+export interface DailyQuality {
+  date: string;
+  avgQuality: number;
+  ratedSessions: number;
+}
+
+export interface ProjectQuality {
+  projectId: number | null;
+  avgQuality: number;
+  ratedSessions: number;
+}
+
+export interface QualityBreakdown {
+  byDay: DailyQuality[];
+  byProject: ProjectQuality[];
+}
+
+export interface FocusLengthBucket {
+  minutes: number;
+  sessions: number;
+  completed: number;
+  completionRate: number;
+}
+
+export interface FocusLengthRecommendation {
+  buckets: FocusLengthBucket[];
+  recommendedMin: number | null;
+  currentMin: number;
+  applied: boolean;
+}
+
+export interface BreakUsage {
+  breakSessions: number;
+  breakSeconds: number;
+  configuredSeconds: number;
+  extendedSeconds: number;
+  extendedSessions: number;
+}
+
+export interface HourlyBucket {
+  hour: number;
+  focusSeconds: number;
+  completedPomodoros: number;
+}
+
+export interface WeekdayBucket {
+  weekday: number;
+  focusSeconds: number;
+  completedPomodoros: number;
+  interruptions: number;
+}
+
+export interface DistributionBucket {
+  fromSec: number;
+  toSec: number | null;
+  count: number;
+}
+
+export interface Distribution {
+  count: number;
+  p50Sec: number | null;
+  p90Sec: number | null;
+  buckets: DistributionBucket[];
+}
+
+export interface SessionDistribution {
+  durations: Distribution;
+  gaps: Distribution;
+}
+
+// Minutes after local midnight.
+export interface StartTimeStats {
+  period: string;
+  sessions: number;
+  days: number;
+  medianStartMin: number;
+  earliestStartMin: number;
+  latestStartMin: number;
+  medianFirstStartMin: number;
+}
+
+export interface InterruptionBucket {
+  bucket: number;
+  interruptions: number;
+  completedPomodoros: number;
+  perPomodoro: number;
+}
+
+export interface InterruptionTrendPoint {
+  date: string;
+  interruptions: number;
+  completedPomodoros: number;
+  perPomodoro: number;
+}
+
+export interface InterruptionAnalytics {
+  byHour: InterruptionBucket[];
+  byWeekday: InterruptionBucket[];
+  averagePerPomodoro: number;
+  trend: InterruptionTrendPoint[];
+}
+
+export interface SessionGap {
+  startedAt: number;
+  endedAt: number;
+  durationSec: number;
 }
 
 export interface TimeseriesPoint {
@@ -84,6 +372,47 @@ export interface Project {
   name: string;
   color?: string | null;
   archived: boolean;
+  billable: boolean;
+  hourlyRate: number | null;
+  pinned: boolean;
+  sortOrder: number;
+  notificationOverride: NotificationOverride;
+  estimatedPomodoros: number | null;
+}
+
+export interface ProjectEstimate {
+  projectId: number;
+  name: string;
+  archived: boolean;
+  estimatedPomodoros: number;
+  actualPomodoros: number;
+  ratio: number;
+  lastPomodoroAt: number | null;
+}
+
+export interface EstimationTrendPoint {
+  date: string;
+  projects: number;
+  meanRatio: number;
+  underestimated: number;
+  overestimated: number;
+}
+
+export interface EstimationAccuracy {
+  projects: ProjectEstimate[];
+  meanRatio: number | null;
+  trend: EstimationTrendPoint[];
+}
+
+export interface AutoArchiveResult {
+  archived: Project[];
+}
+
+export type ProjectSound = "chime" | "gong" | "none";
+
+export interface NotificationOverride {
+  notify?: boolean | null;
+  sound?: ProjectSound | null;
 }
 
 export interface ProjectInput {
@@ -91,6 +420,13 @@ export interface ProjectInput {
   name: string;
   color?: string | null;
   archived?: boolean;
+  billable?: boolean;
+  // null clears the rate.
+  hourlyRate?: number | null;
+  pinned?: boolean;
+  notificationOverride?: NotificationOverride;
+  // 0 clears the estimate.
+  estimatedPomodoros?: number;
 }
 
 export interface Tag {
@@ -98,21 +434,128 @@ export interface Tag {
   name: string;
 }
 
+export interface TagWithStats extends Tag {
+  sessionCount: number;
+  focusSeconds: number;
+}
+
 export interface TagInput {
   id?: number;
   name: string;
 }
 
+export interface BulkTagFilter extends AnalyticsRange {
+  // Only sessions without any tag yet.
+  untagged?: boolean;
+}
+
+export interface BulkUpdateResult {
+  updated: number;
+}
+
 export interface ExportRange {
   from?: number;
   to?: number;
 }
 
+export type CsvDelimiter = "comma" | "semicolon" | "tab";
+export type CsvDateFormat = "epoch" | "iso_local";
+export type CsvColumn =
+  | "id"
+  | "startedAt"
+  | "endedAt"
+  | "phase"
+  | "durationSec"
+  | "completed"
+  | "interruptions"
+  | "projectId"
+  | "tagIds"
+  | "projectName"
+  | "tagNames"
+  | "notes"
+  | "quality"
+  | "billable"
+  | "hourlyRate"
+  | "earnings"
+  | "startedAtLocal"
+  | "endedAtLocal"
+  | "weekday";
+
+export interface CsvOptions {
+  delimiter?: CsvDelimiter;
+  dateFormat?: CsvDateFormat;
+  columns?: CsvColumn[];
+  localTimestamps?: boolean;
+}
+
+export interface JsonExportOptions {
+  localTimestamps?: boolean;
+}
+
 export interface ExportResult {
   filename: string;
   content: string;
 }
 
+export type ImportFormat = "csv" | "json";
+
+export type ReportFormat = "markdown" | "html";
+
+// "replace" wipes projects, tags and sessions first; "merge" matches them by name and skips
+// sessions already present.
+export type ImportMode = "replace" | "merge";
+
+// Header names for each field of an import_csv file; only start and end are required.
+export interface CsvColumnMapping {
+  startedAt: string;
+  endedAt: string;
+  phase?: string;
+  durationSec?: string;
+  completed?: string;
+  interruptions?: string;
+  projectName?: string;
+  tagNames?: string;
+  tagSeparator?: string;
+  quality?: string;
+}
+
+export interface CsvImportResult {
+  imported: number;
+  duplicates: number;
+  projectsCreated: string[];
+  tagsCreated: string[];
+  errors: ImportRowError[];
+}
+
+export interface JsonImportResult {
+  mode: ImportMode;
+  projectsCreated: number;
+  tagsCreated: number;
+  sessionsImported: number;
+  sessionsSkipped: number;
+  settingsRestored: boolean;
+}
+
+export interface ImportRowError {
+  row: number;
+  message: string;
+}
+
+export interface ImportPreview {
+  format: ImportFormat;
+  totalRows: number;
+  validRows: number;
+  duplicateRows: number;
+  projectsToCreate: string[];
+  tagsToCreate: string[];
+  errors: ImportRowError[];
+}
+
+export interface BinaryExportResult {
+  filename: string;
+  contentBase64: string;
+}
+
 export interface ResetAllResult {
   settings: AppSettings;
   timer: TimerState;
@@ -121,4 +564,359 @@ export interface ResetAllResult {
 export interface PhaseCompletedEvent {
   completedPhase: TimerPhase;
   nextPhase: TimerPhase;
+  sessionId: number;
+  notify: boolean;
+  sound: ProjectSound | null;
+}
+
+export interface DatabaseExportResult {
+  path: string;
+  sizeBytes: number;
+}
+
+export interface DatabaseImportResult {
+  settings: AppSettings;
+  timer: TimerState;
+  backupPath: string;
+  tables: TableStats[];
+  integrity: DbCheckReport;
+}
+
+export interface FileExportResult {
+  path: string;
+  rows: number;
+}
+
+export interface LocalBackup {
+  path: string;
+  filename: string;
+  format: "json" | "sqlite";
+  createdAt: number;
+  sizeBytes: number;
+}
+
+export interface CloudBackupStatus {
+  enabled: boolean;
+  webdavUrl: string;
+  username: string;
+  hasPassword: boolean;
+  hasPassphrase: boolean;
+  intervalHours: number;
+  lastBackupAt: number | null;
+  lastError: string | null;
+  syncRevision: number;
+  lastSyncAt: number | null;
+}
+
+export interface CloudBackupConfigPatch {
+  enabled?: boolean;
+  webdavUrl?: string;
+  username?: string;
+  password?: string;
+  passphrase?: string;
+  intervalHours?: number;
+}
+
+export type SyncStatus = "pushed" | "pulled" | "up_to_date" | "conflict";
+
+export interface SyncOutcome {
+  status: SyncStatus;
+  localRevision: number;
+  remoteRevision: number;
+}
+
+export type Trashed<T> = T & {
+  deletedAt: number;
+  purgeAt: number;
+};
+
+export interface TrashList {
+  sessions: Trashed<SessionRecord>[];
+  projects: Trashed<Project>[];
+  tags: Trashed<Tag>[];
+}
+
+export interface DeleteResult {
+  deleted: boolean;
+  detachedSessions: number;
+}
+
+export interface MergeResult {
+  movedSessions: number;
+  removed: number;
+}
+
+export interface DndStatus {
+  active: boolean;
+  queued: boolean;
+}
+
+export type BillingPeriod = "day" | "week" | "month";
+
+export interface ProjectBilling {
+  projectId: number;
+  projectName: string;
+  hourlyRate: number;
+  billableSeconds: number;
+  earnings: number;
+}
+
+export interface PeriodBilling {
+  period: string;
+  billableSeconds: number;
+  earnings: number;
+}
+
+export interface BillingReport {
+  byProject: ProjectBilling[];
+  byPeriod: PeriodBilling[];
+  totalBillableSeconds: number;
+  totalEarnings: number;
+}
+
+export interface EndpointStats {
+  route: string;
+  requests: number;
+  avgLatencyMs: number;
+  maxLatencyMs: number;
+}
+
+export interface AppDiagnostics {
+  version: string;
+  remoteRunning: boolean;
+  remotePort: number | null;
+  remoteEndpoints: EndpointStats[];
+}
+
+export interface DbCheckReport {
+  orphanedSessionTags: number;
+  impossibleDurationSessionIds: number[];
+  danglingProjectSessionIds: number[];
+}
+
+export interface TableStats {
+  table: string;
+  rows: number;
+}
+
+export interface ReadOnlyStatus {
+  readOnly: boolean;
+  reason: string | null;
+  since: number | null;
+  pendingSessions: number;
+  flushedSessions: number;
+}
+
+export interface DbRecoveryReport {
+  error: string;
+  backupPath: string;
+  mode: "recover" | "copy" | "fresh";
+  tables: TableStats[];
+  failedTables: string[];
+  at: number;
+}
+
+export interface ReconcileResult {
+  settings: AppSettings;
+  timer: TimerState;
+  integrity: DbCheckReport;
+}
+
+export type DataDomain =
+  | "sessions"
+  | "projects"
+  | "tags"
+  | "settings"
+  | "timer"
+  | "days_off"
+  | "music"
+  | "experiments"
+  | "remote"
+  | "plan";
+
+export interface DataChangedEvent {
+  domains: DataDomain[];
+}
+
+export interface DayRecord {
+  date: string;
+  value: number;
+}
+
+export interface StreakRecord {
+  days: number;
+  startDate: string;
+  endDate: string;
+}
+
+export interface AnalyticsRecords {
+  longestDailyFocus: DayRecord | null;
+  mostPomodorosDay: DayRecord | null;
+  longestStreak: StreakRecord | null;
+  longestFocusBlock: DayRecord | null;
+}
+
+export interface DayOff {
+  day: string;
+  name: string | null;
+  source: "manual" | "ics";
+}
+
+export interface DaysOffImportResult {
+  imported: number;
+  skipped: number;
+}
+
+export interface BreathingStep {
+  step: "inhale" | "hold" | "exhale" | "hold_out";
+  stepIndex: number;
+  stepSeconds: number;
+  endsAt: number;
+  cycle: number;
+}
+
+export interface PlanItem {
+  id: number;
+  title: string;
+  projectId: number | null;
+  tagIds: number[];
+  createdAt: number;
+}
+
+export interface MusicTrack {
+  id: number;
+  path: string;
+  title: string;
+  volume: number;
+}
+
+export interface MusicPlayEvent {
+  track: MusicTrack;
+  loopTrack: boolean;
+}
+
+export interface MusicTrackData {
+  trackId: number;
+  mime: string;
+  contentBase64: string;
+}
+
+export interface TailscaleHost {
+  dnsName: string | null;
+  ip: string | null;
+}
+
+export interface RemoteToken {
+  id: number;
+  name: string;
+  // Only set on the token remoteTokensCreate returns; the app keeps just a hash.
+  token?: string;
+  scopes: RemoteScope[];
+  createdAt: number;
+  lastUsedAt: number | null;
+}
+
+export interface RemoteAuditEntry {
+  id: number;
+  at: number;
+  // Repeats within a minute of each other fold into one entry.
+  lastAt: number;
+  hits: number;
+  ip: string;
+  route: string;
+  tokenName: string;
+}
+
+export interface RemoteDevice {
+  id: number;
+  tokenId: number;
+  tokenName: string | null;
+  label: string;
+  ip: string;
+  status: "pending" | "approved" | "rejected";
+  createdAt: number;
+  decidedAt: number | null;
+  lastSeenAt: number | null;
+}
+
+export interface RemoteSecurityEvent {
+  at: number;
+  ip: string;
+  route: string;
+  kind: "unauthorized" | "locked_out" | "rate_limited";
+}
+
+export interface RemoteSecurityLog {
+  events: RemoteSecurityEvent[];
+  lockouts: { ip: string; until: number }[];
+  unauthorizedTotal: number;
+  rateLimitedTotal: number;
+}
+
+export interface RemoteStatus {
+  state: "stopped" | "running" | "failed";
+  configuredPort: number | null;
+  port: number | null;
+  reason: string | null;
+}
+
+export interface RemoteDiscoveryInfo {
+  enabled: boolean;
+  port: number;
+  lanIp: string | null;
+  lanUrl: string | null;
+  lanUrls: string[];
+  tailscale: TailscaleHost | null;
+  tailscaleUrl: string | null;
+}
+
+export interface GoalProgress {
+  target: number;
+  value: number;
+  periodStart: number;
+  reached: boolean;
+}
+
+export interface GoalsProgress {
+  dailyPomodoros: GoalProgress | null;
+  weeklyMinutes: GoalProgress | null;
+}
+
+export interface GoalReachedEvent {
+  goal: "daily" | "weekly";
+  target: number;
+  value: number;
+}
+
+export interface WorkerRestartedEvent {
+  worker: string;
+  restarts: number;
+  message: string;
+  at: number;
+}
+
+export interface Experiment {
+  id: number;
+  focusAMin: number;
+  focusBMin: number;
+  startedAt: number;
+  endsAt: number;
+  stoppedAt: number | null;
+}
+
+export interface ExperimentArm {
+  focusMin: number;
+  sessions: number;
+  completed: number;
+  completionRate: number | null;
+  interruptions: number;
+  interruptionsPerSession: number | null;
+  focusSeconds: number;
+  avgFocusSeconds: number | null;
+}
+
+export interface ExperimentReport {
+  experiment: Experiment;
+  active: boolean;
+  arms: ExperimentArm[];
 }
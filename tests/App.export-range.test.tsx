@@ -16,7 +16,6 @@ const { exportCsvMock, exportJsonMock, baseSettings, baseTimer } = vi.hoisted(
       notificationsEnabled: true,
       remoteControlEnabled: false,
       remoteControlPort: 48484,
-      remoteControlToken: "token",
     },
     baseTimer: {
       phase: "focus",